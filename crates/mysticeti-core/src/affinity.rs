@@ -0,0 +1,75 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io;
+
+/// Restrict the calling thread to the given CPU cores.
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(cores: &[usize]) -> io::Result<()> {
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for core in cores {
+        if *core >= libc::CPU_SETSIZE as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("CPU core {core} is out of range"),
+            ));
+        }
+        unsafe { libc::CPU_SET(*core, &mut set) };
+    }
+    let result =
+        unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(_cores: &[usize]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU affinity is only supported on linux",
+    ))
+}
+
+/// Pin the calling thread if any cores are configured. Pinning only reduces jitter, so a failure
+/// is reported but does not stop the thread.
+pub fn pin_thread(name: &str, cores: &[usize]) {
+    if cores.is_empty() {
+        return;
+    }
+    match pin_current_thread(cores) {
+        Ok(()) => tracing::info!("Pinned the {name} thread to CPU cores {cores:?}"),
+        Err(err) => tracing::warn!("Failed to pin the {name} thread to CPU cores {cores:?}: {err}"),
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use std::{mem, thread};
+
+    use super::pin_current_thread;
+
+    fn allowed_cores() -> Vec<usize> {
+        let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+        let result =
+            unsafe { libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set) };
+        assert_eq!(result, 0);
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|core| unsafe { libc::CPU_ISSET(*core, &set) })
+            .collect()
+    }
+
+    #[test]
+    fn pin_thread_to_core() {
+        thread::spawn(|| {
+            let core = allowed_cores()[0];
+            pin_current_thread(&[core]).unwrap();
+            assert_eq!(allowed_cores(), vec![core]);
+            assert!(pin_current_thread(&[libc::CPU_SETSIZE as usize]).is_err());
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Archives the committed transactions in a SQLite database, so that they can be analysed and
+//! audited after the run instead of only being summarised by metrics.
+
+use std::{io, path::Path, thread};
+
+use blake2::Digest;
+use rusqlite::{params, Connection, OptionalExtension};
+use tokio::sync::broadcast;
+
+use crate::{consensus::linearizer::CommittedSubDag, crypto::AsBytes, runtime::timestamp_utc};
+
+type PayloadHasher = blake2::Blake2b<digest::consts::U32>;
+
+pub struct TransactionArchive {
+    connection: Connection,
+    /// The index of the next commit. Continues the indices of the commits already archived.
+    next_index: u64,
+}
+
+impl TransactionArchive {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS commits (
+                leader TEXT PRIMARY KEY,
+                commit_index INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS committed_transactions (
+                transaction_id TEXT PRIMARY KEY,
+                commit_index INTEGER NOT NULL,
+                leader TEXT NOT NULL,
+                leader_round INTEGER NOT NULL,
+                committed_at_ms INTEGER NOT NULL,
+                payload_digest BLOB NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS committed_transactions_by_commit
+                ON committed_transactions (commit_index);",
+        )?;
+        let next_index: i64 = connection.query_row(
+            "SELECT COALESCE(MAX(commit_index) + 1, 0) FROM commits",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(Self {
+            connection,
+            next_index: next_index as u64,
+        })
+    }
+
+    /// Archive the transactions of the next commit. Commits are identified by their leader: those
+    /// archived before (when they are delivered again after a restart) are ignored and do not
+    /// take an index, even if they have no transactions.
+    pub fn append(&mut self, commit: &CommittedSubDag) -> rusqlite::Result<()> {
+        let index = self.next_index as i64;
+        let leader = commit.anchor.to_string();
+        let committed_at = timestamp_utc().as_millis() as i64;
+        let transaction = self.connection.transaction()?;
+        let archived: Option<i64> = transaction
+            .query_row(
+                "SELECT commit_index FROM commits WHERE leader = ?1",
+                params![leader],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if archived.is_some() {
+            return Ok(());
+        }
+        transaction.execute(
+            "INSERT INTO commits (leader, commit_index) VALUES (?1, ?2)",
+            params![leader, index],
+        )?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT OR IGNORE INTO committed_transactions
+                    (transaction_id, commit_index, leader, leader_round, committed_at_ms,
+                    payload_digest)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for block in &commit.blocks {
+                for (locator, payload) in block.shared_transactions() {
+                    let digest = PayloadHasher::digest(payload.as_bytes());
+                    insert.execute(params![
+                        locator.to_string(),
+                        index,
+                        leader,
+                        commit.anchor.round as i64,
+                        committed_at,
+                        digest.as_slice(),
+                    ])?;
+                }
+            }
+        }
+        transaction.commit()?;
+        self.next_index += 1;
+        Ok(())
+    }
+
+    /// Archive the commits delivered by `commits` on a dedicated thread, until all the senders of
+    /// `commits` are dropped.
+    pub fn start(
+        mut self,
+        mut commits: broadcast::Receiver<CommittedSubDag>,
+    ) -> io::Result<thread::JoinHandle<()>> {
+        thread::Builder::new()
+            .name("transaction-archive".to_string())
+            .spawn(move || loop {
+                let commit = match commits.blocking_recv() {
+                    Ok(commit) => commit,
+                    // Skip the index of the missed commits to keep the indices of the next ones.
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Transaction archive missed {skipped} commits");
+                        self.next_index += skipped;
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                if let Err(e) = self.append(&commit) {
+                    tracing::error!("Failed to archive committed transactions: {e}");
+                    return;
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::{
+        data::Data,
+        types::{BaseStatement, StatementBlock, Transaction},
+    };
+
+    fn commit(round: u64, transactions: usize) -> CommittedSubDag {
+        let statements = (0..transactions)
+            .map(|i| BaseStatement::Share(Transaction::new(vec![i as u8; 8])))
+            .collect();
+        let block = Data::new(StatementBlock::new(
+            0,
+            round,
+            vec![],
+            statements,
+            0,
+            false,
+            Default::default(),
+        ));
+        CommittedSubDag::new(*block.reference(), vec![block])
+    }
+
+    fn archived(archive: &TransactionArchive) -> Vec<(i64, i64)> {
+        let mut query = archive
+            .connection
+            .prepare("SELECT commit_index, leader_round FROM committed_transactions ORDER BY rowid")
+            .unwrap();
+        let rows = query.query_map([], |row| Ok((row.get(0)?, row.get(1)?)));
+        rows.unwrap().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn archive_commits() {
+        let dir = TempDir::new("archive_commits").unwrap();
+        let path = dir.path().join("transactions.sqlite");
+        let mut archive = TransactionArchive::open(&path).unwrap();
+        archive.append(&commit(1, 2)).unwrap();
+        archive.append(&commit(2, 1)).unwrap();
+        assert_eq!(archived(&archive), vec![(0, 1), (0, 1), (1, 2)]);
+        drop(archive);
+
+        // The indices continue after a restart, and commits delivered again are not duplicated.
+        let mut archive = TransactionArchive::open(&path).unwrap();
+        archive.append(&commit(2, 1)).unwrap();
+        archive.append(&commit(3, 1)).unwrap();
+        assert_eq!(archived(&archive), vec![(0, 1), (0, 1), (1, 2), (2, 3)]);
+
+        // Commits without transactions take an index once, even when delivered again.
+        archive.append(&commit(4, 0)).unwrap();
+        archive.append(&commit(4, 0)).unwrap();
+        archive.append(&commit(5, 1)).unwrap();
+        assert_eq!(archive.next_index, 5);
+        assert_eq!(archived(&archive).last(), Some(&(4, 5)));
+    }
+}
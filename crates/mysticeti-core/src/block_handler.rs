@@ -11,15 +11,32 @@ use std::{
 
 use minibytes::Bytes;
 use parking_lot::Mutex;
-use tokio::sync::mpsc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc};
 
 use crate::{
     block_store::BlockStore,
-    committee::{Committee, ProcessedTransactionHandler, QuorumThreshold, TransactionAggregator},
+    checkpoint::{Checkpoint, Checkpoints},
+    committee::{
+        Committee,
+        ProcessedTransactionHandler,
+        QuorumThreshold,
+        StakeAggregator,
+        TransactionAggregator,
+    },
     consensus::linearizer::{CommittedSubDag, Linearizer},
+    dag_export::DagExporter,
     data::Data,
-    log::TransactionLog,
-    metrics::{Metrics, UtilizationTimerExt, UtilizationTimerVecExt},
+    log::{TransactionLog, TransactionLogFlusher},
+    metrics::{
+        Metrics,
+        UtilizationTimerExt,
+        UtilizationTimerVecExt,
+        STAGE_BLOCK_CREATED_TO_QUORUM_RECEIVED,
+        STAGE_CERTIFIED_TO_COMMITTED,
+        STAGE_INCLUDED_TO_CERTIFIED,
+        STAGE_SUBMITTED_TO_INCLUDED,
+    },
     runtime::{self, TimeInstant},
     syncer::CommitObserver,
     transactions_generator::TransactionGenerator,
@@ -63,6 +80,10 @@ const fn assert_constants() {
 pub struct RealBlockHandler {
     transaction_votes: TransactionAggregator<QuorumThreshold, TransactionLog>,
     pub transaction_time: Arc<Mutex<HashMap<TransactionLocator, TimeInstant>>>,
+    /// Time at which our own transactions were certified.
+    pub certified_time: Arc<Mutex<HashMap<TransactionLocator, TimeInstant>>>,
+    /// Our own blocks not yet included by blocks of a quorum, with their creation time.
+    own_blocks: Mutex<HashMap<BlockReference, (TimeInstant, StakeAggregator<QuorumThreshold>)>>,
     committee: Arc<Committee>,
     authority: AuthorityIndex,
     block_store: BlockStore,
@@ -70,6 +91,38 @@ pub struct RealBlockHandler {
     receiver: mpsc::Receiver<Vec<Transaction>>,
     pending_transactions: usize,
     consensus_only: bool,
+    transaction_log: TransactionLogFlusher,
+}
+
+/// The state of a [`RealBlockHandler`] persisted in the wal.
+#[derive(Serialize, Deserialize)]
+struct RealBlockHandlerState {
+    /// Votes for the transactions that are not certified yet.
+    transaction_votes: Vec<u8>,
+    /// Length of the certified transactions log when the state was taken. The transactions logged
+    /// past this point are certified again when the following blocks are replayed on recovery.
+    certified_log_position: u64,
+    /// Time (utc) at which our own blocks with pending transactions were proposed. It is kept
+    /// per block (rather than per transaction) so that the state does not grow with the load.
+    proposal_times: Vec<(BlockReference, Duration)>,
+}
+
+/// The header of the handler states persisted in the wal. It tells them apart from the states
+/// written by the previous versions, that only held the encoded transaction votes: read as the
+/// length prefix of those votes, the header would be absurdly large.
+const STATE_HEADER: &[u8; 8] = b"MYSTATE1";
+
+fn encode_state<T: Serialize>(state: &T) -> Bytes {
+    let mut bytes = STATE_HEADER.to_vec();
+    bincode::serialize_into(&mut bytes, state).expect("Serialization failed");
+    bytes.into()
+}
+
+/// Decode a state written by [`encode_state`], or return None for a state written by a previous
+/// version.
+fn decode_state<T: DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    let encoded = bytes.strip_prefix(STATE_HEADER.as_slice())?;
+    Some(bincode::deserialize(encoded).expect("Deserialization failed"))
 }
 
 /// The max number of transactions per block.
@@ -88,23 +141,32 @@ impl RealBlockHandler {
         let (sender, receiver) = mpsc::channel(1024);
         let transaction_log = TransactionLog::start(certified_transactions_log_path)
             .expect("Failed to open certified transaction log for write");
+        let flusher = transaction_log.flusher();
 
         let this = Self {
             transaction_votes: TransactionAggregator::with_handler(transaction_log),
             transaction_time: Default::default(),
+            certified_time: Default::default(),
+            own_blocks: Default::default(),
             committee,
             authority,
             block_store,
             metrics,
             receiver,
-            pending_transactions: 0, // todo - need to initialize correctly when loaded from disk
+            pending_transactions: 0,
             consensus_only,
+            transaction_log: flusher,
         };
         (this, sender)
     }
 }
 
 impl RealBlockHandler {
+    /// Flushes the log of certified transactions.
+    pub fn transaction_log_flusher(&self) -> TransactionLogFlusher {
+        self.transaction_log.clone()
+    }
+
     fn receive_with_limit(&mut self) -> Option<Vec<Transaction>> {
         if self.pending_transactions >= SOFT_MAX_PROPOSED_PER_BLOCK {
             return None;
@@ -114,6 +176,30 @@ impl RealBlockHandler {
         Some(received)
     }
 
+    /// Record the time until our own blocks are included by the blocks of a quorum, which is
+    /// when we know a quorum received them.
+    fn observe_own_blocks_received(&self, blocks: &[Data<StatementBlock>]) {
+        let mut own_blocks = self.own_blocks.lock();
+        if own_blocks.is_empty() {
+            return;
+        }
+        let received = self
+            .metrics
+            .transaction_stage_latency_s
+            .with_label_values(&[STAGE_BLOCK_CREATED_TO_QUORUM_RECEIVED]);
+        for block in blocks {
+            for include in block.includes() {
+                let Some((created, votes)) = own_blocks.get_mut(include) else {
+                    continue;
+                };
+                if votes.add(block.author(), &self.committee) {
+                    received.observe(created.elapsed().as_secs_f64());
+                    own_blocks.remove(include);
+                }
+            }
+        }
+    }
+
     /// Expose a metric for certified transactions.
     fn update_metrics(
         &self,
@@ -129,10 +215,17 @@ impl RealBlockHandler {
                 .inter_block_latency_s
                 .with_label_values(&["owned"])
                 .observe(latency.as_secs_f64());
+            self.metrics
+                .transaction_stage_latency_s
+                .with_label_values(&[STAGE_INCLUDED_TO_CERTIFIED])
+                .observe(latency.as_secs_f64());
         }
 
         // Record end-to-end latency.
-        let tx_submission_timestamp = TransactionGenerator::extract_timestamp(transaction);
+        let Some(tx_submission_timestamp) = TransactionGenerator::extract_timestamp(transaction)
+        else {
+            return;
+        };
         let latency = current_timestamp.saturating_sub(tx_submission_timestamp);
         let square_latency = latency.as_secs_f64().powf(2.0);
         self.metrics
@@ -165,7 +258,9 @@ impl BlockHandler for RealBlockHandler {
                 }
             }
         }
+        self.observe_own_blocks_received(blocks);
         let transaction_time = self.transaction_time.lock();
+        let mut certified_time = self.certified_time.lock();
         for block in blocks {
             let response_option: Option<&mut Vec<BaseStatement>> = if require_response {
                 Some(&mut response)
@@ -178,6 +273,9 @@ impl BlockHandler for RealBlockHandler {
                         .process_block(block, response_option, &self.committee);
                 for processed_locator in processed {
                     let block_creation = transaction_time.get(&processed_locator);
+                    if block_creation.is_some() {
+                        certified_time.insert(processed_locator, TimeInstant::now());
+                    }
                     let transaction = self
                         .block_store
                         .get_transaction(&processed_locator)
@@ -194,11 +292,28 @@ impl BlockHandler for RealBlockHandler {
 
     fn handle_proposal(&mut self, block: &Data<StatementBlock>) {
         // todo - this is not super efficient
-        self.pending_transactions -= block.shared_transactions().count();
+        // Transactions received before a restart are recovered with the pending payload of the core
+        // and proposed again without being counted here.
+        self.pending_transactions = self
+            .pending_transactions
+            .saturating_sub(block.shared_transactions().count());
+        let now = runtime::timestamp_utc();
+        let included = self
+            .metrics
+            .transaction_stage_latency_s
+            .with_label_values(&[STAGE_SUBMITTED_TO_INCLUDED]);
         let mut transaction_time = self.transaction_time.lock();
-        for (locator, _) in block.shared_transactions() {
+        for (locator, transaction) in block.shared_transactions() {
             transaction_time.insert(locator, TimeInstant::now());
+            if let Some(submitted) = TransactionGenerator::extract_timestamp(transaction) {
+                included.observe(now.saturating_sub(submitted).as_secs_f64());
+            }
         }
+        let mut votes = StakeAggregator::new();
+        votes.add(self.authority, &self.committee);
+        self.own_blocks
+            .lock()
+            .insert(*block.reference(), (TimeInstant::now(), votes));
         if !self.consensus_only {
             for range in block.shared_ranges() {
                 self.transaction_votes
@@ -208,11 +323,45 @@ impl BlockHandler for RealBlockHandler {
     }
 
     fn state(&self) -> Bytes {
-        self.transaction_votes.state()
+        let now = runtime::timestamp_utc();
+        // The transactions of a block are all proposed at the same time.
+        let mut proposal_times = HashMap::new();
+        for (locator, instant) in self.transaction_time.lock().iter() {
+            proposal_times
+                .entry(*locator.block())
+                .or_insert_with(|| now.saturating_sub(instant.elapsed()));
+        }
+        let state = RealBlockHandlerState {
+            transaction_votes: self.transaction_votes.state().to_vec(),
+            certified_log_position: self.transaction_votes.handler().position(),
+            proposal_times: proposal_times.into_iter().collect(),
+        };
+        encode_state(&state)
     }
 
     fn recover_state(&mut self, state: &Bytes) {
-        self.transaction_votes.with_state(state);
+        let Some(state) = decode_state::<RealBlockHandlerState>(state) else {
+            tracing::warn!("Recovering the block handler state of a previous version");
+            self.transaction_votes.with_state(state);
+            return;
+        };
+        self.transaction_votes
+            .with_state(&state.transaction_votes.into());
+        self.transaction_votes
+            .handler_mut()
+            .recover(state.certified_log_position)
+            .expect("Failed to read certified transaction log");
+        let now = runtime::timestamp_utc();
+        let mut transaction_time = self.transaction_time.lock();
+        for (reference, proposed) in state.proposal_times {
+            let Some(block) = self.block_store.get_block(reference) else {
+                continue;
+            };
+            let elapsed = now.saturating_sub(proposed);
+            for (locator, _) in block.shared_transactions() {
+                transaction_time.insert(locator, TimeInstant::before(elapsed));
+            }
+        }
     }
 
     fn cleanup(&self) {
@@ -220,6 +369,10 @@ impl BlockHandler for RealBlockHandler {
         // todo - all of this should go away and we should measure tx latency differently
         let mut l = self.transaction_time.lock();
         l.retain(|_k, v| v.elapsed() < Duration::from_secs(10));
+        let mut l = self.certified_time.lock();
+        l.retain(|_k, v| v.elapsed() < Duration::from_secs(10));
+        let mut l = self.own_blocks.lock();
+        l.retain(|_k, (v, _)| v.elapsed() < Duration::from_secs(10));
     }
 }
 
@@ -343,9 +496,22 @@ pub struct TestCommitHandler<H = HashSet<TransactionLocator>> {
     // committed_dags: Vec<CommittedSubDag>,
     start_time: TimeInstant,
     transaction_time: Arc<Mutex<HashMap<TransactionLocator, TimeInstant>>>,
+    certified_time: Option<Arc<Mutex<HashMap<TransactionLocator, TimeInstant>>>>,
 
     metrics: Arc<Metrics>,
     consensus_only: bool,
+    commit_sender: Option<broadcast::Sender<CommittedSubDag>>,
+    /// Digest over the commit history, persisted with the state of the handler.
+    checkpoint: Checkpoint,
+    checkpoints: Option<Checkpoints>,
+    dag_exporter: Option<DagExporter>,
+}
+
+/// The state of [`TestCommitHandler`] persisted with every commit.
+#[derive(Serialize, Deserialize)]
+struct CommitHandlerState {
+    transaction_votes: Vec<u8>,
+    checkpoint: Checkpoint,
 }
 
 impl<H: ProcessedTransactionHandler<TransactionLocator> + Default> TestCommitHandler<H> {
@@ -374,12 +540,45 @@ impl<H: ProcessedTransactionHandler<TransactionLocator>> TestCommitHandler<H> {
             // committed_dags: vec![],
             start_time: TimeInstant::now(),
             transaction_time,
+            certified_time: None,
 
             metrics,
             consensus_only,
+            commit_sender: None,
+            checkpoint: Checkpoint::default(),
+            checkpoints: None,
+            dag_exporter: None,
         }
     }
 
+    /// Also deliver every committed sub-dag to the subscribers of `sender`.
+    pub fn with_commit_sender(mut self, sender: broadcast::Sender<CommittedSubDag>) -> Self {
+        self.commit_sender = Some(sender);
+        self
+    }
+
+    /// Record the time from certification to commit of the transactions certified at the times
+    /// of `certified_time`.
+    pub fn with_certified_time(
+        mut self,
+        certified_time: Arc<Mutex<HashMap<TransactionLocator, TimeInstant>>>,
+    ) -> Self {
+        self.certified_time = Some(certified_time);
+        self
+    }
+
+    /// Record the checkpoint of every commit in `checkpoints`.
+    pub fn with_checkpoints(mut self, checkpoints: Checkpoints) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Record the leader of every commit in `dag_exporter`, to highlight it in the DAG exports.
+    pub fn with_dag_exporter(mut self, dag_exporter: DagExporter) -> Self {
+        self.dag_exporter = Some(dag_exporter);
+        self
+    }
+
     pub fn committed_leaders(&self) -> &Vec<BlockReference> {
         &self.committed_leaders
     }
@@ -410,7 +609,10 @@ impl<H: ProcessedTransactionHandler<TransactionLocator>> TestCommitHandler<H> {
 
         // Record end-to-end latency. The first 8 bytes of the transaction are the timestamp of the
         // transaction submission.
-        let tx_submission_timestamp = TransactionGenerator::extract_timestamp(transaction);
+        let Some(tx_submission_timestamp) = TransactionGenerator::extract_timestamp(transaction)
+        else {
+            return;
+        };
         let latency = current_timestamp.saturating_sub(tx_submission_timestamp);
         let square_latency = latency.as_secs_f64().powf(2.0);
         self.metrics
@@ -438,8 +640,20 @@ impl<H: ProcessedTransactionHandler<TransactionLocator> + Send + Sync> CommitObs
             .commit_interpreter
             .handle_commit(block_store, committed_leaders);
         let transaction_time = self.transaction_time.lock();
+        let mut certified_time = self.certified_time.as_ref().map(|time| time.lock());
+        let certified = self
+            .metrics
+            .transaction_stage_latency_s
+            .with_label_values(&[STAGE_CERTIFIED_TO_COMMITTED]);
         for commit in &committed {
             self.committed_leaders.push(commit.anchor);
+            self.checkpoint = self.checkpoint.next(commit);
+            if let Some(checkpoints) = &self.checkpoints {
+                checkpoints.record(self.checkpoint);
+            }
+            if let Some(dag_exporter) = &self.dag_exporter {
+                dag_exporter.record_leader(commit.anchor);
+            }
             for block in &commit.blocks {
                 if !self.consensus_only {
                     let processed =
@@ -455,6 +669,12 @@ impl<H: ProcessedTransactionHandler<TransactionLocator> + Send + Sync> CommitObs
                     }
                 }
                 for (locator, transaction) in block.shared_transactions() {
+                    if let Some(instant) = certified_time
+                        .as_mut()
+                        .and_then(|time| time.remove(&locator))
+                    {
+                        certified.observe(instant.elapsed().as_secs_f64());
+                    }
                     self.update_metrics(
                         transaction_time.get(&locator),
                         current_timestamp,
@@ -463,24 +683,147 @@ impl<H: ProcessedTransactionHandler<TransactionLocator> + Send + Sync> CommitObs
                 }
             }
             // self.committed_dags.push(commit);
+            if let Some(sender) = &self.commit_sender {
+                // Do not clone the sub-dag when nobody listens to it.
+                if sender.receiver_count() > 0 {
+                    // Fails only if the last subscriber just dropped.
+                    sender.send(commit.clone()).ok();
+                }
+            }
         }
         self.metrics
             .commit_handler_pending_certificates
             .set(self.transaction_votes.len() as i64);
+        self.metrics
+            .commit_checkpoint_height
+            .set(self.checkpoint.height as i64);
+        self.metrics
+            .commit_checkpoint_digest_prefix
+            .set(self.checkpoint.digest_prefix());
         committed
     }
 
     fn aggregator_state(&self) -> Bytes {
-        self.transaction_votes.state()
+        let state = CommitHandlerState {
+            transaction_votes: self.transaction_votes.state().to_vec(),
+            checkpoint: self.checkpoint,
+        };
+        encode_state(&state)
     }
 
     fn recover_committed(&mut self, committed: HashSet<BlockReference>, state: Option<Bytes>) {
         assert!(self.commit_interpreter.committed.is_empty());
         if let Some(state) = state {
-            self.transaction_votes.with_state(&state);
+            match decode_state::<CommitHandlerState>(&state) {
+                Some(state) => {
+                    self.transaction_votes
+                        .with_state(&state.transaction_votes.into());
+                    self.checkpoint = state.checkpoint;
+                    if let Some(checkpoints) = &self.checkpoints {
+                        checkpoints.record(self.checkpoint);
+                    }
+                    // The checkpoint covers every commit, resume the commit index from it.
+                    self.metrics
+                        .committed_subdags_total
+                        .inc_by(self.checkpoint.height);
+                }
+                None => {
+                    tracing::warn!(
+                        "Recovering the commit handler state of a previous version, \
+                        the checkpoints restart from the genesis"
+                    );
+                    self.transaction_votes.with_state(&state);
+                }
+            }
         } else {
             assert!(committed.is_empty());
         }
         self.commit_interpreter.committed = committed;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::{
+        test_util::{committee, test_metrics, TestBlockWriter},
+        types::TransactionLocatorRange,
+    };
+
+    fn legacy_votes_state(committee: &Committee) -> Bytes {
+        let mut votes = TransactionAggregator::<QuorumThreshold>::new();
+        let range = TransactionLocatorRange::new(BlockReference::default(), 0..2);
+        votes.register(range, 0, committee);
+        votes.state()
+    }
+
+    #[tokio::test]
+    async fn recover_block_handler_state() {
+        let dir = TempDir::new("recover_block_handler_state").unwrap();
+        let committee = committee(4);
+        let mut block_writer = TestBlockWriter::new(&committee);
+        let handler = |block_store: BlockStore| {
+            let path = dir.path().join("certified.log");
+            let metrics = test_metrics();
+            let (handler, _) =
+                RealBlockHandler::new(committee.clone(), 0, &path, block_store, metrics, false);
+            handler
+        };
+
+        // The state written by a previous version only holds the transaction votes.
+        let mut recovered = handler(block_writer.block_store());
+        recovered.recover_state(&legacy_votes_state(&committee));
+        assert_eq!(recovered.transaction_votes.len(), 1);
+
+        let mut recovered_again = handler(block_writer.block_store());
+        recovered_again.recover_state(&recovered.state());
+        assert_eq!(recovered_again.transaction_votes.len(), 1);
+
+        // The proposal times are kept per block, whatever the number of transactions.
+        let statements = (0..100u8)
+            .map(|i| BaseStatement::Share(Transaction::new(vec![i])))
+            .collect();
+        let block = StatementBlock::new(0, 1, vec![], statements, 0, false, Default::default());
+        let block = Data::new(block);
+        block_writer.add_block(block.clone());
+        recovered_again.handle_proposal(&block);
+        let state = recovered_again.state();
+        let decoded = decode_state::<RealBlockHandlerState>(&state).unwrap();
+        assert_eq!(decoded.proposal_times.len(), 1);
+
+        let mut recovered_proposals = handler(block_writer.block_store());
+        recovered_proposals.recover_state(&state);
+        assert_eq!(recovered_proposals.transaction_time.lock().len(), 100);
+    }
+
+    #[test]
+    fn recover_commit_handler_state() {
+        let committee = committee(4);
+        let handler = || {
+            TestCommitHandler::<HashSet<TransactionLocator>>::new(
+                committee.clone(),
+                Default::default(),
+                test_metrics(),
+            )
+        };
+        let committed: HashSet<_> = [BlockReference::default()].into();
+
+        // The state written by a previous version only holds the transaction votes.
+        let mut recovered = handler();
+        recovered.recover_committed(committed.clone(), Some(legacy_votes_state(&committee)));
+        assert_eq!(recovered.transaction_votes.len(), 1);
+        assert_eq!(recovered.checkpoint, Checkpoint::default());
+
+        recovered.checkpoint = Checkpoint {
+            height: 3,
+            digest: [1; 32],
+        };
+        let mut recovered_again = handler();
+        recovered_again.recover_committed(committed, Some(recovered.aggregator_state()));
+        assert_eq!(recovered_again.transaction_votes.len(), 1);
+        assert_eq!(recovered_again.checkpoint, recovered.checkpoint);
+        assert_eq!(recovered_again.metrics.committed_subdags_total.get(), 3);
+    }
+}
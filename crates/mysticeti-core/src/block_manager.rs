@@ -15,7 +15,9 @@ use crate::{
 };
 
 /// Block manager suspends incoming blocks until they are connected to the existing graph,
-/// returning newly connected blocks
+/// returning newly connected blocks. Block headers (see `StatementBlock::header`) are connected
+/// like full blocks, and their payload is added to the graph once it is received. Blocks are only
+/// includable in our own blocks once their whole causal history is held with its payloads.
 pub struct BlockManager {
     /// Keeps all pending blocks.
     blocks_pending: HashMap<BlockReference, Data<StatementBlock>>,
@@ -24,34 +26,163 @@ pub struct BlockManager {
     /// Keeps all blocks that need to be synced in order to unblock the processing of other pending
     /// blocks. The indices of the vector correspond the authority indices.
     missing: Vec<HashSet<BlockReference>>,
+    /// Blocks connected from their header, whose payload has not been received yet. They are
+    /// also kept in `missing` so that their payload is requested if it does not arrive.
+    awaiting_payload: HashSet<BlockReference>,
+    incomplete: IncompleteBlocks,
     block_store: BlockStore,
 }
 
+/// The outcome of adding blocks to the block manager.
+#[derive(Default)]
+pub struct AddedBlocks {
+    /// The newly connected blocks (or block headers), along with their position in the wal.
+    pub processed: Vec<(WalPosition, Data<StatementBlock>)>,
+    /// The blocks previously connected from their header whose payload was received.
+    pub payloads: Vec<Data<StatementBlock>>,
+    /// The blocks that became includable in our own blocks, along with the position in the wal
+    /// of the block (or payload) that completed their causal history.
+    pub includable: Vec<(WalPosition, BlockReference)>,
+}
+
+/// The connected blocks whose causal history is not entirely held with its payloads: the blocks
+/// connected from their header whose payload was not received, and their descendants. They are
+/// not included in our own blocks, as peers could then not fetch the payloads from us (e.g., if
+/// a byzantine author never disseminates them) and commits would wait for them forever. Blocks
+/// unknown to the tracker are complete.
+#[derive(Default)]
+pub struct IncompleteBlocks {
+    /// The number of payloads (their own, or those of incomplete includes) each incomplete block
+    /// waits for.
+    waiting: HashMap<BlockReference, usize>,
+    /// The incomplete blocks waiting for each incomplete block.
+    descendants: HashMap<BlockReference, Vec<BlockReference>>,
+}
+
+impl IncompleteBlocks {
+    /// Add a block (or block header) connected to the graph. Returns the block if it is complete.
+    pub fn add(&mut self, block: &StatementBlock) -> Vec<BlockReference> {
+        let reference = *block.reference();
+        let mut waiting = block.is_header_only() as usize;
+        for include in block.includes() {
+            if self.waiting.contains_key(include) {
+                self.descendants
+                    .entry(*include)
+                    .or_default()
+                    .push(reference);
+                waiting += 1;
+            }
+        }
+        if waiting == 0 {
+            return vec![reference];
+        }
+        self.waiting.insert(reference, waiting);
+        vec![]
+    }
+
+    /// The payload of a block connected from its header was received. Returns the blocks that
+    /// became complete, in causal order.
+    pub fn payload_received(&mut self, reference: BlockReference) -> Vec<BlockReference> {
+        let mut complete = vec![];
+        let mut resolved = vec![reference];
+        while let Some(reference) = resolved.pop() {
+            let Some(waiting) = self.waiting.get_mut(&reference) else {
+                continue;
+            };
+            *waiting -= 1;
+            if *waiting > 0 {
+                continue;
+            }
+            self.waiting.remove(&reference);
+            complete.push(reference);
+            if let Some(descendants) = self.descendants.remove(&reference) {
+                resolved.extend(descendants);
+            }
+        }
+        complete
+    }
+
+    /// Whether the causal history of the block is not entirely held with its payloads.
+    pub fn contains(&self, reference: &BlockReference) -> bool {
+        self.waiting.contains_key(reference)
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+}
+
 impl BlockManager {
     pub fn new(block_store: BlockStore, committee: &Arc<Committee>) -> Self {
         Self {
             blocks_pending: Default::default(),
             block_references_waiting: Default::default(),
             missing: (0..committee.len()).map(|_| HashSet::new()).collect(),
+            awaiting_payload: Default::default(),
+            incomplete: Default::default(),
             block_store,
         }
     }
 
+    /// Resume waiting for the payloads of blocks connected from their header before a restart.
+    pub fn with_awaiting_payload(mut self, awaiting_payload: HashSet<BlockReference>) -> Self {
+        for reference in &awaiting_payload {
+            self.missing[reference.authority as usize].insert(*reference);
+        }
+        self.awaiting_payload = awaiting_payload;
+        self
+    }
+
+    /// Resume tracking the blocks whose causal history was incomplete before a restart.
+    pub fn with_incomplete_blocks(mut self, incomplete: IncompleteBlocks) -> Self {
+        self.incomplete = incomplete;
+        self
+    }
+
+    /// Add blocks (or block headers) to the graph. Returns the newly connected blocks, the blocks
+    /// previously connected from their header whose payload was received, and the blocks that
+    /// became includable.
     pub fn add_blocks(
         &mut self,
         blocks: Vec<Data<StatementBlock>>,
         block_writer: &mut impl BlockWriter,
-    ) -> Vec<(WalPosition, Data<StatementBlock>)> {
+    ) -> AddedBlocks {
+        let span = tracing::debug_span!(
+            "block_manager_add",
+            received = blocks.len(),
+            processed = tracing::field::Empty,
+            suspended = tracing::field::Empty,
+        )
+        .entered();
         let mut blocks: VecDeque<Data<StatementBlock>> = blocks.into();
         let mut newly_blocks_processed: Vec<(WalPosition, Data<StatementBlock>)> = vec![];
+        let mut payloads_received = vec![];
+        let mut includable = vec![];
         while let Some(block) = blocks.pop_front() {
             // Update the highest known round number.
 
             // check whether we have already processed this block and skip it if so.
             let block_reference = block.reference();
-            if self.block_store.block_exists(*block_reference)
-                || self.blocks_pending.contains_key(block_reference)
-            {
+            if self.block_store.block_exists(*block_reference) {
+                // Only the payload of a block connected from its header is new.
+                if !block.is_header_only() && self.awaiting_payload.remove(block_reference) {
+                    tracing::trace!(block = %block_reference, "Received payload");
+                    self.missing[block_reference.authority as usize].remove(block_reference);
+                    let position = block_writer.insert_block(block.clone());
+                    let complete = self.incomplete.payload_received(*block_reference);
+                    includable.extend(complete.into_iter().map(|x| (position, x)));
+                    payloads_received.push(block);
+                }
+                continue;
+            }
+            if let Some(pending) = self.blocks_pending.get_mut(block_reference) {
+                if pending.is_header_only() && !block.is_header_only() {
+                    *pending = block;
+                }
                 continue;
             }
 
@@ -73,12 +204,20 @@ impl BlockManager {
             self.missing[block_reference.authority as usize].remove(block_reference);
 
             if !processed {
+                tracing::trace!(block = %block_reference, "Suspended block with missing ancestors");
                 self.blocks_pending.insert(*block_reference, block);
             } else {
                 let block_reference = *block_reference;
+                tracing::trace!(block = %block_reference, "Processed block");
 
                 // Block can be processed. So need to update indexes etc
                 let position = block_writer.insert_block(block.clone());
+                if block.is_header_only() {
+                    self.awaiting_payload.insert(block_reference);
+                    self.missing[block_reference.authority as usize].insert(block_reference);
+                }
+                let complete = self.incomplete.add(&block);
+                includable.extend(complete.into_iter().map(|x| (position, x)));
                 newly_blocks_processed.push((position, block.clone()));
 
                 // Now unlock any pending blocks, and process them if ready.
@@ -104,12 +243,33 @@ impl BlockManager {
             }
         }
 
-        newly_blocks_processed
+        span.record("processed", newly_blocks_processed.len());
+        span.record("suspended", self.blocks_pending.len());
+        AddedBlocks {
+            processed: newly_blocks_processed,
+            payloads: payloads_received,
+            includable,
+        }
     }
 
     pub fn missing_blocks(&self) -> &[HashSet<BlockReference>] {
         &self.missing
     }
+
+    /// Blocks connected from their header whose payload has not been received yet.
+    pub fn awaiting_payload(&self) -> &HashSet<BlockReference> {
+        &self.awaiting_payload
+    }
+
+    /// Blocks whose causal history is not entirely held with its payloads.
+    pub fn incomplete_blocks(&self) -> &IncompleteBlocks {
+        &self.incomplete
+    }
+
+    /// Number of blocks waiting for their ancestors to be processed.
+    pub fn suspended_blocks(&self) -> usize {
+        self.blocks_pending.len()
+    }
 }
 
 #[cfg(test)]
@@ -131,7 +291,9 @@ mod tests {
             let mut bm = BlockManager::new(block_writer.block_store(), &dag.committee());
             let mut processed_blocks = HashSet::new();
             for block in iter {
-                let processed = bm.add_blocks(vec![block.clone()], &mut block_writer);
+                let processed = bm
+                    .add_blocks(vec![block.clone()], &mut block_writer)
+                    .processed;
                 print!("Adding {:?}:", block.reference());
                 for (_, p) in processed {
                     print!("{:?},", p.reference());
@@ -149,6 +311,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_block_manager_add_header() {
+        let dag =
+            Dag::draw("A1:[A0, B0]; B1:[A0, B0]; B2:[A0, B1]; A2:[A1, B2]").add_genesis_blocks();
+        let mut block_writer = TestBlockWriter::new(&dag.committee());
+        let mut bm = BlockManager::new(block_writer.block_store(), &dag.committee());
+        let mut processed_blocks = 0;
+        for block in dag.random_iter(&mut rng(0)) {
+            let header = Data::new(block.header());
+            let added = bm.add_blocks(vec![header], &mut block_writer);
+            processed_blocks += added.processed.len();
+            assert!(added.payloads.is_empty());
+            assert!(added.includable.is_empty());
+        }
+        // Headers are connected like full blocks, and their payload is awaited.
+        assert_eq!(processed_blocks, dag.len());
+        assert_eq!(bm.awaiting_payload().len(), dag.len());
+        assert_eq!(bm.incomplete_blocks().len(), dag.len());
+        let mut received = HashMap::new();
+        let mut includable = HashSet::new();
+        for block in dag.random_iter(&mut rng(1)) {
+            received.insert(*block.reference(), block.clone());
+            let added = bm.add_blocks(vec![block.clone()], &mut block_writer);
+            assert!(added.processed.is_empty());
+            assert_eq!(added.payloads.len(), 1);
+            assert!(!added.payloads[0].is_header_only());
+            // Blocks become includable once the payloads of all their ancestors are received.
+            for (_, reference) in added.includable {
+                let block = &received[&reference];
+                assert!(block.includes().iter().all(|x| includable.contains(x)));
+                assert!(includable.insert(reference));
+            }
+        }
+        assert_eq!(includable.len(), dag.len());
+        assert!(bm.awaiting_payload().is_empty());
+        assert!(bm.incomplete_blocks().is_empty());
+        assert!(bm.missing_blocks().iter().all(HashSet::is_empty));
+    }
+
     fn rng(s: u8) -> StdRng {
         let mut seed = [0; 32];
         seed[0] = s;
@@ -3,8 +3,9 @@
 
 use std::{
     cmp::max,
-    collections::{BTreeMap, HashMap},
+    collections::{btree_map::Entry, BTreeMap, HashMap},
     io::IoSlice,
+    ops::RangeInclusive,
     sync::Arc,
     time::Instant,
 };
@@ -43,6 +44,9 @@ pub struct BlockStore {
 struct BlockStoreInner {
     index: BTreeMap<RoundNumber, HashMap<(AuthorityIndex, BlockDigest), IndexEntry>>,
     own_blocks: BTreeMap<RoundNumber, BlockDigest>,
+    /// The own blocks conflicting with those of `own_blocks` (only created by byzantine
+    /// authorities equivocating).
+    own_equivocations: BTreeMap<RoundNumber, BlockDigest>,
     highest_round: RoundNumber,
     authority: AuthorityIndex,
     last_seen_by_authority: Vec<RoundNumber>,
@@ -145,6 +149,16 @@ impl BlockStore {
         self.read_index_vec(entries)
     }
 
+    /// Return the blocks of the given rounds, in round order, optionally only those of `authority`.
+    pub fn get_blocks_in_range(
+        &self,
+        rounds: RangeInclusive<RoundNumber>,
+        authority: Option<AuthorityIndex>,
+    ) -> Vec<Data<StatementBlock>> {
+        let entries = self.inner.read().get_blocks_in_range(rounds, authority);
+        self.read_index_vec(entries)
+    }
+
     pub fn get_blocks_at_authority_round(
         &self,
         authority: AuthorityIndex,
@@ -223,14 +237,40 @@ impl BlockStore {
             return;
         }
         let _timer = self.metrics.block_store_cleanup_util.utilization_timer();
-        let unloaded = self.inner.write().unload_below_round(threshold_round);
+        let mut inner = self.inner.write();
+        let unloaded = inner.unload_below_round(threshold_round);
         self.metrics
             .block_store_unloaded_blocks
             .inc_by(unloaded as u64);
+        self.metrics
+            .block_store_loaded_entries
+            .set(inner.loaded_above_round(threshold_round) as i64);
+        self.metrics
+            .block_store_indexed_rounds
+            .set(inner.index.len() as i64);
+        drop(inner);
         let retained_maps = self.block_wal_reader.cleanup();
         self.metrics.wal_mappings.set(retained_maps as i64);
     }
 
+    /// Position in the wal of the first block at or above `round`. Pruning the wal below this
+    /// position does not lose any of these blocks.
+    pub fn first_position_from_round(&self, round: RoundNumber) -> Option<WalPosition> {
+        self.inner.read().first_position_from_round(round)
+    }
+
+    /// Forget the blocks stored below `position` once the wal is pruned up to it.
+    pub fn prune(&self, position: WalPosition) {
+        let mut inner = self.inner.write();
+        let pruned = inner.prune(position);
+        if pruned > 0 {
+            tracing::debug!("Pruned {pruned} entries from block store index");
+        }
+        self.metrics
+            .block_store_indexed_rounds
+            .set(inner.index.len() as i64);
+    }
+
     pub fn get_own_blocks(
         &self,
         from_excluded: RoundNumber,
@@ -240,6 +280,12 @@ impl BlockStore {
         self.read_index_vec(entries)
     }
 
+    /// The own block conflicting with the own block of the round, if this authority equivocated.
+    pub fn get_own_equivocation(&self, round: RoundNumber) -> Option<Data<StatementBlock>> {
+        let entry = self.inner.read().get_own_equivocation(round);
+        entry.map(|entry| self.read_index(entry))
+    }
+
     pub fn get_others_blocks(
         &self,
         from_excluded: RoundNumber,
@@ -257,6 +303,11 @@ impl BlockStore {
         self.inner.read().last_seen_by_authority(authority)
     }
 
+    /// The highest round of the blocks of every authority, in the order of the authority index.
+    pub fn last_seen_by_authorities(&self) -> Vec<RoundNumber> {
+        self.inner.read().last_seen_by_authority.clone()
+    }
+
     pub fn last_own_block_ref(&self) -> Option<BlockReference> {
         self.inner.read().last_own_block()
     }
@@ -316,6 +367,14 @@ impl BlockStore {
     }
 }
 
+impl IndexEntry {
+    fn position(&self) -> WalPosition {
+        match self {
+            IndexEntry::WalPosition(position) | IndexEntry::Loaded(position, _) => *position,
+        }
+    }
+}
+
 impl BlockStoreInner {
     pub fn block_exists(&self, reference: BlockReference) -> bool {
         let Some(blocks) = self.index.get(&reference.round) else {
@@ -351,6 +410,22 @@ impl BlockStoreInner {
         blocks.values().cloned().collect()
     }
 
+    pub fn get_blocks_in_range(
+        &self,
+        rounds: RangeInclusive<RoundNumber>,
+        authority: Option<AuthorityIndex>,
+    ) -> Vec<IndexEntry> {
+        self.index
+            .range(rounds)
+            .flat_map(|(_, blocks)| {
+                blocks
+                    .iter()
+                    .filter(move |((a, _), _)| authority.map_or(true, |authority| *a == authority))
+                    .map(|(_, entry)| entry.clone())
+            })
+            .collect()
+    }
+
     pub fn get_block(&self, reference: BlockReference) -> Option<IndexEntry> {
         self.index
             .get(&reference.round)?
@@ -384,6 +459,46 @@ impl BlockStoreInner {
         unloaded
     }
 
+    /// Count loaded entries above threshold_round (entries below are unloaded by cleanup)
+    pub fn loaded_above_round(&self, threshold_round: RoundNumber) -> usize {
+        self.index
+            .range((threshold_round + 1)..)
+            .flat_map(|(_, map)| map.values())
+            .filter(|entry| matches!(entry, IndexEntry::Loaded(..)))
+            .count()
+    }
+
+    pub fn first_position_from_round(&self, round: RoundNumber) -> Option<WalPosition> {
+        self.index
+            .range(round..)
+            .flat_map(|(_, map)| map.values())
+            .map(IndexEntry::position)
+            .min()
+    }
+
+    /// Remove all entries stored below position, returning the number of removed entries
+    pub fn prune(&mut self, position: WalPosition) -> usize {
+        let mut pruned = 0usize;
+        let authority = self.authority;
+        let own_blocks = &mut self.own_blocks;
+        let own_equivocations = &mut self.own_equivocations;
+        self.index.retain(|round, map| {
+            map.retain(|(author, _), entry| {
+                if entry.position() >= position {
+                    return true;
+                }
+                pruned += 1;
+                if *author == authority {
+                    own_blocks.remove(round);
+                    own_equivocations.remove(round);
+                }
+                false
+            });
+            !map.is_empty()
+        });
+        pruned
+    }
+
     pub fn add_unloaded(&mut self, reference: &BlockReference, position: WalPosition) {
         self.highest_round = max(self.highest_round, reference.round());
         let map = self.index.entry(reference.round()).or_default();
@@ -471,10 +586,25 @@ impl BlockStoreInner {
         if reference.round > self.last_own_block.map(|r| r.round).unwrap_or_default() {
             self.last_own_block = Some(*reference);
         }
-        assert!(self
-            .own_blocks
-            .insert(reference.round, reference.digest)
-            .is_none());
+        // The first own block of a round is the one disseminated to all peers.
+        match self.own_blocks.entry(reference.round) {
+            Entry::Vacant(entry) => {
+                entry.insert(reference.digest);
+            }
+            Entry::Occupied(_) => assert!(self
+                .own_equivocations
+                .insert(reference.round, reference.digest)
+                .is_none()),
+        }
+    }
+
+    pub fn get_own_equivocation(&self, round: RoundNumber) -> Option<IndexEntry> {
+        let digest = *self.own_equivocations.get(&round)?;
+        self.get_block(BlockReference {
+            authority: self.authority,
+            round,
+            digest,
+        })
     }
 
     pub fn last_own_block(&self) -> Option<BlockReference> {
@@ -529,7 +659,8 @@ impl OwnBlockData {
     }
 
     pub fn write_to_wal(&self, writer: &mut WalWriter) -> WalPosition {
-        let header = bincode::serialize(&self.next_entry).expect("Serialization failed");
+        let mut header = [0u8; OWN_BLOCK_HEADER_SIZE];
+        bincode::serialize_into(&mut header[..], &self.next_entry).expect("Serialization failed");
         let header = IoSlice::new(&header);
         let block = IoSlice::new(self.block.serialized_bytes());
         writer
@@ -0,0 +1,96 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{mem, ops::Deref, sync::Arc};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// Buffers larger than this are dropped instead of returned to the pool.
+const MAX_POOLED_CAPACITY: usize = 16 * 1024 * 1024;
+/// Maximum number of idle buffers kept by the pool.
+const MAX_POOLED_BUFFERS: usize = 64;
+
+/// A pool of byte buffers for serializing messages that are written out right away.
+/// Serializing every message into a fresh `Vec<u8>` churns the allocator at high throughput,
+/// while buffers returned to the pool keep their capacity for the next message.
+#[derive(Clone, Default)]
+pub struct BufferPool {
+    buffers: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+/// A buffer taken from a [`BufferPool`], returned to the pool when dropped.
+pub struct PooledBuffer {
+    buffer: Vec<u8>,
+    pool: BufferPool,
+}
+
+impl BufferPool {
+    /// Take an empty buffer from the pool, or allocate a new one if the pool is empty.
+    pub fn take(&self) -> PooledBuffer {
+        let buffer = self.buffers.lock().pop().unwrap_or_default();
+        PooledBuffer {
+            buffer,
+            pool: self.clone(),
+        }
+    }
+
+    /// Serialize the value with bincode into a buffer of the pool.
+    pub fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> PooledBuffer {
+        let mut buffer = self.take();
+        bincode::serialize_into(&mut buffer.buffer, value).expect("Serialization should not fail");
+        buffer
+    }
+
+    fn put(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() == 0 || buffer.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+        buffer.clear();
+        let mut buffers = self.buffers.lock();
+        if buffers.len() < MAX_POOLED_BUFFERS {
+            buffers.push(buffer);
+        }
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        self.pool.put(mem::take(&mut self.buffer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffers_are_reused() {
+        let pool = BufferPool::default();
+        let serialized = pool.serialize(&vec![7u64; 100]);
+        assert_eq!(&*serialized, bincode::serialize(&vec![7u64; 100]).unwrap());
+        let capacity = serialized.buffer.capacity();
+        drop(serialized);
+        assert_eq!(pool.buffers.lock().len(), 1);
+
+        let buffer = pool.take();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.buffer.capacity(), capacity);
+        assert!(pool.buffers.lock().is_empty());
+    }
+
+    #[test]
+    fn oversized_buffers_are_dropped() {
+        let pool = BufferPool::default();
+        drop(pool.serialize(&vec![0u8; MAX_POOLED_CAPACITY + 1]));
+        assert!(pool.buffers.lock().is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rolling digests over the commit history. Validators that committed the same sequence of
+//! sub-dags have the same checkpoint at every height, so comparing checkpoints detects divergence
+//! without comparing the whole logs.
+
+use std::{collections::VecDeque, fmt, sync::Arc};
+
+use digest::Digest;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{consensus::linearizer::CommittedSubDag, crypto::AsBytes};
+
+type CheckpointHasher = blake2::Blake2b<digest::consts::U32>;
+
+/// The number of recent checkpoints kept to answer queries by height.
+const CHECKPOINT_HISTORY: usize = 1024;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The number of commits covered by the digest.
+    pub height: u64,
+    pub digest: [u8; 32],
+}
+
+impl Checkpoint {
+    /// The checkpoint following this one once `commit` is committed. It covers the anchor of the
+    /// commit and its transactions, in commit order.
+    pub fn next(&self, commit: &CommittedSubDag) -> Self {
+        let height = self.height + 1;
+        let mut hasher = CheckpointHasher::default();
+        hasher.update(self.digest);
+        hasher.update(height.to_le_bytes());
+        hasher.update(commit.anchor.digest.as_bytes());
+        for block in &commit.blocks {
+            for (_, transaction) in block.shared_transactions() {
+                let transaction = transaction.as_bytes();
+                hasher.update((transaction.len() as u64).to_le_bytes());
+                hasher.update(transaction);
+            }
+        }
+        Self {
+            height,
+            digest: hasher.finalize().into(),
+        }
+    }
+
+    /// The first bytes of the digest as an integer, exact when exported as a float metric.
+    pub fn digest_prefix(&self) -> i64 {
+        let mut prefix = [0u8; 8];
+        prefix[..6].copy_from_slice(&self.digest[..6]);
+        i64::from_le_bytes(prefix)
+    }
+}
+
+impl fmt::Debug for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", hex::encode(self.digest), self.height)
+    }
+}
+
+/// The recent checkpoints of a validator, shared with the admin routes.
+#[derive(Clone, Default)]
+pub struct Checkpoints(Arc<Mutex<VecDeque<Checkpoint>>>);
+
+impl Checkpoints {
+    pub fn record(&self, checkpoint: Checkpoint) {
+        let mut checkpoints = self.0.lock();
+        if checkpoints.len() == CHECKPOINT_HISTORY {
+            checkpoints.pop_front();
+        }
+        checkpoints.push_back(checkpoint);
+    }
+
+    pub fn latest(&self) -> Option<Checkpoint> {
+        self.0.lock().back().copied()
+    }
+
+    /// The checkpoint at `height`, if it is recent enough to be kept.
+    pub fn get(&self, height: u64) -> Option<Checkpoint> {
+        let checkpoints = self.0.lock();
+        let first = checkpoints.front()?.height;
+        let position = height.checked_sub(first)?;
+        checkpoints.get(position as usize).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::Data,
+        types::{BaseStatement, StatementBlock, Transaction},
+    };
+
+    fn commit(transactions: &[&[u8]]) -> CommittedSubDag {
+        let statements = transactions
+            .iter()
+            .map(|data| BaseStatement::Share(Transaction::new(data.to_vec())))
+            .collect();
+        let block = Data::new(StatementBlock::new(
+            0,
+            1,
+            vec![],
+            statements,
+            0,
+            false,
+            Default::default(),
+        ));
+        CommittedSubDag::new(*block.reference(), vec![block])
+    }
+
+    #[test]
+    fn checkpoints_depend_on_history() {
+        let genesis = Checkpoint::default();
+        let first = genesis.next(&commit(&[b"a", b"b"]));
+        assert_eq!(first.height, 1);
+        assert_eq!(first, genesis.next(&commit(&[b"a", b"b"])));
+        // Transactions are delimited, so moving bytes between them changes the digest.
+        assert_ne!(first, genesis.next(&commit(&[b"ab", b""])));
+        assert_ne!(first.next(&commit(&[])), genesis.next(&commit(&[])));
+    }
+
+    #[test]
+    fn checkpoint_history() {
+        let checkpoints = Checkpoints::default();
+        assert_eq!(checkpoints.latest(), None);
+        let mut checkpoint = Checkpoint::default();
+        for _ in 0..CHECKPOINT_HISTORY + 10 {
+            checkpoint = checkpoint.next(&commit(&[]));
+            checkpoints.record(checkpoint);
+        }
+        assert_eq!(checkpoints.latest(), Some(checkpoint));
+        assert_eq!(checkpoints.get(checkpoint.height), Some(checkpoint));
+        assert_eq!(checkpoints.get(10), None);
+        assert_eq!(checkpoints.get(11).unwrap().height, 11);
+        assert_eq!(checkpoints.get(checkpoint.height + 1), None);
+    }
+}
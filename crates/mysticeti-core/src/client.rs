@@ -0,0 +1,397 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A load generator submitting transactions to a validator through its submission endpoint, and
+//! measuring how long they take to be accepted and committed.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use futures::future;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::{select, sync::mpsc};
+
+use crate::{
+    config::{ClientParameters, LoadMode},
+    runtime::{self, spawn_named, timestamp_utc},
+    submission::{self, SubmissionResponse, SubmissionSender, TransactionKey},
+    types::Transaction,
+};
+
+/// The interval at which the client logs its measurements.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a closed-loop client waits for a transaction to be committed before replacing it
+/// (e.g., if the notification of its commit was lost). Checked every `REPORT_INTERVAL`.
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct LoadClient {
+    address: SocketAddr,
+    parameters: ClientParameters,
+    rng: StdRng,
+    /// The id of the last transaction submitted.
+    last_id: u64,
+}
+
+/// What the client measured over its whole run.
+#[derive(Debug, Default)]
+pub struct LoadSummary {
+    pub submitted: u64,
+    pub committed: u64,
+    /// Transactions replaced by a closed-loop client as they were not committed in time.
+    pub timed_out: u64,
+    /// Time from sending transactions until the validator accepts them.
+    pub submission_latency: Option<LatencySummary>,
+    /// Time from the creation of transactions until they are committed.
+    pub commit_latency: Option<LatencySummary>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p99: Duration,
+}
+
+impl LoadClient {
+    pub fn new(address: SocketAddr, parameters: ClientParameters, seed: u64) -> Self {
+        Self {
+            address,
+            parameters,
+            rng: StdRng::seed_from_u64(seed),
+            last_id: 0,
+        }
+    }
+
+    /// Submit transactions following the configured load mode until `duration` elapses, or
+    /// forever if no duration is given. Measurements are logged periodically.
+    pub async fn run(mut self, duration: Option<Duration>) -> io::Result<LoadSummary> {
+        if self.parameters.transaction_size < 16 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Transactions must be at least 16 bytes long",
+            ));
+        }
+        if self.parameters.mode == LoadMode::ClosedLoop(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Closed-loop clients need at least one outstanding transaction",
+            ));
+        }
+        let (mut sender, mut receiver) = submission::connect(self.address).await?;
+        match self.parameters.mode {
+            LoadMode::OpenLoop => tracing::info!(
+                "Submitting {} transactions of {} bytes per second to {}",
+                self.parameters.load,
+                self.parameters.transaction_size,
+                self.address
+            ),
+            LoadMode::ClosedLoop(outstanding) => tracing::info!(
+                "Keeping {outstanding} transactions of {} bytes in flight on {}",
+                self.parameters.transaction_size,
+                self.address
+            ),
+        }
+        // Reading a response is not cancel safe, so responses are read by a dedicated task.
+        let (response_sender, mut responses) = mpsc::channel(1024);
+        let reader = spawn_named("client-reader", async move {
+            loop {
+                let response = receiver.recv().await;
+                let failed = response.is_err();
+                if response_sender.send(response).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        runtime::sleep(self.parameters.initial_delay).await;
+        let block_interval = self.parameters.block_interval;
+        let start = runtime::TimeInstant::now();
+        let mut interval = runtime::TimeInterval::new(block_interval);
+        let mut report = runtime::TimeInterval::new(REPORT_INTERVAL);
+        report.tick().await;
+        let stop = async {
+            match duration {
+                Some(duration) => runtime::sleep(duration).await,
+                None => future::pending().await,
+            }
+        };
+        tokio::pin!(stop);
+
+        self.last_id = self.rng.gen();
+        let mut in_flight = VecDeque::new();
+        let mut summary = LoadSummary::default();
+        let mut total = (Latencies::default(), Latencies::default());
+        let mut period = (Latencies::default(), Latencies::default());
+        let mut period_counts = (0u64, 0u64, 0u64);
+        let open_loop = self.parameters.mode == LoadMode::OpenLoop;
+        // The transactions of a closed-loop client awaiting their commit, with their deadline.
+        let mut awaiting = HashMap::new();
+        if let LoadMode::ClosedLoop(outstanding) = self.parameters.mode {
+            self.submit_awaited(&mut sender, &mut awaiting, outstanding)
+                .await?;
+            in_flight.push_back(Instant::now());
+            period_counts.0 += outstanding as u64;
+        }
+        let result = loop {
+            select! {
+                _ = interval.tick(), if open_loop => {
+                    let load = self.parameters.shape.load(self.parameters.load, start.elapsed());
+                    let transactions_per_interval =
+                        (load as u128 * block_interval.as_millis()).div_ceil(1000) as usize;
+                    if let Err(e) = self.submit(&mut sender, transactions_per_interval).await {
+                        break Err(e);
+                    }
+                    in_flight.push_back(Instant::now());
+                    period_counts.0 += transactions_per_interval as u64;
+                }
+                response = responses.recv() => match response {
+                    Some(Ok(SubmissionResponse::Accepted)) => {
+                        if let Some(sent) = in_flight.pop_front() {
+                            period.0.record(sent.elapsed());
+                        }
+                    }
+                    Some(Ok(SubmissionResponse::Committed(keys))) => {
+                        let now = timestamp_utc();
+                        for key in &keys {
+                            period.1.record(now.saturating_sub(created_at(key)));
+                        }
+                        period_counts.1 += keys.len() as u64;
+                        // Replace the committed transactions, unless they timed out already.
+                        let replaced = keys
+                            .iter()
+                            .filter(|key| awaiting.remove(*key).is_some())
+                            .count();
+                        if replaced > 0 {
+                            let submitted =
+                                self.submit_awaited(&mut sender, &mut awaiting, replaced);
+                            if let Err(e) = submitted.await {
+                                break Err(e);
+                            }
+                            in_flight.push_back(Instant::now());
+                            period_counts.0 += replaced as u64;
+                        }
+                    }
+                    Some(Err(e)) => break Err(e),
+                    None => break Err(io::ErrorKind::UnexpectedEof.into()),
+                },
+                _ = report.tick() => {
+                    // Replace the transactions not committed in time, so that the window of a
+                    // closed-loop client does not shrink when commit notifications are lost.
+                    let now = Instant::now();
+                    let before = awaiting.len();
+                    awaiting.retain(|_, deadline| *deadline > now);
+                    let timed_out = before - awaiting.len();
+                    if timed_out > 0 {
+                        let submitted = self.submit_awaited(&mut sender, &mut awaiting, timed_out);
+                        if let Err(e) = submitted.await {
+                            break Err(e);
+                        }
+                        in_flight.push_back(Instant::now());
+                        period_counts.0 += timed_out as u64;
+                        period_counts.2 += timed_out as u64;
+                    }
+                    tracing::info!(
+                        "Submitted {} and committed {} transactions in the last {}s \
+                        ({} timed out), submission latency: {}, commit latency: {}",
+                        period_counts.0,
+                        period_counts.1,
+                        REPORT_INTERVAL.as_secs(),
+                        period_counts.2,
+                        DisplaySummary(period.0.summary()),
+                        DisplaySummary(period.1.summary()),
+                    );
+                    summary.submitted += period_counts.0;
+                    summary.committed += period_counts.1;
+                    summary.timed_out += period_counts.2;
+                    period_counts = (0, 0, 0);
+                    total.0.append(&mut period.0);
+                    total.1.append(&mut period.1);
+                }
+                _ = &mut stop => break Ok(()),
+            }
+        };
+        reader.abort();
+        result?;
+
+        summary.submitted += period_counts.0;
+        summary.committed += period_counts.1;
+        summary.timed_out += period_counts.2;
+        total.0.append(&mut period.0);
+        total.1.append(&mut period.1);
+        summary.submission_latency = total.0.summary();
+        summary.commit_latency = total.1.summary();
+        Ok(summary)
+    }
+
+    async fn submit(&mut self, sender: &mut SubmissionSender, count: usize) -> io::Result<()> {
+        let batch = self.make_batch(count);
+        sender.submit(batch).await
+    }
+
+    /// Submit transactions that await their commit until `TRANSACTION_TIMEOUT` (closed loop).
+    async fn submit_awaited(
+        &mut self,
+        sender: &mut SubmissionSender,
+        awaiting: &mut HashMap<TransactionKey, Instant>,
+        count: usize,
+    ) -> io::Result<()> {
+        let batch = self.make_batch(count);
+        let deadline = Instant::now() + TRANSACTION_TIMEOUT;
+        let keys = batch.iter().filter_map(submission::transaction_key);
+        awaiting.extend(keys.map(|key| (key, deadline)));
+        sender.submit(batch).await
+    }
+
+    fn make_batch(&mut self, count: usize) -> Vec<Transaction> {
+        let size = self.parameters.transaction_size;
+        (0..count)
+            .map(|_| {
+                self.last_id = self.last_id.wrapping_add(1);
+                make_transaction(size, self.last_id)
+            })
+            .collect()
+    }
+}
+
+/// Transactions start with their creation time in milliseconds, followed by their id.
+fn make_transaction(size: usize, id: u64) -> Transaction {
+    let timestamp = timestamp_utc().as_millis() as u64;
+    let mut transaction = Vec::with_capacity(size);
+    transaction.extend_from_slice(&timestamp.to_le_bytes());
+    transaction.extend_from_slice(&id.to_le_bytes());
+    transaction.resize(size, 0);
+    Transaction::new(transaction)
+}
+
+fn created_at(key: &TransactionKey) -> Duration {
+    let mut timestamp = [0u8; 8];
+    timestamp.copy_from_slice(&key[..8]);
+    Duration::from_millis(u64::from_le_bytes(timestamp))
+}
+
+/// A histogram of latencies, whose size does not grow with the number of transactions: latencies
+/// are counted in buckets of milliseconds rounded down to two significant digits.
+#[derive(Default)]
+struct Latencies {
+    buckets: BTreeMap<u64, usize>,
+    count: usize,
+    sum: Duration,
+}
+
+impl Latencies {
+    fn record(&mut self, latency: Duration) {
+        *self.buckets.entry(Self::bucket(latency)).or_default() += 1;
+        self.count += 1;
+        self.sum += latency;
+    }
+
+    fn bucket(latency: Duration) -> u64 {
+        let mut millis = latency.as_millis() as u64;
+        let mut scale = 1;
+        while millis >= 100 {
+            millis /= 10;
+            scale *= 10;
+        }
+        millis * scale
+    }
+
+    fn append(&mut self, other: &mut Self) {
+        let other = std::mem::take(other);
+        for (bucket, count) in other.buckets {
+            *self.buckets.entry(bucket).or_default() += count;
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+    }
+
+    fn summary(&self) -> Option<LatencySummary> {
+        if self.count == 0 {
+            return None;
+        }
+        let percentile = |p: usize| {
+            let index = (self.count * p / 100).min(self.count - 1);
+            let mut seen = 0;
+            for (bucket, count) in &self.buckets {
+                seen += count;
+                if seen > index {
+                    return Duration::from_millis(*bucket);
+                }
+            }
+            unreachable!("The buckets hold all the latencies")
+        };
+        Some(LatencySummary {
+            count: self.count,
+            mean: self.sum / self.count as u32,
+            p50: percentile(50),
+            p99: percentile(99),
+        })
+    }
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mean {} ms, p50 {} ms, p99 {} ms",
+            self.mean.as_millis(),
+            self.p50.as_millis(),
+            self.p99.as_millis()
+        )
+    }
+}
+
+/// Display a summary, or a dash when nothing was measured.
+struct DisplaySummary(Option<LatencySummary>);
+
+impl fmt::Display for DisplaySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(summary) => summary.fmt(f),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Latencies, LatencySummary};
+
+    #[test]
+    fn latency_summary() {
+        let mut latencies = Latencies::default();
+        assert_eq!(latencies.summary(), None);
+        for ms in (1..=100).rev() {
+            latencies.record(Duration::from_millis(ms));
+        }
+        assert_eq!(
+            latencies.summary(),
+            Some(LatencySummary {
+                count: 100,
+                mean: Duration::from_micros(50_500),
+                p50: Duration::from_millis(51),
+                p99: Duration::from_millis(100),
+            })
+        );
+
+        // Larger latencies are rounded down to two significant digits.
+        let mut other = Latencies::default();
+        for ms in [1234, 5678, 98765] {
+            other.record(Duration::from_millis(ms));
+        }
+        latencies.append(&mut other);
+        assert_eq!(other.summary(), None);
+        let summary = latencies.summary().unwrap();
+        assert_eq!(summary.count, 103);
+        assert_eq!(summary.p99, Duration::from_millis(5600));
+        assert_eq!(latencies.buckets.len(), 103);
+    }
+}
@@ -148,6 +148,30 @@ impl Committee {
         self.authorities.len()
     }
 
+    /// Check a committee loaded from file. Unlike `Committee::new`, deserialization does not
+    /// check the list of authorities.
+    pub fn validate(&self) -> eyre::Result<()> {
+        eyre::ensure!(
+            !self.authorities.is_empty(),
+            "The committee has no authorities"
+        );
+        eyre::ensure!(
+            self.authorities.len() <= 128,
+            "The committee has {} authorities, at most 128 are supported",
+            self.authorities.len()
+        );
+        for (i, authority) in self.authorities.iter().enumerate() {
+            eyre::ensure!(authority.stake > 0, "Authority {i} has no stake");
+            if let Some(j) = self.authorities[..i]
+                .iter()
+                .position(|a| a.public_key == authority.public_key)
+            {
+                eyre::bail!("Authorities {j} and {i} have the same public key");
+            }
+        }
+        Ok(())
+    }
+
     pub fn new_for_benchmarks(committee_size: usize) -> Arc<Self> {
         Self::new(
             Signer::new_for_test(committee_size)
@@ -387,6 +411,14 @@ impl<TH: CommitteeThreshold, H: ProcessedTransactionHandler<TransactionLocator>>
     pub fn is_empty(&self) -> bool {
         self.pending.is_empty()
     }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
 }
 
 impl<TH: CommitteeThreshold> TransactionAggregator<TH> {
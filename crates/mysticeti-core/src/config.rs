@@ -2,31 +2,65 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    collections::HashMap,
+    fmt,
     fs,
     io,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
+    str::FromStr,
     time::Duration,
 };
 
+use eyre::{bail, ensure, Context};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
+    committee::Committee,
+    consensus::MINIMUM_WAVE_LENGTH,
     crypto::{dummy_signer, Signer},
     types::{AuthorityIndex, PublicKey, RoundNumber},
 };
 
+/// The file formats configuration files can be written in, picked from the file extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Files with a `.toml` extension are TOML, anything else is YAML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Yaml,
+        }
+    }
+}
+
 pub trait ImportExport: Serialize + DeserializeOwned {
     fn load<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
         let content = fs::read_to_string(&path)?;
-        let object =
-            serde_yaml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let object = match ConfigFormat::from_path(path.as_ref()) {
+            ConfigFormat::Yaml => serde_yaml::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            ConfigFormat::Toml => toml::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        };
         Ok(object)
     }
 
     fn print<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
-        let content =
-            serde_yaml::to_string(self).expect("Failed to serialize object to YAML string");
+        let content = match ConfigFormat::from_path(path.as_ref()) {
+            ConfigFormat::Yaml => {
+                serde_yaml::to_string(self).expect("Failed to serialize object to YAML string")
+            }
+            // TOML can not represent every value (e.g. integers above i64::MAX).
+            ConfigFormat::Toml => {
+                toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+            }
+        };
         fs::write(&path, content)
     }
 }
@@ -51,6 +85,171 @@ pub struct NodeParameters {
     pub consensus_only: bool,
     #[serde(default = "node_defaults::default_enable_synchronizer")]
     pub enable_synchronizer: bool,
+    #[serde(default = "node_defaults::default_wal_sync")]
+    pub wal_sync: WalSyncPolicy,
+    /// Open the wal with O_DSYNC, so that every write reaches the disk before it returns.
+    /// O_DIRECT is not offered as wal entries are not aligned to the block size of the disk.
+    #[serde(default = "node_defaults::default_wal_write_through")]
+    pub wal_write_through: bool,
+    #[serde(default)]
+    pub block_sync: BlockSyncParameters,
+    /// Log the authorities whose blocks are missing once the threshold clock is stuck in the
+    /// same round for this long.
+    #[serde(default = "node_defaults::default_threshold_clock_stall_alarm")]
+    pub threshold_clock_stall_alarm: Duration,
+    /// Send the header of every block (its references, without the statements) on a priority
+    /// lane ahead of the full block, and blocks without transactions entirely on that lane.
+    /// Peers accept headers in the DAG, so that rounds advance while the payloads are in flight;
+    /// leaders are only committed once the payloads of their sub-dag are received. Note that a
+    /// byzantine author withholding the payload of a header stalls the commits.
+    #[serde(default = "node_defaults::default_header_first")]
+    pub header_first: bool,
+}
+
+/// How missing blocks are requested from peers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockSyncParameters {
+    /// The interval at which missing blocks are checked and requests are sent.
+    #[serde(default = "node_defaults::default_block_sync_sample_interval")]
+    pub sample_interval: Duration,
+    /// How long a block must be missing before it is requested, as it usually arrives on its own.
+    #[serde(default = "node_defaults::default_block_sync_grace_period")]
+    pub grace_period: Duration,
+    /// How long to wait for a requested block before requesting it again.
+    #[serde(default = "node_defaults::default_block_sync_request_timeout")]
+    pub request_timeout: Duration,
+    /// The upper bound of the request timeout, which doubles with every retry.
+    #[serde(default = "node_defaults::default_block_sync_max_request_timeout")]
+    pub max_request_timeout: Duration,
+    /// The fraction of the request timeout drawn at random, so that nodes missing the same
+    /// blocks do not all retry at once.
+    #[serde(default = "node_defaults::default_block_sync_jitter")]
+    pub jitter: f64,
+    /// Blocks missing from an authority are requested by round range rather than one by one
+    /// when more than this many are missing at once.
+    #[serde(default = "node_defaults::default_block_sync_range_request_threshold")]
+    pub range_request_threshold: usize,
+    /// The interval at which peers exchange the highest round they have from every authority.
+    /// Blocks a peer has that are still missing at the next exchange are requested from it.
+    #[serde(default = "node_defaults::default_block_sync_gossip_interval")]
+    pub gossip_interval: Duration,
+}
+
+impl BlockSyncParameters {
+    /// The time to wait for an answer to the given attempt (starting at 0) of a request.
+    /// `random` is drawn uniformly from `[0, 1)`.
+    pub fn request_timeout(&self, attempt: u32, random: f64) -> Duration {
+        let timeout = self
+            .request_timeout
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_request_timeout);
+        timeout.mul_f64(1.0 - self.jitter * random)
+    }
+
+    fn validate(&self) -> eyre::Result<()> {
+        ensure!(
+            !self.sample_interval.is_zero(),
+            "block_sync.sample_interval must be greater than zero"
+        );
+        ensure!(
+            !self.gossip_interval.is_zero(),
+            "block_sync.gossip_interval must be greater than zero"
+        );
+        ensure!(
+            !self.request_timeout.is_zero(),
+            "block_sync.request_timeout must be greater than zero"
+        );
+        ensure!(
+            self.max_request_timeout >= self.request_timeout,
+            "block_sync.max_request_timeout must be at least block_sync.request_timeout"
+        );
+        ensure!(
+            (0.0..1.0).contains(&self.jitter),
+            "block_sync.jitter is {} but must be in [0, 1)",
+            self.jitter
+        );
+        Ok(())
+    }
+}
+
+impl Default for BlockSyncParameters {
+    fn default() -> Self {
+        Self {
+            sample_interval: node_defaults::default_block_sync_sample_interval(),
+            grace_period: node_defaults::default_block_sync_grace_period(),
+            request_timeout: node_defaults::default_block_sync_request_timeout(),
+            max_request_timeout: node_defaults::default_block_sync_max_request_timeout(),
+            jitter: node_defaults::default_block_sync_jitter(),
+            range_request_threshold: node_defaults::default_block_sync_range_request_threshold(),
+            gossip_interval: node_defaults::default_block_sync_gossip_interval(),
+        }
+    }
+}
+
+/// When the wal is synced to disk. Whatever the policy, the wal is synced on shutdown.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WalSyncPolicy {
+    /// Sync after every write, before any block is sent or any commit is reported.
+    EveryWrite,
+    /// Sync in the background at a fixed interval.
+    Periodic(Duration),
+    /// Sync whenever new commits are written.
+    OnCommit,
+}
+
+impl WalSyncPolicy {
+    /// The interval of the background sync, if any.
+    pub fn interval(&self) -> Option<Duration> {
+        match self {
+            Self::Periodic(interval) => Some(*interval),
+            Self::EveryWrite | Self::OnCommit => None,
+        }
+    }
+}
+
+impl fmt::Display for WalSyncPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EveryWrite => write!(f, "every-write"),
+            Self::Periodic(interval) => write!(f, "periodic-{}ms", interval.as_millis()),
+            Self::OnCommit => write!(f, "on-commit"),
+        }
+    }
+}
+
+/// A deviation from the protocol, to evaluate how the honest validators cope with byzantine ones.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ByzantineStrategy {
+    /// Never vote for the leaders of the previous round: the blocks of the validator do not
+    /// reference the blocks of these leaders, unless they are needed to reach a quorum.
+    WithholdVotes,
+    /// Create two conflicting blocks every round, each disseminated to half of the peers.
+    Equivocate,
+}
+
+impl fmt::Display for ByzantineStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WithholdVotes => write!(f, "withhold-votes"),
+            Self::Equivocate => write!(f, "equivocate"),
+        }
+    }
+}
+
+impl FromStr for ByzantineStrategy {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "withhold-votes" => Ok(Self::WithholdVotes),
+            "equivocate" => Ok(Self::Equivocate),
+            _ => bail!(
+                "Unknown byzantine strategy '{s}' (expected 'withhold-votes' or 'equivocate')"
+            ),
+        }
+    }
 }
 
 pub mod node_defaults {
@@ -59,7 +258,7 @@ pub mod node_defaults {
     }
 
     pub fn default_leader_timeout() -> std::time::Duration {
-        std::time::Duration::from_secs(2)
+        std::time::Duration::from_secs(1)
     }
 
     pub fn default_max_block_size() -> usize {
@@ -74,6 +273,10 @@ pub mod node_defaults {
         std::time::Duration::from_secs(2)
     }
 
+    pub fn default_threshold_clock_stall_alarm() -> std::time::Duration {
+        std::time::Duration::from_secs(10)
+    }
+
     pub fn default_number_of_leaders() -> usize {
         2
     }
@@ -89,6 +292,46 @@ pub mod node_defaults {
     pub fn default_enable_synchronizer() -> bool {
         false
     }
+
+    pub fn default_wal_sync() -> super::WalSyncPolicy {
+        super::WalSyncPolicy::Periodic(std::time::Duration::from_secs(1))
+    }
+
+    pub fn default_wal_write_through() -> bool {
+        false
+    }
+
+    pub fn default_block_sync_sample_interval() -> std::time::Duration {
+        std::time::Duration::from_secs(5)
+    }
+
+    pub fn default_block_sync_grace_period() -> std::time::Duration {
+        std::time::Duration::from_secs(15)
+    }
+
+    pub fn default_block_sync_request_timeout() -> std::time::Duration {
+        std::time::Duration::from_secs(5)
+    }
+
+    pub fn default_block_sync_max_request_timeout() -> std::time::Duration {
+        std::time::Duration::from_secs(60)
+    }
+
+    pub fn default_block_sync_jitter() -> f64 {
+        0.5
+    }
+
+    pub fn default_block_sync_range_request_threshold() -> usize {
+        10
+    }
+
+    pub fn default_block_sync_gossip_interval() -> std::time::Duration {
+        std::time::Duration::from_secs(5)
+    }
+
+    pub fn default_header_first() -> bool {
+        false
+    }
 }
 
 impl Default for NodeParameters {
@@ -103,10 +346,52 @@ impl Default for NodeParameters {
             enable_pipelining: node_defaults::default_enable_pipelining(),
             consensus_only: node_defaults::default_consensus_only(),
             enable_synchronizer: node_defaults::default_enable_synchronizer(),
+            wal_sync: node_defaults::default_wal_sync(),
+            wal_write_through: node_defaults::default_wal_write_through(),
+            block_sync: BlockSyncParameters::default(),
+            threshold_clock_stall_alarm: node_defaults::default_threshold_clock_stall_alarm(),
+            header_first: node_defaults::default_header_first(),
         }
     }
 }
 
+impl NodeParameters {
+    /// Check the parameters against the size of the committee.
+    pub fn validate(&self, committee_size: usize) -> eyre::Result<()> {
+        ensure!(
+            self.wave_length >= MINIMUM_WAVE_LENGTH,
+            "wave_length is {} but must be at least {MINIMUM_WAVE_LENGTH}",
+            self.wave_length
+        );
+        ensure!(
+            (1..=committee_size).contains(&self.number_of_leaders),
+            "number_of_leaders is {} but must be between 1 and the committee size ({committee_size})",
+            self.number_of_leaders
+        );
+        ensure!(
+            !self.leader_timeout.is_zero(),
+            "leader_timeout must be greater than zero"
+        );
+        ensure!(
+            !self.threshold_clock_stall_alarm.is_zero(),
+            "threshold_clock_stall_alarm must be greater than zero"
+        );
+        ensure!(
+            self.max_block_size > 0,
+            "max_block_size must be greater than zero"
+        );
+        ensure!(
+            self.rounds_in_epoch > 0,
+            "rounds_in_epoch must be greater than zero"
+        );
+        ensure!(
+            self.wal_sync.interval() != Some(Duration::ZERO),
+            "The interval of the periodic wal_sync must be greater than zero"
+        );
+        self.block_sync.validate()
+    }
+}
+
 impl ImportExport for NodeParameters {}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -125,6 +410,10 @@ pub struct NodePublicConfig {
 impl NodePublicConfig {
     pub const DEFAULT_FILENAME: &'static str = "public-config.yaml";
     pub const PORT_OFFSET_FOR_TESTS: u16 = 1500;
+    /// The network port of every node when they all use the same ports (see `with_uniform_ports`).
+    pub const UNIFORM_NETWORK_PORT: u16 = Self::PORT_OFFSET_FOR_TESTS;
+    /// The metrics port of every node when they all use the same ports.
+    pub const UNIFORM_METRICS_PORT: u16 = Self::UNIFORM_NETWORK_PORT + 1;
 
     pub fn new_for_tests(committee_size: usize) -> Self {
         let keys = Signer::new_for_test(committee_size);
@@ -176,6 +465,16 @@ impl NodePublicConfig {
         self
     }
 
+    /// Bind every node to the same network and metrics ports. Only valid when each node has its
+    /// own ip (e.g., a pod of a kubernetes cluster).
+    pub fn with_uniform_ports(mut self) -> Self {
+        for id in self.identifiers.iter_mut() {
+            id.network_address.set_port(Self::UNIFORM_NETWORK_PORT);
+            id.metrics_address.set_port(Self::UNIFORM_METRICS_PORT);
+        }
+        self
+    }
+
     /// Return all network addresses (including our own) in the order of the authority index.
     pub fn all_network_addresses(&self) -> impl Iterator<Item = SocketAddr> + '_ {
         self.identifiers.iter().map(|id| id.network_address)
@@ -197,6 +496,55 @@ impl NodePublicConfig {
             .get(authority as usize)
             .map(|id| id.metrics_address)
     }
+
+    /// Check that the config is usable with the committee, so that mistakes are reported
+    /// before the node starts rather than as panics during startup.
+    pub fn validate(&self, committee: &Committee) -> eyre::Result<()> {
+        ensure!(
+            !self.identifiers.is_empty(),
+            "The public config lists no authorities"
+        );
+        ensure!(
+            self.identifiers.len() == committee.len(),
+            "The public config lists {} authorities but the committee has {}",
+            self.identifiers.len(),
+            committee.len()
+        );
+        let mut addresses: HashMap<SocketAddr, String> = HashMap::new();
+        for (i, id) in self.identifiers.iter().enumerate() {
+            let authority = i as AuthorityIndex;
+            if committee.get_public_key(authority) != Some(&id.public_key) {
+                bail!("The public key of authority {i} does not match the committee");
+            }
+            for (kind, address) in [
+                ("network", id.network_address),
+                ("metrics", id.metrics_address),
+            ] {
+                let name = format!("{kind} address of authority {i}");
+                validate_address(address).wrap_err(format!("Bad {name}"))?;
+                if let Some(other) = addresses.insert(address, name.clone()) {
+                    bail!("The {name} ({address}) is already used as the {other}");
+                }
+            }
+        }
+        self.parameters
+            .validate(committee.len())
+            .wrap_err("Bad node parameters")
+    }
+}
+
+/// Other authorities connect to the addresses in the public config, so they have to be routable.
+fn validate_address(address: SocketAddr) -> eyre::Result<()> {
+    ensure!(address.port() != 0, "{address} has no port");
+    ensure!(
+        !address.ip().is_unspecified(),
+        "{address} is not a routable address, use the IP of the machine"
+    );
+    ensure!(
+        !address.ip().is_multicast(),
+        "{address} is a multicast address"
+    );
+    Ok(())
 }
 
 impl ImportExport for NodePublicConfig {}
@@ -206,6 +554,108 @@ pub struct NodePrivateConfig {
     authority: AuthorityIndex,
     pub keypair: Signer,
     pub storage_path: PathBuf,
+    #[serde(default)]
+    pub metrics_server: MetricsServerConfig,
+    /// Periodically push metrics to a Pushgateway, for nodes that can not be scraped.
+    #[serde(default)]
+    pub metrics_push: Option<MetricsPushConfig>,
+    /// Maximum disk space (in bytes) used by the storage directory. Once exceeded, the oldest
+    /// committed segments of the wal are pruned. Blocks in pruned segments can no longer be served
+    /// to other authorities, nor replayed by `dry-run`.
+    #[serde(default)]
+    pub disk_budget: Option<u64>,
+    #[serde(default)]
+    pub cpu_affinity: CpuAffinityConfig,
+    /// Accept transactions from clients (such as `mysticeti-client`) on this address.
+    #[serde(default)]
+    pub submission_address: Option<SocketAddr>,
+    /// Serve the gRPC consensus service on this address. Requires the `grpc` feature.
+    #[serde(default)]
+    pub grpc_address: Option<SocketAddr>,
+    /// Archive the committed transactions in a SQLite database in the storage directory.
+    /// Requires the `archive` feature.
+    #[serde(default)]
+    pub archive_transactions: bool,
+    /// Deviate from the protocol. Only meant for benchmarks with byzantine validators.
+    #[serde(default)]
+    pub byzantine_strategy: Option<ByzantineStrategy>,
+}
+
+/// CPU cores to pin the threads of the validator to. To keep the tokio worker threads off these
+/// cores, start the validator restricted to the other cores, e.g. with `taskset`: pinned threads
+/// are moved to their cores after they start. Threads with no cores are not pinned.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct CpuAffinityConfig {
+    /// Cores of the thread running the consensus core, which also writes the wal.
+    #[serde(default)]
+    pub core_thread: Vec<usize>,
+    /// Cores of the thread syncing the wal to disk.
+    #[serde(default)]
+    pub wal_syncer: Vec<usize>,
+}
+
+/// Settings of the HTTP server exposing the node's metrics and admin routes.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct MetricsServerConfig {
+    /// The address to bind the server to. Defaults to all interfaces on the port
+    /// of the node's public metrics address.
+    #[serde(default)]
+    pub bind_address: Option<SocketAddr>,
+    /// Require HTTP basic authentication on every route.
+    #[serde(default)]
+    pub basic_auth: Option<BasicAuthConfig>,
+    /// Serve HTTPS instead of plain HTTP.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BasicAuthConfig {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TlsConfig {
+    /// PEM file holding the certificate chain.
+    pub certificate_path: PathBuf,
+    /// PEM file holding the private key (PKCS#8, RSA or SEC1).
+    pub private_key_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MetricsPushConfig {
+    /// Base url of the Pushgateway, e.g. `http://pushgateway:9091`.
+    pub url: String,
+    /// The job under which the metrics of all nodes are grouped.
+    #[serde(default = "push_defaults::default_job")]
+    pub job: String,
+    /// The interval at which metrics are pushed.
+    #[serde(default = "push_defaults::default_interval")]
+    pub interval: Duration,
+    #[serde(default)]
+    pub basic_auth: Option<BasicAuthConfig>,
+}
+
+mod push_defaults {
+    use super::Duration;
+
+    pub fn default_job() -> String {
+        "mysticeti".to_string()
+    }
+
+    pub fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+}
+
+impl MetricsServerConfig {
+    /// The address to bind the metrics server to, given the public metrics address of the node.
+    pub fn bind_address(&self, metrics_address: SocketAddr) -> SocketAddr {
+        self.bind_address.unwrap_or_else(|| {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), metrics_address.port())
+        })
+    }
 }
 
 impl NodePrivateConfig {
@@ -214,6 +664,14 @@ impl NodePrivateConfig {
             authority: index,
             keypair: dummy_signer(),
             storage_path: PathBuf::from("storage"),
+            metrics_server: MetricsServerConfig::default(),
+            metrics_push: None,
+            disk_budget: None,
+            cpu_affinity: CpuAffinityConfig::default(),
+            submission_address: None,
+            grpc_address: None,
+            archive_transactions: false,
+            byzantine_strategy: None,
         }
     }
 
@@ -228,6 +686,14 @@ impl NodePrivateConfig {
                     authority,
                     keypair,
                     storage_path: path,
+                    metrics_server: MetricsServerConfig::default(),
+                    metrics_push: None,
+                    disk_budget: None,
+                    cpu_affinity: CpuAffinityConfig::default(),
+                    submission_address: None,
+                    grpc_address: None,
+                    archive_transactions: false,
+                    byzantine_strategy: None,
                 }
             })
             .collect()
@@ -249,9 +715,52 @@ impl NodePrivateConfig {
         self.storage_path.join("committed.txt")
     }
 
+    pub fn transaction_archive(&self) -> PathBuf {
+        self.storage_path.join("transactions.sqlite")
+    }
+
     pub fn wal(&self) -> PathBuf {
         self.storage_path.join("wal")
     }
+
+    pub fn authority(&self) -> AuthorityIndex {
+        self.authority
+    }
+
+    /// Check that the config belongs to an authority of the public config and that its storage
+    /// directory exists.
+    pub fn validate(&self, public_config: &NodePublicConfig) -> eyre::Result<()> {
+        let authority = self.authority;
+        let Some(id) = public_config.identifiers.get(authority as usize) else {
+            bail!(
+                "Authority {authority} is not in the public config, which lists {} authorities",
+                public_config.identifiers.len()
+            );
+        };
+        ensure!(
+            self.keypair.public_key() == id.public_key,
+            "The keypair does not match the public key of authority {authority}"
+        );
+        ensure!(
+            self.storage_path.is_dir(),
+            "The storage directory '{}' does not exist",
+            self.storage_path.display()
+        );
+        if let Some(tls) = &self.metrics_server.tls {
+            for path in [&tls.certificate_path, &tls.private_key_path] {
+                ensure!(
+                    path.is_file(),
+                    "The metrics server TLS file '{}' does not exist",
+                    path.display()
+                );
+            }
+        }
+        ensure!(
+            self.disk_budget != Some(0),
+            "disk_budget must be greater than zero"
+        );
+        Ok(())
+    }
 }
 
 impl ImportExport for NodePrivateConfig {}
@@ -267,6 +776,123 @@ pub struct ClientParameters {
     /// The initial delay before starting to send transactions.
     #[serde(default = "client_defaults::default_initial_delay")]
     pub initial_delay: Duration,
+    /// The interval at which transactions are submitted to the block handler.
+    #[serde(default = "client_defaults::default_block_interval")]
+    pub block_interval: Duration,
+    /// Whether to submit at a fixed rate or keep a fixed number of transactions in flight.
+    #[serde(default)]
+    pub mode: LoadMode,
+    /// How the open-loop load varies over time, starting after the initial delay.
+    #[serde(default)]
+    pub shape: LoadShape,
+}
+
+/// How clients decide when to submit transactions.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadMode {
+    /// Submit transactions at the fixed rate of `load`, whether or not earlier ones completed.
+    #[default]
+    OpenLoop,
+    /// Keep this many transactions in flight: a new transaction is submitted whenever one is
+    /// committed, regardless of `load`. Only `mysticeti-client` supports this mode, as it learns
+    /// when its transactions are committed.
+    ClosedLoop(usize),
+}
+
+impl fmt::Display for LoadMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OpenLoop => write!(f, "open-loop"),
+            Self::ClosedLoop(outstanding) => write!(f, "closed-loop-{outstanding}"),
+        }
+    }
+}
+
+/// How the rate of open-loop clients varies over time. Factors multiply the configured `load`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadShape {
+    /// Submit transactions at the constant rate of `load`.
+    #[default]
+    Constant,
+    /// Switch to `factor` times the load after `at`.
+    Step { at: Duration, factor: f64 },
+    /// Grow linearly from `from` times the load to the full load over `duration`.
+    Ramp { from: f64, duration: Duration },
+    /// Oscillate around the load by `amplitude` times the load, with the given `period`.
+    Sine { amplitude: f64, period: Duration },
+    /// Submit `factor` times the load during the first `duration` of every `period`.
+    Burst {
+        factor: f64,
+        duration: Duration,
+        period: Duration,
+    },
+}
+
+impl LoadShape {
+    /// The load (in transactions per second) to generate `elapsed` after the load started.
+    pub fn load(&self, load: usize, elapsed: Duration) -> usize {
+        let factor = match *self {
+            Self::Constant => 1.0,
+            Self::Step { at, factor } => {
+                if elapsed >= at {
+                    factor
+                } else {
+                    1.0
+                }
+            }
+            Self::Ramp { from, duration } => {
+                let progress = if duration.is_zero() {
+                    1.0
+                } else {
+                    (elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.0)
+                };
+                from + (1.0 - from) * progress
+            }
+            Self::Sine { amplitude, period } => {
+                if period.is_zero() {
+                    1.0
+                } else {
+                    let phase = elapsed.as_secs_f64() / period.as_secs_f64();
+                    1.0 + amplitude * (2.0 * std::f64::consts::PI * phase).sin()
+                }
+            }
+            Self::Burst {
+                factor,
+                duration,
+                period,
+            } => {
+                let offset = if period.is_zero() {
+                    elapsed
+                } else {
+                    Duration::from_nanos((elapsed.as_nanos() % period.as_nanos()) as u64)
+                };
+                if offset < duration {
+                    factor
+                } else {
+                    1.0
+                }
+            }
+        };
+        (load as f64 * factor.max(0.0)).round() as usize
+    }
+}
+
+impl fmt::Display for LoadShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Constant => write!(f, "constant"),
+            Self::Step { at, factor } => write!(f, "step-{factor}x-at-{at:?}"),
+            Self::Ramp { from, duration } => write!(f, "ramp-from-{from}x-over-{duration:?}"),
+            Self::Sine { amplitude, period } => write!(f, "sine-{amplitude}x-every-{period:?}"),
+            Self::Burst {
+                factor,
+                duration,
+                period,
+            } => write!(f, "burst-{factor}x-for-{duration:?}-every-{period:?}"),
+        }
+    }
 }
 
 mod client_defaults {
@@ -283,6 +909,10 @@ mod client_defaults {
     pub fn default_initial_delay() -> Duration {
         Duration::from_secs(30)
     }
+
+    pub fn default_block_interval() -> Duration {
+        Duration::from_millis(100)
+    }
 }
 
 impl Default for ClientParameters {
@@ -291,8 +921,227 @@ impl Default for ClientParameters {
             load: client_defaults::default_load(),
             transaction_size: client_defaults::default_transaction_size(),
             initial_delay: client_defaults::default_initial_delay(),
+            block_interval: client_defaults::default_block_interval(),
+            mode: LoadMode::default(),
+            shape: LoadShape::default(),
         }
     }
 }
 
 impl ImportExport for ClientParameters {}
+
+/// The subset of the configuration that can be changed while the validator is running, either
+/// through SIGHUP or the admin endpoint. Fields that are not set keep their current value.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ReloadableConfig {
+    /// Log filter directives, in the same format as `RUST_LOG`.
+    #[serde(default)]
+    pub log_filter: Option<String>,
+    #[serde(default)]
+    pub leader_timeout: Option<Duration>,
+    /// The number of transactions to send to the network per second.
+    #[serde(default)]
+    pub load: Option<usize>,
+    #[serde(default)]
+    pub block_interval: Option<Duration>,
+}
+
+impl ImportExport for ReloadableConfig {}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tempdir::TempDir;
+
+    use super::{
+        BlockSyncParameters,
+        ClientParameters,
+        ConfigFormat,
+        ImportExport,
+        LoadMode,
+        LoadShape,
+        NodeParameters,
+        NodePrivateConfig,
+        NodePublicConfig,
+        WalSyncPolicy,
+    };
+    use crate::committee::Committee;
+
+    #[test]
+    fn load_toml_and_yaml() {
+        let dir = TempDir::new("config").unwrap();
+        let parameters = NodeParameters {
+            rounds_in_epoch: 1000,
+            wal_sync: WalSyncPolicy::Periodic(Duration::from_millis(250)),
+            ..Default::default()
+        };
+        for file in ["parameters.yaml", "parameters.toml"] {
+            let path = dir.path().join(file);
+            parameters.print(&path).unwrap();
+            let loaded = NodeParameters::load(&path).unwrap();
+            assert_eq!(loaded.rounds_in_epoch, 1000);
+            assert_eq!(loaded.leader_timeout, parameters.leader_timeout);
+            assert_eq!(loaded.wal_sync, parameters.wal_sync);
+        }
+        assert_eq!(
+            ConfigFormat::from_path(&dir.path().join("parameters.toml")),
+            ConfigFormat::Toml
+        );
+
+        let path = dir.path().join("partial.toml");
+        std::fs::write(&path, "wave_length = 4\nwal_sync = \"on_commit\"\n").unwrap();
+        let loaded = NodeParameters::load(&path).unwrap();
+        assert_eq!(loaded.wave_length, 4);
+        assert_eq!(loaded.wal_sync, WalSyncPolicy::OnCommit);
+        assert_eq!(
+            loaded.max_block_size,
+            NodeParameters::default().max_block_size
+        );
+    }
+
+    #[test]
+    fn validate_public_config() {
+        let committee = Committee::new_for_benchmarks(4);
+        NodePublicConfig::new_for_tests(4)
+            .validate(&committee)
+            .unwrap();
+
+        let error = NodePublicConfig::new_for_tests(3)
+            .validate(&committee)
+            .unwrap_err();
+        assert!(error.to_string().contains("committee has 4"), "{error}");
+
+        let mut duplicate = NodePublicConfig::new_for_tests(4);
+        duplicate.identifiers[1].network_address = duplicate.identifiers[0].network_address;
+        let error = duplicate.validate(&committee).unwrap_err();
+        assert!(error.to_string().contains("already used"), "{error}");
+
+        let mut unspecified = NodePublicConfig::new_for_tests(4);
+        unspecified.identifiers[2].metrics_address = "0.0.0.0:9000".parse::<SocketAddr>().unwrap();
+        assert!(unspecified.validate(&committee).is_err());
+
+        let mut parameters = NodePublicConfig::new_for_tests(4);
+        parameters.parameters.number_of_leaders = 5;
+        assert!(parameters.validate(&committee).is_err());
+
+        let mut parameters = NodePublicConfig::new_for_tests(4);
+        parameters.parameters.block_sync.jitter = 1.0;
+        assert!(parameters.validate(&committee).is_err());
+    }
+
+    #[test]
+    fn load_client_parameters() {
+        let dir = TempDir::new("config").unwrap();
+        let path = dir.path().join("client.yaml");
+        std::fs::write(&path, "load: 100\n").unwrap();
+        let loaded = ClientParameters::load(&path).unwrap();
+        assert_eq!(loaded.load, 100);
+        assert_eq!(loaded.mode, LoadMode::OpenLoop);
+
+        let parameters = ClientParameters {
+            mode: LoadMode::ClosedLoop(50),
+            ..Default::default()
+        };
+        for file in ["client.yaml", "client.toml"] {
+            let path = dir.path().join(file);
+            parameters.print(&path).unwrap();
+            let loaded = ClientParameters::load(&path).unwrap();
+            assert_eq!(loaded.mode, LoadMode::ClosedLoop(50));
+        }
+    }
+
+    #[test]
+    fn load_shapes() {
+        let second = Duration::from_secs(1);
+        assert_eq!(LoadShape::Constant.load(100, 10 * second), 100);
+
+        let step = LoadShape::Step {
+            at: 10 * second,
+            factor: 2.0,
+        };
+        assert_eq!(step.load(100, 9 * second), 100);
+        assert_eq!(step.load(100, 10 * second), 200);
+
+        let ramp = LoadShape::Ramp {
+            from: 0.0,
+            duration: 10 * second,
+        };
+        assert_eq!(ramp.load(100, Duration::ZERO), 0);
+        assert_eq!(ramp.load(100, 5 * second), 50);
+        assert_eq!(ramp.load(100, 20 * second), 100);
+
+        let sine = LoadShape::Sine {
+            amplitude: 0.5,
+            period: 4 * second,
+        };
+        assert_eq!(sine.load(100, Duration::ZERO), 100);
+        assert_eq!(sine.load(100, second), 150);
+        assert_eq!(sine.load(100, 3 * second), 50);
+
+        let burst = LoadShape::Burst {
+            factor: 3.0,
+            duration: second,
+            period: 10 * second,
+        };
+        assert_eq!(burst.load(100, Duration::ZERO), 300);
+        assert_eq!(burst.load(100, 5 * second), 100);
+        assert_eq!(burst.load(100, 20 * second), 300);
+
+        let dir = TempDir::new("config").unwrap();
+        let parameters = ClientParameters {
+            shape: burst,
+            ..Default::default()
+        };
+        for file in ["client.yaml", "client.toml"] {
+            let path = dir.path().join(file);
+            parameters.print(&path).unwrap();
+            let loaded = ClientParameters::load(&path).unwrap();
+            assert_eq!(loaded.shape, burst);
+        }
+    }
+
+    #[test]
+    fn block_sync_backoff() {
+        let parameters = BlockSyncParameters {
+            request_timeout: Duration::from_secs(2),
+            max_request_timeout: Duration::from_secs(10),
+            jitter: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(parameters.request_timeout(0, 0.0), Duration::from_secs(2));
+        assert_eq!(parameters.request_timeout(1, 0.0), Duration::from_secs(4));
+        assert_eq!(parameters.request_timeout(2, 0.0), Duration::from_secs(8));
+        assert_eq!(parameters.request_timeout(3, 0.0), Duration::from_secs(10));
+        assert_eq!(
+            parameters.request_timeout(100, 0.0),
+            Duration::from_secs(10)
+        );
+        // The jitter shortens the timeout by at most half.
+        assert_eq!(parameters.request_timeout(1, 0.5), Duration::from_secs(3));
+        assert!(parameters.request_timeout(3, 0.999) > Duration::from_secs(5));
+    }
+
+    #[test]
+    fn validate_private_config() {
+        let dir = TempDir::new("config").unwrap();
+        let public_config = NodePublicConfig::new_for_tests(4);
+        let mut configs = NodePrivateConfig::new_for_benchmarks(dir.path(), 4);
+
+        let error = configs[0].validate(&public_config).unwrap_err();
+        assert!(error.to_string().contains("does not exist"), "{error}");
+
+        std::fs::create_dir_all(&configs[0].storage_path).unwrap();
+        configs[0].validate(&public_config).unwrap();
+
+        configs[0].authority = 7;
+        assert!(configs[0].validate(&public_config).is_err());
+    }
+
+    #[test]
+    fn validate_committee() {
+        Committee::new_for_benchmarks(4).validate().unwrap();
+        // Test committees share a dummy public key.
+        assert!(Committee::new_test(vec![1, 1]).validate().is_err());
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compares the commit checkpoints of the validators (served on their checkpoint route) while
+//! they run, to detect as soon as possible that two validators committed different sequences.
+
+use std::{fmt, net::SocketAddr, time::Duration};
+
+use crate::{
+    prometheus::{CheckpointResponse, CHECKPOINT_ROUTE},
+    runtime::{self, spawn_named, JoinHandle},
+    types::AuthorityIndex,
+};
+
+/// Two validators with different checkpoints at the same height.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub height: u64,
+    pub first: (AuthorityIndex, String),
+    pub second: (AuthorityIndex, String),
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "validators {} and {} disagree at commit {}: {} != {}",
+            self.first.0, self.second.0, self.height, self.first.1, self.second.1
+        )
+    }
+}
+
+/// Return the first pair of validators with different checkpoints at the same height.
+pub fn find_divergence(checkpoints: &[(AuthorityIndex, CheckpointResponse)]) -> Option<Divergence> {
+    for (i, (first, a)) in checkpoints.iter().enumerate() {
+        for (second, b) in &checkpoints[i + 1..] {
+            if a.height == b.height && a.digest != b.digest {
+                return Some(Divergence {
+                    height: a.height,
+                    first: (*first, a.digest.clone()),
+                    second: (*second, b.digest.clone()),
+                });
+            }
+        }
+    }
+    None
+}
+
+pub struct ConsistencyChecker {
+    client: reqwest::Client,
+    /// The metrics addresses of the validators, indexed by authority.
+    addresses: Vec<SocketAddr>,
+}
+
+impl ConsistencyChecker {
+    pub fn new(addresses: Vec<SocketAddr>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            addresses,
+        }
+    }
+
+    /// Compare the checkpoints of the reachable validators at the highest height they all
+    /// reached. Returns the height compared, if any validator committed.
+    pub async fn check(&self) -> Result<Option<u64>, Divergence> {
+        let mut latest = Vec::new();
+        for (authority, address) in self.addresses.iter().enumerate() {
+            if let Some(checkpoint) = self.fetch(address, None).await {
+                latest.push((authority as AuthorityIndex, checkpoint));
+            }
+        }
+        let Some(height) = latest.iter().map(|(_, checkpoint)| checkpoint.height).min() else {
+            return Ok(None);
+        };
+
+        let mut checkpoints = Vec::new();
+        for (authority, checkpoint) in latest {
+            // Validators may have moved on, or no longer keep a checkpoint that old.
+            let checkpoint = if checkpoint.height == height {
+                Some(checkpoint)
+            } else {
+                self.fetch(&self.addresses[authority as usize], Some(height))
+                    .await
+            };
+            checkpoints.extend(checkpoint.map(|checkpoint| (authority, checkpoint)));
+        }
+        match find_divergence(&checkpoints) {
+            Some(divergence) => Err(divergence),
+            None => Ok(Some(height)),
+        }
+    }
+
+    /// Check the validators every `interval`, and report divergences as errors.
+    pub fn start(self, interval: Duration) -> JoinHandle<()> {
+        spawn_named("consistency-checker", async move {
+            loop {
+                runtime::sleep(interval).await;
+                match self.check().await {
+                    Ok(Some(height)) => tracing::debug!("Validators agree at commit {height}"),
+                    Ok(None) => (),
+                    Err(divergence) => tracing::error!("Safety violation: {divergence}"),
+                }
+            }
+        })
+    }
+
+    async fn fetch(&self, address: &SocketAddr, height: Option<u64>) -> Option<CheckpointResponse> {
+        let mut request = self
+            .client
+            .get(format!("http://{address}{CHECKPOINT_ROUTE}"));
+        if let Some(height) = height {
+            request = request.query(&[("height", height)]);
+        }
+        let response = request.send().await.ok()?.error_for_status().ok()?;
+        response.json().await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(height: u64, digest: &str) -> CheckpointResponse {
+        CheckpointResponse {
+            height,
+            digest: digest.to_string(),
+        }
+    }
+
+    #[test]
+    fn divergence_at_same_height() {
+        let agreeing = [(0, checkpoint(5, "aa")), (1, checkpoint(5, "aa"))];
+        assert_eq!(find_divergence(&agreeing), None);
+
+        // Checkpoints at different heights are not comparable.
+        let lagging = [(0, checkpoint(5, "aa")), (1, checkpoint(6, "bb"))];
+        assert_eq!(find_divergence(&lagging), None);
+
+        let diverging = [
+            (0, checkpoint(5, "aa")),
+            (1, checkpoint(6, "bb")),
+            (2, checkpoint(5, "cc")),
+        ];
+        assert_eq!(
+            find_divergence(&diverging),
+            Some(Divergence {
+                height: 5,
+                first: (0, "aa".to_string()),
+                second: (2, "cc".to_string()),
+            })
+        );
+    }
+}
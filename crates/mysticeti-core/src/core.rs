@@ -1,10 +1,14 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
 use std::{
     collections::{HashSet, VecDeque},
-    mem,
+    fs, io, mem,
+    path::{Path, PathBuf},
     sync::{atomic::AtomicU64, Arc},
+    time::Duration,
 };
 
 use minibytes::Bytes;
@@ -21,11 +25,18 @@ use crate::{
         WAL_ENTRY_PAYLOAD,
         WAL_ENTRY_STATE,
     },
-    committee::Committee,
-    config::{NodePrivateConfig, NodePublicConfig},
+    committee::{Committee, QuorumThreshold, StakeAggregator},
+    config::{
+        ByzantineStrategy,
+        CpuAffinityConfig,
+        NodePrivateConfig,
+        NodePublicConfig,
+        WalSyncPolicy,
+    },
     consensus::{
         linearizer::CommittedSubDag,
         universal_committer::{UniversalCommitter, UniversalCommitterBuilder},
+        LeaderStatus,
     },
     crypto::Signer,
     data::Data,
@@ -33,7 +44,7 @@ use crate::{
     metrics::{Metrics, UtilizationTimerVecExt},
     runtime::timestamp_utc,
     state::RecoveredState,
-    threshold_clock::ThresholdClockAggregator,
+    threshold_clock::{ThresholdClockAggregator, ThresholdClockStatus},
     types::{AuthorityIndex, BaseStatement, BlockReference, RoundNumber, StatementBlock},
     wal::{WalPosition, WalSyncer, WalWriter},
 };
@@ -47,6 +58,11 @@ pub struct Core<H: BlockHandler> {
     threshold_clock: ThresholdClockAggregator,
     pub(crate) committee: Arc<Committee>,
     last_commit_leader: BlockReference,
+    /// Time at which the threshold clock last advanced to a new round.
+    last_clock_advance: Duration,
+    threshold_clock_stall_alarm: Duration,
+    /// Whether the current stall of the threshold clock was already logged.
+    stall_alarm_raised: bool,
     wal_writer: WalWriter,
     block_store: BlockStore,
     pub(crate) metrics: Arc<Metrics>,
@@ -57,6 +73,17 @@ pub struct Core<H: BlockHandler> {
     epoch_manager: EpochManager,
     rounds_in_epoch: RoundNumber,
     committer: UniversalCommitter,
+    storage_path: PathBuf,
+    disk_budget: Option<u64>,
+    /// Position of the last state written to the wal, recovery needs the wal from there on.
+    last_state_position: WalPosition,
+    wal_sync: WalSyncPolicy,
+    /// Create blocks whose header can be disseminated ahead of them, see
+    /// `NodeParameters::header_first`.
+    header_first: bool,
+    cpu_affinity: CpuAffinityConfig,
+    /// The deviation from the protocol of a byzantine validator (if any).
+    byzantine_strategy: Option<ByzantineStrategy>,
 }
 
 pub struct CoreOptions {
@@ -88,10 +115,14 @@ impl<H: BlockHandler> Core<H> {
             mut pending,
             state,
             unprocessed_blocks,
+            awaiting_payload,
+            incomplete_blocks,
             last_committed_leader,
             committed_blocks,
             committed_state,
         } = recovered;
+        let wal_sync = public_config.parameters.wal_sync;
+        wal_writer.set_sync_every_write(wal_sync == WalSyncPolicy::EveryWrite);
         let mut threshold_clock = ThresholdClockAggregator::new(0);
         let last_own_block = if let Some(own_block) = last_own_block {
             for (_, pending_block) in pending.iter() {
@@ -122,7 +153,9 @@ impl<H: BlockHandler> Core<H> {
             block_writer.insert_own_block(&own_block_data);
             own_block_data
         };
-        let block_manager = BlockManager::new(block_store.clone(), &committee);
+        let block_manager = BlockManager::new(block_store.clone(), &committee)
+            .with_awaiting_payload(awaiting_payload)
+            .with_incomplete_blocks(incomplete_blocks);
 
         if let Some(state) = state {
             block_handler.recover_state(&state);
@@ -143,6 +176,10 @@ impl<H: BlockHandler> Core<H> {
             "Number of leaders: {}",
             public_config.parameters.number_of_leaders
         );
+        tracing::info!("Wal sync policy: {wal_sync}");
+        if let Some(strategy) = private_config.byzantine_strategy {
+            tracing::warn!("Byzantine strategy: {strategy}");
+        }
 
         let mut this = Self {
             block_manager,
@@ -153,6 +190,9 @@ impl<H: BlockHandler> Core<H> {
             threshold_clock,
             committee,
             last_commit_leader: last_committed_leader.unwrap_or_default(),
+            last_clock_advance: timestamp_utc(),
+            threshold_clock_stall_alarm: public_config.parameters.threshold_clock_stall_alarm,
+            stall_alarm_raised: false,
             wal_writer,
             block_store,
             metrics,
@@ -162,7 +202,15 @@ impl<H: BlockHandler> Core<H> {
             epoch_manager,
             rounds_in_epoch: public_config.parameters.rounds_in_epoch,
             committer,
+            storage_path: private_config.storage_path,
+            disk_budget: private_config.disk_budget,
+            cpu_affinity: private_config.cpu_affinity,
+            byzantine_strategy: private_config.byzantine_strategy,
+            last_state_position: WalPosition::default(),
+            wal_sync,
+            header_first: public_config.parameters.header_first,
         };
+        this.report_round_gap();
 
         if !unprocessed_blocks.is_empty() {
             tracing::info!(
@@ -186,18 +234,42 @@ impl<H: BlockHandler> Core<H> {
             .metrics
             .utilization_timer
             .utilization_timer("Core::add_blocks");
-        let processed = self
+        let clock_round = self.threshold_clock.get_round();
+        let added = self
             .block_manager
             .add_blocks(blocks, &mut (&mut self.wal_writer, &self.block_store));
-        let mut result = Vec::with_capacity(processed.len());
-        for (position, processed) in processed.into_iter() {
-            self.threshold_clock
-                .add_block(*processed.reference(), &self.committee);
-            self.pending
-                .push_back((position, MetaStatement::Include(*processed.reference())));
+        self.metrics
+            .suspended_blocks
+            .set(self.block_manager.suspended_blocks() as i64);
+        self.metrics
+            .blocks_awaiting_payload
+            .set(self.block_manager.awaiting_payload().len() as i64);
+        let mut result = Vec::with_capacity(added.processed.len());
+        for (_, processed) in added.processed.into_iter() {
+            self.metrics
+                .blocks_received_total
+                .with_label_values(&[&processed.author().to_string()])
+                .inc();
             result.push(processed);
         }
-        self.run_block_handler(&result);
+        // Blocks are only included (and advance the threshold clock) once the payloads of their
+        // causal history are held, so that peers can fetch them from us.
+        for (position, reference) in added.includable {
+            self.threshold_clock.add_block(reference, &self.committee);
+            self.pending
+                .push_back((position, MetaStatement::Include(reference)));
+        }
+        if self.threshold_clock.get_round() > clock_round {
+            self.threshold_clock_advanced();
+        }
+        // The block handler processes the statements of the blocks, which headers do not carry.
+        let mut handled: Vec<_> = result
+            .iter()
+            .filter(|block| !block.is_header_only())
+            .cloned()
+            .collect();
+        handled.extend(added.payloads);
+        self.run_block_handler(&handled);
         result
     }
 
@@ -209,11 +281,9 @@ impl<H: BlockHandler> Core<H> {
         let statements = self
             .block_handler
             .handle_blocks(processed, !self.epoch_changing());
-        let serialized_statements =
-            bincode::serialize(&statements).expect("Payload serialization failed");
         let position = self
             .wal_writer
-            .write(WAL_ENTRY_PAYLOAD, &serialized_statements)
+            .write_serialized(WAL_ENTRY_PAYLOAD, &statements)
             .expect("Failed to write statements to wal");
         self.pending
             .push_back((position, MetaStatement::Payload(statements)));
@@ -228,6 +298,12 @@ impl<H: BlockHandler> Core<H> {
         if clock_round <= self.last_proposed() {
             return None;
         }
+        let span = tracing::debug_span!(
+            "create_block",
+            round = clock_round,
+            block = tracing::field::Empty
+        )
+        .entered();
 
         let mut includes = vec![];
         let mut statements = vec![];
@@ -272,6 +348,10 @@ impl<H: BlockHandler> Core<H> {
             }
         }
 
+        if self.byzantine_strategy == Some(ByzantineStrategy::WithholdVotes) {
+            self.withhold_votes(clock_round, &mut includes);
+        }
+
         assert!(!includes.is_empty());
         let time_ns = timestamp_utc().as_nanos();
         let block = StatementBlock::new_with_signer(
@@ -282,6 +362,7 @@ impl<H: BlockHandler> Core<H> {
             time_ns,
             self.epoch_changing(),
             &self.signer,
+            self.header_first,
         );
         assert_eq!(
             block.includes().get(0).unwrap().authority,
@@ -291,6 +372,7 @@ impl<H: BlockHandler> Core<H> {
         );
 
         let block = Data::new(block);
+        span.record("block", tracing::field::display(block.reference()));
         if block.serialized_bytes().len() > crate::wal::MAX_ENTRY_SIZE / 2 {
             // Sanity check for now
             panic!(
@@ -302,6 +384,9 @@ impl<H: BlockHandler> Core<H> {
         }
         self.threshold_clock
             .add_block(*block.reference(), &self.committee);
+        if self.threshold_clock.get_round() > clock_round {
+            self.threshold_clock_advanced();
+        }
         self.block_handler.handle_proposal(&block);
         self.proposed_block_stats(&block);
         let next_entry = if let Some((pos, _)) = self.pending.get(0) {
@@ -314,6 +399,9 @@ impl<H: BlockHandler> Core<H> {
             block: block.clone(),
         };
         (&mut self.wal_writer, &self.block_store).insert_own_block(&self.last_own_block);
+        if self.byzantine_strategy == Some(ByzantineStrategy::Equivocate) {
+            self.equivocate(&block);
+        }
 
         if self.options.fsync {
             self.wal_writer.sync().expect("Wal sync failed");
@@ -323,6 +411,50 @@ impl<H: BlockHandler> Core<H> {
         Some(block)
     }
 
+    /// Remove the blocks of the leaders of the previous round from the includes of a new block
+    /// (i.e., do not vote for them), as long as the block keeps a quorum of the previous round.
+    fn withhold_votes(&self, round: RoundNumber, includes: &mut Vec<BlockReference>) {
+        let previous = round - 1;
+        let leaders = self.committer.get_leaders(previous);
+        let is_vote = |include: &BlockReference| {
+            include.round == previous
+                && include.authority != self.authority
+                && leaders.contains(&include.authority)
+        };
+
+        let mut aggregator = StakeAggregator::<QuorumThreshold>::new();
+        let mut is_quorum = false;
+        for include in includes.iter() {
+            if include.round == previous && !is_vote(include) {
+                is_quorum = aggregator.add(include.authority, &self.committee);
+            }
+        }
+        if is_quorum {
+            includes.retain(|include| !is_vote(include));
+        }
+    }
+
+    /// Create a block conflicting with the new own block: of the same round and with the same
+    /// includes, but without statements. Peers streaming the own blocks receive either block.
+    fn equivocate(&mut self, block: &StatementBlock) {
+        let equivocation = StatementBlock::new_with_signer(
+            self.authority,
+            block.round(),
+            block.includes().clone(),
+            vec![],
+            block.meta_creation_time_ns() + 1,
+            block.epoch_changed(),
+            &self.signer,
+            self.header_first,
+        );
+        tracing::debug!("Equivocating with block {equivocation:?}");
+        (&mut self.wal_writer, &self.block_store).insert_block(Data::new(equivocation));
+    }
+
+    pub fn cpu_affinity(&self) -> &CpuAffinityConfig {
+        &self.cpu_affinity
+    }
+
     pub fn wal_syncer(&self) -> WalSyncer {
         self.wal_writer
             .syncer()
@@ -330,6 +462,10 @@ impl<H: BlockHandler> Core<H> {
     }
 
     fn proposed_block_stats(&self, block: &Data<StatementBlock>) {
+        self.metrics
+            .blocks_proposed_total
+            .with_label_values(&[&self.authority.to_string()])
+            .inc();
         self.metrics
             .proposed_block_size_bytes
             .observe(block.serialized_bytes().len());
@@ -349,15 +485,43 @@ impl<H: BlockHandler> Core<H> {
     }
 
     pub fn try_commit(&mut self) -> Vec<Data<StatementBlock>> {
-        let sequence: Vec<_> = self
-            .committer
-            .try_commit(self.last_commit_leader)
+        let _span = tracing::debug_span!("commit_decision", last_leader = %self.last_commit_leader)
+            .entered();
+        let mut decided = Vec::new();
+        let mut stalled = false;
+        for leader in self.committer.try_commit(self.last_commit_leader) {
+            if let LeaderStatus::Commit(block) = &leader {
+                if !self.payloads_received(block) {
+                    stalled = true;
+                    break;
+                }
+            }
+            decided.push(leader);
+        }
+        // The leaders after the last committed one are decided again by the next calls (until
+        // the payloads are received), so they are only reported once committed past.
+        if stalled {
+            let committed = decided
+                .iter()
+                .rposition(|leader| matches!(leader, LeaderStatus::Commit(_)))
+                .map_or(0, |i| i + 1);
+            decided.truncate(committed);
+        }
+        let sequence: Vec<_> = decided
             .into_iter()
+            .inspect(|leader| {
+                tracing::debug!(%leader, "Decided leader");
+                self.metrics
+                    .rounds_led_total
+                    .with_label_values(&[&leader.authority().to_string()])
+                    .inc();
+            })
             .filter_map(|leader| leader.into_decided_block())
             .collect();
 
         if let Some(last) = sequence.last() {
             self.last_commit_leader = *last.reference();
+            self.report_round_gap();
         }
 
         // todo: should ideally come from execution result of epoch smart contract
@@ -368,16 +532,139 @@ impl<H: BlockHandler> Core<H> {
         sequence
     }
 
-    pub fn cleanup(&self) {
+    /// Whether the payloads of all the blocks in the causal history of the leader were received,
+    /// so that its sub-dag can be committed. Otherwise the leader is committed once they arrive.
+    fn payloads_received(&self, leader: &Data<StatementBlock>) -> bool {
+        !self
+            .block_manager
+            .incomplete_blocks()
+            .contains(leader.reference())
+    }
+
+    fn threshold_clock_advanced(&mut self) {
+        self.last_clock_advance = timestamp_utc();
+        self.stall_alarm_raised = false;
+        self.metrics.threshold_clock_stall_ms.set(0);
+        self.report_round_gap();
+        self.report_threshold_clock_contributors();
+    }
+
+    fn report_round_gap(&self) {
+        let clock_round = self.threshold_clock.get_round();
+        let committed_round = self.last_commit_leader.round();
+        self.metrics.threshold_clock_round.set(clock_round as i64);
+        self.metrics
+            .last_committed_round
+            .set(committed_round as i64);
+        self.metrics
+            .uncommitted_rounds
+            .set(clock_round.saturating_sub(committed_round) as i64);
+    }
+
+    /// Update the gauge tracking for how long the threshold clock is stuck in the current round.
+    /// The gauge is reset whenever the clock advances, so this only needs to be called periodically.
+    /// Logs the authorities holding the clock back once the stall exceeds the alarm.
+    pub fn report_threshold_clock_stall(&mut self) {
+        let status = self.threshold_clock_status();
+        self.metrics
+            .threshold_clock_stall_ms
+            .set(status.stalled_for.as_millis() as i64);
+        self.report_threshold_clock_contributors();
+        if status.stalled_for >= self.threshold_clock_stall_alarm && !self.stall_alarm_raised {
+            self.stall_alarm_raised = true;
+            self.metrics.threshold_clock_stall_alarms_total.inc();
+            tracing::warn!(
+                "Threshold clock stuck in round {} for {}ms, missing blocks from authorities {:?}",
+                status.round,
+                status.stalled_for.as_millis(),
+                status.missing
+            );
+        }
+    }
+
+    fn report_threshold_clock_contributors(&self) {
+        let contributors: Vec<_> = self.threshold_clock.contributors().collect();
+        for authority in self.committee.authorities() {
+            self.metrics
+                .threshold_clock_contributors
+                .with_label_values(&[&authority.to_string()])
+                .set(contributors.contains(&authority) as i64);
+        }
+    }
+
+    pub fn threshold_clock_status(&self) -> ThresholdClockStatus {
+        ThresholdClockStatus {
+            round: self.threshold_clock.get_round(),
+            contributors: self.threshold_clock.contributors().collect(),
+            missing: self.threshold_clock.missing(&self.committee),
+            stalled_for: timestamp_utc().saturating_sub(self.last_clock_advance),
+        }
+    }
+
+    pub fn cleanup(&mut self) {
         const RETAIN_BELOW_COMMIT_ROUNDS: RoundNumber = 100;
 
-        self.block_store.cleanup(
-            self.last_commit_leader
-                .round()
-                .saturating_sub(RETAIN_BELOW_COMMIT_ROUNDS),
-        );
+        let threshold_round = self
+            .last_commit_leader
+            .round()
+            .saturating_sub(RETAIN_BELOW_COMMIT_ROUNDS);
+        self.block_store.cleanup(threshold_round);
 
         self.block_handler.cleanup();
+
+        self.enforce_disk_budget(threshold_round);
+
+        self.metrics
+            .wal_file_size_bytes
+            .set(self.wal_writer.file_size() as i64);
+        self.metrics
+            .wal_segments
+            .set(self.wal_writer.segments() as i64);
+    }
+
+    /// Prune the oldest segments of the wal once the storage directory exceeds its disk budget.
+    /// Recovery only needs the wal from the last state and the pending statements on, and blocks
+    /// below `threshold_round` are committed and unloaded, so the segments before all of them go.
+    fn enforce_disk_budget(&mut self, threshold_round: RoundNumber) {
+        let Some(disk_budget) = self.disk_budget else {
+            return;
+        };
+        let mut usage = match disk_usage(&self.storage_path) {
+            Ok(usage) => usage,
+            Err(err) => {
+                tracing::warn!("Failed to measure the disk usage of the storage: {err}");
+                return;
+            }
+        };
+        if usage > disk_budget {
+            let mut prune_to = self.last_state_position;
+            if let Some(position) = self.block_store.first_position_from_round(threshold_round) {
+                prune_to = prune_to.min(position);
+            }
+            if let Some((position, _)) = self.pending.front() {
+                prune_to = prune_to.min(*position);
+            }
+            // Forget the blocks first, so they are not read from the wal while it is pruned
+            self.block_store.prune(prune_to);
+            match self.wal_writer.prune(prune_to) {
+                Ok(0) => {}
+                Ok(released) => {
+                    tracing::info!("Pruned {released} bytes of the wal below {prune_to}");
+                    self.metrics.wal_pruned_bytes.inc_by(released);
+                    usage =
+                        disk_usage(&self.storage_path).unwrap_or(usage.saturating_sub(released));
+                }
+                Err(err) => tracing::warn!("Failed to prune the wal: {err}"),
+            }
+        }
+        self.metrics.storage_disk_usage_bytes.set(usage as i64);
+        let over_budget = usage > disk_budget;
+        if over_budget {
+            tracing::warn!(
+                "Storage uses {usage} bytes, over the disk budget of {disk_budget} bytes"
+            );
+        }
+        self.metrics.storage_over_budget.set(over_budget as i64);
     }
 
     /// This only checks readiness in terms of helping liveness for commit rule,
@@ -403,6 +690,22 @@ impl<H: BlockHandler> Core<H> {
         }
     }
 
+    /// Leaders of the current leader round (the round below the quorum round)
+    /// for which we have not yet received a block.
+    pub fn missing_leaders(&self) -> Vec<AuthorityIndex> {
+        let quorum_round = self.threshold_clock.get_round();
+        let Some(leader_round) = quorum_round.checked_sub(1) else {
+            return vec![];
+        };
+        let mut leaders = self.committer.get_leaders(leader_round);
+        leaders.retain(|leader| {
+            !self
+                .block_store
+                .block_exists_at_authority_round(*leader, leader_round)
+        });
+        leaders
+    }
+
     pub fn handle_committed_subdag(
         &mut self,
         committed: Vec<CommittedSubDag>,
@@ -413,9 +716,16 @@ impl<H: BlockHandler> Core<H> {
             for block in &commit.blocks {
                 self.epoch_manager
                     .observe_committed_block(block, &self.committee);
+                self.metrics
+                    .blocks_committed_total
+                    .with_label_values(&[&block.author().to_string()])
+                    .inc();
             }
             commit_data.push(CommitData::from(commit));
         }
+        self.metrics
+            .committed_subdags_total
+            .inc_by(committed.len() as u64);
         self.write_state(); // todo - this can be done less frequently to reduce IO
         self.write_commits(&commit_data, state);
         // todo - We should also persist state of the epoch manager, otherwise if validator
@@ -431,16 +741,19 @@ impl<H: BlockHandler> Core<H> {
             // We need to put some limit/backpressure on the accumulator state
             return;
         }
-        self.wal_writer
+        self.last_state_position = self
+            .wal_writer
             .write(WAL_ENTRY_STATE, &self.block_handler().state())
             .expect("Write to wal has failed");
     }
 
     pub fn write_commits(&mut self, commits: &[CommitData], state: &Bytes) {
-        let commits = bincode::serialize(&(commits, state)).expect("Commits serialization failed");
         self.wal_writer
-            .write(WAL_ENTRY_COMMIT, &commits)
+            .write_serialized(WAL_ENTRY_COMMIT, &(commits, state))
             .expect("Write to wal has failed");
+        if self.wal_sync == WalSyncPolicy::OnCommit {
+            self.wal_writer.sync().expect("Wal sync failed");
+        }
     }
 
     pub fn take_recovered_committed_blocks(&mut self) -> (HashSet<BlockReference>, Option<Bytes>) {
@@ -510,6 +823,23 @@ impl CoreOptions {
     }
 }
 
+/// Disk space allocated to the files under `path`, which excludes pruned segments of the wal.
+fn disk_usage(path: &Path) -> io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        #[cfg(unix)]
+        let allocated = metadata.blocks() * 512;
+        #[cfg(not(unix))]
+        let allocated = metadata.len();
+        return Ok(allocated);
+    }
+    let mut usage = 0;
+    for entry in fs::read_dir(path)? {
+        usage += disk_usage(&entry?.path())?;
+    }
+    Ok(usage)
+}
+
 #[cfg(test)]
 mod test {
     use std::fmt::Write;
@@ -574,6 +904,230 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_core_withhold_votes() {
+        let (_committee, mut cores, _) = committee_and_cores(7);
+        let mut blocks = vec![];
+        for core in &mut cores {
+            core.run_block_handler(&[]);
+            blocks.push(core.try_new_block().unwrap());
+        }
+
+        // A validator that is not a leader of the first round withholds its votes for them.
+        let leaders = cores[0].committer.get_leaders(1);
+        assert!(!leaders.is_empty());
+        let byzantine = cores
+            .iter()
+            .position(|core| !leaders.contains(&core.authority))
+            .unwrap();
+        cores[byzantine].byzantine_strategy = Some(ByzantineStrategy::WithholdVotes);
+        for (i, core) in cores.iter_mut().enumerate() {
+            core.add_blocks(blocks.clone());
+            let block = core.try_new_block().unwrap();
+            let votes = block
+                .includes()
+                .iter()
+                .filter(|x| x.round == 1 && leaders.contains(&x.authority))
+                .count();
+            let expected = if i == byzantine { 0 } else { leaders.len() };
+            assert_eq!(votes, expected, "Unexpected votes of {i}");
+        }
+    }
+
+    #[test]
+    fn test_core_equivocate() {
+        let (_committee, mut cores, _) = committee_and_cores(4);
+        cores[0].byzantine_strategy = Some(ByzantineStrategy::Equivocate);
+        cores[0].run_block_handler(&[]);
+        let block = cores[0].try_new_block().unwrap();
+
+        // The conflicting block is kept apart from the own block of the round.
+        let block_store = &cores[0].block_store;
+        let equivocation = block_store.get_own_equivocation(1).unwrap();
+        assert_ne!(equivocation.reference(), block.reference());
+        assert_eq!(equivocation.includes(), block.includes());
+        assert_eq!(block_store.get_blocks_at_authority_round(0, 1).len(), 2);
+        let own_blocks: Vec<_> = block_store
+            .get_own_blocks(0, 10)
+            .iter()
+            .map(|x| *x.reference())
+            .collect();
+        assert_eq!(own_blocks, vec![*block.reference()]);
+
+        // Honest validators accept both blocks.
+        let processed = cores[1].add_blocks(vec![block, equivocation]);
+        assert_eq!(processed.len(), 2);
+    }
+
+    #[test]
+    fn test_core_commit_waits_for_payloads() {
+        let (_committee, mut cores, _) = committee_and_cores(4);
+        for core in &mut cores {
+            core.header_first = true;
+        }
+        // The first core only receives the headers of the blocks of its peers
+        let headers = |blocks: &[Data<StatementBlock>]| -> Vec<_> {
+            blocks
+                .iter()
+                .map(|block| match block.author() {
+                    0 => block.clone(),
+                    _ => Data::new(block.header()),
+                })
+                .collect()
+        };
+
+        let mut blocks = vec![];
+        for core in &mut cores {
+            core.run_block_handler(&[]);
+            blocks.push(core.try_new_block().unwrap());
+        }
+        let mut payloads = vec![];
+        let mut early_commits = vec![];
+        for round in 2..=10 {
+            cores[0].add_blocks(headers(&blocks));
+            early_commits.extend(cores[0].try_commit());
+            // Without their payloads, the first core can not include the blocks of its peers
+            assert!(cores[0].try_new_block().is_none());
+            let mut next = vec![];
+            for core in &mut cores[1..] {
+                core.add_blocks(blocks.clone());
+                let block = core.try_new_block().unwrap();
+                assert_eq!(block.round(), round);
+                next.push(block);
+            }
+            payloads.extend(blocks.into_iter().filter(|block| block.author() != 0));
+            blocks = next;
+        }
+        cores[0].add_blocks(headers(&blocks));
+        cores[1].add_blocks(blocks.clone());
+        payloads.extend(blocks.into_iter().filter(|block| block.author() != 0));
+
+        // The leaders are decided, but only committed once their payloads are received
+        let committed = cores[1].try_commit();
+        assert!(cores[0].try_commit().is_empty());
+        assert!(early_commits.len() < committed.len());
+        cores[0].add_blocks(payloads);
+        assert!(cores[0].block_manager.awaiting_payload().is_empty());
+        assert!(cores[0].block_manager.incomplete_blocks().is_empty());
+        early_commits.extend(cores[0].try_commit());
+        assert_eq!(cores[0].try_new_block().unwrap().round(), 11);
+        let references = |leaders: Vec<Data<StatementBlock>>| -> Vec<_> {
+            leaders.iter().map(|leader| *leader.reference()).collect()
+        };
+        assert_eq!(references(early_commits), references(committed));
+
+        // The leaders are reported once, although the first core tried to commit them repeatedly
+        let rounds_led = |core: &Core<_>| -> u64 {
+            (0..4)
+                .map(|authority: AuthorityIndex| {
+                    core.metrics
+                        .rounds_led_total
+                        .with_label_values(&[&authority.to_string()])
+                        .get()
+                })
+                .sum()
+        };
+        assert_eq!(rounds_led(&cores[0]), rounds_led(&cores[1]));
+    }
+
+    #[test]
+    fn test_core_excludes_blocks_without_payloads() {
+        let (_committee, mut cores, _) = committee_and_cores(4);
+        for core in &mut cores {
+            core.header_first = true;
+        }
+        // The last authority only ever disseminates the headers of its blocks
+        let withhold = |blocks: &[Data<StatementBlock>]| -> Vec<_> {
+            blocks
+                .iter()
+                .map(|block| match block.author() {
+                    3 => Data::new(block.header()),
+                    _ => block.clone(),
+                })
+                .collect()
+        };
+
+        let mut blocks = vec![];
+        for core in &mut cores {
+            core.run_block_handler(&[]);
+            blocks.push(core.try_new_block().unwrap());
+        }
+        let mut committed = vec![];
+        for round in 2..=20 {
+            let mut next = vec![];
+            for core in &mut cores {
+                if core.authority == 3 {
+                    core.add_blocks(blocks.clone());
+                } else {
+                    core.add_blocks(withhold(&blocks));
+                }
+                if core.authority == 0 {
+                    committed.extend(core.try_commit());
+                }
+                let Some(block) = core.try_new_block() else {
+                    continue;
+                };
+                assert_eq!(block.round(), round);
+                // Honest validators never include the blocks of the last authority
+                if core.authority != 3 {
+                    assert!(block
+                        .includes()
+                        .iter()
+                        .all(|include| include.authority != 3 || include.round == 0));
+                }
+                next.push(block);
+            }
+            blocks = next;
+        }
+
+        // The honest validators keep committing without the payloads of the last authority
+        assert!(committed.last().unwrap().round() > 10);
+        assert!(committed.iter().all(|leader| leader.author() != 3));
+        assert!(!cores[0].block_manager.awaiting_payload().is_empty());
+    }
+
+    #[test]
+    fn test_core_authority_metrics() {
+        let (_committee, mut cores, _) = committee_and_cores(4);
+
+        let mut blocks = vec![];
+        for core in &mut cores {
+            core.run_block_handler(&[]);
+            blocks.push(core.try_new_block().unwrap());
+        }
+
+        let core = &mut cores[0];
+        // Leaders of the genesis round are always known
+        assert_eq!(core.missing_leaders(), vec![]);
+        core.add_blocks(blocks[1..].to_vec());
+        core.try_new_block().unwrap();
+
+        let metrics = &core.metrics;
+        assert_eq!(
+            metrics
+                .blocks_proposed_total
+                .with_label_values(&["0"])
+                .get(),
+            2
+        );
+        for authority in ["1", "2", "3"] {
+            assert_eq!(
+                metrics
+                    .blocks_received_total
+                    .with_label_values(&[authority])
+                    .get(),
+                1
+            );
+        }
+        assert_eq!(
+            metrics
+                .blocks_proposed_total
+                .with_label_values(&["1"])
+                .get(),
+            0
+        );
+    }
+
     #[test]
     fn test_randomized_simple_exchange() {
         'l: for seed in 0..100 {
@@ -724,6 +1278,54 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_core_recovery_after_pruning() {
+        const ROUNDS: RoundNumber = 300;
+
+        let tmp = tempdir::TempDir::new("test_core_recovery_after_pruning").unwrap();
+        let (_committee, mut cores, _) = committee_and_cores_persisted(4, Some(tmp.path()));
+        // Prune the wal of each core on every cleanup
+        for core in &mut cores {
+            core.storage_path = tmp.path().join(format!("{:03}.wal", core.authority));
+            core.disk_budget = Some(0);
+        }
+
+        let mut blocks = vec![];
+        for core in &mut cores {
+            core.run_block_handler(&[]);
+            blocks.push(core.try_new_block().unwrap());
+        }
+        for round in 2..=ROUNDS {
+            let mut next = vec![];
+            for core in &mut cores {
+                core.add_blocks(blocks.clone());
+                core.try_commit();
+                core.write_state();
+                core.cleanup();
+                let block = core.try_new_block().unwrap();
+                assert_eq!(block.reference().round, round);
+                next.push(block);
+            }
+            blocks = next;
+        }
+        for core in &cores {
+            assert!(core.metrics.wal_pruned_bytes.get() > 0);
+        }
+        drop(cores);
+
+        // The recovered cores resume from their last blocks, above the pruned segments
+        let (_committee, mut cores, _) = committee_and_cores_persisted(4, Some(tmp.path()));
+        for core in &mut cores {
+            assert_eq!(core.last_proposed(), ROUNDS);
+            core.add_blocks(blocks.clone());
+            let block = core
+                .try_new_block()
+                .expect("Must be able to create block after recovery");
+            assert_eq!(block.reference().round, ROUNDS + 1);
+        }
+    }
+
     fn push_all(
         p: &mut Vec<Vec<Data<StatementBlock>>>,
         except: AuthorityIndex,
@@ -1,7 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::HashSet;
+use std::{collections::HashSet, future::Future};
 
 use parking_lot::Mutex;
 
@@ -29,6 +29,11 @@ impl<H: BlockHandler + 'static, S: SyncerSignals + 'static, C: CommitObserver +
         self.syncer.into_inner()
     }
 
+    /// The simulated core runs on the callers of the dispatcher, there is no thread to wait for.
+    pub fn stopped(&self) -> impl Future<Output = ()> + 'static {
+        futures::future::ready(())
+    }
+
     pub async fn add_blocks(&self, blocks: Vec<Data<StatementBlock>>) {
         self.syncer.lock().add_blocks(blocks);
     }
@@ -38,7 +43,7 @@ impl<H: BlockHandler + 'static, S: SyncerSignals + 'static, C: CommitObserver +
     }
 
     pub async fn cleanup(&self) {
-        self.syncer.lock().core().cleanup();
+        self.syncer.lock().cleanup();
     }
 
     pub async fn get_missing_blocks(&self) -> Vec<HashSet<BlockReference>> {
@@ -1,11 +1,12 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{collections::HashSet, sync::Arc, thread};
+use std::{collections::HashSet, future::Future, sync::Arc, thread};
 
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, watch};
 
 use crate::{
+    affinity,
     block_handler::BlockHandler,
     data::Data,
     metrics::{Metrics, UtilizationTimerExt},
@@ -17,15 +18,24 @@ pub struct CoreThreadDispatcher<H: BlockHandler, S: SyncerSignals, C: CommitObse
     sender: mpsc::Sender<CoreThreadCommand>,
     join_handle: thread::JoinHandle<Syncer<H, S, C>>,
     metrics: Arc<Metrics>,
+    /// Closed when the core thread exits.
+    running: watch::Receiver<()>,
 }
 
 pub struct CoreThread<H: BlockHandler, S: SyncerSignals, C: CommitObserver> {
     syncer: Syncer<H, S, C>,
     receiver: mpsc::Receiver<CoreThreadCommand>,
+    _running: watch::Sender<()>,
 }
 
 enum CoreThreadCommand {
-    AddBlocks(Vec<Data<StatementBlock>>, oneshot::Sender<()>),
+    /// Add blocks to the core, within the span of the caller so that processing of a block can
+    /// be followed from its receipt.
+    AddBlocks(
+        Vec<Data<StatementBlock>>,
+        tracing::Span,
+        oneshot::Sender<()>,
+    ),
     ForceNewBlock(RoundNumber, oneshot::Sender<()>),
     Cleanup(oneshot::Sender<()>),
     /// Request missing blocks that need to be synched.
@@ -41,16 +51,26 @@ impl<H: BlockHandler + 'static, S: SyncerSignals + 'static, C: CommitObserver +
 {
     pub fn start(syncer: Syncer<H, S, C>) -> Self {
         let (sender, receiver) = mpsc::channel(32);
+        let (running_sender, running) = watch::channel(());
         let metrics = syncer.core().metrics.clone();
-        let core_thread = CoreThread { syncer, receiver };
+        let cores = syncer.core().cpu_affinity().core_thread.clone();
+        let core_thread = CoreThread {
+            syncer,
+            receiver,
+            _running: running_sender,
+        };
         let join_handle = thread::Builder::new()
             .name("mysticeti-core".to_string())
-            .spawn(move || core_thread.run())
+            .spawn(move || {
+                affinity::pin_thread("core", &cores);
+                core_thread.run()
+            })
             .unwrap();
         Self {
             sender,
             join_handle,
             metrics,
+            running,
         }
     }
 
@@ -59,9 +79,17 @@ impl<H: BlockHandler + 'static, S: SyncerSignals + 'static, C: CommitObserver +
         self.join_handle.join().unwrap()
     }
 
+    /// Resolves once the core thread has exited, even if the dispatcher was dropped meanwhile
+    /// (the thread exits once it has processed the commands queued before the drop).
+    pub fn stopped(&self) -> impl Future<Output = ()> + 'static {
+        let mut running = self.running.clone();
+        async move { while running.changed().await.is_ok() {} }
+    }
+
     pub async fn add_blocks(&self, blocks: Vec<Data<StatementBlock>>) {
         let (sender, receiver) = oneshot::channel();
-        self.send(CoreThreadCommand::AddBlocks(blocks, sender))
+        let span = tracing::Span::current();
+        self.send(CoreThreadCommand::AddBlocks(blocks, span, sender))
             .await;
         receiver.await.expect("core thread is not expected to stop");
     }
@@ -114,7 +142,8 @@ impl<H: BlockHandler, S: SyncerSignals, C: CommitObserver> CoreThread<H, S, C> {
             let _timer = metrics.core_lock_util.utilization_timer();
             metrics.core_lock_dequeued.inc();
             match command {
-                CoreThreadCommand::AddBlocks(blocks, sender) => {
+                CoreThreadCommand::AddBlocks(blocks, span, sender) => {
+                    let _span = span.enter();
                     self.syncer.add_blocks(blocks);
                     sender.send(()).ok();
                 }
@@ -123,7 +152,7 @@ impl<H: BlockHandler, S: SyncerSignals, C: CommitObserver> CoreThread<H, S, C> {
                     sender.send(()).ok();
                 }
                 CoreThreadCommand::Cleanup(sender) => {
-                    self.syncer.core().cleanup();
+                    self.syncer.cleanup();
                     sender.send(()).ok();
                 }
                 CoreThreadCommand::GetMissing(sender) => {
@@ -0,0 +1,261 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Crash-recovery tests. Every validator of a small committee runs in its own tokio runtime so that
+//! it can be killed abruptly, at any point, by dropping its runtime. Killed validators may also have
+//! the last entry of their WAL torn, as if they crashed in the middle of a write. Restarted
+//! validators must recover from their storage, commit again, and agree with the rest of the
+//! committee on the committed sequence.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use rand::{rngs::StdRng, Rng};
+use tempdir::TempDir;
+use tokio::{
+    runtime::{Builder, Runtime},
+    time,
+};
+
+use crate::{
+    committee::Committee,
+    config::{self, NodePrivateConfig, NodePublicConfig},
+    inspect,
+    test_util::rng_at_seed,
+    types::AuthorityIndex,
+    validator::Validator,
+};
+
+/// A validator running in its own runtime.
+struct TestNode {
+    runtime: Runtime,
+    validator: Validator,
+}
+
+/// A committee of validators that can be killed and restarted.
+struct TestCluster {
+    committee: Arc<Committee>,
+    public_config: NodePublicConfig,
+    dir: TempDir,
+    nodes: Vec<Option<TestNode>>,
+}
+
+/// How a validator is killed.
+#[derive(Debug, Clone, Copy)]
+enum Crash {
+    /// The process stops, everything written to the WAL survives.
+    Clean,
+    /// The process stops in the middle of writing the last entry of the WAL.
+    TornWrite,
+}
+
+impl TestNode {
+    fn start(
+        authority: AuthorityIndex,
+        committee: Arc<Committee>,
+        public_config: NodePublicConfig,
+        private_config: NodePrivateConfig,
+    ) -> Self {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name(format!("validator-{authority}"))
+            .enable_all()
+            .build()
+            .unwrap();
+        let validator = runtime
+            .block_on(
+                Validator::builder()
+                    .committee(committee)
+                    .public_config(public_config)
+                    .private_config(private_config)
+                    .start(),
+            )
+            .unwrap();
+        Self { runtime, validator }
+    }
+
+    /// Block until the validator commits, or panic after a few leader timeouts.
+    fn await_commit(&self) {
+        let mut commits = self.validator.subscribe_commits();
+        let timeout = config::node_defaults::default_leader_timeout() * 10;
+        self.runtime.block_on(async {
+            tokio::select! {
+                commit = commits.recv() => { commit.unwrap(); },
+                _ = time::sleep(timeout) => panic!("Failed to commit within a few timeouts"),
+            }
+        });
+    }
+
+    /// Drop all the tasks of the validator, without any of the graceful shutdown steps.
+    fn kill(self) {
+        let core_stopped = self.validator.core_stopped();
+        drop(self.validator);
+        drop(self.runtime);
+        // The core thread exits once the channel feeding it is dropped along with the tasks.
+        futures::executor::block_on(core_stopped);
+    }
+
+    fn shutdown(self) {
+        self.runtime.block_on(self.validator.shutdown()).unwrap();
+    }
+}
+
+impl TestCluster {
+    fn new(name: &str, committee_size: usize, port_offset: u16) -> Self {
+        let committee = Committee::new_for_benchmarks(committee_size);
+        let public_config =
+            NodePublicConfig::new_for_tests(committee_size).with_port_offset(port_offset);
+        let dir = TempDir::new(name).unwrap();
+        let mut cluster = Self {
+            committee,
+            public_config,
+            dir,
+            nodes: Vec::new(),
+        };
+        for authority in cluster.committee.authorities() {
+            let private_config = cluster.private_config(authority);
+            fs::create_dir_all(&private_config.storage_path).unwrap();
+            let node = cluster.start_node(authority);
+            cluster.nodes.push(Some(node));
+        }
+        cluster
+    }
+
+    fn private_config(&self, authority: AuthorityIndex) -> NodePrivateConfig {
+        NodePrivateConfig::new_for_benchmarks(self.dir.as_ref(), self.committee.len())
+            .remove(authority as usize)
+    }
+
+    fn wal_path(&self, authority: AuthorityIndex) -> PathBuf {
+        self.private_config(authority).wal()
+    }
+
+    fn start_node(&self, authority: AuthorityIndex) -> TestNode {
+        TestNode::start(
+            authority,
+            self.committee.clone(),
+            self.public_config.clone(),
+            self.private_config(authority),
+        )
+    }
+
+    fn node(&self, authority: AuthorityIndex) -> &TestNode {
+        self.nodes[authority as usize]
+            .as_ref()
+            .expect("Validator is not running")
+    }
+
+    fn kill(&mut self, authority: AuthorityIndex, crash: Crash, rng: &mut StdRng) {
+        let node = self.nodes[authority as usize]
+            .take()
+            .expect("Validator is not running");
+        node.kill();
+        if let Crash::TornWrite = crash {
+            tear_last_entry(&self.wal_path(authority), rng);
+        }
+    }
+
+    fn restart(&mut self, authority: AuthorityIndex) {
+        assert!(self.nodes[authority as usize].is_none());
+        let node = self.start_node(authority);
+        self.nodes[authority as usize] = Some(node);
+    }
+
+    /// Stop all validators, then check that their storage is consistent: the commits recorded by
+    /// each validator match a replay of its WAL, and all replayed sequences agree.
+    fn shutdown_and_check(self) {
+        for node in self.nodes.iter().flatten() {
+            node.await_commit();
+        }
+        let Self {
+            committee,
+            public_config,
+            dir,
+            nodes,
+        } = self;
+        nodes.into_iter().flatten().for_each(TestNode::shutdown);
+
+        let sequences: Vec<_> = committee
+            .authorities()
+            .map(|authority| {
+                let wal = NodePrivateConfig::new_for_benchmarks(dir.as_ref(), committee.len())
+                    .remove(authority as usize)
+                    .wal();
+                let commits = inspect::replay_commits(
+                    authority,
+                    committee.clone(),
+                    &public_config.parameters,
+                    &wal,
+                )
+                .unwrap();
+                inspect::check_recorded_commits(&wal, &commits).unwrap();
+                inspect::commit_sequence(&commits)
+            })
+            .collect();
+        for sequence in &sequences {
+            assert!(!sequence.is_empty());
+            for other in &sequences {
+                for (a, b) in sequence.iter().zip(other) {
+                    assert_eq!(
+                        a.digest, b.digest,
+                        "Validators disagree at commit {}",
+                        a.height
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Simulate a crash in the middle of writing the last entry of the WAL. Own blocks are never torn:
+/// a crash while writing one happens before it is sent, which the test cannot tell apart from after.
+fn tear_last_entry(path: &Path, rng: &mut StdRng) {
+    let entries = inspect::wal_entries(path).unwrap();
+    let Some(last) = entries.last() else {
+        return;
+    };
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    match last.record {
+        inspect::WalRecord::OwnBlock(_) => {
+            // Garbage of a header and part of an entry following the own block.
+            let garbage: Vec<u8> = (0..rng.gen_range(1..64)).map(|_| rng.gen()).collect();
+            file.write_all(&garbage).unwrap();
+        }
+        _ => {
+            // The entry ends the file: cut part of it.
+            let len = file.metadata().unwrap().len();
+            let cut = rng.gen_range(1..=last.size.max(1) as u64);
+            file.set_len(len - cut).unwrap();
+        }
+    }
+}
+
+/// Kill random validators at random points, including in the middle of WAL writes, and check they
+/// recover to a consistent state and rejoin the committee.
+#[test]
+fn crash_and_recover() {
+    let mut rng = rng_at_seed(0);
+    let mut cluster = TestCluster::new("crash_and_recover", 4, 500);
+    cluster.node(0).await_commit();
+
+    for crash in [Crash::Clean, Crash::TornWrite, Crash::TornWrite] {
+        let authority = rng.gen_range(0..cluster.committee.len()) as AuthorityIndex;
+        thread::sleep(Duration::from_millis(rng.gen_range(0..1_000)));
+        cluster.kill(authority, crash, &mut rng);
+
+        // The rest of the committee makes progress without it.
+        let other = (authority + 1) % cluster.committee.len() as AuthorityIndex;
+        cluster.node(other).await_commit();
+
+        cluster.restart(authority);
+        cluster.node(authority).await_commit();
+    }
+
+    cluster.shutdown_and_check();
+}
@@ -18,6 +18,7 @@ use crate::{
         AuthorityIndex,
         BaseStatement,
         BlockReference,
+        BlockStatements,
         EpochStatus,
         RoundNumber,
         StatementBlock,
@@ -50,7 +51,7 @@ impl BlockDigest {
         authority: AuthorityIndex,
         round: RoundNumber,
         includes: &[BlockReference],
-        statements: &[BaseStatement],
+        statements: &BlockStatements,
         meta_creation_time_ns: TimestampNs,
         epoch_marker: EpochStatus,
         signature: &SignatureBytes,
@@ -74,7 +75,7 @@ impl BlockDigest {
         _authority: AuthorityIndex,
         _round: RoundNumber,
         _includes: &[BlockReference],
-        _statements: &[BaseStatement],
+        _statements: &BlockStatements,
         _meta_creation_time_ns: TimestampNs,
         _epoch_marker: EpochStatus,
         _signature: &SignatureBytes,
@@ -82,6 +83,21 @@ impl BlockDigest {
         Default::default()
     }
 
+    /// The digest of the statements of a block disseminated header-first. The block digest and
+    /// signature of such blocks cover the statements through this digest, so that the header of
+    /// the block can be verified without them.
+    #[cfg(not(test))]
+    pub fn new_payload(statements: &[BaseStatement]) -> Self {
+        let mut hasher = BlockHasher::default();
+        Self::hash_statements(&mut hasher, statements);
+        Self(hasher.finalize().into())
+    }
+
+    #[cfg(test)]
+    pub fn new_payload(_statements: &[BaseStatement]) -> Self {
+        Default::default()
+    }
+
     /// There is a bit of a complexity around what is considered block digest and what is being signed
     ///
     /// * Block signature covers all the fields in the block, except for signature and reference.digest
@@ -95,7 +111,7 @@ impl BlockDigest {
         authority: AuthorityIndex,
         round: RoundNumber,
         includes: &[BlockReference],
-        statements: &[BaseStatement],
+        statements: &BlockStatements,
         meta_creation_time_ns: TimestampNs,
         epoch_marker: EpochStatus,
     ) {
@@ -104,6 +120,16 @@ impl BlockDigest {
         for include in includes {
             include.crypto_hash(hasher);
         }
+        match statements.payload_digest() {
+            Some(payload_digest) => payload_digest.crypto_hash(hasher),
+            None => Self::hash_statements(hasher, statements),
+        }
+        meta_creation_time_ns.crypto_hash(hasher);
+        epoch_marker.crypto_hash(hasher);
+    }
+
+    #[cfg(not(test))]
+    fn hash_statements(hasher: &mut BlockHasher, statements: &[BaseStatement]) {
         for statement in statements {
             match statement {
                 BaseStatement::Share(tx) => {
@@ -129,8 +155,6 @@ impl BlockDigest {
                 }
             }
         }
-        meta_creation_time_ns.crypto_hash(hasher);
-        epoch_marker.crypto_hash(hasher);
     }
 }
 
@@ -185,7 +209,7 @@ impl PublicKey {
             block.author(),
             block.round(),
             block.includes(),
-            block.statements(),
+            block.block_statements(),
             block.meta_creation_time_ns(),
             block.epoch_changed(),
         );
@@ -213,7 +237,7 @@ impl Signer {
         authority: AuthorityIndex,
         round: RoundNumber,
         includes: &[BlockReference],
-        statements: &[BaseStatement],
+        statements: &BlockStatements,
         meta_creation_time_ns: TimestampNs,
         epoch_marker: EpochStatus,
     ) -> SignatureBytes {
@@ -238,7 +262,7 @@ impl Signer {
         _authority: AuthorityIndex,
         _round: RoundNumber,
         _includes: &[BlockReference],
-        _statements: &[BaseStatement],
+        _statements: &BlockStatements,
         _meta_creation_time_ns: TimestampNs,
         _epoch_marker: EpochStatus,
     ) -> SignatureBytes {
@@ -0,0 +1,206 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Export of the recent DAG of a validator, to visualize it when debugging the commit rule. The
+//! blocks are the nodes of the graph, the links to their parents are its edges, and the committed
+//! leaders are highlighted.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    ops::RangeInclusive,
+    sync::{Arc, OnceLock},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block_store::BlockStore,
+    types::{format_authority_round, AuthorityIndex, BlockReference, RoundNumber},
+};
+
+/// The number of recent committed leaders kept to highlight them in the exports.
+const LEADER_HISTORY: usize = 1024;
+
+/// The fill color of the committed leaders in the DOT exports.
+const LEADER_COLOR: &str = "gold";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DagFormat {
+    /// A Graphviz digraph, to render with `dot -Tsvg`.
+    #[default]
+    Dot,
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DagBlock {
+    /// The reference of the block followed by the prefix of its digest, unique even across
+    /// equivocations.
+    pub id: String,
+    pub authority: AuthorityIndex,
+    pub round: RoundNumber,
+    /// The ids of the parents of the block within the exported rounds.
+    pub parents: Vec<String>,
+    pub transactions: usize,
+    pub committed_leader: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DagExport {
+    /// The blocks of the exported rounds, in round order.
+    pub blocks: Vec<DagBlock>,
+}
+
+/// The recent DAG of a running validator, shared with the admin routes. The block store is set
+/// once recovered from the WAL, which happens after the admin routes start serving.
+#[derive(Clone, Default)]
+pub struct DagExporter {
+    block_store: Arc<OnceLock<BlockStore>>,
+    leaders: Arc<Mutex<VecDeque<BlockReference>>>,
+}
+
+impl DagExporter {
+    pub fn set_block_store(&self, block_store: BlockStore) {
+        if self.block_store.set(block_store).is_err() {
+            tracing::warn!("The block store of the DAG exports is already set");
+        }
+    }
+
+    pub fn record_leader(&self, leader: BlockReference) {
+        let mut leaders = self.leaders.lock();
+        if leaders.len() == LEADER_HISTORY {
+            leaders.pop_front();
+        }
+        leaders.push_back(leader);
+    }
+
+    /// The highest round of the DAG, if the block store is recovered.
+    pub fn highest_round(&self) -> Option<RoundNumber> {
+        self.block_store.get().map(BlockStore::highest_round)
+    }
+
+    /// Export the blocks of `rounds`, if the block store is recovered.
+    pub fn export(&self, rounds: RangeInclusive<RoundNumber>) -> Option<DagExport> {
+        let block_store = self.block_store.get()?;
+        let leaders: HashSet<_> = self.leaders.lock().iter().copied().collect();
+        Some(DagExport::new(block_store, rounds, &leaders))
+    }
+}
+
+impl DagExport {
+    /// Export the blocks of `rounds` held by `block_store`, highlighting the `committed_leaders`.
+    pub fn new(
+        block_store: &BlockStore,
+        rounds: RangeInclusive<RoundNumber>,
+        committed_leaders: &HashSet<BlockReference>,
+    ) -> Self {
+        let blocks = block_store
+            .get_blocks_in_range(rounds.clone(), None)
+            .iter()
+            .map(|block| DagBlock {
+                id: block_id(block.reference()),
+                authority: block.author(),
+                round: block.round(),
+                parents: block
+                    .includes()
+                    .iter()
+                    .filter(|parent| rounds.contains(&parent.round))
+                    .map(block_id)
+                    .collect(),
+                transactions: block.shared_transactions().count(),
+                committed_leader: committed_leaders.contains(block.reference()),
+            })
+            .collect();
+        Self { blocks }
+    }
+
+    /// The DAG as a Graphviz digraph. The blocks of a round are laid out on the same rank, and
+    /// every block points to its parents.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot)
+            .expect("Writing to a string cannot fail");
+        dot
+    }
+
+    fn write_dot(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        writeln!(f, "digraph dag {{")?;
+        writeln!(f, "    rankdir=BT;")?;
+        writeln!(f, "    node [shape=box];")?;
+        for round in self.blocks.chunk_by(|a, b| a.round == b.round) {
+            write!(f, "    {{ rank=same;")?;
+            for block in round {
+                write!(f, " \"{}\";", block.id)?;
+            }
+            writeln!(f, " }}")?;
+        }
+        for block in &self.blocks {
+            write!(
+                f,
+                "    \"{}\" [label=\"{}\\n{} tx\"",
+                block.id,
+                format_authority_round(block.authority, block.round),
+                block.transactions
+            )?;
+            if block.committed_leader {
+                write!(f, ", style=filled, fillcolor={LEADER_COLOR}")?;
+            }
+            writeln!(f, "];")?;
+            for parent in &block.parents {
+                writeln!(f, "    \"{}\" -> \"{parent}\";", block.id)?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+fn block_id(reference: &BlockReference) -> String {
+    format!("{reference}{}", reference.digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        committee::Committee,
+        test_util::{build_dag, TestBlockWriter},
+    };
+
+    #[test]
+    fn export_recent_rounds() {
+        let committee = Committee::new_test(vec![1, 1, 1, 1]);
+        let mut block_writer = TestBlockWriter::new(&committee);
+        let references = build_dag(&committee, &mut block_writer, None, 5);
+
+        let exporter = DagExporter::default();
+        assert!(exporter.export(4..=5).is_none());
+        exporter.set_block_store(block_writer.into_block_store());
+        exporter.record_leader(references[1]);
+        assert_eq!(exporter.highest_round(), Some(5));
+
+        let export = exporter.export(4..=5).unwrap();
+        assert_eq!(export.blocks.len(), 8);
+        // The parents of the first exported round are outside of the export.
+        assert!(export.blocks[..4]
+            .iter()
+            .all(|block| block.parents.is_empty()));
+        assert!(export.blocks[4..]
+            .iter()
+            .all(|block| block.parents.len() == 4));
+        let leaders: Vec<_> = export
+            .blocks
+            .iter()
+            .filter(|block| block.committed_leader)
+            .map(|block| block.id.clone())
+            .collect();
+        assert_eq!(leaders, vec![block_id(&references[1])]);
+
+        let dot = export.to_dot();
+        assert!(dot.starts_with("digraph dag {"));
+        assert_eq!(dot.matches(" -> ").count(), 16);
+        assert_eq!(dot.matches("fillcolor").count(), 1);
+    }
+}
@@ -41,7 +41,11 @@ pub static IN_MEMORY_BLOCKS_BYTES: AtomicUsize = AtomicUsize::new(0);
 
 impl<T: Serialize + DeserializeOwned> Data<T> {
     pub fn new(t: T) -> Self {
-        let serialized = bincode::serialize(&t).expect("Serialization should not fail");
+        // The serialized bytes live as long as the value, so they are allocated once at their
+        // exact size rather than grown while serializing.
+        let size = bincode::serialized_size(&t).expect("Serialization should not fail");
+        let mut serialized = Vec::with_capacity(size as usize);
+        bincode::serialize_into(&mut serialized, &t).expect("Serialization should not fail");
         let serialized: Bytes = serialized.into();
         IN_MEMORY_BLOCKS.fetch_add(1, Ordering::Relaxed);
         IN_MEMORY_BLOCKS_BYTES.fetch_add(serialized.len(), Ordering::Relaxed);
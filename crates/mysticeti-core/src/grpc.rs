@@ -0,0 +1,288 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A gRPC service through which other services integrate with a validator: they submit
+//! transactions and stream the committed ones, in commit order (see `proto/mysticeti.proto`).
+
+use std::{
+    io,
+    net::{self, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+};
+
+use futures::{Stream, StreamExt};
+use parking_lot::Mutex;
+use tokio::{
+    net::TcpListener,
+    select,
+    sync::{broadcast, mpsc},
+};
+use tokio_stream::wrappers::{
+    errors::BroadcastStreamRecvError,
+    BroadcastStream,
+    TcpListenerStream,
+};
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::{
+    consensus::linearizer::CommittedSubDag,
+    crypto::AsBytes,
+    runtime::{spawn_named, JoinHandle},
+    transactions_generator::TIMESTAMP_SIZE,
+    types::{self, AuthorityIndex, Transaction},
+};
+
+pub mod proto {
+    tonic::include_proto!("mysticeti");
+}
+
+use proto::{
+    consensus_server::{Consensus, ConsensusServer},
+    BlockReference,
+    Commit,
+    GetStatusRequest,
+    GetStatusResponse,
+    SubmitTransactionRequest,
+    SubmitTransactionResponse,
+    SubscribeCommitsRequest,
+};
+
+/// Capacity of the channel delivering numbered commits to the streams of subscribers.
+/// Subscribers that fall further behind get an error and need to subscribe again.
+const COMMIT_STREAM_CAPACITY: usize = 1024;
+
+struct ConsensusService {
+    transactions: mpsc::Sender<Vec<Transaction>>,
+    commits: broadcast::Sender<Commit>,
+    status: Arc<Mutex<GetStatusResponse>>,
+}
+
+/// Serve the gRPC consensus service on `address`. Submitted transactions are sent to
+/// `transactions` and the sub-dags delivered by `commits` are streamed to subscribers.
+pub fn start_grpc_server(
+    address: SocketAddr,
+    transactions: mpsc::Sender<Vec<Transaction>>,
+    commits: broadcast::Sender<CommittedSubDag>,
+    authority: AuthorityIndex,
+) -> io::Result<JoinHandle<()>> {
+    let listener = net::TcpListener::bind(address)?;
+    start_grpc_server_on(listener, transactions, commits, authority)
+}
+
+/// Same as [`start_grpc_server`], on a listener already bound (e.g., to an ephemeral port).
+pub fn start_grpc_server_on(
+    listener: net::TcpListener,
+    transactions: mpsc::Sender<Vec<Transaction>>,
+    commits: broadcast::Sender<CommittedSubDag>,
+    authority: AuthorityIndex,
+) -> io::Result<JoinHandle<()>> {
+    let address = listener.local_addr()?;
+    listener.set_nonblocking(true)?;
+    let listener = TcpListener::from_std(listener)?;
+    let status = Arc::new(Mutex::new(GetStatusResponse {
+        authority,
+        ..Default::default()
+    }));
+    let (commit_sender, _) = broadcast::channel(COMMIT_STREAM_CAPACITY);
+    let numbering = number_commits(commits.subscribe(), commit_sender.clone(), status.clone());
+    let service = ConsensusService {
+        transactions,
+        commits: commit_sender,
+        status,
+    };
+    let server = Server::builder()
+        .add_service(ConsensusServer::new(service))
+        .serve_with_incoming(TcpListenerStream::new(listener));
+    tracing::info!("Serving gRPC on {address}");
+    Ok(spawn_named("grpc-server", async move {
+        select! {
+            result = server => {
+                if let Err(e) = result {
+                    tracing::warn!("gRPC server failed: {e}");
+                }
+            }
+            _ = numbering => (),
+        }
+    }))
+}
+
+/// Number the commits of the validator and forward them to the streams of subscribers.
+async fn number_commits(
+    mut commits: broadcast::Receiver<CommittedSubDag>,
+    sender: broadcast::Sender<Commit>,
+    status: Arc<Mutex<GetStatusResponse>>,
+) {
+    let mut index = 0;
+    loop {
+        let commit = match commits.recv().await {
+            Ok(commit) => commit,
+            // Skip the index of the missed commits so the indices stay the same across services.
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("gRPC server missed {skipped} commits");
+                index += skipped;
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let transactions: Vec<_> = commit
+            .blocks
+            .iter()
+            .flat_map(|block| block.shared_transactions())
+            .map(|(_, transaction)| transaction.as_bytes().to_vec())
+            .collect();
+        let anchor = block_reference(&commit.anchor);
+        {
+            let mut status = status.lock();
+            status.commits = index + 1;
+            status.last_anchor = Some(anchor.clone());
+            status.committed_transactions += transactions.len() as u64;
+        }
+        let commit = Commit {
+            index,
+            anchor: Some(anchor),
+            transactions,
+        };
+        // Fails only if there are no subscribers.
+        sender.send(commit).ok();
+        index += 1;
+    }
+}
+
+fn block_reference(reference: &types::BlockReference) -> BlockReference {
+    BlockReference {
+        authority: reference.authority,
+        round: reference.round,
+        digest: reference.digest.as_bytes().to_vec(),
+    }
+}
+
+#[tonic::async_trait]
+impl Consensus for ConsensusService {
+    async fn submit_transaction(
+        &self,
+        request: Request<SubmitTransactionRequest>,
+    ) -> Result<Response<SubmitTransactionResponse>, Status> {
+        let transactions: Vec<_> = request
+            .into_inner()
+            .transactions
+            .into_iter()
+            .map(Transaction::new)
+            .collect();
+        if transactions.is_empty() {
+            return Err(Status::invalid_argument("No transactions to submit"));
+        }
+        if transactions
+            .iter()
+            .any(|transaction| transaction.as_bytes().len() < TIMESTAMP_SIZE)
+        {
+            return Err(Status::invalid_argument(format!(
+                "Transactions must start with a {TIMESTAMP_SIZE}-byte submission timestamp"
+            )));
+        }
+        self.transactions
+            .send(transactions)
+            .await
+            .map_err(|_| Status::unavailable("The validator is shutting down"))?;
+        Ok(Response::new(SubmitTransactionResponse {}))
+    }
+
+    type SubscribeCommitsStream = Pin<Box<dyn Stream<Item = Result<Commit, Status>> + Send>>;
+
+    async fn subscribe_commits(
+        &self,
+        _request: Request<SubscribeCommitsRequest>,
+    ) -> Result<Response<Self::SubscribeCommitsStream>, Status> {
+        let stream = BroadcastStream::new(self.commits.subscribe()).map(|commit| {
+            commit.map_err(|BroadcastStreamRecvError::Lagged(skipped)| {
+                Status::data_loss(format!("Subscriber missed {skipped} commits"))
+            })
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_status(
+        &self,
+        _request: Request<GetStatusRequest>,
+    ) -> Result<Response<GetStatusResponse>, Status> {
+        Ok(Response::new(self.status.lock().clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::Data,
+        grpc::proto::consensus_client::ConsensusClient,
+        types::{BaseStatement, StatementBlock},
+    };
+
+    fn block(author: AuthorityIndex, transaction: &[u8]) -> Data<StatementBlock> {
+        Data::new(StatementBlock::new(
+            author,
+            1,
+            vec![],
+            vec![BaseStatement::Share(Transaction::new(transaction.to_vec()))],
+            0,
+            false,
+            Default::default(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn submit_and_subscribe() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (transaction_sender, mut transaction_receiver) = mpsc::channel(16);
+        let (commit_sender, _) = broadcast::channel(16);
+        let _server =
+            start_grpc_server_on(listener, transaction_sender, commit_sender.clone(), 1).unwrap();
+        let mut client = ConsensusClient::connect(format!("http://{address}"))
+            .await
+            .unwrap();
+
+        let request = SubmitTransactionRequest {
+            transactions: vec![vec![1; 16]],
+        };
+        client.submit_transaction(request).await.unwrap();
+        let batch = transaction_receiver.recv().await.unwrap();
+        assert!(batch == vec![Transaction::new(vec![1; 16])]);
+
+        // Transactions too short to hold their submission timestamp are rejected.
+        let request = SubmitTransactionRequest {
+            transactions: vec![vec![1; 16], vec![1; TIMESTAMP_SIZE - 1]],
+        };
+        let status = client.submit_transaction(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let mut commits = client
+            .subscribe_commits(SubscribeCommitsRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        // The stream subscribed to the commits once its response is received.
+        for id in 0..2u8 {
+            let blocks = vec![block(0, &[id; 16]), block(1, &[id + 10; 16])];
+            let anchor = *blocks[0].reference();
+            commit_sender
+                .send(CommittedSubDag::new(anchor, blocks))
+                .unwrap();
+        }
+        for index in 0..2u64 {
+            let commit = commits.message().await.unwrap().unwrap();
+            assert_eq!(commit.index, index);
+            let id = index as u8;
+            assert_eq!(commit.transactions, vec![vec![id; 16], vec![id + 10; 16]]);
+        }
+
+        let status = client
+            .get_status(GetStatusRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.authority, 1);
+        assert_eq!(status.commits, 2);
+        assert_eq!(status.committed_transactions, 4);
+    }
+}
@@ -0,0 +1,430 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline inspection of the storage of a validator. Nothing here touches the network: the WAL
+//! is opened on its own and the blocks it holds are fed directly to the commit rule.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ::prometheus::Registry;
+use digest::Digest;
+use eyre::{bail, ensure, Context, Result};
+use minibytes::Bytes;
+
+use crate::{
+    block_store::{
+        BlockStore,
+        CommitData,
+        OwnBlockData,
+        WAL_ENTRY_BLOCK,
+        WAL_ENTRY_COMMIT,
+        WAL_ENTRY_OWN_BLOCK,
+        WAL_ENTRY_PAYLOAD,
+        WAL_ENTRY_STATE,
+    },
+    committee::Committee,
+    config::NodeParameters,
+    consensus::{
+        linearizer::{CommittedSubDag, Linearizer},
+        universal_committer::UniversalCommitterBuilder,
+    },
+    data::Data,
+    metrics::Metrics,
+    types::{AuthorityIndex, BlockReference, RoundNumber, StatementBlock, TransactionLocator},
+    wal::{self, Tag, WalPosition, WalReader, WalWriter},
+};
+
+/// A single entry of the WAL.
+pub struct WalEntry {
+    /// The offset of the entry in the WAL file.
+    pub position: WalPosition,
+    /// The size of the entry, excluding its header.
+    pub size: usize,
+    pub record: WalRecord,
+}
+
+/// The decoded content of a WAL entry.
+pub enum WalRecord {
+    /// A block received from another validator.
+    Block(BlockReference),
+    /// A block proposed by this validator.
+    OwnBlock(BlockReference),
+    /// Statements waiting to be included in the next own block.
+    Payload,
+    /// A snapshot of the state of the block handler.
+    State,
+    /// The leaders committed since the previous commit record, with the size of their sub-dag.
+    Commit(Vec<(BlockReference, usize)>),
+    /// An entry with a tag unknown to this version.
+    Unknown(Tag),
+    /// An entry that could not be read or decoded.
+    Corrupted(String),
+}
+
+/// Aggregate statistics over the entries of a WAL.
+#[derive(Default)]
+pub struct WalSummary {
+    pub entries: usize,
+    pub bytes: usize,
+    pub blocks: usize,
+    pub own_blocks: usize,
+    pub committed_leaders: usize,
+    pub corrupted: usize,
+    pub highest_round: RoundNumber,
+    pub last_committed_leader: Option<BlockReference>,
+}
+
+/// A committed sub-dag as seen by the application: its leader and the transactions it sequences.
+pub struct CommitRecord {
+    /// The position of the commit in the sequence, starting at 1.
+    pub height: u64,
+    /// The leader (anchor) of the sub-dag.
+    pub leader: BlockReference,
+    /// The shared transactions of the sub-dag, in commit order.
+    pub transactions: Vec<TransactionLocator>,
+    /// The digest of this commit chained with the digest of the previous one. Two sequences agree
+    /// up to some height if and only if their digests at that height are equal.
+    pub digest: [u8; 32],
+}
+
+type CommitHasher = blake2::Blake2b<digest::consts::U32>;
+
+/// List the entries of a WAL, in the order they were written. The path is either the WAL file or
+/// the storage directory of a validator. Corrupted entries are reported rather than failing the
+/// whole listing.
+pub fn wal_entries(path: impl AsRef<Path>) -> Result<Vec<WalEntry>> {
+    let (writer, reader) = open_wal(&wal_path(path.as_ref()))?;
+    Ok(reader
+        .scan_until(&writer)
+        .map(|(position, entry)| match entry {
+            Ok((tag, data)) => WalEntry {
+                position,
+                size: data.len(),
+                record: WalRecord::decode(tag, data),
+            },
+            Err(corruption) => WalEntry {
+                position,
+                size: 0,
+                record: WalRecord::Corrupted(corruption.to_string()),
+            },
+        })
+        .collect())
+}
+
+impl WalRecord {
+    fn decode(tag: Tag, data: Bytes) -> Self {
+        let decoded = match tag {
+            WAL_ENTRY_BLOCK => Data::<StatementBlock>::from_bytes(data)
+                .map(|block| WalRecord::Block(*block.reference())),
+            WAL_ENTRY_OWN_BLOCK => OwnBlockData::from_bytes(data)
+                .map(|(_, block)| WalRecord::OwnBlock(*block.reference())),
+            WAL_ENTRY_PAYLOAD => Ok(WalRecord::Payload),
+            WAL_ENTRY_STATE => Ok(WalRecord::State),
+            WAL_ENTRY_COMMIT => {
+                bincode::deserialize::<(Vec<CommitData>, Bytes)>(&data).map(|(commits, _)| {
+                    WalRecord::Commit(
+                        commits
+                            .iter()
+                            .map(|commit| (commit.leader, commit.sub_dag.len()))
+                            .collect(),
+                    )
+                })
+            }
+            _ => Ok(WalRecord::Unknown(tag)),
+        };
+        decoded.unwrap_or_else(|e| WalRecord::Corrupted(format!("failed to decode: {e}")))
+    }
+}
+
+impl WalSummary {
+    pub fn new(entries: &[WalEntry]) -> Self {
+        let mut summary = Self::default();
+        for entry in entries {
+            summary.entries += 1;
+            summary.bytes += entry.size;
+            match &entry.record {
+                WalRecord::Block(reference) => {
+                    summary.blocks += 1;
+                    summary.highest_round = summary.highest_round.max(reference.round);
+                }
+                WalRecord::OwnBlock(reference) => {
+                    summary.blocks += 1;
+                    summary.own_blocks += 1;
+                    summary.highest_round = summary.highest_round.max(reference.round);
+                }
+                WalRecord::Commit(leaders) => {
+                    summary.committed_leaders += leaders.len();
+                    if let Some((leader, _)) = leaders.last() {
+                        summary.last_committed_leader = Some(*leader);
+                    }
+                }
+                WalRecord::Corrupted(_) => summary.corrupted += 1,
+                WalRecord::Payload | WalRecord::State | WalRecord::Unknown(_) => {}
+            }
+        }
+        summary
+    }
+}
+
+impl fmt::Display for WalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t{}", self.position, self.size, self.record)
+    }
+}
+
+impl fmt::Display for WalRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalRecord::Block(reference) => write!(
+                f,
+                "block {reference} (author {}, round {})",
+                reference.authority, reference.round
+            ),
+            WalRecord::OwnBlock(reference) => write!(
+                f,
+                "own-block {reference} (author {}, round {})",
+                reference.authority, reference.round
+            ),
+            WalRecord::Payload => write!(f, "payload"),
+            WalRecord::State => write!(f, "state"),
+            WalRecord::Commit(leaders) => {
+                write!(f, "commit")?;
+                for (leader, blocks) in leaders {
+                    write!(f, " {leader} ({blocks} blocks)")?;
+                }
+                Ok(())
+            }
+            WalRecord::Unknown(tag) => write!(f, "unknown tag {tag}"),
+            WalRecord::Corrupted(reason) => write!(f, "CORRUPTED: {reason}"),
+        }
+    }
+}
+
+impl fmt::Display for WalSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "entries: {} ({} bytes)", self.entries, self.bytes)?;
+        writeln!(f, "blocks: {} ({} own)", self.blocks, self.own_blocks)?;
+        writeln!(f, "highest round: {}", self.highest_round)?;
+        match self.last_committed_leader {
+            Some(leader) => writeln!(
+                f,
+                "committed leaders: {} (last {leader})",
+                self.committed_leaders
+            )?,
+            None => writeln!(f, "committed leaders: 0")?,
+        }
+        write!(f, "corrupted entries: {}", self.corrupted)
+    }
+}
+
+/// The line printed by a replay in place of the commit sequence when the WAL is pruned.
+pub const PRUNED_WAL_MARKER: &str = "pruned";
+
+/// Whether the validator released the oldest segments of the WAL at `path`. The blocks of those
+/// segments are lost, so a replay does not reproduce the commit sequence of the validator.
+pub fn wal_pruned(path: impl AsRef<Path>) -> Result<bool> {
+    let (writer, reader) = open_wal(&wal_path(path.as_ref()))?;
+    Ok(reader.is_pruned(&writer))
+}
+
+/// Re-run the commit rule over all the blocks stored in the WAL at `path` (written by
+/// `authority`) and return the resulting committed sub-dags, in commit order.
+pub fn replay_commits(
+    authority: AuthorityIndex,
+    committee: Arc<Committee>,
+    parameters: &NodeParameters,
+    path: impl AsRef<Path>,
+) -> Result<Vec<CommittedSubDag>> {
+    let (writer, reader) = open_wal(&wal_path(path.as_ref()))?;
+    let (metrics, _reporter) = Metrics::new(&Registry::new(), Some(&committee));
+    let recovered = BlockStore::open(
+        authority,
+        Arc::new(reader),
+        &writer,
+        metrics.clone(),
+        &committee,
+    );
+    let block_store = recovered.block_store;
+
+    let committer = UniversalCommitterBuilder::new(committee, block_store.clone(), metrics)
+        .with_number_of_leaders(parameters.number_of_leaders)
+        .with_pipeline(parameters.enable_pipelining)
+        .build();
+    let leaders = committer
+        .try_commit(BlockReference::default())
+        .into_iter()
+        .filter_map(|leader| leader.into_decided_block())
+        .collect();
+    Ok(Linearizer::new().handle_commit(&block_store, leaders))
+}
+
+/// Turn a sequence of committed sub-dags into the sequence of commit records of the application.
+pub fn commit_sequence(commits: &[CommittedSubDag]) -> Vec<CommitRecord> {
+    let mut digest = [0u8; 32];
+    commits
+        .iter()
+        .zip(1..)
+        .map(|(commit, height)| {
+            let transactions: Vec<_> = commit
+                .blocks
+                .iter()
+                .flat_map(|block| block.shared_transactions().map(|(locator, _)| locator))
+                .collect();
+            let mut hasher = CommitHasher::default();
+            hasher.update(digest);
+            hasher.update(
+                bincode::serialize(&(commit.anchor, &transactions))
+                    .expect("Serialization failed"),
+            );
+            digest = hasher.finalize().into();
+            CommitRecord {
+                height,
+                leader: commit.anchor,
+                transactions,
+                digest,
+            }
+        })
+        .collect()
+}
+
+/// Check that the leaders the validator committed while running, as recorded in its WAL, agree
+/// with a replayed sequence of commits. Returns the number of recorded leaders.
+pub fn check_recorded_commits(
+    path: impl AsRef<Path>,
+    replayed: &[CommittedSubDag],
+) -> Result<usize> {
+    let recorded: Vec<_> = wal_entries(path)?
+        .into_iter()
+        .filter_map(|entry| match entry.record {
+            WalRecord::Commit(leaders) => Some(leaders),
+            _ => None,
+        })
+        .flatten()
+        .map(|(leader, _)| leader)
+        .collect();
+    for (height, (recorded, replayed)) in recorded.iter().zip(replayed).enumerate() {
+        if *recorded != replayed.anchor {
+            bail!(
+                "Commit {} diverges: the WAL records leader {recorded}, the replay commits {}",
+                height + 1,
+                replayed.anchor
+            );
+        }
+    }
+    Ok(recorded.len())
+}
+
+impl fmt::Display for CommitRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.height,
+            self.leader,
+            self.transactions.len(),
+            hex::encode(self.digest)
+        )
+    }
+}
+
+/// The WAL of a validator is the `wal` file of its storage directory.
+fn wal_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join("wal")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn open_wal(path: &Path) -> Result<(WalWriter, WalReader)> {
+    ensure!(path.is_file(), "No WAL found at '{}'", path.display());
+    wal::wal(path).wrap_err(format!("Failed to open WAL '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::{config::NodePublicConfig, test_util::committee_and_cores_persisted};
+
+    #[test]
+    fn inspect_wal_entries() {
+        let dir = TempDir::new("inspect_wal_entries").unwrap();
+        let committee = Committee::new_test(vec![1, 1, 1, 1]);
+        let (own_genesis, other_genesis) = committee.genesis_blocks(0);
+        let path = dir.path().join("wal");
+        let (mut writer, _reader) = wal::wal(&path).unwrap();
+        for block in &other_genesis {
+            writer
+                .write(WAL_ENTRY_BLOCK, block.serialized_bytes())
+                .unwrap();
+        }
+        let commits = vec![CommitData {
+            leader: *own_genesis.reference(),
+            sub_dag: vec![*own_genesis.reference()],
+        }];
+        let commits = bincode::serialize(&(commits, Bytes::new())).unwrap();
+        writer.write(WAL_ENTRY_COMMIT, &commits).unwrap();
+        // A block entry that does not decode
+        writer.write(WAL_ENTRY_BLOCK, &[0xff; 4]).unwrap();
+        drop(writer);
+
+        // The storage directory resolves to its WAL file
+        let entries = wal_entries(dir.path()).unwrap();
+        assert!(!wal_pruned(dir.path()).unwrap());
+        assert_eq!(entries.len(), other_genesis.len() + 2);
+        for (entry, block) in entries.iter().zip(&other_genesis) {
+            assert!(matches!(entry.record, WalRecord::Block(r) if r == *block.reference()));
+        }
+        let summary = WalSummary::new(&entries);
+        assert_eq!(summary.blocks, other_genesis.len());
+        assert_eq!(summary.committed_leaders, 1);
+        assert_eq!(
+            summary.last_committed_leader,
+            Some(*own_genesis.reference())
+        );
+        assert_eq!(summary.corrupted, 1);
+    }
+
+    #[test]
+    fn replayed_sequences_agree() {
+        let dir = TempDir::new("replayed_sequences_agree").unwrap();
+        let (committee, mut cores, _) = committee_and_cores_persisted(4, Some(dir.path()));
+        for _ in 0..10 {
+            let blocks: Vec<_> = cores
+                .iter_mut()
+                .map(|core| core.try_new_block().expect("Must create a block every round"))
+                .collect();
+            for core in &mut cores {
+                core.add_blocks(blocks.clone());
+            }
+        }
+        drop(cores);
+
+        let parameters = NodePublicConfig::new_for_tests(committee.len()).parameters;
+        let sequences: Vec<_> = committee
+            .authorities()
+            .map(|authority| {
+                let path = dir.path().join(format!("{:03}.wal", authority));
+                let commits =
+                    replay_commits(authority, committee.clone(), &parameters, path).unwrap();
+                commit_sequence(&commits)
+            })
+            .collect();
+
+        let first = &sequences[0];
+        assert!(!first.is_empty());
+        for sequence in &sequences[1..] {
+            assert_eq!(sequence.len(), first.len());
+            for (a, b) in sequence.iter().zip(first) {
+                assert_eq!(a.leader, b.leader);
+                assert_eq!(a.digest, b.digest);
+            }
+        }
+    }
+}
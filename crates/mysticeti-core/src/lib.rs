@@ -1,21 +1,69 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+//! A validator can be embedded by assembling it from its configuration with [`Validator::builder`]:
+//!
+//! ```no_run
+//! # async fn run() -> eyre::Result<()> {
+//! use std::time::SystemTime;
+//!
+//! use mysticeti_core::{
+//!     committee::Committee,
+//!     config::{ImportExport, NodePrivateConfig, NodePublicConfig},
+//!     types::Transaction,
+//!     Validator,
+//! };
+//!
+//! let validator = Validator::builder()
+//!     .committee(Committee::load("committee.yaml")?.into())
+//!     .public_config(NodePublicConfig::load("public-config.yaml")?)
+//!     .private_config(NodePrivateConfig::load("private-config-0.yaml")?)
+//!     .start()
+//!     .await?;
+//!
+//! let mut commits = validator.subscribe_commits();
+//! // Transactions start with their submission time, in milliseconds.
+//! let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+//! let data = [&(now.as_millis() as u64).to_le_bytes()[..], b"hello"].concat();
+//! validator
+//!     .transaction_sender()
+//!     .send(vec![Transaction::new(data)])
+//!     .await
+//!     .ok();
+//! let commit = commits.recv().await?;
+//! println!("Committed sub-dag anchored at {}", commit.anchor);
+//! # Ok(())
+//! # }
+//! ```
+
+mod affinity;
+#[cfg(feature = "archive")]
+pub mod archive;
 pub mod block_handler;
 mod block_manager;
 mod block_store;
+mod buffer_pool;
+pub mod checkpoint;
+pub mod client;
 pub mod committee;
 pub mod config;
 pub mod consensus;
+pub mod consistency;
 pub mod core;
 mod core_thread;
+#[cfg(test)]
+mod crash_recovery;
 mod crypto;
+pub mod dag_export;
 mod data;
 mod epoch_close;
 mod finalization_interpreter;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 #[cfg(test)]
 #[cfg(feature = "simulator")]
 mod future_simulator;
+pub mod inspect;
 #[allow(dead_code)] // todo - delete if unused after a while
 mod lock;
 mod log;
@@ -24,6 +72,7 @@ pub mod net_sync;
 pub mod network;
 pub mod prometheus;
 mod range_map;
+pub mod reload;
 mod runtime;
 mod serde;
 #[cfg(test)]
@@ -35,12 +84,15 @@ mod simulator;
 mod simulator_tracing;
 mod stat;
 mod state;
+pub mod submission;
 mod syncer;
 mod synchronizer;
 #[cfg(test)]
 mod test_util;
-mod threshold_clock;
+pub mod threshold_clock;
 mod transactions_generator;
 pub mod types;
 pub mod validator;
 mod wal;
+
+pub use validator::{Validator, ValidatorBuilder};
@@ -2,37 +2,185 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    collections::HashSet,
     fs::{File, OpenOptions},
     io,
-    io::Write,
-    path::Path,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
 };
 
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
 
 use crate::{committee::ProcessedTransactionHandler, runtime, types::TransactionLocator};
 
 pub struct TransactionLog {
-    ch: UnboundedSender<Vec<TransactionLocator>>,
+    ch: UnboundedSender<LogCommand>,
+    path: PathBuf,
+    /// Length of the log once all the entries submitted so far are written.
+    position: u64,
+    /// Entries written before a restart that are expected to be submitted again while recovering.
+    recovered: HashSet<String>,
+}
+
+/// Waits for the entries submitted to a [`TransactionLog`] to be written to disk.
+#[derive(Clone)]
+pub struct TransactionLogFlusher {
+    ch: UnboundedSender<LogCommand>,
+}
+
+enum LogCommand {
+    Write(String),
+    Flush(oneshot::Sender<io::Result<()>>),
 }
 
 impl TransactionLog {
     pub fn start(path: impl AsRef<Path>) -> io::Result<Self> {
-        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let position = Self::truncate_partial_line(&mut file)?;
         let (sender, receiver) = unbounded_channel();
         runtime::Handle::current().spawn(Self::run(file, receiver));
-        Ok(Self { ch: sender })
+        Ok(Self {
+            ch: sender,
+            path,
+            position,
+            recovered: Default::default(),
+        })
+    }
+
+    /// Drop the end of the last line if the process crashed while writing it, so that new
+    /// entries start on a line of their own. Returns the length of the log.
+    fn truncate_partial_line(file: &mut File) -> io::Result<u64> {
+        const TAIL: u64 = 4 * 1024;
+        let len = file.metadata()?.len();
+        let mut start = len;
+        while start > 0 {
+            let chunk_start = start.saturating_sub(TAIL);
+            let mut chunk = vec![0; (start - chunk_start) as usize];
+            file.seek(SeekFrom::Start(chunk_start))?;
+            file.read_exact(&mut chunk)?;
+            if let Some(newline) = chunk.iter().rposition(|b| *b == b'\n') {
+                start = chunk_start + newline as u64 + 1;
+                break;
+            }
+            start = chunk_start;
+        }
+        if start < len {
+            tracing::warn!(
+                "Dropping {} bytes of a partially written log entry",
+                len - start
+            );
+            file.set_len(start)?;
+        }
+        Ok(start)
+    }
+
+    /// Length of the log once all the entries submitted so far are written.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Load the entries written past `position` before a restart. These entries are skipped when
+    /// they are submitted again, so that replaying the inputs that produced them after `position`
+    /// does not log them twice.
+    pub fn recover(&mut self, position: u64) -> io::Result<()> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(position))?;
+        for line in BufReader::new(file).lines() {
+            self.recovered.insert(line?);
+        }
+        Ok(())
+    }
+
+    pub fn flusher(&self) -> TransactionLogFlusher {
+        TransactionLogFlusher {
+            ch: self.ch.clone(),
+        }
     }
 
-    async fn run(mut file: File, mut receiver: UnboundedReceiver<Vec<TransactionLocator>>) {
-        while let Some(id) = receiver.recv().await {
-            writeln!(file, "{:?}", id).expect("Failed to write to transaction log");
+    async fn run(mut file: File, mut receiver: UnboundedReceiver<LogCommand>) {
+        while let Some(command) = receiver.recv().await {
+            match command {
+                LogCommand::Write(line) => {
+                    writeln!(file, "{line}").expect("Failed to write to transaction log")
+                }
+                LogCommand::Flush(sender) => {
+                    sender.send(file.sync_data()).ok();
+                }
+            }
         }
     }
 }
 
+impl TransactionLogFlusher {
+    /// Resolves once all entries submitted before the call are written and synced to disk.
+    pub async fn flush(&self) -> io::Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        if self.ch.send(LogCommand::Flush(sender)).is_err() {
+            // The log is closed, and all entries are written.
+            return Ok(());
+        }
+        receiver.await.unwrap_or(Ok(()))
+    }
+}
+
 impl ProcessedTransactionHandler<TransactionLocator> for TransactionLog {
     fn transaction_processed(&mut self, k: TransactionLocator) {
-        self.ch.send(vec![k]).ok();
+        let line = format!("{:?}", vec![k]);
+        if self.recovered.remove(&line) {
+            return;
+        }
+        self.position += line.len() as u64 + 1;
+        self.ch.send(LogCommand::Write(line)).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::types::BlockReference;
+
+    fn locator(round: u64, offset: u64) -> TransactionLocator {
+        TransactionLocator::new(BlockReference::new_test(0, round), offset)
+    }
+
+    #[tokio::test]
+    async fn recovered_entries_are_not_logged_twice() {
+        let dir = TempDir::new("recovered_entries_are_not_logged_twice").unwrap();
+        let path = dir.path().join("log");
+
+        let mut log = TransactionLog::start(&path).unwrap();
+        log.transaction_processed(locator(1, 0));
+        let position = log.position();
+        log.transaction_processed(locator(1, 1));
+        log.transaction_processed(locator(2, 0));
+        log.flusher().flush().await.unwrap();
+        drop(log);
+
+        // A crash in the middle of writing an entry.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"[A3:").unwrap();
+
+        let mut log = TransactionLog::start(&path).unwrap();
+        log.recover(position).unwrap();
+        log.transaction_processed(locator(1, 1));
+        log.transaction_processed(locator(2, 0));
+        log.transaction_processed(locator(3, 0));
+        log.flusher().flush().await.unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "[A1:0]\n[A1:1]\n[A2:0]\n[A3:0]\n");
+        assert_eq!(log.position(), content.len() as u64);
     }
 }
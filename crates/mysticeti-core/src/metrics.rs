@@ -29,10 +29,21 @@ const LATENCY_SEC_BUCKETS: &[f64] = &[
     0.1, 0.25, 0.5, 0.75, 1., 1.25, 1.5, 1.75, 2., 2.5, 3.0, 4.0, 5., 10., 20., 30., 60., 90.,
 ];
 
+const PROPAGATION_SEC_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.075, 0.1, 0.15, 0.2, 0.3, 0.5, 0.75, 1., 2., 5., 10.,
+];
+
 /// Metrics collected by the benchmark.
 pub const BENCHMARK_DURATION: &str = "benchmark_duration";
 pub const LATENCY_S: &str = "latency_s";
 pub const LATENCY_SQUARED_S: &str = "latency_squared_s";
+pub const TRANSACTION_STAGE_LATENCY_S: &str = "transaction_stage_latency_s";
+
+/// The stages of the lifetime of a transaction measured by `transaction_stage_latency_s`.
+pub const STAGE_SUBMITTED_TO_INCLUDED: &str = "submitted_to_included";
+pub const STAGE_BLOCK_CREATED_TO_QUORUM_RECEIVED: &str = "block_created_to_quorum_received";
+pub const STAGE_INCLUDED_TO_CERTIFIED: &str = "included_to_certified";
+pub const STAGE_CERTIFIED_TO_COMMITTED: &str = "certified_to_committed";
 
 #[derive(Clone)]
 pub struct Metrics {
@@ -42,6 +53,26 @@ pub struct Metrics {
     pub committed_leaders_total: IntCounterVec,
     pub leader_timeout_total: IntCounter,
     pub inter_block_latency_s: HistogramVec,
+    pub block_propagation_latency_s: HistogramVec,
+    pub transaction_stage_latency_s: HistogramVec,
+
+    pub blocks_proposed_total: IntCounterVec,
+    pub blocks_received_total: IntCounterVec,
+    pub blocks_committed_total: IntCounterVec,
+    pub rounds_led_total: IntCounterVec,
+    pub leader_timeout_by_authority_total: IntCounterVec,
+
+    pub threshold_clock_round: IntGauge,
+    pub last_committed_round: IntGauge,
+    pub uncommitted_rounds: IntGauge,
+    pub threshold_clock_stall_ms: IntGauge,
+    pub threshold_clock_contributors: IntGaugeVec,
+    pub threshold_clock_stall_alarms_total: IntCounter,
+    pub highest_received_round: IntGauge,
+    pub committed_subdags_total: IntCounter,
+    pub commit_checkpoint_height: IntGauge,
+    pub commit_checkpoint_digest_prefix: IntGauge,
+    pub peer_connected: IntGaugeVec,
 
     pub block_store_unloaded_blocks: IntCounter,
     pub block_store_loaded_blocks: IntCounter,
@@ -49,6 +80,15 @@ pub struct Metrics {
     pub block_store_cleanup_util: IntCounter,
 
     pub wal_mappings: IntGauge,
+    pub wal_file_size_bytes: IntGauge,
+    pub wal_segments: IntGauge,
+    pub wal_pruned_bytes: IntCounter,
+    pub storage_disk_usage_bytes: IntGauge,
+    pub storage_over_budget: IntGauge,
+    pub block_store_loaded_entries: IntGauge,
+    pub block_store_indexed_rounds: IntGauge,
+    pub suspended_blocks: IntGauge,
+    pub blocks_awaiting_payload: IntGauge,
 
     pub core_lock_util: IntCounter,
     pub core_lock_enqueued: IntCounter,
@@ -62,6 +102,16 @@ pub struct Metrics {
     pub missing_blocks: IntGaugeVec,
     pub block_sync_requests_sent: IntCounterVec,
     pub block_sync_requests_received: IntCounterVec,
+    pub block_sync_range_requests_received: IntCounterVec,
+    pub block_sync_requests_outstanding: IntGauge,
+    pub block_sync_gossip_requests_sent: IntCounterVec,
+    pub block_sync_request_retries: IntCounter,
+
+    pub network_bytes_sent_total: IntCounterVec,
+    pub network_bytes_received_total: IntCounterVec,
+    pub network_messages_sent_total: IntCounterVec,
+    pub network_messages_received_total: IntCounterVec,
+    pub network_queue_depth: IntGaugeVec,
 
     pub transaction_certified_latency: HistogramSender<Duration>,
     pub certificate_committed_latency: HistogramSender<Duration>,
@@ -216,6 +266,22 @@ impl Metrics {
                 LATENCY_SEC_BUCKETS.to_vec(),
                 registry,
             ).unwrap(),
+            block_propagation_latency_s: register_histogram_vec_with_registry!(
+                "block_propagation_latency_s",
+                "Buckets measuring the delay between block creation by its author and its receipt, in seconds",
+                &["authority"],
+                PROPAGATION_SEC_BUCKETS.to_vec(),
+                registry,
+            )
+            .unwrap(),
+            transaction_stage_latency_s: register_histogram_vec_with_registry!(
+                TRANSACTION_STAGE_LATENCY_S,
+                "Buckets measuring the time spent by transactions in each stage, in seconds: from submission to inclusion in a block, from the creation of the block to its receipt by a quorum, from inclusion to certification and from certification to commit",
+                &["stage"],
+                LATENCY_SEC_BUCKETS.to_vec(),
+                registry,
+            )
+            .unwrap(),
             submitted_transactions: register_int_counter_with_registry!(
                 "submitted_transactions",
                 "Total number of submitted transactions",
@@ -229,6 +295,111 @@ impl Metrics {
             )
             .unwrap(),
 
+            blocks_proposed_total: register_int_counter_vec_with_registry!(
+                "blocks_proposed_total",
+                "Total number of blocks proposed per authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            blocks_received_total: register_int_counter_vec_with_registry!(
+                "blocks_received_total",
+                "Total number of blocks received and accepted by the core per authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            blocks_committed_total: register_int_counter_vec_with_registry!(
+                "blocks_committed_total",
+                "Total number of blocks committed (as part of a sub-dag) per authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            rounds_led_total: register_int_counter_vec_with_registry!(
+                "rounds_led_total",
+                "Total number of decided leader slots (committed or skipped) per authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            leader_timeout_by_authority_total: register_int_counter_vec_with_registry!(
+                "leader_timeout_by_authority_total",
+                "Total number of leader timeouts per authority whose leader block was missing",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+
+            threshold_clock_round: register_int_gauge_with_registry!(
+                "threshold_clock_round",
+                "Current round of the threshold clock",
+                registry,
+            )
+            .unwrap(),
+            last_committed_round: register_int_gauge_with_registry!(
+                "last_committed_round",
+                "Round of the last committed leader",
+                registry,
+            )
+            .unwrap(),
+            uncommitted_rounds: register_int_gauge_with_registry!(
+                "uncommitted_rounds",
+                "Number of rounds between the threshold clock round and the last committed leader",
+                registry,
+            )
+            .unwrap(),
+            threshold_clock_stall_ms: register_int_gauge_with_registry!(
+                "threshold_clock_stall_ms",
+                "Time since the threshold clock last advanced to a new round, in milliseconds",
+                registry,
+            )
+            .unwrap(),
+            threshold_clock_contributors: register_int_gauge_vec_with_registry!(
+                "threshold_clock_contributors",
+                "Whether a block of each authority was seen in the current threshold clock round (1) or not (0)",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            threshold_clock_stall_alarms_total: register_int_counter_with_registry!(
+                "threshold_clock_stall_alarms_total",
+                "Number of times the threshold clock stayed in the same round longer than the stall alarm",
+                registry,
+            )
+            .unwrap(),
+            highest_received_round: register_int_gauge_with_registry!(
+                "highest_received_round",
+                "Highest round of a block received from the network",
+                registry,
+            )
+            .unwrap(),
+            committed_subdags_total: register_int_counter_with_registry!(
+                "committed_subdags_total",
+                "Total number of committed sub-dags, i.e. the index of the last commit",
+                registry,
+            )
+            .unwrap(),
+            commit_checkpoint_height: register_int_gauge_with_registry!(
+                "commit_checkpoint_height",
+                "Number of commits covered by the last checkpoint digest",
+                registry,
+            )
+            .unwrap(),
+            commit_checkpoint_digest_prefix: register_int_gauge_with_registry!(
+                "commit_checkpoint_digest_prefix",
+                "First 6 bytes of the last checkpoint digest, to compare checkpoints across nodes",
+                registry,
+            )
+            .unwrap(),
+            peer_connected: register_int_gauge_vec_with_registry!(
+                "peer_connected",
+                "Whether the connection with each peer authority is established (1) or not (0)",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+
             block_store_loaded_blocks: register_int_counter_with_registry!(
                 "block_store_loaded_blocks",
                 "Blocks loaded from wal position in the block store",
@@ -260,6 +431,60 @@ impl Metrics {
                 registry,
             )
             .unwrap(),
+            wal_file_size_bytes: register_int_gauge_with_registry!(
+                "wal_file_size_bytes",
+                "Size of the wal file in bytes",
+                registry,
+            )
+            .unwrap(),
+            wal_segments: register_int_gauge_with_registry!(
+                "wal_segments",
+                "Number of memory-mappable segments spanned by the wal file",
+                registry,
+            )
+            .unwrap(),
+            wal_pruned_bytes: register_int_counter_with_registry!(
+                "wal_pruned_bytes",
+                "Disk space released by pruning committed segments of the wal",
+                registry,
+            )
+            .unwrap(),
+            storage_disk_usage_bytes: register_int_gauge_with_registry!(
+                "storage_disk_usage_bytes",
+                "Disk space used by the storage directory, reported when a disk budget is set",
+                registry,
+            )
+            .unwrap(),
+            storage_over_budget: register_int_gauge_with_registry!(
+                "storage_over_budget",
+                "Set to 1 when the storage directory exceeds its disk budget even after pruning",
+                registry,
+            )
+            .unwrap(),
+            block_store_loaded_entries: register_int_gauge_with_registry!(
+                "block_store_loaded_entries",
+                "Number of block store entries held in memory, i.e. above the cleanup round",
+                registry,
+            )
+            .unwrap(),
+            block_store_indexed_rounds: register_int_gauge_with_registry!(
+                "block_store_indexed_rounds",
+                "Number of rounds covered by the block store index",
+                registry,
+            )
+            .unwrap(),
+            suspended_blocks: register_int_gauge_with_registry!(
+                "suspended_blocks",
+                "Number of blocks suspended by the block manager while waiting for their ancestors",
+                registry,
+            )
+            .unwrap(),
+            blocks_awaiting_payload: register_int_gauge_with_registry!(
+                "blocks_awaiting_payload",
+                "Number of blocks accepted from their header while waiting for their payload",
+                registry,
+            )
+            .unwrap(),
 
             core_lock_util: register_int_counter_with_registry!(
                 "core_lock_util",
@@ -321,6 +546,68 @@ impl Metrics {
                 registry,
             )
             .unwrap(),
+            block_sync_range_requests_received: register_int_counter_vec_with_registry!(
+                "block_sync_range_requests_received",
+                "Number of requests for a range of rounds received per authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            block_sync_requests_outstanding: register_int_gauge_with_registry!(
+                "block_sync_requests_outstanding",
+                "Number of missing blocks requested from peers and not received yet",
+                registry,
+            )
+            .unwrap(),
+            block_sync_gossip_requests_sent: register_int_counter_vec_with_registry!(
+                "block_sync_gossip_requests_sent",
+                "Number of range requests sent per authority for blocks advertised by a peer",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            block_sync_request_retries: register_int_counter_with_registry!(
+                "block_sync_request_retries",
+                "Number of missing blocks requested again after their request timed out",
+                registry,
+            )
+            .unwrap(),
+
+            network_bytes_sent_total: register_int_counter_vec_with_registry!(
+                "network_bytes_sent_total",
+                "Total number of bytes written to the connection with each peer authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            network_bytes_received_total: register_int_counter_vec_with_registry!(
+                "network_bytes_received_total",
+                "Total number of bytes read from the connection with each peer authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            network_messages_sent_total: register_int_counter_vec_with_registry!(
+                "network_messages_sent_total",
+                "Total number of protocol messages sent to each peer authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            network_messages_received_total: register_int_counter_vec_with_registry!(
+                "network_messages_received_total",
+                "Total number of protocol messages received from each peer authority",
+                &["authority"],
+                registry,
+            )
+            .unwrap(),
+            network_queue_depth: register_int_gauge_vec_with_registry!(
+                "network_queue_depth",
+                "Number of messages waiting in the inbound or outbound queue of each peer connection",
+                &["authority", "direction"],
+                registry,
+            )
+            .unwrap(),
 
             utilization_timer: register_int_counter_vec_with_registry!(
                 "utilization_timer",
@@ -447,8 +734,8 @@ impl AsPrometheusMetric for usize {
 }
 
 impl MetricReporter {
-    pub fn start(self) {
-        runtime::Handle::current().spawn(self.run());
+    pub fn start(self) -> runtime::JoinHandle<()> {
+        runtime::Handle::current().spawn(self.run())
     }
 
     pub fn clear_receive_all(&mut self) {
@@ -3,6 +3,8 @@
 
 use std::{
     collections::HashMap,
+    future::Future,
+    ops::RangeInclusive,
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
@@ -13,8 +15,9 @@ use std::{
 use futures::future::join_all;
 use tokio::{
     select,
-    sync::{mpsc, oneshot, Notify},
+    sync::{mpsc, oneshot, watch, Notify},
 };
+use tracing::Instrument;
 
 use crate::{
     block_handler::BlockHandler,
@@ -25,16 +28,23 @@ use crate::{
     core_thread::CoreThreadDispatcher,
     metrics::Metrics,
     network::{Connection, Network, NetworkMessage},
-    runtime::{self, timestamp_utc, Handle, JoinError, JoinHandle},
+    reload::LiveParameters,
+    runtime::{self, spawn_named, timestamp_utc, JoinError, JoinHandle},
     syncer::{CommitObserver, Syncer, SyncerSignals},
     synchronizer::{BlockDisseminator, BlockFetcher, SynchronizerParameters},
-    types::{format_authority_index, AuthorityIndex},
+    types::{format_authority_index, AuthorityIndex, RoundNumber},
     wal::WalSyncer,
 };
 
 /// The maximum number of blocks that can be requested in a single message.
 pub const MAXIMUM_BLOCK_REQUEST: usize = 10;
 
+/// The maximum number of rounds that can be requested in a single range request.
+pub const MAXIMUM_ROUND_RANGE: RoundNumber = 100;
+
+/// How long to wait on shutdown for the messages queued for peers to be written.
+const CONNECTION_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
 pub struct NetworkSyncer<H: BlockHandler, C: CommitObserver> {
     inner: Arc<NetworkSyncerInner<H, C>>,
     main_task: JoinHandle<()>,
@@ -50,9 +60,14 @@ pub struct NetworkSyncerInner<H: BlockHandler, C: CommitObserver> {
     stop: mpsc::Sender<()>,
     epoch_close_signal: mpsc::Sender<()>,
     pub epoch_closing_time: Arc<AtomicU64>,
+    parameters: watch::Receiver<LiveParameters>,
+    gossip_interval: Duration,
+    /// Send the headers of our own blocks ahead of the blocks, see `NodeParameters::header_first`.
+    pub header_first: bool,
 }
 
 impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C> {
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         network: Network,
         mut core: Core<H>,
@@ -61,15 +76,16 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
         shutdown_grace_period: Duration,
         metrics: Arc<Metrics>,
         public_config: &NodePublicConfig,
+        parameters: watch::Receiver<LiveParameters>,
     ) -> Self {
         let authority_index = core.authority();
-        let handle = Handle::current();
         let notify = Arc::new(Notify::new());
         // todo - ugly, probably need to merge syncer and core
         let (committed, state) = core.take_recovered_committed_blocks();
         commit_observer.recover_committed(committed, state);
         let committee = core.committee().clone();
         let wal_syncer = core.wal_syncer();
+        let wal_syncer_cores = core.cpu_affinity().wal_syncer.clone();
         let block_store = core.block_store().clone();
         let epoch_closing_time = core.epoch_closing_time();
         let mut syncer = Syncer::new(
@@ -93,23 +109,36 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
             stop: stop_sender.clone(),
             epoch_close_signal: epoch_sender.clone(),
             epoch_closing_time,
+            parameters,
+            gossip_interval: public_config.parameters.block_sync.gossip_interval,
+            header_first: public_config.parameters.header_first,
         });
         let block_fetcher = Arc::new(BlockFetcher::start(
             authority_index,
             inner.clone(),
+            public_config.parameters.block_sync.clone(),
             metrics.clone(),
             public_config.parameters.enable_synchronizer,
         ));
-        let main_task = handle.spawn(Self::run(
-            authority_index,
-            network,
-            inner.clone(),
-            epoch_receiver,
-            shutdown_grace_period,
-            block_fetcher,
-            metrics.clone(),
-        ));
-        let syncer_task = AsyncWalSyncer::start(wal_syncer, stop_sender, epoch_sender);
+        let main_task = spawn_named(
+            "net-syncer",
+            Self::run(
+                authority_index,
+                network,
+                inner.clone(),
+                epoch_receiver,
+                shutdown_grace_period,
+                block_fetcher,
+                metrics.clone(),
+            ),
+        );
+        let syncer_task = AsyncWalSyncer::start(
+            wal_syncer,
+            stop_sender,
+            epoch_sender,
+            public_config.parameters.wal_sync.interval(),
+            wal_syncer_cores,
+        );
         Self {
             inner,
             main_task,
@@ -118,15 +147,27 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
         }
     }
 
+    /// Stop proposing and receiving blocks, drain the connections to peers and stop the core
+    /// thread. Resolves once all tasks have exited and the WAL is synced to disk.
     pub async fn shutdown(self) -> Syncer<H, Arc<Notify>, C> {
         drop(self.stop);
-        // todo - wait for network shutdown as well
         self.main_task.await.ok();
         self.syncer_task.await.ok();
         let Ok(inner) = Arc::try_unwrap(self.inner) else {
             panic!("Shutdown failed - not all resources are freed after main task is completed");
         };
-        inner.syncer.stop()
+        let syncer = inner.syncer.stop();
+        syncer
+            .core()
+            .wal_syncer()
+            .sync()
+            .expect("Failed to sync wal");
+        syncer
+    }
+
+    /// Resolves once the core thread has exited, see `CoreThreadDispatcher::stopped`.
+    pub fn core_stopped(&self) -> impl Future<Output = ()> + 'static {
+        self.inner.syncer.stopped()
     }
 
     async fn run(
@@ -139,13 +180,11 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
         metrics: Arc<Metrics>,
     ) {
         let mut connections: HashMap<usize, JoinHandle<Option<()>>> = HashMap::new();
-        let handle = Handle::current();
-        let leader_timeout_task = handle.spawn(Self::leader_timeout_task(
-            inner.clone(),
-            epoch_close_signal,
-            shutdown_grace_period,
-        ));
-        let cleanup_task = handle.spawn(Self::cleanup_task(inner.clone()));
+        let leader_timeout_task = spawn_named(
+            "leader-timeout",
+            Self::leader_timeout_task(inner.clone(), epoch_close_signal, shutdown_grace_period),
+        );
+        let cleanup_task = spawn_named("cleanup", Self::cleanup_task(inner.clone()));
         while let Some(connection) = inner.recv_or_stopped(network.connection_receiver()).await {
             let peer_id = connection.peer_id;
             if let Some(task) = connections.remove(&peer_id) {
@@ -157,13 +196,16 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
             let authority = peer_id as AuthorityIndex;
             block_fetcher.register_authority(authority, sender).await;
 
-            let task = handle.spawn(Self::connection_task(
-                self_peer,
-                connection,
-                inner.clone(),
-                block_fetcher.clone(),
-                metrics.clone(),
-            ));
+            let task = spawn_named(
+                &format!("connection-{peer_id}"),
+                Self::connection_task(
+                    self_peer,
+                    connection,
+                    inner.clone(),
+                    block_fetcher.clone(),
+                    metrics.clone(),
+                ),
+            );
             connections.insert(peer_id, task);
         }
         join_all(
@@ -176,6 +218,7 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
             .unwrap_or_else(|_| panic!("Failed to drop all connections"))
             .shutdown()
             .await;
+        network.shutdown(CONNECTION_DRAIN_TIMEOUT).await;
     }
 
     async fn connection_task(
@@ -198,23 +241,34 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
             self_peer,
             connection.peer_id as AuthorityIndex,
             connection.sender.clone(),
+            connection.priority_sender.clone(),
             inner.clone(),
             SynchronizerParameters::default(),
             metrics.clone(),
         );
 
         let id = connection.peer_id as AuthorityIndex;
+        let gossip = spawn_named(
+            &format!("gossip-highest-rounds-{id}"),
+            Self::gossip_task(inner.clone(), connection.sender.clone()),
+        );
+        // The highest rounds last advertised by the peer.
+        let mut advertised: Option<Vec<RoundNumber>> = None;
         inner.syncer.authority_connection(id, true).await;
+        let connected = metrics.peer_connected.with_label_values(&[&id.to_string()]);
+        connected.set(1);
 
         let peer = format_authority_index(id);
-        while let Some(message) = inner.recv_or_stopped(&mut connection.receiver).await {
+        'messages: while let Some(message) = inner.recv_or_stopped(&mut connection.receiver).await {
             match message {
                 NetworkMessage::SubscribeOwnFrom(round) => {
                     disseminator.disseminate_own_blocks(round).await
                 }
                 NetworkMessage::Block(block) => {
                     tracing::debug!("Received {} from {}", block.reference(), peer);
-                    if let Err(e) = block.verify(&inner.committee) {
+                    let span =
+                        tracing::debug_span!("receive_block", block = %block.reference(), %peer);
+                    if let Err(e) = span.in_scope(|| block.verify(&inner.committee)) {
                         tracing::warn!(
                             "Rejected incorrect block {} from {}: {:?}",
                             block.reference(),
@@ -224,7 +278,19 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
                         // Terminate connection upon receiving incorrect block.
                         break;
                     }
-                    inner.syncer.add_blocks(vec![block]).await;
+                    // The creation timestamp is set by the author, so this also includes
+                    // any clock skew between the author and us.
+                    let propagation_delay = timestamp_utc()
+                        .checked_sub(block.meta_creation_time())
+                        .unwrap_or_default();
+                    metrics
+                        .block_propagation_latency_s
+                        .with_label_values(&[&block.author().to_string()])
+                        .observe(propagation_delay.as_secs_f64());
+                    if block.round() as i64 > metrics.highest_received_round.get() {
+                        metrics.highest_received_round.set(block.round() as i64);
+                    }
+                    inner.syncer.add_blocks(vec![block]).instrument(span).await;
                 }
                 NetworkMessage::RequestBlocks(references) => {
                     if references.len() > MAXIMUM_BLOCK_REQUEST {
@@ -240,24 +306,80 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
                         break;
                     }
                 }
+                NetworkMessage::RequestBlockRange(rounds, author) => {
+                    if rounds.is_empty() || rounds.end() - rounds.start() >= MAXIMUM_ROUND_RANGE {
+                        // Terminate connection on receiving invalid message.
+                        break;
+                    }
+                    let authority = connection.peer_id as AuthorityIndex;
+                    if disseminator
+                        .send_block_range(authority, rounds, author)
+                        .await
+                        .is_none()
+                    {
+                        break;
+                    }
+                }
+                NetworkMessage::HighestRounds(rounds) => {
+                    if rounds.len() != inner.committee.len() {
+                        // Terminate connection on receiving invalid message.
+                        break;
+                    }
+                    // Blocks advertised at the previous exchange and still missing did not
+                    // reach us on their own, request them from the peer.
+                    let ours = inner.block_store.last_seen_by_authorities();
+                    let stalled = advertised
+                        .replace(rounds)
+                        .map(|previous| stalled_ranges(&previous, &ours))
+                        .unwrap_or_default();
+                    for (author, rounds) in stalled {
+                        metrics
+                            .block_sync_gossip_requests_sent
+                            .with_label_values(&[&author.to_string()])
+                            .inc();
+                        let message = NetworkMessage::RequestBlockRange(rounds, Some(author));
+                        if connection.sender.send(message).await.is_err() {
+                            break 'messages;
+                        }
+                    }
+                }
                 NetworkMessage::BlockNotFound(_references) => {
                     // TODO: leverage this signal to request blocks from other peers
                 }
             }
         }
+        gossip.abort();
+        gossip.await.ok();
         inner.syncer.authority_connection(id, false).await;
+        connected.set(0);
         disseminator.shutdown().await;
         block_fetcher.remove_authority(id).await;
         None
     }
 
+    /// Periodically advertise to a peer the highest round we have from every authority.
+    async fn gossip_task(
+        inner: Arc<NetworkSyncerInner<H, C>>,
+        sender: mpsc::Sender<NetworkMessage>,
+    ) -> Option<()> {
+        loop {
+            runtime::sleep(inner.gossip_interval).await;
+            let rounds = inner.block_store.last_seen_by_authorities();
+            sender
+                .send(NetworkMessage::HighestRounds(rounds))
+                .await
+                .ok()?;
+        }
+    }
+
     async fn leader_timeout_task(
         inner: Arc<NetworkSyncerInner<H, C>>,
         mut epoch_close_signal: mpsc::Receiver<()>,
         shutdown_grace_period: Duration,
     ) -> Option<()> {
-        let leader_timeout = Duration::from_secs(1);
         loop {
+            // Read on every iteration so that a reloaded timeout applies to the next round.
+            let leader_timeout = inner.parameters.borrow().leader_timeout;
             let notified = inner.notify.notified();
             let round = inner
                 .block_store
@@ -313,6 +435,12 @@ impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncer<H, C>
     pub async fn await_completion(self) -> Result<(), JoinError> {
         self.main_task.await
     }
+
+    /// Wait for the main task to exit, e.g. after the epoch closed. The syncer must not be shut
+    /// down once this resolved.
+    pub async fn completed(&mut self) -> Result<(), JoinError> {
+        (&mut self.main_task).await
+    }
 }
 
 impl<H: BlockHandler + 'static, C: CommitObserver + 'static> NetworkSyncerInner<H, C> {
@@ -353,6 +481,8 @@ impl SyncerSignals for Arc<Notify> {
 
 pub struct AsyncWalSyncer {
     wal_syncer: WalSyncer,
+    /// Sync at this interval, otherwise only sync on stop.
+    interval: Option<Duration>,
     stop: mpsc::Sender<()>,
     epoch_signal: mpsc::Sender<()>,
     _sender: oneshot::Sender<()>,
@@ -365,10 +495,13 @@ impl AsyncWalSyncer {
         wal_syncer: WalSyncer,
         stop: mpsc::Sender<()>,
         epoch_signal: mpsc::Sender<()>,
+        interval: Option<Duration>,
+        cores: Vec<usize>,
     ) -> oneshot::Receiver<()> {
         let (sender, receiver) = oneshot::channel();
         let this = Self {
             wal_syncer,
+            interval,
             stop,
             epoch_signal,
             _sender: sender,
@@ -376,7 +509,10 @@ impl AsyncWalSyncer {
         };
         std::thread::Builder::new()
             .name("wal-syncer".to_string())
-            .spawn(move || this.run())
+            .spawn(move || {
+                crate::affinity::pin_thread("wal-syncer", &cores);
+                this.run()
+            })
             .expect("Failed to spawn wal-syncer");
         receiver
     }
@@ -386,6 +522,8 @@ impl AsyncWalSyncer {
         _wal_syncer: WalSyncer,
         _stop: mpsc::Sender<()>,
         _epoch_signal: mpsc::Sender<()>,
+        _interval: Option<Duration>,
+        _cores: Vec<usize>,
     ) -> oneshot::Receiver<()> {
         oneshot::channel().1
     }
@@ -402,11 +540,20 @@ impl AsyncWalSyncer {
 
     // Returns true to stop the task
     async fn wait_next(&mut self) -> bool {
+        let interval = self.interval;
+        let interval = async move {
+            match interval {
+                Some(interval) => runtime::sleep(interval).await,
+                None => futures::future::pending().await,
+            }
+        };
         select! {
-            _wait = runtime::sleep(Duration::from_secs(1)) => {
+            _wait = interval => {
                 false
             }
             _signal = self.stop.send(()) => {
+                // Persist everything written before the stop signal.
+                self.wal_syncer.sync().expect("Failed to sync wal");
                 true
             }
             _ = self.epoch_signal.send(()) => {
@@ -417,12 +564,41 @@ impl AsyncWalSyncer {
     }
 }
 
+/// The round ranges, per authority, of the blocks advertised by a peer that are above the highest
+/// rounds we have. Each range spans at most `MAXIMUM_ROUND_RANGE` rounds.
+fn stalled_ranges(
+    advertised: &[RoundNumber],
+    ours: &[RoundNumber],
+) -> Vec<(AuthorityIndex, RangeInclusive<RoundNumber>)> {
+    advertised
+        .iter()
+        .zip(ours)
+        .enumerate()
+        .filter(|(_, (theirs, ours))| theirs > ours)
+        .map(|(authority, (&theirs, &ours))| {
+            let to = theirs.min(ours + MAXIMUM_ROUND_RANGE);
+            (authority as AuthorityIndex, ours + 1..=to)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
+    use super::{stalled_ranges, MAXIMUM_ROUND_RANGE};
     use crate::test_util::{check_commits, network_syncers};
 
+    #[test]
+    fn request_stalled_ranges() {
+        let advertised = [5, 10, 2, 500];
+        let ours = [5, 7, 3, 100];
+        assert_eq!(
+            stalled_ranges(&advertised, &ours),
+            vec![(1, 8..=10), (3, 101..=100 + MAXIMUM_ROUND_RANGE)]
+        );
+    }
+
     #[tokio::test]
     async fn test_network_sync() {
         let network_syncers = network_syncers(4).await;
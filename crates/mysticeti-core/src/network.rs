@@ -1,12 +1,20 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{collections::HashMap, io, net::SocketAddr, ops::Range, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+    time::Duration,
+};
 
 use futures::{
-    future::{select, select_all, Either},
+    future::{join_all, select, select_all, Either},
     FutureExt,
 };
+use prometheus::{IntCounter, IntGauge};
 use rand::{prelude::ThreadRng, thread_rng, Rng, RngCore};
 use serde::{Deserialize, Serialize};
 use tokio::{
@@ -15,17 +23,17 @@ use tokio::{
         tcp::{OwnedReadHalf, OwnedWriteHalf},
         TcpListener, TcpSocket, TcpStream,
     },
-    runtime::Handle,
     select,
-    sync::mpsc,
+    sync::{mpsc, watch},
     time::Instant,
 };
 
 use crate::{
+    buffer_pool::BufferPool,
     config::NodePublicConfig,
     data::Data,
     metrics::{print_network_address_table, Metrics},
-    runtime,
+    runtime::{self, spawn_named, JoinHandle},
     stat::HistogramSender,
     types::{AuthorityIndex, BlockReference, RoundNumber, StatementBlock},
 };
@@ -38,17 +46,28 @@ pub enum NetworkMessage {
     Block(Data<StatementBlock>),
     /// Request a few specific block references (this is not indented for large requests).
     RequestBlocks(Vec<BlockReference>),
+    /// Request all the blocks of a range of rounds, only those of one authority if specified.
+    /// The range spans at most `net_sync::MAXIMUM_ROUND_RANGE` rounds.
+    RequestBlockRange(RangeInclusive<RoundNumber>, Option<AuthorityIndex>),
+    /// The highest round of the blocks of every authority held by the sender.
+    HighestRounds(Vec<RoundNumber>),
     /// Indicate that a requested block is not found.
     BlockNotFound(Vec<BlockReference>),
 }
 
 pub struct Network {
     connection_receiver: mpsc::Receiver<Connection>,
+    stop: watch::Sender<bool>,
+    workers: Vec<JoinHandle<Option<()>>>,
+    server: Option<JoinHandle<()>>,
 }
 
 pub struct Connection {
     pub peer_id: usize,
     pub sender: mpsc::Sender<NetworkMessage>,
+    /// Messages sent on this lane are written ahead of those queued on `sender`, e.g. to keep
+    /// block headers from waiting behind bulk payloads.
+    pub priority_sender: mpsc::Sender<NetworkMessage>,
     pub receiver: mpsc::Receiver<NetworkMessage>,
 }
 
@@ -57,6 +76,9 @@ impl Network {
     pub(crate) fn new_from_raw(connection_receiver: mpsc::Receiver<Connection>) -> Self {
         Self {
             connection_receiver,
+            stop: watch::channel(false).0,
+            workers: vec![],
+            server: None,
         }
     }
 
@@ -92,8 +114,10 @@ impl Network {
             .expect("Failed to bind to local socket");
         let mut worker_senders: HashMap<SocketAddr, mpsc::UnboundedSender<TcpStream>> =
             HashMap::default();
-        let handle = Handle::current();
         let (connection_sender, connection_receiver) = mpsc::channel(16);
+        let (stop, stop_receiver) = watch::channel(false);
+        let buffer_pool = BufferPool::default();
+        let mut workers = Vec::with_capacity(addresses.len());
         for (id, address) in addresses.iter().enumerate() {
             if id == our_id {
                 continue;
@@ -104,7 +128,8 @@ impl Network {
                 "Duplicated address {} in list",
                 address
             );
-            handle.spawn(
+            let worker = spawn_named(
+                &format!("network-worker-{id}"),
                 Worker {
                     our_id,
                     peer: *address,
@@ -112,12 +137,17 @@ impl Network {
                     connection_sender: connection_sender.clone(),
                     bind_addr: bind_addr(local_addr),
                     active_immediately: id < our_id,
-                    latency_sender: metrics.connection_latency_sender.get(id).expect("Can not locate connection_latency_sender metric - did you initialize metrics with correct committee?").clone()
+                    latency_sender: metrics.connection_latency_sender.get(id).expect("Can not locate connection_latency_sender metric - did you initialize metrics with correct committee?").clone(),
+                    metrics: ConnectionMetrics::new(&metrics, id),
+                    buffer_pool: buffer_pool.clone(),
+                    stop: stop_receiver.clone(),
                 }
                 .run(receiver),
             );
+            workers.push(worker);
         }
-        handle.spawn(
+        let server = spawn_named(
+            "network-server",
             Server {
                 server,
                 worker_senders,
@@ -126,6 +156,30 @@ impl Network {
         );
         Self {
             connection_receiver,
+            stop,
+            workers,
+            server: Some(server),
+        }
+    }
+
+    /// Stop connecting to peers and close the open connections once the messages already queued
+    /// for them are written. Connections still open after `timeout` are dropped.
+    pub async fn shutdown(mut self, timeout: Duration) {
+        self.stop.send_replace(true);
+        let drained = select! {
+            _drained = join_all(self.workers.iter_mut()) => true,
+            _timeout = runtime::sleep(timeout) => false,
+        };
+        if !drained {
+            tracing::warn!("Dropping connections that did not drain within {timeout:?}");
+            // Some workers may have completed already, so the handles can not be awaited again.
+            for worker in &self.workers {
+                worker.abort();
+            }
+        }
+        if let Some(server) = self.server {
+            server.abort();
+            server.await.ok();
         }
     }
 }
@@ -182,14 +236,60 @@ struct Worker {
     bind_addr: SocketAddr,
     active_immediately: bool,
     latency_sender: HistogramSender<Duration>,
+    metrics: ConnectionMetrics,
+    /// Shared by the workers of all peers to serialize outgoing messages.
+    buffer_pool: BufferPool,
+    stop: watch::Receiver<bool>,
 }
 
 struct WorkerConnection {
     our_id: usize,
     sender: mpsc::Sender<NetworkMessage>,
     receiver: mpsc::Receiver<NetworkMessage>,
+    priority_receiver: mpsc::Receiver<NetworkMessage>,
+    /// The sending end of `receiver`, to report the depth of the outbound queue. It is weak so
+    /// that the worker does not keep the queue open once the connection is dropped.
+    outbound: mpsc::WeakSender<NetworkMessage>,
     peer_id: usize,
     latency_sender: HistogramSender<Duration>,
+    metrics: ConnectionMetrics,
+    buffer_pool: BufferPool,
+}
+
+/// Traffic metrics of the connection with a single peer, resolved once per worker
+/// so that the read and write loops do not look up labels on every message.
+#[derive(Clone)]
+struct ConnectionMetrics {
+    bytes_sent: IntCounter,
+    bytes_received: IntCounter,
+    messages_sent: IntCounter,
+    messages_received: IntCounter,
+    inbound_queue_depth: IntGauge,
+    outbound_queue_depth: IntGauge,
+}
+
+impl ConnectionMetrics {
+    fn new(metrics: &Metrics, peer_id: usize) -> Self {
+        let peer = peer_id.to_string();
+        Self {
+            bytes_sent: metrics.network_bytes_sent_total.with_label_values(&[&peer]),
+            bytes_received: metrics
+                .network_bytes_received_total
+                .with_label_values(&[&peer]),
+            messages_sent: metrics
+                .network_messages_sent_total
+                .with_label_values(&[&peer]),
+            messages_received: metrics
+                .network_messages_received_total
+                .with_label_values(&[&peer]),
+            inbound_queue_depth: metrics
+                .network_queue_depth
+                .with_label_values(&[&peer, "inbound"]),
+            outbound_queue_depth: metrics
+                .network_queue_depth
+                .with_label_values(&[&peer, "outbound"]),
+        }
+    }
 }
 
 impl Worker {
@@ -207,11 +307,17 @@ impl Worker {
         loop {
             match select(work, receiver.recv().boxed()).await {
                 Either::Left((_work, _receiver)) => {
+                    if self.stopped() {
+                        return None;
+                    }
                     let delay = sample_delay(Duration::from_secs(1)..Duration::from_secs(5));
                     work = self.connect_and_handle(delay, self.peer).boxed();
                 }
-                Either::Right((received, _work)) => {
-                    if let Some(received) = received {
+                Either::Right((received, pending)) => {
+                    if received.is_some() && self.stopped() {
+                        // Let the current connection drain rather than replacing it.
+                        work = pending;
+                    } else if let Some(received) = received {
                         tracing::debug!("Replaced connection for {}", self.peer_id);
                         work = self.handle_passive_stream(received).boxed();
                     } else {
@@ -223,6 +329,10 @@ impl Worker {
         }
     }
 
+    fn stopped(&self) -> bool {
+        *self.stop.borrow()
+    }
+
     async fn connect_and_handle(&self, delay: Duration, peer: SocketAddr) -> io::Result<()> {
         // this is critical to avoid race between active and passive connections
         runtime::sleep(delay).await;
@@ -275,27 +385,45 @@ impl Worker {
             our_id,
             sender,
             receiver,
+            priority_receiver,
+            outbound,
             peer_id,
             latency_sender,
+            metrics,
+            buffer_pool,
         } = connection;
         tracing::debug!("Connected to {}", peer_id);
         let (reader, writer) = stream.into_split();
         let (pong_sender, pong_receiver) = mpsc::channel(16);
-        let write_fut =
-            Self::handle_write_stream(our_id, writer, receiver, pong_receiver, latency_sender)
-                .boxed();
-        let read_fut = Self::handle_read_stream(reader, sender, pong_sender).boxed();
+        let write_fut = Self::handle_write_stream(
+            our_id,
+            writer,
+            receiver,
+            priority_receiver,
+            outbound,
+            pong_receiver,
+            latency_sender,
+            metrics.clone(),
+            buffer_pool,
+        )
+        .boxed();
+        let read_fut = Self::handle_read_stream(reader, sender, pong_sender, metrics).boxed();
         let (r, _, _) = select_all([write_fut, read_fut]).await;
         tracing::debug!("Disconnected from {}", peer_id);
         r
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_write_stream(
         our_id: usize,
         mut writer: OwnedWriteHalf,
         mut receiver: mpsc::Receiver<NetworkMessage>,
+        mut priority_receiver: mpsc::Receiver<NetworkMessage>,
+        outbound: mpsc::WeakSender<NetworkMessage>,
         mut pong_receiver: mpsc::Receiver<i64>,
         latency_sender: HistogramSender<Duration>,
+        metrics: ConnectionMetrics,
+        buffer_pool: BufferPool,
     ) -> io::Result<()> {
         let start = Instant::now();
         let mut ping_deadline = start + PING_INTERVAL;
@@ -309,7 +437,9 @@ impl Worker {
         }
 
         loop {
+            // Branches are polled in order, so that priority messages are written first.
             select! {
+                biased;
                 _deadline = tokio::time::sleep_until(ping_deadline) => {
                     ping_deadline += PING_INTERVAL;
                     let ping_time = start.elapsed().as_micros() as i64;
@@ -317,6 +447,7 @@ impl Worker {
                     assert!(ping_time > 0);
                     let ping = encode_ping(ping_time);
                     writer.write_all(&ping).await?;
+                    metrics.bytes_sent.inc_by(PING_SIZE as u64);
                 }
                 received = pong_receiver.recv() => {
                     // We have an embedded ping-pong protocol for measuring RTT:
@@ -336,6 +467,7 @@ impl Worker {
                             Some(pong) => {
                                 let pong = encode_ping(pong);
                                 writer.write_all(&pong).await?;
+                                metrics.bytes_sent.inc_by(PING_SIZE as u64);
                             },
                             None => {
                                 tracing::warn!("Invalid ping: {ping}");
@@ -364,26 +496,53 @@ impl Worker {
                         }
                     }
                 }
+                received = priority_receiver.recv() => {
+                    let Some(message) = received else {return Ok(())};
+
+                    if drop_message(start, our_id) {
+                        continue;
+                    }
+
+                    Self::write_message(&mut writer, &message, &buffer_pool, &metrics).await?;
+                }
                 received = receiver.recv() => {
                     // todo - pass signal to break main loop
                     let Some(message) = received else {return Ok(())};
+                    if let Some(sender) = outbound.upgrade() {
+                        metrics
+                            .outbound_queue_depth
+                            .set((sender.max_capacity() - sender.capacity()) as i64);
+                    }
 
                     if drop_message(start, our_id) {
                         continue;
                     }
 
-                    let serialized = bincode::serialize(&message).expect("Serialization should not fail");
-                    writer.write_u32(serialized.len() as u32).await?;
-                    writer.write_all(&serialized).await?;
+                    Self::write_message(&mut writer, &message, &buffer_pool, &metrics).await?;
                 }
             }
         }
     }
 
+    async fn write_message(
+        writer: &mut OwnedWriteHalf,
+        message: &NetworkMessage,
+        buffer_pool: &BufferPool,
+        metrics: &ConnectionMetrics,
+    ) -> io::Result<()> {
+        let serialized = buffer_pool.serialize(message);
+        writer.write_u32(serialized.len() as u32).await?;
+        writer.write_all(&serialized).await?;
+        metrics.bytes_sent.inc_by(4 + serialized.len() as u64);
+        metrics.messages_sent.inc();
+        Ok(())
+    }
+
     async fn handle_read_stream(
         mut stream: OwnedReadHalf,
         sender: mpsc::Sender<NetworkMessage>,
         pong_sender: mpsc::Sender<i64>,
+        metrics: ConnectionMetrics,
     ) -> io::Result<()> {
         // stdlib has a special fast implementation for generating n-size byte vectors,
         // see impl SpecFromElem for u8
@@ -400,6 +559,7 @@ impl Worker {
                 let buf = &mut buf[..PING_SIZE - 4 /*Already read size(u32)*/];
                 let read = stream.read_exact(buf).await?;
                 assert_eq!(read, buf.len());
+                metrics.bytes_received.inc_by(PING_SIZE as u64);
                 let pong = decode_ping(buf);
                 if pong_sender.send(pong).await.is_err() {
                     return Ok(()); // write stream closed
@@ -409,12 +569,17 @@ impl Worker {
             let buf = &mut buf[..size as usize];
             let read = stream.read_exact(buf).await?;
             assert_eq!(read, buf.len());
+            metrics.bytes_received.inc_by(4 + size as u64);
             match bincode::deserialize::<NetworkMessage>(buf) {
                 Ok(message) => {
+                    metrics.messages_received.inc();
                     if sender.send(message).await.is_err() {
                         // todo - pass signal to break main loop
                         return Ok(());
                     }
+                    metrics
+                        .inbound_queue_depth
+                        .set((sender.max_capacity() - sender.capacity()) as i64);
                 }
                 Err(err) => {
                     tracing::warn!("Failed to deserialize: {}", err);
@@ -427,9 +592,12 @@ impl Worker {
     async fn make_connection(&self) -> Option<WorkerConnection> {
         let (network_in_sender, network_in_receiver) = mpsc::channel(16);
         let (network_out_sender, network_out_receiver) = mpsc::channel(16);
+        let (priority_out_sender, priority_out_receiver) = mpsc::channel(16);
+        let outbound = network_out_sender.downgrade();
         let connection = Connection {
             peer_id: self.peer_id,
             sender: network_out_sender,
+            priority_sender: priority_out_sender,
             receiver: network_in_receiver,
         };
         self.connection_sender.send(connection).await.ok()?;
@@ -437,8 +605,12 @@ impl Worker {
             our_id: self.our_id,
             sender: network_in_sender,
             receiver: network_out_receiver,
+            priority_receiver: priority_out_receiver,
+            outbound,
             peer_id: self.peer_id,
             latency_sender: self.latency_sender.clone(),
+            metrics: self.metrics.clone(),
+            buffer_pool: self.buffer_pool.clone(),
         })
     }
 }
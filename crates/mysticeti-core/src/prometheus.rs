@@ -1,26 +1,233 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::net::SocketAddr;
+use std::{fs::File, io, io::BufReader, net, net::SocketAddr, path::Path, sync::Arc};
 
-use axum::{http::StatusCode, routing::get, Extension, Router, Server};
-use prometheus::{Registry, TextEncoder};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router, Server,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::server::conn::Http;
+use prometheus::{Encoder, Registry, TextEncoder};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use tokio::net::TcpListener;
+use tokio_rustls::{
+    rustls::{Certificate, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
 
-use crate::runtime::{Handle, JoinHandle};
+use crate::{
+    checkpoint::Checkpoints,
+    committee::Committee,
+    config::{
+        BasicAuthConfig,
+        MetricsPushConfig,
+        MetricsServerConfig,
+        ReloadableConfig,
+        TlsConfig,
+    },
+    dag_export::{DagExporter, DagFormat},
+    metrics::Metrics,
+    reload::ConfigReloader,
+    runtime::{sleep, Handle, JoinHandle},
+    types::{AuthorityIndex, RoundNumber, Stake},
+};
 
 pub const METRICS_ROUTE: &str = "/metrics";
+pub const HEALTH_ROUTE: &str = "/health";
+pub const READY_ROUTE: &str = "/ready";
+pub const STATUS_ROUTE: &str = "/status";
+pub const CONFIG_ROUTE: &str = "/config";
+pub const CHECKPOINT_ROUTE: &str = "/checkpoint";
+pub const THRESHOLD_CLOCK_ROUTE: &str = "/threshold_clock";
+pub const DAG_ROUTE: &str = "/dag";
+
+/// The node is considered to be catching up while it receives blocks that are more than
+/// this many rounds ahead of its own threshold clock.
+const SYNC_LAG_ROUNDS: RoundNumber = 10;
+
+/// The number of rounds of the DAG exported when the request does not set the first round.
+const DEFAULT_DAG_ROUNDS: RoundNumber = 20;
+/// The maximum number of rounds of the DAG exported by a single request.
+const MAX_DAG_ROUNDS: RoundNumber = 500;
 
 pub fn start_prometheus_server(
     address: SocketAddr,
     registry: &Registry,
-) -> JoinHandle<Result<(), hyper::Error>> {
-    let app = Router::new()
+    admin: AdminState,
+    config: &MetricsServerConfig,
+) -> io::Result<JoinHandle<Result<(), hyper::Error>>> {
+    let mut app = Router::new()
         .route(METRICS_ROUTE, get(metrics))
-        .layer(Extension(registry.clone()));
+        .route(HEALTH_ROUTE, get(health))
+        .route(READY_ROUTE, get(ready))
+        .route(STATUS_ROUTE, get(status))
+        .route(CONFIG_ROUTE, get(config).post(reload_config))
+        .route(CHECKPOINT_ROUTE, get(checkpoint))
+        .route(THRESHOLD_CLOCK_ROUTE, get(threshold_clock))
+        .route(DAG_ROUTE, get(dag))
+        .layer(Extension(registry.clone()))
+        .layer(Extension(admin));
+    if let Some(credentials) = &config.basic_auth {
+        let expected = Arc::new(basic_auth_header(credentials));
+        app = app.layer(middleware::from_fn_with_state(expected, basic_auth));
+    }
+
+    // Bind before spawning the server, so that binding failures are returned to the caller.
+    let listener = net::TcpListener::bind(address)?;
+    listener.set_nonblocking(true)?;
+    let handle = match &config.tls {
+        Some(tls) => {
+            let acceptor = TlsAcceptor::from(load_tls_config(tls)?);
+            let listener = TcpListener::from_std(listener)?;
+            tracing::info!("Prometheus server booted on {address} (tls)");
+            Handle::current().spawn(serve_tls(listener, app, acceptor))
+        }
+        None => {
+            let server = Server::from_tcp(listener).map_err(io::Error::other)?;
+            tracing::info!("Prometheus server booted on {address}");
+            Handle::current().spawn(async move { server.serve(app.into_make_service()).await })
+        }
+    };
+    Ok(handle)
+}
+
+/// Periodically push all metrics of the registry to a Prometheus Pushgateway, grouped by job
+/// and instance (the authority index). The gateway replaces the previous push of this instance.
+pub fn start_metrics_push(
+    authority: AuthorityIndex,
+    registry: &Registry,
+    config: MetricsPushConfig,
+) -> JoinHandle<()> {
+    let registry = registry.clone();
+    let url = format!(
+        "{}/metrics/job/{}/instance/{authority}",
+        config.url.trim_end_matches('/'),
+        config.job
+    );
+    tracing::info!("Pushing metrics to {url} every {:?}", config.interval);
+    Handle::current().spawn(async move {
+        let client = reqwest::Client::new();
+        loop {
+            sleep(config.interval).await;
+            let metrics = match TextEncoder.encode_to_string(&registry.gather()) {
+                Ok(metrics) => metrics,
+                Err(e) => {
+                    tracing::warn!("Unable to encode metrics: {e}");
+                    continue;
+                }
+            };
+            let mut request = client
+                .put(&url)
+                .header(header::CONTENT_TYPE, TextEncoder.format_type())
+                .body(metrics);
+            if let Some(credentials) = &config.basic_auth {
+                request = request.basic_auth(&credentials.username, Some(&credentials.password));
+            }
+            if let Err(e) = request.send().await.and_then(|r| r.error_for_status()) {
+                tracing::warn!("Failed to push metrics to {url}: {e}");
+            }
+        }
+    })
+}
+
+async fn serve_tls(
+    listener: TcpListener,
+    app: Router,
+    acceptor: TlsAcceptor,
+) -> Result<(), hyper::Error> {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!("Failed to accept metrics connection: {e}");
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        Handle::current().spawn(async move {
+            match acceptor.accept(stream).await {
+                Ok(stream) => {
+                    if let Err(e) = Http::new().serve_connection(stream, app).await {
+                        tracing::debug!("Metrics connection with {peer} failed: {e}");
+                    }
+                }
+                Err(e) => tracing::debug!("TLS handshake with {peer} failed: {e}"),
+            }
+        });
+    }
+}
+
+fn load_tls_config(config: &TlsConfig) -> io::Result<Arc<ServerConfig>> {
+    let certificates = rustls_pemfile::certs(&mut open_pem(&config.certificate_path)?)?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    let key = rustls_pemfile::read_all(&mut open_pem(&config.private_key_path)?)?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "No private key found in {}",
+                    config.private_key_path.display()
+                ),
+            )
+        })?;
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    Ok(Arc::new(config))
+}
+
+fn open_pem(path: &Path) -> io::Result<BufReader<File>> {
+    File::open(path).map(BufReader::new)
+}
 
-    tracing::info!("Prometheus server booted on {address}");
-    Handle::current()
-        .spawn(async move { Server::bind(&address).serve(app.into_make_service()).await })
+fn basic_auth_header(credentials: &BasicAuthConfig) -> String {
+    let encoded = STANDARD.encode(format!("{}:{}", credentials.username, credentials.password));
+    format!("Basic {encoded}")
+}
+
+async fn basic_auth<B>(
+    State(expected): State<Arc<String>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // Compare in constant time, not to leak the credentials through the response time.
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .map_or(false, |value| {
+            value.as_bytes().ct_eq(expected.as_bytes()).into()
+        });
+    if authorized {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"mysticeti\""),
+            )],
+        )
+            .into_response()
+    }
 }
 
 async fn metrics(registry: Extension<Registry>) -> (StatusCode, String) {
@@ -33,3 +240,412 @@ async fn metrics(registry: Extension<Registry>) -> (StatusCode, String) {
         ),
     }
 }
+
+/// The node state exposed by the admin routes. It is read from the node's metrics so that
+/// serving a request never contends with the core thread.
+#[derive(Clone)]
+pub struct AdminState {
+    authority: AuthorityIndex,
+    committee: Arc<Committee>,
+    metrics: Arc<Metrics>,
+    reloader: Option<ConfigReloader>,
+    checkpoints: Option<Checkpoints>,
+    dag_exporter: Option<DagExporter>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncState {
+    Synced,
+    CatchingUp,
+}
+
+#[derive(Serialize)]
+pub struct HealthResponse {
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct ReadyResponse {
+    ready: bool,
+    connected_to_quorum: bool,
+    sync_state: SyncState,
+}
+
+#[derive(Serialize)]
+pub struct StatusResponse {
+    authority: AuthorityIndex,
+    round: RoundNumber,
+    last_committed_round: RoundNumber,
+    commit_index: u64,
+    highest_received_round: RoundNumber,
+    sync_state: SyncState,
+    peers: Vec<PeerStatus>,
+}
+
+#[derive(Serialize)]
+pub struct PeerStatus {
+    authority: AuthorityIndex,
+    connected: bool,
+}
+
+#[derive(Serialize)]
+pub struct ThresholdClockResponse {
+    round: RoundNumber,
+    /// The authorities whose blocks of the current round were seen.
+    contributors: Vec<AuthorityIndex>,
+    /// The authorities whose blocks of the current round are still missing.
+    missing: Vec<AuthorityIndex>,
+    /// Time since the clock last advanced to a new round.
+    stall_ms: u64,
+}
+
+#[derive(Deserialize)]
+pub struct CheckpointQuery {
+    /// The height of the checkpoint. The latest checkpoint if not provided.
+    height: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct DagQuery {
+    /// The first exported round. The last rounds of the DAG if not provided.
+    from: Option<RoundNumber>,
+    /// The last exported round. The highest round of the DAG if not provided.
+    to: Option<RoundNumber>,
+    #[serde(default)]
+    format: DagFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointResponse {
+    pub height: u64,
+    /// The hex-encoded digest.
+    pub digest: String,
+}
+
+impl AdminState {
+    pub fn new(
+        authority: AuthorityIndex,
+        committee: Arc<Committee>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            authority,
+            committee,
+            metrics,
+            reloader: None,
+            checkpoints: None,
+            dag_exporter: None,
+        }
+    }
+
+    /// Serve and update the reloadable configuration on the config route.
+    pub fn with_reloader(mut self, reloader: ConfigReloader) -> Self {
+        self.reloader = Some(reloader);
+        self
+    }
+
+    /// Serve the recent checkpoints of the commit history on the checkpoint route.
+    pub fn with_checkpoints(mut self, checkpoints: Checkpoints) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Serve exports of the recent DAG on the dag route.
+    pub fn with_dag_exporter(mut self, dag_exporter: DagExporter) -> Self {
+        self.dag_exporter = Some(dag_exporter);
+        self
+    }
+
+    fn round(&self) -> RoundNumber {
+        self.metrics.threshold_clock_round.get() as RoundNumber
+    }
+
+    fn peers(&self) -> Vec<PeerStatus> {
+        self.committee
+            .authorities()
+            .filter(|authority| *authority != self.authority)
+            .map(|authority| PeerStatus {
+                authority,
+                connected: self
+                    .metrics
+                    .peer_connected
+                    .with_label_values(&[&authority.to_string()])
+                    .get()
+                    > 0,
+            })
+            .collect()
+    }
+
+    fn connected_to_quorum(&self, peers: &[PeerStatus]) -> bool {
+        let stake: Stake = peers
+            .iter()
+            .filter(|peer| peer.connected)
+            .map(|peer| peer.authority)
+            .chain([self.authority])
+            .filter_map(|authority| self.committee.get_stake(authority))
+            .sum();
+        self.committee.is_quorum(stake)
+    }
+
+    fn sync_state(&self) -> SyncState {
+        let highest_received_round = self.metrics.highest_received_round.get() as RoundNumber;
+        if highest_received_round > self.round() + SYNC_LAG_ROUNDS {
+            SyncState::CatchingUp
+        } else {
+            SyncState::Synced
+        }
+    }
+}
+
+/// The process is up and serving requests.
+async fn health() -> Json<HealthResponse> {
+    Json(HealthResponse { status: "up" })
+}
+
+/// The node is connected to a quorum and not lagging behind the rest of the committee.
+async fn ready(Extension(state): Extension<AdminState>) -> (StatusCode, Json<ReadyResponse>) {
+    let connected_to_quorum = state.connected_to_quorum(&state.peers());
+    let sync_state = state.sync_state();
+    let ready = connected_to_quorum && sync_state == SyncState::Synced;
+    let code = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let response = ReadyResponse {
+        ready,
+        connected_to_quorum,
+        sync_state,
+    };
+    (code, Json(response))
+}
+
+async fn status(Extension(state): Extension<AdminState>) -> Json<StatusResponse> {
+    let metrics = &state.metrics;
+    Json(StatusResponse {
+        authority: state.authority,
+        round: state.round(),
+        last_committed_round: metrics.last_committed_round.get() as RoundNumber,
+        commit_index: metrics.committed_subdags_total.get(),
+        highest_received_round: metrics.highest_received_round.get() as RoundNumber,
+        sync_state: state.sync_state(),
+        peers: state.peers(),
+    })
+}
+
+/// The threshold clock of the node, as last sampled by the core thread.
+async fn threshold_clock(Extension(state): Extension<AdminState>) -> Json<ThresholdClockResponse> {
+    let metrics = &state.metrics;
+    let (contributors, missing): (Vec<_>, Vec<_>) =
+        state.committee.authorities().partition(|authority| {
+            metrics
+                .threshold_clock_contributors
+                .with_label_values(&[&authority.to_string()])
+                .get()
+                > 0
+        });
+    Json(ThresholdClockResponse {
+        round: state.round(),
+        contributors,
+        missing,
+        stall_ms: metrics.threshold_clock_stall_ms.get() as u64,
+    })
+}
+
+/// The checkpoint of the commit history at the requested height, to compare with other nodes.
+async fn checkpoint(
+    Extension(state): Extension<AdminState>,
+    Query(query): Query<CheckpointQuery>,
+) -> Response {
+    let Some(checkpoints) = &state.checkpoints else {
+        return (
+            StatusCode::NOT_FOUND,
+            "Checkpoints are not enabled on this node",
+        )
+            .into_response();
+    };
+    let checkpoint = match query.height {
+        Some(height) => checkpoints.get(height),
+        None => checkpoints.latest(),
+    };
+    match checkpoint {
+        Some(checkpoint) => Json(CheckpointResponse {
+            height: checkpoint.height,
+            digest: hex::encode(checkpoint.digest),
+        })
+        .into_response(),
+        None => (StatusCode::NOT_FOUND, "No checkpoint at this height").into_response(),
+    }
+}
+
+/// The blocks of the requested rounds of the DAG and the links to their parents, with the
+/// committed leaders highlighted.
+async fn dag(Extension(state): Extension<AdminState>, Query(query): Query<DagQuery>) -> Response {
+    let Some(dag_exporter) = &state.dag_exporter else {
+        return (
+            StatusCode::NOT_FOUND,
+            "DAG exports are not enabled on this node",
+        )
+            .into_response();
+    };
+    let Some(highest_round) = dag_exporter.highest_round() else {
+        return dag_not_recovered();
+    };
+    let to = query.to.unwrap_or(highest_round);
+    let from = query
+        .from
+        .unwrap_or(to.saturating_sub(DEFAULT_DAG_ROUNDS - 1));
+    if from > to || to - from >= MAX_DAG_ROUNDS {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Export between 1 and {MAX_DAG_ROUNDS} rounds"),
+        )
+            .into_response();
+    }
+    let Some(export) = dag_exporter.export(from..=to) else {
+        return dag_not_recovered();
+    };
+    match query.format {
+        DagFormat::Dot => (
+            [(header::CONTENT_TYPE, "text/vnd.graphviz")],
+            export.to_dot(),
+        )
+            .into_response(),
+        DagFormat::Json => Json(export).into_response(),
+    }
+}
+
+/// The reloadable configuration currently in effect.
+async fn config(Extension(state): Extension<AdminState>) -> Response {
+    match &state.reloader {
+        Some(reloader) => Json(reloader.current()).into_response(),
+        None => reload_disabled(),
+    }
+}
+
+/// Change the fields of the reloadable configuration set in the request body.
+async fn reload_config(
+    Extension(state): Extension<AdminState>,
+    Json(update): Json<ReloadableConfig>,
+) -> Response {
+    let Some(reloader) = &state.reloader else {
+        return reload_disabled();
+    };
+    match reloader.apply(update) {
+        Ok(parameters) => Json(parameters).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, format!("{e:#}")).into_response(),
+    }
+}
+
+fn dag_not_recovered() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        "The DAG is not recovered from the WAL yet",
+    )
+        .into_response()
+}
+
+fn reload_disabled() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        "Configuration reloading is not enabled on this node",
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{build_dag, committee, test_metrics, TestBlockWriter};
+
+    #[test]
+    fn basic_auth_header_encoding() {
+        let credentials = BasicAuthConfig {
+            username: "Aladdin".to_string(),
+            password: "open sesame".to_string(),
+        };
+        assert_eq!(
+            basic_auth_header(&credentials),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
+
+    #[tokio::test]
+    async fn ready_requires_quorum_and_sync() {
+        let metrics = test_metrics();
+        let state = AdminState::new(0, committee(4), metrics.clone());
+
+        let (code, Json(response)) = ready(Extension(state.clone())).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!response.connected_to_quorum);
+
+        for peer in ["1", "2"] {
+            metrics.peer_connected.with_label_values(&[peer]).set(1);
+        }
+        let (code, Json(response)) = ready(Extension(state.clone())).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(response.sync_state, SyncState::Synced);
+
+        metrics
+            .highest_received_round
+            .set(SYNC_LAG_ROUNDS as i64 + 1);
+        let (code, Json(response)) = ready(Extension(state.clone())).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.sync_state, SyncState::CatchingUp);
+
+        let Json(status) = status(Extension(state)).await;
+        let connected: Vec<_> = status
+            .peers
+            .iter()
+            .filter(|peer| peer.connected)
+            .map(|peer| peer.authority)
+            .collect();
+        assert_eq!(connected, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn threshold_clock_contributors() {
+        let metrics = test_metrics();
+        let state = AdminState::new(0, committee(4), metrics.clone());
+        metrics.threshold_clock_round.set(7);
+        for authority in ["0", "2", "3"] {
+            metrics
+                .threshold_clock_contributors
+                .with_label_values(&[authority])
+                .set(1);
+        }
+        let Json(response) = threshold_clock(Extension(state)).await;
+        assert_eq!(response.round, 7);
+        assert_eq!(response.contributors, vec![0, 2, 3]);
+        assert_eq!(response.missing, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn dag_export_rounds() {
+        let committee = committee(4);
+        let dag_exporter = DagExporter::default();
+        let state = AdminState::new(0, committee.clone(), test_metrics())
+            .with_dag_exporter(dag_exporter.clone());
+        let query = |from, to| {
+            Query(DagQuery {
+                from,
+                to,
+                format: DagFormat::Json,
+            })
+        };
+
+        let response = dag(Extension(state.clone()), query(None, None)).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let mut block_writer = TestBlockWriter::new(&committee);
+        build_dag(&committee, &mut block_writer, None, 3);
+        dag_exporter.set_block_store(block_writer.into_block_store());
+        let response = dag(Extension(state.clone()), query(None, None)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = dag(Extension(state.clone()), query(Some(3), Some(2))).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = dag(Extension(state), query(Some(0), Some(MAX_DAG_ROUNDS))).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
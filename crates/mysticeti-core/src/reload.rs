@@ -0,0 +1,185 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use eyre::{bail, ensure, Context, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::config::{ClientParameters, ImportExport, NodeParameters, ReloadableConfig};
+
+/// The values of the reloadable configuration currently in effect.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct LiveParameters {
+    pub leader_timeout: Duration,
+    pub load: usize,
+    pub block_interval: Duration,
+    /// The log filter set by the last reload, if any.
+    pub log_filter: Option<String>,
+}
+
+impl LiveParameters {
+    pub fn new(node_parameters: &NodeParameters, client_parameters: &ClientParameters) -> Self {
+        Self {
+            leader_timeout: node_parameters.leader_timeout,
+            load: client_parameters.load,
+            block_interval: client_parameters.block_interval,
+            log_filter: None,
+        }
+    }
+}
+
+type LogFilterReloader = Box<dyn Fn(&str) -> Result<()> + Send + Sync>;
+
+/// Applies configuration changes to a running validator. Tasks subscribe to the live parameters
+/// and pick up new values on their next iteration.
+#[derive(Clone)]
+pub struct ConfigReloader {
+    sender: Arc<watch::Sender<LiveParameters>>,
+    // The subscriber is installed by the binary, so it also owns the reload handle.
+    log_filter: Arc<Mutex<Option<LogFilterReloader>>>,
+}
+
+impl ConfigReloader {
+    pub fn new(parameters: LiveParameters) -> Self {
+        let (sender, _) = watch::channel(parameters);
+        Self {
+            sender: Arc::new(sender),
+            log_filter: Default::default(),
+        }
+    }
+
+    pub fn set_log_filter_reloader(
+        &self,
+        reloader: impl Fn(&str) -> Result<()> + Send + Sync + 'static,
+    ) {
+        *self.log_filter.lock() = Some(Box::new(reloader));
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<LiveParameters> {
+        self.sender.subscribe()
+    }
+
+    pub fn current(&self) -> LiveParameters {
+        self.sender.borrow().clone()
+    }
+
+    /// Apply the fields set in `update`. Nothing is changed if any of them is invalid.
+    pub fn apply(&self, update: ReloadableConfig) -> Result<LiveParameters> {
+        if let Some(leader_timeout) = update.leader_timeout {
+            ensure!(
+                !leader_timeout.is_zero(),
+                "leader_timeout must be greater than zero"
+            );
+        }
+        if let Some(block_interval) = update.block_interval {
+            ensure!(
+                !block_interval.is_zero(),
+                "block_interval must be greater than zero"
+            );
+        }
+        if let Some(filter) = &update.log_filter {
+            match &*self.log_filter.lock() {
+                Some(reload) => reload(filter).wrap_err("Failed to reload the log filter")?,
+                None => bail!("The log filter of this process can not be reloaded"),
+            }
+        }
+
+        self.sender.send_modify(|parameters| {
+            if let Some(leader_timeout) = update.leader_timeout {
+                parameters.leader_timeout = leader_timeout;
+            }
+            if let Some(load) = update.load {
+                parameters.load = load;
+            }
+            if let Some(block_interval) = update.block_interval {
+                parameters.block_interval = block_interval;
+            }
+            if update.log_filter.is_some() {
+                parameters.log_filter = update.log_filter;
+            }
+        });
+        let parameters = self.current();
+        tracing::info!("Reloaded configuration: {parameters:?}");
+        Ok(parameters)
+    }
+
+    pub fn reload_from_file(&self, path: &Path) -> Result<LiveParameters> {
+        let update = ReloadableConfig::load(path).wrap_err(format!(
+            "Failed to load reloadable configuration file '{}'",
+            path.display()
+        ))?;
+        self.apply(update)
+    }
+}
+
+/// Reload the configuration from `path` whenever the process receives SIGHUP.
+#[cfg(all(unix, not(feature = "simulator")))]
+pub fn reload_on_sighup(
+    reloader: ConfigReloader,
+    path: std::path::PathBuf,
+) -> std::io::Result<crate::runtime::JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    Ok(crate::runtime::spawn_named("sighup-reload", async move {
+        while hangup.recv().await.is_some() {
+            if let Err(e) = reloader.reload_from_file(&path) {
+                tracing::warn!("Failed to reload configuration: {e:#}");
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ConfigReloader, LiveParameters};
+    use crate::config::{ClientParameters, NodeParameters, ReloadableConfig};
+
+    #[test]
+    fn apply_partial_update() {
+        let parameters =
+            LiveParameters::new(&NodeParameters::default(), &ClientParameters::default());
+        let reloader = ConfigReloader::new(parameters.clone());
+        let mut receiver = reloader.subscribe();
+
+        let update = ReloadableConfig {
+            leader_timeout: Some(Duration::from_millis(500)),
+            ..Default::default()
+        };
+        let live = reloader.apply(update).unwrap();
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(
+            receiver.borrow_and_update().leader_timeout,
+            Duration::from_millis(500)
+        );
+        assert_eq!(live.load, parameters.load);
+
+        // Invalid updates are rejected as a whole.
+        let update = ReloadableConfig {
+            load: Some(1000),
+            block_interval: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        assert!(reloader.apply(update).is_err());
+        assert!(!receiver.has_changed().unwrap());
+
+        // No log filter reloader was installed.
+        let update = ReloadableConfig {
+            log_filter: Some("debug".to_string()),
+            ..Default::default()
+        };
+        assert!(reloader.apply(update).is_err());
+        reloader.set_log_filter_reloader(|_| Ok(()));
+        let update = ReloadableConfig {
+            log_filter: Some("debug".to_string()),
+            ..Default::default()
+        };
+        let live = reloader.apply(update).unwrap();
+        assert_eq!(live.log_filter.as_deref(), Some("debug"));
+    }
+}
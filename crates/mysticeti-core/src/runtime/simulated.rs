@@ -23,6 +23,13 @@ impl Handle {
     }
 }
 
+pub fn spawn_named<R: Send + 'static, F: Future<Output = R> + Send + 'static>(
+    _name: &str,
+    f: F,
+) -> JoinHandle<R> {
+    simulator_spawn(f)
+}
+
 pub fn sleep(duration: Duration) -> Sleep {
     Sleep::new(duration)
 }
@@ -38,6 +45,11 @@ impl TimeInstant {
     pub fn elapsed(&self) -> Duration {
         SimulatorContext::time() - self.0
     }
+
+    /// The instant `elapsed` before now.
+    pub fn before(elapsed: Duration) -> Self {
+        Self(SimulatorContext::time().saturating_sub(elapsed))
+    }
 }
 
 pub fn timestamp_utc() -> Duration {
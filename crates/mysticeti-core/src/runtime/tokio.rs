@@ -1,7 +1,10 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::time::{Duration, SystemTime};
+use std::{
+    future::Future,
+    time::{Duration, SystemTime},
+};
 
 use tokio::time::{Interval, MissedTickBehavior};
 pub use tokio::{
@@ -10,6 +13,28 @@ pub use tokio::{
     time::{sleep, Instant},
 };
 
+/// Spawn a task on the current runtime under a name that is displayed by tokio-console.
+/// Naming tasks requires building with `RUSTFLAGS="--cfg tokio_unstable"`; otherwise the name
+/// is ignored.
+pub fn spawn_named<F>(name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(tokio_unstable)]
+    {
+        tokio::task::Builder::new()
+            .name(name)
+            .spawn(future)
+            .expect("Failed to spawn task")
+    }
+    #[cfg(not(tokio_unstable))]
+    {
+        let _ = name;
+        Handle::current().spawn(future)
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct TimeInstant(Instant);
@@ -23,6 +48,12 @@ impl TimeInstant {
     pub fn elapsed(&self) -> Duration {
         self.0.elapsed()
     }
+
+    /// The instant `elapsed` before now.
+    pub fn before(elapsed: Duration) -> Self {
+        let now = Instant::now();
+        Self(now.checked_sub(elapsed).unwrap_or(now))
+    }
 }
 
 #[allow(dead_code)]
@@ -57,13 +57,16 @@ impl SimulatedNetwork {
     pub async fn connect(&self, a: usize, b: usize) {
         let (a_sender, a_receiver) = Self::latency_channel();
         let (b_sender, b_receiver) = Self::latency_channel();
+        // The simulated links do not model bandwidth, so both lanes share the same channel.
         let a_connection = Connection {
             peer_id: b,
+            priority_sender: b_sender.clone(),
             sender: b_sender,
             receiver: a_receiver,
         };
         let b_connection = Connection {
             peer_id: a,
+            priority_sender: a_sender.clone(),
             sender: a_sender,
             receiver: b_receiver,
         };
@@ -6,6 +6,7 @@ use std::collections::{BTreeMap, HashSet, VecDeque};
 use minibytes::Bytes;
 
 use crate::{
+    block_manager::IncompleteBlocks,
     block_store::{BlockStore, CommitData, OwnBlockData},
     core::MetaStatement,
     data::Data,
@@ -19,6 +20,10 @@ pub struct RecoveredState {
     pub pending: VecDeque<(WalPosition, MetaStatement)>,
     pub state: Option<Bytes>,
     pub unprocessed_blocks: Vec<Data<StatementBlock>>,
+    /// Blocks accepted from their header whose payload was not received.
+    pub awaiting_payload: HashSet<BlockReference>,
+    /// Blocks whose causal history is not entirely held with its payloads.
+    pub incomplete_blocks: IncompleteBlocks,
 
     pub last_committed_leader: Option<BlockReference>,
     pub committed_blocks: HashSet<BlockReference>,
@@ -27,10 +32,12 @@ pub struct RecoveredState {
 
 #[derive(Default)]
 pub struct RecoveredStateBuilder {
-    pending: BTreeMap<WalPosition, RawMetaStatement>,
+    pending: BTreeMap<WalPosition, Vec<RawMetaStatement>>,
     last_own_block: Option<OwnBlockData>,
     state: Option<Bytes>,
     unprocessed_blocks: Vec<Data<StatementBlock>>,
+    awaiting_payload: HashSet<BlockReference>,
+    incomplete_blocks: IncompleteBlocks,
 
     last_committed_leader: Option<BlockReference>,
     committed_blocks: HashSet<BlockReference>,
@@ -43,13 +50,27 @@ impl RecoveredStateBuilder {
     }
 
     pub fn block(&mut self, pos: WalPosition, block: &Data<StatementBlock>) {
-        self.pending
-            .insert(pos, RawMetaStatement::Include(*block.reference()));
-        self.unprocessed_blocks.push(block.clone());
+        let complete = if block.is_header_only() {
+            self.awaiting_payload.insert(*block.reference());
+            self.incomplete_blocks.add(block)
+        } else if self.awaiting_payload.remove(block.reference()) {
+            // The payload of a block accepted earlier from its header.
+            self.unprocessed_blocks.push(block.clone());
+            self.incomplete_blocks.payload_received(*block.reference())
+        } else {
+            self.unprocessed_blocks.push(block.clone());
+            self.incomplete_blocks.add(block)
+        };
+        // Blocks are only included once the payloads of their causal history are held.
+        let pending = self.pending.entry(pos).or_default();
+        pending.extend(complete.into_iter().map(RawMetaStatement::Include));
     }
 
     pub fn payload(&mut self, pos: WalPosition, payload: Bytes) {
-        self.pending.insert(pos, RawMetaStatement::Payload(payload));
+        self.pending
+            .entry(pos)
+            .or_default()
+            .push(RawMetaStatement::Payload(payload));
     }
 
     pub fn own_block(&mut self, own_block_data: OwnBlockData) {
@@ -77,6 +98,7 @@ impl RecoveredStateBuilder {
         let pending = self
             .pending
             .into_iter()
+            .flat_map(|(pos, raw)| raw.into_iter().map(move |raw| (pos, raw)))
             .map(|(pos, raw)| (pos, raw.into_meta_statement()))
             .collect();
         RecoveredState {
@@ -85,6 +107,8 @@ impl RecoveredStateBuilder {
             block_store,
             state: self.state,
             unprocessed_blocks: self.unprocessed_blocks,
+            awaiting_payload: self.awaiting_payload,
+            incomplete_blocks: self.incomplete_blocks,
             last_committed_leader: self.last_committed_leader,
             committed_blocks: self.committed_blocks,
             committed_state: self.committed_state,
@@ -0,0 +1,309 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The endpoint through which clients submit transactions to a validator over TCP, and learn when
+//! their transactions are committed. Messages are bincode-encoded and prefixed by their length.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    io,
+    net::{self, SocketAddr},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpListener, TcpStream,
+    },
+    select,
+    sync::{broadcast, mpsc},
+};
+
+use crate::{
+    consensus::linearizer::CommittedSubDag,
+    crypto::AsBytes,
+    runtime::{spawn_named, JoinHandle},
+    types::{AuthorityIndex, Transaction},
+};
+
+/// Messages larger than this are rejected.
+const MAXIMUM_MESSAGE_SIZE: u32 = 64 * 1024 * 1024;
+
+/// The most transactions of a connection awaiting their commit. Beyond that, the oldest ones are
+/// forgotten: their commit is not notified (clients eventually time them out).
+const MAXIMUM_PENDING_TRANSACTIONS: usize = 1_000_000;
+
+/// Identifies a submitted transaction: its first 8 bytes are its submission time in milliseconds,
+/// the next 8 bytes are picked by the client to tell apart the transactions submitted at once.
+pub type TransactionKey = [u8; 16];
+
+/// Messages sent by clients to the submission endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SubmissionRequest {
+    /// Transactions to include in the next blocks of the validator. Each transaction starts with
+    /// its [`TransactionKey`].
+    Submit(Vec<Transaction>),
+}
+
+/// Messages sent by the submission endpoint to clients.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SubmissionResponse {
+    /// The transactions of the oldest unanswered `Submit` were handed to the validator.
+    Accepted,
+    /// Transactions submitted on this connection were committed.
+    Committed(Vec<TransactionKey>),
+}
+
+/// Return the key identifying a transaction, if the transaction is long enough to have one.
+pub fn transaction_key(transaction: &Transaction) -> Option<TransactionKey> {
+    transaction.as_bytes().get(..16)?.try_into().ok()
+}
+
+/// Serve clients submitting transactions to `transactions`. Clients are notified when their
+/// transactions are committed in blocks of `authority`, as delivered by `commits`.
+pub fn start_submission_server(
+    address: SocketAddr,
+    transactions: mpsc::Sender<Vec<Transaction>>,
+    commits: broadcast::Sender<CommittedSubDag>,
+    authority: AuthorityIndex,
+) -> io::Result<JoinHandle<()>> {
+    let listener = net::TcpListener::bind(address)?;
+    start_submission_server_on(listener, transactions, commits, authority)
+}
+
+/// Same as [`start_submission_server`], on a listener already bound (e.g., to an ephemeral port).
+pub fn start_submission_server_on(
+    listener: net::TcpListener,
+    transactions: mpsc::Sender<Vec<Transaction>>,
+    commits: broadcast::Sender<CommittedSubDag>,
+    authority: AuthorityIndex,
+) -> io::Result<JoinHandle<()>> {
+    let address = listener.local_addr()?;
+    listener.set_nonblocking(true)?;
+    let listener = TcpListener::from_std(listener)?;
+    tracing::info!("Accepting transactions on {address}");
+    Ok(spawn_named("submission-server", async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("Failed to accept submission connection: {e}");
+                    continue;
+                }
+            };
+            let transactions = transactions.clone();
+            let commits = commits.subscribe();
+            spawn_named(&format!("submission-{peer}"), async move {
+                if let Err(e) = serve_client(stream, transactions, commits, authority).await {
+                    tracing::debug!("Submission connection with {peer} closed: {e}");
+                }
+            });
+        }
+    }))
+}
+
+async fn serve_client(
+    stream: TcpStream,
+    transactions: mpsc::Sender<Vec<Transaction>>,
+    mut commits: broadcast::Receiver<CommittedSubDag>,
+    authority: AuthorityIndex,
+) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let (reader, mut writer) = stream.into_split();
+    // Reading a message is not cancel safe, so requests are read by a dedicated task.
+    let (request_sender, mut requests) = mpsc::channel(16);
+    let reader = spawn_named("submission-reader", async move {
+        let mut reader = reader;
+        while let Ok(request) = read_message::<SubmissionRequest>(&mut reader).await {
+            if request_sender.send(request).await.is_err() {
+                break;
+            }
+        }
+    });
+    // The transactions submitted on this connection and not committed yet, and the order in which
+    // they were submitted (including committed ones) to forget the oldest.
+    let mut pending = HashSet::new();
+    let mut submitted = VecDeque::new();
+    let result = loop {
+        select! {
+            request = requests.recv() => {
+                let Some(SubmissionRequest::Submit(batch)) = request else {
+                    break Ok(());
+                };
+                let Some(keys) = batch.iter().map(transaction_key).collect::<Option<Vec<_>>>()
+                else {
+                    break Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Transaction is shorter than its key",
+                    ));
+                };
+                for key in keys {
+                    if pending.insert(key) {
+                        submitted.push_back(key);
+                    }
+                }
+                while submitted.len() > MAXIMUM_PENDING_TRANSACTIONS {
+                    if let Some(oldest) = submitted.pop_front() {
+                        pending.remove(&oldest);
+                    }
+                }
+                if transactions.send(batch).await.is_err() {
+                    break Ok(());
+                }
+                if let Err(e) = write_message(&mut writer, &SubmissionResponse::Accepted).await {
+                    break Err(e);
+                }
+            }
+            commit = commits.recv() => {
+                let commit = match commit {
+                    Ok(commit) => commit,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Submission connection missed {skipped} commits");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break Ok(()),
+                };
+                let committed: Vec<_> = commit
+                    .blocks
+                    .iter()
+                    .filter(|block| block.author() == authority)
+                    .flat_map(|block| block.shared_transactions())
+                    .filter_map(|(_, transaction)| transaction_key(transaction))
+                    .filter(|key| pending.remove(key))
+                    .collect();
+                if committed.is_empty() {
+                    continue;
+                }
+                let response = SubmissionResponse::Committed(committed);
+                if let Err(e) = write_message(&mut writer, &response).await {
+                    break Err(e);
+                }
+            }
+        }
+    };
+    reader.abort();
+    result
+}
+
+/// Connect to the submission endpoint of a validator.
+pub async fn connect(address: SocketAddr) -> io::Result<(SubmissionSender, SubmissionReceiver)> {
+    let stream = TcpStream::connect(address).await?;
+    stream.set_nodelay(true)?;
+    let (reader, writer) = stream.into_split();
+    Ok((SubmissionSender { writer }, SubmissionReceiver { reader }))
+}
+
+/// Sends transactions to the submission endpoint of a validator.
+pub struct SubmissionSender {
+    writer: OwnedWriteHalf,
+}
+
+/// Receives the responses of the submission endpoint of a validator.
+pub struct SubmissionReceiver {
+    reader: OwnedReadHalf,
+}
+
+impl SubmissionSender {
+    pub async fn submit(&mut self, transactions: Vec<Transaction>) -> io::Result<()> {
+        write_message(&mut self.writer, &SubmissionRequest::Submit(transactions)).await
+    }
+}
+
+impl SubmissionReceiver {
+    /// Not cancel safe: a response may be partially read when the future is dropped.
+    pub async fn recv(&mut self) -> io::Result<SubmissionResponse> {
+        read_message(&mut self.reader).await
+    }
+}
+
+async fn write_message<T: Serialize>(writer: &mut OwnedWriteHalf, message: &T) -> io::Result<()> {
+    let serialized = bincode::serialize(message).expect("Serialization should not fail");
+    writer.write_u32(serialized.len() as u32).await?;
+    writer.write_all(&serialized).await
+}
+
+async fn read_message<T: DeserializeOwned>(reader: &mut OwnedReadHalf) -> io::Result<T> {
+    let size = reader.read_u32().await?;
+    if size > MAXIMUM_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message of {size} bytes is too large"),
+        ));
+    }
+    let mut buffer = vec![0u8; size as usize];
+    reader.read_exact(&mut buffer).await?;
+    bincode::deserialize(&buffer).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        data::Data,
+        types::{BaseStatement, StatementBlock},
+    };
+
+    fn transaction(id: u64) -> Transaction {
+        let mut data = 1000u64.to_le_bytes().to_vec();
+        data.extend_from_slice(&id.to_le_bytes());
+        data.extend_from_slice(&[0u8; 16]);
+        Transaction::new(data)
+    }
+
+    #[tokio::test]
+    async fn submit_and_observe_commits() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (transaction_sender, mut transaction_receiver) = mpsc::channel(16);
+        let (commit_sender, _) = broadcast::channel(16);
+        let _server =
+            start_submission_server_on(listener, transaction_sender, commit_sender.clone(), 1)
+                .unwrap();
+
+        let (mut sender, mut receiver) = connect(address).await.unwrap();
+        sender
+            .submit(vec![transaction(1), transaction(2)])
+            .await
+            .unwrap();
+        assert!(matches!(
+            receiver.recv().await.unwrap(),
+            SubmissionResponse::Accepted
+        ));
+        let batch = transaction_receiver.recv().await.unwrap();
+        assert_eq!(batch.len(), 2);
+
+        // Only the transactions submitted on the connection, in blocks of the validator, count.
+        let blocks = [(0, 1), (1, 2), (1, 3)]
+            .into_iter()
+            .map(|(author, id)| {
+                Data::new(StatementBlock::new(
+                    author,
+                    1,
+                    vec![],
+                    vec![BaseStatement::Share(transaction(id))],
+                    0,
+                    false,
+                    Default::default(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let anchor = *blocks[0].reference();
+        // Wait for the connection to subscribe to commits.
+        while commit_sender.receiver_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        commit_sender
+            .send(CommittedSubDag::new(anchor, blocks))
+            .unwrap();
+        match receiver.recv().await.unwrap() {
+            SubmissionResponse::Committed(keys) => {
+                assert_eq!(keys, vec![transaction_key(&transaction(2)).unwrap()])
+            }
+            response => panic!("Unexpected response {response:?}"),
+        }
+    }
+}
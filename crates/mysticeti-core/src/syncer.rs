@@ -72,8 +72,15 @@ impl<H: BlockHandler, S: SyncerSignals, C: CommitObserver> Syncer<H, S, C> {
     }
 
     pub fn force_new_block(&mut self, round: RoundNumber) -> bool {
+        self.core.report_threshold_clock_stall();
         if self.core.last_proposed() == round {
             self.metrics.leader_timeout_total.inc();
+            for leader in self.core.missing_leaders() {
+                self.metrics
+                    .leader_timeout_by_authority_total
+                    .with_label_values(&[&leader.to_string()])
+                    .inc();
+            }
             self.force_new_block = true;
             self.try_new_block();
             true
@@ -116,6 +123,8 @@ impl<H: BlockHandler, S: SyncerSignals, C: CommitObserver> Syncer<H, S, C> {
                     .collect();
                 tracing::debug!("Committed {:?}", committed_refs);
             }
+            let _span = tracing::debug_span!("commit_observation", leaders = newly_committed.len())
+                .entered();
             let committed_subdag = self
                 .commit_observer
                 .handle_commit(self.core.block_store(), newly_committed);
@@ -134,6 +143,10 @@ impl<H: BlockHandler, S: SyncerSignals, C: CommitObserver> Syncer<H, S, C> {
         &self.core
     }
 
+    pub fn cleanup(&mut self) {
+        self.core.cleanup();
+    }
+
     #[cfg(test)]
     pub fn scheduler_state_id(&self) -> usize {
         self.core.authority() as usize
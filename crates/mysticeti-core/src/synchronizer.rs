@@ -2,22 +2,25 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::RangeInclusive,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use futures::future::join_all;
-use rand::{seq::SliceRandom, thread_rng, RngCore};
+use rand::{random, seq::SliceRandom, thread_rng, RngCore};
 use tokio::sync::mpsc;
+use tracing::Instrument;
 
 use crate::{
     block_handler::BlockHandler,
+    config::BlockSyncParameters,
     data::Data,
     metrics::Metrics,
     net_sync::{self, NetworkSyncerInner},
     network::NetworkMessage,
-    runtime::{sleep, timestamp_utc, Handle, JoinHandle},
+    runtime::{sleep, spawn_named, timestamp_utc, JoinHandle},
     syncer::CommitObserver,
     types::{AuthorityIndex, BlockReference, RoundNumber, StatementBlock},
 };
@@ -30,14 +33,8 @@ pub struct SynchronizerParameters {
     pub maximum_helpers_per_authority: usize,
     /// The number of blocks to send in a single batch.
     pub batch_size: usize,
-    /// The sampling precision with which to re-evaluate the sync strategy.
-    pub sample_precision: Duration,
-    /// The grace period with which to eagerly sync missing blocks.
-    pub grace_period: Duration,
     /// The interval at which to send stream blocks authored by other nodes.
     pub stream_interval: Duration,
-    /// Threshold number of missing block from an authority to open a new stream.
-    pub new_stream_threshold: usize,
 }
 
 impl Default for SynchronizerParameters {
@@ -46,23 +43,49 @@ impl Default for SynchronizerParameters {
             absolute_maximum_helpers: 10,
             maximum_helpers_per_authority: 2,
             batch_size: 10,
-            sample_precision: Duration::from_secs(5),
-            grace_period: Duration::from_secs(15),
             stream_interval: Duration::from_secs(1),
-            new_stream_threshold: 10,
         }
     }
 }
 
+/// The own blocks streamed to a peer on one lane of the connection.
+#[derive(Clone, Copy)]
+enum OwnBlocks {
+    /// The full blocks.
+    Full,
+    /// The headers of the blocks with transactions, and the blocks without (which are as small).
+    Headers,
+    /// The blocks with transactions, once their header is sent.
+    Payloads,
+}
+
+impl OwnBlocks {
+    fn message(self, block: Data<StatementBlock>) -> Option<NetworkMessage> {
+        let block = match self {
+            Self::Full => block,
+            Self::Headers if block.is_vote_only() => block,
+            Self::Headers => Data::new(block.header()),
+            Self::Payloads if block.is_vote_only() => return None,
+            Self::Payloads => block,
+        };
+        Some(NetworkMessage::Block(block))
+    }
+}
+
 pub struct BlockDisseminator<H: BlockHandler, C: CommitObserver> {
     self_peer: AuthorityIndex,
     to_peer: AuthorityIndex,
     /// The sender to the network.
     sender: mpsc::Sender<NetworkMessage>,
+    /// The sender to the priority lane of the network.
+    priority_sender: mpsc::Sender<NetworkMessage>,
     /// The inner state of the network syncer.
     inner: Arc<NetworkSyncerInner<H, C>>,
     /// The handle of the task disseminating our own blocks.
     own_blocks: Option<JoinHandle<Option<()>>>,
+    /// The handle of the task disseminating the headers of our own blocks, if they are sent
+    /// ahead of the blocks.
+    own_headers: Option<JoinHandle<Option<()>>>,
     /// The handles of tasks disseminating other nodes' blocks.
     other_blocks: Vec<JoinHandle<Option<()>>>,
     /// The parameters of the synchronizer.
@@ -82,6 +105,7 @@ where
         self_peer: AuthorityIndex,
         to_peer: AuthorityIndex,
         sender: mpsc::Sender<NetworkMessage>,
+        priority_sender: mpsc::Sender<NetworkMessage>,
         inner: Arc<NetworkSyncerInner<H, C>>,
         parameters: SynchronizerParameters,
         metrics: Arc<Metrics>,
@@ -90,8 +114,10 @@ where
             self_peer,
             to_peer,
             sender,
+            priority_sender,
             inner,
             own_blocks: None,
+            own_headers: None,
             other_blocks: Vec::new(),
             parameters,
             metrics,
@@ -100,8 +126,11 @@ where
     }
 
     pub async fn shutdown(mut self) {
-        let mut waiters = Vec::with_capacity(1 + self.other_blocks.len());
-        if let Some(handle) = self.own_blocks.take() {
+        let mut waiters = Vec::with_capacity(2 + self.other_blocks.len());
+        for handle in [self.own_blocks.take(), self.own_headers.take()]
+            .into_iter()
+            .flatten()
+        {
             handle.abort();
             waiters.push(handle);
         }
@@ -137,21 +166,66 @@ where
             .ok()
     }
 
+    pub async fn send_block_range(
+        &mut self,
+        peer: AuthorityIndex,
+        rounds: RangeInclusive<RoundNumber>,
+        author: Option<AuthorityIndex>,
+    ) -> Option<()> {
+        self.metrics
+            .block_sync_range_requests_received
+            .with_label_values(&[&peer.to_string()])
+            .inc();
+        let blocks = self.inner.block_store.get_blocks_in_range(rounds, author);
+        for block in blocks {
+            self.sender.send(NetworkMessage::Block(block)).await.ok()?;
+        }
+        Some(())
+    }
+
     pub async fn disseminate_own_blocks(&mut self, round: RoundNumber) {
-        if let Some(existing) = self.own_blocks.take() {
+        for existing in [self.own_blocks.take(), self.own_headers.take()]
+            .into_iter()
+            .flatten()
+        {
             existing.abort();
             existing.await.ok();
         }
 
-        let handle = Handle::current().spawn(Self::stream_own_blocks(
-            self.self_peer,
-            self.to_peer,
-            self.sender.clone(),
-            self.inner.clone(),
-            round,
-            self.parameters.batch_size,
-            self.start,
-        ));
+        // Headers are streamed by a separate task, so that they do not wait for the payloads
+        // queued on the connection.
+        let own_blocks = if self.inner.header_first {
+            let handle = spawn_named(
+                &format!("disseminate-own-headers-{}", self.to_peer),
+                Self::stream_own_blocks(
+                    self.self_peer,
+                    self.to_peer,
+                    self.priority_sender.clone(),
+                    self.inner.clone(),
+                    round,
+                    self.parameters.batch_size,
+                    self.start,
+                    OwnBlocks::Headers,
+                ),
+            );
+            self.own_headers = Some(handle);
+            OwnBlocks::Payloads
+        } else {
+            OwnBlocks::Full
+        };
+        let handle = spawn_named(
+            &format!("disseminate-own-blocks-{}", self.to_peer),
+            Self::stream_own_blocks(
+                self.self_peer,
+                self.to_peer,
+                self.sender.clone(),
+                self.inner.clone(),
+                round,
+                self.parameters.batch_size,
+                self.start,
+                own_blocks,
+            ),
+        );
         self.own_blocks = Some(handle);
     }
 
@@ -163,6 +237,7 @@ where
         false
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn stream_own_blocks(
         self_peer: AuthorityIndex,
         to_peer: AuthorityIndex,
@@ -171,6 +246,7 @@ where
         mut round: RoundNumber,
         batch_size: usize,
         start: Instant,
+        own_blocks: OwnBlocks,
     ) -> Option<()> {
         loop {
             let notified = inner.notify.notified();
@@ -180,7 +256,20 @@ where
                     continue;
                 }
                 round = block.round();
-                to.send(NetworkMessage::Block(block)).await.ok()?;
+                // Half of the peers receive the conflicting block of an equivocating authority.
+                let block = match inner.block_store.get_own_equivocation(round) {
+                    Some(equivocation) if to_peer % 2 == 1 => equivocation,
+                    _ => block,
+                };
+                let span = tracing::debug_span!(
+                    "disseminate_block",
+                    block = %block.reference(),
+                    peer = to_peer
+                );
+                let Some(message) = own_blocks.message(block) else {
+                    continue;
+                };
+                to.send(message).instrument(span).await.ok()?;
             }
             notified.await
         }
@@ -195,14 +284,17 @@ where
             return;
         }
 
-        let handle = Handle::current().spawn(Self::stream_others_blocks(
-            self.sender.clone(),
-            self.inner.clone(),
-            round,
-            author,
-            self.parameters.batch_size,
-            self.parameters.stream_interval,
-        ));
+        let handle = spawn_named(
+            &format!("disseminate-blocks-{author}-to-{}", self.to_peer),
+            Self::stream_others_blocks(
+                self.sender.clone(),
+                self.inner.clone(),
+                round,
+                author,
+                self.parameters.batch_size,
+                self.parameters.stream_interval,
+            ),
+        );
         self.other_blocks.push(handle);
     }
 
@@ -241,6 +333,7 @@ impl BlockFetcher {
     pub fn start<B, C>(
         id: AuthorityIndex,
         inner: Arc<NetworkSyncerInner<B, C>>,
+        parameters: BlockSyncParameters,
         metrics: Arc<Metrics>,
         enable: bool,
     ) -> Self
@@ -249,8 +342,8 @@ impl BlockFetcher {
         C: CommitObserver + 'static,
     {
         let (sender, receiver) = mpsc::channel(100);
-        let worker = BlockFetcherWorker::new(id, inner, receiver, metrics, enable);
-        let handle = Handle::current().spawn(worker.run());
+        let worker = BlockFetcherWorker::new(id, inner, receiver, parameters, metrics, enable);
+        let handle = spawn_named("block-fetcher", worker.run());
         Self { sender, handle }
     }
 
@@ -283,13 +376,22 @@ struct BlockFetcherWorker<B: BlockHandler, C: CommitObserver> {
     inner: Arc<NetworkSyncerInner<B, C>>,
     receiver: mpsc::Receiver<BlockFetcherMessage>,
     senders: HashMap<AuthorityIndex, mpsc::Sender<NetworkMessage>>,
-    parameters: SynchronizerParameters,
+    parameters: BlockSyncParameters,
     metrics: Arc<Metrics>,
     /// Hold a timestamp of when blocks were first considered missing.
     missing: HashMap<BlockReference, Duration>,
+    /// Blocks requested from peers that have not been received yet.
+    requested: HashMap<BlockReference, OutstandingRequest>,
     enable: bool,
 }
 
+struct OutstandingRequest {
+    /// The number of times the block was requested again.
+    retries: u32,
+    /// The time after which the block is requested again.
+    deadline: Duration,
+}
+
 impl<B, C> BlockFetcherWorker<B, C>
 where
     B: BlockHandler + 'static,
@@ -299,6 +401,7 @@ where
         id: AuthorityIndex,
         inner: Arc<NetworkSyncerInner<B, C>>,
         receiver: mpsc::Receiver<BlockFetcherMessage>,
+        parameters: BlockSyncParameters,
         metrics: Arc<Metrics>,
         enable: bool,
     ) -> Self {
@@ -307,9 +410,10 @@ where
             inner,
             receiver,
             senders: Default::default(),
-            parameters: Default::default(),
+            parameters,
             metrics,
             missing: Default::default(),
+            requested: Default::default(),
             enable,
         }
     }
@@ -317,7 +421,7 @@ where
     async fn run(mut self) -> Option<()> {
         loop {
             tokio::select! {
-                _ = sleep(self.parameters.sample_precision) => self.sync_strategy().await,
+                _ = sleep(self.parameters.sample_interval) => self.sync_strategy().await,
                 message = self.receiver.recv() => {
                     match message {
                         Some(BlockFetcherMessage::RegisterAuthority(authority, sender)) => {
@@ -333,7 +437,8 @@ where
         }
     }
 
-    /// A simple and naive strategy that requests missing blocks from random peers.
+    /// A simple and naive strategy that requests missing blocks from random peers. Blocks that are
+    /// not received in time are requested again, with an exponential backoff.
     async fn sync_strategy(&mut self) {
         if self.enable {
             return;
@@ -341,6 +446,7 @@ where
 
         let now = timestamp_utc();
         let mut to_request = Vec::new();
+        let mut still_missing = HashSet::new();
         let missing_blocks = self.inner.syncer.get_missing_blocks().await;
         for (authority, missing) in missing_blocks.into_iter().enumerate() {
             self.metrics
@@ -349,27 +455,72 @@ where
                 .set(missing.len() as i64);
 
             for reference in missing {
+                still_missing.insert(reference);
+                if let Some(request) = self.requested.get_mut(&reference) {
+                    if now >= request.deadline {
+                        request.retries += 1;
+                        let timeout = self.parameters.request_timeout(request.retries, random());
+                        request.deadline = now + timeout;
+                        to_request.push(reference);
+                        self.metrics.block_sync_request_retries.inc();
+                    }
+                    continue;
+                }
                 let time = self.missing.entry(reference).or_insert(now);
                 if now.checked_sub(*time).unwrap_or_default() >= self.parameters.grace_period {
+                    self.missing.remove(&reference);
+                    let deadline = now + self.parameters.request_timeout(0, random());
+                    self.requested.insert(
+                        reference,
+                        OutstandingRequest {
+                            retries: 0,
+                            deadline,
+                        },
+                    );
                     to_request.push(reference);
-                    self.missing.remove(&reference); // todo - ensure we receive the block
                 }
             }
         }
-        self.missing.retain(|_, time| {
-            now.checked_sub(*time).unwrap_or_default() < self.parameters.grace_period
-        });
-
-        // TODO: If we are missing many blocks from the same authority
-        // (`missing.len() > self.parameters.new_stream_threshold`), it is likely that
-        // we have a network partition. We should try to find an other peer from which
-        // to (temporarily) sync the blocks from that authority.
+        self.missing
+            .retain(|reference, _| still_missing.contains(reference));
+        self.requested
+            .retain(|reference, _| still_missing.contains(reference));
+        self.metrics
+            .block_sync_requests_outstanding
+            .set(self.requested.len() as i64);
+
+        // Many blocks missing from the same authority (e.g. after being down for a while) are
+        // requested by round range rather than one by one.
+        let mut by_authority: BTreeMap<AuthorityIndex, Vec<BlockReference>> = BTreeMap::new();
+        for reference in to_request {
+            by_authority
+                .entry(reference.authority)
+                .or_default()
+                .push(reference);
+        }
+        let mut messages = Vec::new();
+        let mut references = Vec::new();
+        for (authority, missing) in by_authority {
+            if missing.len() > self.parameters.range_request_threshold {
+                messages.extend(
+                    round_ranges(&missing)
+                        .into_iter()
+                        .map(|rounds| NetworkMessage::RequestBlockRange(rounds, Some(authority))),
+                );
+            } else {
+                references.extend(missing);
+            }
+        }
+        messages.extend(
+            references
+                .chunks(net_sync::MAXIMUM_BLOCK_REQUEST)
+                .map(|chunk| NetworkMessage::RequestBlocks(chunk.to_vec())),
+        );
 
-        for chunks in to_request.chunks(net_sync::MAXIMUM_BLOCK_REQUEST) {
+        for message in messages {
             let Some((peer, permit)) = self.sample_peer(&[self.id]) else {
                 break;
             };
-            let message = NetworkMessage::RequestBlocks(chunks.to_vec());
             permit.send(message);
 
             self.metrics
@@ -399,3 +550,40 @@ where
         None
     }
 }
+
+/// Cover the rounds of the references with as few ranges as possible, each spanning at most
+/// `net_sync::MAXIMUM_ROUND_RANGE` rounds.
+fn round_ranges(references: &[BlockReference]) -> Vec<RangeInclusive<RoundNumber>> {
+    let mut rounds: Vec<_> = references.iter().map(|reference| reference.round).collect();
+    rounds.sort_unstable();
+    let mut ranges: Vec<RangeInclusive<RoundNumber>> = Vec::new();
+    for round in rounds {
+        match ranges.last_mut() {
+            Some(range) if round < range.start() + net_sync::MAXIMUM_ROUND_RANGE => {
+                *range = *range.start()..=round;
+            }
+            _ => ranges.push(round..=round),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net_sync::MAXIMUM_ROUND_RANGE;
+
+    #[test]
+    fn round_ranges_are_bounded() {
+        let (next, far) = (3 + MAXIMUM_ROUND_RANGE, 10 * MAXIMUM_ROUND_RANGE);
+        let references: Vec<_> = [7, 3, 3, 5, next, far]
+            .into_iter()
+            .map(|round| BlockReference::new_test(1, round))
+            .collect();
+        assert_eq!(
+            round_ranges(&references),
+            vec![3..=7, next..=next, far..=far]
+        );
+        assert!(round_ranges(&[]).is_empty());
+    }
+}
@@ -10,6 +10,7 @@ use std::{
 use futures::future::join_all;
 use prometheus::Registry;
 use rand::{rngs::StdRng, SeedableRng};
+use tokio::sync::watch;
 
 #[cfg(feature = "simulator")]
 use crate::future_simulator::OverrideNodeContext;
@@ -19,12 +20,13 @@ use crate::{
     block_handler::{BlockHandler, TestBlockHandler, TestCommitHandler},
     block_store::{BlockStore, BlockWriter, OwnBlockData, WAL_ENTRY_BLOCK},
     committee::Committee,
-    config::{self, NodePrivateConfig, NodePublicConfig},
+    config::{self, ClientParameters, NodePrivateConfig, NodePublicConfig},
     core::{Core, CoreOptions},
     data::Data,
     metrics::{MetricReporter, Metrics},
     net_sync::NetworkSyncer,
     network::Network,
+    reload::{ConfigReloader, LiveParameters},
     syncer::{Syncer, SyncerSignals},
     types::{format_authority_index, AuthorityIndex, BlockReference, RoundNumber, StatementBlock},
     wal::{open_file_for_wal, walf, WalPosition, WalWriter},
@@ -34,6 +36,11 @@ pub fn test_metrics() -> Arc<Metrics> {
     Metrics::new(&Registry::new(), None).0
 }
 
+pub fn test_live_parameters(public_config: &NodePublicConfig) -> watch::Receiver<LiveParameters> {
+    let parameters = LiveParameters::new(&public_config.parameters, &ClientParameters::default());
+    ConfigReloader::new(parameters).subscribe()
+}
+
 pub fn committee(n: usize) -> Arc<Committee> {
     Committee::new_test(vec![1; n])
 }
@@ -213,6 +220,7 @@ pub fn simulated_network_syncers_with_epoch_duration(
             commit_handler,
             config::node_defaults::default_shutdown_grace_period(),
             test_metrics(),
+            test_live_parameters(&NodePublicConfig::new_for_tests(n)),
         );
         drop(node_context);
         network_syncers.push(network_syncer);
@@ -246,6 +254,7 @@ pub async fn network_syncers_with_epoch_duration(
             config::node_defaults::default_shutdown_grace_period(),
             test_metrics(),
             &NodePublicConfig::new_for_tests(n),
+            test_live_parameters(&NodePublicConfig::new_for_tests(n)),
         );
         network_syncers.push(network_syncer);
     }
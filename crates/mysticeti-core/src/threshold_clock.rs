@@ -1,11 +1,11 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::cmp::Ordering;
+use std::{cmp::Ordering, time::Duration};
 
 use crate::{
     committee::{Committee, QuorumThreshold, StakeAggregator},
-    types::{BlockReference, RoundNumber, StatementBlock},
+    types::{AuthorityIndex, BlockReference, RoundNumber, StatementBlock},
 };
 
 // A block is threshold clock valid if:
@@ -36,6 +36,19 @@ pub fn threshold_clock_valid_non_genesis(block: &StatementBlock, committee: &Com
     is_quorum
 }
 
+/// A snapshot of the threshold clock of a validator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThresholdClockStatus {
+    /// The round the validator builds blocks for.
+    pub round: RoundNumber,
+    /// The authorities whose blocks of the current round were seen.
+    pub contributors: Vec<AuthorityIndex>,
+    /// The authorities whose blocks of the current round are still missing.
+    pub missing: Vec<AuthorityIndex>,
+    /// The time since the clock last advanced to a new round.
+    pub stalled_for: Duration,
+}
+
 pub struct ThresholdClockAggregator {
     aggregator: StakeAggregator<QuorumThreshold>,
     round: RoundNumber,
@@ -76,6 +89,20 @@ impl ThresholdClockAggregator {
     pub fn get_round(&self) -> RoundNumber {
         self.round
     }
+
+    /// The authorities whose blocks of the current round were seen.
+    pub fn contributors(&self) -> impl Iterator<Item = AuthorityIndex> + '_ {
+        self.aggregator.voters()
+    }
+
+    /// The authorities whose blocks of the current round are still missing.
+    pub fn missing(&self, committee: &Committee) -> Vec<AuthorityIndex> {
+        let contributors: Vec<_> = self.contributors().collect();
+        committee
+            .authorities()
+            .filter(|authority| !contributors.contains(authority))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -134,4 +161,21 @@ mod tests {
         aggregator.add_block(BlockReference::new_test(3, 1), &committee);
         assert_eq!(aggregator.get_round(), 2);
     }
+
+    #[test]
+    fn test_threshold_clock_contributors() {
+        let committee = Committee::new_test(vec![1, 1, 1, 1]);
+        let mut aggregator = ThresholdClockAggregator::new(0);
+
+        aggregator.add_block(BlockReference::new_test(0, 1), &committee);
+        aggregator.add_block(BlockReference::new_test(2, 1), &committee);
+        assert_eq!(aggregator.contributors().collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(aggregator.missing(&committee), vec![1, 3]);
+
+        // The contributors are reset when the clock advances.
+        aggregator.add_block(BlockReference::new_test(3, 1), &committee);
+        assert_eq!(aggregator.get_round(), 2);
+        assert_eq!(aggregator.contributors().count(), 0);
+        assert_eq!(aggregator.missing(&committee), vec![0, 1, 2, 3]);
+    }
 }
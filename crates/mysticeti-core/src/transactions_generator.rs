@@ -4,38 +4,49 @@
 use std::{cmp::min, sync::Arc, time::Duration};
 
 use rand::{rngs::StdRng, Rng, SeedableRng};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 
 use crate::{
-    config::{ClientParameters, NodePublicConfig},
+    config::{ClientParameters, LoadMode, NodePublicConfig},
     crypto::AsBytes,
     metrics::Metrics,
+    reload::LiveParameters,
     runtime::{self, timestamp_utc},
     types::{AuthorityIndex, Transaction},
 };
 
+/// The size of the submission timestamp that starts every transaction.
+pub const TIMESTAMP_SIZE: usize = 8;
+
 pub struct TransactionGenerator {
     sender: mpsc::Sender<Vec<Transaction>>,
     rng: StdRng,
     client_parameters: ClientParameters,
     node_public_config: NodePublicConfig,
     metrics: Arc<Metrics>,
+    parameters: watch::Receiver<LiveParameters>,
 }
 
 impl TransactionGenerator {
-    const TARGET_BLOCK_INTERVAL: Duration = Duration::from_millis(100);
-
     pub fn start(
         sender: mpsc::Sender<Vec<Transaction>>,
         seed: AuthorityIndex,
         client_parameters: ClientParameters,
         node_public_config: NodePublicConfig,
         metrics: Arc<Metrics>,
+        parameters: watch::Receiver<LiveParameters>,
     ) {
         assert!(client_parameters.transaction_size > 8 + 8); // 8 bytes timestamp + 8 bytes random
+        if client_parameters.mode != LoadMode::OpenLoop {
+            tracing::warn!(
+                "The generator only supports open-loop load, ignoring mode {}",
+                client_parameters.mode
+            );
+        }
         tracing::info!(
-            "Starting generator with {} transactions per second, initial delay {:?}",
+            "Starting generator with {} transactions per second ({} load), initial delay {:?}",
             client_parameters.load,
+            client_parameters.shape,
             client_parameters.initial_delay
         );
         runtime::Handle::current().spawn(
@@ -45,30 +56,32 @@ impl TransactionGenerator {
                 client_parameters,
                 node_public_config,
                 metrics,
+                parameters,
             }
             .run(),
         );
     }
 
     pub async fn run(mut self) {
-        let load = self.client_parameters.load;
-        let transactions_per_block_interval = (load + 9) / 10;
-        tracing::info!(
-            "Generating {transactions_per_block_interval} transactions per {} ms",
-            Self::TARGET_BLOCK_INTERVAL.as_millis()
-        );
         let max_block_size = self.node_public_config.parameters.max_block_size;
-        let target_block_size = min(max_block_size, transactions_per_block_interval);
+        let (mut interval, mut block_interval, mut load) = self.rate();
 
         let mut counter = 0;
         let mut tx_to_report = 0;
         let mut random: u64 = self.rng.gen(); // 8 bytes
         let zeros = vec![0u8; self.client_parameters.transaction_size - 8 - 8]; // 8 bytes timestamp + 8 bytes random
 
-        let mut interval = runtime::TimeInterval::new(Self::TARGET_BLOCK_INTERVAL);
         runtime::sleep(self.client_parameters.initial_delay).await;
+        let start = runtime::TimeInstant::now();
         loop {
             interval.tick().await;
+            if self.parameters.has_changed().unwrap_or(false) {
+                (interval, block_interval, load) = self.rate();
+            }
+            let shaped_load = self.client_parameters.shape.load(load, start.elapsed());
+            let transactions_per_block_interval =
+                (shaped_load as u128 * block_interval.as_millis()).div_ceil(1000) as usize;
+            let target_block_size = min(max_block_size, transactions_per_block_interval);
             let timestamp = (timestamp_utc().as_millis() as u64).to_le_bytes();
 
             let mut block = Vec::with_capacity(target_block_size);
@@ -106,10 +119,30 @@ impl TransactionGenerator {
         }
     }
 
-    pub fn extract_timestamp(transaction: &Transaction) -> Duration {
-        let bytes = transaction.as_bytes()[0..8]
+    /// The interval at which to submit transactions, its duration, and the configured load (in
+    /// transactions per second) before shaping.
+    fn rate(&mut self) -> (runtime::TimeInterval, Duration, usize) {
+        let parameters = self.parameters.borrow_and_update();
+        let block_interval = parameters.block_interval;
+        tracing::info!(
+            "Generating {} transactions per second every {} ms",
+            parameters.load,
+            block_interval.as_millis()
+        );
+        (
+            runtime::TimeInterval::new(block_interval),
+            block_interval,
+            parameters.load,
+        )
+    }
+
+    /// The submission time of the transaction, held in its first 8 bytes. None if the
+    /// transaction is too short to hold it.
+    pub fn extract_timestamp(transaction: &Transaction) -> Option<Duration> {
+        let bytes = transaction.as_bytes().get(..TIMESTAMP_SIZE)?;
+        let bytes = bytes
             .try_into()
-            .expect("Transactions should be at least 8 bytes");
-        Duration::from_millis(u64::from_le_bytes(bytes))
+            .expect("The slice has the size of a timestamp");
+        Some(Duration::from_millis(u64::from_le_bytes(bytes)))
     }
 }
@@ -17,13 +17,13 @@ pub type PublicKey = crate::crypto::PublicKey;
 use std::{
     fmt,
     hash::{Hash, Hasher},
-    ops::Range,
+    ops::{Deref, Range},
     time::Duration,
 };
 
 use digest::Digest;
 use eyre::{bail, ensure};
-use serde::{Deserialize, Serialize};
+use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
 #[cfg(test)]
 pub use test::Dag;
 
@@ -86,7 +86,7 @@ pub struct StatementBlock {
     includes: Vec<BlockReference>,
 
     // A list of base statements in order.
-    statements: Vec<BaseStatement>,
+    statements: BlockStatements,
 
     // Creation time of the block as reported by creator, currently not enforced
     meta_creation_time_ns: TimestampNs,
@@ -97,6 +97,98 @@ pub struct StatementBlock {
     signature: SignatureBytes,
 }
 
+/// The statements of a block. The block digest of blocks disseminated header-first (see
+/// `NodeParameters::header_first`) covers their statements through the digest of the statements,
+/// so that the header of the block can be verified without them. That digest is encoded as the
+/// first entry of the statements, so that the other blocks keep their encoding.
+#[derive(Clone, Default)]
+pub struct BlockStatements {
+    statements: Vec<BaseStatement>,
+    header_first: Option<HeaderFirst>,
+}
+
+#[derive(Clone, Copy)]
+struct HeaderFirst {
+    payload_digest: BlockDigest,
+    // Whether the statements were stripped from the block, see `StatementBlock::header`.
+    // Not covered by the digest: the header and the full block share the same reference.
+    header_only: bool,
+}
+
+/// The encoding of the entries of `BlockStatements`: the variants of `BaseStatement` (in the same
+/// order, so that they are encoded alike), then the digest of the statements of blocks
+/// disseminated header-first.
+#[derive(Serialize, Deserialize)]
+enum StatementEntry {
+    Share(Transaction),
+    Vote(TransactionLocator, Vote),
+    VoteRange(TransactionLocatorRange),
+    HeaderFirst(BlockDigest, bool),
+}
+
+impl BlockStatements {
+    /// The digest of the statements, if the block is disseminated header-first.
+    pub fn payload_digest(&self) -> Option<BlockDigest> {
+        self.header_first.map(|x| x.payload_digest)
+    }
+}
+
+impl Deref for BlockStatements {
+    type Target = Vec<BaseStatement>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.statements
+    }
+}
+
+impl Serialize for BlockStatements {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = self.statements.len() + self.header_first.is_some() as usize;
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        if let Some(header_first) = self.header_first {
+            let entry =
+                StatementEntry::HeaderFirst(header_first.payload_digest, header_first.header_only);
+            seq.serialize_element(&entry)?;
+        }
+        for statement in &self.statements {
+            seq.serialize_element(statement)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockStatements {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Vec::<StatementEntry>::deserialize(deserializer)?;
+        let mut statements = Vec::with_capacity(entries.len());
+        let mut header_first = None;
+        for (i, entry) in entries.into_iter().enumerate() {
+            let statement = match entry {
+                StatementEntry::Share(tx) => BaseStatement::Share(tx),
+                StatementEntry::Vote(locator, vote) => BaseStatement::Vote(locator, vote),
+                StatementEntry::VoteRange(range) => BaseStatement::VoteRange(range),
+                StatementEntry::HeaderFirst(payload_digest, header_only) if i == 0 => {
+                    header_first = Some(HeaderFirst {
+                        payload_digest,
+                        header_only,
+                    });
+                    continue;
+                }
+                StatementEntry::HeaderFirst(..) => {
+                    return Err(de::Error::custom(
+                        "payload digest after the first statement",
+                    ));
+                }
+            };
+            statements.push(statement);
+        }
+        Ok(Self {
+            statements,
+            header_first,
+        })
+    }
+}
+
 #[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize, Default)]
 pub struct AuthoritySet(u128); // todo - support more then 128 authorities
 
@@ -130,6 +222,9 @@ impl StatementBlock {
         ))
     }
 
+    /// Create and sign a block. The header of blocks created `header_first` can be disseminated
+    /// ahead of the block, see `NodeParameters::header_first`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new_with_signer(
         authority: AuthorityIndex,
         round: RoundNumber,
@@ -138,7 +233,16 @@ impl StatementBlock {
         meta_creation_time_ns: TimestampNs,
         epoch_marker: EpochStatus,
         signer: &Signer,
+        header_first: bool,
     ) -> Self {
+        let header_first = header_first.then(|| HeaderFirst {
+            payload_digest: BlockDigest::new_payload(&statements),
+            header_only: false,
+        });
+        let statements = BlockStatements {
+            statements,
+            header_first,
+        };
         let signature = signer.sign_block(
             authority,
             round,
@@ -147,7 +251,7 @@ impl StatementBlock {
             meta_creation_time_ns,
             epoch_marker,
         );
-        Self::new(
+        Self::new_with_statements(
             authority,
             round,
             includes,
@@ -166,6 +270,30 @@ impl StatementBlock {
         meta_creation_time_ns: TimestampNs,
         epoch_marker: EpochStatus,
         signature: SignatureBytes,
+    ) -> Self {
+        let statements = BlockStatements {
+            statements,
+            header_first: None,
+        };
+        Self::new_with_statements(
+            authority,
+            round,
+            includes,
+            statements,
+            meta_creation_time_ns,
+            epoch_marker,
+            signature,
+        )
+    }
+
+    fn new_with_statements(
+        authority: AuthorityIndex,
+        round: RoundNumber,
+        includes: Vec<BlockReference>,
+        statements: BlockStatements,
+        meta_creation_time_ns: TimestampNs,
+        epoch_marker: EpochStatus,
+        signature: SignatureBytes,
     ) -> Self {
         Self {
             reference: BlockReference {
@@ -189,6 +317,46 @@ impl StatementBlock {
         }
     }
 
+    /// The header of the block: the block without its statements. Headers are verified and
+    /// accepted in the DAG like full blocks (they have the same reference), so that rounds can
+    /// advance before the payload of the block is received. Only the headers of blocks created
+    /// header-first verify.
+    pub fn header(&self) -> Self {
+        let payload_digest = self
+            .statements
+            .payload_digest()
+            .unwrap_or_else(|| BlockDigest::new_payload(&self.statements));
+        Self {
+            reference: self.reference,
+            includes: self.includes.clone(),
+            statements: BlockStatements {
+                statements: vec![],
+                header_first: Some(HeaderFirst {
+                    payload_digest,
+                    header_only: true,
+                }),
+            },
+            meta_creation_time_ns: self.meta_creation_time_ns,
+            epoch_marker: self.epoch_marker,
+            signature: self.signature,
+        }
+    }
+
+    /// Whether this is the header of a block, without its statements.
+    pub fn is_header_only(&self) -> bool {
+        self.statements
+            .header_first
+            .is_some_and(|header_first| header_first.header_only)
+    }
+
+    /// Whether the block carries no transactions, only references and votes.
+    pub fn is_vote_only(&self) -> bool {
+        !self
+            .statements
+            .iter()
+            .any(|statement| matches!(statement, BaseStatement::Share(_)))
+    }
+
     pub fn reference(&self) -> &BlockReference {
         &self.reference
     }
@@ -198,6 +366,12 @@ impl StatementBlock {
     }
 
     pub fn statements(&self) -> &Vec<BaseStatement> {
+        &self.statements.statements
+    }
+
+    /// The statements of the block, along with their digest if the block is disseminated
+    /// header-first.
+    pub fn block_statements(&self) -> &BlockStatements {
         &self.statements
     }
 
@@ -273,6 +447,23 @@ impl StatementBlock {
 
     pub fn verify(&self, committee: &Committee) -> eyre::Result<()> {
         let round = self.round();
+        match self.statements.header_first {
+            Some(header_first) if header_first.header_only => ensure!(
+                self.statements.is_empty(),
+                "Header of block {} has statements",
+                self.reference
+            ),
+            Some(header_first) => {
+                let payload_digest = BlockDigest::new_payload(&self.statements);
+                ensure!(
+                    payload_digest == header_first.payload_digest,
+                    "Payload digest does not match, calculated {:?}, provided {:?}",
+                    payload_digest,
+                    header_first.payload_digest
+                );
+            }
+            None => {}
+        }
         let digest = BlockDigest::new(
             self.author(),
             round,
@@ -312,7 +503,7 @@ impl StatementBlock {
                 round
             );
         }
-        for statement in &self.statements {
+        for statement in self.statements.iter() {
             // Also check duplicate statements?
             match statement {
                 BaseStatement::Share(_) => {}
@@ -681,7 +872,7 @@ mod test {
             StatementBlock {
                 reference,
                 includes,
-                statements: vec![],
+                statements: Default::default(),
                 meta_creation_time_ns: 0,
                 epoch_marker: false,
                 signature: Default::default(),
@@ -2,43 +2,102 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    future::Future,
     net::{IpAddr, Ipv4Addr},
     sync::Arc,
+    thread,
 };
 
 use ::prometheus::Registry;
-use eyre::{eyre, Context, Result};
+use eyre::{ensure, eyre, Context, Result};
+use tokio::sync::{broadcast, mpsc};
 
 use crate::{
     block_handler::{RealBlockHandler, TestCommitHandler},
     block_store::BlockStore,
+    checkpoint::Checkpoints,
     committee::Committee,
     config::{ClientParameters, NodePrivateConfig, NodePublicConfig},
+    consensus::linearizer::CommittedSubDag,
     core::{Core, CoreOptions},
-    log::TransactionLog,
+    dag_export::DagExporter,
+    log::{TransactionLog, TransactionLogFlusher},
     metrics::Metrics,
     net_sync::NetworkSyncer,
     network::Network,
     prometheus,
+    reload::{ConfigReloader, LiveParameters},
     runtime::{JoinError, JoinHandle},
+    submission,
     transactions_generator::TransactionGenerator,
-    types::AuthorityIndex,
+    types::{AuthorityIndex, Transaction},
     wal::{self, walf},
 };
 
+/// Capacity of the channel delivering committed sub-dags to subscribers. Subscribers that fall
+/// further behind miss the oldest commits.
+const COMMIT_SUBSCRIPTION_CAPACITY: usize = 1024;
+
 pub struct Validator {
     network_synchronizer: NetworkSyncer<RealBlockHandler, TestCommitHandler<TransactionLog>>,
     metrics_handle: JoinHandle<Result<(), hyper::Error>>,
+    reloader: ConfigReloader,
+    transaction_sender: mpsc::Sender<Vec<Transaction>>,
+    commit_sender: broadcast::Sender<CommittedSubDag>,
+    transaction_logs: Vec<TransactionLogFlusher>,
+    /// Reporting tasks that run until the validator shuts down.
+    background_tasks: Vec<JoinHandle<()>>,
+    /// The thread archiving the committed transactions (if enabled), which exits once the
+    /// commits are no longer delivered.
+    archive: Option<thread::JoinHandle<()>>,
 }
 
-impl Validator {
-    pub async fn start(
-        authority: AuthorityIndex,
-        committee: Arc<Committee>,
-        public_config: NodePublicConfig,
-        private_config: NodePrivateConfig,
-        client_parameters: ClientParameters,
-    ) -> Result<Self> {
+/// Assembles a validator from its configuration. The committee, public and private configs are
+/// required; the authority of the validator is the one of the private config.
+#[derive(Default)]
+pub struct ValidatorBuilder {
+    committee: Option<Arc<Committee>>,
+    public_config: Option<NodePublicConfig>,
+    private_config: Option<NodePrivateConfig>,
+    client_parameters: Option<ClientParameters>,
+    registry: Option<Registry>,
+}
+
+impl ValidatorBuilder {
+    pub fn committee(mut self, committee: Arc<Committee>) -> Self {
+        self.committee = Some(committee);
+        self
+    }
+
+    pub fn public_config(mut self, public_config: NodePublicConfig) -> Self {
+        self.public_config = Some(public_config);
+        self
+    }
+
+    pub fn private_config(mut self, private_config: NodePrivateConfig) -> Self {
+        self.private_config = Some(private_config);
+        self
+    }
+
+    /// Run the built-in transaction generator with these parameters (for benchmarks).
+    pub fn client_parameters(mut self, client_parameters: ClientParameters) -> Self {
+        self.client_parameters = Some(client_parameters);
+        self
+    }
+
+    /// Register the metrics of the validator in an existing registry.
+    pub fn registry(mut self, registry: Registry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Recover the state of the validator from its WAL and start it.
+    pub async fn start(self) -> Result<Validator> {
+        let committee = self.committee.ok_or(eyre!("Missing committee"))?;
+        let public_config = self.public_config.ok_or(eyre!("Missing public config"))?;
+        let private_config = self.private_config.ok_or(eyre!("Missing private config"))?;
+        let authority = private_config.authority();
+
         let network_address = public_config
             .network_address(authority)
             .ok_or(eyre!("No network address for authority {authority}"))
@@ -50,20 +109,43 @@ impl Validator {
             .metrics_address(authority)
             .ok_or(eyre!("No metrics address for authority {authority}"))
             .wrap_err("Unknown authority")?;
-        let mut binding_metrics_address = metrics_address;
-        binding_metrics_address.set_ip(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let binding_metrics_address = private_config.metrics_server.bind_address(metrics_address);
 
         // Boot the prometheus server.
-        let registry = Registry::new();
+        let registry = self.registry.unwrap_or_default();
         let (metrics, reporter) = Metrics::new(&registry, Some(&committee));
-        reporter.start();
-
-        let metrics_handle =
-            prometheus::start_prometheus_server(binding_metrics_address, &registry);
+        let mut background_tasks = vec![reporter.start()];
+
+        let reloader = ConfigReloader::new(LiveParameters::new(
+            &public_config.parameters,
+            self.client_parameters
+                .as_ref()
+                .unwrap_or(&ClientParameters::default()),
+        ));
+        let checkpoints = Checkpoints::default();
+        let dag_exporter = DagExporter::default();
+        let admin = prometheus::AdminState::new(authority, committee.clone(), metrics.clone())
+            .with_reloader(reloader.clone())
+            .with_checkpoints(checkpoints.clone())
+            .with_dag_exporter(dag_exporter.clone());
+        let metrics_handle = prometheus::start_prometheus_server(
+            binding_metrics_address,
+            &registry,
+            admin,
+            &private_config.metrics_server,
+        )
+        .wrap_err("Failed to start the metrics server")?;
+        if let Some(push) = private_config.metrics_push.clone() {
+            background_tasks.push(prometheus::start_metrics_push(authority, &registry, push));
+        }
 
         // Open the block store.
-        let wal_file =
-            wal::open_file_for_wal(private_config.wal()).expect("Failed to open wal file");
+        let wal_file = if public_config.parameters.wal_write_through {
+            wal::open_file_for_wal_write_through(private_config.wal())
+        } else {
+            wal::open_file_for_wal(private_config.wal())
+        }
+        .expect("Failed to open wal file");
         let (wal_writer, wal_reader) = walf(wal_file).expect("Failed to open wal");
         let recovered = BlockStore::open(
             authority,
@@ -72,6 +154,7 @@ impl Validator {
             metrics.clone(),
             &committee,
         );
+        dag_exporter.set_block_store(recovered.block_store.clone());
 
         // Boot the validator node.
         let (block_handler, block_sender) = RealBlockHandler::new(
@@ -83,22 +166,76 @@ impl Validator {
             public_config.parameters.consensus_only,
         );
 
-        TransactionGenerator::start(
-            block_sender,
-            authority,
-            client_parameters,
-            public_config.clone(),
-            metrics.clone(),
-        );
+        if let Some(client_parameters) = self.client_parameters {
+            TransactionGenerator::start(
+                block_sender.clone(),
+                authority,
+                client_parameters,
+                public_config.clone(),
+                metrics.clone(),
+                reloader.subscribe(),
+            );
+        }
         let committed_transaction_log =
             TransactionLog::start(private_config.committed_transactions_log())
                 .expect("Failed to open committed transaction log for write");
+        let transaction_logs = vec![
+            block_handler.transaction_log_flusher(),
+            committed_transaction_log.flusher(),
+        ];
+        let (commit_sender, _) = broadcast::channel(COMMIT_SUBSCRIPTION_CAPACITY);
+        if let Some(address) = private_config.submission_address {
+            let server = submission::start_submission_server(
+                address,
+                block_sender.clone(),
+                commit_sender.clone(),
+                authority,
+            )
+            .wrap_err("Failed to start the submission server")?;
+            background_tasks.push(server);
+        }
+        if let Some(address) = private_config.grpc_address {
+            #[cfg(feature = "grpc")]
+            background_tasks.push(
+                crate::grpc::start_grpc_server(
+                    address,
+                    block_sender.clone(),
+                    commit_sender.clone(),
+                    authority,
+                )
+                .wrap_err("Failed to start the gRPC server")?,
+            );
+            #[cfg(not(feature = "grpc"))]
+            return Err(eyre!(
+                "Cannot serve gRPC on {address}: built without the `grpc` feature"
+            ));
+        }
+        let archive = match private_config.archive_transactions {
+            #[cfg(feature = "archive")]
+            true => Some(
+                crate::archive::TransactionArchive::open(private_config.transaction_archive())
+                    .wrap_err("Failed to open the transaction archive")?
+                    .start(commit_sender.subscribe())
+                    .wrap_err("Failed to start the transaction archive")?,
+            ),
+            #[cfg(not(feature = "archive"))]
+            true => {
+                return Err(eyre!(
+                    "Cannot archive transactions: built without the `archive` feature"
+                ))
+            }
+            false => None,
+        };
         let commit_handler = TestCommitHandler::new_with_handler(
             committee.clone(),
             block_handler.transaction_time.clone(),
             metrics.clone(),
             committed_transaction_log,
-        );
+        )
+        .with_certified_time(block_handler.certified_time.clone())
+        .with_commit_sender(commit_sender.clone())
+        .with_checkpoints(checkpoints)
+        .with_dag_exporter(dag_exporter);
         let core = Core::open(
             block_handler,
             authority,
@@ -125,16 +262,73 @@ impl Validator {
             public_config.parameters.shutdown_grace_period,
             metrics,
             &public_config,
+            reloader.subscribe(),
         );
 
         tracing::info!("Validator {authority} listening on {network_address}");
         tracing::info!("Validator {authority} exposing metrics on {metrics_address}");
 
-        Ok(Self {
+        Ok(Validator {
             network_synchronizer,
             metrics_handle,
+            reloader,
+            transaction_sender: block_sender,
+            commit_sender,
+            transaction_logs,
+            background_tasks,
+            archive,
         })
     }
+}
+
+impl Validator {
+    pub fn builder() -> ValidatorBuilder {
+        ValidatorBuilder::default()
+    }
+
+    /// Start a validator running the transaction generator (for benchmarks).
+    pub async fn start(
+        authority: AuthorityIndex,
+        committee: Arc<Committee>,
+        public_config: NodePublicConfig,
+        private_config: NodePrivateConfig,
+        client_parameters: ClientParameters,
+    ) -> Result<Self> {
+        ensure!(
+            private_config.authority() == authority,
+            "The private config belongs to authority {}, not {authority}",
+            private_config.authority()
+        );
+        Self::builder()
+            .committee(committee)
+            .public_config(public_config)
+            .private_config(private_config)
+            .client_parameters(client_parameters)
+            .start()
+            .await
+    }
+
+    /// Submit transactions to be included in the next blocks of this validator. The first 8 bytes
+    /// of a transaction are its submission time in milliseconds, used to measure latency.
+    pub fn transaction_sender(&self) -> mpsc::Sender<Vec<Transaction>> {
+        self.transaction_sender.clone()
+    }
+
+    /// Receive the sub-dags committed from now on, in commit order.
+    pub fn subscribe_commits(&self) -> broadcast::Receiver<CommittedSubDag> {
+        self.commit_sender.subscribe()
+    }
+
+    /// Resolves once the core thread of the validator has exited, e.g. once the validator and its
+    /// runtime are dropped without shutting down.
+    pub fn core_stopped(&self) -> impl Future<Output = ()> + 'static {
+        self.network_synchronizer.core_stopped()
+    }
+
+    /// Changes the configuration of the running validator.
+    pub fn reloader(&self) -> &ConfigReloader {
+        &self.reloader
+    }
 
     pub async fn await_completion(
         self,
@@ -148,8 +342,45 @@ impl Validator {
         )
     }
 
-    pub async fn stop(self) {
+    /// Run until the validator stops on its own, or shut it down gracefully once `signal`
+    /// resolves.
+    pub async fn run_until(mut self, signal: impl Future<Output = ()>) -> Result<()> {
+        let completed = tokio::select! {
+            result = self.network_synchronizer.completed() => Some(result),
+            _signal = signal => None,
+        };
+        match completed {
+            Some(result) => result.wrap_err("Validator crashed"),
+            None => self.shutdown().await,
+        }
+    }
+
+    /// Stop the validator: block production stops, connections to peers are drained, and the WAL
+    /// and transaction logs are synced to disk, so that a restart does not recover from a dirty
+    /// state. Resolves once all tasks of the validator have exited.
+    pub async fn shutdown(self) -> Result<()> {
         self.network_synchronizer.shutdown().await;
+        for log in &self.transaction_logs {
+            log.flush()
+                .await
+                .wrap_err("Failed to flush transaction log")?;
+        }
+        self.metrics_handle.abort();
+        self.metrics_handle.await.ok();
+        for task in self.background_tasks {
+            task.abort();
+            task.await.ok();
+        }
+        // The archive stops once every sender of the commits is dropped, after archiving the
+        // commits delivered so far.
+        drop(self.commit_sender);
+        if let Some(archive) = self.archive {
+            tokio::task::spawn_blocking(move || archive.join())
+                .await
+                .wrap_err("Failed to wait for the transaction archive")?
+                .map_err(|_| eyre!("The transaction archive panicked"))?;
+        }
+        Ok(())
     }
 }
 
@@ -164,6 +395,7 @@ mod smoke_tests {
     use crate::{
         committee::Committee,
         config::{self, ClientParameters, NodePrivateConfig, NodePublicConfig},
+        consistency::ConsistencyChecker,
         prometheus,
         types::AuthorityIndex,
     };
@@ -349,4 +581,116 @@ mod smoke_tests {
             _ = time::sleep(timeout) => panic!("Failed to gather commits within a few timeouts"),
         }
     }
+
+    /// Ensure a validator assembled with the builder delivers its commits to subscribers.
+    #[tokio::test]
+    async fn validator_builder_commits() {
+        let committee_size = 4;
+        let committee = Committee::new_for_benchmarks(committee_size);
+        let public_config = NodePublicConfig::new_for_tests(committee_size).with_port_offset(300);
+
+        let dir = TempDir::new("validator_builder_commits").unwrap();
+        let mut validators = Vec::new();
+        for private_config in NodePrivateConfig::new_for_benchmarks(dir.as_ref(), committee_size) {
+            fs::create_dir_all(&private_config.storage_path).unwrap();
+            let validator = Validator::builder()
+                .committee(committee.clone())
+                .public_config(public_config.clone())
+                .private_config(private_config)
+                .start()
+                .await
+                .unwrap();
+            validators.push(validator);
+        }
+
+        let mut commits = validators[0].subscribe_commits();
+        let timeout = config::node_defaults::default_leader_timeout() * 5;
+        let commit = tokio::select! {
+            commit = commits.recv() => commit.unwrap(),
+            _ = time::sleep(timeout) => panic!("Failed to receive a commit within a few timeouts"),
+        };
+        assert!(!commit.blocks.is_empty());
+    }
+
+    /// Ensure a validator that shut down restarts from its storage and commits again.
+    #[tokio::test]
+    async fn validator_shutdown_and_restart() {
+        let committee_size = 4;
+        let committee = Committee::new_for_benchmarks(committee_size);
+        let public_config = NodePublicConfig::new_for_tests(committee_size).with_port_offset(400);
+
+        let dir = TempDir::new("validator_shutdown_and_restart").unwrap();
+        let mut validators = Vec::new();
+        for private_config in NodePrivateConfig::new_for_benchmarks(dir.as_ref(), committee_size) {
+            fs::create_dir_all(&private_config.storage_path).unwrap();
+            let validator = Validator::builder()
+                .committee(committee.clone())
+                .public_config(public_config.clone())
+                .private_config(private_config)
+                .start()
+                .await
+                .unwrap();
+            validators.push(validator);
+        }
+
+        let timeout = config::node_defaults::default_leader_timeout() * 5;
+        let mut commits = validators[0].subscribe_commits();
+        tokio::select! {
+            commit = commits.recv() => commit.unwrap(),
+            _ = time::sleep(timeout) => panic!("Failed to receive a commit within a few timeouts"),
+        };
+        validators.remove(0).shutdown().await.unwrap();
+
+        let private_config =
+            NodePrivateConfig::new_for_benchmarks(dir.as_ref(), committee_size).remove(0);
+        let validator = Validator::builder()
+            .committee(committee.clone())
+            .public_config(public_config.clone())
+            .private_config(private_config)
+            .start()
+            .await
+            .unwrap();
+        let mut commits = validator.subscribe_commits();
+        tokio::select! {
+            commit = commits.recv() => commit.unwrap(),
+            _ = time::sleep(timeout) => panic!("Failed to commit after restart"),
+        };
+    }
+
+    /// Ensure the checkpoints of honest validators agree.
+    #[tokio::test]
+    async fn validator_checkpoints_agree() {
+        let committee_size = 4;
+        let committee = Committee::new_for_benchmarks(committee_size);
+        let public_config = NodePublicConfig::new_for_tests(committee_size).with_port_offset(600);
+
+        let dir = TempDir::new("validator_checkpoints_agree").unwrap();
+        let mut validators = Vec::new();
+        for private_config in NodePrivateConfig::new_for_benchmarks(dir.as_ref(), committee_size) {
+            fs::create_dir_all(&private_config.storage_path).unwrap();
+            let validator = Validator::builder()
+                .committee(committee.clone())
+                .public_config(public_config.clone())
+                .private_config(private_config)
+                .start()
+                .await
+                .unwrap();
+            validators.push(validator);
+        }
+
+        let checker = ConsistencyChecker::new(public_config.all_metric_addresses().collect());
+        let compared = async {
+            loop {
+                time::sleep(Duration::from_millis(100)).await;
+                if let Some(height) = checker.check().await.unwrap() {
+                    break height;
+                }
+            }
+        };
+        let timeout = config::node_defaults::default_leader_timeout() * 5;
+        tokio::select! {
+            height = compared => assert!(height > 0),
+            _ = time::sleep(timeout) => panic!("Failed to compare checkpoints within a few timeouts"),
+        }
+    }
 }
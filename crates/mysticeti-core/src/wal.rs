@@ -7,7 +7,11 @@ use std::{
     fs::{File, OpenOptions},
     io,
     io::{IoSlice, Seek, SeekFrom, Write},
-    os::fd::{AsRawFd, RawFd},
+    mem,
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::fs::OpenOptionsExt,
+    },
     path::Path,
 };
 
@@ -19,6 +23,11 @@ use serde::{Deserialize, Serialize};
 pub struct WalWriter {
     file: File,
     pos: u64,
+    /// The wal is pruned up to this offset.
+    pruned: u64,
+    sync_every_write: bool,
+    /// Reused to serialize the entries written with write_serialized.
+    buffer: Vec<u8>,
 }
 
 pub struct WalReader {
@@ -39,9 +48,14 @@ pub struct WalPosition {
 
 pub type Tag = u32;
 
+/// Opens the wal for recovery: an entry torn by a crash in the middle of a write at the end of
+/// the wal is truncated, so that new entries are appended right after the last complete one.
 pub fn walf(mut file: File) -> io::Result<(WalWriter, WalReader)> {
     file.seek(SeekFrom::End(0))?;
-    make_wal(file)
+    let (mut writer, reader) = make_wal(file)?;
+    writer.truncate_torn_tail(&reader)?;
+    writer.pruned = reader.first_position(writer.pos).start;
+    Ok((writer, reader))
 }
 
 /// Opens file with mode suitable for walf
@@ -53,6 +67,16 @@ pub fn open_file_for_wal(p: impl AsRef<Path>) -> io::Result<File> {
         .open(p)
 }
 
+/// Opens file with mode suitable for walf, every write reaches the disk before it returns (O_DSYNC)
+pub fn open_file_for_wal_write_through(p: impl AsRef<Path>) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .custom_flags(libc::O_DSYNC)
+        .open(p)
+}
+
 /// Creates wal reader and wal writer on the file.
 /// WalWriter methods generally take &mut references so normally only one thread can access WalWriter at a time.
 /// WalReader methods take & reference, you can wrap WalReader in Arc and safely share it across different threads.
@@ -97,6 +121,9 @@ fn make_wal(file: File) -> io::Result<(WalWriter, WalReader)> {
     };
     let writer = WalWriter {
         pos: file.metadata()?.len(),
+        pruned: 0,
+        sync_every_write: false,
+        buffer: Vec::new(),
         file,
     };
     Ok((writer, reader))
@@ -156,6 +183,21 @@ impl WalWriter {
         self.writev(tag, &[IoSlice::new(b)])
     }
 
+    /// Serialize the value with bincode and write it, reusing the same buffer for every entry.
+    pub fn write_serialized<T: Serialize + ?Sized>(
+        &mut self,
+        tag: Tag,
+        value: &T,
+    ) -> io::Result<WalPosition> {
+        let mut buffer = mem::take(&mut self.buffer);
+        buffer.clear();
+        bincode::serialize_into(&mut buffer, value)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let position = self.write(tag, &buffer);
+        self.buffer = buffer;
+        position
+    }
+
     pub fn writev(&mut self, tag: Tag, v: &[IoSlice]) -> io::Result<WalPosition> {
         let v_len = v.iter().map(|s| s.len()).sum::<usize>();
         let len = v_len as u64 + HEADER_LEN_BYTES;
@@ -191,15 +233,81 @@ impl WalWriter {
         written_expected += len as usize;
         let written = self.file.write_vectored(&buffs)?;
         assert_eq!(written, written_expected);
+        if self.sync_every_write {
+            self.file.sync_data()?;
+        }
         let position = WalPosition { start: self.pos };
         self.pos += len;
         Ok(position)
     }
 
+    /// Sync the wal to disk after every write.
+    pub fn set_sync_every_write(&mut self, sync_every_write: bool) {
+        self.sync_every_write = sync_every_write;
+    }
+
     pub fn sync(&self) -> io::Result<()> {
         self.file.sync_data()
     }
 
+    /// Current size of the wal file in bytes.
+    pub fn file_size(&self) -> u64 {
+        self.pos
+    }
+
+    /// Number of MAP_SIZE segments spanned by the wal file.
+    /// Entries never cross a segment boundary, each segment is mapped independently by the reader.
+    pub fn segments(&self) -> u64 {
+        self.pos.div_ceil(MAP_SIZE)
+    }
+
+    /// Release the disk space of the segments of the wal entirely below `position`, returning the
+    /// number of bytes released. Pruned segments read as zeros and are skipped when iterating the
+    /// wal, so the entries they contained must not be needed for recovery. The segment currently
+    /// written is never pruned.
+    pub fn prune(&mut self, position: WalPosition) -> io::Result<u64> {
+        let end = offset(position.start.min(self.pos));
+        if end <= self.pruned {
+            return Ok(0);
+        }
+        punch_hole(&self.file, self.pruned, end - self.pruned)?;
+        let released = end - self.pruned;
+        self.pruned = end;
+        Ok(released)
+    }
+
+    /// Truncate everything after the last complete entry of the wal. A valid wal always ends with a
+    /// complete entry, since padding is written together with the entry that follows it. Entries
+    /// do not cross segments, so only the last segments are scanned, back to the one holding the
+    /// last complete entry (usually the last segment).
+    fn truncate_torn_tail(&mut self, reader: &WalReader) -> io::Result<()> {
+        let mut segment = offset(self.pos.saturating_sub(1));
+        let valid_end = loop {
+            let end = reader
+                .scan_from(WalPosition { start: segment }, self)
+                .filter_map(|(position, entry)| {
+                    let (_, data) = entry.ok()?;
+                    Some(position.start + HEADER_LEN_BYTES + data.len() as u64)
+                })
+                .last();
+            match end {
+                Some(end) => break end,
+                None if segment == 0 => break 0,
+                None => segment -= MAP_SIZE,
+            }
+        };
+        if valid_end < self.pos {
+            tracing::warn!(
+                "Truncating {} bytes torn at the end of the wal",
+                self.pos - valid_end
+            );
+            self.file.set_len(valid_end)?;
+            self.file.seek(SeekFrom::Start(valid_end))?;
+            self.pos = valid_end;
+        }
+        Ok(())
+    }
+
     /// Allow to retrieve a 'syncer' instance that allows
     /// to fsync wal to disk without acquiring a lock on wal itself.
     ///
@@ -211,6 +319,24 @@ impl WalWriter {
     }
 }
 
+#[cfg(target_os = "linux")]
+fn punch_hole(file: &File, offset: u64, len: u64) -> io::Result<()> {
+    let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+    let result = unsafe { libc::fallocate(file.as_raw_fd(), mode, offset as i64, len as i64) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn punch_hole(_file: &File, _offset: u64, _len: u64) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Pruning the wal is only supported on linux",
+    ))
+}
+
 impl WalSyncer {
     pub fn sync(&self) -> io::Result<()> {
         self.file.sync_data()
@@ -265,6 +391,27 @@ impl WalReader {
         Ok(Some((tag, bytes)))
     }
 
+    /// Scan all entries up to writer position at the time scan_until(...) is called.
+    /// Unlike iter_until(...), corrupted entries are reported instead of panicking. Since entries
+    /// never cross a segment boundary, the scan resumes at the next segment after a corrupted header.
+    pub fn scan_until(&self, w: &WalWriter) -> WalScan {
+        self.scan_from(self.first_position(w.pos), w)
+    }
+
+    /// Scan the entries from `position` (e.g., the start of a segment) up to the writer position.
+    fn scan_from(&self, position: WalPosition, w: &WalWriter) -> WalScan {
+        WalScan {
+            wal_reader: self,
+            position: Some(position),
+            end_position: w.pos,
+        }
+    }
+
+    /// Whether segments at the start of the wal were released by WalWriter::prune.
+    pub fn is_pruned(&self, w: &WalWriter) -> bool {
+        self.first_position(w.pos).start > 0
+    }
+
     // Attempts cleaning internal mem maps, returning number of retained maps
     // Map can be freed when all buffers linked to this portion of a file are dropped
     pub fn cleanup(&self) -> usize {
@@ -277,11 +424,25 @@ impl WalReader {
     pub fn iter_until(&self, w: &WalWriter) -> WalIterator {
         WalIterator {
             wal_reader: self,
-            position: Some(WalPosition { start: 0 }),
+            position: Some(self.first_position(w.pos)),
             end_position: w.pos,
         }
     }
 
+    /// The position of the first entry after the segments released by WalWriter::prune.
+    /// A segment always starts with an entry, so segments starting with an empty header are pruned.
+    fn first_position(&self, end_position: u64) -> WalPosition {
+        let mut position = WalPosition { start: 0 };
+        while position.start + HEADER_LEN_BYTES <= end_position {
+            let bytes = self.map_offset(position.start).expect("Failed to map wal");
+            if Self::read_header(&bytes) != (0, 0, 0) {
+                break;
+            }
+            position = position.next_start_offset();
+        }
+        position
+    }
+
     fn map_offset(&self, offset: u64) -> io::Result<Bytes> {
         let mut maps = self.maps.lock();
         let bytes = match maps.entry(offset) {
@@ -348,6 +509,89 @@ impl<'a> WalIterator<'a> {
     }
 }
 
+/// A problem found by WalReader::scan_until in the entry at some position of the wal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalCorruption {
+    /// The header is zero where an entry is expected.
+    EmptyHeader,
+    /// The header has a zero length but a non-zero crc.
+    NonZeroCrc { crc: u64 },
+    /// The length in the header is too small, crosses a segment or runs past the end of the wal.
+    InvalidLength { len: u64 },
+    /// The crc in the header does not match the data of the entry.
+    CrcMismatch { expected: u64, found: u64 },
+}
+
+pub struct WalScan<'a> {
+    wal_reader: &'a WalReader,
+    position: Option<WalPosition>,
+    end_position: u64,
+}
+
+impl<'a> Iterator for WalScan<'a> {
+    type Item = (WalPosition, Result<(Tag, Bytes), WalCorruption>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let position = self.position.take()?;
+            if position.start >= self.end_position {
+                return None;
+            }
+            let offset = offset(position.start);
+            let buf_offset = position.start - offset;
+            // Not enough room left in the segment for a header, the writer padded it
+            if buf_offset + HEADER_LEN_BYTES > MAP_SIZE {
+                self.position = Some(position.next_start_offset());
+                continue;
+            }
+            if position.start + HEADER_LEN_BYTES > self.end_position {
+                return Some((position, Err(WalCorruption::InvalidLength { len: 0 })));
+            }
+            let bytes = self
+                .wal_reader
+                .map_offset(offset)
+                .expect("Failed to map wal");
+            let buf_offset = buf_offset as usize;
+            let (crc, len, tag) = WalReader::read_header(&bytes[buf_offset..]);
+            if len == 0 && crc == 0 && !position.first_in_map() {
+                // Padding until the end of the segment
+                self.position = Some(position.next_start_offset());
+                continue;
+            }
+            let corruption = if len == 0 && crc == 0 {
+                Some(WalCorruption::EmptyHeader)
+            } else if len == 0 {
+                Some(WalCorruption::NonZeroCrc { crc })
+            } else if len < HEADER_LEN_BYTES
+                || buf_offset as u64 + len > MAP_SIZE
+                || position.start + len > self.end_position
+            {
+                Some(WalCorruption::InvalidLength { len })
+            } else {
+                None
+            };
+            if let Some(corruption) = corruption {
+                self.position = Some(position.next_start_offset());
+                return Some((position, Err(corruption)));
+            }
+            // The length is plausible, so the scan can carry on after this entry even if its data is corrupted
+            self.position = Some(position.add(len));
+            let data = bytes.slice(buf_offset + HEADER_LEN_BYTES_USIZE..buf_offset + len as usize);
+            let found = crc32fast::hash(data.as_ref()) as u64;
+            if found != crc {
+                return Some((
+                    position,
+                    Err(WalCorruption::CrcMismatch {
+                        expected: crc,
+                        found,
+                    }),
+                ));
+            }
+            return Some((position, Ok((tag, data))));
+        }
+    }
+}
+
 impl WalPosition {
     pub const MAX: WalPosition = WalPosition { start: u64::MAX };
 
@@ -386,10 +630,42 @@ impl fmt::Display for WalPosition {
     }
 }
 
+impl fmt::Display for WalCorruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalCorruption::EmptyHeader => write!(f, "empty header"),
+            WalCorruption::NonZeroCrc { crc } => write!(f, "non-zero crc {crc} at len 0"),
+            WalCorruption::InvalidLength { len } => write!(f, "invalid length {len}"),
+            WalCorruption::CrcMismatch { expected, found } => {
+                write!(f, "crc mismatch, expected {expected}, found {found}")
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_wal_size() {
+        let temp = tempdir::TempDir::new("test_wal_size").unwrap();
+        let (mut writer, _reader) = wal(temp.path().join("wal")).unwrap();
+        assert_eq!(writer.file_size(), 0);
+        assert_eq!(writer.segments(), 0);
+
+        writer.write(1, &[1u8; 1024]).unwrap();
+        assert_eq!(writer.file_size(), 1024 + HEADER_LEN_BYTES);
+        assert_eq!(writer.segments(), 1);
+
+        // Does not fit in the remainder of the first segment, so it is padded to the next one
+        writer
+            .write(2, &[2u8; (MAP_SIZE - HEADER_LEN_BYTES) as usize])
+            .unwrap();
+        assert_eq!(writer.file_size(), 2 * MAP_SIZE);
+        assert_eq!(writer.segments(), 2);
+    }
+
     #[test]
     fn test_wal() {
         let temp = tempdir::TempDir::new("test_wal").unwrap();
@@ -505,4 +781,128 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_walf_truncates_torn_tail() {
+        let temp = tempdir::TempDir::new("test_walf_torn_tail").unwrap();
+        let file = temp.path().join("wal");
+        let (mut writer, reader) = wal(&file).unwrap();
+        writer.write(1, &[1u8; 15]).unwrap();
+        let two_pos = writer.write(2, &[2u8; 18]).unwrap();
+        drop(reader);
+        drop(writer);
+
+        // Crash in the middle of writing the second entry
+        let len = std::fs::metadata(&file).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_len(len - 5)
+            .unwrap();
+
+        let (mut writer, reader) = walf(open_file_for_wal(&file).unwrap()).unwrap();
+        assert_eq!(writer.file_size(), two_pos.start);
+        let three_pos = writer.write(3, &[3u8; 20]).unwrap();
+        assert_eq!(three_pos, two_pos);
+        let tags: Vec<_> = reader
+            .iter_until(&writer)
+            .map(|(_, (tag, _))| tag)
+            .collect();
+        assert_eq!(tags, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_walf_truncates_torn_tail_at_segment_start() {
+        let temp = tempdir::TempDir::new("test_walf_torn_tail_segment").unwrap();
+        let file = temp.path().join("wal");
+        let (mut writer, reader) = wal(&file).unwrap();
+        writer
+            .write(1, &[1u8; (MAP_SIZE - HEADER_LEN_BYTES) as usize])
+            .unwrap();
+        let two_pos = writer.write(2, &[2u8; 18]).unwrap();
+        assert_eq!(two_pos.start, MAP_SIZE);
+        drop(reader);
+        drop(writer);
+
+        // Crash in the middle of writing the first entry of the second segment
+        let len = std::fs::metadata(&file).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_len(len - 5)
+            .unwrap();
+
+        let (writer, reader) = walf(open_file_for_wal(&file).unwrap()).unwrap();
+        assert_eq!(writer.file_size(), MAP_SIZE);
+        let tags: Vec<_> = reader
+            .iter_until(&writer)
+            .map(|(_, (tag, _))| tag)
+            .collect();
+        assert_eq!(tags, vec![1]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_wal_prune() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = tempdir::TempDir::new("test_wal_prune").unwrap();
+        let file = temp.path().join("wal");
+        let (mut writer, reader) = wal(&file).unwrap();
+        let entry = [1u8; (MAP_SIZE / 2 - HEADER_LEN_BYTES) as usize];
+        let positions: Vec<_> = (0..6).map(|_| writer.write(1, &entry).unwrap()).collect();
+        assert!(!reader.is_pruned(&writer));
+        // Two entries per segment, the segment of the position is retained
+        let released = writer.prune(positions[5]).unwrap();
+        assert_eq!(released, 2 * MAP_SIZE);
+        assert_eq!(writer.prune(positions[5]).unwrap(), 0);
+        drop(reader);
+        drop(writer);
+        assert!(std::fs::metadata(&file).unwrap().blocks() * 512 < 2 * MAP_SIZE);
+
+        let (mut writer, reader) = walf(open_file_for_wal(&file).unwrap()).unwrap();
+        assert!(reader.is_pruned(&writer));
+        let iterated: Vec<_> = reader.iter_until(&writer).map(|(pos, _)| pos).collect();
+        assert_eq!(iterated, &positions[4..]);
+        let scanned: Vec<_> = reader.scan_until(&writer).map(|(pos, _)| pos).collect();
+        assert_eq!(scanned, &positions[4..]);
+        // Segments pruned before the restart are not released again
+        assert_eq!(writer.prune(positions[5]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_wal_scan_reports_corruption() {
+        let temp = tempdir::TempDir::new("test_wal_scan").unwrap();
+        let file = temp.path().join("wal");
+        let (mut writer, reader) = wal(&file).unwrap();
+        let one_pos = writer.write(1, &[1u8; 15]).unwrap();
+        let two_pos = writer.write(2, &[2u8; 18]).unwrap();
+        // Does not fit in the remainder of the first segment, so it is padded to the next one
+        let three_pos = writer
+            .write(3, &[3u8; (MAP_SIZE - HEADER_LEN_BYTES) as usize])
+            .unwrap();
+        drop(reader);
+        drop(writer);
+
+        // Flip a byte in the data of the second entry
+        let mut bytes = std::fs::read(&file).unwrap();
+        bytes[(two_pos.start + HEADER_LEN_BYTES) as usize] ^= 0xff;
+        std::fs::write(&file, bytes).unwrap();
+
+        let (writer, reader) = wal(&file).unwrap();
+        let scanned: Vec<_> = reader
+            .scan_until(&writer)
+            .map(|(pos, entry)| (pos, entry.map(|(tag, _)| tag)))
+            .collect();
+        assert_eq!(scanned.len(), 3);
+        assert_eq!(scanned[0], (one_pos, Ok(1)));
+        assert_eq!(scanned[1].0, two_pos);
+        assert!(matches!(
+            scanned[1].1,
+            Err(WalCorruption::CrcMismatch { .. })
+        ));
+        assert_eq!(scanned[2], (three_pos, Ok(3)));
+    }
 }
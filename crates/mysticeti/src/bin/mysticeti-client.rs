@@ -0,0 +1,94 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Drives load against the submission endpoint of a validator (see `submission_address` in the
+//! private config of the validator) and reports the submission and commit latency.
+
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use clap::{command, Parser};
+use eyre::{Context, Result};
+use mysticeti_core::{
+    client::LoadClient,
+    config::{ClientParameters, ImportExport, LoadMode},
+};
+use tracing_subscriber::{filter::LevelFilter, EnvFilter};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The submission address of the validator.
+    #[clap(long, value_name = "ADDR")]
+    address: SocketAddr,
+    /// Path to the file holding the client parameters. If not provided, default parameters are
+    /// used.
+    #[clap(long, value_name = "FILE")]
+    client_parameters_path: Option<PathBuf>,
+    /// The number of transactions to submit per second, overriding the client parameters.
+    #[clap(long, value_name = "INT")]
+    load: Option<usize>,
+    /// The size of the transactions in bytes, overriding the client parameters.
+    #[clap(long, value_name = "INT")]
+    transaction_size: Option<usize>,
+    /// Keep this many transactions in flight instead of submitting at a fixed rate, overriding
+    /// the client parameters.
+    #[clap(long, value_name = "INT")]
+    closed_loop: Option<usize>,
+    /// Stop after this many seconds. Runs until interrupted if not provided.
+    #[clap(long, value_name = "INT")]
+    duration_secs: Option<u64>,
+    /// Seed of the ids of the transactions. Clients submitting to the same validator need
+    /// different seeds.
+    #[clap(long, value_name = "INT", default_value_t = 0)]
+    seed: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install()?;
+    let args = Args::parse();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .init();
+
+    let mut parameters = match &args.client_parameters_path {
+        Some(path) => ClientParameters::load(path).wrap_err(format!(
+            "Failed to load client parameters file '{}'",
+            path.display()
+        ))?,
+        None => ClientParameters::default(),
+    };
+    if let Some(load) = args.load {
+        parameters.load = load;
+    }
+    if let Some(transaction_size) = args.transaction_size {
+        parameters.transaction_size = transaction_size;
+    }
+    if let Some(outstanding) = args.closed_loop {
+        parameters.mode = LoadMode::ClosedLoop(outstanding);
+    }
+
+    let client = LoadClient::new(args.address, parameters, args.seed);
+    let summary = tokio::select! {
+        summary = client.run(args.duration_secs.map(Duration::from_secs)) => {
+            summary.wrap_err("Client failed")?
+        }
+        _ = tokio::signal::ctrl_c() => return Ok(()),
+    };
+    println!("Submitted transactions: {}", summary.submitted);
+    println!("Committed transactions: {}", summary.committed);
+    if summary.timed_out > 0 {
+        println!("Timed out transactions: {}", summary.timed_out);
+    }
+    if let Some(latency) = summary.submission_latency {
+        println!("Submission latency: {latency}");
+    }
+    if let Some(latency) = summary.commit_latency {
+        println!("Commit latency: {latency}");
+    }
+    Ok(())
+}
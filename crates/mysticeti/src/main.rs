@@ -9,18 +9,34 @@ use std::{
 };
 
 use clap::{command, Parser};
-use eyre::{eyre, Context, Result};
+use eyre::{ensure, eyre, Context, Result};
 use mysticeti_core::{
     committee::Committee,
-    config::{ClientParameters, ImportExport, NodeParameters, NodePrivateConfig, NodePublicConfig},
+    config::{
+        ByzantineStrategy, ClientParameters, ImportExport, NodeParameters, NodePrivateConfig,
+        NodePublicConfig,
+    },
+    inspect::{self, WalSummary},
+    reload::reload_on_sighup,
     types::AuthorityIndex,
     validator::Validator,
 };
-use tracing_subscriber::{filter::LevelFilter, fmt, EnvFilter};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*, reload, EnvFilter, Registry};
+
+/// Jemalloc dumps the heap profile of the node when enabled by `_RJEM_MALLOC_CONF`.
+#[cfg(feature = "heap-profiling")]
+#[global_allocator]
+static ALLOCATOR: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    /// Export tracing spans to the OpenTelemetry collector listening on this (gRPC) endpoint,
+    /// e.g. http://localhost:4317.
+    #[cfg(feature = "otlp")]
+    #[clap(long, global = true, value_name = "URL")]
+    otlp_endpoint: Option<String>,
     #[clap(subcommand)]
     operation: Operation,
 }
@@ -29,7 +45,8 @@ struct Args {
 enum Operation {
     /// Generate a committee file, parameters files and the private config files of all validators
     /// from a list of initial peers. This is only suitable for benchmarks as it exposes all keys.
-    BenchmarkGenesis {
+    #[clap(alias = "benchmark-genesis")]
+    Genesis {
         /// The list of ip addresses of the all validators.
         #[clap(long, value_name = "ADDR", value_delimiter = ' ', num_args(4..))]
         ips: Vec<IpAddr>,
@@ -57,9 +74,48 @@ enum Operation {
         /// Path to the file holding the client parameters (for benchmarks).
         #[clap(long, value_name = "FILE")]
         client_parameters_path: String,
+        /// Path to the file holding the configuration to apply when the node receives SIGHUP
+        /// (log filter, leader timeout, load and block interval).
+        #[clap(long, value_name = "FILE")]
+        reload_config_path: Option<String>,
+        /// Deviate from the protocol (e.g., 'withhold-votes'), overriding the private validator
+        /// configurations. Only meant for benchmarks with byzantine validators.
+        #[clap(long, value_name = "STRATEGY")]
+        byzantine_strategy: Option<ByzantineStrategy>,
     },
-    /// Deploy a local validator for test. Dryrun mode uses default keys and committee configurations.
+    /// Replay the WAL of a validator without networking and print the resulting commit sequence,
+    /// one commit per line: height, leader, number of transactions and the digest of the sequence
+    /// up to that commit. The output of two validators can be diffed to check they agree. A pruned
+    /// WAL cannot be replayed, so only the line 'pruned' is printed instead.
     DryRun {
+        /// The authority index of the node that wrote the WAL.
+        #[clap(long, value_name = "INT")]
+        authority: AuthorityIndex,
+        /// Path to the file holding the public committee information.
+        #[clap(long, value_name = "FILE")]
+        committee_path: String,
+        /// Path to the file holding the public validator configurations (such as network addresses).
+        #[clap(long, value_name = "FILE")]
+        public_config_path: String,
+        /// Path to the file holding the private validator configurations (locating the WAL).
+        #[clap(long, value_name = "FILE")]
+        private_config_path: String,
+        /// Also print the transactions of each commit, in commit order.
+        #[clap(long)]
+        transactions: bool,
+    },
+    /// Print the entries of a WAL (block references, commit records, corrupted entries) and
+    /// a summary of its content. Fails if the WAL holds corrupted entries.
+    WalInspect {
+        /// Path to the WAL file, or to the storage directory of a validator.
+        #[clap(long, value_name = "FILE")]
+        wal_path: PathBuf,
+        /// Only print the summary.
+        #[clap(long)]
+        summary: bool,
+    },
+    /// Deploy a local validator for test. Local mode uses default keys and committee configurations.
+    Local {
         /// The authority index of this node.
         #[clap(long, value_name = "INT")]
         authority: AuthorityIndex,
@@ -73,24 +129,25 @@ enum Operation {
 async fn main() -> Result<()> {
     // Nice colored error messages.
     color_eyre::install()?;
-    let filter = EnvFilter::builder()
-        .with_default_directive(LevelFilter::INFO.into())
-        .from_env_lossy();
-    fmt().with_env_filter(filter).init();
 
     // Parse the command line arguments.
-    match Args::parse().operation {
-        Operation::BenchmarkGenesis {
+    let args = Args::parse();
+    let log_filter = init_tracing(&args)?;
+
+    match args.operation {
+        Operation::Genesis {
             ips,
             working_directory,
             node_parameters_path,
-        } => benchmark_genesis(ips, working_directory, node_parameters_path)?,
+        } => genesis(ips, working_directory, node_parameters_path)?,
         Operation::Run {
             authority,
             committee_path,
             public_config_path,
             private_config_path,
             client_parameters_path,
+            reload_config_path,
+            byzantine_strategy,
         } => {
             run(
                 authority,
@@ -98,19 +155,38 @@ async fn main() -> Result<()> {
                 public_config_path,
                 private_config_path,
                 client_parameters_path,
+                reload_config_path,
+                byzantine_strategy,
+                log_filter,
             )
             .await?
         }
         Operation::DryRun {
+            authority,
+            committee_path,
+            public_config_path,
+            private_config_path,
+            transactions,
+        } => dryrun(
+            authority,
+            committee_path,
+            public_config_path,
+            private_config_path,
+            transactions,
+        )?,
+        Operation::WalInspect { wal_path, summary } => wal_inspect(wal_path, summary)?,
+        Operation::Local {
             authority,
             committee_size,
-        } => dryrun(authority, committee_size).await?,
+        } => local(authority, committee_size).await?,
     }
 
+    #[cfg(feature = "otlp")]
+    opentelemetry::global::shutdown_tracer_provider();
     Ok(())
 }
 
-fn benchmark_genesis(
+fn genesis(
     ips: Vec<IpAddr>,
     working_directory: PathBuf,
     node_parameters_path: Option<PathBuf>,
@@ -167,12 +243,16 @@ fn benchmark_genesis(
 }
 
 /// Boot a single validator node.
+#[allow(clippy::too_many_arguments)]
 async fn run(
     authority: AuthorityIndex,
     committee_path: String,
     public_config_path: String,
     private_config_path: String,
     client_parameters_path: String,
+    reload_config_path: Option<String>,
+    byzantine_strategy: Option<ByzantineStrategy>,
+    log_filter: LogFilterHandle,
 ) -> Result<()> {
     tracing::info!("Starting validator {authority}");
 
@@ -181,13 +261,31 @@ async fn run(
     let public_config = NodePublicConfig::load(&public_config_path).wrap_err(format!(
         "Failed to load parameters file '{public_config_path}'"
     ))?;
-    let private_config = NodePrivateConfig::load(&private_config_path).wrap_err(format!(
+    let mut private_config = NodePrivateConfig::load(&private_config_path).wrap_err(format!(
         "Failed to load private configuration file '{private_config_path}'"
     ))?;
+    if let Some(strategy) = byzantine_strategy {
+        private_config.byzantine_strategy = Some(strategy);
+    }
     let client_parameters = ClientParameters::load(&client_parameters_path).wrap_err(format!(
         "Failed to load client parameters file '{client_parameters_path}'"
     ))?;
 
+    committee
+        .validate()
+        .wrap_err(format!("Invalid committee file '{committee_path}'"))?;
+    public_config
+        .validate(&committee)
+        .wrap_err(format!("Invalid parameters file '{public_config_path}'"))?;
+    ensure!(
+        private_config.authority() == authority,
+        "The private configuration file '{private_config_path}' belongs to authority {}, not {authority}",
+        private_config.authority()
+    );
+    private_config.validate(&public_config).wrap_err(format!(
+        "Invalid private configuration file '{private_config_path}'"
+    ))?;
+
     let committee = Arc::new(committee);
 
     let network_address = public_config
@@ -213,14 +311,97 @@ async fn run(
         client_parameters,
     )
     .await?;
-    let (network_result, _metrics_result) = validator.await_completion().await;
-    network_result.expect("Validator crashed");
+    let reloader = validator.reloader();
+    reloader.set_log_filter_reloader(move |directives| {
+        let filter = EnvFilter::builder().parse(directives)?;
+        log_filter.reload(filter)?;
+        Ok(())
+    });
+    if let Some(path) = reload_config_path {
+        reload_on_sighup(reloader.clone(), path.into()).wrap_err("Failed to listen for SIGHUP")?;
+    }
+
+    validator.run_until(shutdown_signal()).await?;
+    tracing::info!("Validator {authority} stopped");
+    Ok(())
+}
+
+/// Replay the WAL of a validator offline and print its commit sequence.
+fn dryrun(
+    authority: AuthorityIndex,
+    committee_path: String,
+    public_config_path: String,
+    private_config_path: String,
+    print_transactions: bool,
+) -> Result<()> {
+    let committee = Committee::load(&committee_path)
+        .wrap_err(format!("Failed to load committee file '{committee_path}'"))?;
+    let public_config = NodePublicConfig::load(&public_config_path).wrap_err(format!(
+        "Failed to load parameters file '{public_config_path}'"
+    ))?;
+    let private_config = NodePrivateConfig::load(&private_config_path).wrap_err(format!(
+        "Failed to load private configuration file '{private_config_path}'"
+    ))?;
+
+    let wal_path = private_config.wal();
+    tracing::info!(
+        "Replaying WAL '{}' of validator {authority}",
+        wal_path.display()
+    );
+    // The blocks of the pruned segments are lost, the replay would only diverge.
+    if inspect::wal_pruned(&wal_path)? {
+        tracing::warn!(
+            "The WAL '{}' is pruned and cannot be replayed",
+            wal_path.display()
+        );
+        println!("{}", inspect::PRUNED_WAL_MARKER);
+        return Ok(());
+    }
+    let commits = inspect::replay_commits(
+        authority,
+        Arc::new(committee),
+        &public_config.parameters,
+        &wal_path,
+    )?;
+    let recorded = inspect::check_recorded_commits(&wal_path, &commits)
+        .wrap_err("The replay disagrees with the commits recorded in the WAL")?;
+    for record in inspect::commit_sequence(&commits) {
+        println!("{record}");
+        if print_transactions {
+            for transaction in &record.transactions {
+                println!("\t{transaction}");
+            }
+        }
+    }
+    tracing::info!(
+        "Replayed {} commits ({recorded} recorded in the WAL)",
+        commits.len()
+    );
     Ok(())
 }
 
-async fn dryrun(authority: AuthorityIndex, committee_size: usize) -> Result<()> {
+/// Print the position, size and content of every entry of a WAL, followed by a summary.
+fn wal_inspect(wal_path: PathBuf, summary_only: bool) -> Result<()> {
+    let entries = inspect::wal_entries(&wal_path)?;
+    if !summary_only {
+        for entry in &entries {
+            println!("{entry}");
+        }
+    }
+    let summary = WalSummary::new(&entries);
+    println!("{summary}");
+    ensure!(
+        summary.corrupted == 0,
+        "Found {} corrupted entries in '{}'",
+        summary.corrupted,
+        wal_path.display()
+    );
+    Ok(())
+}
+
+async fn local(authority: AuthorityIndex, committee_size: usize) -> Result<()> {
     tracing::warn!(
-        "Starting validator {authority} in dryrun mode (committee size: {committee_size})"
+        "Starting validator {authority} in local mode (committee size: {committee_size})"
     );
     let ips = vec![IpAddr::V4(Ipv4Addr::LOCALHOST); committee_size];
     let committee = Committee::new_for_benchmarks(committee_size);
@@ -228,7 +409,7 @@ async fn dryrun(authority: AuthorityIndex, committee_size: usize) -> Result<()>
     let node_parameters = NodeParameters::default();
     let public_config = NodePublicConfig::new_for_benchmarks(ips, Some(node_parameters));
 
-    let working_dir = PathBuf::from(format!("dryrun-validator-{authority}"));
+    let working_dir = PathBuf::from(format!("local-validator-{authority}"));
     let mut all_private_config =
         NodePrivateConfig::new_for_benchmarks(&working_dir, committee_size);
     let private_config = all_private_config.remove(authority as usize);
@@ -260,8 +441,91 @@ async fn dryrun(authority: AuthorityIndex, committee_size: usize) -> Result<()>
         client_parameters,
     )
     .await?;
-    let (network_result, _metrics_result) = validator.await_completion().await;
-    network_result.expect("Validator crashed");
+    validator.run_until(shutdown_signal()).await?;
+    tracing::info!("Validator {authority} stopped");
 
     Ok(())
 }
+
+/// Resolves when the process receives SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    let received = tokio::select! {
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+        _ = terminate.recv() => "SIGTERM",
+    };
+    tracing::info!("Received {received}, shutting down gracefully");
+}
+
+/// Swaps the filter of the console logs at runtime.
+type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+#[cfg_attr(not(feature = "otlp"), allow(unused_variables))]
+fn init_tracing(args: &Args) -> Result<LogFilterHandle> {
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy();
+    let (filter, log_filter) = reload::Layer::new(filter);
+    // Logs go to stderr so that the output of the inspection commands can be piped.
+    let subscriber = tracing_subscriber::registry().with(
+        fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_filter(filter),
+    );
+    #[cfg(feature = "otlp")]
+    let subscriber = {
+        let tracer = args
+            .otlp_endpoint
+            .as_deref()
+            .map(otlp::tracer)
+            .transpose()?;
+        // The consensus spans are emitted at debug level, so they are exported regardless
+        // of the log level of the console.
+        subscriber.with(tracer.map(|tracer| {
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(otlp::filter())
+        }))
+    };
+    // Listens on 127.0.0.1:6669 by default, see `console_subscriber::Builder::with_default_env`.
+    #[cfg(feature = "tokio-console")]
+    let subscriber = subscriber.with(console_subscriber::spawn());
+    subscriber.init();
+    Ok(log_filter)
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use eyre::{Context, Result};
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{
+        runtime,
+        trace::{self, Tracer},
+        Resource,
+    };
+    use tracing_subscriber::filter::{LevelFilter, Targets};
+
+    /// Install a batch exporter sending spans to the OpenTelemetry collector at `endpoint`.
+    pub fn tracer(endpoint: &str) -> Result<Tracer> {
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(
+                trace::config()
+                    .with_resource(Resource::new([KeyValue::new("service.name", "mysticeti")])),
+            )
+            .install_batch(runtime::Tokio)
+            .wrap_err(format!("Failed to install OTLP exporter to '{endpoint}'"))
+    }
+
+    pub fn filter() -> Targets {
+        Targets::new()
+            .with_target("mysticeti_core", LevelFilter::DEBUG)
+            .with_default(LevelFilter::INFO)
+    }
+}
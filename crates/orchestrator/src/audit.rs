@@ -0,0 +1,107 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use mysticeti_core::inspect::PRUNED_WAL_MARKER;
+
+/// Checks that the commit sequences replayed from the storage of the nodes agree. Nodes may have
+/// committed more or less of the sequence when the benchmark stopped, but every sequence must be
+/// a prefix of the longest one.
+#[derive(Default)]
+pub struct SafetyAudit {
+    /// The commit lines printed by each node, in commit order.
+    sequences: Vec<Vec<String>>,
+    /// The nodes whose storage is pruned, and therefore cannot be replayed.
+    pruned: Vec<usize>,
+}
+
+impl SafetyAudit {
+    /// Add the output of the replay command of a node. Only the lines starting with the height of
+    /// a commit are retained. A node reporting a pruned storage contributes no sequence.
+    pub fn add_node_output(&mut self, stdout: &str) {
+        if stdout.lines().any(|line| line == PRUNED_WAL_MARKER) {
+            self.pruned.push(self.sequences.len());
+            self.sequences.push(Vec::new());
+            return;
+        }
+        let sequence = stdout
+            .lines()
+            .filter(|line| {
+                line.split('\t')
+                    .next()
+                    .map_or(false, |height| height.parse::<u64>().is_ok())
+            })
+            .map(|line| line.to_string())
+            .collect();
+        self.sequences.push(sequence);
+    }
+
+    /// Return the length of the longest commit sequence if all the others are a prefix of it, or
+    /// a description of the first divergence otherwise.
+    pub fn check(&self) -> Result<usize, String> {
+        let Some((longest_node, longest)) = self
+            .sequences
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, sequence)| sequence.len())
+        else {
+            return Ok(0);
+        };
+        for (node, sequence) in self.sequences.iter().enumerate() {
+            if let Some((commit, expected)) = sequence
+                .iter()
+                .zip(longest)
+                .find(|(commit, expected)| commit != expected)
+            {
+                return Err(format!(
+                    "node {node} committed '{commit}' where node {longest_node} committed '{expected}'"
+                ));
+            }
+        }
+        Ok(longest.len())
+    }
+
+    /// The nodes excluded from the audit because their storage is pruned.
+    pub fn pruned_nodes(&self) -> &[usize] {
+        &self.pruned
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SafetyAudit;
+
+    #[test]
+    fn prefixes_agree() {
+        let mut audit = SafetyAudit::default();
+        audit.add_node_output("1\tA1\t10\taa\n2\tB2\t12\tbb\n3\tC3\t9\tcc\n");
+        audit.add_node_output("1\tA1\t10\taa\n2\tB2\t12\tbb\n");
+        audit.add_node_output("");
+        assert_eq!(audit.check(), Ok(3));
+    }
+
+    #[test]
+    fn ignore_other_lines() {
+        let mut audit = SafetyAudit::default();
+        audit.add_node_output("Compiling mysticeti\n1\tA1\t10\taa\n\tA1:0\n");
+        audit.add_node_output("1\tA1\t10\taa\n");
+        assert_eq!(audit.check(), Ok(1));
+    }
+
+    #[test]
+    fn detect_divergence() {
+        let mut audit = SafetyAudit::default();
+        audit.add_node_output("1\tA1\t10\taa\n2\tB2\t12\tbb\n3\tC3\t9\tcc\n");
+        audit.add_node_output("1\tA1\t10\taa\n2\tC2\t12\tdd\n");
+        assert!(audit.check().is_err());
+    }
+
+    #[test]
+    fn exclude_pruned_nodes() {
+        let mut audit = SafetyAudit::default();
+        audit.add_node_output("1\tA1\t10\taa\n2\tB2\t12\tbb\n");
+        audit.add_node_output("pruned\n");
+        audit.add_node_output("1\tA1\t10\taa\n");
+        assert_eq!(audit.check(), Ok(2));
+        assert_eq!(audit.pruned_nodes(), &[1]);
+    }
+}
@@ -1,11 +1,20 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    path::Path,
+};
 
+use eyre::Context;
 use serde::{Deserialize, Serialize};
 
-use crate::{protocol::ProtocolParameters, settings::Settings, ClientParameters, NodeParameters};
+use crate::{
+    faults::FaultsType,
+    protocol::ProtocolParameters,
+    settings::{Protocol, Settings},
+    ClientParameters, NodeParameters,
+};
 
 /// Shortcut avoiding to use the generic version of the benchmark parameters.
 pub type BenchmarkParameters = BenchmarkParametersGeneric<NodeParameters, ClientParameters>;
@@ -24,10 +33,17 @@ pub struct BenchmarkParametersGeneric<N, C> {
     pub nodes: usize,
     /// The total load (tx/s) to submit to the system.
     pub load: usize,
+    /// The index of the run, when running the same parameters several times.
+    #[serde(default)]
+    pub repetition: usize,
 }
 
 impl<N: Debug, C: Debug> Debug for BenchmarkParametersGeneric<N, C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The benchmarks of Mysticeti keep their names.
+        if self.settings.protocol != Protocol::default() {
+            write!(f, "{}-", self.settings.protocol)?;
+        }
         write!(
             f,
             "{:?}-{:?}-{:?}-{}-{}",
@@ -36,7 +52,12 @@ impl<N: Debug, C: Debug> Debug for BenchmarkParametersGeneric<N, C> {
             self.settings.faults,
             self.nodes,
             self.load
-        )
+        )?;
+        // The first run keeps the name of single runs.
+        if self.repetition != 0 {
+            write!(f, "-r{}", self.repetition)?;
+        }
+        Ok(())
     }
 }
 
@@ -46,29 +67,24 @@ impl<N, C> Display for BenchmarkParametersGeneric<N, C> {
             f,
             "{} nodes ({}) - {} tx/s",
             self.nodes, self.settings.faults, self.load
-        )
+        )?;
+        if self.repetition != 0 {
+            write!(f, " - run {}", self.repetition + 1)?;
+        }
+        Ok(())
     }
 }
 
 impl<N: ProtocolParameters, C: ProtocolParameters> BenchmarkParametersGeneric<N, C> {
-    /// Make a new benchmark parameters.
-    pub fn new_from_loads(
-        settings: Settings,
-        node_parameters: N,
-        client_parameters: C,
-        nodes: usize,
-        loads: Vec<usize>,
-    ) -> Vec<Self> {
-        loads
-            .into_iter()
-            .map(|load| Self {
-                settings: settings.clone(),
-                node_parameters: node_parameters.clone(),
-                client_parameters: client_parameters.clone(),
-                nodes,
-                load,
-            })
-            .collect()
+    /// Whether the nodes of both benchmarks run with the same configuration files, in which case
+    /// the testbed does not need to be reconfigured between them.
+    pub fn same_configuration(&self, other: &Self) -> bool {
+        let configuration = |x: &Self| {
+            let faults = &x.settings.faults;
+            let parameters = (&x.node_parameters, &x.client_parameters, faults);
+            (x.nodes, x.load, serde_json::to_string(&parameters).unwrap())
+        };
+        configuration(self) == configuration(other)
     }
 
     #[cfg(test)]
@@ -79,17 +95,196 @@ impl<N: ProtocolParameters, C: ProtocolParameters> BenchmarkParametersGeneric<N,
             client_parameters: C::default(),
             nodes: 4,
             load: 500,
+            repetition: 0,
         }
     }
 }
 
+/// A point of a sweep of benchmarks. The unspecified dimensions take the value of the base
+/// parameters of the sweep.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SweepPoint {
+    /// The committee size.
+    pub nodes: usize,
+    /// The total load (tx/s) to submit to the system.
+    pub load: usize,
+    /// The size of the transactions (in bytes).
+    #[serde(default)]
+    pub transaction_size: Option<usize>,
+    /// The faults to apply to the nodes.
+    #[serde(default)]
+    pub faults: Option<FaultsType>,
+}
+
+impl SweepPoint {
+    /// Load an explicit list of sweep points from a YAML (or JSON) file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, eyre::Error> {
+        let path = path.as_ref();
+        let error_message = format!("Unable to load sweep from {}", path.display());
+        let reader = std::fs::File::open(path).wrap_err(error_message)?;
+        Ok(serde_yaml::from_reader(reader)?)
+    }
+}
+
+/// Generate the benchmark parameters of a sweep over combinations of committee sizes, loads,
+/// transaction sizes, and faults (either their cartesian product or an explicit list).
+pub struct BenchmarkParametersGenerator<N, C> {
+    /// The testbed settings (including the default faults).
+    settings: Settings,
+    /// The node's configuration parameters.
+    node_parameters: N,
+    /// The client's configuration parameters (including the default transaction size).
+    client_parameters: C,
+    /// The points of the sweep.
+    points: Vec<SweepPoint>,
+    /// The number of times to run each point.
+    repetitions: usize,
+}
+
+impl<N: ProtocolParameters, C: ProtocolParameters> BenchmarkParametersGenerator<N, C> {
+    /// Make a new (empty) generator from the base parameters of the sweep.
+    pub fn new(settings: Settings, node_parameters: N, client_parameters: C) -> Self {
+        Self {
+            settings,
+            node_parameters,
+            client_parameters,
+            points: Vec::new(),
+            repetitions: 1,
+        }
+    }
+
+    /// Sweep over the cartesian product of the specified dimensions. An empty dimension takes
+    /// the value of the base parameters.
+    pub fn with_cartesian_product(
+        mut self,
+        committees: &[usize],
+        loads: &[usize],
+        transaction_sizes: &[usize],
+        faults: &[FaultsType],
+    ) -> Self {
+        let transaction_sizes: Vec<_> = match transaction_sizes {
+            [] => vec![None],
+            sizes => sizes.iter().copied().map(Some).collect(),
+        };
+        let faults: Vec<_> = match faults {
+            [] => vec![None],
+            faults => faults.iter().cloned().map(Some).collect(),
+        };
+        for &nodes in committees {
+            for faults in &faults {
+                for &transaction_size in &transaction_sizes {
+                    for &load in loads {
+                        self.points.push(SweepPoint {
+                            nodes,
+                            load,
+                            transaction_size,
+                            faults: faults.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Sweep over an explicit list of points.
+    pub fn with_points(mut self, points: Vec<SweepPoint>) -> Self {
+        self.points.extend(points);
+        self
+    }
+
+    /// Run each point the specified number of times (the runs of the same point being
+    /// consecutive).
+    pub fn with_repetitions(mut self, repetitions: usize) -> Self {
+        self.repetitions = repetitions;
+        self
+    }
+
+    /// Generate the parameters of every benchmark of the sweep.
+    pub fn generate(&self) -> Vec<BenchmarkParametersGeneric<N, C>> {
+        self.points
+            .iter()
+            .flat_map(|point| (0..self.repetitions).map(move |repetition| (point, repetition)))
+            .map(|(point, repetition)| {
+                let mut settings = self.settings.clone();
+                if let Some(faults) = &point.faults {
+                    settings.faults = faults.clone();
+                }
+                let mut client_parameters = self.client_parameters.clone();
+                if let Some(size) = point.transaction_size {
+                    client_parameters.set_transaction_size(size);
+                }
+                BenchmarkParametersGeneric {
+                    settings,
+                    node_parameters: self.node_parameters.clone(),
+                    client_parameters,
+                    nodes: point.nodes,
+                    load: point.load,
+                    repetition,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The search of the maximum load meeting a latency bound: the load doubles until it misses
+/// the bound, then a binary search narrows the gap between the highest load meeting the bound
+/// and the lowest load missing it.
+#[derive(Default)]
+pub struct LoadSearch {
+    /// The highest load meeting the bound so far.
+    best: Option<usize>,
+    /// The lowest load missing the bound so far.
+    worst: Option<usize>,
+}
+
+impl LoadSearch {
+    /// The search stops once the gap between the best and worst loads is under this fraction
+    /// of the best load.
+    const PRECISION: f64 = 0.05;
+
+    /// Make a new search.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The highest load meeting the bound so far.
+    pub fn best(&self) -> Option<usize> {
+        self.best
+    }
+
+    /// Record whether the specified load meets the bound and return the next load to try, if
+    /// the search is not over.
+    pub fn next(&mut self, load: usize, meets_bound: bool) -> Option<usize> {
+        if meets_bound {
+            self.best = self.best.max(Some(load));
+        } else {
+            self.worst = Some(self.worst.map_or(load, |x| x.min(load)));
+        }
+
+        let next = match (self.best, self.worst) {
+            (Some(best), None) => best.checked_mul(2)?,
+            (None, Some(worst)) => worst / 2,
+            (Some(best), Some(worst)) => {
+                if (worst - best) as f64 <= best as f64 * Self::PRECISION {
+                    return None;
+                }
+                (best + worst) / 2
+            }
+            (None, None) => unreachable!("The search recorded a load"),
+        };
+        (next != 0 && Some(next) != self.best && Some(next) != self.worst).then_some(next)
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use std::{fmt::Display, str::FromStr};
 
     use serde::{Deserialize, Serialize};
 
-    use super::ProtocolParameters;
+    use super::{BenchmarkParametersGenerator, LoadSearch, ProtocolParameters, SweepPoint};
+    use crate::{faults::FaultsType, settings::Settings};
 
     /// Mock benchmark type for unit tests.
     #[derive(
@@ -112,4 +307,69 @@ pub mod test {
     }
 
     impl ProtocolParameters for TestNodeConfig {}
+
+    #[test]
+    fn load_search() {
+        // The maximum load meeting the bound is 3000 tx/s.
+        let mut search = LoadSearch::new();
+        let mut load = Some(1000);
+        let mut steps = 0;
+        while let Some(next) = load {
+            load = search.next(next, next <= 3000);
+            steps += 1;
+        }
+        let best = search.best().unwrap();
+        assert!((2850..=3000).contains(&best), "{best}");
+        assert!(steps < 10);
+
+        // No load meets the bound.
+        let mut search = LoadSearch::new();
+        let mut load = Some(100);
+        while let Some(next) = load {
+            load = search.next(next, false);
+        }
+        assert_eq!(search.best(), None);
+    }
+
+    #[test]
+    fn cartesian_product() {
+        let faults = [FaultsType::default(), FaultsType::Permanent { faults: 1 }];
+        let sweep = BenchmarkParametersGenerator::new(
+            Settings::new_for_test(),
+            TestNodeConfig,
+            TestNodeConfig,
+        )
+        .with_cartesian_product(&[4, 10], &[100, 200, 300], &[], &faults)
+        .with_repetitions(2)
+        .generate();
+
+        assert_eq!(sweep.len(), 2 * 3 * 2 * 2);
+        assert_eq!(sweep[0].repetition, 0);
+        assert_eq!(sweep[1].repetition, 1);
+        assert_eq!(sweep[1].load, 100);
+        assert_eq!(sweep[2].load, 200);
+        assert_eq!(sweep[5].settings.faults, faults[0]);
+        assert_eq!(sweep[6].settings.faults, faults[1]);
+        assert_eq!(sweep.last().unwrap().nodes, 10);
+    }
+
+    #[test]
+    fn explicit_points() {
+        let points: Vec<SweepPoint> = serde_yaml::from_str(
+            "- nodes: 4\n  load: 100\n- nodes: 10\n  load: 500\n  faults: !Permanent\n    faults: 3\n",
+        )
+        .unwrap();
+        let sweep = BenchmarkParametersGenerator::new(
+            Settings::new_for_test(),
+            TestNodeConfig,
+            TestNodeConfig,
+        )
+        .with_points(points)
+        .generate();
+
+        assert_eq!(sweep.len(), 2);
+        assert_eq!(sweep[0].settings.faults, FaultsType::default());
+        let faults = FaultsType::Permanent { faults: 3 };
+        assert_eq!(sweep[1].settings.faults, faults);
+    }
 }
@@ -17,11 +17,14 @@ use aws_sdk_ec2::{
             BlockDeviceMappingBuilder,
             EbsBlockDeviceBuilder,
             FilterBuilder,
+            PlacementBuilder,
             TagBuilder,
             TagSpecificationBuilder,
         },
+        EnaSupport,
         EphemeralNvmeSupport,
         Instance as AwsInstance,
+        PlacementStrategy,
         ResourceType,
         VolumeType,
     },
@@ -59,8 +62,6 @@ impl Display for AwsClient {
 }
 
 impl AwsClient {
-    const OS_IMAGE: &'static str =
-        "Canonical, Ubuntu, 22.04 LTS, amd64 jammy image build on 2023-02-16";
     const DEFAULT_EBS_SIZE_GB: i32 = 500; // Default size of the EBS volume in GB.
 
     /// Make a new AWS client.
@@ -113,6 +114,10 @@ impl AwsClient {
                 .unwrap_or("0.0.0.0") // Stopped instances do not have an ip address.
                 .parse()
                 .expect("AWS instance should have a valid ip"),
+            // All instances of the (default) VPC of a region share its private network.
+            private_ip: aws_instance
+                .private_ip_address()
+                .and_then(|x| x.parse().ok()),
             tags: vec![self.settings.testbed_id.clone()],
             specs: format!(
                 "{:?}",
@@ -133,6 +138,12 @@ impl AwsClient {
         }
     }
 
+    /// The description of the image of the instances (for the architecture of the settings).
+    fn os_image(&self) -> String {
+        let architecture = self.settings.architecture.debian_name();
+        format!("Canonical, Ubuntu, 22.04 LTS, {architecture} jammy image build on 2023-02-16")
+    }
+
     /// Query the image id determining the os of the instances.
     /// NOTE: The image id changes depending on the region.
     async fn find_image_id(&self, client: &aws_sdk_ec2::Client) -> CloudProviderResult<String> {
@@ -140,7 +151,7 @@ impl AwsClient {
         let request = client.describe_images().filters(
             FilterBuilder::default()
                 .name("description")
-                .values(Self::OS_IMAGE)
+                .values(self.os_image())
                 .build(),
         );
         let response = request.send().await?;
@@ -189,6 +200,58 @@ impl AwsClient {
         Ok(())
     }
 
+    /// Create a cluster placement group for the instances of the region (if it doesn't already
+    /// exist). The group is left behind when the testbed is destroyed; it is free of charge.
+    async fn create_placement_group(
+        &self,
+        client: &aws_sdk_ec2::Client,
+    ) -> CloudProviderResult<()> {
+        let request = client
+            .create_placement_group()
+            .group_name(&self.settings.testbed_id)
+            .strategy(PlacementStrategy::Cluster);
+
+        let response = request.send().await;
+        Self::check_but_ignore_duplicates(response)
+    }
+
+    /// Check that both the specified instance type (the specs the instance is created with) and
+    /// the image support enhanced networking (ENA).
+    async fn check_enhanced_networking(
+        &self,
+        client: &aws_sdk_ec2::Client,
+        specs: &str,
+        image_id: &str,
+    ) -> CloudProviderResult<()> {
+        let request = client
+            .describe_instance_types()
+            .instance_types(specs.into());
+        let response = request.send().await?;
+        let ena_support = response
+            .instance_types()
+            .iter()
+            .find(|x| x.instance_type().map(|x| x.as_str()) == Some(specs))
+            .and_then(|x| x.network_info())
+            .and_then(|x| x.ena_support());
+        if !matches!(
+            ena_support,
+            Some(EnaSupport::Supported | EnaSupport::Required)
+        ) {
+            return Err(CloudProviderError::RequestError(format!(
+                "Instance type {specs} does not support enhanced networking"
+            )));
+        }
+
+        let response = client.describe_images().image_ids(image_id).send().await?;
+        let image_support = response.images().first().and_then(|x| x.ena_support());
+        if image_support != Some(true) {
+            return Err(CloudProviderError::RequestError(format!(
+                "Image {image_id} does not support enhanced networking"
+            )));
+        }
+        Ok(())
+    }
+
     /// Return the command to mount the first (standard) NVMe drive.
     fn nvme_mount_command(&self) -> Vec<String> {
         const DRIVE: &str = "nvme1n1";
@@ -205,37 +268,37 @@ impl AwsClient {
         vec![format!("(sudo umount {directory} || true)")]
     }
 
-    /// Check whether the instance type specified in the settings supports NVMe drives.
+    /// Check whether the instance types specified in the settings (for every region) support
+    /// NVMe drives.
     async fn check_nvme_support(&self) -> CloudProviderResult<bool> {
-        // Get the client for the first region. A given instance type should either have NVMe
-        // support in all regions or in none.
-        let client = match self
-            .settings
-            .regions
-            .first()
-            .and_then(|x| self.clients.get(x))
-        {
-            Some(client) => client,
-            None => return Ok(false),
-        };
-
-        // Request storage details for the instance type specified in the settings.
-        let request = client
-            .describe_instance_types()
-            .instance_types(self.settings.specs.as_str().into());
+        if self.settings.regions.is_empty() {
+            return Ok(false);
+        }
 
-        // Send the request.
-        let response = request.send().await?;
+        for region in &self.settings.regions {
+            let Some(client) = self.clients.get(region) else {
+                return Ok(false);
+            };
 
-        // Return true if the response contains references to NVMe drives.
-        if let Some(info) = response.instance_types().first() {
-            if let Some(info) = info.instance_storage_info() {
-                if info.nvme_support() == Some(&EphemeralNvmeSupport::Required) {
-                    return Ok(true);
-                }
+            // Request storage details for the instance type of the region.
+            let request = client
+                .describe_instance_types()
+                .instance_types(self.settings.specs_for_region(region).into());
+
+            // Send the request.
+            let response = request.send().await?;
+
+            // Check whether the response contains references to NVMe drives.
+            let nvme_support = response
+                .instance_types()
+                .first()
+                .and_then(|x| x.instance_storage_info())
+                .and_then(|x| x.nvme_support());
+            if nvme_support != Some(&EphemeralNvmeSupport::Required) {
+                return Ok(false);
             }
         }
-        Ok(false)
+        Ok(true)
     }
 }
 
@@ -307,7 +370,7 @@ impl ServerProviderClient for AwsClient {
         Ok(())
     }
 
-    async fn create_instance<S>(&self, region: S) -> CloudProviderResult<Instance>
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
     where
         S: Into<String> + Serialize + Send,
     {
@@ -321,8 +384,17 @@ impl ServerProviderClient for AwsClient {
         // Create a security group (if needed).
         self.create_security_group(client).await?;
 
+        // Create a placement group (if needed).
+        if self.settings.aws_placement_group {
+            self.create_placement_group(client).await?;
+        }
+
         // Query the image id.
         let image_id = self.find_image_id(client).await?;
+        if self.settings.aws_enhanced_networking {
+            self.check_enhanced_networking(client, specs, &image_id)
+                .await?;
+        }
 
         // Create a new instance.
         let tags = TagSpecificationBuilder::default()
@@ -341,16 +413,23 @@ impl ServerProviderClient for AwsClient {
             )
             .build();
 
-        let request = client
+        let mut request = client
             .run_instances()
             .image_id(image_id)
-            .instance_type(self.settings.specs.as_str().into())
+            .instance_type(specs.into())
             .key_name(testbed_id)
             .min_count(1)
             .max_count(1)
             .security_groups(&self.settings.testbed_id)
             .block_device_mappings(storage)
             .tag_specifications(tags);
+        if self.settings.aws_placement_group {
+            let placement = PlacementBuilder::default().group_name(testbed_id).build();
+            request = request.placement(placement);
+        }
+        if self.settings.aws_enhanced_networking {
+            request = request.ebs_optimized(true);
+        }
 
         let response = request.send().await?;
         let instance = &response
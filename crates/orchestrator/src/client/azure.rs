@@ -0,0 +1,379 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt::{Display, Formatter};
+
+use futures::future::try_join_all;
+use serde::Serialize;
+use tokio::process::Command;
+
+use super::{Instance, InstanceStatus, ServerProviderClient};
+use crate::{
+    error::{CloudProviderError, CloudProviderResult},
+    settings::{Architecture, Settings},
+};
+
+/// Manages the instances of the testbed through the Azure CLI (`az`), which must be installed
+/// and logged in. All the resources of the testbed live in a resource group named after it.
+pub struct AzureClient {
+    /// The settings of the testbed.
+    settings: Settings,
+}
+
+impl Display for AzureClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Azure")
+    }
+}
+
+impl AzureClient {
+    const OS_IMAGE: &'static str = "Ubuntu2204";
+    const ARM64_OS_IMAGE: &'static str =
+        "Canonical:0001-com-ubuntu-server-jammy:22_04-lts-arm64:latest";
+
+    pub fn new(settings: Settings) -> Self {
+        Self { settings }
+    }
+
+    /// The image of the instances (for the architecture of the settings).
+    fn os_image(&self) -> &'static str {
+        match self.settings.architecture {
+            Architecture::X86_64 => Self::OS_IMAGE,
+            Architecture::Arm64 => Self::ARM64_OS_IMAGE,
+        }
+    }
+
+    /// The resource group holding all the resources of the testbed.
+    fn resource_group(&self) -> String {
+        format!("{}-rg", self.settings.testbed_id)
+    }
+
+    /// The name of the ssh key registered for the testbed.
+    fn ssh_key_name(&self) -> String {
+        format!("{}-key", self.settings.testbed_id)
+    }
+
+    /// Run an Azure CLI command and return its (JSON) output.
+    async fn az(&self, args: &[&str]) -> CloudProviderResult<Vec<u8>> {
+        let output = Command::new("az")
+            .args(args)
+            .args(["--output", "json"])
+            .output()
+            .await
+            .map_err(|e| CloudProviderError::RequestError(format!("Failed to run az: {e}")))?;
+        if !output.status.success() {
+            return Err(CloudProviderError::FailureResponseCode(
+                format!("{:?}", output.status.code()),
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Create the resource group of the testbed (if it doesn't already exist).
+    async fn create_resource_group(&self) -> CloudProviderResult<()> {
+        let location = self.settings.regions.first().ok_or_else(|| {
+            CloudProviderError::RequestError("No region specified in the settings".into())
+        })?;
+        self.az(&[
+            "group",
+            "create",
+            "--name",
+            &self.resource_group(),
+            "--location",
+            location,
+        ])
+        .await?;
+        Ok(())
+    }
+
+    /// Convert an Azure virtual machine (as output by `az vm list --show-details`) into an
+    /// orchestrator instance (used in the rest of the codebase).
+    fn make_instance(&self, vm: &serde_json::Value) -> Instance {
+        // The power state is of the form "VM running", "VM deallocated", etc.
+        let power_state = vm["powerState"]
+            .as_str()
+            .unwrap_or_default()
+            .trim_start_matches("VM ");
+        let status = match vm["provisioningState"].as_str() {
+            Some("Deleting") => InstanceStatus::Terminated,
+            _ => InstanceStatus::from(power_state),
+        };
+        Instance {
+            id: vm["name"]
+                .as_str()
+                .expect("Azure instance should have a name")
+                .into(),
+            region: vm["location"]
+                .as_str()
+                .expect("Azure instance should have a location")
+                .into(),
+            main_ip: vm["publicIps"]
+                .as_str()
+                .filter(|ip| !ip.is_empty())
+                .unwrap_or("0.0.0.0") // Deallocated instances do not have an ip address.
+                .parse()
+                .expect("Azure instance should have a valid ip"),
+            private_ip: None,
+            tags: vec![self.settings.testbed_id.clone()],
+            specs: vm["hardwareProfile"]["vmSize"]
+                .as_str()
+                .expect("Azure instance should have a size")
+                .into(),
+            status,
+        }
+    }
+
+    /// Return the command to mount the first (standard) NVMe drive.
+    fn nvme_mount_command(&self) -> Vec<String> {
+        const DRIVE: &str = "nvme0n1";
+        let directory = self.settings.working_dir.display();
+        vec![
+            format!("(sudo mkfs.ext4 -E nodiscard /dev/{DRIVE} || true)"),
+            format!("(sudo mount /dev/{DRIVE} {directory} || true)"),
+            format!("sudo chmod 777 -R {directory}"),
+        ]
+    }
+
+    fn nvme_unmount_command(&self) -> Vec<String> {
+        let directory = self.settings.working_dir.display();
+        vec![format!("(sudo umount {directory} || true)")]
+    }
+}
+
+impl ServerProviderClient for AzureClient {
+    const USERNAME: &'static str = "azureuser";
+
+    async fn list_instances(&self) -> CloudProviderResult<Vec<Instance>> {
+        let resource_group = self.resource_group();
+        let exists = self
+            .az(&["group", "exists", "--name", &resource_group])
+            .await?;
+        if !serde_json::from_slice::<bool>(&exists)? {
+            return Ok(Vec::new());
+        }
+
+        let output = self
+            .az(&[
+                "vm",
+                "list",
+                "--show-details",
+                "--resource-group",
+                &resource_group,
+            ])
+            .await?;
+        let vms: Vec<serde_json::Value> = serde_json::from_slice(&output)?;
+        Ok(vms.iter().map(|vm| self.make_instance(vm)).collect())
+    }
+
+    async fn start_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        let resource_group = self.resource_group();
+        let futures = instances.map(|instance| {
+            self.az(&[
+                "vm",
+                "start",
+                "--resource-group",
+                &resource_group,
+                "--name",
+                &instance.id,
+                "--no-wait",
+            ])
+        });
+        try_join_all(futures).await?;
+        Ok(())
+    }
+
+    async fn stop_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        // Deallocate rather than stop the instances, so that their compute is no longer billed.
+        let resource_group = self.resource_group();
+        let futures = instances.map(|instance| {
+            self.az(&[
+                "vm",
+                "deallocate",
+                "--resource-group",
+                &resource_group,
+                "--name",
+                &instance.id,
+                "--no-wait",
+            ])
+        });
+        try_join_all(futures).await?;
+        Ok(())
+    }
+
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
+    where
+        S: Into<String> + Serialize + Send,
+    {
+        let region = region.into();
+        let testbed_id = &self.settings.testbed_id;
+        let instance_id = format!("{testbed_id}-{}", rand::random::<u16>());
+        let resource_group = self.resource_group();
+
+        self.create_resource_group().await?;
+
+        // Create a new instance. Its disks and network interface are deleted along with it.
+        self.az(&[
+            "vm",
+            "create",
+            "--resource-group",
+            &resource_group,
+            "--name",
+            &instance_id,
+            "--location",
+            &region,
+            "--image",
+            self.os_image(),
+            "--size",
+            specs,
+            "--os-disk-size-gb",
+            "200",
+            "--admin-username",
+            self.settings.ssh_username(Self::USERNAME),
+            "--ssh-key-name",
+            &self.ssh_key_name(),
+            "--public-ip-sku",
+            "Standard",
+            "--os-disk-delete-option",
+            "Delete",
+            "--nic-delete-option",
+            "Delete",
+            "--tags",
+            &format!("testbed={testbed_id}"),
+        ])
+        .await?;
+
+        // Allow all traffic (used for benchmarks).
+        self.az(&[
+            "vm",
+            "open-port",
+            "--resource-group",
+            &resource_group,
+            "--name",
+            &instance_id,
+            "--port",
+            "*",
+        ])
+        .await?;
+
+        let output = self
+            .az(&[
+                "vm",
+                "show",
+                "--show-details",
+                "--resource-group",
+                &resource_group,
+                "--name",
+                &instance_id,
+            ])
+            .await?;
+        let vm: serde_json::Value = serde_json::from_slice(&output)?;
+        Ok(self.make_instance(&vm))
+    }
+
+    async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()> {
+        let resource_group = self.resource_group();
+        self.az(&[
+            "vm",
+            "delete",
+            "--resource-group",
+            &resource_group,
+            "--name",
+            &instance.id,
+            "--yes",
+        ])
+        .await?;
+
+        // The public ip outlives the instance (`az vm create` names it after the instance).
+        self.az(&[
+            "network",
+            "public-ip",
+            "delete",
+            "--resource-group",
+            &resource_group,
+            "--name",
+            &format!("{}PublicIP", instance.id),
+        ])
+        .await?;
+        Ok(())
+    }
+
+    async fn register_ssh_public_key(&self, public_key: String) -> CloudProviderResult<()> {
+        self.create_resource_group().await?;
+        let key_name = self.ssh_key_name();
+        let resource_group = self.resource_group();
+        let result = self
+            .az(&[
+                "sshkey",
+                "create",
+                "--resource-group",
+                &resource_group,
+                "--name",
+                &key_name,
+                "--public-key",
+                &public_key,
+            ])
+            .await;
+        match result {
+            Err(CloudProviderError::FailureResponseCode(_, message))
+                if message.to_lowercase().contains("already exists") =>
+            {
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn instance_setup_commands(&self) -> CloudProviderResult<Vec<String>> {
+        if self.settings.nvme {
+            Ok(self.nvme_mount_command())
+        } else {
+            Ok(self.nvme_unmount_command())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::AzureClient;
+    use crate::{client::InstanceStatus, settings::Settings};
+
+    #[test]
+    fn make_instance() {
+        let client = AzureClient::new(Settings::new_for_test());
+        let vm = json!({
+            "name": "testbed-42",
+            "location": "eastus",
+            "publicIps": "20.1.2.3",
+            "powerState": "VM running",
+            "provisioningState": "Succeeded",
+            "hardwareProfile": { "vmSize": "Standard_L8s_v3" },
+        });
+        let instance = client.make_instance(&vm);
+        assert_eq!(instance.id, "testbed-42");
+        assert_eq!(instance.region, "eastus");
+        assert_eq!(instance.main_ip.to_string(), "20.1.2.3");
+        assert_eq!(instance.specs, "Standard_L8s_v3");
+        assert_eq!(instance.status, InstanceStatus::Active);
+
+        let vm = json!({
+            "name": "testbed-42",
+            "location": "eastus",
+            "publicIps": "",
+            "powerState": "VM deallocated",
+            "provisioningState": "Succeeded",
+            "hardwareProfile": { "vmSize": "Standard_L8s_v3" },
+        });
+        let instance = client.make_instance(&vm);
+        assert_eq!(instance.main_ip.to_string(), "0.0.0.0");
+        assert_eq!(instance.status, InstanceStatus::Inactive);
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{fmt::Display, net::Ipv4Addr};
+
+use futures::future::try_join_all;
+use reqwest::{Client as NetworkClient, Response, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{Instance, InstanceStatus, ServerProviderClient};
+use crate::{
+    error::{CloudProviderError, CloudProviderResult},
+    settings::Settings,
+};
+
+/// Represents the ssh key information as defined by DigitalOcean.
+#[derive(Debug, Deserialize)]
+pub struct SshKey {
+    pub id: u64,
+    pub name: String,
+}
+
+/// Represents a region as defined by DigitalOcean (only its slug, e.g., 'nyc1', is of interest).
+#[derive(Debug, Deserialize)]
+pub struct DropletRegion {
+    pub slug: String,
+}
+
+/// Represents a network interface of a droplet.
+#[derive(Debug, Deserialize)]
+pub struct DropletNetwork {
+    pub ip_address: Ipv4Addr,
+    #[serde(rename = "type")]
+    pub network_type: String,
+}
+
+/// Represents the network interfaces of a droplet.
+#[derive(Debug, Deserialize, Default)]
+pub struct DropletNetworks {
+    #[serde(default)]
+    pub v4: Vec<DropletNetwork>,
+}
+
+/// Represents an instance (droplet) as defined by DigitalOcean.
+#[derive(Debug, Deserialize)]
+pub struct Droplet {
+    pub id: u64,
+    pub status: String,
+    pub size_slug: String,
+    pub region: DropletRegion,
+    #[serde(default)]
+    pub networks: DropletNetworks,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl From<Droplet> for Instance {
+    fn from(droplet: Droplet) -> Self {
+        let main_ip = droplet
+            .networks
+            .v4
+            .iter()
+            .find(|x| x.network_type == "public")
+            .map(|x| x.ip_address)
+            .unwrap_or(Ipv4Addr::UNSPECIFIED); // New droplets do not have an ip address yet.
+        let status = match droplet.status.as_str() {
+            "active" => InstanceStatus::Active,
+            "archive" => InstanceStatus::Terminated,
+            _ => InstanceStatus::Inactive,
+        };
+        Self {
+            id: droplet.id.to_string(),
+            region: droplet.region.slug,
+            main_ip,
+            private_ip: None,
+            tags: droplet.tags,
+            specs: droplet.size_slug,
+            status,
+        }
+    }
+}
+
+/// A DigitalOcean client.
+pub struct DigitalOceanClient {
+    token: String,
+    settings: Settings,
+    base_url: Url,
+    client: NetworkClient,
+}
+
+impl Display for DigitalOceanClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DigitalOcean API client v2")
+    }
+}
+
+impl DigitalOceanClient {
+    const BASE_URL: &'static str = "https://api.digitalocean.com/v2/";
+    const DEFAULT_IMAGE: &'static str = "ubuntu-22-04-x64";
+    const PAGE_SIZE: usize = 200;
+
+    /// Make a new DigitalOcean client.
+    pub fn new<T: Into<String>>(token: T, settings: Settings) -> Self {
+        Self {
+            token: token.into(),
+            settings,
+            base_url: Self::BASE_URL.parse().unwrap(),
+            client: NetworkClient::new(),
+        }
+    }
+
+    /// Check an http response and return its (json) body. DigitalOcean reports errors with a
+    /// non-success status code and a body of the form `{ "id": ..., "message": ... }`.
+    async fn check_response(response: Response) -> CloudProviderResult<Value> {
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|x| x["message"].as_str().map(|x| x.to_string()))
+                .unwrap_or(body);
+            return Err(CloudProviderError::FailureResponseCode(
+                status.to_string(),
+                message,
+            ));
+        }
+        if body.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Retrieve the ssh key associated with the current testbed.
+    pub async fn get_key(&self) -> CloudProviderResult<Option<SshKey>> {
+        let mut url = self.base_url.join("account/keys").unwrap();
+        url.query_pairs_mut()
+            .append_pair("per_page", &Self::PAGE_SIZE.to_string());
+        let response = self.client.get(url).bearer_auth(&self.token).send().await?;
+
+        let json = Self::check_response(response).await?;
+        let content = json["ssh_keys"].clone();
+        let keys: Vec<SshKey> = serde_json::from_value(content)?;
+
+        Ok(keys
+            .into_iter()
+            .find(|x| x.name == self.settings.testbed_id))
+    }
+
+    /// Perform an action (e.g., 'power_on') on a droplet.
+    async fn droplet_action(&self, instance: &Instance, action: &str) -> CloudProviderResult<()> {
+        let url = self
+            .base_url
+            .join(&format!("droplets/{}/actions", instance.id))
+            .unwrap();
+        let parameters = json!({ "type": action });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&parameters)
+            .send()
+            .await?;
+
+        Self::check_response(response).await?;
+        Ok(())
+    }
+}
+
+impl ServerProviderClient for DigitalOceanClient {
+    const USERNAME: &'static str = "root";
+
+    async fn list_instances(&self) -> CloudProviderResult<Vec<Instance>> {
+        let mut url = self.base_url.join("droplets").unwrap();
+        url.query_pairs_mut()
+            .append_pair("tag_name", &self.settings.testbed_id)
+            .append_pair("per_page", &Self::PAGE_SIZE.to_string());
+        let response = self.client.get(url).bearer_auth(&self.token).send().await?;
+
+        let json = Self::check_response(response).await?;
+        let content = json["droplets"].clone();
+        let droplets: Vec<Droplet> = serde_json::from_value(content)?;
+
+        Ok(droplets.into_iter().map(|x| x.into()).collect())
+    }
+
+    async fn start_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        let futures = instances.map(|instance| self.droplet_action(instance, "power_on"));
+        try_join_all(futures).await?;
+        Ok(())
+    }
+
+    async fn stop_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        // NOTE: DigitalOcean keeps billing powered off droplets.
+        let futures = instances.map(|instance| self.droplet_action(instance, "power_off"));
+        try_join_all(futures).await?;
+        Ok(())
+    }
+
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
+    where
+        S: Into<String> + Serialize + Send,
+    {
+        let region: String = region.into();
+        let testbed_name = self.settings.testbed_id.clone();
+        let ssh_key_id = match self.get_key().await? {
+            Some(key) => key.id,
+            None => return Err(CloudProviderError::SshKeyNotFound(testbed_name.clone())),
+        };
+
+        let url = self.base_url.join("droplets").unwrap();
+        let parameters = json!({
+                "name": format!("{testbed_name}-{}", rand::random::<u16>()),
+                "region": region,
+                "size": specs,
+                "image": Self::DEFAULT_IMAGE,
+                "ssh_keys": [ssh_key_id],
+                "tags": [testbed_name]
+        });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&parameters)
+            .send()
+            .await?;
+
+        let json = Self::check_response(response).await?;
+        let content = json["droplet"].clone();
+        let droplet: Droplet = serde_json::from_value(content)?;
+        Ok(droplet.into())
+    }
+
+    async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()> {
+        let url = self
+            .base_url
+            .join(&format!("droplets/{}", &instance.id))
+            .unwrap();
+
+        let response = self
+            .client
+            .delete(url)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+
+        Self::check_response(response).await?;
+        Ok(())
+    }
+
+    async fn register_ssh_public_key(&self, public_key: String) -> CloudProviderResult<()> {
+        // Do not upload the key if it already exists.
+        if self.get_key().await?.is_some() {
+            return Ok(());
+        }
+
+        let url = self.base_url.join("account/keys").unwrap();
+        let parameters = json!({
+                "name": self.settings.testbed_id.clone(),
+                "public_key": public_key
+        });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&parameters)
+            .send()
+            .await?;
+
+        Self::check_response(response).await?;
+        Ok(())
+    }
+
+    async fn instance_setup_commands(&self) -> CloudProviderResult<Vec<String>> {
+        Ok(vec!["sudo ufw disable".into()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::Droplet;
+    use crate::client::{Instance, InstanceStatus};
+
+    #[test]
+    fn droplet_to_instance() {
+        let droplet: Droplet = serde_json::from_value(json!({
+            "id": 3164444,
+            "name": "testbed-42",
+            "status": "active",
+            "size_slug": "s-4vcpu-8gb",
+            "region": { "slug": "nyc3", "name": "New York 3" },
+            "networks": {
+                "v4": [
+                    { "ip_address": "10.128.192.124", "type": "private" },
+                    { "ip_address": "192.241.165.154", "type": "public" }
+                ]
+            },
+            "tags": ["testbed"]
+        }))
+        .unwrap();
+        let instance: Instance = droplet.into();
+        assert_eq!(instance.id, "3164444");
+        assert_eq!(instance.region, "nyc3");
+        assert_eq!(instance.main_ip.to_string(), "192.241.165.154");
+        assert_eq!(instance.specs, "s-4vcpu-8gb");
+        assert_eq!(instance.status, InstanceStatus::Active);
+
+        // Droplets that are being created have no network yet.
+        let droplet: Droplet = serde_json::from_value(json!({
+            "id": 3164445,
+            "status": "new",
+            "size_slug": "s-4vcpu-8gb",
+            "region": { "slug": "nyc3" },
+            "networks": {},
+            "tags": ["testbed"]
+        }))
+        .unwrap();
+        let instance: Instance = droplet.into();
+        assert_eq!(instance.main_ip.to_string(), "0.0.0.0");
+        assert_eq!(instance.status, InstanceStatus::Inactive);
+    }
+}
@@ -1,32 +1,217 @@
-use futures::future::try_join_all;
-use serde::Serialize;
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use crate::error::CloudProviderError;
-use crate::{error::CloudProviderResult, settings::Settings};
+use futures::future::try_join_all;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::{Client as NetworkClient, RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
 
 use super::{Instance, ServerProviderClient};
-use core::fmt;
-use std::str;
-use tokio::process::Command;
+use crate::{
+    error::{CloudProviderError, CloudProviderResult},
+    settings::{Architecture, Settings},
+};
+
+/// The service account key (in json format) as downloaded from the GCP console.
+#[derive(Debug, Deserialize)]
+struct ServiceAccount {
+    project_id: String,
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+/// The claims of the (signed) assertion exchanged for an access token.
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
 
+/// An access token of the service account.
+struct AccessToken {
+    token: String,
+    expiry: Instant,
+}
+
+/// A client of the GCP Compute Engine API, authenticated with a service account.
 pub struct GcpClient {
     /// The settings of the testbed.
     settings: Settings,
+    /// The service account used to authenticate the requests.
+    service_account: ServiceAccount,
+    /// The base url of the compute API of the project.
+    base_url: Url,
+    client: NetworkClient,
+    /// The current access token (if any).
+    access_token: Mutex<Option<AccessToken>>,
+    /// The instance templates of the testbed (once created), indexed by machine type.
+    instance_templates: Mutex<HashMap<String, String>>,
 }
 
-impl fmt::Display for GcpClient {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "GCP")
+impl Display for GcpClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GCP Compute API v1 ({})",
+            self.service_account.project_id
+        )
     }
 }
 
 impl GcpClient {
-    pub fn new(settings: Settings) -> Self {
-        Self { settings }
+    const BASE_URL: &'static str = "https://compute.googleapis.com/compute/v1/";
+    const SCOPE: &'static str = "https://www.googleapis.com/auth/compute";
+    const OS_IMAGE_FAMILY: &'static str =
+        "projects/ubuntu-os-cloud/global/images/family/ubuntu-2004-lts";
+    /// The lifetime of the access tokens (the maximum allowed by GCP).
+    const TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+    /// The default key of the project metadata holding the ssh keys.
+    const SSH_KEYS_METADATA: &'static str = "ssh-keys";
+
+    /// Make a new GCP client from the content of a service account key file.
+    pub fn new(service_account: &str, settings: Settings) -> CloudProviderResult<Self> {
+        let service_account: ServiceAccount = serde_json::from_str(service_account)?;
+        let base_url = Url::parse(Self::BASE_URL)
+            .unwrap()
+            .join(&format!("projects/{}/", service_account.project_id))
+            .map_err(|e| CloudProviderError::RequestError(e.to_string()))?;
+        Ok(Self {
+            settings,
+            service_account,
+            base_url,
+            client: NetworkClient::new(),
+            access_token: Mutex::new(None),
+            instance_templates: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Return a valid access token, requesting a new one if the current one is (about to be)
+    /// expired. Tokens are obtained by signing an assertion with the key of the service account.
+    async fn access_token(&self) -> CloudProviderResult<String> {
+        let mut guard = self.access_token.lock().await;
+        if let Some(access_token) = guard.as_ref() {
+            if access_token.expiry > Instant::now() + Duration::from_secs(60) {
+                return Ok(access_token.token.clone());
+            }
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let claims = Claims {
+            iss: &self.service_account.client_email,
+            scope: Self::SCOPE,
+            aud: &self.service_account.token_uri,
+            iat: now.as_secs(),
+            exp: (now + Self::TOKEN_LIFETIME).as_secs(),
+        };
+        let key = EncodingKey::from_rsa_pem(self.service_account.private_key.as_bytes())
+            .map_err(|e| CloudProviderError::RequestError(format!("Invalid private key: {e}")))?;
+        let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)
+            .map_err(|e| CloudProviderError::RequestError(format!("Failed to sign: {e}")))?;
+
+        let parameters = [
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", &assertion),
+        ];
+        let response = self
+            .client
+            .post(&self.service_account.token_uri)
+            .form(&parameters)
+            .send()
+            .await?;
+        let json = Self::check_response(response).await?;
+        let token = json["access_token"]
+            .as_str()
+            .ok_or_else(|| CloudProviderError::UnexpectedResponse(json.to_string()))?
+            .to_string();
+        let expires_in = json["expires_in"].as_u64().unwrap_or_default();
+
+        *guard = Some(AccessToken {
+            token: token.clone(),
+            expiry: Instant::now() + Duration::from_secs(expires_in),
+        });
+        Ok(token)
+    }
+
+    /// Send an authenticated request and return its (json) body.
+    async fn send(&self, request: RequestBuilder) -> CloudProviderResult<Value> {
+        let token = self.access_token().await?;
+        let response = request.bearer_auth(token).send().await?;
+        Self::check_response(response).await
+    }
+
+    /// Check an http response and return its (json) body. GCP reports errors with a non-success
+    /// status code and a body of the form `{ "error": { "message": ... } }`.
+    async fn check_response(response: reqwest::Response) -> CloudProviderResult<Value> {
+        let status = response.status();
+        let text = response.text().await?;
+        let json: Value = if text.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text)?
+        };
+        if !status.is_success() {
+            let message = json["error"]["message"]
+                .as_str()
+                .map(|x| x.to_string())
+                .unwrap_or(text);
+            return Err(CloudProviderError::FailureResponseCode(
+                status.to_string(),
+                message,
+            ));
+        }
+        Ok(json)
+    }
+
+    /// The url of a resource of the project.
+    fn url(&self, path: &str) -> Url {
+        self.base_url.join(path).unwrap()
+    }
+
+    /// Wait for a (zonal or global) operation to complete and check whether it succeeded.
+    async fn wait_for_operation(&self, operation: &Value) -> CloudProviderResult<()> {
+        let name = operation["name"]
+            .as_str()
+            .ok_or_else(|| CloudProviderError::UnexpectedResponse(operation.to_string()))?;
+        let path = match operation["zone"].as_str() {
+            Some(zone) => {
+                let zone = zone.split('/').last().unwrap();
+                format!("zones/{zone}/operations/{name}/wait")
+            }
+            None => format!("global/operations/{name}/wait"),
+        };
+
+        // Each call waits up to two minutes for the operation to complete.
+        loop {
+            let operation = self.send(self.client.post(self.url(&path))).await?;
+            if operation["status"] != "DONE" {
+                continue;
+            }
+            if let Some(errors) = operation["error"]["errors"].as_array() {
+                let message = errors
+                    .iter()
+                    .filter_map(|x| x["message"].as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(CloudProviderError::UnexpectedResponse(message));
+            }
+            return Ok(());
+        }
     }
 
     /// Convert a GCP instance into an orchestrator instance (used in the rest of the codebase).
-    fn make_instance(&self, region: String, gcp_instance: &serde_json::Value) -> Instance {
+    fn make_instance(&self, region: String, gcp_instance: &Value) -> Instance {
         Instance {
             id: gcp_instance["name"]
                 .as_str()
@@ -38,60 +223,163 @@ impl GcpClient {
                 .unwrap_or("0.0.0.0") // Stopped instances do not have an ip address.
                 .parse()
                 .expect("GCP instance should have a valid ip"),
+            // The default network of the project spans all regions.
+            private_ip: gcp_instance["networkInterfaces"][0]["networkIP"]
+                .as_str()
+                .and_then(|x| x.parse().ok()),
             tags: vec![self.settings.testbed_id.clone()],
-            specs: format!(
-                "{}",
-                gcp_instance["machineType"]
-                    .as_str()
-                    .expect("GCP instance should have a type")
-                    .split('/')
-                    .last()
-                    .unwrap()
-            ),
-            status: format!(
-                "{}",
-                gcp_instance["status"]
-                    .as_str()
-                    .expect("GCP instance should have a status")
-            )
-            .as_str()
-            .into(),
+            specs: gcp_instance["machineType"]
+                .as_str()
+                .expect("GCP instance should have a type")
+                .split('/')
+                .last()
+                .unwrap()
+                .to_string(),
+            status: gcp_instance["status"]
+                .as_str()
+                .expect("GCP instance should have a status")
+                .into(),
         }
     }
 
+    /// List the instances of the testbed in the specified zone.
+    async fn list_zone(&self, zone: &str) -> CloudProviderResult<Vec<Instance>> {
+        let mut instances = Vec::new();
+        let mut page_token = None;
+        loop {
+            let mut url = self.url(&format!("zones/{zone}/instances"));
+            if let Some(token) = &page_token {
+                url.query_pairs_mut().append_pair("pageToken", token);
+            }
+            let json = self.send(self.client.get(url)).await?;
+
+            // The API does not support filtering instances by network tags.
+            let gcp_instances = json["items"].as_array().cloned().unwrap_or_default();
+            for instance in gcp_instances.iter().filter(|x| {
+                x["tags"]["items"]
+                    .as_array()
+                    .is_some_and(|tags| tags.iter().any(|x| x == &self.settings.testbed_id))
+            }) {
+                instances.push(self.make_instance(zone.into(), instance));
+            }
+
+            match json["nextPageToken"].as_str() {
+                Some(token) => page_token = Some(token.to_string()),
+                None => break,
+            }
+        }
+        Ok(instances)
+    }
+
+    /// The image of the instances (for the architecture of the settings).
+    fn os_image(&self) -> String {
+        match self.settings.architecture {
+            Architecture::X86_64 => Self::OS_IMAGE_FAMILY.to_string(),
+            Architecture::Arm64 => format!("{}-arm64", Self::OS_IMAGE_FAMILY),
+        }
+    }
+
+    /// The properties of the instances of the testbed: the specified machine type, two local
+    /// SSDs, and the network tags of the testbed and its firewall rule.
+    fn instance_properties(&self, specs: &str) -> Value {
+        let testbed_id = &self.settings.testbed_id;
+        let local_ssd = json!({
+            "type": "SCRATCH",
+            "interface": "NVME",
+            "autoDelete": true,
+            "initializeParams": { "diskType": "local-ssd" }
+        });
+        json!({
+            "machineType": specs,
+            "disks": [
+                {
+                    "boot": true,
+                    "autoDelete": true,
+                    "initializeParams": {
+                        "sourceImage": self.os_image(),
+                        "diskSizeGb": "200" // Default boot disk size
+                    }
+                },
+                local_ssd,
+                local_ssd
+            ],
+            "networkInterfaces": [{
+                "network": "global/networks/default",
+                "accessConfigs": [{ "type": "ONE_TO_ONE_NAT", "name": "External NAT" }]
+            }],
+            "tags": { "items": [testbed_id, format!("{testbed_id}-firewall")] }
+        })
+    }
+
+    /// Return the instance template of the testbed for the specified machine type, creating it
+    /// (along with the firewall rule of the testbed) if it doesn't already exist. Templates are
+    /// global and immutable; their name thus includes the machine type.
+    async fn get_or_create_instance_template(&self, specs: &str) -> CloudProviderResult<String> {
+        let mut guard = self.instance_templates.lock().await;
+        if let Some(template) = guard.get(specs) {
+            return Ok(template.clone());
+        }
+
+        self.create_firewall_rule().await?;
+
+        let name = format!("{}-{specs}", self.settings.testbed_id)
+            .to_lowercase()
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "-");
+        let parameters = json!({ "name": name, "properties": self.instance_properties(specs) });
+        let request = self
+            .client
+            .post(self.url("global/instanceTemplates"))
+            .json(&parameters);
+        match self.send(request).await {
+            Ok(operation) => self.wait_for_operation(&operation).await?,
+            Err(CloudProviderError::FailureResponseCode(status, _))
+                if status == StatusCode::CONFLICT.to_string() => {}
+            Err(e) => return Err(e),
+        }
+
+        let template = format!("global/instanceTemplates/{name}");
+        guard.insert(specs.to_string(), template.clone());
+        Ok(template)
+    }
+
     /// Create a new firewall rule for the instance (if it doesn't already exist).
     async fn create_firewall_rule(&self) -> CloudProviderResult<()> {
-        // Create a firewall rule (if it doesn't already exist).
         let firewall_name = format!("{}-firewall", &self.settings.testbed_id);
-        let output = Command::new("gcloud")
-            .args(&[
-                "compute",
-                "firewall-rules",
-                "create",
-                &firewall_name,
-                "--allow",
-                "tcp,udp,icmp",
-                "--source-ranges",
-                "0.0.0.0/0",
-                "--description",
-                "Allow all traffic (used for benchmarks).",
-                "--target-tags",
-                &firewall_name,
-            ])
-            .output()
-            .await
-            .expect("Failed to execute command");
-
-        if !output.status.success() {
-            let error_message = str::from_utf8(&output.stderr).unwrap();
-            if !error_message.to_lowercase().contains("already exists") {
-                return Err(CloudProviderError::UnexpectedResponse(format!(
-                    "Failed to create firewall rule: {}",
-                    error_message
-                )));
+        let parameters = json!({
+            "name": firewall_name,
+            "description": "Allow all traffic (used for benchmarks).",
+            "network": "global/networks/default",
+            "sourceRanges": ["0.0.0.0/0"],
+            "targetTags": [firewall_name],
+            "allowed": [
+                { "IPProtocol": "tcp" },
+                { "IPProtocol": "udp" },
+                { "IPProtocol": "icmp" }
+            ]
+        });
+
+        let request = self
+            .client
+            .post(self.url("global/firewalls"))
+            .json(&parameters);
+        match self.send(request).await {
+            Ok(operation) => self.wait_for_operation(&operation).await,
+            Err(CloudProviderError::FailureResponseCode(status, _))
+                if status == StatusCode::CONFLICT.to_string() =>
+            {
+                Ok(())
             }
+            Err(e) => Err(e),
         }
+    }
 
+    /// Perform an action (e.g., 'start') on an instance.
+    async fn instance_action(&self, instance: &Instance, action: &str) -> CloudProviderResult<()> {
+        let path = format!(
+            "zones/{}/instances/{}/{action}",
+            instance.region, instance.id
+        );
+        self.send(self.client.post(self.url(&path))).await?;
         Ok(())
     }
 
@@ -113,33 +401,13 @@ impl GcpClient {
 }
 
 impl ServerProviderClient for GcpClient {
-    const USERNAME: &'static str = "balaji";
+    const USERNAME: &'static str = "ubuntu";
 
     async fn list_instances(&self) -> CloudProviderResult<Vec<Instance>> {
         let mut instances = Vec::new();
         for region in &self.settings.regions {
-            let output = Command::new("gcloud")
-                .args(&[
-                    "compute",
-                    "instances",
-                    "list",
-                    "--format",
-                    "json",
-                    "--filter",
-                    &format!(
-                        "tags.items={} AND zone:({})",
-                        self.settings.testbed_id, region
-                    ),
-                ])
-                .output()
-                .await
-                .expect("Failed to execute command");
-            let gcp_instances: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)?;
-            for instance in gcp_instances {
-                instances.push(self.make_instance(region.clone(), &instance));
-            }
+            instances.extend(self.list_zone(region).await?);
         }
-
         Ok(instances)
     }
 
@@ -147,25 +415,8 @@ impl ServerProviderClient for GcpClient {
     where
         I: Iterator<Item = &'a Instance> + Send,
     {
-        let mut fut_vec = Vec::new();
-        for instance in instances {
-            let fut = Command::new("gcloud")
-                .args(&[
-                    "compute",
-                    "instances",
-                    "start",
-                    &instance.id,
-                    "--zone",
-                    &instance.region,
-                ])
-                .output();
-            fut_vec.push(fut);
-        }
-
-        try_join_all(fut_vec)
-            .await
-            .expect("Failed to execute command");
-
+        let futures = instances.map(|instance| self.instance_action(instance, "start"));
+        try_join_all(futures).await?;
         Ok(())
     }
 
@@ -173,30 +424,13 @@ impl ServerProviderClient for GcpClient {
     where
         I: Iterator<Item = &'a Instance> + Send,
     {
-        let mut fut_vec = Vec::new();
-        for instance in instances {
-            let fut = Command::new("gcloud")
-                .args(&[
-                    "compute",
-                    "instances",
-                    "stop",
-                    &instance.id,
-                    "--zone",
-                    &instance.region,
-                    "--discard-local-ssd=true",
-                ])
-                .output();
-            fut_vec.push(fut);
-        }
-
-        try_join_all(fut_vec)
-            .await
-            .expect("Failed to execute command");
-
+        let futures =
+            instances.map(|instance| self.instance_action(instance, "stop?discardLocalSsd=true"));
+        try_join_all(futures).await?;
         Ok(())
     }
 
-    async fn create_instance<S>(&self, region: S) -> CloudProviderResult<Instance>
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
     where
         S: Into<String> + Serialize + Send,
     {
@@ -206,86 +440,94 @@ impl ServerProviderClient for GcpClient {
         let testbed_id = &self.settings.testbed_id;
         let instance_id = format!("{}-{}", testbed_id, random_id);
 
-        // Create a firewall rule (if needed).
-        self.create_firewall_rule().await?;
+        // Create a new instance from the template of the testbed.
+        let template = self.get_or_create_instance_template(specs).await?;
+        let parameters = json!({ "name": instance_id });
 
-        // Create a new instance.
-        const OS_IMAGE: &str = "ubuntu-2004-lts";
-
-        let output = Command::new("gcloud")
-            .args(&[
-                "compute",
-                "instances",
-                "create",
-                &instance_id,
-                "--image-family",
-                OS_IMAGE,
-                "--image-project",
-                "ubuntu-os-cloud",
-                "--boot-disk-size",
-                "200GB", // Default boot disk size
-                "--local-ssd",
-                "interface=nvme,size=375GB", // Use local SSD
-                "--local-ssd",
-                "interface=nvme,size=375GB", // Use local SSD
-                "--machine-type",
-                &self.settings.specs,
-                "--zone",
-                &region,
-                "--tags",
-                &format!("{},{}-firewall", testbed_id, testbed_id),
-                "--format",
-                "json",
-            ])
-            .output()
-            .await
-            .expect("Failed to execute command");
-        if !output.status.success() {
-            return Err(CloudProviderError::FailureResponseCode(
-                format!("{:?}", output.status.code()),
-                format!("{}", String::from_utf8(output.stderr).unwrap()),
-            ));
+        let mut url = self.url(&format!("zones/{region}/instances"));
+        url.query_pairs_mut()
+            .append_pair("sourceInstanceTemplate", &template);
+        let operation = self.send(self.client.post(url).json(&parameters)).await?;
+        self.wait_for_operation(&operation).await?;
+
+        let url = self.url(&format!("zones/{region}/instances/{instance_id}"));
+        let gcp_instance = self.send(self.client.get(url)).await?;
+        Ok(self.make_instance(region, &gcp_instance))
+    }
+
+    async fn create_instances<S>(
+        &self,
+        region: S,
+        specs: &str,
+        quantity: usize,
+    ) -> CloudProviderResult<Vec<Instance>>
+    where
+        S: Into<String> + Serialize + Send,
+    {
+        if quantity == 0 {
+            return Ok(Vec::new());
         }
-        let gcp_instance: serde_json::Value = serde_json::from_slice(&output.stdout)?;
-        let gcp_instance = &gcp_instance[0];
-        Ok(self.make_instance(region, gcp_instance))
+        let region = region.into();
+        // Generate a unique prefix for the names of the new instances.
+        let prefix = format!("{}-{}", self.settings.testbed_id, rand::random::<u16>());
+
+        // Create all instances from the template of the testbed with a single request.
+        let template = self.get_or_create_instance_template(specs).await?;
+        let parameters = json!({
+            "count": quantity,
+            "minCount": quantity,
+            "namePattern": format!("{prefix}-####"),
+            "sourceInstanceTemplate": template
+        });
+        let url = self.url(&format!("zones/{region}/instances/bulkInsert"));
+        let operation = self.send(self.client.post(url).json(&parameters)).await?;
+        self.wait_for_operation(&operation).await?;
+
+        Ok(self
+            .list_zone(&region)
+            .await?
+            .into_iter()
+            .filter(|x| x.id.starts_with(&format!("{prefix}-")))
+            .collect())
     }
 
     async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()> {
-        let result = Command::new("gcloud")
-            .args(&[
-                "compute",
-                "instances",
-                "delete",
-                &instance.id,
-                "--zone",
-                &instance.region,
-                "--delete-disks",
-                "all",
-                "--quiet",
-            ])
-            .output()
-            .await
-            .expect("Failed to execute command");
-        assert!(result.status.success(), "Failed to delete instance");
-
+        // All disks are deleted along with the instance.
+        let path = format!("zones/{}/instances/{}", instance.region, instance.id);
+        self.send(self.client.delete(self.url(&path))).await?;
         Ok(())
     }
 
     async fn register_ssh_public_key(&self, public_key: String) -> CloudProviderResult<()> {
-        Command::new("gcloud")
-            .args(&[
-                "compute",
-                "project-info",
-                "add-metadata",
-                "--metadata",
-                &format!("ssh-keys=balaji:{}", public_key),
-            ])
-            .output()
-            .await
-            .expect("Failed to execute command");
+        // Add the key to the project-wide metadata (keeping the existing keys).
+        let project = self.send(self.client.get(self.base_url.clone())).await?;
+        let metadata = &project["commonInstanceMetadata"];
+        let mut items = metadata["items"].as_array().cloned().unwrap_or_default();
 
-        Ok(())
+        // GCP creates the user account specified by the entry on the instances.
+        let username = self.settings.ssh_username(Self::USERNAME);
+        let entry = format!("{username}:{public_key}");
+        let key = self
+            .settings
+            .gcp_ssh_keys_metadata
+            .as_deref()
+            .unwrap_or(Self::SSH_KEYS_METADATA);
+        match items.iter_mut().find(|x| x["key"] == key) {
+            Some(item) => {
+                let keys = item["value"].as_str().unwrap_or_default();
+                if keys.lines().any(|x| x == entry) {
+                    return Ok(());
+                }
+                item["value"] = format!("{keys}\n{entry}").into();
+            }
+            None => items.push(json!({ "key": key, "value": entry })),
+        }
+
+        // The fingerprint prevents overwriting concurrent changes to the metadata.
+        let parameters = json!({ "fingerprint": metadata["fingerprint"], "items": items });
+        let url = self.url("setCommonInstanceMetadata");
+        let operation = self.send(self.client.post(url).json(&parameters)).await?;
+        self.wait_for_operation(&operation).await
     }
 
     async fn instance_setup_commands(&self) -> CloudProviderResult<Vec<String>> {
@@ -296,3 +538,39 @@ impl ServerProviderClient for GcpClient {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::GcpClient;
+    use crate::{client::InstanceStatus, settings::Settings};
+
+    #[test]
+    fn make_instance() {
+        let service_account = json!({
+            "project_id": "project",
+            "client_email": "orchestrator@project.iam.gserviceaccount.com",
+            "private_key": "",
+            "token_uri": "https://oauth2.googleapis.com/token"
+        });
+        let client =
+            GcpClient::new(&service_account.to_string(), Settings::new_for_test()).unwrap();
+        assert_eq!(
+            client.url("zones/us-east1-b/instances").as_str(),
+            "https://compute.googleapis.com/compute/v1/projects/project/zones/us-east1-b/instances"
+        );
+
+        let gcp_instance = json!({
+            "name": "testbed-42",
+            "machineType": "https://www.googleapis.com/compute/v1/projects/project/zones/us-east1-b/machineTypes/n2-standard-16",
+            "status": "RUNNING",
+            "networkInterfaces": [{ "accessConfigs": [{ "natIP": "203.0.113.1" }] }]
+        });
+        let instance = client.make_instance("us-east1-b".into(), &gcp_instance);
+        assert_eq!(instance.id, "testbed-42");
+        assert_eq!(instance.main_ip.to_string(), "203.0.113.1");
+        assert_eq!(instance.specs, "n2-standard-16");
+        assert_eq!(instance.status, InstanceStatus::Active);
+    }
+}
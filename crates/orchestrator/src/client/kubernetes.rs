@@ -0,0 +1,491 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fmt::{Display, Formatter},
+    net::Ipv4Addr,
+    process::Stdio,
+    time::Duration,
+};
+
+use mysticeti_core::config::NodePublicConfig;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::{io::AsyncWriteExt, process::Command, sync::Mutex, time::sleep};
+
+use super::{Instance, InstanceStatus, ServerProviderClient};
+use crate::{
+    error::{CloudProviderError, CloudProviderResult},
+    settings::Settings,
+};
+
+/// Deploys the testbed onto an existing kubernetes cluster (through `kubectl`, which must be
+/// configured to access it). Each region of the settings is a zone of the cluster hosting one
+/// StatefulSet, whose pods are the instances of the testbed in that zone. The pods are spread
+/// across nodes (pod anti-affinity), persist their home directory in a per-pod volume (holding
+/// the codebase and configuration files), and the orchestrator runs commands in them through
+/// `kubectl exec`. The specs of the settings (per zone) select the instance type of the nodes.
+///
+/// StatefulSets only shrink from their highest ordinal: deleting any instance of a zone removes
+/// its last pod.
+pub struct KubernetesClient {
+    /// The settings of the testbed.
+    settings: Settings,
+    /// Serializes changes to the StatefulSets.
+    lock: Mutex<()>,
+}
+
+impl Display for KubernetesClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Kubernetes ({})", self.settings.kubernetes_namespace)
+    }
+}
+
+impl KubernetesClient {
+    const IMAGE: &'static str = "ubuntu:22.04";
+    /// The annotation of a StatefulSet recording its number of instances (including the ones
+    /// that are stopped, i.e., beyond its number of replicas).
+    const INSTANCES_ANNOTATION: &'static str = "mysticeti/instances";
+    /// The maximum time to wait for a new pod to be ready.
+    const READY_TIMEOUT: Duration = Duration::from_secs(600);
+    const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The name of the StatefulSet hosting the instances of the specified zone.
+    fn stateful_set_name(&self, zone: &str) -> String {
+        format!("{}-{zone}", self.settings.testbed_id)
+    }
+
+    /// Run a kubectl command (in the namespace of the testbed) and return its output. The
+    /// optional input is written to its stdin.
+    async fn kubectl(&self, args: &[&str], input: Option<&[u8]>) -> CloudProviderResult<Vec<u8>> {
+        let mut child = Command::new("kubectl")
+            .args(["--namespace", &self.settings.kubernetes_namespace])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| CloudProviderError::RequestError(format!("Failed to run kubectl: {e}")))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input.unwrap_or_default()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(CloudProviderError::FailureResponseCode(
+                format!("{:?}", output.status.code()),
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Get the kubernetes objects of the specified kind belonging to the testbed.
+    async fn get_items(&self, kind: &str) -> CloudProviderResult<Vec<Value>> {
+        let selector = format!("testbed={}", self.settings.testbed_id);
+        let output = self
+            .kubectl(
+                &["get", kind, "--selector", &selector, "--output", "json"],
+                None,
+            )
+            .await?;
+        let json: Value = serde_json::from_slice(&output)?;
+        Ok(json["items"].as_array().cloned().unwrap_or_default())
+    }
+
+    /// The manifests of the headless service and of the StatefulSet of a zone.
+    fn manifests(&self, zone: &str) -> Value {
+        let testbed_id = &self.settings.testbed_id;
+        let labels = json!({ "testbed": testbed_id, "zone": zone });
+        // Install the tools the orchestrator expects on an instance, then idle.
+        let setup = "apt-get update && apt-get -y install sudo tmux git curl && sleep infinity";
+
+        let service = json!({
+            "apiVersion": "v1",
+            "kind": "Service",
+            "metadata": { "name": testbed_id, "labels": { "testbed": testbed_id } },
+            "spec": { "clusterIP": "None", "selector": { "testbed": testbed_id } }
+        });
+        let stateful_set = json!({
+            "apiVersion": "apps/v1",
+            "kind": "StatefulSet",
+            "metadata": {
+                "name": self.stateful_set_name(zone),
+                "labels": labels,
+                "annotations": { (Self::INSTANCES_ANNOTATION): "0" }
+            },
+            "spec": {
+                "serviceName": testbed_id,
+                "replicas": 0,
+                "podManagementPolicy": "Parallel",
+                "selector": { "matchLabels": labels },
+                "template": {
+                    "metadata": {
+                        "labels": labels,
+                        "annotations": {
+                            "prometheus.io/scrape": "true",
+                            "prometheus.io/path": mysticeti_core::prometheus::METRICS_ROUTE,
+                            "prometheus.io/port": NodePublicConfig::UNIFORM_METRICS_PORT.to_string()
+                        }
+                    },
+                    "spec": {
+                        "nodeSelector": {
+                            "topology.kubernetes.io/zone": zone,
+                            "node.kubernetes.io/instance-type": self.settings.specs_for_region(zone)
+                        },
+                        "affinity": {
+                            "podAntiAffinity": {
+                                "requiredDuringSchedulingIgnoredDuringExecution": [{
+                                    "labelSelector": { "matchLabels": { "testbed": testbed_id } },
+                                    "topologyKey": "kubernetes.io/hostname"
+                                }]
+                            }
+                        },
+                        "containers": [{
+                            "name": "instance",
+                            "image": Self::IMAGE,
+                            "command": ["bash", "-c", setup],
+                            "ports": [
+                                {
+                                    "name": "network",
+                                    "containerPort": NodePublicConfig::UNIFORM_NETWORK_PORT
+                                },
+                                {
+                                    "name": "metrics",
+                                    "containerPort": NodePublicConfig::UNIFORM_METRICS_PORT
+                                }
+                            ],
+                            "readinessProbe": {
+                                "exec": { "command": ["which", "sudo", "tmux", "git", "curl"] },
+                                "periodSeconds": 5
+                            },
+                            "volumeMounts": [{ "name": "home", "mountPath": "/root" }]
+                        }]
+                    }
+                },
+                "volumeClaimTemplates": [{
+                    "metadata": { "name": "home", "labels": labels },
+                    "spec": {
+                        "accessModes": ["ReadWriteOnce"],
+                        "resources": { "requests": { "storage": "200Gi" } }
+                    }
+                }]
+            }
+        });
+        json!({ "apiVersion": "v1", "kind": "List", "items": [service, stateful_set] })
+    }
+
+    /// Set the number of instances and replicas of the StatefulSet of a zone.
+    async fn scale(
+        &self,
+        name: &str,
+        instances: usize,
+        replicas: usize,
+    ) -> CloudProviderResult<()> {
+        let patch = json!({
+            "metadata": { "annotations": { (Self::INSTANCES_ANNOTATION): instances.to_string() } },
+            "spec": { "replicas": replicas }
+        });
+        let patch = patch.to_string();
+        let target = format!("statefulset/{name}");
+        self.kubectl(
+            &["patch", &target, "--type", "merge", "--patch", &patch],
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Return the number of instances and replicas of a StatefulSet.
+    fn size(stateful_set: &Value) -> (usize, usize) {
+        let instances = stateful_set["metadata"]["annotations"][Self::INSTANCES_ANNOTATION]
+            .as_str()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_default();
+        let replicas = stateful_set["spec"]["replicas"]
+            .as_u64()
+            .unwrap_or_default();
+        (instances, replicas as usize)
+    }
+
+    /// Convert the StatefulSets and pods of the testbed into orchestrator instances (used in the
+    /// rest of the codebase). Instances without a ready pod are inactive.
+    fn make_instances(&self, stateful_sets: &[Value], pods: &[Value]) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        for stateful_set in stateful_sets {
+            let name = stateful_set["metadata"]["name"]
+                .as_str()
+                .unwrap_or_default();
+            let zone = stateful_set["metadata"]["labels"]["zone"]
+                .as_str()
+                .unwrap_or_default();
+            let (size, _) = Self::size(stateful_set);
+            for i in 0..size {
+                let id = format!("{name}-{i}");
+                let pod = pods
+                    .iter()
+                    .find(|x| x["metadata"]["name"].as_str() == Some(id.as_str()));
+                let ready = pod.map_or(false, |pod| {
+                    pod["status"]["conditions"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .any(|x| x["type"] == "Ready" && x["status"] == "True")
+                });
+                let main_ip = pod
+                    .and_then(|pod| pod["status"]["podIP"].as_str())
+                    .and_then(|x| x.parse().ok())
+                    .unwrap_or(Ipv4Addr::UNSPECIFIED);
+                instances.push(Instance {
+                    id,
+                    region: zone.into(),
+                    main_ip,
+                    private_ip: None,
+                    tags: vec![self.settings.testbed_id.clone()],
+                    specs: self.settings.specs_for_region(zone).into(),
+                    status: if ready {
+                        InstanceStatus::Active
+                    } else {
+                        InstanceStatus::Inactive
+                    },
+                });
+            }
+        }
+        instances
+    }
+
+    /// Set the number of replicas of the StatefulSets hosting the specified instances.
+    /// Starting an instance starts all the instances of lower ordinal of its zone, and stopping
+    /// it stops all the instances of higher ordinal.
+    async fn set_running<'a, I>(&self, instances: I, running: bool) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance>,
+    {
+        let _guard = self.lock.lock().await;
+        let stateful_sets = self.get_items("statefulsets").await?;
+        for instance in instances {
+            let Some((name, ordinal)) = instance.id.rsplit_once('-') else {
+                continue;
+            };
+            let Ok(ordinal) = ordinal.parse::<usize>() else {
+                continue;
+            };
+            let Some(stateful_set) = stateful_sets
+                .iter()
+                .find(|x| x["metadata"]["name"].as_str() == Some(name))
+            else {
+                continue;
+            };
+            let (size, replicas) = Self::size(stateful_set);
+            let target = if running {
+                replicas.max(ordinal + 1)
+            } else {
+                replicas.min(ordinal)
+            };
+            if target != replicas {
+                self.scale(name, size, target).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ServerProviderClient for KubernetesClient {
+    // NOTE: Unused, commands run in the pods through `kubectl exec`.
+    const USERNAME: &'static str = "root";
+
+    async fn list_instances(&self) -> CloudProviderResult<Vec<Instance>> {
+        let stateful_sets = self.get_items("statefulsets").await?;
+        let pods = self.get_items("pods").await?;
+        Ok(self.make_instances(&stateful_sets, &pods))
+    }
+
+    async fn start_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        self.set_running(instances, true).await
+    }
+
+    async fn stop_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        self.set_running(instances, false).await
+    }
+
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
+    where
+        S: Into<String> + Serialize + Send,
+    {
+        let zone = region.into();
+        let name = self.stateful_set_name(&zone);
+
+        // The nodes of a zone host a single StatefulSet (of a single instance type).
+        if specs != self.settings.specs_for_region(&zone) {
+            return Err(CloudProviderError::RequestError(format!(
+                "Kubernetes testbeds only support the specs of the zone ({zone})"
+            )));
+        }
+
+        // Add a pod to the StatefulSet of the zone (creating it if needed).
+        let id = {
+            let _guard = self.lock.lock().await;
+            let stateful_sets = self.get_items("statefulsets").await?;
+            let stateful_set = stateful_sets
+                .iter()
+                .find(|x| x["metadata"]["name"].as_str() == Some(name.as_str()));
+            let (size, replicas) = match stateful_set {
+                Some(stateful_set) => Self::size(stateful_set),
+                None => {
+                    let manifests = self.manifests(&zone).to_string();
+                    self.kubectl(&["apply", "--filename", "-"], Some(manifests.as_bytes()))
+                        .await?;
+                    (0, 0)
+                }
+            };
+            self.scale(&name, size + 1, replicas.max(size + 1)).await?;
+            format!("{name}-{size}")
+        };
+
+        // Wait until the pod is ready.
+        let mut waited = Duration::ZERO;
+        loop {
+            let instance = self
+                .list_instances()
+                .await?
+                .into_iter()
+                .find(|x| x.id == id);
+            match instance {
+                Some(instance) if instance.is_active() => return Ok(instance),
+                _ if waited >= Self::READY_TIMEOUT => {
+                    return Err(CloudProviderError::UnexpectedResponse(format!(
+                        "Pod {id} is not ready after {}s",
+                        waited.as_secs()
+                    )))
+                }
+                _ => {
+                    sleep(Self::POLL_INTERVAL).await;
+                    waited += Self::POLL_INTERVAL;
+                }
+            }
+        }
+    }
+
+    async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()> {
+        let _guard = self.lock.lock().await;
+        let stateful_sets = self.get_items("statefulsets").await?;
+        let Some(stateful_set) = stateful_sets
+            .iter()
+            .find(|x| x["metadata"]["labels"]["zone"].as_str() == Some(instance.region.as_str()))
+        else {
+            return Ok(());
+        };
+        let name = stateful_set["metadata"]["name"]
+            .as_str()
+            .unwrap_or_default();
+        let (size, replicas) = Self::size(stateful_set);
+
+        // Delete the StatefulSet (and the volumes of its pods) along with its last instance.
+        if size <= 1 {
+            let selector = format!(
+                "testbed={},zone={}",
+                self.settings.testbed_id, instance.region
+            );
+            let target = format!("statefulset/{name}");
+            self.kubectl(&["delete", &target, "--wait=false"], None)
+                .await?;
+            self.kubectl(
+                &["delete", "pvc", "--selector", &selector, "--wait=false"],
+                None,
+            )
+            .await?;
+            if stateful_sets.len() == 1 {
+                let target = format!("service/{}", self.settings.testbed_id);
+                self.kubectl(&["delete", &target, "--ignore-not-found"], None)
+                    .await?;
+            }
+            return Ok(());
+        }
+        self.scale(name, size - 1, replicas.min(size - 1)).await
+    }
+
+    async fn register_ssh_public_key(&self, _public_key: String) -> CloudProviderResult<()> {
+        // The pods are not accessed through ssh.
+        Ok(())
+    }
+
+    async fn instance_setup_commands(&self) -> CloudProviderResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysticeti_core::config::NodePublicConfig;
+    use serde_json::json;
+
+    use super::KubernetesClient;
+    use crate::{client::InstanceStatus, settings::Settings};
+
+    #[test]
+    fn make_instances() {
+        let client = KubernetesClient::new(Settings::new_for_test());
+        let stateful_sets = [json!({
+            "metadata": {
+                "name": "testbed-zone-a",
+                "labels": { "testbed": "testbed", "zone": "zone-a" },
+                "annotations": { "mysticeti/instances": "2" }
+            },
+            "spec": { "replicas": 1 }
+        })];
+        let pods = [json!({
+            "metadata": { "name": "testbed-zone-a-0" },
+            "status": {
+                "podIP": "10.0.0.7",
+                "conditions": [{ "type": "Ready", "status": "True" }]
+            }
+        })];
+
+        let instances = client.make_instances(&stateful_sets, &pods);
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].id, "testbed-zone-a-0");
+        assert_eq!(instances[0].region, "zone-a");
+        assert_eq!(instances[0].main_ip.to_string(), "10.0.0.7");
+        assert_eq!(instances[0].status, InstanceStatus::Active);
+        // The second instance is stopped (beyond the number of replicas).
+        assert_eq!(instances[1].id, "testbed-zone-a-1");
+        assert_eq!(instances[1].status, InstanceStatus::Inactive);
+    }
+
+    #[test]
+    fn manifests() {
+        let client = KubernetesClient::new(Settings::new_for_test());
+        let manifests = client.manifests("zone-a");
+        let stateful_set = &manifests["items"][1];
+        assert_eq!(stateful_set["metadata"]["name"], "testbed-zone-a");
+        assert_eq!(
+            stateful_set["spec"]["template"]["spec"]["nodeSelector"]["topology.kubernetes.io/zone"],
+            "zone-a"
+        );
+        assert_eq!(
+            stateful_set["spec"]["template"]["metadata"]["annotations"]["prometheus.io/scrape"],
+            "true"
+        );
+        assert_eq!(
+            stateful_set["spec"]["template"]["metadata"]["annotations"]["prometheus.io/port"],
+            NodePublicConfig::UNIFORM_METRICS_PORT.to_string()
+        );
+        let ports = &stateful_set["spec"]["template"]["spec"]["containers"][0]["ports"];
+        assert_eq!(
+            ports[1]["containerPort"],
+            NodePublicConfig::UNIFORM_METRICS_PORT
+        );
+    }
+}
@@ -0,0 +1,393 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{fmt::Display, net::Ipv4Addr};
+
+use futures::future::try_join_all;
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::{Client as NetworkClient, Response, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use super::{Instance, InstanceStatus, ServerProviderClient};
+use crate::{
+    error::{CloudProviderError, CloudProviderResult},
+    settings::Settings,
+};
+
+/// Represents the ssh key information as defined by Linode.
+#[derive(Debug, Deserialize)]
+pub struct SshKey {
+    pub id: u64,
+    pub label: String,
+    pub ssh_key: String,
+}
+
+/// Represents a cloud firewall as defined by Linode.
+#[derive(Debug, Deserialize)]
+pub struct Firewall {
+    pub id: u64,
+    pub label: String,
+}
+
+/// Represents an instance as defined by Linode.
+#[derive(Debug, Deserialize)]
+pub struct LinodeInstance {
+    pub id: u64,
+    pub region: String,
+    #[serde(rename = "type")]
+    pub instance_type: String,
+    pub status: String,
+    #[serde(default)]
+    pub ipv4: Vec<Ipv4Addr>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl From<LinodeInstance> for Instance {
+    fn from(instance: LinodeInstance) -> Self {
+        let status = match instance.status.as_str() {
+            "deleting" => InstanceStatus::Terminated,
+            status => status.into(),
+        };
+        Self {
+            id: instance.id.to_string(),
+            region: instance.region,
+            // Instances may also have a private address (if requested).
+            main_ip: instance
+                .ipv4
+                .into_iter()
+                .find(|x| !x.is_private())
+                .unwrap_or(Ipv4Addr::UNSPECIFIED),
+            private_ip: None,
+            tags: instance.tags,
+            specs: instance.instance_type,
+            status,
+        }
+    }
+}
+
+/// A Linode client.
+pub struct LinodeClient {
+    token: String,
+    settings: Settings,
+    base_url: Url,
+    client: NetworkClient,
+    /// Serializes the creation of the firewall (creating instances concurrently).
+    firewall_lock: Mutex<()>,
+}
+
+impl Display for LinodeClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Linode API client v4")
+    }
+}
+
+impl LinodeClient {
+    const BASE_URL: &'static str = "https://api.linode.com/v4/";
+    const DEFAULT_IMAGE: &'static str = "linode/ubuntu22.04";
+
+    /// Make a new Linode client.
+    pub fn new<T: Into<String>>(token: T, settings: Settings) -> Self {
+        Self {
+            token: token.into(),
+            settings,
+            base_url: Self::BASE_URL.parse().unwrap(),
+            client: NetworkClient::new(),
+            firewall_lock: Mutex::new(()),
+        }
+    }
+
+    /// The filter (passed in the 'X-Filter' header) selecting the resources labeled or tagged
+    /// with the testbed id.
+    fn filter(&self, field: &str) -> String {
+        json!({ (field): self.settings.testbed_id }).to_string()
+    }
+
+    /// Check an http response and return its (json) body. Linode reports errors with a
+    /// non-success status code and a body of the form `{ "errors": [{ "reason": ... }] }`.
+    async fn check_response(response: Response) -> CloudProviderResult<Value> {
+        let status = response.status();
+        let json: Value = response.json().await?;
+        if !status.is_success() {
+            let message = json["errors"]
+                .as_array()
+                .map(|errors| {
+                    errors
+                        .iter()
+                        .filter_map(|x| x["reason"].as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_else(|| json.to_string());
+            return Err(CloudProviderError::FailureResponseCode(
+                status.to_string(),
+                message,
+            ));
+        }
+        Ok(json)
+    }
+
+    /// Retrieve the ssh key associated with the current testbed.
+    pub async fn get_key(&self) -> CloudProviderResult<Option<SshKey>> {
+        let url = self.base_url.join("profile/sshkeys").unwrap();
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.token)
+            .header("X-Filter", self.filter("label"))
+            .send()
+            .await?;
+
+        let json = Self::check_response(response).await?;
+        let content = json["data"].clone();
+        let keys: Vec<SshKey> = serde_json::from_value(content)?;
+
+        Ok(keys
+            .into_iter()
+            .find(|x| x.label == self.settings.testbed_id))
+    }
+
+    /// Retrieve the firewall of the testbed or create it if it doesn't already exist. The
+    /// firewall allows all traffic (used for benchmarks).
+    async fn get_or_create_firewall(&self) -> CloudProviderResult<Firewall> {
+        let _guard = self.firewall_lock.lock().await;
+        let url = self.base_url.join("networking/firewalls").unwrap();
+        let response = self
+            .client
+            .get(url.clone())
+            .bearer_auth(&self.token)
+            .header("X-Filter", self.filter("label"))
+            .send()
+            .await?;
+
+        let json = Self::check_response(response).await?;
+        let content = json["data"].clone();
+        let firewalls: Vec<Firewall> = serde_json::from_value(content)?;
+        if let Some(firewall) = firewalls
+            .into_iter()
+            .find(|x| x.label == self.settings.testbed_id)
+        {
+            return Ok(firewall);
+        }
+
+        let parameters = json!({
+                "label": self.settings.testbed_id.clone(),
+                "rules": {
+                    "inbound_policy": "ACCEPT",
+                    "outbound_policy": "ACCEPT"
+                },
+                "tags": [self.settings.testbed_id.clone()]
+        });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&parameters)
+            .send()
+            .await?;
+
+        let json = Self::check_response(response).await?;
+        serde_json::from_value(json).map_err(CloudProviderError::from)
+    }
+
+    /// Perform an action (e.g., 'boot') on an instance.
+    async fn instance_action(&self, instance: &Instance, action: &str) -> CloudProviderResult<()> {
+        let url = self
+            .base_url
+            .join(&format!("linode/instances/{}/{action}", instance.id))
+            .unwrap();
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+
+        Self::check_response(response).await?;
+        Ok(())
+    }
+}
+
+impl ServerProviderClient for LinodeClient {
+    const USERNAME: &'static str = "root";
+
+    async fn list_instances(&self) -> CloudProviderResult<Vec<Instance>> {
+        let mut instances = Vec::new();
+        let mut page = 1;
+        loop {
+            let mut url = self.base_url.join("linode/instances").unwrap();
+            url.query_pairs_mut().append_pair("page", &page.to_string());
+            let response = self
+                .client
+                .get(url)
+                .bearer_auth(&self.token)
+                .header("X-Filter", self.filter("tags"))
+                .send()
+                .await?;
+
+            let json = Self::check_response(response).await?;
+            let content = json["data"].clone();
+            let data: Vec<LinodeInstance> = serde_json::from_value(content)?;
+            instances.extend(data.into_iter().map(Instance::from));
+
+            if json["pages"].as_u64().unwrap_or_default() <= page {
+                break;
+            }
+            page += 1;
+        }
+        Ok(instances)
+    }
+
+    async fn start_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        let futures = instances.map(|instance| self.instance_action(instance, "boot"));
+        try_join_all(futures).await?;
+        Ok(())
+    }
+
+    async fn stop_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        let futures = instances.map(|instance| self.instance_action(instance, "shutdown"));
+        try_join_all(futures).await?;
+        Ok(())
+    }
+
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
+    where
+        S: Into<String> + Serialize + Send,
+    {
+        let region: String = region.into();
+        let testbed_name = self.settings.testbed_id.clone();
+        let ssh_key = match self.get_key().await? {
+            Some(key) => key.ssh_key,
+            None => return Err(CloudProviderError::SshKeyNotFound(testbed_name.clone())),
+        };
+        let firewall = self.get_or_create_firewall().await?;
+
+        // Linode requires a root password; it is never used since we log in with the ssh key.
+        let root_password: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+
+        let url = self.base_url.join("linode/instances").unwrap();
+        let parameters = json!({
+                "region": region,
+                "type": specs,
+                "image": Self::DEFAULT_IMAGE,
+                "label": format!("{testbed_name}-{}", rand::random::<u16>()),
+                "authorized_keys": [ssh_key],
+                "root_pass": root_password,
+                "firewall_id": firewall.id,
+                "booted": true,
+                "tags": [testbed_name]
+        });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&parameters)
+            .send()
+            .await?;
+
+        let json = Self::check_response(response).await?;
+        let instance: LinodeInstance = serde_json::from_value(json)?;
+        Ok(instance.into())
+    }
+
+    async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()> {
+        let url = self
+            .base_url
+            .join(&format!("linode/instances/{}", &instance.id))
+            .unwrap();
+
+        let response = self
+            .client
+            .delete(url)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+
+        Self::check_response(response).await?;
+        Ok(())
+    }
+
+    async fn register_ssh_public_key(&self, public_key: String) -> CloudProviderResult<()> {
+        // Do not upload the key if it already exists.
+        if self.get_key().await?.is_some() {
+            return Ok(());
+        }
+
+        let url = self.base_url.join("profile/sshkeys").unwrap();
+        let parameters = json!({
+                "label": self.settings.testbed_id.clone(),
+                "ssh_key": public_key
+        });
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&parameters)
+            .send()
+            .await?;
+
+        Self::check_response(response).await?;
+        Ok(())
+    }
+
+    async fn instance_setup_commands(&self) -> CloudProviderResult<Vec<String>> {
+        Ok(vec!["sudo ufw disable".into()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::LinodeInstance;
+    use crate::client::{Instance, InstanceStatus};
+
+    #[test]
+    fn linode_instance_to_instance() {
+        let instance: LinodeInstance = serde_json::from_value(json!({
+            "id": 123,
+            "label": "testbed-42",
+            "region": "us-east",
+            "type": "g6-standard-2",
+            "status": "running",
+            "ipv4": ["192.168.133.7", "203.0.113.1"],
+            "tags": ["testbed"]
+        }))
+        .unwrap();
+        let instance: Instance = instance.into();
+        assert_eq!(instance.id, "123");
+        assert_eq!(instance.region, "us-east");
+        assert_eq!(instance.main_ip.to_string(), "203.0.113.1");
+        assert_eq!(instance.specs, "g6-standard-2");
+        assert_eq!(instance.status, InstanceStatus::Active);
+
+        let instance: LinodeInstance = serde_json::from_value(json!({
+            "id": 123,
+            "region": "us-east",
+            "type": "g6-standard-2",
+            "status": "deleting",
+            "ipv4": [],
+            "tags": ["testbed"]
+        }))
+        .unwrap();
+        let instance: Instance = instance.into();
+        assert_eq!(instance.main_ip.to_string(), "0.0.0.0");
+        assert_eq!(instance.status, InstanceStatus::Terminated);
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    env,
+    fmt::{Display, Formatter},
+    fs, io,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use super::{Instance, InstanceStatus, ServerProviderClient};
+use crate::{error::CloudProviderResult, settings::Settings};
+
+/// The state of a local instance (persisted across runs of the orchestrator).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct LocalInstance {
+    id: String,
+    region: String,
+    /// The specs of the instance (only used to select instances, local instances all share the
+    /// hardware of the host).
+    #[serde(default)]
+    specs: String,
+    active: bool,
+}
+
+/// A testbed made of local processes. Each instance is a home directory on the local machine
+/// (under `Settings::local_dir`) in which the orchestrator runs commands as local processes
+/// rather than through ssh (see `SshConnectionManager::local`). All instances listen on the
+/// loopback address, and the protocol already assigns distinct ports to each node. This allows
+/// to exercise the whole benchmark pipeline before paying for cloud instances.
+///
+/// The instances share the rust toolchain of the host but each builds its own copy of the
+/// repository; use `--skip-testbed-update` after the first benchmark.
+pub struct LocalClient {
+    /// The settings of the testbed.
+    settings: Settings,
+    /// The directory holding the home directories of the instances.
+    root: PathBuf,
+    /// The instances of the testbed.
+    instances: Mutex<Vec<LocalInstance>>,
+}
+
+impl Display for LocalClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Local ({})", self.root.display())
+    }
+}
+
+impl LocalClient {
+    const STATE_FILE: &'static str = "instances.json";
+
+    /// Make a new local client. Resumes from the instances previously created (if any).
+    pub fn new(settings: Settings) -> CloudProviderResult<Self> {
+        let root = Self::root(&settings)?;
+        let path = root.join(Self::STATE_FILE);
+        let instances = if path.exists() {
+            serde_json::from_slice(&fs::read(&path)?)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            settings,
+            root,
+            instances: Mutex::new(instances),
+        })
+    }
+
+    /// The (absolute) directory holding the home directories of the instances of the testbed.
+    pub fn root(settings: &Settings) -> CloudProviderResult<PathBuf> {
+        let root = settings.local_dir.join(&settings.testbed_id);
+        fs::create_dir_all(&root)?;
+        Ok(fs::canonicalize(root)?)
+    }
+
+    /// Persist the state of the instances.
+    fn persist(&self, instances: &[LocalInstance]) -> CloudProviderResult<()> {
+        let data = serde_json::to_vec_pretty(instances)?;
+        fs::write(self.root.join(Self::STATE_FILE), data)?;
+        Ok(())
+    }
+
+    /// Convert a local instance into an orchestrator instance (used in the rest of the codebase).
+    fn make_instance(&self, instance: &LocalInstance) -> Instance {
+        Instance {
+            id: instance.id.clone(),
+            region: instance.region.clone(),
+            main_ip: Ipv4Addr::LOCALHOST,
+            private_ip: None,
+            tags: vec![self.settings.testbed_id.clone()],
+            specs: instance.specs.clone(),
+            status: if instance.active {
+                InstanceStatus::Active
+            } else {
+                InstanceStatus::Inactive
+            },
+        }
+    }
+
+    /// Create the home directory of an instance. It links the rust toolchain of the host so
+    /// that the instance can build the codebase.
+    fn create_home(home: &Path) -> io::Result<()> {
+        fs::create_dir_all(home)?;
+        if let Some(host_home) = env::var_os("HOME").map(PathBuf::from) {
+            for toolchain in [".cargo", ".rustup"] {
+                let (source, link) = (host_home.join(toolchain), home.join(toolchain));
+                if source.exists() && !link.exists() {
+                    std::os::unix::fs::symlink(source, link)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Kill all processes started by the orchestrator on an instance (they all run in the
+    /// tmux server of the instance).
+    async fn kill_processes(&self, instance_id: &str) {
+        let home = self.root.join(instance_id);
+        // The command fails if no tmux server is running.
+        let _ = Command::new("tmux")
+            .arg("kill-server")
+            .env("TMUX_TMPDIR", &home)
+            .output()
+            .await;
+    }
+}
+
+impl ServerProviderClient for LocalClient {
+    const USERNAME: &'static str = "local";
+
+    async fn list_instances(&self) -> CloudProviderResult<Vec<Instance>> {
+        let instances = self.instances.lock().unwrap();
+        Ok(instances.iter().map(|x| self.make_instance(x)).collect())
+    }
+
+    async fn start_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        let ids: Vec<_> = instances.map(|x| x.id.clone()).collect();
+        let mut state = self.instances.lock().unwrap();
+        for instance in state.iter_mut().filter(|x| ids.contains(&x.id)) {
+            instance.active = true;
+        }
+        self.persist(&state)
+    }
+
+    async fn stop_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        let ids: Vec<_> = instances.map(|x| x.id.clone()).collect();
+        for id in &ids {
+            self.kill_processes(id).await;
+        }
+
+        let mut state = self.instances.lock().unwrap();
+        for instance in state.iter_mut().filter(|x| ids.contains(&x.id)) {
+            instance.active = false;
+        }
+        self.persist(&state)
+    }
+
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
+    where
+        S: Into<String> + Serialize + Send,
+    {
+        let mut state = self.instances.lock().unwrap();
+        let index = (0..)
+            .find(|i| !state.iter().any(|x| x.id == format!("local-{i}")))
+            .unwrap();
+        let instance = LocalInstance {
+            id: format!("local-{index}"),
+            region: region.into(),
+            specs: specs.into(),
+            active: true,
+        };
+        Self::create_home(&self.root.join(&instance.id))?;
+
+        state.push(instance.clone());
+        self.persist(&state)?;
+        Ok(self.make_instance(&instance))
+    }
+
+    async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()> {
+        self.kill_processes(&instance.id).await;
+        let home = self.root.join(&instance.id);
+        if home.exists() {
+            fs::remove_dir_all(home)?;
+        }
+
+        let mut state = self.instances.lock().unwrap();
+        state.retain(|x| x.id != instance.id);
+        self.persist(&state)
+    }
+
+    async fn register_ssh_public_key(&self, _public_key: String) -> CloudProviderResult<()> {
+        // Local instances are not accessed through ssh.
+        Ok(())
+    }
+
+    async fn instance_setup_commands(&self) -> CloudProviderResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LocalClient;
+    use crate::{client::ServerProviderClient, settings::Settings};
+
+    #[tokio::test]
+    async fn local_instances() {
+        let mut settings = Settings::new_for_test();
+        settings.local_dir = tempfile::tempdir().unwrap().into_path();
+        settings.regions = vec!["local".into()];
+        let client = LocalClient::new(settings.clone()).unwrap();
+
+        let first = client.create_instance("local", "local").await.unwrap();
+        let second = client.create_instance("local", "local").await.unwrap();
+        assert_ne!(first.id, second.id);
+        assert!(first.is_active());
+        assert!(client.root.join(&first.id).is_dir());
+
+        client.stop_instances([&first].into_iter()).await.unwrap();
+        client.delete_instance(second.clone()).await.unwrap();
+        assert!(!client.root.join(&second.id).exists());
+
+        // The state of the testbed survives the client.
+        let client = LocalClient::new(settings).unwrap();
+        let instances = client.list_instances().await.unwrap();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].id, first.id);
+        assert!(instances[0].is_inactive());
+    }
+}
@@ -6,12 +6,19 @@ use std::{
     net::{Ipv4Addr, SocketAddr},
 };
 
+use futures::future::try_join_all;
 use serde::{Deserialize, Serialize};
 
 use crate::error::CloudProviderResult;
 
 pub mod aws;
+pub mod azure;
+pub mod digitalocean;
 pub mod gcp;
+pub mod kubernetes;
+pub mod linode;
+pub mod local;
+pub mod terraform;
 pub mod vultr;
 
 #[derive(Debug, Deserialize, Clone, Eq, PartialEq, Hash)]
@@ -40,6 +47,10 @@ pub struct Instance {
     pub region: String,
     /// The public ip address of the instance (accessible from anywhere).
     pub main_ip: Ipv4Addr,
+    /// The private ip address of the instance (only accessible from the private network of the
+    /// testbed), if any.
+    #[serde(default)]
+    pub private_ip: Option<Ipv4Addr>,
     /// The list of tags associated with the instance.
     pub tags: Vec<String>,
     /// The specs of the instance.
@@ -75,6 +86,7 @@ impl Instance {
             id,
             region: Default::default(),
             main_ip: Ipv4Addr::LOCALHOST,
+            private_ip: None,
             tags: Default::default(),
             specs: Default::default(),
             status: InstanceStatus::Active,
@@ -99,15 +111,37 @@ pub trait ServerProviderClient: Display {
     where
         I: Iterator<Item = &'a Instance> + Send;
 
-    /// Create an instance in a specific region.
-    async fn create_instance<S>(&self, region: S) -> CloudProviderResult<Instance>
+    /// Create an instance with the specified specs in a specific region.
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
     where
         S: Into<String> + Serialize + Send;
 
+    /// Create the specified number of instances (with the specified specs) in a specific region.
+    /// Providers supporting bulk creation override this method to create all instances with a
+    /// single request.
+    async fn create_instances<S>(
+        &self,
+        region: S,
+        specs: &str,
+        quantity: usize,
+    ) -> CloudProviderResult<Vec<Instance>>
+    where
+        S: Into<String> + Serialize + Send,
+    {
+        let region: String = region.into();
+        try_join_all((0..quantity).map(|_| self.create_instance(region.clone(), specs))).await
+    }
+
     /// Delete a specific instance. Calling this function ensures we are no longer billed for
     /// the specified instance.
     async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()>;
 
+    /// Delete the resources shared by the instances of the testbed (e.g., its private networks)
+    /// once all its instances are deleted.
+    async fn delete_testbed_resources(&self) -> CloudProviderResult<()> {
+        Ok(())
+    }
+
     /// Authorize the provided ssh public key to access machines.
     async fn register_ssh_public_key(&self, public_key: String) -> CloudProviderResult<()>;
 
@@ -117,7 +151,13 @@ pub trait ServerProviderClient: Display {
 
 #[cfg(test)]
 pub mod test_client {
-    use std::{fmt::Display, sync::Mutex};
+    use std::{
+        fmt::Display,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
 
     use serde::Serialize;
 
@@ -127,6 +167,7 @@ pub mod test_client {
     pub struct TestClient {
         settings: Settings,
         instances: Mutex<Vec<Instance>>,
+        next_id: AtomicUsize,
     }
 
     impl TestClient {
@@ -134,6 +175,7 @@ pub mod test_client {
             Self {
                 settings,
                 instances: Mutex::new(Vec::new()),
+                next_id: AtomicUsize::new(0),
             }
         }
     }
@@ -176,18 +218,23 @@ pub mod test_client {
             Ok(())
         }
 
-        async fn create_instance<S>(&self, region: S) -> CloudProviderResult<Instance>
+        async fn create_instance<S>(
+            &self,
+            region: S,
+            specs: &str,
+        ) -> CloudProviderResult<Instance>
         where
             S: Into<String> + Serialize + Send,
         {
             let mut guard = self.instances.lock().unwrap();
-            let id = guard.len();
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
             let instance = Instance {
                 id: id.to_string(),
                 region: region.into(),
                 main_ip: format!("0.0.0.{id}").parse().unwrap(),
+                private_ip: None,
                 tags: Vec::new(),
-                specs: self.settings.specs.clone(),
+                specs: specs.into(),
                 status: InstanceStatus::Active,
             };
             guard.push(instance.clone());
@@ -0,0 +1,401 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    fs,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{process::Command, sync::Mutex};
+
+use super::{Instance, InstanceStatus, ServerProviderClient};
+use crate::{
+    error::{CloudProviderError, CloudProviderResult},
+    settings::Settings,
+};
+
+/// The desired state of an instance, as passed to the terraform module.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DesiredInstance {
+    pub region: String,
+    /// The specs of the instance.
+    #[serde(default)]
+    pub specs: String,
+    pub running: bool,
+}
+
+/// The inputs of the terraform module describing the testbed. The module is provided by the
+/// user (see `Settings::terraform_module`) and must accept these variables.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TestbedModule {
+    /// The source of the terraform module (e.g., a local path or a git url).
+    pub source: String,
+    pub testbed_id: String,
+    pub specs: String,
+    pub ssh_public_key: String,
+    /// The instances of the testbed, indexed by name.
+    pub instances: BTreeMap<String, DesiredInstance>,
+}
+
+/// The terraform configuration rendered in the state directory (as `main.tf.json`). The module
+/// must output a map `instances` from instance name to [`TerraformInstance`].
+#[derive(Serialize, Deserialize, Debug)]
+struct Configuration {
+    module: BTreeMap<String, TestbedModule>,
+    output: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Configuration {
+    const MODULE: &'static str = "testbed";
+    const OUTPUT: &'static str = "instances";
+
+    fn new(module: TestbedModule) -> Self {
+        let value = format!("${{module.{}.{}}}", Self::MODULE, Self::OUTPUT);
+        Self {
+            module: [(Self::MODULE.to_string(), module)].into(),
+            output: [(
+                Self::OUTPUT.to_string(),
+                [("value".to_string(), value)].into(),
+            )]
+            .into(),
+        }
+    }
+}
+
+/// Represents an instance as output by the terraform module.
+#[derive(Debug, Deserialize)]
+pub struct TerraformInstance {
+    pub region: String,
+    pub public_ip: Option<Ipv4Addr>,
+    pub specs: String,
+    pub running: bool,
+}
+
+impl TerraformInstance {
+    fn into_instance(self, name: String, testbed_id: &str) -> Instance {
+        Instance {
+            id: name,
+            region: self.region,
+            // Stopped instances may not have an ip address.
+            main_ip: self.public_ip.unwrap_or(Ipv4Addr::UNSPECIFIED),
+            private_ip: None,
+            tags: vec![testbed_id.to_string()],
+            specs: self.specs,
+            status: if self.running {
+                InstanceStatus::Active
+            } else {
+                InstanceStatus::Inactive
+            },
+        }
+    }
+}
+
+/// The desired state of the testbed along with a version number, incremented at every change.
+struct DesiredState {
+    module: TestbedModule,
+    version: u64,
+}
+
+/// Provisions the testbed by rendering a terraform configuration from the settings and running
+/// `terraform apply` (or `destroy`). The actual infrastructure is described by a user-provided
+/// terraform module, so this client works with any cloud supported by terraform. The rendered
+/// configuration and the terraform state are kept (and can be reviewed) in the state directory.
+pub struct TerraformClient {
+    /// The settings of the testbed.
+    settings: Settings,
+    /// The directory holding the rendered configuration and the terraform state.
+    directory: PathBuf,
+    /// The desired state of the testbed.
+    desired: Mutex<DesiredState>,
+    /// The version of the desired state last applied. Holding this lock serializes the calls
+    /// to terraform, and concurrent changes are applied together.
+    applied: Mutex<u64>,
+}
+
+impl Display for TerraformClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Terraform ({})", self.directory.display())
+    }
+}
+
+impl TerraformClient {
+    const CONFIGURATION_FILE: &'static str = "main.tf.json";
+
+    /// Make a new terraform client. Resumes from the configuration previously rendered in the
+    /// state directory (if any).
+    pub fn new(settings: Settings, module_source: String) -> CloudProviderResult<Self> {
+        let directory = settings.terraform_dir.join(&settings.testbed_id);
+        fs::create_dir_all(&directory).map_err(|e| {
+            CloudProviderError::RequestError(format!(
+                "Failed to create terraform directory {}: {e}",
+                directory.display()
+            ))
+        })?;
+
+        let instances = Self::load_configuration(&directory)?
+            .and_then(|mut x| x.module.remove(Configuration::MODULE))
+            .map(|x| x.instances)
+            .unwrap_or_default();
+        let module = TestbedModule {
+            source: module_source,
+            testbed_id: settings.testbed_id.clone(),
+            specs: settings.specs.clone(),
+            ssh_public_key: String::new(),
+            instances,
+        };
+
+        Ok(Self {
+            settings,
+            directory,
+            desired: Mutex::new(DesiredState { module, version: 0 }),
+            applied: Mutex::new(0),
+        })
+    }
+
+    /// Load the configuration previously rendered in the specified directory.
+    fn load_configuration(directory: &Path) -> CloudProviderResult<Option<Configuration>> {
+        let path = directory.join(Self::CONFIGURATION_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(&path).map_err(|e| {
+            CloudProviderError::RequestError(format!("Failed to read {}: {e}", path.display()))
+        })?;
+        Ok(Some(serde_json::from_slice(&data)?))
+    }
+
+    /// Run a terraform command in the state directory and return its output.
+    async fn terraform(&self, args: &[&str]) -> CloudProviderResult<Vec<u8>> {
+        let output = Command::new("terraform")
+            .current_dir(&self.directory)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| {
+                CloudProviderError::RequestError(format!("Failed to run terraform: {e}"))
+            })?;
+        if !output.status.success() {
+            return Err(CloudProviderError::FailureResponseCode(
+                format!("{:?}", output.status.code()),
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Update the desired state of the testbed and apply it. Changes made concurrently by
+    /// other callers are applied together.
+    async fn update<F>(&self, f: F) -> CloudProviderResult<()>
+    where
+        F: FnOnce(&mut TestbedModule),
+    {
+        let version = {
+            let mut desired = self.desired.lock().await;
+            f(&mut desired.module);
+            desired.version += 1;
+            desired.version
+        };
+
+        let mut applied = self.applied.lock().await;
+        if *applied >= version {
+            return Ok(());
+        }
+
+        let (module, version) = {
+            let desired = self.desired.lock().await;
+            (desired.module.clone(), desired.version)
+        };
+        let destroy = module.instances.is_empty();
+        let configuration = serde_json::to_vec_pretty(&Configuration::new(module))?;
+        let path = self.directory.join(Self::CONFIGURATION_FILE);
+        fs::write(&path, configuration).map_err(|e| {
+            CloudProviderError::RequestError(format!("Failed to write {}: {e}", path.display()))
+        })?;
+
+        self.terraform(&["init", "-input=false", "-no-color"])
+            .await?;
+        let command = if destroy { "destroy" } else { "apply" };
+        self.terraform(&[command, "-input=false", "-no-color", "-auto-approve"])
+            .await?;
+
+        *applied = version;
+        Ok(())
+    }
+
+    /// Return the instances currently output by the terraform module, indexed by name.
+    async fn output_instances(&self) -> CloudProviderResult<BTreeMap<String, TerraformInstance>> {
+        let output = self
+            .terraform(&["output", "-json", "-no-color", Configuration::OUTPUT])
+            .await?;
+        Ok(serde_json::from_slice(&output)?)
+    }
+}
+
+impl ServerProviderClient for TerraformClient {
+    // NOTE: The images selected by the terraform module must allow this user to log in.
+    const USERNAME: &'static str = "ubuntu";
+
+    async fn list_instances(&self) -> CloudProviderResult<Vec<Instance>> {
+        // Terraform has no outputs before the first apply.
+        if self.desired.lock().await.module.instances.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let testbed_id = &self.settings.testbed_id;
+        Ok(self
+            .output_instances()
+            .await?
+            .into_iter()
+            .map(|(name, instance)| instance.into_instance(name, testbed_id))
+            .collect())
+    }
+
+    async fn start_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        let names: Vec<_> = instances.map(|x| x.id.clone()).collect();
+        self.update(|module| {
+            for name in &names {
+                if let Some(instance) = module.instances.get_mut(name) {
+                    instance.running = true;
+                }
+            }
+        })
+        .await
+    }
+
+    async fn stop_instances<'a, I>(&self, instances: I) -> CloudProviderResult<()>
+    where
+        I: Iterator<Item = &'a Instance> + Send,
+    {
+        let names: Vec<_> = instances.map(|x| x.id.clone()).collect();
+        self.update(|module| {
+            for name in &names {
+                if let Some(instance) = module.instances.get_mut(name) {
+                    instance.running = false;
+                }
+            }
+        })
+        .await
+    }
+
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
+    where
+        S: Into<String> + Serialize + Send,
+    {
+        let name = format!("{}-{}", self.settings.testbed_id, rand::random::<u16>());
+        let region: String = region.into();
+        let desired = DesiredInstance {
+            specs: specs.into(),
+            region,
+            running: true,
+        };
+        self.update(|module| {
+            module.instances.insert(name.clone(), desired);
+        })
+        .await?;
+
+        let testbed_id = &self.settings.testbed_id;
+        self.output_instances()
+            .await?
+            .remove(&name)
+            .map(|instance| instance.into_instance(name.clone(), testbed_id))
+            .ok_or_else(|| {
+                CloudProviderError::UnexpectedResponse(format!(
+                    "Terraform module did not output instance '{name}'"
+                ))
+            })
+    }
+
+    async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()> {
+        self.update(|module| {
+            module.instances.remove(&instance.id);
+        })
+        .await
+    }
+
+    async fn register_ssh_public_key(&self, public_key: String) -> CloudProviderResult<()> {
+        // The key is passed to the module at the next apply.
+        self.desired.lock().await.module.ssh_public_key = public_key;
+        Ok(())
+    }
+
+    async fn instance_setup_commands(&self) -> CloudProviderResult<Vec<String>> {
+        // The terraform module is responsible for setting up the instances.
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{Configuration, DesiredInstance, TerraformInstance, TestbedModule};
+    use crate::client::InstanceStatus;
+
+    #[test]
+    fn render_configuration() {
+        let module = TestbedModule {
+            source: "./modules/aws".into(),
+            testbed_id: "testbed".into(),
+            specs: "m5d.8xlarge".into(),
+            ssh_public_key: "ssh-ed25519 AAAA".into(),
+            instances: [(
+                "testbed-1".to_string(),
+                DesiredInstance {
+                    region: "us-east-1".into(),
+                    specs: "m5d.8xlarge".into(),
+                    running: true,
+                },
+            )]
+            .into(),
+        };
+        let rendered = serde_json::to_value(Configuration::new(module)).unwrap();
+        assert_eq!(rendered["module"]["testbed"]["source"], "./modules/aws");
+        assert_eq!(
+            rendered["module"]["testbed"]["instances"]["testbed-1"]["region"],
+            "us-east-1"
+        );
+        assert_eq!(
+            rendered["output"]["instances"]["value"],
+            "${module.testbed.instances}"
+        );
+
+        // The rendered configuration can be loaded back.
+        let loaded: Configuration = serde_json::from_value(rendered).unwrap();
+        assert_eq!(loaded.module["testbed"].instances.len(), 1);
+    }
+
+    #[test]
+    fn parse_output() {
+        let output = r#"{
+            "testbed-1": {
+                "region": "us-east-1",
+                "public_ip": "203.0.113.1",
+                "specs": "m5d.8xlarge",
+                "running": true
+            },
+            "testbed-2": {
+                "region": "us-east-1",
+                "public_ip": null,
+                "specs": "m5d.8xlarge",
+                "running": false
+            }
+        }"#;
+        let instances: BTreeMap<String, TerraformInstance> = serde_json::from_str(output).unwrap();
+        let instances: Vec<_> = instances
+            .into_iter()
+            .map(|(name, x)| x.into_instance(name, "testbed"))
+            .collect();
+        assert_eq!(instances[0].id, "testbed-1");
+        assert_eq!(instances[0].main_ip.to_string(), "203.0.113.1");
+        assert_eq!(instances[0].status, InstanceStatus::Active);
+        assert_eq!(instances[1].main_ip.to_string(), "0.0.0.0");
+        assert_eq!(instances[1].status, InstanceStatus::Inactive);
+    }
+}
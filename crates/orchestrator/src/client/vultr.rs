@@ -6,6 +6,7 @@ use std::{fmt::Display, net::Ipv4Addr};
 use reqwest::{Client as NetworkClient, Response, Url};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use tokio::sync::Mutex;
 
 use super::{Instance, ServerProviderClient};
 use crate::{
@@ -40,17 +41,29 @@ pub struct VultrInstance {
     pub id: String,
     pub region: String,
     pub main_ip: Ipv4Addr,
+    /// The address of the instance in its VPC (empty if it is not attached to any).
+    #[serde(default)]
+    pub internal_ip: String,
     pub tags: Vec<String>,
     pub plan: String,
     pub power_status: String,
 }
 
+/// Represents a VPC (private network of a region) as defined by Vultr.
+#[derive(Debug, Deserialize)]
+pub struct Vpc {
+    pub id: String,
+    pub region: String,
+    pub description: String,
+}
+
 impl From<VultrInstance> for Instance {
     fn from(instance: VultrInstance) -> Self {
         Self {
             id: instance.id,
             region: instance.region,
             main_ip: instance.main_ip,
+            private_ip: instance.internal_ip.parse().ok(),
             tags: instance.tags,
             specs: instance.plan,
             status: instance.power_status.as_str().into(),
@@ -63,7 +76,7 @@ impl VultrInstance {
     pub fn filter(&self, settings: &Settings) -> bool {
         settings.regions.contains(&self.region)
             && self.tags.contains(&settings.testbed_id)
-            && self.plan == settings.specs
+            && settings.accepts_specs(&self.region, &self.plan)
     }
 }
 
@@ -73,6 +86,8 @@ pub struct VultrClient {
     settings: Settings,
     base_url: Url,
     client: NetworkClient,
+    /// Serializes the creation of VPCs (creating instances concurrently).
+    vpc_lock: Mutex<()>,
 }
 
 impl Display for VultrClient {
@@ -92,6 +107,7 @@ impl VultrClient {
             settings,
             base_url: Self::BASE_URL.parse().unwrap(),
             client: NetworkClient::new(),
+            vpc_lock: Mutex::new(()),
         }
     }
 
@@ -132,6 +148,51 @@ impl VultrClient {
             .into_iter()
             .find(|x| x.name == self.settings.testbed_id))
     }
+
+    /// Retrieve the VPCs of the testbed (at most one per region).
+    async fn list_vpcs(&self) -> CloudProviderResult<Vec<Vpc>> {
+        let url = self.base_url.join("vpcs").unwrap();
+        let response = self.client.get(url).bearer_auth(&self.token).send().await?;
+
+        let json: Value = response.json().await?;
+        Self::check_response(&json)?;
+        let content = json["vpcs"].clone();
+        let vpcs: Vec<Vpc> = serde_json::from_value(content)?;
+        Ok(vpcs
+            .into_iter()
+            .filter(|x| x.description == self.settings.testbed_id)
+            .collect())
+    }
+
+    /// Retrieve the VPC of the testbed in the specified region or create it if it doesn't
+    /// already exist. VPCs are regional: instances of different regions cannot reach each
+    /// other through their private addresses.
+    async fn get_or_create_vpc(&self, region: &str) -> CloudProviderResult<Vpc> {
+        let _guard = self.vpc_lock.lock().await;
+        let vpcs = self.list_vpcs().await?;
+        if let Some(vpc) = vpcs.into_iter().find(|x| x.region == region) {
+            return Ok(vpc);
+        }
+
+        let parameters = json!({
+                "region": region,
+                "description": self.settings.testbed_id.clone()
+        });
+
+        let url = self.base_url.join("vpcs").unwrap();
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&parameters)
+            .send()
+            .await?;
+
+        let json: Value = response.json().await?;
+        Self::check_response(&json)?;
+        let content = json["vpc"].clone();
+        serde_json::from_value(content).map_err(CloudProviderError::from)
+    }
 }
 
 impl ServerProviderClient for VultrClient {
@@ -195,7 +256,7 @@ impl ServerProviderClient for VultrClient {
         Ok(())
     }
 
-    async fn create_instance<S>(&self, region: S) -> CloudProviderResult<Instance>
+    async fn create_instance<S>(&self, region: S, specs: &str) -> CloudProviderResult<Instance>
     where
         S: Into<String> + Serialize + Send,
     {
@@ -205,16 +266,21 @@ impl ServerProviderClient for VultrClient {
             None => return Err(CloudProviderError::SshKeyNotFound(testbed_name.clone())),
         };
 
+        let region: String = region.into();
         let url = self.base_url.join("instances").unwrap();
-        let parameters = json!({
+        let mut parameters = json!({
                 "region": region,
-                "plan": self.settings.specs.clone(),
+                "plan": specs,
                 "os_id": Self::DEFAULT_OS,
                 "label": self.settings.testbed_id.clone(),
                 "sshkey_id": [ssh_key_id],
                 "hostname": "validator",
                 "tag": testbed_name
         });
+        if self.settings.private_networking {
+            let vpc = self.get_or_create_vpc(&region).await?;
+            parameters["attach_vpc"] = json!([vpc.id]);
+        }
 
         let response = self
             .client
@@ -227,7 +293,8 @@ impl ServerProviderClient for VultrClient {
         let json: Value = response.json().await?;
         Self::check_response(&json)?;
         let content = json["instance"].clone();
-        serde_json::from_value::<Instance>(content).map_err(CloudProviderError::from)
+        let instance: VultrInstance = serde_json::from_value(content)?;
+        Ok(instance.into())
     }
 
     async fn delete_instance(&self, instance: Instance) -> CloudProviderResult<()> {
@@ -247,6 +314,20 @@ impl ServerProviderClient for VultrClient {
         Ok(())
     }
 
+    async fn delete_testbed_resources(&self) -> CloudProviderResult<()> {
+        for vpc in self.list_vpcs().await? {
+            let url = self.base_url.join(&format!("vpcs/{}", vpc.id)).unwrap();
+            let response = self
+                .client
+                .delete(url)
+                .bearer_auth(&self.token)
+                .send()
+                .await?;
+            Self::check_status_code(&response)?;
+        }
+        Ok(())
+    }
+
     async fn register_ssh_public_key(&self, public_key: String) -> CloudProviderResult<()> {
         // Do not upload the key if it already exists.
         if self.get_key().await?.is_some() {
@@ -0,0 +1,265 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use prettytable::{row, Table};
+
+use crate::{
+    display,
+    measurements::{BenchmarkResult, MeasurementsCollection},
+};
+
+/// The identifier of a benchmark workload compared across two sets of results: the committee
+/// size, faults, load, client parameters, and workload.
+type Key = (usize, String, usize, String, String);
+
+/// Load the results of a measurements file, or of all the measurements files of a directory.
+pub fn load_results<P: AsRef<Path>>(path: P) -> Result<Vec<BenchmarkResult>, std::io::Error> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(MeasurementsCollection::load(path)?.results());
+    }
+
+    let mut results = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with("measurements-") && name.ends_with(".json") {
+            results.extend(MeasurementsCollection::load(&path)?.results());
+        }
+    }
+    Ok(results)
+}
+
+/// A metric of a workload compared across two sets of results.
+struct Delta {
+    /// The name of the metric.
+    name: &'static str,
+    /// The value of the baseline, if known.
+    baseline: Option<f64>,
+    /// The value of the candidate, if known.
+    candidate: Option<f64>,
+    /// Whether higher values are better (e.g., throughput) or worse (e.g., latency).
+    higher_is_better: bool,
+}
+
+impl Delta {
+    /// The relative change (in percent) from the baseline to the candidate, if known.
+    fn change(&self) -> Option<f64> {
+        match (self.baseline, self.candidate) {
+            (Some(baseline), Some(candidate)) if baseline != 0.0 => {
+                Some((candidate - baseline) / baseline * 100.0)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the candidate is worse than the baseline by more than the specified threshold
+    /// (in percent).
+    fn is_regression(&self, threshold: f64) -> bool {
+        self.change().is_some_and(|change| {
+            if self.higher_is_better {
+                change < -threshold
+            } else {
+                change > threshold
+            }
+        })
+    }
+
+    /// Format the values and change of the metric.
+    fn display(&self) -> String {
+        let value = |x: Option<f64>| x.map_or("-".into(), |x| format!("{x:.0}"));
+        let change = self
+            .change()
+            .map_or(String::new(), |x| format!(" ({x:+.1}%)"));
+        format!(
+            "{} -> {}{change}",
+            value(self.baseline),
+            value(self.candidate)
+        )
+    }
+}
+
+/// The comparison of two sets of results (e.g., of the main branch and of a feature branch),
+/// flagging the regressions beyond a threshold.
+pub struct Comparison {
+    /// The threshold (in percent) beyond which a change is a regression.
+    threshold: f64,
+    /// The compared metrics of each workload present in both sets of results.
+    workloads: Vec<(Key, Vec<Delta>)>,
+    /// The number of workloads present in only one set of results.
+    unmatched: usize,
+}
+
+impl Comparison {
+    /// Compare the candidate results to the baseline results. The results of the repeated runs
+    /// of the same benchmark are averaged.
+    pub fn new(
+        baseline: &[BenchmarkResult],
+        candidate: &[BenchmarkResult],
+        threshold: f64,
+    ) -> Self {
+        let baseline = Self::average(baseline);
+        let mut candidate = Self::average(candidate);
+
+        let mut workloads = Vec::new();
+        let mut unmatched = 0;
+        for (key, base) in baseline {
+            let Some(cand) = candidate.remove(&key) else {
+                unmatched += 1;
+                continue;
+            };
+            let delta =
+                |name, metric: fn(&BenchmarkResult) -> Option<f64>, higher_is_better| Delta {
+                    name,
+                    baseline: metric(&base),
+                    candidate: metric(&cand),
+                    higher_is_better,
+                };
+            let deltas = vec![
+                delta("TPS", |x| Some(x.tps as f64), true),
+                delta("Latency (avg)", |x| Some(x.average_latency_ms), false),
+                delta("Latency (p50)", |x| x.p50_latency_ms, false),
+                delta("Latency (p99)", |x| x.p99_latency_ms, false),
+            ];
+            workloads.push((key, deltas));
+        }
+        unmatched += candidate.len();
+
+        Self {
+            threshold,
+            workloads,
+            unmatched,
+        }
+    }
+
+    /// Average the results of the same workload (ignoring failed benchmarks).
+    fn average(results: &[BenchmarkResult]) -> BTreeMap<Key, BenchmarkResult> {
+        let mut groups: BTreeMap<Key, Vec<&BenchmarkResult>> = BTreeMap::new();
+        for result in results.iter().filter(|x| x.failure.is_none()) {
+            let key = (
+                result.nodes,
+                result.faults.clone(),
+                result.load,
+                result.client_parameters.clone(),
+                result.workload.clone(),
+            );
+            groups.entry(key).or_default().push(result);
+        }
+
+        groups
+            .into_iter()
+            .map(|(key, group)| {
+                let n = group.len() as f64;
+                let mean = |metric: fn(&BenchmarkResult) -> f64| {
+                    group.iter().map(|x| metric(x)).sum::<f64>() / n
+                };
+                let mean_quantile = |metric: fn(&BenchmarkResult) -> Option<f64>| {
+                    let values: Vec<_> = group.iter().filter_map(|x| metric(x)).collect();
+                    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+                };
+                let result = BenchmarkResult {
+                    tps: mean(|x| x.tps as f64).round() as u64,
+                    average_latency_ms: mean(|x| x.average_latency_ms),
+                    stdev_latency_ms: mean(|x| x.stdev_latency_ms),
+                    p50_latency_ms: mean_quantile(|x| x.p50_latency_ms),
+                    p99_latency_ms: mean_quantile(|x| x.p99_latency_ms),
+                    ..group[0].clone()
+                };
+                (key, result)
+            })
+            .collect()
+    }
+
+    /// The number of metrics that regressed beyond the threshold.
+    pub fn regressions(&self) -> usize {
+        self.workloads
+            .iter()
+            .flat_map(|(_, deltas)| deltas)
+            .filter(|x| x.is_regression(self.threshold))
+            .count()
+    }
+
+    /// Print the change of every metric of every workload, flagging the regressions.
+    pub fn display(&self) {
+        let mut table = Table::new();
+        table.set_format(display::default_table_format());
+
+        table.set_titles(
+            row![bH3->format!("Comparison (regression threshold: {}%)", self.threshold)],
+        );
+        for ((nodes, faults, load, client_parameters, workload), deltas) in &self.workloads {
+            table.add_row(row![bH3->""]);
+            table.add_row(row![
+                b->"Benchmark:",
+                format!("{nodes} nodes ({faults}) - {load} tx/s - {client_parameters}"),
+                ""
+            ]);
+            table.add_row(row![b->"Workload:", workload, ""]);
+            for delta in deltas {
+                let flag = if delta.is_regression(self.threshold) {
+                    "REGRESSION"
+                } else {
+                    ""
+                };
+                table.add_row(row![b->format!("{}:", delta.name), delta.display(), bFr->flag]);
+            }
+        }
+
+        display::newline();
+        table.printstd();
+        if self.unmatched != 0 {
+            display::warn(format!(
+                "{} workloads are present in only one set of results",
+                self.unmatched
+            ));
+        }
+        display::newline();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Comparison;
+    use crate::measurements::BenchmarkResult;
+
+    fn result(load: usize, tps: u64, p99_latency_ms: f64) -> BenchmarkResult {
+        BenchmarkResult {
+            benchmark: format!("{load}"),
+            nodes: 4,
+            faults: "0 faulty".into(),
+            load,
+            client_parameters: "512B tx".into(),
+            repetition: 0,
+            workload: "shared".into(),
+            tps,
+            average_latency_ms: p99_latency_ms / 2.0,
+            stdev_latency_ms: 0.0,
+            p50_latency_ms: None,
+            p99_latency_ms: Some(p99_latency_ms),
+            failure: None,
+        }
+    }
+
+    #[test]
+    fn regressions() {
+        let baseline = [result(100, 100, 1000.0), result(200, 200, 1000.0)];
+
+        // Within the threshold.
+        let candidate = [result(100, 98, 1030.0), result(200, 205, 900.0)];
+        let comparison = Comparison::new(&baseline, &candidate, 5.0);
+        assert_eq!(comparison.regressions(), 0);
+
+        // Lower throughput and higher latency.
+        let candidate = [result(100, 90, 1000.0), result(200, 200, 1500.0)];
+        let comparison = Comparison::new(&baseline, &candidate, 5.0);
+        assert_eq!(comparison.regressions(), 3);
+
+        // Repeated runs are averaged and unmatched workloads ignored.
+        let candidate = [result(100, 90, 1000.0), result(100, 110, 1000.0)];
+        let comparison = Comparison::new(&baseline, &candidate, 5.0);
+        assert_eq!(comparison.regressions(), 0);
+        assert_eq!(comparison.unmatched, 1);
+    }
+}
@@ -0,0 +1,185 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use prettytable::{row, Table};
+
+use crate::{
+    display,
+    error::{SettingsError, SettingsResult},
+    settings::Settings,
+};
+
+/// Approximate on-demand hourly prices (in USD, in the cheapest US regions) of the instances
+/// commonly used for benchmarks. Prices vary across regions and over time; the settings may
+/// override them (see `instance_prices`).
+const PRICES: &[(&str, f64)] = &[
+    // AWS.
+    ("t3.medium", 0.0416),
+    ("m5d.2xlarge", 0.452),
+    ("m5d.4xlarge", 0.904),
+    ("m5d.8xlarge", 1.808),
+    ("m6gd.8xlarge", 1.4464),
+    ("c5d.4xlarge", 0.768),
+    ("i3.2xlarge", 0.624),
+    // GCP.
+    ("e2-standard-4", 0.134),
+    ("n2-standard-8", 0.3885),
+    ("n2-standard-16", 0.7769),
+    ("n2d-standard-16", 0.6759),
+    ("t2a-standard-16", 0.616),
+    // Azure.
+    ("Standard_D8s_v5", 0.384),
+    ("Standard_D16s_v5", 0.768),
+    ("Standard_D16ps_v5", 0.616),
+    // DigitalOcean.
+    ("s-2vcpu-4gb", 0.0357),
+    ("c-16", 0.5),
+    ("c-32", 1.0),
+    // Vultr.
+    ("vc2-4c-8gb", 0.06),
+    ("vhp-8c-16gb-amd", 0.143),
+    ("voc-c-16c-32gb-300s-amd", 0.571),
+    // Linode.
+    ("g6-dedicated-8", 0.216),
+    ("g6-dedicated-16", 0.432),
+    ("g6-dedicated-32", 0.864),
+];
+
+/// The estimated cost of the instances of a testbed.
+pub struct CostEstimate {
+    /// The number, specs, and hourly price of the instances of each region.
+    regions: Vec<(String, Vec<(String, usize, f64)>)>,
+}
+
+impl CostEstimate {
+    /// Estimate the cost of a testbed with the specified number of instances per region.
+    pub fn new(settings: &Settings, quantity: usize) -> SettingsResult<Self> {
+        let regions = settings
+            .regions
+            .iter()
+            .map(|region| {
+                let instances = settings
+                    .instances_by_specs(region, quantity)
+                    .into_iter()
+                    .map(|(specs, n)| {
+                        let price = Self::hourly_price(settings, &specs)?;
+                        Ok((specs, n, price))
+                    })
+                    .collect::<SettingsResult<_>>()?;
+                Ok((region.clone(), instances))
+            })
+            .collect::<SettingsResult<_>>()?;
+        Ok(Self { regions })
+    }
+
+    /// The hourly price of an instance with the specified specs. Local instances are free.
+    fn hourly_price(settings: &Settings, specs: &str) -> SettingsResult<f64> {
+        if settings.is_local() {
+            return Ok(0.0);
+        }
+        settings
+            .instance_prices
+            .iter()
+            .map(|(specs, price)| (specs.as_str(), *price))
+            .chain(PRICES.iter().copied())
+            .find(|(x, _)| Settings::same_specs(x, specs))
+            .map(|(_, price)| price)
+            .ok_or_else(|| SettingsError::UnknownInstancePrice(specs.into()))
+    }
+
+    /// The hourly cost of the instances of a region.
+    fn region_hourly_cost(instances: &[(String, usize, f64)]) -> f64 {
+        instances
+            .iter()
+            .map(|(_, n, price)| *n as f64 * price)
+            .sum()
+    }
+
+    /// The hourly cost of the whole testbed.
+    pub fn hourly_cost(&self) -> f64 {
+        self.regions
+            .iter()
+            .map(|(_, instances)| Self::region_hourly_cost(instances))
+            .sum()
+    }
+
+    /// The cost of running the whole testbed for the specified duration.
+    pub fn cost_for(&self, duration: Duration) -> f64 {
+        self.hourly_cost() * duration.as_secs_f64() / 3600.0
+    }
+
+    /// Print the hourly and daily cost per region and of the whole testbed, as well as the
+    /// projected cost of a benchmark of the specified duration (if any).
+    pub fn display(&self, benchmark_duration: Option<Duration>) {
+        let mut table = Table::new();
+        table.set_format(display::default_table_format());
+
+        table.set_titles(row![bH4->"Estimated cost (USD)"]);
+        table.add_row(row![b->"Region", b->"Instances", b->"Hourly", b->"Daily"]);
+        for (region, instances) in &self.regions {
+            let hourly = Self::region_hourly_cost(instances);
+            let specs = instances
+                .iter()
+                .map(|(specs, n, _)| format!("{n} x {specs}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            table.add_row(row![
+                region,
+                specs,
+                format!("{hourly:.2}"),
+                format!("{:.2}", hourly * 24.0)
+            ]);
+        }
+        table.add_row(row![]);
+        let hourly = self.hourly_cost();
+        table.add_row(row![
+            b->"Total",
+            "",
+            b->format!("{hourly:.2}"),
+            b->format!("{:.2}", hourly * 24.0)
+        ]);
+        if let Some(duration) = benchmark_duration {
+            table.add_row(row![
+                b->format!("Benchmark ({} s)", duration.as_secs()),
+                "",
+                b->format!("{:.2}", self.cost_for(duration)),
+                ""
+            ]);
+        }
+
+        display::newline();
+        table.printstd();
+        display::newline();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::CostEstimate;
+    use crate::settings::{HardwareProfile, Settings};
+
+    #[test]
+    fn estimate() {
+        let mut settings = Settings::new_for_test();
+        settings.regions = vec!["us-east-1".into(), "eu-west-1".into()];
+        settings.specs = "m5d.8xlarge".into();
+        settings.instance_prices = [("m5d.2xlarge".into(), 0.5)].into();
+        settings.hardware_profiles = vec![HardwareProfile {
+            specs: "m5d.2xlarge".into(),
+            share: 0.5,
+        }];
+
+        let estimate = CostEstimate::new(&settings, 2).unwrap();
+        let hourly = 2.0 * (1.808 + 0.5);
+        assert!((estimate.hourly_cost() - hourly).abs() < 1e-9);
+        let cost = estimate.cost_for(Duration::from_secs(1800));
+        assert!((cost - hourly / 2.0).abs() < 1e-9);
+
+        settings.specs = "unknown".into();
+        assert!(CostEstimate::new(&settings, 2).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fmt::Display,
+    io::{self, stdout, Stdout},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{Hide, Show},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Row, Sparkline, Table},
+    Frame, Terminal,
+};
+
+use crate::{
+    benchmark::BenchmarkParameters,
+    measurements::{IntervalSummary, MeasurementsCollection},
+};
+
+/// The status of a node during a benchmark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeStatus {
+    /// The node is running.
+    Running,
+    /// The node was killed by the faults schedule.
+    Killed,
+    /// The node was partitioned from the other nodes by the faults schedule.
+    Partitioned,
+}
+
+impl Display for NodeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::Killed => write!(f, "killed"),
+            Self::Partitioned => write!(f, "partitioned"),
+        }
+    }
+}
+
+/// The state displayed by the dashboard.
+struct DashboardState {
+    /// The description of the benchmark.
+    title: String,
+    /// The (minimum) duration of the benchmark.
+    duration: Duration,
+    /// The time elapsed since the beginning of the benchmark.
+    elapsed: Duration,
+    /// The metrics of each scrape interval so far.
+    intervals: Vec<IntervalSummary>,
+    /// The status of each node.
+    nodes: Vec<NodeStatus>,
+    /// The events of the benchmark so far (e.g., faults), with the time they occurred.
+    events: Vec<(Duration, String)>,
+}
+
+impl DashboardState {
+    /// Render the state on the specified frame.
+    fn render(&self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(8),
+                Constraint::Min(8),
+            ])
+            .split(frame.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(rows[2]);
+        let sidebar = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(columns[1]);
+
+        let block = |title: &'static str| Block::default().title(title).borders(Borders::ALL);
+        let millis =
+            |x: Option<Duration>| x.map_or("-".into(), |x| format!("{} ms", x.as_millis()));
+
+        // The progress of the benchmark.
+        let progress = format!(
+            "{} - {}s / {}s",
+            self.title,
+            self.elapsed.as_secs(),
+            self.duration.as_secs()
+        );
+        frame.render_widget(Paragraph::new(progress).block(block("Benchmark")), rows[0]);
+
+        // The throughput of each interval.
+        let tps: Vec<_> = self.intervals.iter().map(|x| x.tps).collect();
+        let sparkline = Sparkline::default()
+            .block(block("Throughput (tx/s)"))
+            .data(&tps)
+            .style(Style::default().fg(Color::Green));
+        frame.render_widget(sparkline, rows[1]);
+
+        // The metrics of the latest intervals.
+        let visible = columns[0].height.saturating_sub(3) as usize;
+        let intervals = self.intervals.iter().rev().take(visible).map(|x| {
+            Row::new(vec![
+                format!("{}s", x.end.as_secs()),
+                format!("{} tx/s", x.tps),
+                millis(x.p50_latency),
+                millis(x.p99_latency),
+            ])
+        });
+        let header = Row::new(vec!["Time", "TPS", "Latency (p50)", "Latency (p99)"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        let widths = [Constraint::Ratio(1, 4); 4];
+        let table = Table::new(intervals, widths)
+            .header(header)
+            .block(block("Intervals"));
+        frame.render_widget(table, columns[0]);
+
+        // The status of each node.
+        let nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, status)| {
+                let color = match status {
+                    NodeStatus::Running => Color::Green,
+                    NodeStatus::Killed => Color::Red,
+                    NodeStatus::Partitioned => Color::Yellow,
+                };
+                ListItem::new(format!("node {i}: {status}")).style(Style::default().fg(color))
+            })
+            .collect();
+        frame.render_widget(List::new(nodes).block(block("Nodes")), sidebar[0]);
+
+        // The latest events.
+        let visible = sidebar[1].height.saturating_sub(2) as usize;
+        let start = self.events.len().saturating_sub(visible);
+        let events: Vec<_> = self.events[start..]
+            .iter()
+            .map(|(time, event)| ListItem::new(format!("[{}s] {event}", time.as_secs())))
+            .collect();
+        frame.render_widget(List::new(events).block(block("Events")), sidebar[1]);
+    }
+}
+
+/// A live view of a running benchmark in the terminal: the throughput and latency of every
+/// scrape interval, the status of the nodes, and the fault events as they happen. The dashboard
+/// takes over the terminal (in its alternate screen) until it is dropped.
+pub struct Dashboard {
+    /// The terminal displaying the dashboard.
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    /// The state displayed by the dashboard.
+    state: DashboardState,
+}
+
+impl Dashboard {
+    /// Take over the terminal to display the specified benchmark.
+    pub fn new(parameters: &BenchmarkParameters) -> io::Result<Self> {
+        crossterm::execute!(stdout(), EnterAlternateScreen, Hide)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+        terminal.clear()?;
+
+        Ok(Self {
+            terminal,
+            state: DashboardState {
+                title: parameters.to_string(),
+                duration: parameters.settings.benchmark_duration,
+                elapsed: Duration::ZERO,
+                intervals: Vec::new(),
+                nodes: vec![NodeStatus::Running; parameters.nodes],
+                events: Vec::new(),
+            },
+        })
+    }
+
+    /// Update the metrics displayed by the dashboard with the measurements collected so far.
+    pub fn update(&mut self, elapsed: Duration, measurements: &MeasurementsCollection) {
+        self.state.elapsed = elapsed;
+        self.state.intervals = measurements.interval_summaries();
+    }
+
+    /// Set the status of the specified node (identified by its index in the committee).
+    pub fn set_node_status(&mut self, index: usize, status: NodeStatus) {
+        if let Some(node) = self.state.nodes.get_mut(index) {
+            *node = status;
+        }
+    }
+
+    /// Record an event of the benchmark.
+    pub fn event<S: Display>(&mut self, event: S) {
+        self.state
+            .events
+            .push((self.state.elapsed, event.to_string()));
+    }
+
+    /// Redraw the dashboard. A failure to draw it does not interrupt the benchmark; the next
+    /// draw simply tries again.
+    pub fn draw(&mut self) {
+        // Other output (e.g., of the faults schedule) may have overwritten the screen.
+        let _ = self.terminal.clear();
+        let state = &self.state;
+        let _ = self.terminal.draw(|frame| state.render(frame));
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(stdout(), Show, LeaveAlternateScreen);
+    }
+}
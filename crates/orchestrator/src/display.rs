@@ -82,6 +82,21 @@ pub fn done() {
     }
 }
 
+/// Print a line of the output of a remote command, prefixed by the name of its instance. Call
+/// `newline` after starting the action whose output is streamed, so that it is reported as done
+/// below the output.
+pub fn remote_output<N: Display, S: Display>(name: N, line: S) {
+    if cfg!(not(test)) {
+        crossterm::execute!(
+            stdout(),
+            PrintStyledContent(format!("[{name}] ").dim()),
+            Print(format!("{line}\n")),
+            SavePosition
+        )
+        .unwrap();
+    }
+}
+
 pub fn newline() {
     if cfg!(not(test)) {
         crossterm::execute!(stdout(), Print("\n")).unwrap();
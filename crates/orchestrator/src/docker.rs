@@ -0,0 +1,107 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use tokio::process::Command;
+
+use crate::{
+    error::{TestbedError, TestbedResult},
+    protocol::BINARY_PATH,
+    settings::Repository,
+};
+
+/// Deploys the codebase as a container image rather than compiling it on every instance. The
+/// image is built once (locally, from the repository and commit specified in the settings) and
+/// pushed to a registry, from which the instances pull it.
+pub struct DockerDeployment {
+    /// The tag of the image (including its registry), e.g., 'ghcr.io/org/mysticeti:bench'.
+    image: String,
+}
+
+impl DockerDeployment {
+    /// The label of the containers started by the orchestrator.
+    const LABEL: &'static str = "orchestrator";
+
+    /// Create a new docker deployment of the specified image.
+    pub fn new(image: String) -> Self {
+        Self { image }
+    }
+
+    /// Build the image from the repository (at the specified commit) and push it to its
+    /// registry. This requires docker on the local machine and to be logged in the registry.
+    pub async fn build_and_push(&self, repository: &Repository) -> TestbedResult<()> {
+        let context = format!("{}#{}", repository.url, repository.commit);
+        Self::docker(&["build", "--tag", &self.image, &context]).await?;
+        Self::docker(&["push", &self.image]).await
+    }
+
+    /// Run a docker command on the local machine.
+    async fn docker(args: &[&str]) -> TestbedResult<()> {
+        let output = Command::new("docker")
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| TestbedError::DockerError(format!("Failed to run docker: {e}")))?;
+        if !output.status.success() {
+            return Err(TestbedError::DockerError(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The command to run on the instances (from the repository directory) to install docker,
+    /// pull the image, and replace each of the protocol binaries (e.g., the node and the client)
+    /// by a script running it in a container. The protocol commands thus remain unchanged. The
+    /// container shares the network and the home directory of the instance (where all
+    /// configuration files, storage, and logs are).
+    pub fn update_command(&self, binaries: &[&str]) -> String {
+        let image = &self.image;
+        let label = Self::LABEL;
+        let mut command = vec![
+            "(docker --version || (sudo apt-get update && sudo apt-get -y install docker.io))"
+                .to_string(),
+            format!("sudo docker pull {image}"),
+            format!("mkdir -p {BINARY_PATH}"),
+        ];
+        // Kill the container when the script is killed (e.g., when its tmux session is).
+        command.extend(binaries.iter().map(|binary| {
+            let path = format!("{BINARY_PATH}/{binary}");
+            format!(
+                "cat > {path} <<'EOF' && chmod +x {path}
+#!/bin/bash
+name={binary}-$$
+trap 'sudo docker rm -f $name > /dev/null; exit' EXIT HUP INT TERM
+sudo docker run --rm --init --sig-proxy=false --name $name --label {label} \\
+    --network host --user $(id -u):$(id -g) -v $HOME:$HOME -w $PWD \\
+    --entrypoint {binary} {image} \"$@\" &
+wait $!
+EOF
+"
+            )
+        }));
+        command.join(" && ")
+    }
+
+    /// The command to kill all containers started by the orchestrator on an instance.
+    pub fn cleanup_command() -> String {
+        let label = Self::LABEL;
+        format!("(sudo docker rm -f $(sudo docker ps -q --filter label={label}) || true)")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DockerDeployment;
+
+    #[test]
+    fn update_command() {
+        let deployment = DockerDeployment::new("registry.example.com/mysticeti:test".into());
+        let command = deployment.update_command(&["mysticeti", "mysticeti-client"]);
+        assert!(command.contains("sudo docker pull registry.example.com/mysticeti:test"));
+        assert!(command.contains("cat > target/release/mysticeti <<'EOF'"));
+        assert!(command.contains("--entrypoint mysticeti registry.example.com"));
+        assert!(command.contains("cat > target/release/mysticeti-client <<'EOF'"));
+        assert!(command.contains("--entrypoint mysticeti-client registry.example.com"));
+        assert!(command.ends_with("\nEOF\n"));
+    }
+}
@@ -24,6 +24,18 @@ pub enum SettingsError {
 
     #[error("Failed to read ssh public key file '{file:?}': {message}")]
     SshPublicKeyFileError { file: String, message: String },
+
+    #[error("Invalid testbed name '{0}' (use lowercase letters, digits, and hyphens)")]
+    InvalidTestbedName(String),
+
+    #[error("Unknown price for instances '{0}' (specify it in 'instance_prices')")]
+    UnknownInstancePrice(String),
+
+    #[error("Invalid setting '{field}': {message}")]
+    InvalidField {
+        field: &'static str,
+        message: String,
+    },
 }
 
 pub type CloudProviderResult<T> = Result<T, CloudProviderError>;
@@ -43,6 +55,13 @@ pub enum CloudProviderError {
     SshKeyNotFound(String),
 }
 
+/// Make an error signaling a failure to manage the files of the local testbed.
+impl From<std::io::Error> for CloudProviderError {
+    fn from(e: std::io::Error) -> Self {
+        Self::RequestError(e.to_string())
+    }
+}
+
 pub type SshResult<T> = Result<T, SshError>;
 
 #[derive(thiserror::Error, Debug)]
@@ -65,6 +84,9 @@ pub enum SshError {
         code: i32,
         message: String,
     },
+
+    #[error("Checksum mismatch of file {path} transferred with {address}")]
+    ChecksumMismatch { address: SocketAddr, path: String },
 }
 
 pub type MonitorResult<T> = Result<T, MonitorError>;
@@ -96,4 +118,37 @@ pub enum TestbedError {
 
     #[error(transparent)]
     MonitorError(#[from] MonitorError),
+
+    #[error("Clocks are not synchronized: {0}")]
+    ClockSkew(String),
+
+    #[error("Safety audit failed: {0}")]
+    SafetyViolation(String),
+
+    #[error("Failed to check the consistency of the nodes: {0}")]
+    ConsistencyCheckError(String),
+
+    #[error("Failed to build or push the container image: {0}")]
+    DockerError(String),
+
+    #[error("Failed to compile the codebase: {0}")]
+    CompilationError(String),
+
+    #[error("Failed to synchronize the local working tree: {0}")]
+    WorkingTreeError(String),
+
+    #[error("Failed to download the release binary {0}")]
+    ReleaseDownloadError(String),
+
+    #[error("Health check failed on {0} instances")]
+    UnhealthyInstances(usize),
+
+    #[error("Failed to access the ttl record of the testbed: {0}")]
+    TtlRecordError(String),
+
+    #[error("Failed to access the progress of the benchmark sweep: {0}")]
+    SweepError(String),
+
+    #[error("Invalid faults: {0}")]
+    InvalidFaults(String),
 }
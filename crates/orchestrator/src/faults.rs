@@ -2,15 +2,491 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-    collections::{BTreeMap, HashMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     fmt::{Debug, Display},
+    path::Path,
+    sync::Arc,
     time::Duration,
 };
 
 use plotters::coord::combinators::ToGroupByRange;
 use serde::{Deserialize, Serialize};
 
-use crate::client::Instance;
+use mysticeti_core::{committee::Committee, config::ByzantineStrategy, types::RoundNumber};
+
+use crate::{client::Instance, NodeParameters};
+
+/// A network impairment emulated with `tc netem` on the egress traffic of some nodes (e.g., to
+/// emulate a WAN on a single-region testbed, or to degrade the links of specific nodes).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NetworkImpairment {
+    /// The nodes (identified by their index in the committee) whose egress traffic is impaired.
+    pub nodes: Vec<usize>,
+    /// Only impair the traffic to these nodes (identified by their index in the committee). If
+    /// empty, all the egress consensus traffic of the nodes is impaired.
+    #[serde(default)]
+    pub peers: Vec<usize>,
+    /// The added latency (in milliseconds).
+    #[serde(default)]
+    pub delay: u64,
+    /// The jitter (in milliseconds) of the added latency.
+    #[serde(default)]
+    pub jitter: u64,
+    /// The packet loss (in percent).
+    #[serde(default)]
+    pub loss: f64,
+}
+
+impl Display for NetworkImpairment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}ms delay", self.delay)?;
+        if self.jitter != 0 {
+            write!(f, " (±{}ms)", self.jitter)?;
+        }
+        if self.loss != 0.0 {
+            write!(f, ", {}% loss", self.loss)?;
+        }
+        Ok(())
+    }
+}
+
+impl NetworkImpairment {
+    /// The command finding the network interface of the default route.
+    const INTERFACE: &'static str =
+        "IFACE=$(ip -o route show default | awk '{print $5}' | head -n 1)";
+    /// The rate of the traffic classes, high enough to never throttle the traffic.
+    const RATE: &'static str = "100gbit";
+
+    /// The command removing any impairment from an instance.
+    pub fn cleanup_command() -> String {
+        format!(
+            "{} && (sudo tc qdisc del dev $IFACE root 2> /dev/null || true)",
+            Self::INTERFACE
+        )
+    }
+
+    /// The netem discipline emulating the impairment.
+    fn netem(&self) -> String {
+        let mut netem = format!("netem delay {}ms", self.delay);
+        if self.jitter != 0 {
+            netem.push_str(&format!(" {}ms", self.jitter));
+        }
+        if self.loss != 0.0 {
+            netem.push_str(&format!(" loss {}%", self.loss));
+        }
+        netem
+    }
+
+    /// The commands applying the specified impairments to the instances of the nodes (in
+    /// committee order), given the consensus ports of the nodes (in committee order). Only the
+    /// consensus traffic is impaired: the ssh and metrics traffic of the orchestrator and the
+    /// traffic of the clients go through the default class of an htb discipline, unimpaired.
+    /// Every node impaired by at least one impairment gets one class for its impairment of all
+    /// the consensus traffic (if any), and one class per impairment of the consensus traffic to
+    /// specific peers (selected by their ip addresses). Later impairments take precedence over
+    /// earlier ones.
+    pub fn commands(
+        impairments: &[Self],
+        nodes: &[Instance],
+        ports: &[u16],
+    ) -> Vec<(Instance, String)> {
+        let mut commands = Vec::new();
+        for (i, instance) in nodes.iter().enumerate() {
+            let impairments: Vec<_> = impairments
+                .iter()
+                .filter(|x| x.nodes.contains(&i))
+                .collect();
+            if impairments.is_empty() {
+                continue;
+            }
+
+            let dev = "dev $IFACE";
+            let mut command = vec![
+                Self::cleanup_command(),
+                format!("sudo tc qdisc add {dev} root handle 1: htb default 1"),
+                format!(
+                    "sudo tc class add {dev} parent 1: classid 1:1 htb rate {}",
+                    Self::RATE
+                ),
+            ];
+            // The consensus traffic of the node to a peer goes to the port of the peer (on the
+            // connections the node opened) or comes from the port of the node (on the
+            // connections the peer opened).
+            let own_port = ports[i];
+            let consensus = |port: u16| {
+                [
+                    format!("match ip dport {port} 0xffff"),
+                    format!("match ip sport {own_port} 0xffff"),
+                ]
+            };
+            let filter = |prio: u8, matches: &str, class: &str| {
+                format!(
+                    "sudo tc filter add {dev} parent 1: protocol ip prio {prio} u32 {matches} \
+                    flowid {class}"
+                )
+            };
+
+            if let Some(impairment) = impairments.iter().rev().find(|x| x.peers.is_empty()) {
+                let netem = impairment.netem();
+                command.push(format!(
+                    "sudo tc class add {dev} parent 1: classid 1:2 htb rate {}",
+                    Self::RATE
+                ));
+                command.push(format!(
+                    "sudo tc qdisc add {dev} parent 1:2 handle 10: {netem}"
+                ));
+                // Peers may share a port (e.g., on kubernetes), so deduplicate the filters.
+                let matches: BTreeSet<_> = ports
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .flat_map(|(_, port)| consensus(*port))
+                    .collect();
+                for matches in matches {
+                    command.push(filter(2, &matches, "1:2"));
+                }
+            }
+
+            // The filters of the impairments of specific peers take precedence (lower prio), and
+            // filters of the same prio are matched in the order they are added, so add the later
+            // impairments first.
+            let targeted = impairments.iter().rev().filter(|x| !x.peers.is_empty());
+            for (j, impairment) in (3..).zip(targeted) {
+                let (class, handle, netem) = (format!("1:{j:x}"), 0x100 + j, impairment.netem());
+                command.push(format!(
+                    "sudo tc class add {dev} parent 1: classid {class} htb rate {}",
+                    Self::RATE
+                ));
+                command.push(format!(
+                    "sudo tc qdisc add {dev} parent {class} handle {handle:x}: {netem}"
+                ));
+                for &peer in &impairment.peers {
+                    let Some(instance) = nodes.get(peer) else {
+                        continue;
+                    };
+                    let addresses = [Some(instance.main_ip), instance.private_ip];
+                    for address in addresses.into_iter().flatten() {
+                        for traffic in consensus(ports[peer]) {
+                            let matches = format!("match ip dst {address}/32 {traffic}");
+                            command.push(filter(1, &matches, &class));
+                        }
+                    }
+                }
+            }
+            commands.push((instance.clone(), command.join(" && ")));
+        }
+        commands
+    }
+}
+
+/// The byzantine nodes of the benchmarks: a fraction of the committee deviating from the protocol
+/// (with the strategy of the nodes) for the whole benchmark.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ByzantineNodes {
+    /// The fraction of the committee that is byzantine (rounded down).
+    pub fraction: f64,
+    /// The deviation from the protocol of the byzantine nodes.
+    pub strategy: ByzantineStrategy,
+}
+
+impl Display for ByzantineNodes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}% {}", self.fraction * 100.0, self.strategy)
+    }
+}
+
+impl ByzantineNodes {
+    /// The byzantine nodes of a committee (identified by their index): the last nodes of the
+    /// committee, so that they do not overlap with the first nodes targeted by other faults.
+    pub fn nodes(&self, committee_size: usize) -> Vec<usize> {
+        let count = (self.fraction * committee_size as f64).floor() as usize;
+        (committee_size.saturating_sub(count)..committee_size).collect()
+    }
+}
+
+/// The resource that a stress fault exhausts on the instances of the nodes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum StressType {
+    /// Burn the cpu with the specified number of workers (0 for one per cpu), each loading its
+    /// cpu at the specified percentage.
+    Cpu { workers: usize, load: u8 },
+    /// Allocate and continuously touch the specified amount of memory (in MB).
+    Memory { size: u64 },
+    /// Saturate the disk with the specified number of workers writing and reading files.
+    Disk { workers: usize },
+    /// Issue small random writes with the specified number of jobs, each followed by an fsync.
+    Fsync { jobs: usize },
+}
+
+impl Display for StressType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cpu { workers: 0, load } => write!(f, "cpu ({load}% on all cpus)"),
+            Self::Cpu { workers, load } => write!(f, "cpu ({load}% on {workers} cpus)"),
+            Self::Memory { size } => write!(f, "memory ({size} MB)"),
+            Self::Disk { workers } => write!(f, "disk ({workers} workers)"),
+            Self::Fsync { jobs } => write!(f, "fsync ({jobs} jobs)"),
+        }
+    }
+}
+
+/// A resource-stress fault: stress tools (`stress-ng` and `fio`) starving some nodes of a
+/// resource during a window of the benchmark, while the nodes keep running.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ResourceStress {
+    /// The nodes (identified by their index in the committee) to stress.
+    pub nodes: Vec<usize>,
+    /// The resource to stress.
+    pub stress: StressType,
+    /// The delay (in seconds) after the start of the nodes before stressing them.
+    #[serde(default)]
+    pub start: u64,
+    /// The duration (in seconds) of the stress. If not specified, the nodes are stressed until
+    /// the end of the benchmark.
+    #[serde(default)]
+    pub duration: Option<u64>,
+}
+
+impl Display for ResourceStress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} from {}s", self.stress, self.start)?;
+        match self.duration {
+            Some(duration) => write!(f, " for {duration}s"),
+            None => write!(f, " until the end"),
+        }
+    }
+}
+
+impl ResourceStress {
+    /// The directory (relative to the working directory) holding the files of the disk stress.
+    const DIRECTORY: &'static str = "stress";
+    /// The size of the file written by each fsync job.
+    const FSYNC_FILE_SIZE: &'static str = "256M";
+
+    /// The command installing the stress tools (if they are not installed yet).
+    pub fn install_command() -> String {
+        "(command -v stress-ng > /dev/null && command -v fio > /dev/null) || \
+        (sudo apt-get update && sudo apt-get -y install stress-ng fio)"
+            .into()
+    }
+
+    /// The command stopping the stress tools and deleting their files.
+    pub fn cleanup_command(working_dir: &Path) -> String {
+        let directory = working_dir.join(Self::DIRECTORY);
+        format!(
+            "(pkill -x stress-ng || true) ; (pkill -x fio || true) ; (rm -rf {} || true)",
+            directory.display()
+        )
+    }
+
+    /// The id of the background command of the i-th stress fault.
+    pub fn command_id(i: usize) -> String {
+        format!("stress-{i}")
+    }
+
+    /// The command stressing a node once the window of the fault starts. A duration of zero
+    /// (i.e., a benchmark running indefinitely) stresses the node until the testbed is cleaned up.
+    pub fn command(&self, working_dir: &Path, benchmark_duration: Duration) -> String {
+        let duration = self
+            .duration
+            .unwrap_or(benchmark_duration.as_secs().saturating_sub(self.start));
+        let directory = working_dir.join(Self::DIRECTORY);
+        let directory = directory.display();
+        let stress = match &self.stress {
+            StressType::Cpu { workers, load } => {
+                format!("stress-ng --cpu {workers} --cpu-load {load} --timeout {duration}s")
+            }
+            StressType::Memory { size } => {
+                format!("stress-ng --vm 1 --vm-bytes {size}M --vm-keep --timeout {duration}s")
+            }
+            StressType::Disk { workers } => {
+                format!("stress-ng --hdd {workers} --temp-path {directory} --timeout {duration}s")
+            }
+            StressType::Fsync { jobs } => {
+                let runtime = if duration == 0 {
+                    "365d".to_string()
+                } else {
+                    format!("{duration}s")
+                };
+                format!(
+                    "fio --name=fsync --directory={directory} --rw=randwrite --bs=4k \
+                    --size={} --fsync=1 --numjobs={jobs} --time_based --runtime={runtime}",
+                    Self::FSYNC_FILE_SIZE
+                )
+            }
+        };
+        [
+            format!("mkdir -p {directory}"),
+            format!("sleep {}", self.start),
+            stress,
+        ]
+        .join(" && ")
+    }
+}
+
+/// The way a gray failure degrades the nodes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum GrayFailureType {
+    /// Throttle the write bandwidth (in MB/s) of the disk holding the storage (and the WAL) of
+    /// the nodes.
+    SlowDisk { bandwidth: u64 },
+    /// Drop the specified percentage of the packets sent and received by the nodes.
+    LossyNic { loss: f64 },
+    /// Repeatedly pause the process of the nodes (with SIGSTOP) for `pause` milliseconds, every
+    /// `interval` milliseconds.
+    Pause { pause: u64, interval: u64 },
+}
+
+impl Display for GrayFailureType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SlowDisk { bandwidth } => write!(f, "slow disk ({bandwidth} MB/s)"),
+            Self::LossyNic { loss } => write!(f, "lossy nic ({loss}% loss)"),
+            Self::Pause { pause, interval } => write!(f, "pauses ({pause}ms every {interval}ms)"),
+        }
+    }
+}
+
+/// A gray failure: a fault degrading some nodes (without crashing them) during a window of the
+/// benchmark, to reproduce the partial failures that are hard to detect by the other nodes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GrayFailure {
+    /// The nodes (identified by their index in the committee) to degrade.
+    pub nodes: Vec<usize>,
+    /// The way the nodes are degraded.
+    pub failure: GrayFailureType,
+    /// The delay (in seconds) after the start of the nodes before degrading them.
+    #[serde(default)]
+    pub start: u64,
+    /// The duration (in seconds) of the failure. If not specified, the nodes are degraded until
+    /// the end of the benchmark.
+    #[serde(default)]
+    pub duration: Option<u64>,
+}
+
+impl Display for GrayFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} from {}s", self.failure, self.start)?;
+        match self.duration {
+            Some(duration) => write!(f, " for {duration}s"),
+            None => write!(f, " until the end"),
+        }
+    }
+}
+
+impl GrayFailure {
+    /// The name of the process of the nodes.
+    const PROCESS: &'static str = "mysticeti";
+    /// The cgroup throttling the disk of the nodes.
+    const CGROUP: &'static str = "/sys/fs/cgroup/gray";
+    /// The iptables chain dropping the packets of the nodes.
+    const CHAIN: &'static str = "gray";
+
+    /// The command removing the packet drops of the lossy nics (if any).
+    fn heal_command() -> String {
+        let chain = Self::CHAIN;
+        format!(
+            "(sudo iptables -D INPUT -j {chain} ; sudo iptables -D OUTPUT -j {chain} ; \
+            sudo iptables -F {chain} ; sudo iptables -X {chain}) 2> /dev/null || true"
+        )
+    }
+
+    /// The command removing the gray failures from an instance (once the nodes are killed).
+    pub fn cleanup_command() -> String {
+        format!(
+            "{} ; (sudo rmdir {} 2> /dev/null || true)",
+            Self::heal_command(),
+            Self::CGROUP
+        )
+    }
+
+    /// The id of the background command of the i-th gray failure.
+    pub fn command_id(i: usize) -> String {
+        format!("gray-{i}")
+    }
+
+    /// The command degrading a node once the window of the failure starts, and restoring it once
+    /// the window ends. A duration of zero (i.e., a benchmark running indefinitely) degrades the
+    /// node until the testbed is cleaned up. The command runs in the background (within double
+    /// quotes), so its variables are escaped.
+    pub fn command(&self, working_dir: &Path, benchmark_duration: Duration) -> String {
+        let duration = self
+            .duration
+            .unwrap_or(benchmark_duration.as_secs().saturating_sub(self.start));
+        let pid = format!("\\$(pgrep -x {} | head -n 1)", Self::PROCESS);
+        let (inject, recover) = match &self.failure {
+            GrayFailureType::SlowDisk { bandwidth } => {
+                // The io controller only throttles whole disks, not their partitions.
+                let (cgroup, sysfs) = (Self::CGROUP, "/sys/dev/block/\\$PART");
+                let device = [
+                    format!(
+                        "PART=\\$(findmnt -no MAJ:MIN --target {} | tr -d ' ')",
+                        working_dir.display()
+                    ),
+                    format!(
+                        "DISK=\\$(if [ -e {sysfs}/partition ]; then cat {sysfs}/../dev; \
+                        else echo \\$PART; fi)"
+                    ),
+                ];
+                let bytes = bandwidth * 1024 * 1024;
+                let inject = [
+                    format!("sudo mkdir -p {cgroup}"),
+                    "echo +io | sudo tee /sys/fs/cgroup/cgroup.subtree_control > /dev/null".into(),
+                    format!("echo \\$DISK wbps={bytes} | sudo tee {cgroup}/io.max > /dev/null"),
+                    format!("echo {pid} | sudo tee {cgroup}/cgroup.procs > /dev/null"),
+                ];
+                let recover =
+                    format!("echo \\$DISK wbps=max | sudo tee {cgroup}/io.max > /dev/null");
+                (device.into_iter().chain(inject).collect(), Some(recover))
+            }
+            GrayFailureType::LossyNic { loss } => {
+                // Keep the ssh traffic of the orchestrator.
+                let chain = Self::CHAIN;
+                let inject = vec![
+                    Self::heal_command(),
+                    format!("sudo iptables -N {chain}"),
+                    format!("sudo iptables -I INPUT -j {chain}"),
+                    format!("sudo iptables -I OUTPUT -j {chain}"),
+                    format!("sudo iptables -A {chain} -p tcp --dport 22 -j RETURN"),
+                    format!("sudo iptables -A {chain} -p tcp --sport 22 -j RETURN"),
+                    format!(
+                        "sudo iptables -A {chain} -m statistic --mode random --probability {} \
+                        -j DROP",
+                        loss / 100.0
+                    ),
+                ];
+                (inject, Some(Self::heal_command()))
+            }
+            GrayFailureType::Pause { pause, interval } => {
+                let seconds = |ms: &u64| Duration::from_millis(*ms).as_secs_f64();
+                let mut pauses = format!(
+                    "bash -c 'while true; do sleep {}; kill -STOP \\$1; sleep {}; \
+                    kill -CONT \\$1; done' pause \\$PID",
+                    seconds(interval),
+                    seconds(pause)
+                );
+                if duration != 0 {
+                    pauses = format!("timeout {duration}s {pauses}");
+                }
+                // The pauses last for the whole window and end with the timeout (which fails).
+                let inject = vec![
+                    format!("PID={pid}"),
+                    format!("({pauses} || true)"),
+                    "kill -CONT \\$PID".into(),
+                ];
+                (inject, None)
+            }
+        };
+
+        let mut command = vec![format!("sleep {}", self.start)];
+        command.extend(inject);
+        if let Some(recover) = recover.filter(|_| duration != 0) {
+            command.push(format!("sleep {duration}"));
+            command.push(recover);
+        }
+        command.join(" && ")
+    }
+}
 
 #[derive(Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
 pub enum FaultsType {
@@ -21,6 +497,20 @@ pub enum FaultsType {
         max_faults: usize,
         interval: Duration,
     },
+    /// Periodically crash the leaders of the upcoming rounds (the worst case for latency), and
+    /// recover them at the next period.
+    Leader {
+        max_faults: usize,
+        interval: Duration,
+    },
+    /// Take down all the nodes of a region at once after an interval (modeling the outage of a
+    /// cloud region), and bring them back after another interval. The nodes are either crashed
+    /// or partitioned from the nodes of the other regions.
+    RegionOutage {
+        region: String,
+        interval: Duration,
+        partition: bool,
+    },
 }
 
 impl FaultsType {
@@ -28,6 +518,9 @@ impl FaultsType {
         match self {
             Self::Permanent { faults } => *faults,
             Self::CrashRecovery { max_faults, .. } => *max_faults,
+            Self::Leader { max_faults, .. } => *max_faults,
+            // The nodes of the region are only down temporarily.
+            Self::RegionOutage { .. } => 0,
         }
     }
 }
@@ -46,6 +539,18 @@ impl Debug for FaultsType {
                 max_faults,
                 interval,
             } => write!(f, "{max_faults}-{}cr", interval.as_secs()),
+            Self::Leader {
+                max_faults,
+                interval,
+            } => write!(f, "{max_faults}-{}ld", interval.as_secs()),
+            Self::RegionOutage {
+                region,
+                interval,
+                partition,
+            } => {
+                let kind = if *partition { "rp" } else { "ro" };
+                write!(f, "{region}-{}{kind}", interval.as_secs())
+            }
         }
     }
 }
@@ -64,6 +569,18 @@ impl Display for FaultsType {
                 max_faults,
                 interval,
             } => write!(f, "{max_faults} crash-recovery, {}s", interval.as_secs()),
+            Self::Leader {
+                max_faults,
+                interval,
+            } => write!(f, "{max_faults} leaders crashed, {}s", interval.as_secs()),
+            Self::RegionOutage {
+                region,
+                interval,
+                partition,
+            } => {
+                let kind = if *partition { "partition" } else { "outage" };
+                write!(f, "{region} {kind}, {}s", interval.as_secs())
+            }
         }
     }
 }
@@ -75,30 +592,55 @@ impl FaultsType {
         match self {
             Self::Permanent { .. } => Duration::from_secs(1),
             Self::CrashRecovery { interval, .. } => *interval,
+            Self::Leader { interval, .. } => *interval,
+            Self::RegionOutage { interval, .. } => *interval,
         }
     }
+
+    /// Whether the faults partition nodes (with `iptables`) rather than only crashing them.
+    pub fn partitions_network(&self) -> bool {
+        matches!(
+            self,
+            Self::RegionOutage {
+                partition: true,
+                ..
+            }
+        )
+    }
 }
 
-/// The actions to apply to the testbed, i.e., which instances to crash and recover.
+/// The actions to apply to the testbed, i.e., which instances to crash and recover (or to
+/// partition and reconnect).
 #[derive(Default)]
 pub struct CrashRecoveryAction {
     /// The instances to boot.
     pub boot: Vec<Instance>,
     /// The instances to kill.
     pub kill: Vec<Instance>,
+    /// The instances to partition from the other nodes.
+    pub partition: Vec<Instance>,
+    /// The instances to reconnect to the other nodes.
+    pub heal: Vec<Instance>,
 }
 
 impl Display for CrashRecoveryAction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let booted = self.boot.len();
-        let killed = self.kill.len();
+        let changes = [
+            (self.kill.len(), "killed"),
+            (self.boot.len(), "recovered"),
+            (self.partition.len(), "partitioned"),
+            (self.heal.len(), "reconnected"),
+        ];
+        let changes: Vec<_> = changes
+            .iter()
+            .filter(|(count, _)| *count != 0)
+            .map(|(count, change)| format!("{count} node(s) {change}"))
+            .collect();
 
-        if self.boot.is_empty() {
-            write!(f, "{killed} node(s) killed")
-        } else if self.kill.is_empty() {
-            write!(f, "{booted} node(s) recovered")
+        if changes.is_empty() {
+            write!(f, "0 node(s) killed")
         } else {
-            write!(f, "{killed} node(s) killed and {booted} node(s) recovered")
+            write!(f, "{}", changes.join(" and "))
         }
     }
 }
@@ -107,20 +649,157 @@ impl CrashRecoveryAction {
     pub fn boot(instances: impl Iterator<Item = Instance>) -> Self {
         Self {
             boot: instances.collect(),
-            kill: Vec::new(),
+            ..Self::default()
         }
     }
 
     pub fn kill(instances: impl Iterator<Item = Instance>) -> Self {
         Self {
-            boot: Vec::new(),
             kill: instances.collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn partition(instances: impl Iterator<Item = Instance>) -> Self {
+        Self {
+            partition: instances.collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn heal(instances: impl Iterator<Item = Instance>) -> Self {
+        Self {
+            heal: instances.collect(),
+            ..Self::default()
         }
     }
 
     pub fn no_op() -> Self {
         Self::default()
     }
+
+    /// Whether the action leaves the testbed unchanged.
+    pub fn is_no_op(&self) -> bool {
+        self.boot.is_empty()
+            && self.kill.is_empty()
+            && self.partition.is_empty()
+            && self.heal.is_empty()
+    }
+}
+
+/// A change of the testbed applied by the faults schedule, recorded with the measurements to
+/// correlate it with the performance of the benchmark.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaultEvent {
+    /// The time (since the beginning of the benchmark) of the change.
+    pub time: Duration,
+    /// The description of the change (e.g., '3 node(s) killed').
+    pub description: String,
+    /// The nodes affected by the change (identified by their index in the committee).
+    pub nodes: Vec<usize>,
+}
+
+impl FaultEvent {
+    pub fn new(time: Duration, action: &CrashRecoveryAction, nodes: &[Instance]) -> Self {
+        let affected = [&action.kill, &action.boot, &action.partition, &action.heal];
+        let mut indices: Vec<_> = affected
+            .into_iter()
+            .flatten()
+            .filter_map(|instance| nodes.iter().position(|x| x == instance))
+            .collect();
+        indices.sort();
+        Self {
+            time,
+            description: action.to_string(),
+            nodes: indices,
+        }
+    }
+}
+
+/// Partitions some nodes from the other nodes with `iptables`, dropping all traffic between them
+/// (the traffic with the orchestrator and the monitoring instance is unaffected).
+pub struct NetworkPartition;
+
+impl NetworkPartition {
+    /// The iptables chain holding the rules of the partition.
+    const CHAIN: &'static str = "partition";
+
+    /// The command removing the partition from an instance (if any).
+    pub fn heal_command() -> String {
+        let chain = Self::CHAIN;
+        format!(
+            "(sudo iptables -D INPUT -j {chain} ; sudo iptables -D OUTPUT -j {chain} ; \
+            sudo iptables -F {chain} ; sudo iptables -X {chain}) 2> /dev/null || true"
+        )
+    }
+
+    /// The commands partitioning the specified instances from the other nodes.
+    pub fn commands(partitioned: &[Instance], nodes: &[Instance]) -> Vec<(Instance, String)> {
+        let chain = Self::CHAIN;
+        let mut command = vec![
+            Self::heal_command(),
+            format!("sudo iptables -N {chain}"),
+            format!("sudo iptables -I INPUT -j {chain}"),
+            format!("sudo iptables -I OUTPUT -j {chain}"),
+        ];
+        let others = nodes.iter().filter(|x| !partitioned.contains(x));
+        let addresses = others.flat_map(|x| [Some(x.main_ip), x.private_ip]);
+        for address in addresses.flatten() {
+            command.push(format!("sudo iptables -A {chain} -s {address} -j DROP"));
+            command.push(format!("sudo iptables -A {chain} -d {address} -j DROP"));
+        }
+        let command = command.join(" && ");
+        partitioned
+            .iter()
+            .map(|instance| (instance.clone(), command.clone()))
+            .collect()
+    }
+}
+
+/// The leaders elected by the nodes in each round (mirroring the committers of the nodes), to
+/// crash the leaders of the upcoming rounds.
+pub struct LeaderSchedule {
+    committee: Arc<Committee>,
+    wave_length: RoundNumber,
+    number_of_leaders: usize,
+    pipelining: bool,
+}
+
+impl LeaderSchedule {
+    pub fn new(committee_size: usize, parameters: &NodeParameters) -> Self {
+        Self {
+            committee: Committee::new_for_benchmarks(committee_size),
+            wave_length: parameters.wave_length,
+            number_of_leaders: parameters.number_of_leaders,
+            pipelining: parameters.enable_pipelining,
+        }
+    }
+
+    /// The leaders of the specified round (none if the round is not a leader round).
+    fn leaders(&self, round: RoundNumber) -> Vec<usize> {
+        if !self.pipelining && round % self.wave_length != 0 {
+            return Vec::new();
+        }
+        (0..self.number_of_leaders as RoundNumber)
+            .map(|offset| self.committee.elect_leader(round + offset) as usize)
+            .collect()
+    }
+
+    /// The (distinct) leaders of the rounds following the specified round, by order of
+    /// election and up to the specified number.
+    pub fn upcoming(&self, round: RoundNumber, count: usize) -> Vec<usize> {
+        let mut upcoming = Vec::new();
+        let horizon = self.committee.len() as RoundNumber * self.wave_length;
+        for leader in (round + 1..=round + horizon).flat_map(|r| self.leaders(r)) {
+            if upcoming.len() == count {
+                break;
+            }
+            if !upcoming.contains(&leader) {
+                upcoming.push(leader);
+            }
+        }
+        upcoming
+    }
 }
 
 pub struct CrashRecoverySchedule {
@@ -130,6 +809,10 @@ pub struct CrashRecoverySchedule {
     instances: Vec<Instance>,
     /// The current number of dead nodes.
     dead: usize,
+    /// The leaders crashed by the last update (only used by leader faults).
+    crashed_leaders: Vec<Instance>,
+    /// Whether the region is back after its outage (only used by region outages).
+    region_recovered: bool,
 }
 
 impl CrashRecoverySchedule {
@@ -138,8 +821,34 @@ impl CrashRecoverySchedule {
             faults_type,
             instances,
             dead: 0,
+            crashed_leaders: Vec::new(),
+            region_recovered: false,
         }
     }
+
+    /// Recover the leaders crashed by the previous update and crash the specified upcoming
+    /// leaders instead (identified by their index in the committee). Leaders that are still
+    /// upcoming stay crashed.
+    pub fn update_leaders(&mut self, leaders: &[usize]) -> CrashRecoveryAction {
+        let previous = std::mem::take(&mut self.crashed_leaders);
+        let targets: Vec<_> = leaders
+            .iter()
+            .filter_map(|&i| self.instances.get(i).cloned())
+            .take(self.faults_type.len())
+            .collect();
+
+        let boot = previous.iter().filter(|x| !targets.contains(x));
+        let kill = targets.iter().filter(|x| !previous.contains(x));
+        let action = CrashRecoveryAction {
+            boot: boot.cloned().collect(),
+            kill: kill.cloned().collect(),
+            ..CrashRecoveryAction::default()
+        };
+        self.dead = targets.len();
+        self.crashed_leaders = targets;
+        action
+    }
+
     pub fn update(&mut self) -> CrashRecoveryAction {
         let mut instances = self.instances.clone();
 
@@ -223,16 +932,52 @@ impl CrashRecoverySchedule {
                     CrashRecoveryAction::kill(to_kill)
                 }
             }
+
+            // Leaders can only be targeted knowing the current round (see `update_leaders`).
+            FaultsType::Leader { .. } => CrashRecoveryAction::no_op(),
+
+            // Take down all the nodes of the region at once, then bring them back.
+            FaultsType::RegionOutage {
+                region, partition, ..
+            } => {
+                let regional: Vec<_> = self
+                    .instances
+                    .iter()
+                    .filter(|x| &x.region == region)
+                    .cloned()
+                    .collect();
+                if self.region_recovered {
+                    CrashRecoveryAction::no_op()
+                } else if self.dead == 0 {
+                    self.dead = regional.len();
+                    match partition {
+                        true => CrashRecoveryAction::partition(regional.into_iter()),
+                        false => CrashRecoveryAction::kill(regional.into_iter()),
+                    }
+                } else {
+                    self.dead = 0;
+                    self.region_recovered = true;
+                    match partition {
+                        true => CrashRecoveryAction::heal(regional.into_iter()),
+                        false => CrashRecoveryAction::boot(regional.into_iter()),
+                    }
+                }
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod faults_tests {
-    use std::time::Duration;
+    use std::{net::Ipv4Addr, path::PathBuf, time::Duration};
 
-    use super::{CrashRecoverySchedule, FaultsType};
-    use crate::client::Instance;
+    use mysticeti_core::config::ByzantineStrategy;
+
+    use super::{
+        ByzantineNodes, CrashRecoverySchedule, FaultsType, GrayFailure, GrayFailureType,
+        LeaderSchedule, NetworkImpairment, NetworkPartition, ResourceStress, StressType,
+    };
+    use crate::{client::Instance, NodeParameters};
 
     #[test]
     fn crash_recovery_1_fault() {
@@ -337,4 +1082,181 @@ mod faults_tests {
             assert_eq!(action.kill.len(), min_faults);
         }
     }
+
+    #[test]
+    fn network_impairments() {
+        let nodes: Vec<_> = (0..3)
+            .map(|i| Instance {
+                main_ip: Ipv4Addr::new(10, 0, 0, i),
+                ..Instance::new_for_test(i.to_string())
+            })
+            .collect();
+        let impairments = [
+            NetworkImpairment {
+                nodes: vec![0, 1],
+                peers: vec![],
+                delay: 100,
+                jitter: 10,
+                loss: 0.0,
+            },
+            NetworkImpairment {
+                nodes: vec![1],
+                peers: vec![2],
+                delay: 200,
+                jitter: 0,
+                loss: 1.5,
+            },
+        ];
+
+        let ports = [1500, 1501, 1502];
+
+        let commands = NetworkImpairment::commands(&impairments, &nodes, &ports);
+        assert_eq!(commands.len(), 2);
+        let (instance, command) = &commands[1];
+        assert_eq!(instance.id, "1");
+        // The default class (e.g., ssh and metrics traffic) is not impaired.
+        assert!(command.contains("htb default 1"));
+        assert!(!command.contains("parent 1:1 handle"));
+        assert!(command.contains("parent 1:2 handle 10: netem delay 100ms 10ms"));
+        assert!(command.contains("match ip dport 1500 0xffff flowid 1:2"));
+        assert!(command.contains("match ip sport 1501 0xffff flowid 1:2"));
+        assert!(!command.contains("match ip dport 1501"));
+        assert!(command.contains("parent 1:3 handle 103: netem delay 200ms loss 1.5%"));
+        assert!(command.contains("match ip dst 10.0.0.2/32 match ip dport 1502 0xffff flowid 1:3"));
+        assert!(command.contains("match ip dst 10.0.0.2/32 match ip sport 1501 0xffff flowid 1:3"));
+        assert!(!command.contains("10.0.0.0/32"));
+    }
+
+    #[test]
+    fn resource_stress_commands() {
+        let working_dir = PathBuf::from("/home/ubuntu/working_dir");
+        let benchmark_duration = Duration::from_secs(180);
+
+        let stress = ResourceStress {
+            nodes: vec![0],
+            stress: StressType::Cpu {
+                workers: 0,
+                load: 80,
+            },
+            start: 60,
+            duration: None,
+        };
+        let command = stress.command(&working_dir, benchmark_duration);
+        assert!(command.contains("sleep 60 && stress-ng --cpu 0 --cpu-load 80 --timeout 120s"));
+
+        let stress = ResourceStress {
+            stress: StressType::Fsync { jobs: 2 },
+            duration: Some(30),
+            ..stress
+        };
+        let command = stress.command(&working_dir, benchmark_duration);
+        assert!(command.starts_with("mkdir -p /home/ubuntu/working_dir/stress"));
+        assert!(command.contains("--fsync=1 --numjobs=2 --time_based --runtime=30s"));
+    }
+
+    #[test]
+    fn gray_failure_commands() {
+        let working_dir = PathBuf::from("/home/ubuntu/working_dir");
+        let benchmark_duration = Duration::from_secs(180);
+
+        let failure = GrayFailure {
+            nodes: vec![0],
+            failure: GrayFailureType::Pause {
+                pause: 500,
+                interval: 2000,
+            },
+            start: 60,
+            duration: Some(30),
+        };
+        let command = failure.command(&working_dir, benchmark_duration);
+        assert!(command.starts_with("sleep 60 && PID=\\$(pgrep -x mysticeti | head -n 1)"));
+        assert!(command.contains("(timeout 30s bash -c 'while true; do sleep 2; kill -STOP"));
+        assert!(command.ends_with("|| true) && kill -CONT \\$PID"));
+
+        let failure = GrayFailure {
+            failure: GrayFailureType::LossyNic { loss: 5.0 },
+            duration: None,
+            ..failure
+        };
+        let command = failure.command(&working_dir, benchmark_duration);
+        assert!(command.contains("--mode random --probability 0.05 -j DROP && sleep 120"));
+
+        let failure = GrayFailure {
+            failure: GrayFailureType::SlowDisk { bandwidth: 10 },
+            ..failure
+        };
+        let command = failure.command(&working_dir, Duration::ZERO);
+        assert!(command.contains("--target /home/ubuntu/working_dir"));
+        assert!(command.contains("echo \\$DISK wbps=10485760 | sudo tee"));
+        assert!(command.ends_with("| sudo tee /sys/fs/cgroup/gray/cgroup.procs > /dev/null"));
+    }
+
+    #[test]
+    fn leader_schedule() {
+        let schedule = LeaderSchedule::new(4, &NodeParameters::default());
+        // Two leaders per round by default: the leaders of round r are r and r + 1 (mod 4).
+        assert_eq!(schedule.upcoming(10, 1), vec![3]);
+        assert_eq!(schedule.upcoming(10, 3), vec![3, 0, 1]);
+    }
+
+    #[test]
+    fn crash_leaders() {
+        let instances: Vec<_> = (0..4)
+            .map(|i| Instance::new_for_test(i.to_string()))
+            .collect();
+        let faults_type = FaultsType::Leader {
+            max_faults: 1,
+            interval: Duration::from_secs(10),
+        };
+        let mut schedule = CrashRecoverySchedule::new(faults_type, instances.clone());
+
+        let action = schedule.update_leaders(&[2, 3]);
+        assert_eq!(action.kill, vec![instances[2].clone()]);
+        assert!(action.boot.is_empty());
+
+        // A leader that is still upcoming stays crashed.
+        let action = schedule.update_leaders(&[2]);
+        assert!(action.kill.is_empty() && action.boot.is_empty());
+
+        let action = schedule.update_leaders(&[0]);
+        assert_eq!(action.kill, vec![instances[0].clone()]);
+        assert_eq!(action.boot, vec![instances[2].clone()]);
+    }
+
+    #[test]
+    fn region_outage() {
+        let instances: Vec<_> = (0..4)
+            .map(|i| Instance {
+                region: if i < 2 { "us-east-1" } else { "eu-west-1" }.into(),
+                ..Instance::new_for_test(i.to_string())
+            })
+            .collect();
+        let faults_type = FaultsType::RegionOutage {
+            region: "eu-west-1".into(),
+            interval: Duration::from_secs(30),
+            partition: true,
+        };
+        let mut schedule = CrashRecoverySchedule::new(faults_type, instances.clone());
+
+        let action = schedule.update();
+        assert_eq!(action.partition, instances[2..].to_vec());
+        assert!(action.kill.is_empty());
+        let (instance, command) = &NetworkPartition::commands(&action.partition, &instances)[0];
+        assert_eq!(instance, &instances[2]);
+        assert!(command.contains("sudo iptables -I INPUT -j partition"));
+
+        let action = schedule.update();
+        assert_eq!(action.heal, instances[2..].to_vec());
+        assert!(schedule.update().is_no_op());
+    }
+
+    #[test]
+    fn byzantine_nodes() {
+        let byzantine = ByzantineNodes {
+            fraction: 0.25,
+            strategy: ByzantineStrategy::WithholdVotes,
+        };
+        assert_eq!(byzantine.nodes(10), vec![8, 9]);
+        assert_eq!(byzantine.nodes(3), Vec::<usize>::new());
+    }
 }
@@ -0,0 +1,156 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{ops::Range, path::PathBuf};
+
+use mysticeti_core::config::NodePublicConfig;
+
+use crate::settings::Settings;
+
+/// Checks that an instance is ready to run a benchmark: its clock is synchronized, it has enough
+/// free disk, the ports of the nodes are free, and its working directory is usable (and mounted,
+/// if the setup commands of the provider mount a drive on it). The check runs a single command
+/// on the instance, printing one `name=value` line per property.
+pub struct HealthCheck {
+    /// The working directory on the instances.
+    working_dir: PathBuf,
+    /// Whether the working directory should be a mount point.
+    expect_mount: bool,
+    /// The ports used by the nodes (of the largest committee the testbed can host).
+    ports: Range<u16>,
+}
+
+impl HealthCheck {
+    /// The minimum free disk space (in GB) to run a benchmark.
+    pub const MIN_FREE_DISK_GB: u64 = 10;
+
+    /// Make a new health check for a testbed of the specified number of instances.
+    pub fn new(settings: &Settings, expect_mount: bool, instances: usize) -> Self {
+        // Nodes listen on a network port and a metrics port, allocated contiguously.
+        let start = NodePublicConfig::PORT_OFFSET_FOR_TESTS;
+        let end = start + 2 * instances as u16;
+        Self {
+            working_dir: settings.working_dir.clone(),
+            expect_mount,
+            ports: start..end,
+        }
+    }
+
+    /// The command printing the properties checked on an instance.
+    pub fn command(&self) -> String {
+        let working_dir = self.working_dir.display();
+        let (start, end) = (self.ports.start, self.ports.end);
+        [
+            // Containers and pods may not run systemd: their clock is the one of the host.
+            "echo clock=$(timedatectl show -p NTPSynchronized --value 2>/dev/null || echo unknown)"
+                .to_string(),
+            "echo disk=$(df --output=avail -BG ~ | tail -1 | tr -dc '0-9')".to_string(),
+            format!("echo ports=$(ss -Htln '( sport >= :{start} and sport < :{end} )' | wc -l)"),
+            format!("echo working_dir=$(test -d {working_dir} -a -w {working_dir} && echo ok)"),
+            format!("echo mounted=$(mountpoint -q {working_dir} && echo ok)"),
+        ]
+        .join(" && ")
+    }
+
+    /// The command printing the clock offset of an instance with respect to its time servers
+    /// (as reported by chrony or, failing that, by systemd-timesyncd), if known.
+    pub fn clock_offset_command() -> String {
+        [
+            "if chronyc -c tracking > /dev/null 2>&1",
+            "then chronyc -c tracking | cut -d, -f5",
+            "else timedatectl timesync-status 2>/dev/null | awk '/Offset:/ {print $2}'",
+            "fi",
+        ]
+        .join("; ")
+    }
+
+    /// Parse the output of the clock offset command into an offset in seconds. Chrony prints
+    /// seconds while timesyncd prints a value with a unit (e.g., '+1.234ms').
+    pub fn parse_clock_offset(stdout: &str) -> Option<f64> {
+        let value = stdout.trim();
+        let units = [("us", 1e-6), ("µs", 1e-6), ("ms", 1e-3), ("s", 1.0)];
+        let (number, scale) = units
+            .iter()
+            .find_map(|(unit, scale)| value.strip_suffix(unit).map(|x| (x, *scale)))
+            .unwrap_or((value, 1.0));
+        number.parse::<f64>().ok().map(|x| x * scale)
+    }
+
+    /// Return the failures reported by the output of the check command.
+    pub fn failures(&self, stdout: &str) -> Vec<String> {
+        let value = |name: &str| {
+            stdout
+                .lines()
+                .find_map(|line| line.strip_prefix(&format!("{name}=")))
+                .map(|x| x.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let mut failures = Vec::new();
+        if value("clock") == "no" {
+            failures.push("clock not synchronized".to_string());
+        }
+        match value("disk").parse::<u64>() {
+            Ok(free) if free >= Self::MIN_FREE_DISK_GB => (),
+            Ok(free) => failures.push(format!("low free disk ({free} GB)")),
+            Err(_) => failures.push("unknown free disk".to_string()),
+        }
+        match value("ports").parse::<u64>() {
+            Ok(0) => (),
+            Ok(n) => failures.push(format!(
+                "{n} ports in use in {}..{}",
+                self.ports.start, self.ports.end
+            )),
+            Err(_) => failures.push("unknown ports usage".to_string()),
+        }
+        if value("working_dir") != "ok" {
+            failures.push(format!("{} not writable", self.working_dir.display()));
+        } else if self.expect_mount && value("mounted") != "ok" {
+            failures.push(format!("{} not mounted", self.working_dir.display()));
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HealthCheck;
+    use crate::settings::Settings;
+
+    #[test]
+    fn healthy() {
+        let check = HealthCheck::new(&Settings::new_for_test(), true, 4);
+        let stdout = "clock=yes\ndisk=120\nports=0\nworking_dir=ok\nmounted=ok\n";
+        assert!(check.failures(stdout).is_empty());
+
+        // Pods without systemd do not report the clock status.
+        let stdout = "clock=unknown\ndisk=120\nports=0\nworking_dir=ok\nmounted=ok\n";
+        assert!(check.failures(stdout).is_empty());
+    }
+
+    #[test]
+    fn parse_clock_offset() {
+        let cases = [
+            ("0.000012345\n", 0.000012345),
+            ("+1.5ms\n", 0.0015),
+            ("-250us", -0.00025),
+            ("+2s", 2.0),
+        ];
+        for (stdout, expected) in cases {
+            let offset = HealthCheck::parse_clock_offset(stdout).unwrap();
+            assert!((offset - expected).abs() < 1e-12, "{stdout}");
+        }
+        assert_eq!(HealthCheck::parse_clock_offset(""), None);
+    }
+
+    #[test]
+    fn unhealthy() {
+        let check = HealthCheck::new(&Settings::new_for_test(), true, 4);
+        let stdout = "clock=no\ndisk=3\nports=2\nworking_dir=ok\nmounted=\n";
+        assert_eq!(check.failures(stdout).len(), 4);
+
+        let check = HealthCheck::new(&Settings::new_for_test(), false, 4);
+        let stdout = "clock=yes\ndisk=120\nports=0\nworking_dir=\nmounted=\n";
+        assert_eq!(check.failures(stdout).len(), 1);
+    }
+}
@@ -1,68 +1,424 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::cmp::max;
+use std::fmt::Display;
 
-use crate::display;
+use prettytable::{row, Table};
+use serde::{Deserialize, Serialize};
 
-/// A simple log analyzer counting the number of errors and panics.
-#[derive(Default, PartialEq, Eq)]
-pub struct LogsAnalyzer {
-    /// The number of errors in the nodes' log files.
-    pub node_errors: usize,
-    /// Whether a node panicked.
-    pub node_panic: bool,
-    /// The number of errors in the clients' log files.
-    pub client_errors: usize,
-    /// Whether a client panicked.
-    pub client_panic: bool,
+use crate::{display, settings::Settings};
+
+/// A kind of issue found in a log file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LogIssue {
+    /// The kind of issue (e.g., 'panic', 'error', or the name of a known bad pattern).
+    pub kind: String,
+    /// The number of lines reporting the issue.
+    pub count: usize,
+    /// The (1-based) number of the first line reporting the issue.
+    pub first_line: usize,
+    /// The lines surrounding the first occurrence of the issue.
+    pub context: Vec<String>,
 }
 
-impl PartialOrd for LogsAnalyzer {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
+/// The issues found in the log file of a node or client.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LogSummary {
+    /// The source of the log file (e.g., 'node-3' or 'client-0').
+    pub source: String,
+    /// The issues found in the log file, in order of severity.
+    pub issues: Vec<LogIssue>,
 }
 
-impl Ord for LogsAnalyzer {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.node_panic
-            || self.client_panic
-            || self.client_errors > other.client_errors
-            || self.node_errors > other.node_errors
-        {
-            std::cmp::Ordering::Greater
-        } else {
-            std::cmp::Ordering::Less
+impl LogSummary {
+    /// The number of lines kept before and after the first occurrence of an issue.
+    const CONTEXT_LINES: usize = 2;
+    /// The maximum length of a line of context.
+    const MAX_LINE_LENGTH: usize = 300;
+
+    /// The kinds of issues, in order of severity, with the pattern identifying them.
+    const PATTERNS: [(&'static str, &'static str); 8] = [
+        ("panic", "panicked at"),
+        ("error", " ERROR "),
+        ("out of memory", "Cannot allocate memory"),
+        ("disk full", "No space left on device"),
+        ("too many open files", "Too many open files"),
+        ("address in use", "Address already in use"),
+        ("connection refused", "Connection refused"),
+        ("broken pipe", "Broken pipe"),
+    ];
+
+    /// Scan the specified log file for issues.
+    pub fn new(source: String, log: &str) -> Self {
+        let lines: Vec<_> = log.lines().collect();
+        let mut issues: Vec<LogIssue> = Vec::new();
+        for (kind, pattern) in Self::PATTERNS {
+            let mut matches = lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.contains(pattern));
+            let Some((first, _)) = matches.next() else {
+                continue;
+            };
+
+            let start = first.saturating_sub(Self::CONTEXT_LINES);
+            let end = (first + Self::CONTEXT_LINES + 1).min(lines.len());
+            let context = lines[start..end]
+                .iter()
+                .map(|line| line.chars().take(Self::MAX_LINE_LENGTH).collect())
+                .collect();
+            issues.push(LogIssue {
+                kind: kind.into(),
+                count: 1 + matches.count(),
+                first_line: first + 1,
+                context,
+            });
         }
+        Self { source, issues }
+    }
+
+    /// The number of lines reporting the specified kind of issue.
+    pub fn count(&self, kind: &str) -> usize {
+        self.issues
+            .iter()
+            .find(|x| x.kind == kind)
+            .map(|x| x.count)
+            .unwrap_or_default()
     }
 }
 
+/// A log analyzer summarizing the panics, errors, and known bad patterns of the log files of the
+/// nodes and clients.
+#[derive(Default)]
+pub struct LogsAnalyzer {
+    /// The summary of each log file.
+    summaries: Vec<LogSummary>,
+}
+
 impl LogsAnalyzer {
-    /// Deduce the number of nodes errors from the logs.
-    pub fn set_node_errors(&mut self, log: &str) {
-        self.node_errors = log.matches(" ERROR").count();
-        self.node_panic = log.contains("panic");
+    /// Scan the log file of the specified node.
+    pub fn add_node_log(&mut self, index: usize, log: &str) {
+        self.summaries
+            .push(LogSummary::new(format!("node-{index}"), log));
+    }
+
+    /// Scan the log file of the specified client.
+    pub fn add_client_log(&mut self, index: usize, log: &str) {
+        self.summaries
+            .push(LogSummary::new(format!("client-{index}"), log));
     }
 
-    /// Deduce the number of clients errors from the logs.
-    pub fn set_client_errors(&mut self, log: &str) {
-        self.client_errors = max(self.client_errors, log.matches(" ERROR").count());
-        self.client_panic = log.contains("panic");
+    /// The summary of each log file.
+    pub fn into_summaries(self) -> Vec<LogSummary> {
+        self.summaries
     }
 
-    /// Print a summary of the errors.
+    /// Print a summary of the issues of each log file, along with the context of the first
+    /// panic (if any).
     pub fn print_summary(&self) {
-        if self.node_panic {
-            display::error("Node(s) panicked!");
-        } else if self.client_panic {
-            display::error("Client(s) panicked!");
-        } else if self.node_errors != 0 || self.client_errors != 0 {
+        let total = |kind: &str| -> usize { self.summaries.iter().map(|x| x.count(kind)).sum() };
+        let panicked: Vec<_> = self
+            .summaries
+            .iter()
+            .filter(|x| x.count("panic") != 0)
+            .map(|x| x.source.as_str())
+            .collect();
+        if !panicked.is_empty() {
+            display::error(format!("Panicked: {}", panicked.join(", ")));
+        } else if total("error") != 0 {
+            display::newline();
+            display::warn(format!("Logs contain {} errors", total("error")));
+        }
+
+        if self.summaries.iter().all(|x| x.issues.is_empty()) {
+            return;
+        }
+
+        let mut table = Table::new();
+        table.set_format(display::default_table_format());
+        table.set_titles(row![bH4->"Logs Summary"]);
+        table.add_row(row![b->"Source", b->"Issue", b->"Count", b->"First line"]);
+        for summary in &self.summaries {
+            for issue in &summary.issues {
+                table.add_row(row![
+                    summary.source,
+                    issue.kind,
+                    issue.count,
+                    issue.first_line
+                ]);
+            }
+        }
+        display::newline();
+        table.printstd();
+
+        let first_panic = self.summaries.iter().find_map(|summary| {
+            let issue = summary.issues.first().filter(|x| x.kind == "panic")?;
+            Some((summary, issue))
+        });
+        if let Some((summary, issue)) = first_panic {
             display::newline();
-            display::warn(format!(
-                "Logs contain errors (node: {}, client: {})",
-                self.node_errors, self.client_errors
-            ));
+            display::config(
+                format!(
+                    "First panic ({}, line {})",
+                    summary.source, issue.first_line
+                ),
+                format!("\n{}", issue.context.join("\n")),
+            );
+        }
+        display::newline();
+    }
+}
+
+/// The lines of the log files to retrieve from the instances, to avoid downloading multi-GB log
+/// files in full.
+#[derive(Clone, Debug, Default)]
+pub struct LogSelection {
+    /// The maximum number of bytes (at the end of the file) to retrieve, if any.
+    max_size: Option<u64>,
+    /// Only retrieve the lines matching this (extended) regular expression, if any.
+    filter: Option<String>,
+}
+
+impl LogSelection {
+    /// Select the lines of the log files specified by the settings.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            max_size: settings.log_max_size.map(|x| x * 1024 * 1024),
+            filter: settings.log_filter.clone(),
+        }
+    }
+
+    /// Only retrieve the lines matching the specified (extended) regular expression.
+    pub fn with_filter(mut self, filter: Option<String>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// The command printing the selected lines of the specified log file, or `None` if the file
+    /// is retrieved in full.
+    pub fn command(&self, path: &str) -> Option<String> {
+        match (self.max_size, &self.filter) {
+            (None, None) => None,
+            (Some(size), None) => Some(format!("tail -c {size} {path}")),
+            (None, Some(_)) => Some(format!("{} {path}", self.grep(false))),
+            (Some(size), Some(_)) => Some(format!("tail -c {size} {path} | {}", self.grep(false))),
+        }
+    }
+
+    /// The command printing the last lines of the specified log file, and then the selected
+    /// lines appended to it until interrupted.
+    pub fn follow_command(&self, path: &str, lines: usize) -> String {
+        let tail = format!("tail -n {lines} -F {path}");
+        match &self.filter {
+            Some(_) => format!("{tail} | {}", self.grep(true)),
+            None => tail,
+        }
+    }
+
+    /// The command filtering its input by the filter of the selection. Not finding any matching
+    /// line is not an error.
+    fn grep(&self, line_buffered: bool) -> String {
+        let filter = self.filter.as_deref().unwrap_or_default();
+        let options = if line_buffered {
+            " --line-buffered"
+        } else {
+            ""
+        };
+        // Quote the filter for the shell.
+        let filter = format!("'{}'", filter.replace('\'', r"'\''"));
+        format!("(grep{options} -E {filter} || [ $? -eq 1 ])")
+    }
+}
+
+/// A consensus event logged by a node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineEvent {
+    /// The time of the event (in seconds since the unix epoch), corrected by the clock offset of
+    /// the node.
+    pub time: f64,
+    /// The index of the node in the committee.
+    pub node: usize,
+    /// The kind of event (e.g., 'block', 'timeout', or 'commit').
+    pub kind: &'static str,
+    /// The log line of the event (without its timestamp).
+    pub message: String,
+}
+
+/// The consensus events of all nodes merged into a single timeline, to follow a bad run across
+/// the committee.
+#[derive(Default)]
+pub struct Timeline {
+    /// The events of all nodes, in chronological order.
+    events: Vec<TimelineEvent>,
+}
+
+impl Timeline {
+    /// The kinds of consensus events, with the pattern identifying them in the logs.
+    const PATTERNS: [(&'static str, &'static str); 4] = [
+        ("block", "Created block"),
+        ("timeout", "Timeout "),
+        ("leader", "Decided leader"),
+        ("commit", "Committed "),
+    ];
+
+    /// Add the consensus events of the log file of the specified node. The timestamps of the
+    /// node are corrected by its clock offset (in seconds, positive if its clock is ahead), if
+    /// known.
+    pub fn add_node_log(&mut self, index: usize, log: &str, clock_offset: Option<f64>) {
+        let offset = clock_offset.unwrap_or_default();
+        let events = log.lines().filter_map(|line| {
+            let line = Self::strip_ansi(line);
+            let &(kind, _) = Self::PATTERNS.iter().find(|(_, x)| line.contains(x))?;
+            let (timestamp, message) = line.split_once(' ')?;
+            Some(TimelineEvent {
+                time: Self::parse_timestamp(timestamp)? - offset,
+                node: index,
+                kind,
+                message: message.trim().to_string(),
+            })
+        });
+        self.events.extend(events);
+        self.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    /// The events of all nodes, in chronological order.
+    pub fn events(&self) -> &[TimelineEvent] {
+        &self.events
+    }
+
+    /// Remove the terminal color codes from a log line.
+    fn strip_ansi(line: &str) -> String {
+        let mut output = String::with_capacity(line.len());
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            if c == '\u{1b}' {
+                // Skip the escape sequence up to its final letter (e.g., 'm').
+                chars.by_ref().find(|x| x.is_ascii_alphabetic());
+            } else {
+                output.push(c);
+            }
+        }
+        output
+    }
+
+    /// Parse an RFC 3339 UTC timestamp (e.g., '2024-01-01T00:00:00.123456Z') into seconds since
+    /// the unix epoch.
+    fn parse_timestamp(timestamp: &str) -> Option<f64> {
+        let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+        let mut date = date.splitn(3, '-').map(|x| x.parse::<i64>().ok());
+        let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+        let mut time = time.splitn(3, ':');
+        let hours = time.next()?.parse::<f64>().ok()?;
+        let minutes = time.next()?.parse::<f64>().ok()?;
+        let seconds = time.next()?.parse::<f64>().ok()?;
+
+        // Count the days since the epoch (see http://howardhinnant.github.io/date_algorithms.html).
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era - 719468;
+
+        Some(days as f64 * 86400.0 + hours * 3600.0 + minutes * 60.0 + seconds)
+    }
+}
+
+impl Display for Timeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let start = self.events.first().map(|x| x.time).unwrap_or_default();
+        for event in &self.events {
+            writeln!(
+                f,
+                "[{:>10.3}s] node-{:<3} {:<8} {}",
+                event.time - start,
+                event.node,
+                event.kind,
+                event.message
+            )?;
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LogSelection, LogSummary, Timeline};
+
+    #[test]
+    fn summarize_issues() {
+        let log = [
+            "2024-01-01T00:00:00Z  INFO node: started",
+            "2024-01-01T00:00:01Z ERROR net: Connection refused",
+            "2024-01-01T00:00:02Z  INFO node: round 10",
+            "2024-01-01T00:00:03Z ERROR net: Connection refused",
+            "thread 'main' panicked at src/core.rs:10:5:",
+            "index out of bounds",
+        ]
+        .join("\n");
+        let summary = LogSummary::new("node-0".into(), &log);
+
+        assert_eq!(summary.count("panic"), 1);
+        assert_eq!(summary.count("error"), 2);
+        assert_eq!(summary.count("connection refused"), 2);
+        assert_eq!(summary.count("disk full"), 0);
+
+        let panic = &summary.issues[0];
+        assert_eq!(panic.first_line, 5);
+        assert_eq!(panic.context.len(), 4);
+        assert_eq!(panic.context[3], "index out of bounds");
+        assert_eq!(
+            summary.issues[1].context[0],
+            "2024-01-01T00:00:00Z  INFO node: started"
+        );
+    }
+
+    #[test]
+    fn merge_timeline() {
+        let node_0 = [
+            "2024-01-01T00:00:01.000000Z DEBUG mysticeti_core::core: Created block A1",
+            "2024-01-01T00:00:02.000000Z  INFO mysticeti_core::core: Pruned 10 bytes",
+            "2024-01-01T00:00:03.000000Z DEBUG mysticeti_core::syncer: Committed [A1]",
+        ]
+        .join("\n");
+        let node_1 = [
+            "\u{1b}[2m2024-01-01T00:00:02.500000Z\u{1b}[0m DEBUG net_sync: Timeout 1",
+            "2024-01-01T00:00:03.200000Z DEBUG mysticeti_core::core: Created block B2",
+        ]
+        .join("\n");
+
+        let mut timeline = Timeline::default();
+        timeline.add_node_log(0, &node_0, None);
+        // The clock of node 1 is 1s ahead.
+        timeline.add_node_log(1, &node_1, Some(1.0));
+
+        let events: Vec<_> = timeline.events().iter().map(|x| (x.node, x.kind)).collect();
+        assert_eq!(
+            events,
+            [(0, "block"), (1, "timeout"), (1, "block"), (0, "commit")]
+        );
+        assert_eq!(timeline.events()[1].message, "DEBUG net_sync: Timeout 1");
+        assert_eq!(
+            Timeline::parse_timestamp("2024-01-01T00:00:01.5Z"),
+            Some(1704067201.5)
+        );
+    }
+
+    #[test]
+    fn select_lines() {
+        assert_eq!(LogSelection::default().command("node.log"), None);
+
+        let selection = LogSelection {
+            max_size: Some(1024),
+            filter: Some("panicked|it's".into()),
+        };
+        assert_eq!(
+            selection.command("node.log").unwrap(),
+            r"tail -c 1024 node.log | (grep -E 'panicked|it'\''s' || [ $? -eq 1 ])"
+        );
+        assert_eq!(
+            selection.follow_command("node.log", 10),
+            r"tail -n 10 -F node.log | (grep --line-buffered -E 'panicked|it'\''s' || [ $? -eq 1 ])"
+        );
     }
 }
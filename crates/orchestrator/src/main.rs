@@ -3,36 +3,64 @@
 
 //! Orchestrator entry point.
 
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
-use benchmark::BenchmarkParameters;
+use benchmark::{BenchmarkParametersGenerator, SweepPoint};
 use clap::Parser;
-use client::{aws::AwsClient, gcp::GcpClient, vultr::VultrClient, ServerProviderClient};
-use eyre::Context;
-use measurements::MeasurementsCollection;
+use cost::CostEstimate;
+use client::{
+    aws::AwsClient,
+    azure::AzureClient,
+    digitalocean::DigitalOceanClient,
+    gcp::GcpClient,
+    kubernetes::KubernetesClient,
+    linode::LinodeClient,
+    local::LocalClient,
+    terraform::TerraformClient,
+    vultr::VultrClient,
+    Instance,
+    ServerProviderClient,
+};
+use eyre::{eyre, Context};
+use faults::FaultsType;
+use logs::LogSelection;
+use measurements::{LoadStability, MeasurementsCollection};
+use mysticeti_core::config::LoadMode;
 use orchestrator::Orchestrator;
-use protocol::ProtocolParameters;
+use profile::{ProfileWindow, Profiler};
+use protocol::{
+    mysticeti::MysticetiProtocol, single_leader::SingleLeaderProtocol, ProtocolCommands,
+    ProtocolMetrics, ProtocolParameters,
+};
 use serde_json::json;
-use settings::{CloudProvider, Settings};
+use settings::{CloudProvider, Protocol, Settings};
 use ssh::SshConnectionManager;
 use testbed::Testbed;
 
+mod audit;
 mod benchmark;
 mod client;
+mod compare;
+mod cost;
+mod dashboard;
 mod display;
+mod docker;
 mod error;
 mod faults;
+mod health;
 mod logs;
 mod measurements;
 mod monitor;
 mod orchestrator;
+mod profile;
 mod protocol;
+mod provenance;
 mod settings;
 mod ssh;
+mod sweep;
 mod testbed;
 
-/// NOTE: Link these types to the correct protocol.
-type Protocol = protocol::mysticeti::MysticetiProtocol;
+/// NOTE: Link these types to the parameters of the protocols.
 type NodeParameters = protocol::mysticeti::MysticetiNodeParameters;
 type ClientParameters = protocol::mysticeti::MysticetiClientParameters;
 
@@ -51,6 +79,18 @@ pub struct Opts {
     )]
     settings_path: String,
 
+    /// The name of the testbed, overriding the testbed id of the settings file. This allows
+    /// running several testbeds (e.g., different experiments) from the same settings file.
+    #[clap(long, value_name = "NAME", global = true)]
+    testbed_name: Option<String>,
+
+    /// Override a field of the settings file, as 'key=value' (e.g., 'specs=m5d.8xlarge' or
+    /// 'repository.commit=main' for a nested field). The value is parsed as yaml (e.g.,
+    /// '[us-east-1, eu-west-1]' for a list). Takes precedence over the environment variables
+    /// overriding the settings (e.g., 'ORCHESTRATOR_REPOSITORY__COMMIT=main').
+    #[clap(long = "set", value_name = "KEY=VALUE", global = true)]
+    overrides: Vec<String>,
+
     /// The type of operation to run.
     #[clap(subcommand)]
     operation: Operation,
@@ -68,9 +108,10 @@ pub enum Operation {
     },
     /// Deploy nodes and run a benchmark on the specified testbed.
     Benchmark {
-        /// The committee size to deploy.
-        #[clap(long, value_name = "INT", default_value_t = 4, global = true)]
-        committee: usize,
+        /// The set of committee sizes to deploy. Each committee size triggers separate benchmark
+        /// runs.
+        #[clap(long, value_name = "[INT]", default_value = "4", global = true)]
+        committee: Vec<usize>,
 
         /// The set of loads to submit to the system (tx/s). Each load triggers a separate
         /// benchmark run. Setting a load to zero will not deploy any benchmark clients
@@ -78,6 +119,42 @@ pub enum Operation {
         #[clap(long, value_name = "[INT]", default_value = "200", global = true)]
         loads: Vec<usize>,
 
+        /// The set of transaction sizes (in bytes) to benchmark. If not specified, use the
+        /// transaction size of the client's parameters.
+        #[clap(long, value_name = "[INT]", global = true)]
+        transaction_sizes: Vec<usize>,
+
+        /// The set of numbers of (permanently) crashed nodes to benchmark. If not specified, use
+        /// the faults of the setting file.
+        #[clap(long, value_name = "[INT]", global = true)]
+        faults: Vec<usize>,
+
+        /// A YAML file listing the points of the sweep (committee size, load, and optionally
+        /// transaction size and faults) to benchmark, rather than the cartesian product of the
+        /// options above.
+        #[clap(long, value_name = "FILE", global = true)]
+        sweep_file: Option<PathBuf>,
+
+        /// The number of times to run the benchmark of each point of the sweep. Running it several
+        /// times reports the mean, standard deviation, and confidence interval of the measurements.
+        #[clap(long, value_name = "INT", default_value_t = 1, global = true)]
+        repetitions: usize,
+
+        /// Search the maximum load (tx/s) at which the p99 latency (in milliseconds) stays under
+        /// this bound, starting from the specified loads, rather than running the sweep.
+        #[clap(long, value_name = "INT", global = true)]
+        latency_slo: Option<u64>,
+
+        /// The maximum number of benchmarks to run per load search.
+        #[clap(long, value_name = "INT", default_value_t = 10, global = true)]
+        search_steps: usize,
+
+        /// The number of consecutive scrape intervals during which the p99 latency must stay
+        /// under the bound for a load to be sustainable. The search stops a benchmark early if
+        /// its latency grows during as many intervals (ending above the bound).
+        #[clap(long, value_name = "INT", default_value_t = 3, global = true)]
+        stable_intervals: usize,
+
         /// Whether to skip testbed updates before running benchmarks. This is a dangerous
         /// operation as it may lead to running benchmarks on outdated nodes. It is however
         /// useful when debugging in some specific scenarios.
@@ -89,15 +166,138 @@ pub enum Operation {
         /// useful when debugging in some specific scenarios.
         #[clap(long, action, default_value_t = false, global = true)]
         skip_testbed_configuration: bool,
+
+        /// Whether to replace the dead (terminated or unreachable) instances of the testbed
+        /// before running benchmarks, rather than only warning about them.
+        #[clap(long, action, default_value_t = false, global = true)]
+        replace_dead_instances: bool,
+
+        /// The delay (in milliseconds) between the startup of consecutive validators. Setting
+        /// this value to zero starts all validators at once.
+        #[clap(long, value_name = "INT", default_value_t = 0, global = true)]
+        startup_delay: u64,
+
+        /// The maximum random delay (in milliseconds) added to the startup of each validator.
+        #[clap(long, value_name = "INT", default_value_t = 0, global = true)]
+        startup_jitter: u64,
+
+        /// Print the plan of the benchmarks (the assignment of the instances, the generated
+        /// configurations, and every command to execute) without touching any instance.
+        #[clap(long, action, default_value_t = false, global = true)]
+        dry_run: bool,
+
+        /// Display a live dashboard of each benchmark (throughput and latency of every scrape
+        /// interval, status of the nodes, and fault events) while it runs.
+        #[clap(long, action, default_value_t = false, global = true)]
+        dashboard: bool,
+
+        /// Whether to run all benchmarks, rather than resuming the sweep from the benchmarks
+        /// completed by a previous (interrupted) run of the same command.
+        #[clap(long, action, default_value_t = false, global = true)]
+        restart: bool,
+
+        /// The validators (identified by their index in the committee) whose CPU profile to
+        /// record during each benchmark. Their flamegraphs are saved in the results directory.
+        #[clap(long, value_name = "[INT]", global = true)]
+        profile: Vec<usize>,
+
+        /// The delay (in seconds) after the startup of the validators before recording their
+        /// profile.
+        #[clap(long, value_name = "INT", default_value_t = 30, global = true)]
+        profile_start: u64,
+
+        /// The duration (in seconds) of the recording of the profile.
+        #[clap(long, value_name = "INT", default_value_t = 30, global = true)]
+        profile_duration: u64,
+
+        /// The validators (identified by their index in the committee) whose heap to profile
+        /// during each benchmark (requires 'heap_profiling' in the settings). Their heap dumps
+        /// are saved in the results directory.
+        #[clap(long, value_name = "[INT]", global = true)]
+        heap_profile: Vec<usize>,
     },
     /// Print a summary of the specified measurements collection.
     Summarize {
         /// The path to the settings file.
         #[clap(long, value_name = "FILE")]
         path: PathBuf,
+
+        /// The output format: a table for humans, or the aggregate and per-interval metrics in
+        /// a machine-readable format.
+        #[clap(long, value_enum, default_value_t = SummaryFormat::Table)]
+        format: SummaryFormat,
+    },
+    /// Compare two sets of measurements (e.g., of the main branch and of a feature branch) and
+    /// flag the performance regressions.
+    Compare {
+        /// The baseline measurements: a measurements file or a results directory.
+        #[clap(long, value_name = "PATH")]
+        baseline: PathBuf,
+
+        /// The candidate measurements: a measurements file or a results directory.
+        #[clap(long, value_name = "PATH")]
+        candidate: PathBuf,
+
+        /// The change (in percent) of throughput or latency beyond which a benchmark regressed.
+        #[clap(long, value_name = "PERCENT", default_value_t = 5.0)]
+        threshold: f64,
+    },
+    /// Record the CPU profile of the validators running on the specified instances (e.g., of a
+    /// benchmark running indefinitely) and download their flamegraphs.
+    Profile {
+        /// The instances to profile, identified by their id or their (public) ip address.
+        #[clap(long, required = true)]
+        instance: Vec<String>,
+
+        /// The delay (in seconds) before recording the profile.
+        #[clap(long, value_name = "INT", default_value_t = 0)]
+        start: u64,
+
+        /// The duration (in seconds) of the recording of the profile.
+        #[clap(long, value_name = "INT", default_value_t = 30)]
+        duration: u64,
+    },
+    /// Inspect the log files of the instances.
+    Logs {
+        /// The action to perform on the log files.
+        #[clap(subcommand)]
+        action: LogsAction,
     },
 }
 
+/// The action to perform on the log files of the instances.
+#[derive(Parser, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub enum LogsAction {
+    /// Print the last lines of the log file of an instance, and then follow it until
+    /// interrupted.
+    Tail {
+        /// The instance to follow, identified by its id or its (public) ip address.
+        #[clap(long)]
+        instance: String,
+
+        /// Follow the log file of the load generator rather than of the node.
+        #[clap(long)]
+        client: bool,
+
+        /// The number of (last) lines to print before following the log file.
+        #[clap(long, value_name = "INT", default_value_t = 100)]
+        lines: usize,
+
+        /// Only print the lines matching this (extended) regular expression.
+        #[clap(long, value_name = "REGEX")]
+        filter: Option<String>,
+    },
+}
+
+/// The output format of a summary of measurements.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum SummaryFormat {
+    Table,
+    Csv,
+    Json,
+}
+
 /// The action to perform on the testbed.
 #[derive(Parser, Debug)]
 #[clap(rename_all = "kebab-case")]
@@ -116,6 +316,11 @@ pub enum TestbedAction {
         /// setting file.
         #[clap(long)]
         region: Option<String>,
+
+        /// The time to live (in seconds) of the testbed, after which 'testbed reap' destroys
+        /// it. This parameter overrides the ttl of the setting file.
+        #[clap(long, value_name = "INT")]
+        ttl: Option<u64>,
     },
 
     /// Start at most the specified number of instances per region on an existing testbed.
@@ -130,6 +335,29 @@ pub enum TestbedAction {
 
     /// Destroy the testbed and terminate all instances.
     Destroy,
+
+    /// Destroy the testbed if it is past its time to live.
+    Reap,
+
+    /// Replace the dead (terminated or unreachable) instances with new instances in the same
+    /// regions, and install the codebase on them.
+    Replace,
+
+    /// Check that all active instances are reachable and ready to run benchmarks (clock sync,
+    /// free disk, free ports, and working directory).
+    Check,
+
+    /// Estimate the cost of a testbed with the specified number of instances per region.
+    Cost {
+        /// Number of instances per region.
+        #[clap(long)]
+        instances: usize,
+
+        /// The duration (in seconds) of the planned benchmark. If this parameter is not
+        /// specified, the command uses the benchmark duration of the setting file (if any).
+        #[clap(long, value_name = "INT")]
+        duration: Option<u64>,
+    },
 }
 
 #[tokio::main]
@@ -138,7 +366,13 @@ async fn main() -> eyre::Result<()> {
     let opts: Opts = Opts::parse();
 
     // Load the settings files.
-    let settings = Settings::load(&opts.settings_path).wrap_err("Failed to load settings")?;
+    let mut settings =
+        Settings::load(&opts.settings_path, &opts.overrides).wrap_err("Failed to load settings")?;
+    if let Some(name) = &opts.testbed_name {
+        settings
+            .set_testbed_name(name)
+            .wrap_err("Failed to set the testbed name")?;
+    }
 
     match &settings.cloud_provider {
         CloudProvider::Aws => {
@@ -159,8 +393,105 @@ async fn main() -> eyre::Result<()> {
             run(settings, client, opts).await
         }
         CloudProvider::Gcp => {
-            let client = GcpClient::new(settings.clone());
+            // Create the client for the cloud provider.
+            let service_account = settings
+                .load_token()
+                .wrap_err("Failed to load cloud provider's service account")?;
+            let client = GcpClient::new(&service_account, settings.clone())?;
+
+            // Execute the command.
+            run(settings, client, opts).await
+        }
+        CloudProvider::Azure => {
+            let client = AzureClient::new(settings.clone());
+
+            run(settings, client, opts).await
+        }
+        CloudProvider::DigitalOcean => {
+            // Create the client for the cloud provider.
+            let token = settings
+                .load_token()
+                .wrap_err("Failed to load cloud provider's token")?;
+            let client = DigitalOceanClient::new(token, settings.clone());
+
+            // Execute the command.
+            run(settings, client, opts).await
+        }
+        CloudProvider::Linode => {
+            // Create the client for the cloud provider.
+            let token = settings
+                .load_token()
+                .wrap_err("Failed to load cloud provider's token")?;
+            let client = LinodeClient::new(token, settings.clone());
+
+            // Execute the command.
+            run(settings, client, opts).await
+        }
+        CloudProvider::Terraform => {
+            // Create the client for the cloud provider.
+            let module = settings
+                .terraform_module
+                .clone()
+                .ok_or_else(|| eyre!("Terraform requires a module (see 'terraform_module')"))?;
+            let client = TerraformClient::new(settings.clone(), module)?;
+
+            // Execute the command.
+            run(settings, client, opts).await
+        }
+        CloudProvider::Local => {
+            // The monitoring stack requires a dedicated (remote) instance.
+            eyre::ensure!(
+                !settings.monitoring,
+                "Local testbeds do not support monitoring (set 'monitoring' to false)"
+            );
+            // All local instances share the network stack of the local machine.
+            eyre::ensure!(
+                settings.network_impairments.is_empty(),
+                "Local testbeds do not support network impairments"
+            );
+            eyre::ensure!(
+                settings.resource_stress.is_empty(),
+                "Local testbeds do not support resource-stress faults"
+            );
+            eyre::ensure!(
+                settings.gray_failures.is_empty(),
+                "Local testbeds do not support gray failures"
+            );
+            eyre::ensure!(
+                !settings.faults.partitions_network(),
+                "Local testbeds do not support network partitions"
+            );
+            let client = LocalClient::new(settings.clone())?;
+
+            // Execute the command.
+            run(settings, client, opts).await
+        }
+        CloudProvider::Kubernetes => {
+            // The pods are monitored by the prometheus instance of the cluster.
+            eyre::ensure!(
+                !settings.monitoring,
+                "Kubernetes testbeds do not support monitoring (set 'monitoring' to false)"
+            );
+            // The pods are not allowed to change the traffic control of their nodes.
+            eyre::ensure!(
+                settings.network_impairments.is_empty(),
+                "Kubernetes testbeds do not support network impairments"
+            );
+            eyre::ensure!(
+                settings.resource_stress.is_empty(),
+                "Kubernetes testbeds do not support resource-stress faults"
+            );
+            eyre::ensure!(
+                settings.gray_failures.is_empty(),
+                "Kubernetes testbeds do not support gray failures"
+            );
+            eyre::ensure!(
+                !settings.faults.partitions_network(),
+                "Kubernetes testbeds do not support network partitions"
+            );
+            let client = KubernetesClient::new(settings.clone());
 
+            // Execute the command.
             run(settings, client, opts).await
         }
     }
@@ -171,19 +502,45 @@ async fn run<C: ServerProviderClient>(
     client: C,
     opts: Opts,
 ) -> eyre::Result<()> {
+    match settings.protocol {
+        Protocol::Mysticeti => run_protocol::<C, MysticetiProtocol>(settings, client, opts).await,
+        Protocol::SingleLeader => {
+            run_protocol::<C, SingleLeaderProtocol>(settings, client, opts).await
+        }
+    }
+}
+
+async fn run_protocol<C, P>(settings: Settings, client: C, opts: Opts) -> eyre::Result<()>
+where
+    C: ServerProviderClient,
+    P: ProtocolCommands + ProtocolMetrics,
+{
     // Create a new testbed.
     let mut testbed = Testbed::new(settings.clone(), client)
         .await
         .wrap_err("Failed to create testbed")?;
 
+    // Warn about forgotten testbeds.
+    if let Some(overdue) = testbed.expired().wrap_err("Failed to check the testbed ttl")? {
+        display::warn(format!(
+            "Testbed '{}' expired {}s ago: run 'testbed reap' to destroy it",
+            settings.testbed_id,
+            overdue.as_secs()
+        ));
+    }
+
     match opts.operation {
         Operation::Testbed { action } => match action {
             // Display the current status of the testbed.
             TestbedAction::Status => testbed.status(),
 
             // Deploy the specified number of instances on the testbed.
-            TestbedAction::Deploy { instances, region } => testbed
-                .deploy(instances, region)
+            TestbedAction::Deploy {
+                instances,
+                region,
+                ttl,
+            } => testbed
+                .deploy(instances, region, ttl.map(Duration::from_secs))
                 .await
                 .wrap_err("Failed to deploy testbed")?,
 
@@ -201,21 +558,89 @@ async fn run<C: ServerProviderClient>(
                 .destroy()
                 .await
                 .wrap_err("Failed to destroy testbed")?,
+
+            // Check the health of the instances.
+            TestbedAction::Check => testbed
+                .check(&ssh_manager(&settings, &testbed)?)
+                .await
+                .wrap_err("Failed to check testbed")?,
+
+            // Replace the dead instances of the testbed.
+            TestbedAction::Replace => {
+                let dead = testbed.dead_instances().await;
+                replace_dead_instances::<_, P>(&settings, &mut testbed, dead)
+                    .await
+                    .wrap_err("Failed to replace dead instances")?;
+            }
+
+            // Destroy the testbed if it expired.
+            TestbedAction::Reap => testbed.reap().await.wrap_err("Failed to reap testbed")?,
+
+            // Estimate the cost of the testbed.
+            TestbedAction::Cost {
+                instances,
+                duration,
+            } => {
+                let duration = duration
+                    .map(Duration::from_secs)
+                    .or(Some(settings.benchmark_duration))
+                    .filter(|x| !x.is_zero());
+                CostEstimate::new(&settings, instances)
+                    .wrap_err("Failed to estimate the testbed cost")?
+                    .display(duration);
+            }
         },
 
         // Run benchmarks.
         Operation::Benchmark {
             committee,
             loads,
+            transaction_sizes,
+            faults,
+            sweep_file,
+            repetitions,
+            latency_slo,
+            search_steps,
+            stable_intervals,
             skip_testbed_update,
             skip_testbed_configuration,
+            replace_dead_instances: replace,
+            startup_delay,
+            startup_jitter,
+            dry_run,
+            restart,
+            dashboard,
+            profile,
+            profile_start,
+            profile_duration,
+            heap_profile,
         } => {
+            eyre::ensure!(repetitions > 0, "The number of repetitions must be positive");
+
+            // Replace (or warn about) the dead instances of the testbed. Dry runs do not connect
+            // to the instances to find them.
+            let dead = if dry_run {
+                Vec::new()
+            } else {
+                testbed.dead_instances().await
+            };
+            if replace && !dry_run {
+                replace_dead_instances::<_, P>(&settings, &mut testbed, dead)
+                    .await
+                    .wrap_err("Failed to replace dead instances")?;
+            } else if !dead.is_empty() {
+                display::warn(format!(
+                    "{} instances are dead: use '--replace-dead-instances' to replace them",
+                    dead.len()
+                ));
+            }
+
             // Create a new orchestrator to instruct the testbed.
-            let username = testbed.username();
-            let private_key_file = settings.ssh_private_key_file.clone();
-            let ssh_manager = SshConnectionManager::new(username.into(), private_key_file)
-                .with_timeout(settings.ssh_timeout)
-                .with_retries(settings.ssh_retries);
+            let ssh_manager = if dry_run {
+                SshConnectionManager::dry_run()
+            } else {
+                ssh_manager(&settings, &testbed)?
+            };
 
             let instances = testbed.instances();
 
@@ -224,28 +649,83 @@ async fn run<C: ServerProviderClient>(
                 .await
                 .wrap_err("Failed to load testbed setup commands")?;
 
-            let protocol_commands = Protocol::new(&settings);
+            let protocol_commands = P::new(&settings);
             let node_parameters = match &settings.node_parameters_path {
                 Some(path) => {
                     NodeParameters::load(path).wrap_err("Failed to load node's parameters")?
                 }
                 None => NodeParameters::default(),
             };
+            // The benchmarks of the single-leader baseline run (and record) the node parameters
+            // of a single leader.
+            let node_parameters = match settings.protocol {
+                Protocol::Mysticeti => node_parameters,
+                Protocol::SingleLeader => node_parameters.single_leader(),
+            };
             let client_parameters = match &settings.client_parameters_path {
                 Some(path) => {
                     ClientParameters::load(path).wrap_err("Failed to load client's parameters")?
                 }
                 None => ClientParameters::default(),
             };
-            let set_of_benchmark_parameters = BenchmarkParameters::new_from_loads(
+            // The load generators running within the nodes only submit at a fixed rate.
+            eyre::ensure!(
+                client_parameters.mode == LoadMode::OpenLoop
+                    || settings.dedicated_client_instances() != 0,
+                "Closed-loop load requires dedicated load generators (see 'client_placement')"
+            );
+            let generator = BenchmarkParametersGenerator::new(
                 settings.clone(),
                 node_parameters,
                 client_parameters,
-                committee,
-                loads,
+            )
+            .with_repetitions(repetitions);
+            let generator = match sweep_file {
+                Some(path) => generator.with_points(SweepPoint::load(path)?),
+                None => {
+                    let faults: Vec<_> = faults
+                        .into_iter()
+                        .map(|faults| FaultsType::Permanent { faults })
+                        .collect();
+                    generator.with_cartesian_product(
+                        &committee,
+                        &loads,
+                        &transaction_sizes,
+                        &faults,
+                    )
+                }
+            };
+            let set_of_benchmark_parameters = generator.generate();
+
+            // The profile window must fit in the benchmark.
+            let profile_window = ProfileWindow {
+                start: Duration::from_secs(profile_start),
+                duration: Duration::from_secs(profile_duration),
+            };
+            eyre::ensure!(
+                profile.is_empty()
+                    || profile_window.start + profile_window.duration
+                        <= settings.benchmark_duration,
+                "The profile window ends after the benchmark ({}s)",
+                settings.benchmark_duration.as_secs()
             );
 
-            Orchestrator::new(
+            eyre::ensure!(
+                heap_profile.is_empty() || settings.heap_profiling,
+                "Heap profiling requires building the nodes with 'heap_profiling' (see settings)"
+            );
+            eyre::ensure!(
+                settings
+                    .byzantine
+                    .as_ref()
+                    .map_or(true, |x| (0.0..1.0).contains(&x.fraction)),
+                "The fraction of byzantine nodes must be in [0, 1)"
+            );
+            for parameters in &set_of_benchmark_parameters {
+                parameters.settings.validate_faults(parameters.nodes)?;
+            }
+
+            let mut orchestrator = Orchestrator::new(
                 settings,
                 instances,
                 setup_commands,
@@ -254,13 +734,168 @@ async fn run<C: ServerProviderClient>(
             )
             .skip_testbed_update(skip_testbed_update)
             .skip_testbed_configuration(skip_testbed_configuration)
-            .run_benchmarks(set_of_benchmark_parameters)
-            .await
-            .wrap_err("Failed to run benchmarks")?;
+            .restart_sweep(restart)
+            .with_dashboard(dashboard)
+            .with_profiling(profile, profile_window)
+            .with_heap_profiling(heap_profile)
+            .with_startup_delay(
+                Duration::from_millis(startup_delay),
+                Duration::from_millis(startup_jitter),
+            );
+            if dry_run {
+                orchestrator
+                    .dry_run(set_of_benchmark_parameters)
+                    .await
+                    .wrap_err("Failed to plan benchmarks")?;
+            } else if let Some(bound) = latency_slo {
+                let stability = LoadStability::new(Duration::from_millis(bound), stable_intervals);
+                orchestrator
+                    .search_load(set_of_benchmark_parameters, stability, search_steps)
+                    .await
+                    .wrap_err("Failed to search the maximum load")?;
+            } else {
+                orchestrator
+                    .run_benchmarks(set_of_benchmark_parameters)
+                    .await
+                    .wrap_err("Failed to run benchmarks")?;
+            }
         }
 
         // Print a summary of the specified measurements collection.
-        Operation::Summarize { path } => MeasurementsCollection::load(path)?.display_summary(),
+        Operation::Summarize { path, format } => {
+            let measurements = MeasurementsCollection::load(path)?;
+            match format {
+                SummaryFormat::Table => measurements.display_summary(),
+                SummaryFormat::Csv => print!("{}", measurements.to_csv()),
+                SummaryFormat::Json => println!("{}", measurements.to_json()),
+            }
+        }
+
+        // Compare two sets of measurements and fail upon regressions.
+        Operation::Compare {
+            baseline,
+            candidate,
+            threshold,
+        } => {
+            let baseline = compare::load_results(&baseline)
+                .wrap_err_with(|| format!("Failed to load {}", baseline.display()))?;
+            let candidate = compare::load_results(&candidate)
+                .wrap_err_with(|| format!("Failed to load {}", candidate.display()))?;
+            let comparison = compare::Comparison::new(&baseline, &candidate, threshold);
+            comparison.display();
+            eyre::ensure!(
+                comparison.regressions() == 0,
+                "{} metrics regressed by more than {threshold}%",
+                comparison.regressions()
+            );
+        }
+
+        // Profile the validators of the specified instances.
+        Operation::Profile {
+            instance,
+            start,
+            duration,
+        } => {
+            let instances = testbed.instances();
+            let mut targets = Vec::new();
+            for id in instance {
+                let target = instances
+                    .iter()
+                    .find(|x| x.id == id || x.main_ip.to_string() == id)
+                    .ok_or_else(|| eyre!("Unknown instance '{id}'"))?;
+                targets.push((id, target.clone()));
+            }
+            let window = ProfileWindow {
+                start: Duration::from_secs(start),
+                duration: Duration::from_secs(duration),
+            };
+            let profiler = Profiler::new(targets, window, ssh_manager(&settings, &testbed)?);
+
+            display::action("Recording profiles");
+            profiler.install().await?;
+            profiler.start().await?;
+            let path = settings.results_dir.join("profiles");
+            profiler.download(&path).await?;
+            display::done();
+            display::config("Flamegraphs", path.display());
+        }
+
+        // Follow the log file of an instance.
+        Operation::Logs { action } => match action {
+            LogsAction::Tail {
+                instance,
+                client,
+                lines,
+                filter,
+            } => {
+                let target = testbed
+                    .instances()
+                    .into_iter()
+                    .find(|x| x.id == instance || x.main_ip.to_string() == instance)
+                    .ok_or_else(|| eyre!("Unknown instance '{instance}'"))?;
+                let path = if client { "client.log" } else { "node.log" };
+                let command = LogSelection::default()
+                    .with_filter(filter)
+                    .follow_command(path, lines);
+                ssh_manager(&settings, &testbed)?
+                    .stream(&target, command)
+                    .await
+                    .wrap_err_with(|| format!("Failed to tail the logs of {instance}"))?;
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Create the manager executing commands on the instances of the testbed.
+fn ssh_manager<C: ServerProviderClient>(
+    settings: &Settings,
+    testbed: &Testbed<C>,
+) -> eyre::Result<SshConnectionManager> {
+    Ok(match settings.cloud_provider {
+        CloudProvider::Local => SshConnectionManager::local(LocalClient::root(settings)?),
+        CloudProvider::Kubernetes => {
+            SshConnectionManager::kubernetes(settings.kubernetes_namespace.clone())
+        }
+        _ => {
+            let username = testbed.username();
+            let authentication = settings.ssh_authentication();
+            let manager = SshConnectionManager::new(username.into(), authentication)
+                .with_timeout(settings.ssh_timeout)
+                .with_retries(settings.ssh_retries);
+            match settings.jump_host() {
+                Some(jump_host) => manager.with_jump_host(jump_host),
+                None => manager,
+            }
+        }
+    })
+}
+
+/// Replace the specified dead instances of the testbed and set up the replacements (without
+/// touching the other instances).
+async fn replace_dead_instances<C, P>(
+    settings: &Settings,
+    testbed: &mut Testbed<C>,
+    dead: Vec<Instance>,
+) -> eyre::Result<()>
+where
+    C: ServerProviderClient,
+    P: ProtocolCommands + ProtocolMetrics,
+{
+    if dead.is_empty() {
+        return Ok(());
     }
+    let replacements = testbed.replace(&dead).await?;
+
+    let setup_commands = testbed.setup_commands().await?;
+    let orchestrator = Orchestrator::new(
+        settings.clone(),
+        replacements,
+        setup_commands,
+        P::new(settings),
+        ssh_manager(settings, testbed)?,
+    );
+    orchestrator.install().await?;
+    orchestrator.update().await?;
     Ok(())
 }
@@ -14,7 +14,10 @@ use prettytable::{row, Table};
 use prometheus_parse::Scrape;
 use serde::{Deserialize, Serialize};
 
-use crate::{benchmark::BenchmarkParameters, display, protocol::ProtocolMetrics};
+use crate::{
+    benchmark::BenchmarkParameters, display, faults::FaultEvent, logs::LogSummary, monitor::Alert,
+    protocol::ProtocolMetrics, provenance::Provenance,
+};
 
 /// The identifier of prometheus latency buckets.
 type BucketId = String;
@@ -109,6 +112,56 @@ impl Measurement {
         measurements
     }
 
+    /// Return the measurement of the interval between the specified (earlier) measurement and
+    /// this one.
+    pub fn since(&self, earlier: &Self) -> Self {
+        let buckets = self
+            .buckets
+            .iter()
+            .map(|(id, count)| {
+                let before = earlier.buckets.get(id).copied().unwrap_or_default();
+                (id.clone(), count.saturating_sub(before))
+            })
+            .collect();
+        Self {
+            timestamp: self.timestamp.saturating_sub(earlier.timestamp),
+            buckets,
+            sum: self.sum.saturating_sub(earlier.sum),
+            count: self.count.saturating_sub(earlier.count),
+            squared_sum: (self.squared_sum - earlier.squared_sum).max(0.0),
+        }
+    }
+
+    /// Estimate the specified quantile (e.g., 0.99) of the latency from the latency buckets: the
+    /// upper bound of the first bucket holding that fraction of the transactions. Returns `None`
+    /// if there are no transactions and `Duration::MAX` if the quantile falls beyond the largest
+    /// finite bucket.
+    pub fn quantile_latency(&self, quantile: f64) -> Option<Duration> {
+        let mut buckets: Vec<_> = self
+            .buckets
+            .iter()
+            .filter_map(|(id, count)| Some((id.parse::<f64>().ok()?, *count)))
+            .collect();
+        buckets.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        // The buckets are cumulative: the last one counts all transactions.
+        let total = buckets.last()?.1;
+        if total == 0 {
+            return None;
+        }
+        let target = (quantile * total as f64).ceil() as usize;
+        buckets
+            .iter()
+            .find(|(_, count)| *count >= target)
+            .map(|(bound, _)| {
+                if bound.is_finite() {
+                    Duration::from_secs_f64(*bound)
+                } else {
+                    Duration::MAX
+                }
+            })
+    }
+
     /// Compute the average latency.
     pub fn average_latency(&self) -> Duration {
         self.sum.checked_div(self.count as u32).unwrap_or_default()
@@ -140,6 +193,71 @@ impl Measurement {
     }
 }
 
+/// Statistics of a metric over the repeated runs of a benchmark.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Statistics {
+    /// The mean of the samples.
+    pub mean: f64,
+    /// The (sample) standard deviation of the samples.
+    pub stdev: f64,
+    /// The half-width of the 95% confidence interval of the mean.
+    pub confidence_interval: f64,
+}
+
+impl Statistics {
+    /// The 0.975 quantiles of the Student's t-distribution, for 1 to 30 degrees of freedom.
+    const T_QUANTILES: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+
+    /// The 0.975 quantile of the normal distribution (for more than 30 degrees of freedom).
+    const Z_QUANTILE: f64 = 1.96;
+
+    /// Compute the statistics of the specified samples. The standard deviation and confidence
+    /// interval of less than two samples are zero.
+    pub fn new(samples: &[f64]) -> Self {
+        let n = samples.len();
+        if n == 0 {
+            return Self {
+                mean: 0.0,
+                stdev: 0.0,
+                confidence_interval: 0.0,
+            };
+        }
+
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        if n == 1 {
+            return Self {
+                mean,
+                stdev: 0.0,
+                confidence_interval: 0.0,
+            };
+        }
+
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        let stdev = variance.sqrt();
+        let quantile = Self::T_QUANTILES
+            .get(n - 2)
+            .copied()
+            .unwrap_or(Self::Z_QUANTILE);
+        Self {
+            mean,
+            stdev,
+            confidence_interval: quantile * stdev / (n as f64).sqrt(),
+        }
+    }
+
+    /// Format the statistics with the specified unit.
+    fn display(&self, unit: &str) -> String {
+        format!(
+            "{:.0} ± {:.0} {unit} (stdev {:.0} {unit})",
+            self.mean, self.confidence_interval, self.stdev
+        )
+    }
+}
+
 /// The identifier of the scrapers collecting the prometheus metrics.
 type ScraperId = usize;
 
@@ -149,6 +267,35 @@ pub struct MeasurementsCollection {
     pub parameters: BenchmarkParameters,
     /// The data collected by each scraper.
     pub data: HashMap<Label, HashMap<ScraperId, Vec<Measurement>>>,
+    /// The clock offset (in seconds) of each node measured before the benchmark, if known.
+    #[serde(default)]
+    pub clock_offsets: Vec<Option<f64>>,
+    /// The cause of the failure of the benchmark (if it failed), in which case the measurements
+    /// are incomplete and should not be used as a data point.
+    #[serde(default)]
+    pub failure: Option<String>,
+    /// The number of times each node (identified by its index in the committee) was restarted
+    /// during the benchmark.
+    #[serde(default)]
+    pub restarts: HashMap<usize, usize>,
+    /// The code version and testbed of the benchmark, if recorded.
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// The anomalies detected by the alerting rules of the monitoring instance (if any) during
+    /// the benchmark.
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+    /// The panics, errors, and known bad patterns found in the log files of the nodes and
+    /// clients (if they were processed).
+    #[serde(default)]
+    pub logs: Vec<LogSummary>,
+    /// The changes of the testbed applied by the faults schedule (e.g., the start and end of a
+    /// region outage), with the time they occurred.
+    #[serde(default)]
+    pub fault_events: Vec<FaultEvent>,
+    /// The byzantine nodes (identified by their index in the committee) of the benchmark.
+    #[serde(default)]
+    pub byzantine_nodes: Vec<usize>,
 }
 
 impl MeasurementsCollection {
@@ -160,6 +307,14 @@ impl MeasurementsCollection {
         Self {
             parameters,
             data: HashMap::new(),
+            clock_offsets: Vec::new(),
+            failure: None,
+            restarts: HashMap::new(),
+            provenance: None,
+            alerts: Vec::new(),
+            logs: Vec::new(),
+            fault_events: Vec::new(),
+            byzantine_nodes: Vec::new(),
         }
     }
 
@@ -193,7 +348,28 @@ impl MeasurementsCollection {
         self.data.keys()
     }
 
-    /// Get the maximum result of a function applied to the measurements.
+    /// Return the measurement of the window of a series of measurements of a scraper that
+    /// excludes the warm-up and cool-down periods of the settings: from the last measurement
+    /// taken before the end of the warm-up, to the last measurement taken before the start of the
+    /// cool-down. Returns `None` if the window is empty.
+    fn window(&self, measurements: &[Measurement]) -> Option<Measurement> {
+        let settings = &self.parameters.settings;
+        let end = measurements
+            .last()?
+            .timestamp
+            .checked_sub(settings.cooldown)?;
+        let last = measurements.iter().rev().find(|x| x.timestamp <= end)?;
+        let first = measurements
+            .iter()
+            .rev()
+            .find(|x| x.timestamp <= settings.warmup)
+            .cloned()
+            .unwrap_or_default();
+        (last.timestamp > first.timestamp).then(|| last.since(&first))
+    }
+
+    /// Get the maximum result of a function applied to the measurements of each scraper
+    /// (excluding the warm-up and cool-down periods).
     fn max_result<T: Default + Ord>(
         &self,
         label: &Label,
@@ -201,16 +377,19 @@ impl MeasurementsCollection {
     ) -> T {
         self.all_measurements(label)
             .iter()
-            .filter_map(|x| x.last())
-            .map(function)
+            .filter_map(|x| self.window(x))
+            .map(|x| function(&x))
             .max()
             .unwrap_or_default()
     }
 
     /// Aggregate the benchmark duration of multiple data points by taking the max.
     pub fn benchmark_duration(&self) -> Duration {
-        self.labels()
-            .map(|label| self.max_result(label, |x| x.timestamp))
+        self.data
+            .values()
+            .flat_map(|x| x.values())
+            .filter_map(|x| x.last())
+            .map(|x| x.timestamp)
             .max()
             .unwrap_or_default()
     }
@@ -225,7 +404,10 @@ impl MeasurementsCollection {
     /// Aggregate the average latency of multiple data points by taking the average.
     pub fn aggregate_average_latency(&self, label: &Label) -> Duration {
         let all_measurements = self.all_measurements(label);
-        let last_data_points: Vec<_> = all_measurements.iter().filter_map(|x| x.last()).collect();
+        let last_data_points: Vec<_> = all_measurements
+            .iter()
+            .filter_map(|x| self.window(x))
+            .collect();
         last_data_points
             .iter()
             .map(|x| x.average_latency())
@@ -239,12 +421,239 @@ impl MeasurementsCollection {
         self.max_result(label, |x| x.stdev_latency())
     }
 
+    /// The path of the json file holding the measurements of the specified benchmark.
+    pub fn path<P: AsRef<Path>>(directory: P, parameters: &BenchmarkParameters) -> PathBuf {
+        let mut file = PathBuf::from(directory.as_ref());
+        file.push(format!("measurements-{parameters:?}.json"));
+        file
+    }
+
+    /// Aggregate the specified quantile of the latency of multiple data points by taking the max.
+    pub fn aggregate_quantile_latency(&self, label: &Label, quantile: f64) -> Option<Duration> {
+        self.all_measurements(label)
+            .iter()
+            .filter_map(|x| self.window(x))
+            .filter_map(|x| x.quantile_latency(quantile))
+            .max()
+    }
+
+    /// The specified quantile of the latency during each scrape interval after the warm-up,
+    /// taking the max across scrapers and workloads. Intervals without finalized transactions
+    /// have an infinite latency.
+    pub fn interval_quantile_latencies(&self, quantile: f64) -> Vec<Duration> {
+        let warmup = self.parameters.settings.warmup;
+        let mut latencies: Vec<Duration> = Vec::new();
+        for measurements in self.data.values().flat_map(|x| x.values()) {
+            let measurements: Vec<_> = measurements
+                .iter()
+                .filter(|x| x.timestamp >= warmup)
+                .collect();
+            for (i, pair) in measurements.windows(2).enumerate() {
+                let latency = pair[1]
+                    .since(pair[0])
+                    .quantile_latency(quantile)
+                    .unwrap_or(Duration::MAX);
+                match latencies.get_mut(i) {
+                    Some(x) => *x = (*x).max(latency),
+                    None => latencies.push(latency),
+                }
+            }
+        }
+        latencies
+    }
+
+    /// The aggregate metrics of each scrape interval since the start of the benchmark: the
+    /// throughput summed across scrapers and workloads, and the latency quantiles taking the max
+    /// across them.
+    pub fn interval_summaries(&self) -> Vec<IntervalSummary> {
+        let mut summaries: Vec<IntervalSummary> = Vec::new();
+        for measurements in self.data.values().flat_map(|x| x.values()) {
+            let first = Measurement::default();
+            let previous = std::iter::once(&first).chain(measurements.iter());
+            for (i, (before, after)) in previous.zip(measurements.iter()).enumerate() {
+                let interval = after.since(before);
+                if summaries.len() == i {
+                    summaries.push(IntervalSummary::default());
+                }
+                let summary = &mut summaries[i];
+                summary.end = summary.end.max(after.timestamp);
+                summary.tps += (interval.count as u64)
+                    .checked_div(interval.timestamp.as_secs())
+                    .unwrap_or_default();
+                summary.p50_latency = summary.p50_latency.max(interval.quantile_latency(0.5));
+                summary.p99_latency = summary.p99_latency.max(interval.quantile_latency(0.99));
+            }
+        }
+        summaries
+    }
+
     /// Save the collection of measurements as a json file.
     pub fn save<P: AsRef<Path>>(&self, path: P) {
         let json = serde_json::to_string_pretty(self).expect("Cannot serialize metrics");
-        let mut file = PathBuf::from(path.as_ref());
-        file.push(format!("measurements-{:?}.json", self.parameters));
-        fs::write(file, json).unwrap();
+        fs::write(Self::path(path, &self.parameters), json).unwrap();
+    }
+
+    /// The largest (absolute) clock offset of the nodes, in seconds, if any is known.
+    pub fn max_clock_offset(&self) -> Option<f64> {
+        self.clock_offsets
+            .iter()
+            .flatten()
+            .map(|x| x.abs())
+            .reduce(f64::max)
+    }
+
+    /// The results of each workload of the benchmark.
+    pub fn results(&self) -> Vec<BenchmarkResult> {
+        let mut labels: Vec<_> = self.labels().cloned().collect();
+        labels.sort();
+        // Failed benchmarks may not have measured any workload.
+        if labels.is_empty() && self.failure.is_some() {
+            labels.push(Label::new());
+        }
+
+        labels
+            .into_iter()
+            .map(|label| BenchmarkResult {
+                benchmark: format!("{:?}", self.parameters),
+                nodes: self.parameters.nodes,
+                faults: self.parameters.settings.faults.to_string(),
+                load: self.parameters.load,
+                client_parameters: self.parameters.client_parameters.to_string(),
+                repetition: self.parameters.repetition,
+                tps: self.aggregate_tps(&label),
+                average_latency_ms: self.aggregate_average_latency(&label).as_secs_f64() * 1e3,
+                stdev_latency_ms: self.max_stdev_latency(&label).as_secs_f64() * 1e3,
+                p50_latency_ms: self
+                    .aggregate_quantile_latency(&label, 0.5)
+                    .map(|x| x.as_secs_f64() * 1e3),
+                p99_latency_ms: self
+                    .aggregate_quantile_latency(&label, 0.99)
+                    .map(|x| x.as_secs_f64() * 1e3),
+                failure: self.failure.clone(),
+                workload: label,
+            })
+            .collect()
+    }
+
+    /// The metrics of the specified workload measured by each scraper (excluding the warm-up and
+    /// cool-down periods). Load generators are collocated with the nodes (unless the testbed
+    /// dedicates instances to them), so scraper `i` measures the workload of node `i`.
+    pub fn node_metrics(&self, label: &Label) -> Vec<NodeMetrics> {
+        let Some(data) = self.data.get(label) else {
+            return Vec::new();
+        };
+        let mut scrapers: Vec<_> = data.iter().collect();
+        scrapers.sort_by_key(|(id, _)| **id);
+
+        // Consecutive measurements further apart than two scrape intervals missed a scrape.
+        let max_interval = 2 * self.parameters.settings.scrape_interval;
+        scrapers
+            .into_iter()
+            .map(|(scraper, measurements)| {
+                let window = self.window(measurements).unwrap_or_default();
+                let millis = |x: Option<Duration>| x.map(|x| x.as_secs_f64() * 1e3);
+                NodeMetrics {
+                    scraper: *scraper,
+                    tps: (window.count as u64)
+                        .checked_div(window.timestamp.as_secs())
+                        .unwrap_or_default(),
+                    average_latency_ms: window.average_latency().as_secs_f64() * 1e3,
+                    p50_latency_ms: millis(window.quantile_latency(0.5)),
+                    p99_latency_ms: millis(window.quantile_latency(0.99)),
+                    scrape_gaps: measurements
+                        .windows(2)
+                        .filter(|x| x[1].timestamp.saturating_sub(x[0].timestamp) > max_interval)
+                        .count(),
+                    restarts: self.restarts.get(scraper).copied().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    /// The metrics of each workload measured by each scraper during each scrape interval.
+    pub fn interval_metrics(&self) -> Vec<IntervalMetrics> {
+        let mut labels: Vec<_> = self.labels().collect();
+        labels.sort();
+        let mut metrics = Vec::new();
+        for label in labels {
+            let mut scrapers: Vec<_> = self.data[label].iter().collect();
+            scrapers.sort_by_key(|(id, _)| **id);
+            for (scraper, measurements) in scrapers {
+                let first = Measurement::default();
+                let previous = std::iter::once(&first).chain(measurements.iter());
+                for (before, after) in previous.zip(measurements.iter()) {
+                    let interval = after.since(before);
+                    let duration = interval.timestamp.as_secs();
+                    metrics.push(IntervalMetrics {
+                        workload: label.clone(),
+                        scraper: *scraper,
+                        start_s: before.timestamp.as_secs(),
+                        end_s: after.timestamp.as_secs(),
+                        tps: (interval.count as u64)
+                            .checked_div(duration)
+                            .unwrap_or_default(),
+                        average_latency_ms: interval.average_latency().as_secs_f64() * 1e3,
+                        p99_latency_ms: interval
+                            .quantile_latency(0.99)
+                            .map(|x| x.as_secs_f64() * 1e3),
+                    });
+                }
+            }
+        }
+        metrics
+    }
+
+    /// Export the aggregate and per-interval metrics as json.
+    pub fn to_json(&self) -> String {
+        let export = Export {
+            aggregate: self.results(),
+            intervals: self.interval_metrics(),
+        };
+        serde_json::to_string_pretty(&export).expect("Cannot serialize metrics")
+    }
+
+    /// Export the aggregate and per-interval metrics as csv, one row per workload and interval.
+    /// The rows of the aggregate metrics have the scraper 'all' and span the whole benchmark
+    /// (excluding the warm-up and cool-down periods).
+    pub fn to_csv(&self) -> String {
+        let field = |x: &str| {
+            if x.contains([',', '"', '\n']) {
+                format!("\"{}\"", x.replace('"', "\"\""))
+            } else {
+                x.to_string()
+            }
+        };
+        let latency = |x: Option<f64>| x.map(|x| format!("{x:.3}")).unwrap_or_default();
+
+        let mut lines = vec![
+            "workload,scraper,start_s,end_s,tps,average_latency_ms,p99_latency_ms".to_string(),
+        ];
+        let settings = &self.parameters.settings;
+        let end = self.benchmark_duration().saturating_sub(settings.cooldown);
+        for result in self.results() {
+            lines.push(format!(
+                "{},all,{},{},{},{:.3},{}",
+                field(&result.workload),
+                settings.warmup.as_secs(),
+                end.as_secs(),
+                result.tps,
+                result.average_latency_ms,
+                latency(result.p99_latency_ms)
+            ));
+        }
+        for x in self.interval_metrics() {
+            lines.push(format!(
+                "{},{},{},{},{},{:.3},{}",
+                field(&x.workload),
+                x.scraper,
+                x.start_s,
+                x.end_s,
+                x.tps,
+                x.average_latency_ms,
+                latency(x.p99_latency_ms)
+            ));
+        }
+        lines.join("\n") + "\n"
     }
 
     /// Display a summary of the measurements.
@@ -259,8 +668,33 @@ impl MeasurementsCollection {
         table.add_row(row![bH2->""]);
         table.add_row(row![b->"Nodes:", self.parameters.nodes]);
         table.add_row(row![b->"Faults:", self.parameters.settings.faults]);
+        if let Some(byzantine) = &self.parameters.settings.byzantine {
+            let nodes = &self.byzantine_nodes;
+            table.add_row(row![b->"Byzantine:", format!("{byzantine} (nodes {nodes:?})")]);
+        }
         table.add_row(row![b->"Load:", format!("{} tx/s", self.parameters.load)]);
         table.add_row(row![b->"Duration:", format!("{} s", duration.as_secs())]);
+        if let Some(cause) = &self.failure {
+            table.add_row(row![b->"Failed:", cause]);
+        }
+        if let Some(offset) = self.max_clock_offset() {
+            table.add_row(row![b->"Clock offset (max):", format!("{:.1} ms", offset * 1e3)]);
+        }
+        for alert in &self.alerts {
+            let time = format!("{}s", alert.seen.as_secs());
+            table.add_row(row![b->format!("Alert ({time}):"), alert]);
+        }
+        if let Some(provenance) = &self.provenance {
+            let dirty = if provenance.dirty { " (dirty)" } else { "" };
+            table.add_row(row![bH2->""]);
+            table.add_row(row![b->"Commit:", format!("{}{dirty}", provenance.commit)]);
+            table.add_row(row![b->"Instances:", provenance.instances_summary()]);
+            if !provenance.client_placement.is_empty() {
+                table.add_row(row![b->"Load generators:", provenance.client_placement]);
+            }
+            table.add_row(row![b->"Orchestrator:", provenance.orchestrator_version]);
+            table.add_row(row![b->"Node config:", provenance.node_config.trim_end()]);
+        }
 
         let mut labels: Vec<_> = self.labels().collect();
         labels.sort();
@@ -274,21 +708,468 @@ impl MeasurementsCollection {
             table.add_row(row![b->"TPS:", format!("{total_tps} tx/s")]);
             table.add_row(row![b->"Latency (avg):", format!("{} ms", average_latency.as_millis())]);
             table.add_row(row![b->"Latency (stdev):", format!("{} ms", stdev_latency.as_millis())]);
+            if let Some(latency) = self.aggregate_quantile_latency(label, 0.99) {
+                table.add_row(row![b->"Latency (p99):", format!("{} ms", latency.as_millis())]);
+            }
         }
 
         display::newline();
         table.printstd();
         display::newline();
+
+        self.display_nodes_summary();
+    }
+
+    /// Display the contribution of each node to the aggregate metrics, to spot a single
+    /// misbehaving instance skewing them.
+    pub fn display_nodes_summary(&self) {
+        let mut labels: Vec<_> = self.labels().collect();
+        labels.sort();
+        for label in labels {
+            let metrics = self.node_metrics(label);
+            let total_tps: u64 = metrics.iter().map(|x| x.tps).sum();
+            let latency = |x: Option<f64>| x.map_or("-".into(), |x| format!("{x:.0} ms"));
+
+            let mut table = Table::new();
+            table.set_format(display::default_table_format());
+            table.set_titles(row![bH7->format!("Nodes Summary ({label})")]);
+            table.add_row(row![
+                b->"Node",
+                b->"TPS",
+                b->"Latency (avg)",
+                b->"Latency (p50)",
+                b->"Latency (p99)",
+                b->"Scrape gaps",
+                b->"Restarts"
+            ]);
+            for x in metrics {
+                let share = (x.tps as f64 / total_tps.max(1) as f64) * 100.0;
+                table.add_row(row![
+                    x.scraper,
+                    format!("{} tx/s ({share:.0}%)", x.tps),
+                    format!("{:.0} ms", x.average_latency_ms),
+                    latency(x.p50_latency_ms),
+                    latency(x.p99_latency_ms),
+                    x.scrape_gaps,
+                    x.restarts
+                ]);
+            }
+
+            table.printstd();
+            display::newline();
+        }
+    }
+}
+
+/// The criteria deciding whether a load is sustainable, based on the p99 latency of the last
+/// scrape intervals of a benchmark.
+#[derive(Clone, Copy, Debug)]
+pub struct LoadStability {
+    /// The maximum p99 latency of a sustainable load.
+    latency_bound: Duration,
+    /// The number of consecutive intervals that must meet the bound (or whose latency must grow
+    /// to detect a collapse).
+    intervals: usize,
+}
+
+impl LoadStability {
+    /// The quantile of the latency to compare with the bound.
+    const QUANTILE: f64 = 0.99;
+
+    /// Make new stability criteria.
+    pub fn new(latency_bound: Duration, intervals: usize) -> Self {
+        Self {
+            latency_bound,
+            intervals: intervals.max(1),
+        }
+    }
+
+    /// The latencies of the last intervals considered by the criteria, if enough intervals
+    /// elapsed.
+    fn last_latencies(&self, measurements: &MeasurementsCollection) -> Option<Vec<Duration>> {
+        let latencies = measurements.interval_quantile_latencies(Self::QUANTILE);
+        let start = latencies.len().checked_sub(self.intervals)?;
+        Some(latencies[start..].to_vec())
+    }
+
+    /// Whether the benchmark sustained its load: it did not fail and its p99 latency stayed
+    /// under the bound during its last consecutive intervals.
+    pub fn is_stable(&self, measurements: &MeasurementsCollection) -> bool {
+        measurements.failure.is_none()
+            && self
+                .last_latencies(measurements)
+                .is_some_and(|x| x.iter().all(|latency| *latency <= self.latency_bound))
+    }
+
+    /// Whether the latency of the benchmark collapsed: it grew during its last consecutive
+    /// intervals, ending above the bound. The load is then unsustainable, and there is no
+    /// point in running the benchmark any longer.
+    pub fn collapsed(&self, measurements: &MeasurementsCollection) -> bool {
+        self.last_latencies(measurements).is_some_and(|x| {
+            x.windows(2).all(|pair| pair[0] < pair[1])
+                && x.last()
+                    .is_some_and(|latency| *latency > self.latency_bound)
+        })
+    }
+}
+
+/// The results of a benchmark of a sweep for one workload.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BenchmarkResult {
+    /// The benchmark (identified by the debug representation of its parameters).
+    pub benchmark: String,
+    /// The committee size.
+    pub nodes: usize,
+    /// The faults applied to the nodes.
+    pub faults: String,
+    /// The total load (tx/s) submitted to the system.
+    pub load: usize,
+    /// The client's configuration parameters (e.g., the transaction size).
+    pub client_parameters: String,
+    /// The index of the run of the benchmark.
+    pub repetition: usize,
+    /// The workload.
+    pub workload: Label,
+    /// The throughput (tx/s).
+    pub tps: u64,
+    /// The average latency (in milliseconds).
+    pub average_latency_ms: f64,
+    /// The standard deviation of the latency (in milliseconds).
+    pub stdev_latency_ms: f64,
+    /// The median latency (in milliseconds), if known.
+    #[serde(default)]
+    pub p50_latency_ms: Option<f64>,
+    /// The p99 latency (in milliseconds), if known.
+    #[serde(default)]
+    pub p99_latency_ms: Option<f64>,
+    /// The cause of the failure of the benchmark, if it failed.
+    pub failure: Option<String>,
+}
+
+/// The metrics of a workload measured by a scraper over the whole benchmark (excluding the
+/// warm-up and cool-down periods).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NodeMetrics {
+    /// The scraper that collected the measurements.
+    pub scraper: ScraperId,
+    /// The throughput (tx/s).
+    pub tps: u64,
+    /// The average latency (in milliseconds).
+    pub average_latency_ms: f64,
+    /// The median latency (in milliseconds), if known.
+    pub p50_latency_ms: Option<f64>,
+    /// The p99 latency (in milliseconds), if known.
+    pub p99_latency_ms: Option<f64>,
+    /// The number of scrapes missed.
+    pub scrape_gaps: usize,
+    /// The number of times the node was restarted.
+    pub restarts: usize,
+}
+
+/// The metrics of a scrape interval aggregated across scrapers and workloads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IntervalSummary {
+    /// The end of the interval (since the beginning of the benchmark).
+    pub end: Duration,
+    /// The throughput (tx/s).
+    pub tps: u64,
+    /// The median latency, if known.
+    pub p50_latency: Option<Duration>,
+    /// The p99 latency, if known.
+    pub p99_latency: Option<Duration>,
+}
+
+/// The metrics of a workload measured by a scraper during a scrape interval.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IntervalMetrics {
+    /// The workload.
+    pub workload: Label,
+    /// The scraper that collected the measurements.
+    pub scraper: ScraperId,
+    /// The start of the interval (in seconds since the beginning of the benchmark).
+    pub start_s: u64,
+    /// The end of the interval (in seconds since the beginning of the benchmark).
+    pub end_s: u64,
+    /// The throughput (tx/s) during the interval.
+    pub tps: u64,
+    /// The average latency (in milliseconds) during the interval.
+    pub average_latency_ms: f64,
+    /// The p99 latency (in milliseconds) during the interval, if any transaction finalized.
+    pub p99_latency_ms: Option<f64>,
+}
+
+/// The machine-readable export of a collection of measurements.
+#[derive(Serialize)]
+struct Export {
+    /// The aggregate metrics of each workload.
+    aggregate: Vec<BenchmarkResult>,
+    /// The metrics of each workload during each scrape interval.
+    intervals: Vec<IntervalMetrics>,
+}
+
+/// Save the results of all workloads of the specified benchmarks of a sweep in a single json
+/// file of the specified directory, and return its path.
+pub fn save_sweep_results<P: AsRef<Path>>(
+    directory: P,
+    runs: &[MeasurementsCollection],
+) -> Result<PathBuf, std::io::Error> {
+    let results: Vec<_> = runs.iter().flat_map(|x| x.results()).collect();
+    let json = serde_json::to_string_pretty(&results)?;
+    fs::create_dir_all(&directory)?;
+    let path = directory.as_ref().join("results.json");
+    fs::write(&path, json)?;
+    Ok(path)
+}
+
+/// Display a summary of the repeated runs of the same benchmark: the mean, standard deviation,
+/// and 95% confidence interval of the throughput and latency (average, p50, p95, and p99) of each
+/// workload. Failed runs are ignored.
+pub fn display_repetitions_summary(runs: &[MeasurementsCollection]) {
+    let runs: Vec<_> = runs.iter().filter(|x| x.failure.is_none()).collect();
+    let Some(first) = runs.first() else {
+        return;
+    };
+
+    let mut table = Table::new();
+    table.set_format(display::default_table_format());
+
+    table.set_titles(row![bH2->"Repeated Benchmark Summary"]);
+    table.add_row(row![b->"Benchmark type:", first.parameters.node_parameters]);
+    table.add_row(row![bH2->""]);
+    table.add_row(row![b->"Nodes:", first.parameters.nodes]);
+    table.add_row(row![b->"Faults:", first.parameters.settings.faults]);
+    table.add_row(row![b->"Load:", format!("{} tx/s", first.parameters.load)]);
+    table.add_row(row![b->"Runs:", runs.len()]);
+
+    let mut labels: Vec<_> = runs.iter().flat_map(|x| x.labels()).collect();
+    labels.sort();
+    labels.dedup();
+    for label in labels {
+        let with_label: Vec<_> = runs.iter().filter(|x| x.data.contains_key(label)).collect();
+        let tps: Vec<_> = with_label
+            .iter()
+            .map(|x| x.aggregate_tps(label) as f64)
+            .collect();
+        let latency: Vec<_> = with_label
+            .iter()
+            .map(|x| x.aggregate_average_latency(label).as_secs_f64() * 1e3)
+            .collect();
+
+        table.add_row(row![bH2->""]);
+        table.add_row(row![b->"Workload:", label]);
+        table.add_row(row![b->"TPS:", Statistics::new(&tps).display("tx/s")]);
+        table.add_row(row![b->"Latency (avg):", Statistics::new(&latency).display("ms")]);
+        for (name, quantile) in [("p50", 0.5), ("p95", 0.95), ("p99", 0.99)] {
+            let latency: Vec<_> = with_label
+                .iter()
+                .filter_map(|x| x.aggregate_quantile_latency(label, quantile))
+                .map(|x| x.as_secs_f64() * 1e3)
+                .collect();
+            let statistics = Statistics::new(&latency).display("ms");
+            table.add_row(row![b->format!("Latency ({name}):"), statistics]);
+        }
     }
+
+    display::newline();
+    table.printstd();
+    display::newline();
 }
 
 #[cfg(test)]
 mod test {
     use std::{collections::HashMap, time::Duration};
 
-    use super::{BenchmarkParameters, Measurement, MeasurementsCollection};
+    use super::{
+        BenchmarkParameters, LoadStability, Measurement, MeasurementsCollection, Statistics,
+    };
     use crate::protocol::test_protocol_metrics::TestProtocolMetrics;
 
+    #[test]
+    fn statistics() {
+        let statistics = Statistics::new(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert!((statistics.mean - 3.0).abs() < 1e-9);
+        assert!((statistics.stdev - 2.5_f64.sqrt()).abs() < 1e-9);
+        let confidence_interval = 2.776 * 2.5_f64.sqrt() / 5_f64.sqrt();
+        assert!((statistics.confidence_interval - confidence_interval).abs() < 1e-9);
+
+        let statistics = Statistics::new(&[42.0]);
+        assert!((statistics.mean - 42.0).abs() < 1e-9);
+        assert_eq!(statistics.confidence_interval, 0.0);
+    }
+
+    #[test]
+    fn warmup_and_cooldown() {
+        let mut parameters = BenchmarkParameters::new_for_tests();
+        parameters.settings.warmup = Duration::from_secs(10);
+        parameters.settings.cooldown = Duration::from_secs(10);
+        let mut aggregator = MeasurementsCollection::new(parameters);
+
+        // A slow start and end around 100 tx/s at 100 ms.
+        let series = [
+            (10, 10, 10.0),
+            (20, 1010, 110.0),
+            (30, 2010, 210.0),
+            (40, 2020, 220.0),
+        ];
+        for (timestamp, count, sum) in series {
+            let measurement = Measurement {
+                timestamp: Duration::from_secs(timestamp),
+                buckets: HashMap::new(),
+                sum: Duration::from_secs_f64(sum),
+                count,
+                squared_sum: 0.0,
+            };
+            aggregator.add(0, "owned".into(), measurement);
+        }
+
+        let label = "owned".to_string();
+        assert_eq!(aggregator.aggregate_tps(&label), 100);
+        assert_eq!(
+            aggregator.aggregate_average_latency(&label),
+            Duration::from_millis(100)
+        );
+        assert_eq!(aggregator.benchmark_duration(), Duration::from_secs(40));
+    }
+
+    #[test]
+    fn quantile_latency() {
+        let buckets = [("0.5", 50), ("1", 98), ("2", 100), ("inf", 100)];
+        let data = Measurement {
+            timestamp: Duration::from_secs(10),
+            buckets: buckets.iter().map(|(id, n)| (id.to_string(), *n)).collect(),
+            sum: Duration::from_secs(60),
+            count: 100,
+            squared_sum: 0.0,
+        };
+
+        assert_eq!(data.quantile_latency(0.5), Some(Duration::from_millis(500)));
+        assert_eq!(data.quantile_latency(0.9), Some(Duration::from_secs(1)));
+        assert_eq!(data.quantile_latency(0.99), Some(Duration::from_secs(2)));
+        assert_eq!(Measurement::default().quantile_latency(0.99), None);
+    }
+
+    #[test]
+    fn load_stability() {
+        let stability = LoadStability::new(Duration::from_secs(1), 3);
+        let measurements = |latencies: &[f64]| {
+            let mut aggregator = MeasurementsCollection::new(BenchmarkParameters::new_for_tests());
+            let mut buckets: HashMap<String, usize> = HashMap::new();
+            for (i, latency) in latencies.iter().enumerate() {
+                // Each interval finalizes 100 transactions, all with the specified latency.
+                for bound in ["0.5", "1", "2", "5", "inf"] {
+                    let count = buckets.entry(bound.into()).or_default();
+                    if bound.parse::<f64>().unwrap() >= *latency {
+                        *count += 100;
+                    }
+                }
+                let measurement = Measurement {
+                    timestamp: Duration::from_secs(10 * (i as u64 + 1)),
+                    buckets: buckets.clone(),
+                    sum: Duration::ZERO,
+                    count: 100 * (i + 1),
+                    squared_sum: 0.0,
+                };
+                aggregator.add(0, "owned".into(), measurement);
+            }
+            aggregator
+        };
+
+        // Stable during the last 3 intervals.
+        let aggregator = measurements(&[0.5, 2.0, 0.5, 0.5, 0.5]);
+        assert!(stability.is_stable(&aggregator));
+        assert!(!stability.collapsed(&aggregator));
+
+        // Not enough intervals.
+        let aggregator = measurements(&[0.5, 0.5, 0.5]);
+        assert!(!stability.is_stable(&aggregator));
+
+        // Latency collapse.
+        let aggregator = measurements(&[0.5, 0.5, 1.0, 2.0, 5.0]);
+        assert!(!stability.is_stable(&aggregator));
+        assert!(stability.collapsed(&aggregator));
+    }
+
+    #[test]
+    fn export() {
+        let mut aggregator = MeasurementsCollection::new(BenchmarkParameters::new_for_tests());
+        for (timestamp, count) in [(10, 1000), (20, 3000)] {
+            let measurement = Measurement {
+                timestamp: Duration::from_secs(timestamp),
+                buckets: [("1".into(), count), ("inf".into(), count)].into(),
+                sum: Duration::from_secs(count as u64 / 2),
+                count,
+                squared_sum: 0.0,
+            };
+            aggregator.add(0, "owned,x".into(), measurement);
+        }
+
+        let intervals = aggregator.interval_metrics();
+        assert_eq!(intervals.len(), 2);
+        assert_eq!((intervals[1].start_s, intervals[1].end_s), (10, 20));
+        assert_eq!(intervals[1].tps, 200);
+        assert_eq!(intervals[1].p99_latency_ms, Some(1000.0));
+
+        let csv = aggregator.to_csv();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1], "\"owned,x\",all,0,20,150,500.000,1000.000");
+        assert_eq!(lines[3], "\"owned,x\",0,10,20,200,500.000,1000.000");
+
+        let json: serde_json::Value = serde_json::from_str(&aggregator.to_json()).unwrap();
+        assert_eq!(json["aggregate"][0]["tps"], 150);
+        assert_eq!(json["intervals"][0]["tps"], 100);
+
+        // A second scraper adds to the throughput of each interval.
+        let measurement = Measurement {
+            timestamp: Duration::from_secs(10),
+            buckets: [("2".into(), 500), ("inf".into(), 500)].into(),
+            sum: Duration::from_secs(500),
+            count: 500,
+            squared_sum: 0.0,
+        };
+        aggregator.add(1, "owned,x".into(), measurement);
+        let summaries = aggregator.interval_summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].tps, 150);
+        assert_eq!(summaries[0].p99_latency, Some(Duration::from_secs(2)));
+        assert_eq!(
+            (summaries[1].end, summaries[1].tps),
+            (Duration::from_secs(20), 200)
+        );
+    }
+
+    #[test]
+    fn node_metrics() {
+        let mut parameters = BenchmarkParameters::new_for_tests();
+        parameters.settings.scrape_interval = Duration::from_secs(10);
+        let mut aggregator = MeasurementsCollection::new(parameters);
+        aggregator.restarts.insert(1, 2);
+
+        // Node 1 is slower and missed a scrape.
+        let series = [(0, [10, 20, 30]), (1, [10, 20, 50])];
+        for (scraper, timestamps) in series {
+            for timestamp in timestamps {
+                let count = 100 * timestamp / (scraper + 1);
+                let measurement = Measurement {
+                    timestamp: Duration::from_secs(timestamp as u64),
+                    buckets: [("1".into(), count), ("inf".into(), count)].into(),
+                    sum: Duration::from_millis(100 * (scraper as u64 + 1) * count as u64),
+                    count,
+                    squared_sum: 0.0,
+                };
+                aggregator.add(scraper, "owned".into(), measurement);
+            }
+        }
+
+        let metrics = aggregator.node_metrics(&"owned".to_string());
+        assert_eq!(metrics.len(), 2);
+        assert_eq!((metrics[0].tps, metrics[1].tps), (100, 50));
+        assert_eq!(metrics[0].average_latency_ms, 100.0);
+        assert_eq!(metrics[1].average_latency_ms, 200.0);
+        assert_eq!((metrics[0].scrape_gaps, metrics[1].scrape_gaps), (0, 1));
+        assert_eq!((metrics[0].restarts, metrics[1].restarts), (0, 2));
+    }
+
     #[test]
     fn average_latency() {
         let data = Measurement {
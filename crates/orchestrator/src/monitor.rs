@@ -1,7 +1,15 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{fs, net::SocketAddr, path::PathBuf};
+use std::{
+    fmt::Display,
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     benchmark::BenchmarkParameters,
@@ -64,8 +72,18 @@ impl Monitor {
         Ok(())
     }
 
-    /// Start grafana on the dedicated motoring machine.
-    pub async fn start_grafana(&self) -> MonitorResult<()> {
+    /// Start grafana on the dedicated motoring machine, provisioned with the dashboards of the
+    /// repository (parameterized by the specified testbed).
+    pub async fn start_grafana(&self, testbed: &str) -> MonitorResult<()> {
+        // Upload the dashboards.
+        for (name, template) in Grafana::DASHBOARDS {
+            let path = Grafana::dashboard_upload_path(name);
+            let dashboard = Grafana::dashboard(template, testbed);
+            self.ssh_manager
+                .upload(&self.instance, &path, dashboard.as_bytes())
+                .await?;
+        }
+
         // Configure and reload grafana.
         let instance = std::iter::once(self.instance.clone());
         let commands = Grafana::setup_commands();
@@ -76,20 +94,115 @@ impl Monitor {
         Ok(())
     }
 
+    /// Dump the time series scraped by prometheus between the specified times (the metrics of
+    /// the nodes and the resource usage of their instances) as json, to analyze them after the
+    /// testbed is gone.
+    pub async fn dump_prometheus(
+        &self,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> MonitorResult<String> {
+        let instance = [self.instance.clone()];
+        let command = Prometheus::dump_command(start, end);
+        let stdio = self
+            .ssh_manager
+            .execute(instance, command, CommandContext::default())
+            .await?;
+        Ok(stdio.into_iter().map(|(stdout, _)| stdout).collect())
+    }
+
+    /// The alerts currently firing on prometheus.
+    pub async fn firing_alerts(&self) -> MonitorResult<Vec<Alert>> {
+        let instance = [self.instance.clone()];
+        let command = Prometheus::alerts_command();
+        let stdio = self
+            .ssh_manager
+            .execute(instance, command, CommandContext::default())
+            .await?;
+        let stdout: String = stdio.into_iter().map(|(stdout, _)| stdout).collect();
+        Ok(Prometheus::parse_alerts(&stdout))
+    }
+
     /// The public address of the grafana instance.
     pub fn grafana_address(&self) -> String {
         format!("http://{}:{}", self.instance.main_ip, Grafana::DEFAULT_PORT)
     }
 }
 
+/// An anomaly of a benchmark detected by the alerting rules of prometheus.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Alert {
+    /// The name of the alerting rule.
+    pub name: String,
+    /// The scrape job of the anomalous instance (e.g., 'instance-node-3').
+    pub job: String,
+    /// The description of the anomaly.
+    pub summary: String,
+    /// The time (since the beginning of the benchmark) the orchestrator first saw the alert.
+    #[serde(default)]
+    pub seen: Duration,
+}
+
+impl Alert {
+    /// Whether the two alerts report the same anomaly of the same instance.
+    pub fn same_anomaly(&self, other: &Self) -> bool {
+        self.name == other.name && self.job == other.job
+    }
+}
+
+impl Display for Alert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} on {}: {}", self.name, self.job, self.summary)
+    }
+}
+
+/// An alerting rule of prometheus.
+struct AlertingRule {
+    /// The name of the rule.
+    name: &'static str,
+    /// The promql expression of the rule, selecting the anomalous series.
+    expr: &'static str,
+    /// How long the expression must hold before the alert fires (if not immediately).
+    duration: Option<&'static str>,
+    /// The description of the anomaly.
+    summary: &'static str,
+}
+
 /// Generate the commands to setup prometheus on the given instances.
 pub struct Prometheus;
 
 impl Prometheus {
     /// The default prometheus configuration path.
     const DEFAULT_PROMETHEUS_CONFIG_PATH: &'static str = "/etc/prometheus/prometheus.yml";
+    /// The path of the alerting rules.
+    const RULES_PATH: &'static str = "/etc/prometheus/testbed-rules.yml";
     /// The default prometheus port.
     pub const DEFAULT_PORT: u16 = 9090;
+    /// The interval (in seconds) between two scrapes of the metrics.
+    const SCRAPE_INTERVAL_SECS: u64 = 5;
+
+    /// The alerting rules detecting the anomalies of a benchmark.
+    const ALERTING_RULES: [AlertingRule; 3] = [
+        AlertingRule {
+            name: "NoCommits",
+            expr: "changes(last_committed_round[30s]) == 0",
+            duration: Some("30s"),
+            summary: "no commit for 30s",
+        },
+        AlertingRule {
+            name: "RoundStuck",
+            expr: "changes(threshold_clock_round[30s]) == 0",
+            duration: Some("30s"),
+            summary: "round stuck for 30s",
+        },
+        AlertingRule {
+            name: "DiskFull",
+            expr: "100 * (1 - node_filesystem_avail_bytes{fstype!~\"tmpfs|overlay|squashfs\"} \
+                / node_filesystem_size_bytes{fstype!~\"tmpfs|overlay|squashfs\"}) > 90",
+            duration: None,
+            summary: "disk above 90%",
+        },
+    ];
 
     /// The commands to install prometheus.
     pub fn install_commands() -> Vec<&'static str> {
@@ -102,7 +215,7 @@ impl Prometheus {
     /// Generate the commands to update the prometheus configuration and restart prometheus.
     pub fn setup_commands<I, P>(
         nodes: I,
-        _clients: I,
+        clients: I,
         protocol: &P,
         parameters: &BenchmarkParameters,
     ) -> String
@@ -113,7 +226,8 @@ impl Prometheus {
         // Generate the prometheus configuration.
         let mut config = vec![Self::global_configuration()];
 
-        let nodes_metrics_path = protocol.nodes_metrics_path(nodes, parameters);
+        let nodes: Vec<_> = nodes.into_iter().collect();
+        let nodes_metrics_path = protocol.nodes_metrics_path(nodes.clone(), parameters);
         for (i, (_, nodes_metrics_path)) in nodes_metrics_path.into_iter().enumerate() {
             let id = format!("node-{i}");
             let scrape_config = Self::scrape_configuration(&id, &nodes_metrics_path);
@@ -128,11 +242,85 @@ impl Prometheus {
         //     config.push(scrape_config);
         // }
 
+        // Scrape the host metrics of the instances dedicated to the load generators.
+        let dedicated_clients = clients.into_iter().filter(|x| !nodes.contains(x));
+        for (i, instance) in dedicated_clients.enumerate() {
+            let id = format!("client-{i}");
+            let scrape_config = Self::node_exporter_configuration(&id, instance.main_ip.into());
+            config.push(scrape_config);
+        }
+
         // Make the command to configure and restart prometheus.
         format!(
-            "sudo echo \"{}\" > {} && sudo service prometheus restart",
+            "sudo echo \"{}\" > {} && sudo echo \"{}\" > {} && sudo service prometheus restart",
             config.join("\n"),
-            Self::DEFAULT_PROMETHEUS_CONFIG_PATH
+            Self::DEFAULT_PROMETHEUS_CONFIG_PATH,
+            Self::alerting_rules().replace('"', "\\\""),
+            Self::RULES_PATH
+        )
+    }
+
+    /// Generate the alerting rules of prometheus.
+    /// NOTE: The rules file is a yaml file so spaces are important.
+    fn alerting_rules() -> String {
+        let mut rules = vec![
+            "groups:".to_string(),
+            "  - name: testbed".into(),
+            "    rules:".into(),
+        ];
+        for rule in &Self::ALERTING_RULES {
+            rules.push(format!("      - alert: {}", rule.name));
+            rules.push(format!("        expr: '{}'", rule.expr));
+            if let Some(duration) = rule.duration {
+                rules.push(format!("        for: {duration}"));
+            }
+            rules.push("        annotations:".into());
+            rules.push(format!("          summary: {}", rule.summary));
+        }
+        rules.join("\n")
+    }
+
+    /// Generate the command printing (as json) the alerts currently firing.
+    pub fn alerts_command() -> String {
+        format!(
+            "curl -sf http://localhost:{}/api/v1/alerts",
+            Self::DEFAULT_PORT
+        )
+    }
+
+    /// Parse the response of the alerts api of prometheus into the alerts currently firing.
+    pub fn parse_alerts(stdout: &str) -> Vec<Alert> {
+        let Ok(response) = serde_json::from_str::<serde_json::Value>(stdout) else {
+            return Vec::new();
+        };
+        let Some(alerts) = response["data"]["alerts"].as_array() else {
+            return Vec::new();
+        };
+        let field = |x: &serde_json::Value| x.as_str().unwrap_or_default().to_string();
+        alerts
+            .iter()
+            .filter(|x| x["state"] == "firing")
+            .map(|x| Alert {
+                name: field(&x["labels"]["alertname"]),
+                job: field(&x["labels"]["job"]),
+                summary: field(&x["annotations"]["summary"]),
+                seen: Duration::ZERO,
+            })
+            .collect()
+    }
+
+    /// Generate the command printing (as json) all the time series of the testbed between the
+    /// specified times, at the resolution of the scrapes.
+    pub fn dump_command(start: SystemTime, end: SystemTime) -> String {
+        let seconds = |x: SystemTime| x.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        format!(
+            "curl -sf -G http://localhost:{}/api/v1/query_range \
+                --data-urlencode 'query={{job=~\"instance-.+\"}}' \
+                -d start={} -d end={} -d step={}s",
+            Self::DEFAULT_PORT,
+            seconds(start),
+            seconds(end),
+            Self::SCRAPE_INTERVAL_SECS
         )
     }
 
@@ -141,8 +329,10 @@ impl Prometheus {
     fn global_configuration() -> String {
         [
             "global:",
-            "  scrape_interval: 5s",
-            "  evaluation_interval: 5s",
+            &format!("  scrape_interval: {}s", Self::SCRAPE_INTERVAL_SECS),
+            &format!("  evaluation_interval: {}s", Self::SCRAPE_INTERVAL_SECS),
+            "rule_files:",
+            &format!("  - {}", Self::RULES_PATH),
             "scrape_configs:",
         ]
         .join("\n")
@@ -163,10 +353,20 @@ impl Prometheus {
             "    static_configs:",
             "      - targets:",
             &format!("        - {ip}:{port}"),
+            &Self::node_exporter_configuration(id, ip),
+        ]
+        .join("\n")
+    }
+
+    /// Generate the prometheus configuration scraping the host metrics (cpu, memory, disk, and
+    /// network) exported by node exporter on the given instance.
+    /// NOTE: The configuration file is a yaml file so spaces are important.
+    fn node_exporter_configuration(id: &str, ip: IpAddr) -> String {
+        [
             &format!("  - job_name: instance-node-exporter-{id}"),
             "    static_configs:",
             "      - targets:",
-            &format!("        - {ip}:9200"),
+            &format!("        - {ip}:{}", NodeExporter::DEFAULT_PORT),
         ]
         .join("\n")
     }
@@ -177,9 +377,32 @@ pub struct Grafana;
 impl Grafana {
     /// The path to the datasources directory.
     const DATASOURCES_PATH: &'static str = "/etc/grafana/provisioning/datasources";
+    /// The path to the dashboard providers directory.
+    const DASHBOARD_PROVIDERS_PATH: &'static str = "/etc/grafana/provisioning/dashboards";
+    /// The directory holding the provisioned dashboards.
+    const DASHBOARDS_PATH: &'static str = "/var/lib/grafana/dashboards/testbed";
+    /// The uid of the prometheus datasource of the testbed.
+    const DATASOURCE_UID: &'static str = "Fixed-UID-testbed";
     /// The default grafana port.
     pub const DEFAULT_PORT: u16 = 3000;
 
+    /// The dashboards provisioned on grafana (name and template). The templates refer to the
+    /// datasource as '@datasource@' and to the testbed as '@testbed@'.
+    const DASHBOARDS: [(&'static str, &'static str); 3] = [
+        (
+            "consensus-overview",
+            include_str!("../assets/dashboards/consensus-overview.json"),
+        ),
+        (
+            "node-health",
+            include_str!("../assets/dashboards/node-health.json"),
+        ),
+        (
+            "network-traffic",
+            include_str!("../assets/dashboards/network-traffic.json"),
+        ),
+    ];
+
     /// The commands to install grafana.
     pub fn install_commands() -> Vec<&'static str> {
         vec![
@@ -206,11 +429,55 @@ impl Grafana {
                 Self::datasource(),
                 Self::DATASOURCES_PATH
             ),
+            &format!("(rm -r {} || true)", Self::DASHBOARD_PROVIDERS_PATH),
+            &format!("mkdir -p {}", Self::DASHBOARD_PROVIDERS_PATH),
+            &format!(
+                "sudo echo \"{}\" > {}/testbed.yml",
+                Self::dashboard_provider(),
+                Self::DASHBOARD_PROVIDERS_PATH
+            ),
+            &format!("sudo rm -rf {}", Self::DASHBOARDS_PATH),
+            &format!("sudo mkdir -p {}", Self::DASHBOARDS_PATH),
+            &format!(
+                "sudo mv {} {}",
+                Self::dashboard_upload_path("*"),
+                Self::DASHBOARDS_PATH
+            ),
+            &format!("sudo chmod -R 755 {}", Self::DASHBOARDS_PATH),
             "sudo service grafana-server restart",
         ]
         .join(" && ")
     }
 
+    /// The path (relative to the home directory) where to upload the specified dashboard.
+    fn dashboard_upload_path(name: &str) -> String {
+        format!("grafana-dashboard-{name}.json")
+    }
+
+    /// Instantiate the specified dashboard template for the specified testbed.
+    fn dashboard(template: &str, testbed: &str) -> String {
+        template
+            .replace("@datasource@", Self::DATASOURCE_UID)
+            .replace("@testbed@", testbed)
+    }
+
+    /// Generate the content of the file provisioning the dashboards.
+    /// NOTE: The provider file is a yaml file so spaces are important.
+    fn dashboard_provider() -> String {
+        [
+            "apiVersion: 1",
+            "providers:",
+            "  - name: testbed",
+            "    orgId: 1",
+            "    folder: testbed",
+            "    type: file",
+            "    allowUiUpdates: true",
+            "    options:",
+            &format!("      path: {}", Self::DASHBOARDS_PATH),
+        ]
+        .join("\n")
+    }
+
     /// Generate the content of the datasource file for the given instance.
     /// NOTE: The datasource file is a yaml file so spaces are important.
     fn datasource() -> String {
@@ -226,7 +493,7 @@ impl Grafana {
             "    orgId: 1",
             &format!("    url: http://localhost:{}", Prometheus::DEFAULT_PORT),
             "    editable: true",
-            "    uid: Fixed-UID-testbed",
+            &format!("    uid: {}", Self::DATASOURCE_UID),
         ]
         .join("\n")
     }
@@ -313,29 +580,32 @@ impl LocalGrafana {
 struct NodeExporter;
 
 impl NodeExporter {
-    const RELEASE: &'static str = "0.18.1";
+    const RELEASE: &'static str = "1.8.2";
     const DEFAULT_PORT: u16 = 9200;
     const SERVICE_PATH: &'static str = "/etc/systemd/system/node_exporter.service";
 
     pub fn install_commands() -> Vec<String> {
-        let build = format!("node_exporter-{}.linux-amd64", Self::RELEASE);
+        // The build matches the architecture of the instance.
+        let build = format!(
+            "node_exporter-{}.linux-$(dpkg --print-architecture)",
+            Self::RELEASE
+        );
         let source = format!(
             "https://github.com/prometheus/node_exporter/releases/download/v{}/{build}.tar.gz",
             Self::RELEASE
         );
 
-        [
-            "(sudo systemctl status node_exporter && exit 0)",
+        // Skip the installation if the release is already running.
+        let installed = format!(
+            "(/usr/local/bin/node_exporter --version 2>&1 | grep -q 'version {}' \
+                && systemctl is-active --quiet node_exporter)",
+            Self::RELEASE
+        );
+        let install = [
             &format!("curl -LO {source}"),
-            &format!(
-                "tar -xvf node_exporter-{}.linux-amd64.tar.gz",
-                Self::RELEASE
-            ),
-            &format!(
-                "sudo mv node_exporter-{}.linux-amd64/node_exporter /usr/local/bin/",
-                Self::RELEASE
-            ),
-            "sudo useradd -rs /bin/false node_exporter || true",
+            &format!("tar -xvf {build}.tar.gz"),
+            &format!("sudo mv {build}/node_exporter /usr/local/bin/"),
+            "(sudo useradd -rs /bin/false node_exporter || true)",
             "sudo chmod 777 -R /etc/systemd/system/",
             &format!(
                 "sudo echo \"{}\" > {}",
@@ -343,11 +613,11 @@ impl NodeExporter {
                 Self::SERVICE_PATH
             ),
             "sudo systemctl daemon-reload",
-            "sudo systemctl start node_exporter",
+            "sudo systemctl restart node_exporter",
             "sudo systemctl enable node_exporter",
         ]
-        .map(|x| x.to_string())
-        .to_vec()
+        .join(" && ");
+        vec![format!("({installed} || ({install}))")]
     }
 
     fn service_config() -> String {
@@ -369,3 +639,78 @@ impl NodeExporter {
         .join("\n")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Grafana, NodeExporter, Prometheus};
+    use crate::{
+        benchmark::BenchmarkParameters,
+        client::Instance,
+        protocol::{mysticeti::MysticetiProtocol, ProtocolCommands},
+        settings::Settings,
+    };
+
+    #[test]
+    fn scrape_host_metrics() {
+        let instance = |id: &str, ip: [u8; 4]| Instance {
+            main_ip: ip.into(),
+            ..Instance::new_for_test(id.into())
+        };
+        let nodes = vec![instance("a", [10, 0, 0, 1]), instance("b", [10, 0, 0, 2])];
+        let clients = vec![nodes[0].clone(), instance("c", [10, 0, 0, 3])];
+        let protocol = MysticetiProtocol::new(&Settings::new_for_test());
+        let parameters = BenchmarkParameters::new_for_tests();
+
+        let command = Prometheus::setup_commands(nodes, clients, &protocol, &parameters);
+        let port = NodeExporter::DEFAULT_PORT;
+        for ip in ["10.0.0.1", "10.0.0.2", "10.0.0.3"] {
+            assert!(command.contains(&format!("- {ip}:{port}")), "{ip}");
+        }
+        assert!(command.contains("instance-node-exporter-client-0"));
+        assert!(!command.contains("instance-node-exporter-client-1"));
+    }
+
+    #[test]
+    fn alerting_rules() {
+        let rules = Prometheus::alerting_rules();
+        let lines: Vec<_> = rules.lines().collect();
+        let no_commits = lines
+            .iter()
+            .position(|x| x.ends_with("alert: NoCommits"))
+            .unwrap();
+        assert_eq!(lines[no_commits + 2], "        for: 30s");
+        let disk_full = lines
+            .iter()
+            .position(|x| x.ends_with("alert: DiskFull"))
+            .unwrap();
+        assert_eq!(lines[disk_full + 2], "        annotations:");
+    }
+
+    #[test]
+    fn parse_alerts() {
+        let stdout = r#"{"status":"success","data":{"alerts":[
+            {"labels":{"alertname":"NoCommits","job":"instance-node-1"},
+             "annotations":{"summary":"no commit for 30s"},"state":"firing"},
+            {"labels":{"alertname":"DiskFull","job":"instance-node-exporter-node-2"},
+             "annotations":{"summary":"disk above 90%"},"state":"pending"}
+        ]}}"#;
+        let alerts = Prometheus::parse_alerts(stdout);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].name, "NoCommits");
+        assert_eq!(alerts[0].job, "instance-node-1");
+        assert!(Prometheus::parse_alerts("").is_empty());
+    }
+
+    #[test]
+    fn dashboards() {
+        for (name, template) in Grafana::DASHBOARDS {
+            let dashboard = Grafana::dashboard(template, "my-testbed");
+            let json: serde_json::Value = serde_json::from_str(&dashboard).unwrap();
+            assert!(
+                json["title"].as_str().unwrap().contains("my-testbed"),
+                "{name}"
+            );
+            assert!(!dashboard.contains('@'), "{name}");
+        }
+    }
+}
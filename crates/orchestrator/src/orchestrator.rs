@@ -5,23 +5,41 @@ use std::{
     collections::{HashMap, VecDeque},
     fs,
     path::PathBuf,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
-use tokio::time::{self, Instant};
+use futures::future::{join_all, try_join_all};
+use mysticeti_core::{consistency, prometheus::CheckpointResponse, types::AuthorityIndex};
+use rand::Rng;
+use reqwest::Url;
+use tokio::{
+    process::Command,
+    time::{self, Instant},
+};
 
 use crate::{
-    benchmark::BenchmarkParameters,
+    audit::SafetyAudit,
+    benchmark::{BenchmarkParameters, LoadSearch},
     client::Instance,
-    display, ensure,
+    dashboard::{Dashboard, NodeStatus},
+    display,
+    docker::DockerDeployment,
+    ensure,
     error::{TestbedError, TestbedResult},
-    faults::CrashRecoverySchedule,
-    logs::LogsAnalyzer,
-    measurements::{Measurement, MeasurementsCollection},
+    faults::{
+        CrashRecoverySchedule, FaultEvent, FaultsType, GrayFailure, LeaderSchedule,
+        NetworkImpairment, NetworkPartition, ResourceStress,
+    },
+    health::HealthCheck,
+    logs::{LogSelection, LogsAnalyzer, Timeline},
+    measurements::{self, LoadStability, Measurement, MeasurementsCollection},
     monitor::Monitor,
-    protocol::{ProtocolCommands, ProtocolMetrics},
-    settings::Settings,
+    profile::{HeapProfiler, ProfileWindow, Profiler},
+    protocol::{ProtocolCommands, ProtocolMetrics, BINARY_PATH},
+    provenance::Provenance,
+    settings::{Architecture, ClientPlacement, Settings},
     ssh::{CommandContext, CommandStatus, SshConnectionManager},
+    sweep::SweepProgress,
 };
 
 /// An orchestrator to deploy nodes and run benchmarks on a testbed.
@@ -43,6 +61,24 @@ pub struct Orchestrator<P> {
     /// Skip the testbed configuration. Setting this value to true is dangerous and may
     /// lead to unexpected behavior.
     skip_testbed_configuration: bool,
+    /// The delay between the startup of consecutive nodes (zero to start them all at once).
+    startup_delay: Duration,
+    /// The maximum random delay added to the startup of each node.
+    startup_jitter: Duration,
+    /// Run all benchmarks rather than resuming an interrupted sweep.
+    restart_sweep: bool,
+    /// The criteria deciding whether the load of a benchmark is sustainable (when searching for
+    /// the maximum load). Benchmarks stop early when their latency collapses.
+    stability: Option<LoadStability>,
+    /// Display a live dashboard of each benchmark while it runs.
+    dashboard: bool,
+    /// The nodes (identified by their index in the committee) to profile during each benchmark.
+    profiled_nodes: Vec<usize>,
+    /// The window of each benchmark during which to profile the nodes.
+    profile_window: ProfileWindow,
+    /// The nodes (identified by their index in the committee) whose heap to profile during each
+    /// benchmark.
+    heap_profiled_nodes: Vec<usize>,
 }
 
 impl<P> Orchestrator<P> {
@@ -62,6 +98,14 @@ impl<P> Orchestrator<P> {
             ssh_manager,
             skip_testbed_update: false,
             skip_testbed_configuration: false,
+            startup_delay: Duration::ZERO,
+            startup_jitter: Duration::ZERO,
+            restart_sweep: false,
+            stability: None,
+            dashboard: false,
+            profiled_nodes: Vec::new(),
+            profile_window: ProfileWindow::default(),
+            heap_profiled_nodes: Vec::new(),
         }
     }
 
@@ -84,6 +128,55 @@ impl<P> Orchestrator<P> {
         self
     }
 
+    /// Run all benchmarks, rather than skipping those completed by a previous run of the same
+    /// sweep.
+    pub fn restart_sweep(mut self, restart_sweep: bool) -> Self {
+        self.restart_sweep = restart_sweep;
+        self
+    }
+
+    /// Display a live dashboard of each benchmark in the terminal while it runs, rather than
+    /// only its summary once it completes.
+    pub fn with_dashboard(mut self, dashboard: bool) -> Self {
+        self.dashboard = dashboard;
+        self
+    }
+
+    /// Record the CPU profile of the specified nodes (identified by their index in the
+    /// committee) during a window of each benchmark, and download their flamegraphs.
+    pub fn with_profiling(mut self, nodes: Vec<usize>, window: ProfileWindow) -> Self {
+        self.profiled_nodes = nodes;
+        self.profile_window = window;
+        self
+    }
+
+    /// Profile the heap of the specified nodes (identified by their index in the committee)
+    /// during each benchmark, and download their heap dumps. The nodes must be built with heap
+    /// profiling support.
+    pub fn with_heap_profiling(mut self, nodes: Vec<usize>) -> Self {
+        self.heap_profiled_nodes = nodes;
+        self
+    }
+
+    /// Stagger the startup of the nodes: node `i` starts after `i * delay` plus a random jitter
+    /// (up to the specified maximum), rather than all nodes starting at once.
+    pub fn with_startup_delay(mut self, delay: Duration, jitter: Duration) -> Self {
+        self.startup_delay = delay;
+        self.startup_jitter = jitter;
+        self
+    }
+
+    /// The startup offset of each of the specified number of nodes.
+    fn startup_offsets(&self, nodes: usize) -> Vec<Duration> {
+        let mut rng = rand::thread_rng();
+        (0..nodes)
+            .map(|i| {
+                let jitter = rng.gen_range(Duration::ZERO..=self.startup_jitter);
+                self.startup_delay * i as u32 + jitter
+            })
+            .collect()
+    }
+
     /// Returns the instances of the testbed on which to run the benchmarks.
     ///
     /// This function returns two vectors of instances; the first contains the instances on which to
@@ -95,10 +188,11 @@ impl<P> Orchestrator<P> {
     ) -> TestbedResult<(Vec<Instance>, Vec<Instance>, Option<Instance>)> {
         // Ensure there are enough active instances.
         let available_instances: Vec<_> = self.instances.iter().filter(|x| x.is_active()).collect();
+        let dedicated_clients = self.settings.dedicated_client_instances();
         let minimum_instances = if self.settings.monitoring {
-            parameters.nodes + self.settings.dedicated_clients + 1
+            parameters.nodes + dedicated_clients + 1
         } else {
-            parameters.nodes + self.settings.dedicated_clients
+            parameters.nodes + dedicated_clients
         };
         let required_nodes = parameters.nodes;
         ensure!(
@@ -107,10 +201,17 @@ impl<P> Orchestrator<P> {
         );
 
         // Sort the instances by region. This step ensures that the instances are selected as
-        // equally as possible from all regions.
+        // equally as possible from all regions. Instances of the hardware profiles are kept
+        // apart and only host nodes.
+        let profiles = &self.settings.hardware_profiles;
         let mut instances_by_regions = HashMap::new();
+        let mut profile_instances_by_regions = vec![HashMap::new(); profiles.len()];
         for instance in available_instances {
-            instances_by_regions
+            let queues = match profiles.iter().position(|x| x.matches(instance)) {
+                Some(i) => &mut profile_instances_by_regions[i],
+                None => &mut instances_by_regions,
+            };
+            queues
                 .entry(&instance.region)
                 .or_insert_with(VecDeque::new)
                 .push_back(instance);
@@ -122,43 +223,104 @@ impl<P> Orchestrator<P> {
             let region = &self.settings.regions[0];
             monitoring_instance = instances_by_regions
                 .get_mut(region)
-                .map(|instances| instances.pop_front().unwrap().clone());
+                .and_then(|instances| instances.pop_front())
+                .cloned();
         }
 
-        // Select the instances to host exclusively load generators.
-        let mut client_instances = Vec::new();
-        for region in self.settings.regions.iter().cycle() {
-            if client_instances.len() == self.settings.dedicated_clients {
-                break;
+        // Select the instances to host exclusively load generators. The regions of remote load
+        // generators host no nodes.
+        let mut client_instances = match &self.settings.client_placement {
+            ClientPlacement::Dedicated => {
+                self.select_from_regions(&mut instances_by_regions, dedicated_clients)?
             }
-            if let Some(regional_instances) = instances_by_regions.get_mut(region) {
-                if let Some(instance) = regional_instances.pop_front() {
-                    client_instances.push(instance.clone());
-                }
+            ClientPlacement::PerRegion { clients } => {
+                let regions = &self.settings.regions;
+                Self::select_per_region(&mut instances_by_regions, regions, *clients)?
             }
-        }
+            ClientPlacement::Collocated { .. } => Vec::new(),
+            ClientPlacement::Remote { regions, clients } => {
+                let selected =
+                    Self::select_per_region(&mut instances_by_regions, regions, *clients)?;
+                instances_by_regions.retain(|region, _| !regions.contains(*region));
+                selected
+            }
+        };
 
-        // Select the instances to host the nodes.
+        // Select the instances to host the nodes. The nodes of the hardware profiles come first
+        // (and thus get the lowest authority indices).
         let mut nodes_instances = Vec::new();
-        for region in self.settings.regions.iter().cycle() {
-            if nodes_instances.len() == required_nodes {
-                break;
-            }
-            if let Some(regional_instances) = instances_by_regions.get_mut(region) {
-                if let Some(instance) = regional_instances.pop_front() {
-                    nodes_instances.push(instance.clone());
-                }
-            }
+        for (profile, queues) in profiles.iter().zip(profile_instances_by_regions.iter_mut()) {
+            let quantity = profile
+                .instances(required_nodes)
+                .min(required_nodes - nodes_instances.len());
+            nodes_instances.extend(self.select_from_regions(queues, quantity)?);
         }
+        nodes_instances.extend(self.select_from_regions(
+            &mut instances_by_regions,
+            required_nodes - nodes_instances.len(),
+        )?);
 
         // Spawn a load generate collocated with each node if there are no instances dedicated
-        // to excursively run load generators.
-        if client_instances.is_empty() {
+        // to excursively run load generators (or only with the nodes of the placement).
+        if let ClientPlacement::Collocated { nodes } = &self.settings.client_placement {
+            client_instances = nodes
+                .iter()
+                .filter_map(|i| nodes_instances.get(*i))
+                .cloned()
+                .collect();
+        } else if client_instances.is_empty() {
             client_instances.clone_from(&nodes_instances);
         }
 
         Ok((client_instances, nodes_instances, monitoring_instance))
     }
+
+    /// Select the specified number of instances in each of the specified regions.
+    fn select_per_region(
+        instances_by_regions: &mut HashMap<&String, VecDeque<&Instance>>,
+        regions: &[String],
+        quantity: usize,
+    ) -> TestbedResult<Vec<Instance>> {
+        let mut selected = Vec::new();
+        for region in regions {
+            let available = instances_by_regions.get(region).map_or(0, |x| x.len());
+            ensure!(
+                available >= quantity,
+                TestbedError::InsufficientCapacity(quantity - available)
+            );
+            let instances = instances_by_regions.get_mut(region).unwrap();
+            selected.extend(instances.drain(..quantity).cloned());
+        }
+        Ok(selected)
+    }
+
+    /// Select the specified number of instances, picking them in turn from each region.
+    fn select_from_regions(
+        &self,
+        instances_by_regions: &mut HashMap<&String, VecDeque<&Instance>>,
+        quantity: usize,
+    ) -> TestbedResult<Vec<Instance>> {
+        let mut selected = Vec::new();
+        while selected.len() < quantity {
+            let before = selected.len();
+            for region in &self.settings.regions {
+                if selected.len() == quantity {
+                    break;
+                }
+                if let Some(instance) = instances_by_regions
+                    .get_mut(region)
+                    .and_then(|instances| instances.pop_front())
+                {
+                    selected.push(instance.clone());
+                }
+            }
+            ensure!(
+                selected.len() > before,
+                TestbedError::InsufficientCapacity(quantity - selected.len())
+            );
+        }
+        Ok(selected)
+    }
 }
 
 impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
@@ -168,6 +330,22 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
 
         let working_dir = self.settings.working_dir.display();
         let url = &self.settings.repository.url;
+
+        // Local instances share the system packages and the rust toolchain of the host.
+        if self.settings.is_local() {
+            let command = [
+                format!("mkdir -p {working_dir}"),
+                format!("(git clone {url} || true)"),
+            ]
+            .join(" && ");
+            let active = self.instances.iter().filter(|x| x.is_active()).cloned();
+            let context = CommandContext::default();
+            self.ssh_manager.execute(active, command, context).await?;
+
+            display::done();
+            return Ok(());
+        }
+
         let basic_commands = [
             "sudo apt-get update",
             "sudo apt-get -y upgrade",
@@ -179,8 +357,9 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
             // * sysstat - for getting disk stats
             // * iftop - for getting network stats
             // * libssl-dev - Required to compile the orchestrator
+            // * rsync - for synchronizing the local working tree
             // TODO: Remove libssl-dev dependency #7
-            "sudo apt-get -y install build-essential sysstat iftop libssl-dev",
+            "sudo apt-get -y install build-essential sysstat iftop libssl-dev rsync",
             "sudo apt-get -y install linux-tools-common linux-tools-generic pkg-config",
             // Install rust (non-interactive).
             "curl --proto \"=https\" --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y",
@@ -221,22 +400,114 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
     pub async fn update(&self) -> TestbedResult<()> {
         display::action("Updating all instances");
 
+        // Deploy the container image rather than compiling the codebase on every instance.
+        if let Some(image) = &self.settings.docker_image {
+            let deployment = DockerDeployment::new(image.clone());
+            if !self.ssh_manager.is_dry_run() {
+                deployment.build_and_push(&self.settings.repository).await?;
+            }
+
+            let active = self.instances.iter().filter(|x| x.is_active()).cloned();
+            let repo_name = self.settings.repository_name();
+            let context = CommandContext::new().with_execute_from_path(repo_name.into());
+            let binaries = self.protocol_commands.binaries();
+            self.ssh_manager
+                .execute(active, deployment.update_command(&binaries), context)
+                .await?;
+
+            display::done();
+            return Ok(());
+        }
+
+        // Deploy a prebuilt binary (downloaded or compiled once on the local machine) rather than
+        // compiling the codebase on every instance.
+        // Dry runs only print the commands deploying the binary.
+        let dry_run = self.ssh_manager.is_dry_run();
+        let placeholders = || {
+            let binaries = self.protocol_commands.binaries().into_iter();
+            Some(binaries.map(|name| (name, Vec::new())).collect())
+        };
+        let prebuilt = match &self.settings.release_binary {
+            Some(_) if dry_run => placeholders(),
+            Some(url) => Some(self.download_release(url).await?),
+            None if self.settings.cross_compile && dry_run => placeholders(),
+            None if self.settings.cross_compile => Some(self.cross_compile().await?),
+            None => None,
+        };
+        if let Some(binaries) = prebuilt {
+            let active: Vec<_> = self
+                .instances
+                .iter()
+                .filter(|x| x.is_active())
+                .cloned()
+                .collect();
+            let directory = format!("{}/{BINARY_PATH}", self.settings.repository_name());
+            let command = format!("mkdir -p {directory}");
+            self.ssh_manager
+                .execute(active.clone(), command, CommandContext::new())
+                .await?;
+
+            let binaries: Vec<_> = binaries
+                .iter()
+                .map(|(name, binary)| (format!("{directory}/{name}"), binary))
+                .collect();
+            let uploads = active.iter().flat_map(|instance| {
+                binaries
+                    .iter()
+                    .map(move |(path, binary)| (instance, path.as_str(), binary.as_slice()))
+            });
+            self.ssh_manager.upload_files(uploads).await?;
+
+            display::done();
+            return Ok(());
+        }
+
         // Update all active instances. This requires compiling the codebase in release (which
         // may take a long time) so we run the command in the background to avoid keeping alive
         // many ssh connections for too long.
-        let commit = &self.settings.repository.commit;
-        let command = [
-            &format!("git fetch origin {commit}"),
-            &format!("(git checkout -b {commit} || git checkout -f origin/{commit})"),
-            "source $HOME/.cargo/env",
-            "RUSTFLAGS=-Ctarget-cpu=native cargo build --release",
-        ]
-        .join(" && ");
-
         let active = self.instances.iter().filter(|x| x.is_active()).cloned();
+        let repo_name = self.settings.repository_name();
+
+        // Either synchronize the local working tree to the instances or checkout the commit.
+        let mut command = Vec::new();
+        if self.settings.sync_working_tree {
+            let local = std::env::current_dir()
+                .map_err(|e| TestbedError::WorkingTreeError(e.to_string()))?;
+            let instances: Vec<_> = active.clone().collect();
+            let syncs = instances
+                .iter()
+                .map(|instance| self.ssh_manager.sync(instance, &local, &repo_name));
+            try_join_all(syncs).await?;
+        } else {
+            let commit = &self.settings.repository.commit;
+            command.push(format!("git fetch origin {commit}"));
+            command.push(format!(
+                "(git checkout -b {commit} || git checkout -f origin/{commit})"
+            ));
+        }
+        command.push("source $HOME/.cargo/env".into());
+        let features = if self.settings.heap_profiling {
+            " --features heap-profiling"
+        } else {
+            ""
+        };
+        command.push(format!(
+            "RUSTFLAGS=-Ctarget-cpu=native cargo build --release{features}"
+        ));
+        let command = command.join(" && ");
+
+        // Print the output of the compilation as it comes (if specified).
+        if self.settings.stream_output {
+            display::newline();
+            let targets = active.map(|x| (x.main_ip.to_string(), x, command.clone()));
+            let context = CommandContext::new().with_execute_from_path(repo_name.into());
+            self.ssh_manager.execute_streamed(targets, context).await?;
+
+            display::done();
+            return Ok(());
+        }
 
         let id = "update";
-        let repo_name = self.settings.repository_name();
         let context = CommandContext::new()
             .run_background(id.into())
             .with_execute_from_path(repo_name.into());
@@ -253,6 +524,91 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         Ok(())
     }
 
+    /// Compile the codebase of the local working tree for the architecture of the instances and
+    /// return the binaries of the protocol, with their names.
+    async fn cross_compile(&self) -> TestbedResult<Vec<(&'static str, Vec<u8>)>> {
+        let target = self.settings.architecture.rust_target();
+        let binaries = self.protocol_commands.binaries();
+        let mut command = Command::new("cargo");
+        command.args(["build", "--release", "--target", target]);
+        for binary in &binaries {
+            command.args(["--bin", binary]);
+        }
+        if self.settings.heap_profiling {
+            command.args(["--features", "heap-profiling"]);
+        }
+
+        // Use the cross linker of the target (unless specified otherwise by the user).
+        let linker = "CARGO_TARGET_AARCH64_UNKNOWN_LINUX_GNU_LINKER";
+        if self.settings.architecture == Architecture::Arm64
+            && std::env::consts::ARCH != "aarch64"
+            && std::env::var_os(linker).is_none()
+        {
+            command.env(linker, "aarch64-linux-gnu-gcc");
+        }
+
+        let output = command
+            .output()
+            .await
+            .map_err(|e| TestbedError::CompilationError(format!("Failed to run cargo: {e}")))?;
+        ensure!(
+            output.status.success(),
+            TestbedError::CompilationError(String::from_utf8_lossy(&output.stderr).into_owned())
+        );
+
+        binaries
+            .into_iter()
+            .map(|name| {
+                let path: PathBuf = ["target", target, "release", name].iter().collect();
+                let binary = fs::read(&path).map_err(|e| {
+                    TestbedError::CompilationError(format!(
+                        "Failed to read {}: {e}",
+                        path.display()
+                    ))
+                })?;
+                Ok((name, binary))
+            })
+            .collect()
+    }
+
+    /// Download the prebuilt binaries of a release: the node binary from `url` and the other
+    /// binaries of the protocol from the same location.
+    async fn download_release(&self, url: &Url) -> TestbedResult<Vec<(&'static str, Vec<u8>)>> {
+        let mut binaries = Vec::new();
+        for (i, name) in self.protocol_commands.binaries().into_iter().enumerate() {
+            let url = match i {
+                0 => url.clone(),
+                _ => url
+                    .join(name)
+                    .map_err(|e| TestbedError::ReleaseDownloadError(format!("{url}: {e}")))?,
+            };
+            let error =
+                |e: reqwest::Error| TestbedError::ReleaseDownloadError(format!("{url}: {e}"));
+            let response = reqwest::get(url.clone()).await.map_err(error)?;
+            let response = response.error_for_status().map_err(error)?;
+            let binary = response.bytes().await.map_err(error)?;
+            binaries.push((name, binary.to_vec()));
+        }
+        Ok(binaries)
+    }
+
+    /// Resolve the (absolute) working directory of each instance (not resolved in dry runs).
+    async fn working_dirs(&self, instances: &[Instance]) -> TestbedResult<Vec<PathBuf>> {
+        let working_dir = self.settings.working_dir.display();
+        let command = format!("mkdir -p {working_dir} && cd {working_dir} && pwd");
+        let context = CommandContext::default();
+        Ok(self
+            .ssh_manager
+            .execute(instances.to_vec(), command, context)
+            .await?
+            .into_iter()
+            .map(|(stdout, _)| match stdout.trim() {
+                "" => self.settings.working_dir.clone(),
+                x => x.into(),
+            })
+            .collect())
+    }
+
     /// Configure the instances with the appropriate configuration files.
     pub async fn configure(&self, parameters: &BenchmarkParameters) -> TestbedResult<()> {
         display::config("Configuring instances", "");
@@ -266,28 +622,37 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
             display::config(format!("  - client {i}"), &client.ssh_address());
         }
 
-        // Generate the genesis configuration file and the keystore allowing access to gas objects.
-        let command = self
-            .protocol_commands
-            .genesis_command(nodes.iter(), parameters)
-            .await;
+        // Load generators collocated with the nodes need no instance of their own.
+        let clients: Vec<_> = clients.into_iter().filter(|x| !nodes.contains(x)).collect();
+        let mut instances = nodes.clone();
+        instances.extend(clients.clone());
+        let working_dirs = self.working_dirs(&instances).await?;
 
-        let id = "configure";
-        let repo_name = self.settings.repository_name();
-        let context = CommandContext::new()
-            .run_background(id.into())
-            .with_log_file(format!("~/{id}.log").into())
-            .with_execute_from_path(repo_name.into());
-        let mut instances = nodes;
-        if parameters.settings.dedicated_clients != 0 {
-            instances.extend(clients);
-        };
-
-        self.ssh_manager
-            .execute(instances.clone(), command, context)
-            .await?;
+        // Generate the committee, the keys, and the parameters of the nodes on the orchestrator.
+        let nodes_with_dirs = nodes.into_iter().zip(working_dirs.iter().cloned());
+        let mut files = self
+            .protocol_commands
+            .genesis_files(nodes_with_dirs, parameters);
+        // The clients only need the committee, the addresses of the nodes, and their parameters.
+        let shared = files
+            .first()
+            .map(|(_, x)| self.protocol_commands.client_files(x))
+            .unwrap_or_default();
+        files.extend(clients.into_iter().map(|x| (x, shared.clone())));
+
+        // Upload the files to the working directory of each instance.
+        let uploads: Vec<_> = files
+            .iter()
+            .zip(&working_dirs)
+            .flat_map(|((instance, files), working_dir)| {
+                files.iter().map(move |(path, content)| {
+                    let path = working_dir.join(path).display().to_string();
+                    (instance, path, content.as_bytes())
+                })
+            })
+            .collect();
         self.ssh_manager
-            .wait_for_command(instances, id, CommandStatus::Terminated)
+            .upload_files(uploads.iter().map(|(x, path, y)| (*x, path.as_str(), *y)))
             .await?;
 
         Ok(())
@@ -299,11 +664,27 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
 
         // Kill all tmux servers and delete the nodes dbs. Optionally clear logs.
         let mut command = vec!["(tmux kill-server || true)".into()];
+        if self.settings.docker_image.is_some() {
+            command.push(DockerDeployment::cleanup_command());
+        }
         for path in self.protocol_commands.db_directories() {
             command.push(format!("(rm -rf {} || true)", path.display()));
         }
         if delete_logs {
             command.push("(rm -rf ~/*log* || true)".into());
+            command.push(HeapProfiler::cleanup_command());
+        }
+        if !self.settings.network_impairments.is_empty() {
+            command.push(NetworkImpairment::cleanup_command());
+        }
+        if !self.settings.resource_stress.is_empty() {
+            command.push(ResourceStress::cleanup_command(&self.settings.working_dir));
+        }
+        if !self.settings.gray_failures.is_empty() {
+            command.push(GrayFailure::cleanup_command());
+        }
+        if self.settings.faults.partitions_network() {
+            command.push(NetworkPartition::heal_command());
         }
         let command = command.join(" ; ");
 
@@ -325,7 +706,7 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
             let monitor = Monitor::new(instance, clients, nodes, self.ssh_manager.clone());
             let commands = &self.protocol_commands;
             monitor.start_prometheus(commands, parameters).await?;
-            monitor.start_grafana().await?;
+            monitor.start_grafana(&self.settings.testbed_id).await?;
 
             display::done();
             display::config("Grafana address", monitor.grafana_address());
@@ -334,6 +715,94 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         Ok(())
     }
 
+    /// Save the time series scraped by the monitoring instance (if any) since the specified time
+    /// in the results directory, so that questions arising after the benchmark (e.g., about the
+    /// cpu usage at some point) can be answered without running it again. Failing to do so only
+    /// warns: the measurements of the benchmark are unaffected.
+    async fn save_prometheus_data(&self, parameters: &BenchmarkParameters, start: SystemTime) {
+        let Ok((clients, nodes, Some(instance))) = self.select_instances(parameters) else {
+            return;
+        };
+        display::action("Downloading prometheus data");
+        let monitor = Monitor::new(instance, clients, nodes, self.ssh_manager.clone());
+        let path = self
+            .results_directory()
+            .join(format!("prometheus-{parameters:?}.json"));
+        let result = match monitor.dump_prometheus(start, SystemTime::now()).await {
+            Ok(data) => fs::create_dir_all(self.results_directory())
+                .and_then(|_| fs::write(&path, data))
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => {
+                display::done();
+                display::config("Prometheus data", path.display());
+            }
+            Err(e) => display::warn(format!("Failed to download prometheus data: {e}")),
+        }
+    }
+
+    /// The profiler of the nodes to profile during the benchmark, if any.
+    fn profiler(&self, parameters: &BenchmarkParameters) -> TestbedResult<Option<Profiler>> {
+        if self.profiled_nodes.is_empty() {
+            return Ok(None);
+        }
+        let (_, nodes, _) = self.select_instances(parameters)?;
+        let targets = self
+            .profiled_nodes
+            .iter()
+            .filter_map(|&i| Some((format!("node-{i}"), nodes.get(i)?.clone())))
+            .collect();
+        let profiler = Profiler::new(targets, self.profile_window, self.ssh_manager.clone());
+        Ok(Some(profiler))
+    }
+
+    /// Wait for the profiles of the nodes to be recorded and save them in the results directory.
+    /// A failure to collect them does not fail the benchmark.
+    async fn save_profiles(&self, profiler: &Profiler, parameters: &BenchmarkParameters) {
+        display::action("Downloading profiles");
+        let path = self
+            .results_directory()
+            .join(format!("profiles-{parameters:?}"));
+        match profiler.download(&path).await {
+            Ok(_) => {
+                display::done();
+                display::config("Flamegraphs", path.display());
+            }
+            Err(e) => display::warn(format!("Failed to download profiles: {e}")),
+        }
+    }
+
+    /// Download the heap dumps of the heap profiled nodes (if any) into the results directory.
+    /// A failure to collect them does not fail the benchmark.
+    async fn save_heap_profiles(&self, parameters: &BenchmarkParameters) {
+        if self.heap_profiled_nodes.is_empty() {
+            return;
+        }
+        let Ok((_, nodes, _)) = self.select_instances(parameters) else {
+            return;
+        };
+        let targets = self
+            .heap_profiled_nodes
+            .iter()
+            .filter_map(|&i| Some((format!("node-{i}"), nodes.get(i)?.clone())))
+            .collect();
+        let profiler = HeapProfiler::new(targets, self.ssh_manager.clone());
+
+        display::action("Downloading heap profiles");
+        let path = self
+            .results_directory()
+            .join(format!("heap-{parameters:?}"));
+        match profiler.download(&path).await {
+            Ok(dumps) => {
+                display::done();
+                display::config("Heap profiles", format!("{dumps} in {}", path.display()));
+            }
+            Err(e) => display::warn(format!("Failed to download heap profiles: {e}")),
+        }
+    }
+
     /// Boot a node on the specified instances.
     async fn boot_nodes(
         &self,
@@ -341,23 +810,53 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         parameters: &BenchmarkParameters,
     ) -> TestbedResult<()> {
         // Run one node per instance.
-        let targets = self
+        let mut targets = self
             .protocol_commands
             .node_command(instances.clone(), parameters);
 
+        // Enable heap profiling on the selected nodes.
+        if !self.heap_profiled_nodes.is_empty() {
+            let (_, nodes, _) = self.select_instances(parameters)?;
+            for (instance, command) in &mut targets {
+                let index = nodes.iter().position(|x| x == instance);
+                if index.is_some_and(|i| self.heap_profiled_nodes.contains(&i)) {
+                    *command = format!("{} && {command}", HeapProfiler::node_command_prefix());
+                }
+            }
+        }
+
         let repo = self.settings.repository_name();
+        let log_file = self.protocol_commands.node_log_file();
         let context = CommandContext::new()
             .run_background("node".into())
-            .with_log_file("~/node.log".into())
+            .with_log_file(PathBuf::from("~").join(log_file))
             .with_execute_from_path(repo.into());
-        self.ssh_manager
-            .execute_per_instance(targets, context)
-            .await?;
+        if self.startup_delay.is_zero() && self.startup_jitter.is_zero() {
+            self.ssh_manager
+                .execute_per_instance(targets, context)
+                .await?;
+        } else {
+            // Stagger the startup of the nodes to avoid a storm of connections.
+            let offsets = self.startup_offsets(targets.len());
+            let boots = targets.into_iter().zip(offsets).map(|(target, offset)| {
+                let context = context.clone();
+                async move {
+                    time::sleep(offset).await;
+                    self.ssh_manager
+                        .execute_per_instance([target], context)
+                        .await
+                }
+            });
+            try_join_all(boots).await?;
+        }
 
         // Wait until all nodes are reachable.
         let commands = self
             .protocol_commands
             .nodes_metrics_command(instances.clone(), parameters);
+        if self.settings.stream_output {
+            return self.follow_startup(commands, parameters).await;
+        }
         let _ = tokio::time::timeout(
             Duration::from_secs(60),
             self.ssh_manager.wait_for_success(commands),
@@ -367,8 +866,168 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         Ok(())
     }
 
+    /// Print the log of the nodes as it comes until they are reachable (i.e., the specified
+    /// metrics commands succeed), or for at most a minute.
+    async fn follow_startup(
+        &self,
+        commands: Vec<(Instance, String)>,
+        parameters: &BenchmarkParameters,
+    ) -> TestbedResult<()> {
+        let (_, nodes, _) = self.select_instances(parameters)?;
+        let log_file = self.protocol_commands.node_log_file();
+        let log_file = log_file.display();
+        let targets = commands.into_iter().map(|(instance, metrics)| {
+            let index = nodes.iter().position(|x| x == &instance);
+            let name = index.map_or(instance.id.clone(), |i| format!("node-{i}"));
+            let command = format!(
+                "tail -n +1 -F ~/{log_file} & TAIL=$! ; \
+                for i in $(seq 60); do {metrics} > /dev/null 2>&1 && break ; sleep 1 ; done ; \
+                kill $TAIL"
+            );
+            (name, instance, command)
+        });
+        display::newline();
+        if let Err(e) = self
+            .ssh_manager
+            .execute_streamed(targets, CommandContext::default())
+            .await
+        {
+            display::warn(format!("Failed to follow the startup of the nodes: {e}"));
+        }
+        Ok(())
+    }
+
+    /// Emulate the network impairments of the settings (if any) on the instances of the nodes.
+    pub async fn impair_network(&self, parameters: &BenchmarkParameters) -> TestbedResult<()> {
+        let impairments = &parameters.settings.network_impairments;
+        if impairments.is_empty() {
+            return Ok(());
+        }
+        display::action("Impairing the network of the nodes");
+
+        let (_, nodes, _) = self.select_instances(parameters)?;
+        let ports = self.protocol_commands.consensus_ports(parameters);
+        let commands = NetworkImpairment::commands(impairments, &nodes, &ports);
+        let context = CommandContext::default();
+        self.ssh_manager
+            .execute_per_instance(commands, context)
+            .await?;
+
+        display::done();
+        for impairment in impairments {
+            let peers = if impairment.peers.is_empty() {
+                "all".to_string()
+            } else {
+                format!("{:?}", impairment.peers)
+            };
+            display::config(
+                format!("Nodes {:?} to {peers}", impairment.nodes),
+                impairment,
+            );
+        }
+        Ok(())
+    }
+
+    /// The instances of the nodes targeted by each resource-stress fault of the settings.
+    fn stress_targets(
+        &self,
+        parameters: &BenchmarkParameters,
+    ) -> TestbedResult<Vec<(&ResourceStress, Vec<Instance>)>> {
+        let (_, nodes, _) = self.select_instances(parameters)?;
+        let targets = parameters
+            .settings
+            .resource_stress
+            .iter()
+            .map(|stress| {
+                let instances = stress.nodes.iter().filter_map(|&i| nodes.get(i).cloned());
+                (stress, instances.collect())
+            })
+            .collect();
+        Ok(targets)
+    }
+
+    /// Install the stress tools on the nodes targeted by resource-stress faults (if any).
+    pub async fn install_stress_tools(
+        &self,
+        parameters: &BenchmarkParameters,
+    ) -> TestbedResult<()> {
+        let targets = self.stress_targets(parameters)?;
+        let mut instances: Vec<_> = targets.into_iter().flat_map(|(_, x)| x).collect();
+        instances.sort_by(|a, b| a.id.cmp(&b.id));
+        instances.dedup();
+        if instances.is_empty() {
+            return Ok(());
+        }
+
+        display::action("Installing stress tools");
+        let context = CommandContext::default();
+        self.ssh_manager
+            .execute(instances, ResourceStress::install_command(), context)
+            .await?;
+        display::done();
+        Ok(())
+    }
+
+    /// Start the resource-stress faults of the settings (if any) in the background. Each fault
+    /// waits for its window to start, so this should be called right after booting the nodes.
+    pub async fn stress_nodes(&self, parameters: &BenchmarkParameters) -> TestbedResult<()> {
+        let targets = self.stress_targets(parameters)?;
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        display::action("Scheduling resource stress");
+        let (working_dir, duration) =
+            (&self.settings.working_dir, self.settings.benchmark_duration);
+        for (i, (stress, instances)) in targets.iter().enumerate() {
+            let command = stress.command(working_dir, duration);
+            let context = CommandContext::new().run_background(ResourceStress::command_id(i));
+            self.ssh_manager
+                .execute(instances.clone(), command, context)
+                .await?;
+        }
+        display::done();
+        for (stress, _) in &targets {
+            display::config(format!("Nodes {:?}", stress.nodes), stress);
+        }
+        Ok(())
+    }
+
+    /// Start the gray failures of the settings (if any) in the background. Each failure waits
+    /// for its window to start, so this should be called right after booting the nodes.
+    pub async fn inject_gray_failures(
+        &self,
+        parameters: &BenchmarkParameters,
+    ) -> TestbedResult<()> {
+        let failures = &parameters.settings.gray_failures;
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        display::action("Scheduling gray failures");
+        let (_, nodes, _) = self.select_instances(parameters)?;
+        let (working_dir, duration) =
+            (&self.settings.working_dir, self.settings.benchmark_duration);
+        for (i, failure) in failures.iter().enumerate() {
+            let instances = failure.nodes.iter().filter_map(|&x| nodes.get(x).cloned());
+            let command = failure.command(working_dir, duration);
+            let context = CommandContext::new().run_background(GrayFailure::command_id(i));
+            self.ssh_manager
+                .execute(instances, command, context)
+                .await?;
+        }
+        display::done();
+        for failure in failures {
+            display::config(format!("Nodes {:?}", failure.nodes), failure);
+        }
+        Ok(())
+    }
+
     /// Deploy the nodes.
     pub async fn run_nodes(&self, parameters: &BenchmarkParameters) -> TestbedResult<()> {
+        // Impair the network before booting the nodes so that the whole run is affected.
+        self.impair_network(parameters).await?;
+
         display::action("\nDeploying validators");
 
         // Select the instances to run.
@@ -391,12 +1050,12 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         display::action("Setting up load generators");
 
         // Select the instances to run.
-        let (clients, _, _) = self.select_instances(parameters)?;
+        let (clients, nodes, _) = self.select_instances(parameters)?;
 
         // Deploy the load generators.
         let targets = self
             .protocol_commands
-            .client_command(clients.clone(), parameters);
+            .client_command(clients.clone(), &nodes, parameters);
 
         let repo = self.settings.repository_name();
         let context = CommandContext::new()
@@ -410,17 +1069,76 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         // Wait until all load generators are reachable.
         let commands = self
             .protocol_commands
-            .clients_metrics_command(clients, parameters);
+            .clients_metrics_command(self.metrics_instances(clients, nodes), parameters);
         self.ssh_manager.wait_for_success(commands).await;
 
         display::done();
         Ok(())
     }
 
+    /// The instances exposing the metrics of the load generators. Dedicated load generators expose
+    /// none: the nodes measure the latency of the transactions they commit.
+    fn metrics_instances(&self, clients: Vec<Instance>, nodes: Vec<Instance>) -> Vec<Instance> {
+        if self.settings.dedicated_client_instances() == 0 {
+            clients
+        } else {
+            nodes
+        }
+    }
+
+    /// Measure the clock offset of every node and ensure that none exceeds the maximum offset
+    /// of the settings (or only warn about them, if clock skew is allowed). Returns the offset
+    /// (in seconds) of each node, if known.
+    pub async fn check_clocks(
+        &self,
+        parameters: &BenchmarkParameters,
+    ) -> TestbedResult<Vec<Option<f64>>> {
+        display::action("Checking clocks synchronization");
+
+        let (_, nodes, _) = self.select_instances(parameters)?;
+        let command = HealthCheck::clock_offset_command();
+        let context = CommandContext::default();
+        let offsets: Vec<_> = self
+            .ssh_manager
+            .execute(nodes, command, context)
+            .await?
+            .iter()
+            .map(|(stdout, _)| HealthCheck::parse_clock_offset(stdout))
+            .collect();
+        display::done();
+
+        let max_offset = self.settings.max_clock_offset;
+        let skewed: Vec<_> = offsets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, offset)| offset.map(|x| (i, x)))
+            .filter(|(_, offset)| offset.abs() > max_offset.as_secs_f64())
+            .map(|(i, offset)| format!("node {i} ({:.1} ms)", offset * 1e3))
+            .collect();
+        if !skewed.is_empty() {
+            let message = format!(
+                "clock offset above {} ms: {}",
+                max_offset.as_millis(),
+                skewed.join(", ")
+            );
+            ensure!(
+                self.settings.allow_clock_skew,
+                TestbedError::ClockSkew(message)
+            );
+            display::warn(format!("Warning: {message}"));
+        }
+        let unknown = offsets.iter().filter(|x| x.is_none()).count();
+        if unknown != 0 {
+            display::warn(format!("Unknown clock offset of {unknown} nodes"));
+        }
+        Ok(offsets)
+    }
+
     /// Collect metrics from the load generators.
     pub async fn run(
         &self,
         parameters: &BenchmarkParameters,
+        clock_offsets: Vec<Option<f64>>,
     ) -> TestbedResult<MeasurementsCollection> {
         display::action(format!(
             "Scraping metrics (at least {}s)",
@@ -428,15 +1146,29 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         ));
 
         // Select the instances to run.
-        let (clients, nodes, _) = self.select_instances(parameters)?;
+        let (clients, nodes, monitoring) = self.select_instances(parameters)?;
+        let provenance = Provenance::new(parameters, &nodes, &clients);
+        let monitor = monitoring.map(|instance| {
+            Monitor::new(
+                instance,
+                clients.clone(),
+                nodes.clone(),
+                self.ssh_manager.clone(),
+            )
+        });
         let mut killed_nodes: Vec<Instance> = Vec::new();
 
         // Regularly scrape the client metrics.
         let metrics_commands = self
             .protocol_commands
-            .clients_metrics_command(clients, parameters);
+            .clients_metrics_command(self.metrics_instances(clients, nodes.clone()), parameters);
 
         let mut aggregator = MeasurementsCollection::new(parameters.clone());
+        aggregator.clock_offsets = clock_offsets;
+        if let Some(byzantine) = &parameters.settings.byzantine {
+            aggregator.byzantine_nodes = byzantine.nodes(parameters.nodes);
+        }
+        aggregator.provenance = Some(provenance);
         let mut metrics_interval = time::interval(self.settings.scrape_interval);
         metrics_interval.tick().await; // The first tick returns immediately.
 
@@ -444,6 +1176,23 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         let mut faults_schedule = CrashRecoverySchedule::new(faults_type, nodes.clone());
         let mut faults_interval = time::interval(self.settings.faults.crash_interval());
         faults_interval.tick().await; // The first tick returns immediately.
+        let leader_schedule = LeaderSchedule::new(nodes.len(), &parameters.node_parameters);
+        if let FaultsType::RegionOutage { region, .. } = &parameters.settings.faults {
+            ensure!(
+                nodes.iter().any(|x| &x.region == region),
+                TestbedError::InvalidFaults(format!("no node in region '{region}'"))
+            );
+        }
+        let mut partitioned_nodes: Vec<Instance> = Vec::new();
+
+        // The dashboard is only a view: the benchmark runs without it if the terminal fails.
+        let mut dashboard = if self.dashboard {
+            Dashboard::new(parameters)
+                .map_err(|e| display::warn(format!("Failed to start the dashboard: {e}")))
+                .ok()
+        } else {
+            None
+        };
 
         let start = Instant::now();
         loop {
@@ -451,27 +1200,86 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
                 // Scrape metrics.
                 now = metrics_interval.tick() => {
                     let elapsed = now.duration_since(start).as_secs_f64().ceil() as u64;
-                    display::status(format!("{elapsed}s"));
+                    if dashboard.is_none() {
+                        display::status(format!("{elapsed}s"));
+                    }
 
-                    let mut instances = metrics_commands.clone();
-                    instances.retain(|(instance, _)| !killed_nodes.contains(instance));
+                    // Stop the benchmark if a node crashed (outside of the faults schedule).
+                    let running: Vec<_> = nodes
+                        .iter()
+                        .filter(|instance| !killed_nodes.contains(instance))
+                        .cloned()
+                        .collect();
+                    let crashed = self.crashed_nodes(&nodes, &running).await?;
+                    if !crashed.is_empty() {
+                        aggregator.failure = Some(format!("Nodes crashed: {}", crashed.join(", ")));
+                        self.save_measurements(&aggregator);
+                        break;
+                    }
+
+                    // Scrapers keep the index of their load generator when others are down.
+                    let (scrapers, instances): (Vec<_>, Vec<_>) = metrics_commands
+                        .iter()
+                        .cloned()
+                        .enumerate()
+                        .filter(|(_, (instance, _))| !killed_nodes.contains(instance))
+                        .unzip();
 
-                    let stdio = self
+                    let stdio = match self
                         .ssh_manager
                         .execute_per_instance(instances, CommandContext::default())
-                        .await?;
+                        .await
+                    {
+                        Ok(stdio) => stdio,
+                        Err(e) => {
+                            aggregator.failure = Some(format!("Failed to scrape metrics: {e}"));
+                            self.save_measurements(&aggregator);
+                            break;
+                        }
+                    };
 
-                    for (i, (stdout, _stderr)) in stdio.iter().enumerate() {
+                    for (i, (stdout, _stderr)) in scrapers.iter().zip(stdio.iter()) {
                         for (label, measurement) in Measurement::from_prometheus::<P>(stdout) {
-                            aggregator.add(i, label, measurement);
+                            aggregator.add(*i, label, measurement);
                         }
                     }
 
-                    let results_directory = &self.settings.results_dir;
-                    let commit = &self.settings.repository.commit;
-                    let path: PathBuf = results_directory.join(&format!("results-{commit}"));
-                    fs::create_dir_all(&path).expect("Failed to create log directory");
-                    aggregator.save(path);
+                    // Report the new anomalies detected by the alerting rules (if any).
+                    let alerts = match &monitor {
+                        Some(monitor) => monitor.firing_alerts().await.unwrap_or_default(),
+                        None => Vec::new(),
+                    };
+                    for mut alert in alerts {
+                        if aggregator.alerts.iter().any(|x| x.same_anomaly(&alert)) {
+                            continue;
+                        }
+                        alert.seen = Duration::from_secs(elapsed);
+                        match &mut dashboard {
+                            Some(dashboard) => dashboard.event(format!("Alert: {alert}")),
+                            None => {
+                                display::newline();
+                                display::config("Alert", &alert);
+                            }
+                        }
+                        aggregator.alerts.push(alert);
+                    }
+                    self.save_measurements(&aggregator);
+                    if let Some(dashboard) = &mut dashboard {
+                        dashboard.update(Duration::from_secs(elapsed), &aggregator);
+                        dashboard.draw();
+                    }
+
+                    // Stop the benchmark early if its load is clearly unsustainable.
+                    if self.stability.is_some_and(|x| x.collapsed(&aggregator)) {
+                        dashboard = None;
+                        display::newline();
+                        display::config("Latency collapse", "stopping the benchmark early");
+                        break;
+                    }
+
+                    if self.settings.consistency_check {
+                        self.check_consistency(&nodes, &running, parameters).await?;
+                    }
 
                     let benchmark_duration = parameters.settings.benchmark_duration.as_secs();
                     if elapsed > benchmark_duration {
@@ -481,7 +1289,21 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
 
                 // Kill and recover nodes according to the input schedule.
                 _ = faults_interval.tick() => {
-                    let action = faults_schedule.update();
+                    let action = match &parameters.settings.faults {
+                        FaultsType::Leader { max_faults, .. } => {
+                            let running: Vec<_> = nodes
+                                .iter()
+                                .filter(|instance| !killed_nodes.contains(instance))
+                                .cloned()
+                                .collect();
+                            let leaders = match self.current_round(running, parameters).await {
+                                Some(round) => leader_schedule.upcoming(round, *max_faults),
+                                None => Vec::new(),
+                            };
+                            faults_schedule.update_leaders(&leaders)
+                        }
+                        _ => faults_schedule.update(),
+                    };
                     if !action.kill.is_empty() {
                         killed_nodes.extend(action.kill.clone());
                         self.ssh_manager.kill(action.kill.clone(), "node").await?;
@@ -489,9 +1311,45 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
                     if !action.boot.is_empty() {
                         // Monitor not yet supported for this
                         killed_nodes.retain(|instance| !action.boot.contains(instance));
+                        for instance in &action.boot {
+                            if let Some(index) = nodes.iter().position(|x| x == instance) {
+                                *aggregator.restarts.entry(index).or_default() += 1;
+                            }
+                        }
                         self.boot_nodes(action.boot.clone(), parameters).await?;
                     }
-                    if !action.kill.is_empty() || !action.boot.is_empty() {
+                    if !action.partition.is_empty() {
+                        partitioned_nodes.extend(action.partition.clone());
+                        let commands = NetworkPartition::commands(&action.partition, &nodes);
+                        self.ssh_manager
+                            .execute_per_instance(commands, CommandContext::default())
+                            .await?;
+                    }
+                    if !action.heal.is_empty() {
+                        partitioned_nodes.retain(|instance| !action.heal.contains(instance));
+                        self.heal_partition(action.heal.clone()).await?;
+                    }
+                    if !action.is_no_op() {
+                        let event = FaultEvent::new(start.elapsed(), &action, &nodes);
+                        aggregator.fault_events.push(event);
+                    }
+                    if let Some(dashboard) = &mut dashboard {
+                        for (index, instance) in nodes.iter().enumerate() {
+                            if action.kill.contains(instance) {
+                                dashboard.set_node_status(index, NodeStatus::Killed);
+                            } else if action.partition.contains(instance) {
+                                dashboard.set_node_status(index, NodeStatus::Partitioned);
+                            } else if action.boot.contains(instance)
+                                || action.heal.contains(instance)
+                            {
+                                dashboard.set_node_status(index, NodeStatus::Running);
+                            }
+                        }
+                        if !action.is_no_op() {
+                            dashboard.event(&action);
+                            dashboard.draw();
+                        }
+                    } else if !action.is_no_op() {
                         display::newline();
                         display::config("Testbed update", action);
                     }
@@ -499,14 +1357,234 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
             }
         }
 
+        // Give the terminal back before printing anything else.
+        drop(dashboard);
         display::done();
+
+        // Reconnect the nodes still partitioned (the benchmark ended during a region outage).
+        if !partitioned_nodes.is_empty() {
+            self.heal_partition(partitioned_nodes).await?;
+        }
         Ok(aggregator)
     }
 
-    /// Download the log files from the nodes and clients.
+    /// Reconnect the specified (partitioned) nodes to the other nodes.
+    async fn heal_partition(&self, instances: Vec<Instance>) -> TestbedResult<()> {
+        let command = NetworkPartition::heal_command();
+        self.ssh_manager
+            .execute(instances, command, CommandContext::default())
+            .await?;
+        Ok(())
+    }
+
+    /// The highest round reached by the specified nodes (as reported by their metrics), or
+    /// `None` if no node reports it.
+    async fn current_round(
+        &self,
+        nodes: Vec<Instance>,
+        parameters: &BenchmarkParameters,
+    ) -> Option<u64> {
+        let commands = self
+            .protocol_commands
+            .nodes_metrics_command(nodes, parameters)
+            .into_iter()
+            .map(|(instance, command)| (instance, format!("({command} || true)")));
+        let stdio = self
+            .ssh_manager
+            .execute_per_instance(commands, CommandContext::default())
+            .await
+            .ok()?;
+        stdio
+            .iter()
+            .flat_map(|(stdout, _)| stdout.lines())
+            .filter_map(|line| line.strip_prefix(P::CURRENT_ROUND)?.strip_prefix(' '))
+            .filter_map(|value| value.trim().parse::<f64>().ok())
+            .map(|round| round as u64)
+            .max()
+    }
+
+    /// The directory holding the results of the benchmarks of the current commit.
+    fn results_directory(&self) -> PathBuf {
+        let commit = &self.settings.repository.commit;
+        self.settings.results_dir.join(format!("results-{commit}"))
+    }
+
+    /// Save the measurements collected so far in the results directory.
+    fn save_measurements(&self, aggregator: &MeasurementsCollection) {
+        let path = self.results_directory();
+        fs::create_dir_all(&path).expect("Failed to create log directory");
+        aggregator.save(path);
+    }
+
+    /// Return a description of the specified running nodes whose process terminated. Nodes are
+    /// described by their index in the committee and their address.
+    async fn crashed_nodes(
+        &self,
+        nodes: &[Instance],
+        running: &[Instance],
+    ) -> TestbedResult<Vec<String>> {
+        let stdio = self
+            .ssh_manager
+            .execute(
+                running.to_vec(),
+                "(tmux ls || true)",
+                CommandContext::default(),
+            )
+            .await?;
+        let crashed = running
+            .iter()
+            .zip(stdio)
+            .filter(|(_, (stdout, _))| {
+                CommandStatus::status("node", stdout) == CommandStatus::Terminated
+            })
+            .map(|(instance, _)| {
+                let index = nodes.iter().position(|x| x == instance).unwrap_or_default();
+                format!("node {index} ({})", instance.main_ip)
+            })
+            .collect();
+        Ok(crashed)
+    }
+
+    /// Compare the commit checkpoints of the running nodes at the highest height they all
+    /// reached, and fail as soon as two of them disagree.
+    async fn check_consistency(
+        &self,
+        nodes: &[Instance],
+        running: &[Instance],
+        parameters: &BenchmarkParameters,
+    ) -> TestbedResult<()> {
+        let latest = self
+            .fetch_checkpoints(nodes, running, parameters, None)
+            .await?;
+        let height = latest
+            .iter()
+            .map(|(_, checkpoint)| checkpoint.height)
+            .min()
+            .unwrap_or_default();
+        let checkpoints = self
+            .fetch_checkpoints(nodes, running, parameters, Some(height))
+            .await?;
+        if let Some(divergence) = consistency::find_divergence(&checkpoints) {
+            return Err(TestbedError::SafetyViolation(divergence.to_string()));
+        }
+        Ok(())
+    }
+
+    /// The checkpoints of the running nodes, along with their authority. The nodes that did not
+    /// answer (or with an invalid answer) are reported, and it fails if fewer than two
+    /// checkpoints are left to compare.
+    async fn fetch_checkpoints(
+        &self,
+        nodes: &[Instance],
+        running: &[Instance],
+        parameters: &BenchmarkParameters,
+        height: Option<u64>,
+    ) -> TestbedResult<Vec<(AuthorityIndex, CheckpointResponse)>> {
+        // Nodes keep their authority when others are down.
+        let (authorities, commands): (Vec<_>, Vec<_>) = self
+            .protocol_commands
+            .checkpoint_command(nodes.to_vec(), parameters, height)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (instance, _))| running.contains(instance))
+            .unzip();
+        let handles = self
+            .ssh_manager
+            .run_per_instance(commands, CommandContext::default());
+
+        let mut checkpoints = Vec::new();
+        let mut failures = Vec::new();
+        for (i, result) in authorities.into_iter().zip(join_all(handles).await) {
+            let checkpoint = match result.unwrap() {
+                Ok((stdout, _stderr)) => serde_json::from_str::<CheckpointResponse>(&stdout)
+                    .map_err(|e| format!("invalid checkpoint: {e}")),
+                Err(e) => Err(e.to_string()),
+            };
+            match checkpoint {
+                Ok(checkpoint) => checkpoints.push((i as AuthorityIndex, checkpoint)),
+                Err(e) => failures.push(format!("node {i} ({e})")),
+            }
+        }
+        if !failures.is_empty() {
+            display::warn(format!("No checkpoint from {}", failures.join(", ")));
+        }
+        ensure!(
+            checkpoints.len() >= 2,
+            TestbedError::ConsistencyCheckError(format!(
+                "only {} checkpoints collected",
+                checkpoints.len()
+            ))
+        );
+        Ok(checkpoints)
+    }
+
+    /// Stop the nodes, replay their storage offline and check that they all committed the same
+    /// sequence.
+    pub async fn audit(&self, parameters: &BenchmarkParameters) -> TestbedResult<()> {
+        display::action("Auditing the commit sequences");
+
+        // Select the instances to run.
+        let (_, nodes, _) = self.select_instances(parameters)?;
+
+        // Stop the nodes so that their storage no longer changes.
+        self.ssh_manager.kill(nodes.clone(), "node").await?;
+
+        // Replay the storage of every node from the configuration files of its working directory.
+        let working_dirs = self.working_dirs(&nodes).await?;
+        let targets = self
+            .protocol_commands
+            .replay_command(nodes.into_iter().zip(working_dirs), parameters);
+        let repo = self.settings.repository_name();
+        let context = CommandContext::new().with_execute_from_path(repo.into());
+        let stdio = self
+            .ssh_manager
+            .execute_per_instance(targets, context)
+            .await?;
+
+        let mut audit = SafetyAudit::default();
+        for (stdout, _stderr) in &stdio {
+            audit.add_node_output(stdout);
+        }
+        let commits = audit.check().map_err(TestbedError::SafetyViolation)?;
+
+        display::done();
+        display::config("Audited commits", commits);
+        if !audit.pruned_nodes().is_empty() {
+            display::warn(format!(
+                "Nodes {:?} pruned their WAL and could not be audited",
+                audit.pruned_nodes()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Download the selected lines of a log file (specified relatively to the home directory)
+    /// from each of the specified instances.
+    async fn download_log(
+        &self,
+        instances: &[Instance],
+        path: &str,
+        selection: &LogSelection,
+    ) -> TestbedResult<Vec<String>> {
+        let Some(command) = selection.command(path) else {
+            let downloads = instances.iter().map(|instance| (instance, path));
+            return Ok(self.ssh_manager.download_files(downloads).await?);
+        };
+        let context = CommandContext::default();
+        let output = self
+            .ssh_manager
+            .execute(instances.to_vec(), command, context)
+            .await?;
+        Ok(output.into_iter().map(|(stdout, _)| stdout).collect())
+    }
+
+    /// Download the log files from the nodes and clients. If specified by the settings, also
+    /// merge the consensus events of the nodes into a single timeline, correcting their
+    /// timestamps by the specified clock offsets.
     pub async fn download_logs(
         &self,
         parameters: &BenchmarkParameters,
+        clock_offsets: &[Option<f64>],
     ) -> TestbedResult<LogsAnalyzer> {
         // Select the instances to run.
         let (clients, nodes, _) = self.select_instances(parameters)?;
@@ -522,58 +1600,57 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
         .collect();
         fs::create_dir_all(&path).expect("Failed to create log directory");
 
-        // NOTE: Our ssh library does not seem to be able to transfers files in parallel reliably.
-        let mut log_parsers = Vec::new();
+        // The log files are transferred with bounded parallelism and verified checksums.
+        let selection = LogSelection::new(&self.settings);
+        let mut analyzer = LogsAnalyzer::default();
+        let mut timeline = Timeline::default();
 
         // Download the clients log files.
         display::action("Downloading clients logs");
-        for (i, instance) in clients.iter().enumerate() {
-            display::status(format!("{}/{}", i + 1, clients.len()));
-
-            let connection = self.ssh_manager.connect(instance.ssh_address()).await?;
-            let client_log_content = connection.download("client.log")?;
-
+        let clients_logs = self
+            .download_log(&clients, "client.log", &selection)
+            .await?;
+        for (i, client_log_content) in clients_logs.into_iter().enumerate() {
             let client_log_file = [path.clone(), format!("client-{i}.log").into()]
                 .iter()
                 .collect::<PathBuf>();
             fs::write(&client_log_file, client_log_content.as_bytes())
                 .expect("Cannot write log file");
 
-            let mut log_parser = LogsAnalyzer::default();
-            log_parser.set_client_errors(&client_log_content);
-            log_parsers.push(log_parser)
+            analyzer.add_client_log(i, &client_log_content);
         }
         display::done();
 
         display::action("Downloading nodes logs");
-        for (i, instance) in nodes.iter().enumerate() {
-            display::status(format!("{}/{}", i + 1, nodes.len()));
-
-            let connection = self.ssh_manager.connect(instance.ssh_address()).await?;
-            let node_log_content = connection.download("node.log")?;
-
+        let log_file = self.protocol_commands.node_log_file();
+        let log_file = log_file.display().to_string();
+        let nodes_logs = self.download_log(&nodes, &log_file, &selection).await?;
+        for (i, node_log_content) in nodes_logs.into_iter().enumerate() {
             let node_log_file = [path.clone(), format!("node-{i}.log").into()]
                 .iter()
                 .collect::<PathBuf>();
             fs::write(&node_log_file, node_log_content.as_bytes()).expect("Cannot write log file");
 
-            let mut log_parser = LogsAnalyzer::default();
-            log_parser.set_node_errors(&node_log_content);
-            log_parsers.push(log_parser)
+            analyzer.add_node_log(i, &node_log_content);
+            if self.settings.log_timeline {
+                let offset = clock_offsets.get(i).copied().flatten();
+                timeline.add_node_log(i, &node_log_content, offset);
+            }
         }
         display::done();
 
-        Ok(log_parsers
-            .into_iter()
-            .max()
-            .expect("At least one log parser"))
+        if self.settings.log_timeline {
+            let timeline_file = path.join("timeline.log");
+            fs::write(&timeline_file, timeline.to_string()).expect("Cannot write timeline file");
+            display::config("Timeline", timeline_file.display());
+        }
+
+        Ok(analyzer)
     }
 
-    /// Run all the benchmarks specified by the benchmark generator.
-    pub async fn run_benchmarks(
-        &mut self,
-        set_of_parameters: Vec<BenchmarkParameters>,
-    ) -> TestbedResult<()> {
+    /// Prepare the testbed for a series of benchmarks: cleanup the instances and update their
+    /// software.
+    async fn prepare_testbed(&self) -> TestbedResult<()> {
         display::header("Preparing testbed");
         display::config("Commit", format!("'{}'", &self.settings.repository.commit));
         display::newline();
@@ -586,53 +1663,300 @@ impl<P: ProtocolCommands + ProtocolMetrics> Orchestrator<P> {
             self.install().await?;
             self.update().await?;
         }
+        Ok(())
+    }
 
-        // Run all benchmarks.
-        let mut i = 1;
-        let mut latest_committee_size = 0;
-        for parameters in set_of_parameters {
-            display::header(format!("Starting benchmark {i}"));
-            display::config("Node Parameters", &parameters.node_parameters);
-            display::config("Benchmark Parameters", &parameters);
-            display::newline();
+    /// Run a single benchmark, (re)configuring the instances beforehand if specified. Returns
+    /// the measurements of the benchmark, or `None` if it runs indefinitely (in which case the
+    /// nodes are left running).
+    async fn run_benchmark(
+        &self,
+        i: usize,
+        parameters: &BenchmarkParameters,
+        configure: bool,
+    ) -> TestbedResult<Option<MeasurementsCollection>> {
+        display::header(format!("Starting benchmark {i}"));
+        display::config("Node Parameters", &parameters.node_parameters);
+        display::config("Benchmark Parameters", parameters);
+        display::newline();
 
-            // Cleanup the testbed (in case the previous run was not completed).
-            self.cleanup(true).await?;
-            // Start the instance monitoring tools.
-            self.start_monitoring(&parameters).await?;
+        // Cleanup the testbed (in case the previous run was not completed).
+        self.cleanup(true).await?;
+        // Start the instance monitoring tools.
+        self.start_monitoring(parameters).await?;
+
+        // Configure all instances (if needed).
+        if !self.skip_testbed_configuration && configure {
+            self.configure(parameters).await?;
+        }
+
+        // Check that the clocks of the nodes are synchronized.
+        let clock_offsets = self.check_clocks(parameters).await?;
+
+        // Install the profiling tools on the profiled nodes (if any).
+        let profiler = self.profiler(parameters)?;
+        if let Some(profiler) = &profiler {
+            profiler.install().await?;
+        }
+        // Install the stress tools on the stressed nodes (if any).
+        self.install_stress_tools(parameters).await?;
+
+        // Deploy the validators and schedule the resource-stress faults and gray failures.
+        let start = SystemTime::now();
+        self.run_nodes(parameters).await?;
+        self.stress_nodes(parameters).await?;
+        self.inject_gray_failures(parameters).await?;
+        if parameters.settings.benchmark_duration.as_secs() == 0 {
+            return Ok(None);
+        }
+
+        // Start profiling the nodes (once the profile window starts).
+        if let Some(profiler) = &profiler {
+            profiler.start().await?;
+        }
 
-            // Configure all instances (if needed).
-            if !self.skip_testbed_configuration && latest_committee_size != parameters.nodes {
-                self.configure(&parameters).await?;
-                latest_committee_size = parameters.nodes;
+        // Deploy the load generators.
+        self.run_clients(parameters).await?;
+
+        // Wait for the benchmark to terminate. Then save the results and print a summary.
+        let mut aggregator = self.run(parameters, clock_offsets).await?;
+        aggregator.display_summary();
+        self.save_prometheus_data(parameters, start).await;
+        self.save_heap_profiles(parameters).await;
+        if let Some(profiler) = profiler.as_ref().filter(|_| aggregator.failure.is_none()) {
+            self.save_profiles(profiler, parameters).await;
+        }
+
+        // Stop a failed benchmark and collect the logs of all instances to investigate it.
+        if let Some(cause) = &aggregator.failure {
+            display::error(format!("Benchmark {i} failed: {cause}"));
+            self.cleanup(false).await?;
+            let analyzer = self
+                .download_logs(parameters, &aggregator.clock_offsets)
+                .await?;
+            analyzer.print_summary();
+            aggregator.logs = analyzer.into_summaries();
+            self.save_measurements(&aggregator);
+            return Ok(Some(aggregator));
+        }
+
+        // Check that all nodes committed the same sequence (before their storage is deleted).
+        if self.settings.safety_audit {
+            self.audit(parameters).await?;
+        }
+
+        // Kill the nodes and clients (without deleting the log files).
+        self.cleanup(false).await?;
+
+        // Download the log files.
+        if self.settings.log_processing {
+            let analyzer = self
+                .download_logs(parameters, &aggregator.clock_offsets)
+                .await?;
+            analyzer.print_summary();
+            aggregator.logs = analyzer.into_summaries();
+            self.save_measurements(&aggregator);
+        }
+
+        Ok(Some(aggregator))
+    }
+
+    /// Run all the benchmarks specified by the benchmark generator.
+    pub async fn run_benchmarks(
+        &mut self,
+        set_of_parameters: Vec<BenchmarkParameters>,
+    ) -> TestbedResult<()> {
+        self.prepare_testbed().await?;
+
+        // Resume the sweep from the benchmarks completed by a previous run (if any).
+        let mut progress = SweepProgress::load(self.results_directory(), &set_of_parameters)?;
+        if self.restart_sweep {
+            progress.reset()?;
+        } else if progress.completed() != 0 {
+            display::warn(format!(
+                "Resuming the sweep: skipping {} completed benchmarks",
+                progress.completed()
+            ));
+        }
+
+        // Run all benchmarks.
+        let mut latest_configuration: Option<&BenchmarkParameters> = None;
+        for (i, parameters) in set_of_parameters.iter().enumerate() {
+            if progress.is_completed(parameters) {
+                continue;
             }
 
-            // Deploy the validators.
-            self.run_nodes(&parameters).await?;
-            if parameters.settings.benchmark_duration.as_secs() == 0 {
+            let configure = !latest_configuration.is_some_and(|x| x.same_configuration(parameters));
+            let Some(aggregator) = self.run_benchmark(i + 1, parameters, configure).await? else {
                 return Ok(());
+            };
+            if configure {
+                latest_configuration = Some(parameters);
             }
 
-            // Deploy the load generators.
-            // self.run_clients(&parameters).await?;
+            // Record the completion of the benchmark to resume the sweep after an interruption.
+            if aggregator.failure.is_none() {
+                progress.complete(parameters)?;
+            }
+        }
+
+        // Load the measurements of the whole sweep, including those of the benchmarks completed
+        // before resuming it.
+        let directory = self.results_directory();
+        let runs: Vec<_> = set_of_parameters
+            .iter()
+            .map(|x| {
+                let path = MeasurementsCollection::path(&directory, x);
+                (x, MeasurementsCollection::load(path).ok())
+            })
+            .collect();
+
+        // Summarize the benchmarks that ran several times (the runs of the same parameters are
+        // consecutive).
+        if set_of_parameters.iter().any(|x| x.repetition != 0) {
+            for repetitions in runs.chunk_by(|_, (next, _)| next.repetition != 0) {
+                let measurements: Vec<_> =
+                    repetitions.iter().filter_map(|(_, x)| x.clone()).collect();
+                measurements::display_repetitions_summary(&measurements);
+            }
+        }
 
-            // Wait for the benchmark to terminate. Then save the results and print a summary.
-            let aggregator = self.run(&parameters).await?;
-            aggregator.display_summary();
+        // Save the results of the whole sweep in a single file.
+        let measurements: Vec<_> = runs.into_iter().filter_map(|(_, x)| x).collect();
+        self.save_sweep_results(&measurements)?;
 
-            // Kill the nodes and clients (without deleting the log files).
-            self.cleanup(false).await?;
+        display::header("Benchmark completed");
+        Ok(())
+    }
+
+    /// Search the maximum sustainable load (tx/s) according to the specified criteria, starting
+    /// from the load of each of the specified benchmarks. Each search runs at most the specified
+    /// number of benchmarks.
+    pub async fn search_load(
+        &mut self,
+        set_of_parameters: Vec<BenchmarkParameters>,
+        stability: LoadStability,
+        max_steps: usize,
+    ) -> TestbedResult<()> {
+        self.prepare_testbed().await?;
+        self.stability = Some(stability);
 
-            // Download the log files.
-            if self.settings.log_processing {
-                let error_counter = self.download_logs(&parameters).await?;
-                error_counter.print_summary();
+        let mut i = 1;
+        let mut measurements = Vec::new();
+        let mut summary = Vec::new();
+        let starting_points = set_of_parameters.into_iter().filter(|x| x.repetition == 0);
+        for mut parameters in starting_points {
+            let mut search = LoadSearch::new();
+            let mut load = Some(parameters.load);
+            for _ in 0..max_steps {
+                let Some(next) = load else {
+                    break;
+                };
+                parameters.load = next;
+                let Some(aggregator) = self.run_benchmark(i, &parameters, true).await? else {
+                    return Ok(());
+                };
+                i += 1;
+
+                let stable = stability.is_stable(&aggregator);
+                display::config("Sustainable load", stable);
+                load = search.next(next, stable);
+                measurements.push(aggregator);
             }
+            summary.push((parameters, search.best()));
+        }
 
-            i += 1;
+        display::header("Search completed");
+        for (parameters, best) in summary {
+            let parameters = format!(
+                "{} nodes ({}), {}",
+                parameters.nodes, parameters.settings.faults, parameters.client_parameters
+            );
+            match best {
+                Some(load) => display::config(parameters, format!("{load} tx/s")),
+                None => display::config(parameters, "no load meets the latency bound"),
+            }
         }
+        self.save_sweep_results(&measurements)
+    }
 
-        display::header("Benchmark completed");
+    /// Save the results of the specified benchmarks in a single file.
+    fn save_sweep_results(&self, measurements: &[MeasurementsCollection]) -> TestbedResult<()> {
+        let path = measurements::save_sweep_results(self.results_directory(), measurements)
+            .map_err(|e| TestbedError::SweepError(e.to_string()))?;
+        display::config("Results", path.display());
+        Ok(())
+    }
+
+    /// Print the plan of the specified benchmarks without touching any instance: the
+    /// assignment of the instances, and every command that would run on them (including the
+    /// generation of the configuration files). Requires a dry-run ssh manager.
+    pub async fn dry_run(&self, set_of_parameters: Vec<BenchmarkParameters>) -> TestbedResult<()> {
+        assert!(
+            self.ssh_manager.is_dry_run(),
+            "Dry runs require a dry-run ssh manager"
+        );
+
+        display::header("Dry run: preparing testbed");
+        display::config("Commit", format!("'{}'", &self.settings.repository.commit));
+        self.cleanup(true).await?;
+        self.print_recorded_commands();
+        if !self.skip_testbed_update {
+            self.install().await?;
+            self.print_recorded_commands();
+            self.update().await?;
+            self.print_recorded_commands();
+        }
+
+        for (i, parameters) in set_of_parameters.iter().enumerate() {
+            display::header(format!("Dry run: benchmark {}", i + 1));
+            display::config("Node Parameters", &parameters.node_parameters);
+            display::config("Benchmark Parameters", parameters);
+
+            let (clients, nodes, monitoring) = self.select_instances(parameters)?;
+            display::config("Nodes", nodes.len());
+            for (i, node) in nodes.iter().enumerate() {
+                display::config(format!("  - node {i}"), node.main_ip);
+            }
+            display::config("Clients", clients.len());
+            for (i, client) in clients.iter().enumerate() {
+                display::config(format!("  - client {i}"), client.main_ip);
+            }
+            if let Some(instance) = monitoring {
+                display::config("Monitoring", instance.main_ip);
+            }
+            display::newline();
+
+            self.cleanup(true).await?;
+            self.print_recorded_commands();
+            self.start_monitoring(parameters).await?;
+            self.print_recorded_commands();
+            if !self.skip_testbed_configuration {
+                self.configure(parameters).await?;
+                self.print_recorded_commands();
+            }
+            self.install_stress_tools(parameters).await?;
+            self.print_recorded_commands();
+            self.run_nodes(parameters).await?;
+            self.print_recorded_commands();
+            self.stress_nodes(parameters).await?;
+            self.print_recorded_commands();
+            self.inject_gray_failures(parameters).await?;
+            self.print_recorded_commands();
+            self.run_clients(parameters).await?;
+            self.print_recorded_commands();
+            self.cleanup(false).await?;
+            self.print_recorded_commands();
+        }
+
+        display::header("Dry run completed");
         Ok(())
     }
+
+    /// Print the commands recorded by a dry-run ssh manager since the last call.
+    fn print_recorded_commands(&self) {
+        for (address, command) in self.ssh_manager.take_recorded_commands() {
+            display::config(format!("  [{address}]"), command);
+        }
+    }
 }
@@ -0,0 +1,274 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    client::Instance,
+    error::SshResult,
+    ssh::{CommandContext, CommandStatus, SshConnectionManager},
+};
+
+/// The window of a benchmark during which to profile the nodes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProfileWindow {
+    /// The delay after the start of the nodes before recording the profile.
+    pub start: Duration,
+    /// The duration of the recording.
+    pub duration: Duration,
+}
+
+/// Records the CPU profile of the nodes (with `perf`) during a window of a benchmark and renders
+/// it as a flamegraph (with `inferno`), without having to ssh into the instances.
+pub struct Profiler {
+    /// The instances to profile, along with the name of their profile (e.g., 'node-3').
+    targets: Vec<(String, Instance)>,
+    /// The window during which to profile the instances.
+    window: ProfileWindow,
+    ssh_manager: SshConnectionManager,
+}
+
+impl Profiler {
+    /// The id of the command recording the profile in the background.
+    const COMMAND_ID: &'static str = "profile";
+    /// The sampling frequency (in Hz), slightly off 100 Hz to avoid lockstep sampling.
+    const FREQUENCY: u32 = 99;
+    /// The name of the process of the nodes.
+    const PROCESS: &'static str = "mysticeti";
+    /// The files produced by the profiler on the instances, along with the extension of their
+    /// local copy: the folded stacks and the flamegraph.
+    const OUTPUTS: [(&'static str, &'static str); 2] =
+        [("perf.folded", "folded"), ("flamegraph.svg", "svg")];
+
+    /// Create a new profiler.
+    pub fn new(
+        targets: Vec<(String, Instance)>,
+        window: ProfileWindow,
+        ssh_manager: SshConnectionManager,
+    ) -> Self {
+        Self {
+            targets,
+            window,
+            ssh_manager,
+        }
+    }
+
+    /// The command installing `perf` and `inferno` (if they are not installed yet).
+    fn install_command() -> String {
+        [
+            "source $HOME/.cargo/env",
+            "(command -v perf > /dev/null || sudo apt-get -y install linux-tools-common \
+            linux-tools-generic linux-tools-$(uname -r) || true)",
+            "(command -v inferno-flamegraph > /dev/null || cargo install inferno)",
+        ]
+        .join(" && ")
+    }
+
+    /// The command recording the profile of the node once the window starts and rendering it.
+    /// The command runs in the background (within double quotes), so the id of the node process
+    /// is only resolved when the recording starts.
+    fn record_command(&self) -> String {
+        let [(folded, _), (flamegraph, _)] = Self::OUTPUTS;
+        let pid = format!("\\$(pgrep -x {} | head -n 1)", Self::PROCESS);
+        [
+            "source $HOME/.cargo/env".to_string(),
+            format!("rm -f perf.data {folded} {flamegraph}"),
+            format!("sleep {}", self.window.start.as_secs()),
+            format!(
+                "sudo perf record -F {} -g -o perf.data -p {pid} -- sleep {}",
+                Self::FREQUENCY,
+                self.window.duration.as_secs()
+            ),
+            format!("sudo perf script -i perf.data | inferno-collapse-perf > {folded}"),
+            format!("inferno-flamegraph {folded} > {flamegraph}"),
+        ]
+        .join(" && ")
+    }
+
+    /// The instances to profile.
+    fn instances(&self) -> impl Iterator<Item = Instance> + Clone + '_ {
+        self.targets.iter().map(|(_, instance)| instance.clone())
+    }
+
+    /// Install the profiling tools on the instances (before the benchmark, since compiling
+    /// them may take a while).
+    pub async fn install(&self) -> SshResult<()> {
+        let context = CommandContext::default();
+        self.ssh_manager
+            .execute(self.instances(), Self::install_command(), context)
+            .await?;
+        Ok(())
+    }
+
+    /// Start recording the profiles in the background (once the window starts).
+    pub async fn start(&self) -> SshResult<()> {
+        let context = CommandContext::new().run_background(Self::COMMAND_ID.into());
+        self.ssh_manager
+            .execute(self.instances(), self.record_command(), context)
+            .await?;
+        Ok(())
+    }
+
+    /// Wait for the profiles to be recorded and download them into the specified directory.
+    /// Returns the paths of the downloaded flamegraphs.
+    pub async fn download<P: AsRef<Path>>(&self, path: P) -> SshResult<Vec<PathBuf>> {
+        self.ssh_manager
+            .wait_for_command(
+                self.instances(),
+                Self::COMMAND_ID,
+                CommandStatus::Terminated,
+            )
+            .await?;
+
+        let path = path.as_ref();
+        fs::create_dir_all(path).expect("Failed to create profiles directory");
+        let outputs: Vec<_> = self
+            .targets
+            .iter()
+            .flat_map(|(name, instance)| Self::OUTPUTS.map(|x| (name, instance, x)))
+            .collect();
+        let downloads = outputs
+            .iter()
+            .map(|(_, instance, (output, _))| (*instance, *output));
+        let contents = self.ssh_manager.download_files(downloads).await?;
+
+        let mut flamegraphs = Vec::new();
+        for ((name, _, (_, extension)), content) in outputs.iter().zip(contents) {
+            let file = path.join(format!("{name}.{extension}"));
+            fs::write(&file, content).expect("Cannot write profile file");
+            if *extension == "svg" {
+                flamegraphs.push(file);
+            }
+        }
+        Ok(flamegraphs)
+    }
+}
+
+/// Tracks the memory usage of the nodes over long runs: the nodes (built with the
+/// `heap-profiling` feature) periodically dump their heap profile with jemalloc, and the profiler
+/// downloads a sample of these dumps (to analyze with `jeprof` and the binary of the nodes).
+pub struct HeapProfiler {
+    /// The instances to profile, along with the name of their profile (e.g., 'node-3').
+    targets: Vec<(String, Instance)>,
+    ssh_manager: SshConnectionManager,
+}
+
+impl HeapProfiler {
+    /// The directory (relative to the home directory) where the nodes dump their heap profile.
+    const DIRECTORY: &'static str = "heap";
+    /// The (log2 of the) number of bytes allocated between consecutive dumps (8 GiB).
+    const LG_INTERVAL: u32 = 33;
+    /// The maximum number of dumps to download per node.
+    const MAX_DUMPS: usize = 16;
+
+    /// Create a new heap profiler.
+    pub fn new(targets: Vec<(String, Instance)>, ssh_manager: SshConnectionManager) -> Self {
+        Self {
+            targets,
+            ssh_manager,
+        }
+    }
+
+    /// The command enabling heap profiling, to prepend to the command running a node.
+    pub fn node_command_prefix() -> String {
+        let directory = format!("$HOME/{}", Self::DIRECTORY);
+        let conf = format!(
+            "prof:true,prof_prefix:{directory}/jeprof,lg_prof_interval:{}",
+            Self::LG_INTERVAL
+        );
+        format!("mkdir -p {directory} && export _RJEM_MALLOC_CONF={conf}")
+    }
+
+    /// The command deleting the heap profiles of a previous run.
+    pub fn cleanup_command() -> String {
+        format!("(rm -rf ~/{} || true)", Self::DIRECTORY)
+    }
+
+    /// Select at most `MAX_DUMPS` dumps evenly spaced across the run (in chronological order),
+    /// always including the last one.
+    fn sample<'a>(dumps: &[&'a str]) -> Vec<&'a str> {
+        if dumps.len() <= Self::MAX_DUMPS {
+            return dumps.to_vec();
+        }
+        let last = dumps.len() - 1;
+        (0..Self::MAX_DUMPS)
+            .map(|i| dumps[last - (Self::MAX_DUMPS - 1 - i) * last / (Self::MAX_DUMPS - 1)])
+            .collect()
+    }
+
+    /// Download a sample of the heap dumps of each node into a sub-directory (named after the
+    /// node) of the specified directory. Returns the number of downloaded dumps.
+    pub async fn download<P: AsRef<Path>>(&self, path: P) -> SshResult<usize> {
+        let mut downloads = Vec::new();
+        for (name, instance) in &self.targets {
+            // List the dumps in chronological order.
+            let command = format!("(ls -1tr {} 2> /dev/null || true)", Self::DIRECTORY);
+            let context = CommandContext::default();
+            let output = self
+                .ssh_manager
+                .execute([instance.clone()], command, context)
+                .await?;
+            let stdout = output.into_iter().map(|(x, _)| x).collect::<String>();
+            let dumps: Vec<_> = stdout.lines().filter(|x| x.ends_with(".heap")).collect();
+
+            let directory = path.as_ref().join(name);
+            fs::create_dir_all(&directory).expect("Failed to create heap profiles directory");
+            for dump in Self::sample(&dumps) {
+                let remote = format!("{}/{dump}", Self::DIRECTORY);
+                downloads.push((instance, remote, directory.join(dump)));
+            }
+        }
+
+        let remotes = downloads
+            .iter()
+            .map(|(instance, remote, _)| (*instance, remote.as_str()));
+        let contents = self.ssh_manager.download_files(remotes).await?;
+        for ((_, _, file), content) in downloads.iter().zip(contents) {
+            fs::write(file, content).expect("Cannot write heap profile");
+        }
+        Ok(downloads.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{HeapProfiler, ProfileWindow, Profiler};
+    use crate::{client::Instance, ssh::SshConnectionManager};
+
+    #[test]
+    fn record_command() {
+        let window = ProfileWindow {
+            start: Duration::from_secs(30),
+            duration: Duration::from_secs(20),
+        };
+        let targets = vec![("node-0".into(), Instance::new_for_test("0".into()))];
+        let profiler = Profiler::new(targets, window, SshConnectionManager::dry_run());
+
+        let command = profiler.record_command();
+        assert!(command.contains("sleep 30 && sudo perf record -F 99"));
+        assert!(command.contains("-p \\$(pgrep -x mysticeti | head -n 1) -- sleep 20"));
+        assert!(command.ends_with("inferno-flamegraph perf.folded > flamegraph.svg"));
+    }
+
+    #[test]
+    fn sample_heap_dumps() {
+        let names: Vec<_> = (0..100)
+            .map(|i| format!("jeprof.1.{i}.i{i}.heap"))
+            .collect();
+        let dumps: Vec<_> = names.iter().map(|x| x.as_str()).collect();
+
+        assert_eq!(HeapProfiler::sample(&dumps[..3]), &dumps[..3]);
+
+        let sample = HeapProfiler::sample(&dumps);
+        assert_eq!(sample.len(), HeapProfiler::MAX_DUMPS);
+        assert_eq!(sample[0], dumps[0]);
+        assert_eq!(sample[HeapProfiler::MAX_DUMPS - 1], dumps[99]);
+        assert!(sample.windows(2).all(|x| x[0] != x[1]));
+    }
+}
@@ -9,9 +9,10 @@ use std::{
 use eyre::Context;
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{benchmark::BenchmarkParameters, client::Instance};
+use crate::{benchmark::BenchmarkParameters, client::Instance, settings::Settings};
 
 pub mod mysticeti;
+pub mod single_leader;
 
 pub const BINARY_PATH: &str = "target/release";
 
@@ -25,26 +26,43 @@ pub trait ProtocolParameters:
         let reader = std::fs::File::open(path).wrap_err(error_message)?;
         Ok(serde_yaml::from_reader(reader)?)
     }
+
+    /// Set the size of the transactions submitted by the clients. Parameters without a notion
+    /// of transaction size ignore it.
+    fn set_transaction_size(&mut self, _size: usize) {}
 }
 
 /// The minimum interface that the protocol should implement to allow benchmarks from
 /// the orchestrator.
 pub trait ProtocolCommands {
+    /// Make a new instance of the protocol commands generator.
+    fn new(settings: &Settings) -> Self;
+
     /// The list of dependencies to install (e.g., through apt-get).
     fn protocol_dependencies(&self) -> Vec<&'static str>;
 
+    /// The binaries of the codebase run by the benchmarks (e.g., the node and the load
+    /// generator), deployed on every instance. The first one runs the nodes.
+    fn binaries(&self) -> Vec<&'static str>;
+
     /// The directories of all databases (that should be erased before each run).
     fn db_directories(&self) -> Vec<PathBuf>;
 
-    /// The command to generate the genesis and all configuration files. This command
-    /// is run on each remote machine.
-    async fn genesis_command<'a, I>(
+    /// The genesis and all configuration files of the nodes (e.g., the committee, the keys, and
+    /// the parameters), generated by the orchestrator and uploaded to the instances. The nodes
+    /// are provided along with the (absolute) path of their working directory. The function
+    /// returns the files of each node, named relatively to its working directory.
+    fn genesis_files<I>(
         &self,
         instances: I,
         parameters: &BenchmarkParameters,
-    ) -> String
+    ) -> Vec<(Instance, Vec<(PathBuf, String)>)>
     where
-        I: Iterator<Item = &'a Instance>;
+        I: IntoIterator<Item = (Instance, PathBuf)>;
+
+    /// The configuration files of the clients (e.g., the committee and the client parameters),
+    /// picked among the genesis files of a node. They exclude the private keys of the node.
+    fn client_files(&self, node_files: &[(PathBuf, String)]) -> Vec<(PathBuf, String)>;
 
     /// The command to run a node. The function returns a vector of commands along with the
     /// associated instance on which to run the command.
@@ -56,15 +74,55 @@ pub trait ProtocolCommands {
     where
         I: IntoIterator<Item = Instance>;
 
-    /// The command to run a client. The function returns a vector of commands along with the
-    /// associated instance on which to run the command.
+    /// The command to run a client, submitting its transactions to the provided nodes. The
+    /// function returns a vector of commands along with the associated instance on which to run
+    /// the command.
     fn client_command<I>(
         &self,
         instances: I,
+        nodes: &[Instance],
         parameters: &BenchmarkParameters,
     ) -> Vec<(Instance, String)>
     where
         I: IntoIterator<Item = Instance>;
+
+    /// The command to replay the storage of a (stopped) node offline. The command prints the
+    /// commit sequence of the node on stdout, one commit per line: the height of the commit
+    /// followed by tab-separated fields identifying the sequence up to that commit. The nodes are
+    /// provided along with the (absolute) path of their working directory. The function returns a
+    /// vector of commands along with the associated instance on which to run the command.
+    fn replay_command<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = (Instance, PathBuf)>;
+
+    /// The command to print the commit checkpoint of a running node as JSON (with a `height` and
+    /// a `digest` field): the checkpoint at `height`, or the latest one if not provided. The
+    /// command fails if the node does not answer or has no such checkpoint. Protocols without
+    /// checkpoints return no commands.
+    fn checkpoint_command<I>(
+        &self,
+        _instances: I,
+        _parameters: &BenchmarkParameters,
+        _height: Option<u64>,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = Instance>,
+    {
+        vec![]
+    }
+
+    /// The file where the nodes write their log, relative to the home directory of the instances.
+    fn node_log_file(&self) -> PathBuf {
+        "node.log".into()
+    }
+
+    /// The ports on which the nodes accept the connections of their peers (in committee order),
+    /// which carry all the consensus traffic between the nodes.
+    fn consensus_ports(&self, parameters: &BenchmarkParameters) -> Vec<u16>;
 }
 
 /// The names of the minimum metrics exposed by the protocol that are required to
@@ -82,6 +140,8 @@ pub trait ProtocolMetrics {
     /// The name of the metric reporting the square of the sum of the end-to-end latency of all
     /// finalized transactions.
     const LATENCY_SQUARED_SUM: &'static str;
+    /// The name of the metric reporting the current round of a node.
+    const CURRENT_ROUND: &'static str;
 
     /// The network path where the nodes expose prometheus metrics.
     fn nodes_metrics_path<I>(
@@ -145,6 +205,7 @@ pub mod test_protocol_metrics {
         const LATENCY_BUCKETS: &'static str = "latency_s";
         const LATENCY_SUM: &'static str = "latency_s_sum";
         const LATENCY_SQUARED_SUM: &'static str = "latency_squared_s";
+        const CURRENT_ROUND: &'static str = "current_round";
 
         fn nodes_metrics_path<I>(
             &self,
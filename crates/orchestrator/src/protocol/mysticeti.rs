@@ -3,23 +3,47 @@
 
 use std::{
     fmt::{Debug, Display},
-    net::IpAddr,
+    fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     ops::Deref,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+use eyre::Context;
 use mysticeti_core::{
-    config::{self, ClientParameters, NodeParameters},
+    committee::Committee,
+    config::{
+        self, ClientParameters, LoadMode, LoadShape, NodeParameters, NodePrivateConfig,
+        NodePublicConfig,
+    },
     types::AuthorityIndex,
 };
 use serde::{Deserialize, Serialize};
 
 use super::{ProtocolCommands, ProtocolMetrics, ProtocolParameters, BINARY_PATH};
-use crate::{benchmark::BenchmarkParameters, client::Instance, settings::Settings};
+use crate::{
+    benchmark::BenchmarkParameters,
+    client::Instance,
+    settings::{ClientPlacement, CloudProvider, Settings},
+};
+
+/// The log filter of the nodes enabling the debug logs of the consensus events (block creation,
+/// leader timeouts, and commits).
+const LOG_TIMELINE_FILTER: &str =
+    "info,mysticeti_core::core=debug,mysticeti_core::net_sync=debug,mysticeti_core::syncer=debug";
+
+/// The file holding the parameters of the load generators.
+const CLIENT_PARAMETERS_FILENAME: &str = "client-parameters.yaml";
 
+/// The placeholders of the node parameters file expanded for each node: its index, its region,
+/// and the ip address through which it communicates with the other nodes.
+const TEMPLATE_PLACEHOLDERS: [&str; 3] = ["{index}", "{region}", "{ip}"];
+
+/// The node parameters, along with the template of the parameters file if its fields differ
+/// between nodes.
 #[derive(Clone, Serialize, Deserialize, Default)]
 #[serde(transparent)]
-pub struct MysticetiNodeParameters(NodeParameters);
+pub struct MysticetiNodeParameters(NodeParameters, #[serde(skip)] Option<String>);
 
 impl Deref for MysticetiNodeParameters {
     type Target = NodeParameters;
@@ -32,24 +56,94 @@ impl Deref for MysticetiNodeParameters {
 impl Debug for MysticetiNodeParameters {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.consensus_only {
-            write!(f, "c")
+            write!(f, "c")?;
         } else {
-            write!(f, "fpc")
+            write!(f, "fpc")?;
+        }
+        if self.wal_sync != config::node_defaults::default_wal_sync() {
+            write!(f, "-{}", self.wal_sync)?;
         }
+        if self.header_first {
+            write!(f, "-header-first")?;
+        }
+        if self.1.is_some() {
+            write!(f, "-templated")?;
+        }
+        Ok(())
     }
 }
 
 impl Display for MysticetiNodeParameters {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.consensus_only {
-            write!(f, "Consensus-only mode")
+            write!(f, "Consensus-only mode")?;
         } else {
-            write!(f, "FPC mode")
+            write!(f, "FPC mode")?;
+        }
+        if self.wal_sync != config::node_defaults::default_wal_sync() {
+            write!(f, ", {} wal sync", self.wal_sync)?;
+        }
+        if self.header_first {
+            write!(f, ", header-first dissemination")?;
+        }
+        if self.1.is_some() {
+            write!(f, ", per-node parameters")?;
         }
+        Ok(())
     }
 }
 
-impl ProtocolParameters for MysticetiNodeParameters {}
+impl ProtocolParameters for MysticetiNodeParameters {
+    /// Load the parameters from a YAML (or JSON) file. Its fields may contain the placeholders
+    /// `{index}`, `{region}`, and `{ip}`, expanded for each node.
+    fn load<P: AsRef<Path>>(path: P) -> Result<Self, eyre::Error> {
+        let path = path.as_ref();
+        let error_message = format!("Unable to load config from {}", path.display());
+        let template = fs::read_to_string(path).wrap_err(error_message)?;
+        let parameters = serde_yaml::from_str(&Self::expand(&template, 0, "", "0.0.0.0"))?;
+        let templated = TEMPLATE_PLACEHOLDERS.iter().any(|x| template.contains(x));
+        Ok(Self(parameters, templated.then_some(template)))
+    }
+}
+
+impl MysticetiNodeParameters {
+    /// The parameters committing a single leader per wave, without pipelining the waves.
+    pub fn single_leader(&self) -> Self {
+        let mut parameters = self.0.clone();
+        parameters.number_of_leaders = 1;
+        parameters.enable_pipelining = false;
+        Self(parameters, self.1.clone())
+    }
+
+    /// The parameters of the specified node. The fields of the template whose expansion differs
+    /// from the one loaded by default override the parameters (the others are left untouched).
+    pub fn for_node(&self, index: usize, region: &str, ip: &str) -> NodeParameters {
+        let Some(template) = &self.1 else {
+            return self.0.clone();
+        };
+        let parse = |x: String| -> serde_yaml::Mapping {
+            serde_yaml::from_str(&x).expect("Node parameters should be a mapping")
+        };
+        let reference = parse(Self::expand(template, 0, "", "0.0.0.0"));
+        let node = parse(Self::expand(template, index, region, ip));
+
+        let mut parameters = serde_yaml::to_value(&self.0).unwrap();
+        for (field, value) in node {
+            if reference.get(&field) != Some(&value) {
+                parameters[&field] = value;
+            }
+        }
+        serde_yaml::from_value(parameters).expect("Invalid expansion of the node parameters")
+    }
+
+    /// Expand the placeholders of the template of the parameters file.
+    fn expand(template: &str, index: usize, region: &str, ip: &str) -> String {
+        template
+            .replace("{index}", &index.to_string())
+            .replace("{region}", region)
+            .replace("{ip}", ip)
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Default)]
 #[serde(transparent)]
@@ -65,86 +159,168 @@ impl Deref for MysticetiClientParameters {
 
 impl Debug for MysticetiClientParameters {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.transaction_size)
+        write!(f, "{}", self.transaction_size)?;
+        if self.mode != LoadMode::OpenLoop {
+            write!(f, "-{}", self.mode)?;
+        }
+        if self.shape != LoadShape::Constant {
+            write!(f, "-{}", self.shape)?;
+        }
+        Ok(())
     }
 }
 
 impl Display for MysticetiClientParameters {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}B tx", self.transaction_size)
+        write!(f, "{}B tx", self.transaction_size)?;
+        if self.mode != LoadMode::OpenLoop {
+            write!(f, ", {} load", self.mode)?;
+        }
+        if self.shape != LoadShape::Constant {
+            write!(f, ", {} load shape", self.shape)?;
+        }
+        Ok(())
     }
 }
 
-impl ProtocolParameters for MysticetiClientParameters {}
+impl ProtocolParameters for MysticetiClientParameters {
+    fn set_transaction_size(&mut self, size: usize) {
+        self.0.transaction_size = size;
+    }
+}
 
 pub struct MysticetiProtocol {
     working_dir: PathBuf,
 }
 
 impl ProtocolCommands for MysticetiProtocol {
+    fn new(settings: &Settings) -> Self {
+        Self {
+            working_dir: settings.working_dir.clone(),
+        }
+    }
+
     fn protocol_dependencies(&self) -> Vec<&'static str> {
         vec!["sudo apt -y install libfontconfig1-dev"]
     }
 
+    fn binaries(&self) -> Vec<&'static str> {
+        vec!["mysticeti", "mysticeti-client"]
+    }
+
     fn db_directories(&self) -> Vec<std::path::PathBuf> {
         vec![self.working_dir.join("storage-*")]
     }
 
-    async fn genesis_command<'a, I>(&self, instances: I, parameters: &BenchmarkParameters) -> String
+    fn genesis_files<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, Vec<(PathBuf, String)>)>
     where
-        I: Iterator<Item = &'a Instance>,
+        I: IntoIterator<Item = (Instance, PathBuf)>,
     {
-        let ips = instances
-            .map(|x| x.main_ip.to_string())
-            .collect::<Vec<_>>()
-            .join(" ");
-
-        let node_parameters = parameters.node_parameters.clone();
-        let node_parameters_string = serde_yaml::to_string(&node_parameters).unwrap();
-        let node_parameters_path = self.working_dir.join("node-parameters.yaml");
-        let upload_node_parameters = format!(
-            "echo -e '{node_parameters_string}' > {}",
-            node_parameters_path.display()
-        );
-
-        let mut client_parameters = parameters.client_parameters.clone();
-        client_parameters.0.load =
-            parameters.load / (parameters.nodes - parameters.settings.faults.len());
-        let client_parameters_string = serde_yaml::to_string(&client_parameters).unwrap();
-        let client_parameters_path = self.working_dir.join("client-parameters.yaml");
-        let upload_client_parameters = format!(
-            "echo -e '{client_parameters_string}' > {}",
-            client_parameters_path.display()
-        );
-
-        let genesis = [
-            &format!("./{BINARY_PATH}/mysticeti"),
-            "benchmark-genesis",
-            &format!(
-                "--ips {ips} --working-directory {} --node-parameters-path {}",
-                self.working_dir.display(),
-                node_parameters_path.display(),
-            ),
-        ]
-        .join(" ");
-
-        [
-            "source $HOME/.cargo/env",
-            &upload_node_parameters,
-            &upload_client_parameters,
-            &genesis,
-        ]
-        .join(" && ")
+        // The nodes communicate through their private addresses (if all have one).
+        let (instances, working_dirs): (Vec<_>, Vec<_>) = instances.into_iter().unzip();
+        let private = parameters.settings.private_networking
+            && instances.iter().all(|x| x.private_ip.is_some());
+        let ips: Vec<IpAddr> = instances
+            .iter()
+            .map(|x| match x.private_ip {
+                Some(ip) if private => IpAddr::V4(ip),
+                _ => IpAddr::V4(x.main_ip),
+            })
+            .collect();
+
+        let committee = Committee::new_for_benchmarks(instances.len());
+        let committee = serde_yaml::to_string(committee.as_ref()).unwrap();
+
+        // The load is split among the nodes running a load generator. Nodes served by dedicated
+        // load generators accept their transactions instead.
+        let generators = match &parameters.settings.client_placement {
+            ClientPlacement::Collocated { nodes } => Some(nodes),
+            _ => None,
+        };
+        let dedicated_clients = parameters.settings.dedicated_client_instances() != 0;
+        let submission_ports = Self::submission_ports(&parameters.settings, ips.len());
+
+        // The storage of each node is in its own working directory.
+        let private_configs = NodePrivateConfig::new_for_benchmarks(Path::new(""), instances.len());
+
+        instances
+            .into_iter()
+            .zip(working_dirs)
+            .zip(private_configs)
+            .enumerate()
+            .map(|(i, ((instance, working_dir), mut private_config))| {
+                // Each node runs with its own parameters.
+                let ip = ips[i].to_string();
+                let node_parameters = parameters
+                    .node_parameters
+                    .for_node(i, &instance.region, &ip);
+                let public_config =
+                    Self::public_config(&parameters.settings, ips.clone(), node_parameters);
+                private_config.storage_path = working_dir.join(&private_config.storage_path);
+                if dedicated_clients {
+                    let port = submission_ports[i];
+                    let address = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port);
+                    private_config.submission_address = Some(address);
+                }
+                let mut client_parameters = parameters.client_parameters.clone();
+                client_parameters.0.load = match generators {
+                    _ if dedicated_clients => 0,
+                    Some(nodes) if !nodes.contains(&i) => 0,
+                    Some(nodes) => parameters.load / nodes.len(),
+                    None => parameters.load / (parameters.nodes - parameters.settings.faults.len()),
+                };
+
+                let authority = i as AuthorityIndex;
+                let files = vec![
+                    (Committee::DEFAULT_FILENAME.into(), committee.clone()),
+                    (
+                        NodePublicConfig::DEFAULT_FILENAME.into(),
+                        serde_yaml::to_string(&public_config).unwrap(),
+                    ),
+                    (
+                        NodePrivateConfig::default_filename(authority),
+                        serde_yaml::to_string(&private_config).unwrap(),
+                    ),
+                    (
+                        CLIENT_PARAMETERS_FILENAME.into(),
+                        serde_yaml::to_string(&client_parameters).unwrap(),
+                    ),
+                ];
+                (instance, files)
+            })
+            .collect()
+    }
+
+    fn client_files(&self, node_files: &[(PathBuf, String)]) -> Vec<(PathBuf, String)> {
+        let shared = [
+            Committee::DEFAULT_FILENAME,
+            NodePublicConfig::DEFAULT_FILENAME,
+            CLIENT_PARAMETERS_FILENAME,
+        ];
+        node_files
+            .iter()
+            .filter(|(path, _)| shared.iter().any(|x| path == Path::new(x)))
+            .cloned()
+            .collect()
     }
 
     fn node_command<I>(
         &self,
         instances: I,
-        _parameters: &BenchmarkParameters,
+        parameters: &BenchmarkParameters,
     ) -> Vec<(Instance, String)>
     where
         I: IntoIterator<Item = Instance>,
     {
+        let byzantine = parameters
+            .settings
+            .byzantine
+            .as_ref()
+            .map(|x| (x.nodes(parameters.nodes), x.strategy));
         instances
             .into_iter()
             .enumerate()
@@ -155,10 +331,19 @@ impl ProtocolCommands for MysticetiProtocol {
                 let private_config_path = self
                     .working_dir
                     .join(format!("private-config-{authority}.yaml"));
-                let client_parameters_path = self.working_dir.join("client-parameters.yaml");
-
-                let run = [
-                    &format!("./{BINARY_PATH}/mysticeti"),
+                let client_parameters_path = self.working_dir.join(CLIENT_PARAMETERS_FILENAME);
+                let storage_path = self
+                    .working_dir
+                    .join(NodePrivateConfig::default_storage_path(authority));
+
+                // Log the consensus events merged into the timeline of the benchmark.
+                let log_filter = if parameters.settings.log_timeline {
+                    format!("RUST_LOG={LOG_TIMELINE_FILTER} ")
+                } else {
+                    String::new()
+                };
+                let mut run = [
+                    &format!("{log_filter}./{BINARY_PATH}/mysticeti"),
                     "run",
                     &format!("--authority {authority}"),
                     &format!("--committee-path {}", committee_path.display()),
@@ -170,8 +355,19 @@ impl ProtocolCommands for MysticetiProtocol {
                     ),
                 ]
                 .join(" ");
-
-                let command = ["source $HOME/.cargo/env", &run].join(" && ");
+                // Byzantine nodes deviate from the protocol.
+                if let Some((nodes, strategy)) = &byzantine {
+                    if nodes.contains(&i) {
+                        run.push_str(&format!(" --byzantine-strategy {strategy}"));
+                    }
+                }
+
+                let command = [
+                    "source $HOME/.cargo/env",
+                    &format!("mkdir -p {}", storage_path.display()),
+                    &run,
+                ]
+                .join(" && ");
                 (instance, command)
             })
             .collect()
@@ -179,14 +375,119 @@ impl ProtocolCommands for MysticetiProtocol {
 
     fn client_command<I>(
         &self,
-        _instances: I,
-        _parameters: &BenchmarkParameters,
+        instances: I,
+        nodes: &[Instance],
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = Instance>,
+    {
+        // Load generators collocated with the nodes run within the nodes.
+        if parameters.settings.dedicated_client_instances() == 0 {
+            return vec![];
+        }
+
+        let clients: Vec<_> = instances.into_iter().collect();
+        let load = parameters.load / clients.len().max(1);
+        let submission_ports = Self::submission_ports(&parameters.settings, nodes.len());
+        let client_parameters_path = self.working_dir.join(CLIENT_PARAMETERS_FILENAME);
+        clients
+            .into_iter()
+            .enumerate()
+            .map(|(i, instance)| {
+                // Each load generator submits to a node of its region (if any), spreading the
+                // load generators of a region over its nodes.
+                let mut targets: Vec<_> = (0..nodes.len())
+                    .filter(|j| nodes[*j].region == instance.region)
+                    .collect();
+                if targets.is_empty() {
+                    targets = (0..nodes.len()).collect();
+                }
+                let target = targets[i % targets.len()];
+                let ip = nodes[target].main_ip;
+                let port = submission_ports[target];
+
+                let run = [
+                    &format!("./{BINARY_PATH}/mysticeti-client"),
+                    &format!("--address {ip}:{port}"),
+                    &format!(
+                        "--client-parameters-path {}",
+                        client_parameters_path.display()
+                    ),
+                    &format!("--load {load}"),
+                    &format!("--seed {i}"),
+                ]
+                .join(" ");
+
+                // Wait for the node to accept transactions, and resubmit once it recovers from
+                // a crash.
+                let wait =
+                    format!("until (echo > /dev/tcp/{ip}/{port}) 2>/dev/null; do sleep 1; done");
+                let command = [
+                    "source $HOME/.cargo/env",
+                    &format!("while true; do {wait}; {run}; sleep 1; done"),
+                ]
+                .join(" && ");
+                (instance, command)
+            })
+            .collect()
+    }
+
+    fn checkpoint_command<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+        height: Option<u64>,
     ) -> Vec<(Instance, String)>
     where
         I: IntoIterator<Item = Instance>,
     {
-        // TODO: Isolate clients from the node (#9).
-        vec![]
+        // Fails on the nodes that are down or no longer keep the checkpoint.
+        Self::checkpoint_urls(instances, parameters, height)
+            .into_iter()
+            .map(|(instance, url)| (instance, format!("curl -sf '{url}'")))
+            .collect()
+    }
+
+    fn consensus_ports(&self, parameters: &BenchmarkParameters) -> Vec<u16> {
+        let ips = vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED); parameters.nodes];
+        let node_parameters = parameters.node_parameters.deref().clone();
+        Self::public_config(&parameters.settings, ips, node_parameters)
+            .all_network_addresses()
+            .map(|x| x.port())
+            .collect()
+    }
+
+    fn replay_command<I>(
+        &self,
+        instances: I,
+        _parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = (Instance, PathBuf)>,
+    {
+        instances
+            .into_iter()
+            .enumerate()
+            .map(|(i, (instance, working_dir))| {
+                let authority = i as AuthorityIndex;
+                let committee_path = working_dir.join(Committee::DEFAULT_FILENAME);
+                let public_config_path = working_dir.join(NodePublicConfig::DEFAULT_FILENAME);
+                let private_config_path =
+                    working_dir.join(NodePrivateConfig::default_filename(authority));
+
+                let command = [
+                    &format!("./{BINARY_PATH}/mysticeti"),
+                    "dry-run",
+                    &format!("--authority {authority}"),
+                    &format!("--committee-path {}", committee_path.display()),
+                    &format!("--public-config-path {}", public_config_path.display()),
+                    &format!("--private-config-path {}", private_config_path.display()),
+                ]
+                .join(" ");
+                (instance, command)
+            })
+            .collect()
     }
 }
 
@@ -196,6 +497,7 @@ impl ProtocolMetrics for MysticetiProtocol {
     const LATENCY_BUCKETS: &'static str = "latency_s";
     const LATENCY_SUM: &'static str = "latency_s_sum";
     const LATENCY_SQUARED_SUM: &'static str = mysticeti_core::metrics::LATENCY_SQUARED_S;
+    const CURRENT_ROUND: &'static str = "threshold_clock_round";
 
     fn nodes_metrics_path<I>(
         &self,
@@ -210,8 +512,8 @@ impl ProtocolMetrics for MysticetiProtocol {
             .map(|x| (IpAddr::V4(x.main_ip), x))
             .unzip();
 
-        let node_parameters = Some(parameters.node_parameters.deref().clone());
-        let node_config = config::NodePublicConfig::new_for_benchmarks(ips, node_parameters);
+        let node_parameters = parameters.node_parameters.deref().clone();
+        let node_config = Self::public_config(&parameters.settings, ips, node_parameters);
         let metrics_paths = node_config
             .all_metric_addresses()
             .map(|x| format!("{x}{}", mysticeti_core::prometheus::METRICS_ROUTE));
@@ -233,10 +535,161 @@ impl ProtocolMetrics for MysticetiProtocol {
 }
 
 impl MysticetiProtocol {
-    /// Make a new instance of the Mysticeti protocol commands generator.
-    pub fn new(settings: &Settings) -> Self {
-        Self {
-            working_dir: settings.working_dir.clone(),
+    /// The public configuration of the nodes. The pods of kubernetes testbeds each have their own
+    /// ip, so all nodes use the same ports (the ones their pods declare).
+    fn public_config(
+        settings: &Settings,
+        ips: Vec<IpAddr>,
+        node_parameters: NodeParameters,
+    ) -> NodePublicConfig {
+        let public_config = NodePublicConfig::new_for_benchmarks(ips, Some(node_parameters));
+        if matches!(settings.cloud_provider, CloudProvider::Kubernetes) {
+            public_config.with_uniform_ports()
+        } else {
+            public_config
+        }
+    }
+
+    /// The ports on which the nodes accept the transactions of dedicated load generators, after
+    /// their network and metrics ports.
+    fn submission_ports(settings: &Settings, committee_size: usize) -> Vec<u16> {
+        if matches!(settings.cloud_provider, CloudProvider::Kubernetes) {
+            return vec![NodePublicConfig::UNIFORM_METRICS_PORT + 1; committee_size];
+        }
+        let ips = vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED); committee_size];
+        config::NodePublicConfig::new_for_benchmarks(ips, None)
+            .all_network_addresses()
+            .map(|x| x.port() + 2 * committee_size as u16)
+            .collect()
+    }
+
+    /// The admin route serving the checkpoints of the nodes, next to their metrics.
+    fn checkpoint_urls<I>(
+        instances: I,
+        parameters: &BenchmarkParameters,
+        height: Option<u64>,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = Instance>,
+    {
+        let (ips, instances): (_, Vec<_>) = instances
+            .into_iter()
+            .map(|x| (IpAddr::V4(x.main_ip), x))
+            .unzip();
+
+        let node_parameters = parameters.node_parameters.deref().clone();
+        let node_config = Self::public_config(&parameters.settings, ips, node_parameters);
+        let query = height.map_or(String::new(), |height| format!("?height={height}"));
+        let urls = node_config
+            .all_metric_addresses()
+            .map(|x| format!("{x}{}{query}", mysticeti_core::prometheus::CHECKPOINT_ROUTE));
+
+        instances.into_iter().zip(urls).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::{MysticetiNodeParameters, MysticetiProtocol};
+    use crate::{
+        benchmark::BenchmarkParameters,
+        client::Instance,
+        protocol::{ProtocolCommands, ProtocolParameters},
+        settings::ClientPlacement,
+    };
+
+    #[test]
+    fn node_parameters_template() {
+        let path = tempfile::tempdir()
+            .unwrap()
+            .into_path()
+            .join("node-parameters.yaml");
+        fs::write(&path, "max_block_size: 100{index}\nnumber_of_leaders: 3\n").unwrap();
+        let parameters = MysticetiNodeParameters::load(&path)
+            .unwrap()
+            .single_leader();
+        assert_eq!(format!("{parameters:?}"), "fpc-templated");
+
+        let node = parameters.for_node(2, "us-east-1", "10.0.0.2");
+        assert_eq!(node.max_block_size, 1002);
+        assert_eq!(node.number_of_leaders, 1);
+        let node = parameters.for_node(0, "us-east-1", "10.0.0.1");
+        assert_eq!(node.max_block_size, 1000);
+    }
+
+    #[test]
+    fn client_files() {
+        let parameters = BenchmarkParameters::new_for_tests();
+        let instances = (0..4).map(|i| (Instance::new_for_test(i.to_string()), "/home".into()));
+        let protocol = MysticetiProtocol::new(&parameters.settings);
+        let (_, files) = protocol.genesis_files(instances, &parameters).remove(0);
+
+        // The clients get no private config.
+        let files: Vec<_> = protocol
+            .client_files(&files)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        let expected = [
+            "committee.yaml",
+            "public-config.yaml",
+            "client-parameters.yaml",
+        ];
+        assert_eq!(files, expected.map(PathBuf::from));
+    }
+
+    #[test]
+    fn client_command_placement() {
+        let instance = |id: &str, region: &str, ip: [u8; 4]| Instance {
+            region: region.into(),
+            main_ip: ip.into(),
+            ..Instance::new_for_test(id.into())
+        };
+        let nodes = vec![
+            instance("n0", "a", [10, 0, 0, 1]),
+            instance("n1", "b", [10, 0, 0, 2]),
+            instance("n2", "a", [10, 0, 0, 3]),
+            instance("n3", "b", [10, 0, 0, 4]),
+        ];
+        let clients = vec![
+            instance("c0", "b", [10, 0, 1, 1]),
+            instance("c1", "b", [10, 0, 1, 2]),
+            instance("c2", "c", [10, 0, 1, 3]),
+        ];
+
+        let mut parameters = BenchmarkParameters::new_for_tests();
+        let protocol = MysticetiProtocol::new(&parameters.settings);
+        let commands = protocol.client_command(clients.clone(), &nodes, &parameters);
+        assert!(commands.is_empty());
+
+        // The clients submit to the nodes of their region, or to any node if there are none.
+        parameters.settings.regions = vec!["a".into(), "b".into()];
+        parameters.settings.client_placement = ClientPlacement::PerRegion { clients: 1 };
+        let commands = protocol.client_command(clients, &nodes, &parameters);
+        let targets = ["10.0.0.2:1509", "10.0.0.4:1511", "10.0.0.3:1510"];
+        for ((_, command), target) in commands.iter().zip(targets) {
+            assert!(command.contains(&format!("--address {target} ")));
+            assert!(command.contains("--load 166 "));
         }
     }
+
+    #[test]
+    fn replay_command_working_dirs() {
+        let parameters = BenchmarkParameters::new_for_tests();
+        let instances = (0..2).map(|i| {
+            let working_dir = PathBuf::from(format!("/home/node-{i}"));
+            (Instance::new_for_test(i.to_string()), working_dir)
+        });
+        let protocol = MysticetiProtocol::new(&parameters.settings);
+        let commands = protocol.replay_command(instances, &parameters);
+
+        // Each node replays the configuration files of its own working directory.
+        let (_, command) = &commands[1];
+        assert!(command.starts_with("./target/release/mysticeti dry-run --authority 1 "));
+        assert!(command.contains("--committee-path /home/node-1/committee.yaml "));
+        assert!(command.contains("--public-config-path /home/node-1/public-config.yaml "));
+        assert!(command.ends_with("--private-config-path /home/node-1/private-config-1.yaml"));
+    }
 }
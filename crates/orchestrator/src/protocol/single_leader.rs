@@ -0,0 +1,174 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::PathBuf;
+
+use super::{mysticeti::MysticetiProtocol, ProtocolCommands, ProtocolMetrics};
+use crate::{benchmark::BenchmarkParameters, client::Instance, settings::Settings};
+
+/// The single-leader baseline: the Mysticeti codebase committing a single leader per wave,
+/// without pipelining the waves (as in Cordial Miners). Both protocols share the same binary and
+/// metrics, so that their benchmarks only differ by the commit rule. The benchmark parameters of
+/// the baseline hold the node parameters of a single leader (see
+/// `MysticetiNodeParameters::single_leader`).
+pub struct SingleLeaderProtocol {
+    mysticeti: MysticetiProtocol,
+}
+
+impl ProtocolCommands for SingleLeaderProtocol {
+    fn new(settings: &Settings) -> Self {
+        Self {
+            mysticeti: MysticetiProtocol::new(settings),
+        }
+    }
+
+    fn protocol_dependencies(&self) -> Vec<&'static str> {
+        self.mysticeti.protocol_dependencies()
+    }
+
+    fn binaries(&self) -> Vec<&'static str> {
+        self.mysticeti.binaries()
+    }
+
+    fn db_directories(&self) -> Vec<PathBuf> {
+        self.mysticeti.db_directories()
+    }
+
+    fn genesis_files<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, Vec<(PathBuf, String)>)>
+    where
+        I: IntoIterator<Item = (Instance, PathBuf)>,
+    {
+        self.mysticeti.genesis_files(instances, parameters)
+    }
+
+    fn node_command<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = Instance>,
+    {
+        self.mysticeti.node_command(instances, parameters)
+    }
+
+    fn client_files(&self, node_files: &[(PathBuf, String)]) -> Vec<(PathBuf, String)> {
+        self.mysticeti.client_files(node_files)
+    }
+
+    fn client_command<I>(
+        &self,
+        instances: I,
+        nodes: &[Instance],
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = Instance>,
+    {
+        self.mysticeti.client_command(instances, nodes, parameters)
+    }
+
+    fn replay_command<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = (Instance, PathBuf)>,
+    {
+        self.mysticeti.replay_command(instances, parameters)
+    }
+
+    fn checkpoint_command<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+        height: Option<u64>,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = Instance>,
+    {
+        self.mysticeti
+            .checkpoint_command(instances, parameters, height)
+    }
+
+    fn node_log_file(&self) -> PathBuf {
+        self.mysticeti.node_log_file()
+    }
+
+    fn consensus_ports(&self, parameters: &BenchmarkParameters) -> Vec<u16> {
+        self.mysticeti.consensus_ports(parameters)
+    }
+}
+
+impl ProtocolMetrics for SingleLeaderProtocol {
+    const BENCHMARK_DURATION: &'static str = MysticetiProtocol::BENCHMARK_DURATION;
+    const TOTAL_TRANSACTIONS: &'static str = MysticetiProtocol::TOTAL_TRANSACTIONS;
+    const LATENCY_BUCKETS: &'static str = MysticetiProtocol::LATENCY_BUCKETS;
+    const LATENCY_SUM: &'static str = MysticetiProtocol::LATENCY_SUM;
+    const LATENCY_SQUARED_SUM: &'static str = MysticetiProtocol::LATENCY_SQUARED_SUM;
+    const CURRENT_ROUND: &'static str = MysticetiProtocol::CURRENT_ROUND;
+
+    fn nodes_metrics_path<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = Instance>,
+    {
+        self.mysticeti.nodes_metrics_path(instances, parameters)
+    }
+
+    fn clients_metrics_path<I>(
+        &self,
+        instances: I,
+        parameters: &BenchmarkParameters,
+    ) -> Vec<(Instance, String)>
+    where
+        I: IntoIterator<Item = Instance>,
+    {
+        self.mysticeti.clients_metrics_path(instances, parameters)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::SingleLeaderProtocol;
+    use crate::{
+        benchmark::BenchmarkParameters,
+        client::Instance,
+        protocol::{mysticeti::MysticetiProtocol, ProtocolCommands},
+        settings::Settings,
+    };
+
+    #[test]
+    fn genesis_files() {
+        let settings = Settings::new_for_test();
+        let mut parameters = BenchmarkParameters::new_for_tests();
+        parameters.node_parameters = parameters.node_parameters.single_leader();
+        let instances: Vec<_> = (0..4)
+            .map(|i| (Instance::new_for_test(i.to_string()), "/home/ubuntu".into()))
+            .collect();
+
+        // The public config holds the parameters of the nodes, left as provided.
+        let public_config = |mut files: Vec<(Instance, Vec<(PathBuf, String)>)>| {
+            let (_, mut files) = files.remove(0);
+            files.retain(|(path, _)| path == Path::new("public-config.yaml"));
+            files.remove(0).1
+        };
+        let mysticeti = MysticetiProtocol::new(&settings);
+        let mysticeti = public_config(mysticeti.genesis_files(instances.clone(), &parameters));
+        let baseline = SingleLeaderProtocol::new(&settings);
+        let baseline = public_config(baseline.genesis_files(instances, &parameters));
+        assert_eq!(mysticeti, baseline);
+        assert!(baseline.contains("number_of_leaders: 1"));
+        assert!(baseline.contains("enable_pipelining: false"));
+    }
+}
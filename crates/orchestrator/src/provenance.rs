@@ -0,0 +1,171 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{collections::BTreeMap, process::Command};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    benchmark::BenchmarkParameters,
+    client::Instance,
+    settings::{ClientPlacement, Repository},
+};
+
+/// The version of the orchestrator that ran the benchmark.
+const ORCHESTRATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The instances of a benchmark sharing the same role, region, and specs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InstanceGroup {
+    /// The role of the instances (e.g., 'node' or 'client').
+    pub role: String,
+    /// The region of the instances.
+    pub region: String,
+    /// The specs (instance type) of the instances.
+    pub specs: String,
+    /// The number of instances.
+    pub count: usize,
+}
+
+/// Where a benchmark comes from: the code version it ran and the testbed it ran on. The
+/// settings and parameters of the benchmark are recorded along with its measurements.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Provenance {
+    /// The git commit hash of the code deployed on the instances (or the commit of the settings
+    /// if it cannot be resolved).
+    pub commit: String,
+    /// Whether the deployed code had uncommitted changes.
+    pub dirty: bool,
+    /// The contents of the node configuration file deployed on the instances.
+    pub node_config: String,
+    /// The instances running the benchmark.
+    pub instances: Vec<InstanceGroup>,
+    /// The placement of the load generators (e.g., 'collocated with nodes 0, 1').
+    #[serde(default)]
+    pub client_placement: String,
+    /// The version of the orchestrator.
+    pub orchestrator_version: String,
+}
+
+impl Provenance {
+    /// Record the provenance of a benchmark run on the specified instances.
+    pub fn new(parameters: &BenchmarkParameters, nodes: &[Instance], clients: &[Instance]) -> Self {
+        let settings = &parameters.settings;
+        let (commit, dirty) = if settings.sync_working_tree {
+            (Self::git(&["rev-parse", "HEAD"]), Self::is_dirty())
+        } else {
+            (Self::resolve_commit(&settings.repository), false)
+        };
+
+        // Load generators collocated with the nodes are not listed separately.
+        let clients: Vec<_> = clients.iter().filter(|x| !nodes.contains(x)).collect();
+        let client_placement = match &settings.client_placement {
+            ClientPlacement::Dedicated if clients.is_empty() => "collocated".into(),
+            placement => placement.to_string(),
+        };
+        let instances = nodes
+            .iter()
+            .map(|x| ("node", x))
+            .chain(clients.into_iter().map(|x| ("client", x)));
+
+        Self {
+            commit: commit.unwrap_or_else(|| settings.repository.commit.clone()),
+            dirty,
+            node_config: serde_yaml::to_string(&parameters.node_parameters).unwrap_or_default(),
+            instances: Self::group(instances),
+            client_placement,
+            orchestrator_version: ORCHESTRATOR_VERSION.into(),
+        }
+    }
+
+    /// Group the instances by role, region, and specs.
+    fn group<'a>(instances: impl Iterator<Item = (&'a str, &'a Instance)>) -> Vec<InstanceGroup> {
+        let mut groups = BTreeMap::new();
+        for (role, instance) in instances {
+            let key = (role, instance.region.clone(), instance.specs.clone());
+            *groups.entry(key).or_insert(0) += 1;
+        }
+        groups
+            .into_iter()
+            .map(|((role, region, specs), count)| InstanceGroup {
+                role: role.into(),
+                region,
+                specs,
+                count,
+            })
+            .collect()
+    }
+
+    /// Resolve the commit (or branch name) of the repository into a commit hash using the local
+    /// clone of the repository, if it knows it.
+    fn resolve_commit(repository: &Repository) -> Option<String> {
+        let commit = &repository.commit;
+        Self::git(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{commit}^{{commit}}"),
+        ])
+        .or_else(|| {
+            let remote = format!("origin/{commit}^{{commit}}");
+            Self::git(&["rev-parse", "--verify", "--quiet", &remote])
+        })
+    }
+
+    /// Whether the local working tree has uncommitted changes.
+    fn is_dirty() -> bool {
+        Self::git(&["status", "--porcelain"]).is_some_and(|x| !x.is_empty())
+    }
+
+    /// Run a git command in the local working tree and return its (trimmed) output, if it
+    /// succeeded.
+    fn git(args: &[&str]) -> Option<String> {
+        let output = Command::new("git").args(args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// A one-line description of the instances (e.g., '4 node m5d.8xlarge in us-east-1').
+    pub fn instances_summary(&self) -> String {
+        self.instances
+            .iter()
+            .map(|x| format!("{} {} {} in {}", x.count, x.role, x.specs, x.region))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Provenance;
+    use crate::client::Instance;
+
+    #[test]
+    fn group_instances() {
+        let instance = |id: &str, region: &str| Instance {
+            region: region.into(),
+            specs: "m5d.8xlarge".into(),
+            ..Instance::new_for_test(id.into())
+        };
+        let nodes = [
+            instance("a", "us-east-1"),
+            instance("b", "eu-north-1"),
+            instance("c", "us-east-1"),
+        ];
+        let clients = [instance("d", "us-east-1")];
+        let instances = nodes
+            .iter()
+            .map(|x| ("node", x))
+            .chain(clients.iter().map(|x| ("client", x)));
+
+        let groups = Provenance::group(instances);
+        assert_eq!(groups.len(), 3);
+        assert_eq!((groups[0].role.as_str(), groups[0].count), ("client", 1));
+        assert_eq!(
+            (groups[2].region.as_str(), groups[2].count),
+            ("us-east-1", 2)
+        );
+    }
+}
@@ -2,26 +2,31 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    collections::HashMap,
     env,
     fmt::Display,
     fs,
     path::{Path, PathBuf},
+    process::Command,
     time::Duration,
 };
 
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DisplayFromStr, DurationSeconds};
+use serde_with::{serde_as, DisplayFromStr, DurationMilliSeconds, DurationSeconds};
 
 use crate::{
     client::Instance,
+    ensure,
     error::{SettingsError, SettingsResult},
-    faults::FaultsType,
+    faults::{ByzantineNodes, FaultsType, GrayFailure, NetworkImpairment, ResourceStress},
+    ssh::{JumpHost, SshAuthentication},
 };
 
 /// The git repository holding the codebase.
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Repository {
     /// The url of the repository.
     #[serde_as(as = "DisplayFromStr")]
@@ -47,6 +52,19 @@ impl Repository {
         self.commit = "unknown".into();
     }
 
+    /// Set the commit to the description of the local working tree (e.g., 'a1b2c3d-dirty' if
+    /// it has uncommitted changes), when deploying the working tree rather than a commit.
+    pub fn set_working_tree_commit(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(["describe", "--always", "--dirty"])
+            .output()?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).into());
+        }
+        self.commit = String::from_utf8(output.stdout)?.trim().to_string();
+        Ok(())
+    }
+
     /// Remove the Github access token from the repository url.
     pub fn remove_access_token(&mut self) {
         self.url.set_password(None).unwrap();
@@ -54,6 +72,93 @@ impl Repository {
     }
 }
 
+/// A hardware profile, running a share of the nodes on instances of different specs (e.g., to
+/// study the impact of underpowered validators).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HardwareProfile {
+    /// The specs of the instances of the profile (in all regions).
+    pub specs: String,
+    /// The share of the nodes running on instances of the profile, between 0 and 1.
+    pub share: f64,
+}
+
+impl HardwareProfile {
+    /// The number of instances (out of the specified total) belonging to the profile.
+    pub fn instances(&self, total: usize) -> usize {
+        ((total as f64 * self.share).round() as usize).min(total)
+    }
+
+    /// Whether the specified instance belongs to the profile.
+    pub fn matches(&self, instance: &Instance) -> bool {
+        Settings::same_specs(&instance.specs, &self.specs)
+    }
+}
+
+/// Where the load generators run.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum ClientPlacement {
+    /// On `dedicated_clients` instances picked in turn from each region, or collocated with
+    /// every node if there are none.
+    #[default]
+    Dedicated,
+    /// On the specified number of dedicated instances in each region of the testbed.
+    PerRegion { clients: usize },
+    /// Collocated with the specified nodes only (identified by their index in the committee),
+    /// which share the load.
+    Collocated { nodes: Vec<usize> },
+    /// On the specified number of dedicated instances in each of the specified regions, which
+    /// host no nodes (to measure the latency of transactions submitted from afar).
+    Remote {
+        regions: Vec<String>,
+        clients: usize,
+    },
+}
+
+impl Display for ClientPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Dedicated => write!(f, "dedicated"),
+            Self::PerRegion { clients } => write!(f, "{clients} per region"),
+            Self::Collocated { nodes } => {
+                let nodes: Vec<_> = nodes.iter().map(|x| x.to_string()).collect();
+                write!(f, "collocated with nodes {}", nodes.join(", "))
+            }
+            Self::Remote { regions, clients } => {
+                write!(f, "{clients} per region in {}", regions.join(", "))
+            }
+        }
+    }
+}
+
+/// The cpu architecture of the instances.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Architecture {
+    #[default]
+    #[serde(alias = "x86_64", alias = "amd64")]
+    X86_64,
+    /// E.g., AWS Graviton or GCP Tau T2A instances.
+    #[serde(alias = "arm64", alias = "aarch64")]
+    Arm64,
+}
+
+impl Architecture {
+    /// The rust target triple of the architecture.
+    pub fn rust_target(&self) -> &'static str {
+        match self {
+            Self::X86_64 => "x86_64-unknown-linux-gnu",
+            Self::Arm64 => "aarch64-unknown-linux-gnu",
+        }
+    }
+
+    /// The name of the architecture in debian (and ubuntu) packages and images.
+    pub fn debian_name(&self) -> &'static str {
+        match self {
+            Self::X86_64 => "amd64",
+            Self::Arm64 => "arm64",
+        }
+    }
+}
+
 /// The list of supported cloud providers.
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub enum CloudProvider {
@@ -64,18 +169,131 @@ pub enum CloudProvider {
     Vultr,
     #[serde(alias = "gcp")]
     Gcp,
+    #[serde(alias = "azure")]
+    Azure,
+    #[serde(alias = "digitalocean", alias = "digital_ocean")]
+    DigitalOcean,
+    #[serde(alias = "linode")]
+    Linode,
+    #[serde(alias = "terraform")]
+    Terraform,
+    /// Run the instances as local processes (for testing the benchmark pipeline).
+    #[serde(alias = "local")]
+    Local,
+    /// Run the instances as pods of an existing kubernetes cluster.
+    #[serde(alias = "kubernetes")]
+    Kubernetes,
+}
+
+impl CloudProvider {
+    /// The regions of the cloud provider, if the orchestrator knows them. This catches typos
+    /// and regions of other providers before creating any instance.
+    pub fn known_regions(&self) -> Option<&'static [&'static str]> {
+        match self {
+            Self::Aws => Some(&[
+                "us-east-1",
+                "us-east-2",
+                "us-west-1",
+                "us-west-2",
+                "us-gov-east-1",
+                "us-gov-west-1",
+                "af-south-1",
+                "ap-east-1",
+                "ap-south-1",
+                "ap-south-2",
+                "ap-northeast-1",
+                "ap-northeast-2",
+                "ap-northeast-3",
+                "ap-southeast-1",
+                "ap-southeast-2",
+                "ap-southeast-3",
+                "ap-southeast-4",
+                "ap-southeast-5",
+                "ap-southeast-7",
+                "ca-central-1",
+                "ca-west-1",
+                "eu-central-1",
+                "eu-central-2",
+                "eu-north-1",
+                "eu-south-1",
+                "eu-south-2",
+                "eu-west-1",
+                "eu-west-2",
+                "eu-west-3",
+                "il-central-1",
+                "me-central-1",
+                "me-south-1",
+                "mx-central-1",
+                "sa-east-1",
+            ]),
+            Self::DigitalOcean => Some(&[
+                "nyc1", "nyc2", "nyc3", "sfo1", "sfo2", "sfo3", "tor1", "ams2", "ams3", "lon1",
+                "fra1", "sgp1", "blr1", "syd1",
+            ]),
+            Self::Vultr => Some(&[
+                "ams", "atl", "blr", "bom", "cdg", "del", "dfw", "ewr", "fra", "hnl", "icn", "itm",
+                "jnb", "lax", "lhr", "mad", "man", "mel", "mex", "mia", "nrt", "ord", "sao", "scl",
+                "sea", "sgp", "sjc", "sto", "syd", "tlv", "waw", "yto",
+            ]),
+            _ => None,
+        }
+    }
+
+    /// The families of instance sizes of the cloud provider, if the orchestrator knows them.
+    /// The size slugs start with their family (e.g., 's-2vcpu-4gb' on DigitalOcean or
+    /// 'vhf-2c-4gb' on Vultr).
+    pub fn known_specs_families(&self) -> Option<&'static [&'static str]> {
+        match self {
+            Self::DigitalOcean => {
+                Some(&["s", "c", "c2", "g", "gd", "m", "m3", "so", "so1_5", "gpu"])
+            }
+            Self::Vultr => Some(&["vc2", "vhf", "vhp", "voc", "vdc", "vbm", "vcg"]),
+            _ => None,
+        }
+    }
+
+    /// Whether the cloud provider authenticates with the token file of the settings.
+    pub fn uses_token(&self) -> bool {
+        matches!(
+            self,
+            Self::Aws | Self::Vultr | Self::Gcp | Self::DigitalOcean | Self::Linode
+        )
+    }
+}
+
+/// The list of protocols that the orchestrator can benchmark.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Protocol {
+    #[default]
+    #[serde(alias = "mysticeti")]
+    Mysticeti,
+    /// The single-leader baseline: Mysticeti committing a single leader per wave, without
+    /// pipelining the waves.
+    #[serde(alias = "single_leader", alias = "single-leader")]
+    SingleLeader,
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mysticeti => write!(f, "mysticeti"),
+            Self::SingleLeader => write!(f, "single-leader"),
+        }
+    }
 }
 
 /// The testbed settings. Those are topically specified in a file.
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Settings {
     /// The testbed unique id. This allows multiple users to run concurrent testbeds on the
     /// same cloud provider's account without interference with each others.
     pub testbed_id: String,
     /// The cloud provider hosting the testbed.
     pub cloud_provider: CloudProvider,
-    /// The path to the secret token for authentication with the cloud provider.
+    /// The path to the secret token for authentication with the cloud provider (the json key
+    /// of a service account on GCP).
     #[serde(skip_serializing)]
     pub token_file: PathBuf,
     /// The ssh private key to access the instances.
@@ -84,13 +302,61 @@ pub struct Settings {
     /// The corresponding ssh public key registered on the instances. If not specified. the
     /// public key defaults the same path as the private key with an added extension 'pub'.
     pub ssh_public_key_file: Option<PathBuf>,
-    /// The list of cloud provider regions to deploy the testbed.
+    /// The username used to access the instances through ssh. If not specified, it defaults to
+    /// the default user of the cloud provider's images (e.g., 'ubuntu' on AWS). This notably
+    /// selects the user account that GCP creates for the registered ssh key.
+    #[serde(default)]
+    pub ssh_username: Option<String>,
+    /// The passphrase of the ssh private key (if encrypted). Rather than writing it in the
+    /// settings file, reference an environment variable (e.g., '${SSH_KEY_PASSPHRASE}').
+    #[serde(default, skip_serializing)]
+    pub ssh_passphrase: Option<String>,
+    /// Whether to authenticate to the instances with the identities of the ssh agent of the user
+    /// (found through `SSH_AUTH_SOCK`) rather than with the private key file.
+    #[serde(default)]
+    pub ssh_agent: bool,
+    /// The jump host (bastion) relaying the ssh connections to the instances, as
+    /// `[user@]host[:port]`, for cloud accounts that do not allow public addresses on the
+    /// instances. The orchestrator then reaches the instances through their private addresses,
+    /// with the same private key as the jump host.
+    #[serde(default)]
+    pub ssh_jump_host: Option<String>,
+    /// The list of cloud provider regions to deploy the testbed. Those are the provider's own
+    /// region names, e.g., 'us-east-1' on AWS or the region slug 'nyc3' on DigitalOcean.
     pub regions: Vec<String>,
     /// The specs of the instances to deploy. Those are dependent on the cloud provider, e.g.,
-    /// specifying 't3.medium' creates instances with 2 vCPU and 4GBo of ram on AWS.
+    /// specifying 't3.medium' creates instances with 2 vCPU and 4GBo of ram on AWS (the same
+    /// machine is the size slug 's-2vcpu-4gb' on DigitalOcean).
     pub specs: String,
+    /// The specs of the instances of specific regions, overriding `specs` (e.g., for regions
+    /// lacking the default machine type).
+    #[serde(default)]
+    pub region_specs: HashMap<String, String>,
+    /// The hardware profiles of the testbed. Each profile runs a share of the nodes (the ones
+    /// with the lowest authority indices) on instances of its specs; the remaining nodes run
+    /// on instances of the default specs.
+    #[serde(default)]
+    pub hardware_profiles: Vec<HardwareProfile>,
+    /// The hourly price (in USD) of the instances of specific specs, overriding the built-in
+    /// (approximate, on-demand) prices used to estimate the cost of the testbed.
+    #[serde(default)]
+    pub instance_prices: HashMap<String, f64>,
+    /// The time to live of the testbeds (in seconds), recorded when deploying instances. Every
+    /// invocation of the orchestrator warns about testbeds past their ttl, and the command
+    /// 'testbed reap' destroys them. If not specified, testbeds live until destroyed.
+    #[serde(default)]
+    #[serde_as(as = "Option<DurationSeconds>")]
+    pub testbed_ttl: Option<Duration>,
+    /// The directory (on the local machine) recording the deployment time and ttl of the
+    /// testbeds.
+    #[serde(default = "defaults::default_testbeds_dir")]
+    pub testbeds_dir: PathBuf,
     /// The details of the git reposit to deploy.
     pub repository: Repository,
+    /// The protocol to benchmark. The baselines run from the same codebase, so that the
+    /// benchmarks of different protocols are comparable (e.g., with the `compare` command).
+    #[serde(default)]
+    pub protocol: Protocol,
     /// The path to the node's configuration file. If not specified, the orchestrator uses the
     /// default configurations.
     pub node_parameters_path: Option<String>,
@@ -105,6 +371,25 @@ pub struct Settings {
     /// The default faults type to apply to the testbed's nodes.
     #[serde(default = "defaults::default_faults_type")]
     pub faults: FaultsType,
+    /// The network impairments (latency, jitter, and packet loss) to emulate with `tc netem` on
+    /// the instances of the nodes, e.g., to emulate a WAN on a single-region testbed. Each
+    /// impairment applies to the egress traffic of some nodes, optionally only to some peers.
+    /// Not supported by the local and kubernetes cloud providers.
+    #[serde(default)]
+    pub network_impairments: Vec<NetworkImpairment>,
+    /// The resource-stress faults (cpu, memory, disk, or fsync stress) to run on the instances
+    /// of some nodes during a window of the benchmark, to study how the protocol degrades when
+    /// nodes are starved of a resource rather than crashed.
+    #[serde(default)]
+    pub resource_stress: Vec<ResourceStress>,
+    /// The gray failures (slow disk, lossy nic, or process pauses) degrading some nodes during a
+    /// window of the benchmark, without crashing them.
+    #[serde(default)]
+    pub gray_failures: Vec<GrayFailure>,
+    /// The byzantine nodes deviating from the protocol during the benchmarks (if any), e.g.,
+    /// withholding their votes for the leaders.
+    #[serde(default)]
+    pub byzantine: Option<ByzantineNodes>,
     /// The working directory on the remote instance (containing all configuration files).
     #[serde(default = "defaults::default_working_dir")]
     pub working_dir: PathBuf,
@@ -121,16 +406,61 @@ pub struct Settings {
     #[serde(default = "defaults::default_scrape_interval")]
     #[serde_as(as = "DurationSeconds")]
     pub scrape_interval: Duration,
+    /// The initial part of the benchmark (startup of the nodes and ramp-up of the load) excluded
+    /// from the throughput and latency measurements.
+    #[serde(default)]
+    #[serde_as(as = "DurationSeconds")]
+    pub warmup: Duration,
+    /// The final part of the benchmark (shutdown) excluded from the throughput and latency
+    /// measurements.
+    #[serde(default)]
+    #[serde_as(as = "DurationSeconds")]
+    pub cooldown: Duration,
     /// Whether to downloading and analyze the client and node log files.
     #[serde(default = "defaults::default_log_processing")]
     pub log_processing: bool,
+    /// The maximum size (in MB) of each log file to download, keeping only the end of larger
+    /// files. If not specified, the log files are downloaded in full.
+    #[serde(default)]
+    pub log_max_size: Option<u64>,
+    /// Only download the lines of the log files matching this (extended) regular expression
+    /// (e.g., 'ERROR|panicked'). If not specified, all lines are downloaded.
+    #[serde(default)]
+    pub log_filter: Option<String>,
+    /// Whether to log the consensus events of the nodes (block creation, leader timeouts, and
+    /// commits) and merge the node log files into a single timeline when downloading them.
+    #[serde(default)]
+    pub log_timeline: bool,
+    /// Whether to replay the storage of all nodes after each benchmark and check that they
+    /// committed the same sequence.
+    #[serde(default = "defaults::default_safety_audit")]
+    pub safety_audit: bool,
+    /// Whether to compare the commit checkpoints of the nodes at every scrape during the
+    /// benchmark, and abort it as soon as two nodes disagree.
+    #[serde(default = "defaults::default_consistency_check")]
+    pub consistency_check: bool,
     /// Number of instances running only load generators (not nodes). If this value is set
     /// to zero, the orchestrator runs a load generate collocated with each node.
     #[serde(default = "defaults::default_dedicated_clients")]
     pub dedicated_clients: usize,
+    /// Where the load generators run: collocated with the nodes or on `dedicated_clients`
+    /// instances by default, or following another placement policy (e.g., in regions without
+    /// nodes). The placement is recorded with the measurements.
+    #[serde(default)]
+    pub client_placement: ClientPlacement,
     /// Whether to start a grafana and prometheus instance on a dedicate machine.
     #[serde(default = "defaults::default_monitoring")]
     pub monitoring: bool,
+    /// The maximum clock offset (in milliseconds) of the nodes with respect to their time
+    /// servers. The orchestrator checks the offsets before every benchmark, since latencies
+    /// measured across machines are meaningless with skewed clocks.
+    #[serde(default = "defaults::default_max_clock_offset")]
+    #[serde_as(as = "DurationMilliSeconds")]
+    pub max_clock_offset: Duration,
+    /// Whether to run benchmarks despite clock offsets above the maximum (only warning about
+    /// them) rather than refusing to start.
+    #[serde(default)]
+    pub allow_clock_skew: bool,
     /// The timeout duration for ssh commands (in seconds).
     #[serde(default = "defaults::default_ssh_timeout")]
     #[serde_as(as = "DurationSeconds")]
@@ -138,6 +468,85 @@ pub struct Settings {
     /// The number of times the orchestrator should retry an ssh command.
     #[serde(default = "defaults::default_ssh_retries")]
     pub ssh_retries: usize,
+    /// Whether to print the output of the long-running remote commands (the compilation of the
+    /// codebase and the startup of the nodes) as it comes, prefixed by the name of each instance,
+    /// rather than only reporting their completion. This keeps an ssh connection open to each
+    /// instance while the commands run.
+    #[serde(default)]
+    pub stream_output: bool,
+    /// Whether the nodes communicate through the private network of the testbed rather than
+    /// through their public addresses (ssh and metrics still use the public addresses). This
+    /// requires every instance to have a private address reachable by all the others: GCP's
+    /// default network spans all regions, while AWS and Vultr only provide private networks
+    /// within a region (so all instances should then be in the same region).
+    #[serde(default)]
+    pub private_networking: bool,
+    /// Whether to launch the instances of each region in a cluster placement group (i.e., on
+    /// nearby hardware) so that the variance of intra-region latencies does not pollute the
+    /// measurements. Only used by AWS; not all instance types support cluster placement.
+    #[serde(default)]
+    pub aws_placement_group: bool,
+    /// Whether to require enhanced networking (ENA) on the instances. The orchestrator then
+    /// checks that both the instance type and the image support it, and launches EBS-optimized
+    /// instances (so that storage traffic does not compete with the network). Only used by AWS.
+    #[serde(default)]
+    pub aws_enhanced_networking: bool,
+    /// The key of the project metadata holding the ssh keys of the instances (e.g., 'sshKeys'
+    /// for legacy projects). If not specified, it defaults to 'ssh-keys'. Only used by GCP.
+    #[serde(default)]
+    pub gcp_ssh_keys_metadata: Option<String>,
+    /// The source of the terraform module describing the instances (e.g., a local path or a
+    /// git url). Only used (and required) by the terraform cloud provider.
+    #[serde(default)]
+    pub terraform_module: Option<String>,
+    /// The directory (on the local machine) holding the rendered terraform configuration and
+    /// the terraform state of the testbeds.
+    #[serde(default = "defaults::default_terraform_dir")]
+    pub terraform_dir: PathBuf,
+    /// The directory (on the local machine) holding the home directories of the instances of
+    /// local testbeds. Only used by the local cloud provider.
+    #[serde(default = "defaults::default_local_dir")]
+    pub local_dir: PathBuf,
+    /// The kubernetes namespace (which must exist) hosting the pods of the testbeds. Only used
+    /// by the kubernetes cloud provider.
+    #[serde(default = "defaults::default_kubernetes_namespace")]
+    pub kubernetes_namespace: String,
+    /// The container image (including its registry, e.g., 'ghcr.io/org/mysticeti:bench') to
+    /// deploy on the instances. If specified, the orchestrator builds the image once on the
+    /// local machine (from the repository url and commit, which requires a '.git' url) and
+    /// pushes it, rather than compiling the codebase on every instance.
+    #[serde(default)]
+    pub docker_image: Option<String>,
+    /// The cpu architecture of the instances (which must match the specs). It selects the
+    /// image of the instances on AWS, GCP, and Azure.
+    #[serde(default)]
+    pub architecture: Architecture,
+    /// Whether to compile the codebase once on the local machine (for the architecture of the
+    /// instances) and upload the binary to the instances, rather than compiling it on every
+    /// instance. This compiles the local working tree, which should thus be at the commit
+    /// specified in the settings, and cross-compiling requires the linker of the target (e.g.,
+    /// 'aarch64-linux-gnu-gcc') and its rust standard library ('rustup target add').
+    #[serde(default)]
+    pub cross_compile: bool,
+    /// Whether to build the nodes with jemalloc and its heap profiling support (the
+    /// `heap-profiling` feature), to profile the heap of some nodes during the benchmarks. This
+    /// does not apply to prebuilt binaries.
+    #[serde(default)]
+    pub heap_profiling: bool,
+    /// The url of a prebuilt binary (e.g., the artifact of a release) to deploy on the
+    /// instances, rather than compiling the codebase. The other binaries of the protocol (such
+    /// as the load generator) are downloaded from the same location. The binaries must match the
+    /// architecture of the instances; the measurements still report the commit of the settings,
+    /// which should thus be the one of the release.
+    #[serde(default)]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub release_binary: Option<Url>,
+    /// Whether to synchronize (with rsync) the local working tree, including its uncommitted
+    /// changes, to the instances rather than checking out the commit of the repository. The
+    /// measurements then report the description of the working tree (e.g., 'a1b2c3d-dirty')
+    /// as commit. This requires rsync on the local machine and the instances.
+    #[serde(default)]
+    pub sync_working_tree: bool,
 }
 
 mod defaults {
@@ -177,6 +586,14 @@ mod defaults {
         false
     }
 
+    pub fn default_safety_audit() -> bool {
+        false
+    }
+
+    pub fn default_consistency_check() -> bool {
+        false
+    }
+
     pub fn default_dedicated_clients() -> usize {
         0
     }
@@ -189,21 +606,66 @@ mod defaults {
         Duration::from_secs(60)
     }
 
+    pub fn default_max_clock_offset() -> Duration {
+        Duration::from_millis(100)
+    }
+
     pub fn default_ssh_retries() -> usize {
         3
     }
+
+    pub fn default_testbeds_dir() -> PathBuf {
+        [".", "testbeds"].iter().collect()
+    }
+
+    pub fn default_terraform_dir() -> PathBuf {
+        [".", "terraform"].iter().collect()
+    }
+
+    pub fn default_local_dir() -> PathBuf {
+        [".", "local"].iter().collect()
+    }
+
+    pub fn default_kubernetes_namespace() -> String {
+        "mysticeti".into()
+    }
 }
 
 impl Settings {
-    /// Load the settings from a json file.
-    pub fn load<P>(path: P) -> SettingsResult<Self>
+    /// The prefix of the environment variables overriding fields of the settings file.
+    const ENV_PREFIX: &'static str = "ORCHESTRATOR_";
+
+    /// Load the settings from a json file. Fields of the file are overridden by the environment
+    /// variables prefixed by `ORCHESTRATOR_` (see `env_key`), and then by the specified overrides
+    /// (formatted as 'key=value').
+    pub fn load<P>(path: P, overrides: &[String]) -> SettingsResult<Self>
     where
         P: AsRef<Path> + Display + Clone,
     {
         let reader = || -> Result<Self, Box<dyn std::error::Error>> {
             let data = fs::read(path.clone())?;
             let data = Self::resolve_env(&path, std::str::from_utf8(&data)?)?;
-            let settings: Settings = serde_yaml::from_slice(data.as_bytes())?;
+            let mut value: serde_yaml::Value = serde_yaml::from_slice(data.as_bytes())?;
+
+            let mut env_overrides: Vec<_> = env::vars()
+                .filter_map(|(name, value)| Some((Self::env_key(&name)?, value)))
+                .collect();
+            env_overrides.sort();
+            for (key, field) in env_overrides {
+                Self::override_field(&mut value, &key, &field)?;
+            }
+            for x in overrides {
+                let (key, field) = x
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid override '{x}' (expected 'key=value')"))?;
+                Self::override_field(&mut value, key, field)?;
+            }
+
+            let mut settings: Settings = serde_yaml::from_value(value)?;
+            settings.validate()?;
+            if settings.sync_working_tree {
+                settings.repository.set_working_tree_commit()?;
+            }
 
             fs::create_dir_all(&settings.results_dir)?;
             fs::create_dir_all(&settings.logs_dir)?;
@@ -217,6 +679,302 @@ impl Settings {
         })
     }
 
+    /// Check the settings up front, rather than failing halfway through the creation of the
+    /// testbed: the regions must exist on the cloud provider, the key files must exist, the
+    /// repository url must be cloneable, and the options must not conflict with each other.
+    pub fn validate(&self) -> SettingsResult<()> {
+        let invalid = |field, message: String| SettingsError::InvalidField { field, message };
+
+        // Check the regions of the testbed.
+        let cloud = !matches!(
+            self.cloud_provider,
+            CloudProvider::Local | CloudProvider::Kubernetes
+        );
+        ensure!(
+            !cloud || !self.regions.is_empty(),
+            invalid("regions", "specify at least one region".into())
+        );
+        if let Some(known) = self.cloud_provider.known_regions() {
+            for region in &self.regions {
+                ensure!(
+                    known.contains(&region.as_str()),
+                    invalid(
+                        "regions",
+                        format!("unknown region '{region}' (valid: {})", known.join(", "))
+                    )
+                );
+            }
+        }
+        if matches!(self.cloud_provider, CloudProvider::Gcp) {
+            for region in &self.regions {
+                let zone = region.rsplit_once('-').is_some_and(|(_, x)| {
+                    x.len() == 1 && x.chars().all(|c| c.is_ascii_lowercase())
+                });
+                ensure!(
+                    zone,
+                    invalid(
+                        "regions",
+                        format!(
+                            "'{region}' is not a zone (GCP regions are zones, e.g., '{region}-a')"
+                        )
+                    )
+                );
+            }
+        }
+        if let Some(families) = self.cloud_provider.known_specs_families() {
+            let specs = std::iter::once(&self.specs)
+                .chain(self.region_specs.values())
+                .chain(self.hardware_profiles.iter().map(|x| &x.specs));
+            for specs in specs {
+                let known = specs
+                    .split_once('-')
+                    .is_some_and(|(family, _)| families.contains(&family));
+                ensure!(
+                    known,
+                    invalid(
+                        "specs",
+                        format!(
+                            "unknown size '{specs}' (valid families: {})",
+                            families.join(", ")
+                        )
+                    )
+                );
+            }
+        }
+        for region in self.region_specs.keys() {
+            ensure!(
+                self.regions.contains(region),
+                invalid(
+                    "region_specs",
+                    format!("region '{region}' is not one of the testbed regions")
+                )
+            );
+        }
+
+        // Check the key files.
+        if self.cloud_provider.uses_token() {
+            ensure!(
+                self.token_file.is_file(),
+                invalid(
+                    "token_file",
+                    format!("file '{}' not found", self.token_file.display())
+                )
+            );
+        }
+        if self.uses_ssh() && !self.ssh_agent {
+            ensure!(
+                self.ssh_private_key_file.is_file(),
+                invalid(
+                    "ssh_private_key_file",
+                    format!("file '{}' not found", self.ssh_private_key_file.display())
+                )
+            );
+        }
+        if let Some(file) = &self.ssh_public_key_file {
+            ensure!(
+                file.is_file(),
+                invalid(
+                    "ssh_public_key_file",
+                    format!("file '{}' not found", file.display())
+                )
+            );
+        }
+
+        // Check the repository url.
+        let url = &self.repository.url;
+        ensure!(
+            ["http", "https", "ssh", "git", "file"].contains(&url.scheme()),
+            invalid(
+                "repository.url",
+                format!(
+                    "unsupported scheme '{}' (use https, ssh, git, or file)",
+                    url.scheme()
+                )
+            )
+        );
+        ensure!(
+            url.path_segments()
+                .and_then(|mut x| x.next_back())
+                .is_some_and(|x| !x.is_empty()),
+            invalid(
+                "repository.url",
+                format!("'{url}' does not name a repository (e.g., 'https://host/author/repo')")
+            )
+        );
+        ensure!(
+            !self.repository.commit.is_empty(),
+            invalid(
+                "repository.commit",
+                "specify a commit or branch name".into()
+            )
+        );
+
+        // Check the placement of the load generators.
+        match &self.client_placement {
+            ClientPlacement::Dedicated | ClientPlacement::PerRegion { .. } => (),
+            ClientPlacement::Collocated { nodes } => ensure!(
+                !nodes.is_empty(),
+                invalid(
+                    "client_placement",
+                    "specify at least one node hosting a load generator".into()
+                )
+            ),
+            ClientPlacement::Remote { regions, .. } => {
+                for region in regions {
+                    ensure!(
+                        self.regions.contains(region),
+                        invalid(
+                            "client_placement",
+                            format!("region '{region}' is not one of the testbed regions")
+                        )
+                    );
+                }
+                ensure!(
+                    regions.len() < self.regions.len(),
+                    invalid(
+                        "client_placement",
+                        "leave at least one region of the testbed to the nodes".into()
+                    )
+                );
+            }
+        }
+        ensure!(
+            self.dedicated_clients == 0 || self.client_placement == ClientPlacement::Dedicated,
+            invalid(
+                "dedicated_clients",
+                format!(
+                    "conflicts with the '{}' client placement",
+                    self.client_placement
+                )
+            )
+        );
+
+        // Check the conflicting options.
+        let builds = [
+            ("docker_image", self.docker_image.is_some()),
+            ("release_binary", self.release_binary.is_some()),
+            ("cross_compile", self.cross_compile),
+            ("sync_working_tree", self.sync_working_tree),
+        ];
+        let selected: Vec<_> = builds.iter().filter(|(_, x)| *x).map(|(x, _)| *x).collect();
+        ensure!(
+            selected.len() <= 1,
+            invalid(
+                selected[0],
+                format!(
+                    "conflicts with '{}' (select one way to deploy the codebase)",
+                    selected[1]
+                )
+            )
+        );
+        ensure!(
+            self.docker_image.is_none() || url.path().ends_with(".git"),
+            invalid(
+                "docker_image",
+                "building the image requires a '.git' repository url".into()
+            )
+        );
+        ensure!(
+            !(self.ssh_agent && self.ssh_passphrase.is_some()),
+            invalid(
+                "ssh_passphrase",
+                "conflicts with 'ssh_agent' (the agent holds the decrypted keys)".into()
+            )
+        );
+        ensure!(
+            !matches!(self.cloud_provider, CloudProvider::Terraform)
+                || self.terraform_module.is_some(),
+            invalid(
+                "terraform_module",
+                "the terraform cloud provider requires a module".into()
+            )
+        );
+        // Only AWS, GCP, and Vultr report the private addresses of the instances.
+        let regional = matches!(
+            self.cloud_provider,
+            CloudProvider::Aws | CloudProvider::Vultr
+        );
+        let gcp = matches!(self.cloud_provider, CloudProvider::Gcp);
+        ensure!(
+            !self.private_networking || regional || gcp,
+            invalid(
+                "private_networking",
+                "the instances of this cloud provider have no private address".into()
+            )
+        );
+        ensure!(
+            !(self.private_networking && regional && self.regions.len() > 1),
+            invalid(
+                "private_networking",
+                "the private networks of this cloud provider do not span regions".into()
+            )
+        );
+        Ok(())
+    }
+
+    /// Check that the byzantine nodes and the crashed nodes of a committee of the specified size
+    /// do not exceed the faults tolerated by the protocol (otherwise the benchmark may lose
+    /// safety rather than measure the cost of the faults).
+    pub fn validate_faults(&self, committee_size: usize) -> SettingsResult<()> {
+        let Some(byzantine) = &self.byzantine else {
+            return Ok(());
+        };
+        let byzantine = byzantine.nodes(committee_size).len();
+        let crashed = self.faults.len();
+        let tolerated = committee_size.saturating_sub(1) / 3;
+        ensure!(
+            byzantine + crashed <= tolerated,
+            SettingsError::InvalidField {
+                field: "byzantine",
+                message: format!(
+                    "{byzantine} byzantine and {crashed} crashed nodes exceed the {tolerated} \
+                    faults tolerated by a committee of {committee_size} nodes"
+                ),
+            }
+        );
+        Ok(())
+    }
+
+    /// The settings field (as a path of field names separated by dots) overridden by an
+    /// environment variable, if any: e.g., 'ORCHESTRATOR_SPECS' overrides 'specs' and
+    /// 'ORCHESTRATOR_REPOSITORY__COMMIT' overrides 'repository.commit'.
+    fn env_key(name: &str) -> Option<String> {
+        let key = name.strip_prefix(Self::ENV_PREFIX)?;
+        Some(key.to_lowercase().replace("__", "."))
+    }
+
+    /// Override a field of the settings (specified as a path of field names separated by dots).
+    /// The value is parsed as yaml (e.g., '[us-east-1, eu-west-1]' for a list), unless the field
+    /// is already a string (e.g., a commit hash made of digits).
+    fn override_field(
+        settings: &mut serde_yaml::Value,
+        key: &str,
+        value: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (parents, field) = match key.rsplit_once('.') {
+            Some((parents, field)) => (parents.split('.').collect(), field),
+            None => (Vec::new(), key),
+        };
+        let mut current = settings;
+        for parent in parents {
+            let mapping = current
+                .as_mapping_mut()
+                .ok_or_else(|| format!("Cannot override '{key}': '{parent}' is not a struct"))?;
+            current = mapping
+                .entry(parent.into())
+                .or_insert_with(|| serde_yaml::Mapping::new().into());
+        }
+        let mapping = current
+            .as_mapping_mut()
+            .ok_or_else(|| format!("Cannot override '{key}': its parent is not a struct"))?;
+        let value = match mapping.get(field) {
+            Some(serde_yaml::Value::String(_)) => value.into(),
+            _ => serde_yaml::from_str(value)?,
+        };
+        mapping.insert(field.into(), value);
+        Ok(())
+    }
+
     // Resolves ${ENV} into it's value for each env variable.
     fn resolve_env<P>(path: P, s: &str) -> SettingsResult<String>
     where
@@ -275,11 +1033,118 @@ impl Settings {
         }
     }
 
+    /// Override the testbed id, e.g., to run several testbeds from the same settings file. All
+    /// cloud providers tag (and name) the resources of a testbed after its id, so the name
+    /// may only contain lowercase letters, digits, and hyphens.
+    pub fn set_testbed_name<S: Into<String>>(&mut self, name: S) -> SettingsResult<()> {
+        let name = name.into();
+        let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        ensure!(valid, SettingsError::InvalidTestbedName(name));
+        self.testbed_id = name;
+        Ok(())
+    }
+
+    /// The username used to access the instances through ssh (if not overridden by the
+    /// settings, the default username of the cloud provider).
+    pub fn ssh_username<'a>(&'a self, default: &'a str) -> &'a str {
+        self.ssh_username.as_deref().unwrap_or(default)
+    }
+
+    /// How the orchestrator authenticates to the instances through ssh.
+    pub fn ssh_authentication(&self) -> SshAuthentication {
+        if self.ssh_agent {
+            SshAuthentication::Agent
+        } else {
+            SshAuthentication::PrivateKey {
+                file: self.ssh_private_key_file.clone(),
+                passphrase: self.ssh_passphrase.clone(),
+            }
+        }
+    }
+
+    /// The jump host relaying the ssh connections to the instances (if any).
+    pub fn jump_host(&self) -> Option<JumpHost> {
+        let authentication = self.ssh_authentication();
+        self.ssh_jump_host
+            .clone()
+            .map(|x| JumpHost::new(x, authentication))
+    }
+
+    /// Whether the testbed is made of local processes (rather than cloud instances).
+    pub fn is_local(&self) -> bool {
+        matches!(self.cloud_provider, CloudProvider::Local)
+    }
+
+    /// Whether the orchestrator accesses the instances through ssh.
+    pub fn uses_ssh(&self) -> bool {
+        !matches!(
+            self.cloud_provider,
+            CloudProvider::Local | CloudProvider::Kubernetes
+        )
+    }
+
+    /// The number of instances dedicated to the load generators.
+    pub fn dedicated_client_instances(&self) -> usize {
+        match &self.client_placement {
+            ClientPlacement::Dedicated => self.dedicated_clients,
+            ClientPlacement::PerRegion { clients } => clients * self.regions.len(),
+            ClientPlacement::Collocated { .. } => 0,
+            ClientPlacement::Remote { regions, clients } => clients * regions.len(),
+        }
+    }
+
+    /// The specs of the instances of the specified region.
+    pub fn specs_for_region(&self, region: &str) -> &str {
+        self.region_specs.get(region).unwrap_or(&self.specs)
+    }
+
+    /// The specs of the specified instance as configured (rather than as reported by the cloud
+    /// provider): those of its hardware profile, if any, or of its region.
+    pub fn configured_specs(&self, instance: &Instance) -> &str {
+        self.hardware_profiles
+            .iter()
+            .find(|x| x.matches(instance))
+            .map_or_else(|| self.specs_for_region(&instance.region), |x| &x.specs)
+    }
+
+    /// Whether two specs designate the same machine type (cloud providers may not report them
+    /// exactly as specified, e.g., AWS reports 'M5d8xlarge' for 'm5d.8xlarge').
+    pub fn same_specs(a: &str, b: &str) -> bool {
+        a.to_lowercase().replace('.', "") == b.to_lowercase().replace('.', "")
+    }
+
+    /// Whether the settings accept instances of the specified specs in the specified region:
+    /// those of the region or of any hardware profile.
+    pub fn accepts_specs(&self, region: &str, specs: &str) -> bool {
+        Self::same_specs(specs, self.specs_for_region(region))
+            || self
+                .hardware_profiles
+                .iter()
+                .any(|x| Self::same_specs(specs, &x.specs))
+    }
+
+    /// Split the specified number of instances of a region by specs: each hardware profile gets
+    /// its share of the instances and the remaining ones have the default specs of the region.
+    pub fn instances_by_specs(&self, region: &str, quantity: usize) -> Vec<(String, usize)> {
+        let mut remaining = quantity;
+        let mut instances = Vec::new();
+        for profile in &self.hardware_profiles {
+            let profile_quantity = profile.instances(quantity).min(remaining);
+            instances.push((profile.specs.clone(), profile_quantity));
+            remaining -= profile_quantity;
+        }
+        instances.push((self.specs_for_region(region).to_string(), remaining));
+        instances.retain(|(_, quantity)| *quantity > 0);
+        instances
+    }
+
     /// Check whether the input instance matches the criteria described in the settings.
     pub fn filter_instances(&self, instance: &Instance) -> bool {
         self.regions.contains(&instance.region)
-            && instance.specs.to_lowercase().replace('.', "")
-                == self.specs.to_lowercase().replace('.', "")
+            && self.accepts_specs(&instance.region, &instance.specs)
     }
 
     /// The number of regions specified in the settings.
@@ -310,9 +1175,14 @@ impl Settings {
 
 #[cfg(test)]
 mod test {
+    use mysticeti_core::config::ByzantineStrategy;
     use reqwest::Url;
 
-    use crate::settings::Settings;
+    use crate::{
+        error::SettingsError,
+        faults::{ByzantineNodes, FaultsType},
+        settings::{ClientPlacement, CloudProvider, HardwareProfile, Repository, Settings},
+    };
 
     #[test]
     fn load_ssh_public_key() {
@@ -338,4 +1208,199 @@ mod test {
             Url::parse("https://example.com/author/name").unwrap()
         );
     }
+
+    #[test]
+    fn set_testbed_name() {
+        let mut settings = Settings::new_for_test();
+        settings.set_testbed_name("alice-exp-2").unwrap();
+        assert_eq!(settings.testbed_id, "alice-exp-2");
+        assert!(settings.set_testbed_name("Alice_exp").is_err());
+        assert!(settings.set_testbed_name("").is_err());
+        assert_eq!(settings.testbed_id, "alice-exp-2");
+    }
+
+    #[test]
+    fn ssh_username() {
+        let mut settings = Settings::new_for_test();
+        assert_eq!(settings.ssh_username("ubuntu"), "ubuntu");
+        settings.ssh_username = Some("alice".into());
+        assert_eq!(settings.ssh_username("ubuntu"), "alice");
+    }
+
+    #[test]
+    fn jump_host() {
+        let mut settings = Settings::new_for_test();
+        assert!(settings.jump_host().is_none());
+        settings.ssh_jump_host = Some("alice@bastion.example.com:2222".into());
+        let jump_host = settings.jump_host().unwrap();
+        assert!(jump_host
+            .proxy_command("%h:%p")
+            .starts_with("ssh -i /path/to/private/key/file "));
+        assert!(jump_host
+            .proxy_command("%h:%p")
+            .ends_with("-W %h:%p ssh://alice@bastion.example.com:2222"));
+
+        // The ssh agent provides the key.
+        settings.ssh_agent = true;
+        let jump_host = settings.jump_host().unwrap();
+        assert!(!jump_host.proxy_command("%h:%p").contains("-i "));
+    }
+
+    #[test]
+    fn overrides() {
+        let mut value: serde_yaml::Value = serde_yaml::from_str(
+            "specs: m5d.8xlarge\nrepository:\n  url: https://example.com/name\n  commit: main",
+        )
+        .unwrap();
+        Settings::override_field(&mut value, "specs", "m5d.2xlarge").unwrap();
+        Settings::override_field(&mut value, "regions", "[us-east-1, eu-west-1]").unwrap();
+        Settings::override_field(&mut value, "repository.commit", "1234567").unwrap();
+        Settings::override_field(&mut value, "ssh_retries", "3").unwrap();
+        assert!(Settings::override_field(&mut value, "specs.name", "x").is_err());
+
+        assert_eq!(value["specs"], "m5d.2xlarge");
+        assert_eq!(value["regions"][1], "eu-west-1");
+        assert_eq!(value["repository"]["commit"], "1234567");
+        assert_eq!(value["ssh_retries"], 3);
+
+        let key = Settings::env_key("ORCHESTRATOR_REPOSITORY__COMMIT");
+        assert_eq!(key.as_deref(), Some("repository.commit"));
+        assert_eq!(Settings::env_key("HOME"), None);
+    }
+
+    #[test]
+    fn client_placement() {
+        let mut settings = Settings::new_for_test();
+        settings.regions = vec!["us-east-1".into(), "eu-west-1".into(), "ap-south-1".into()];
+        settings.dedicated_clients = 2;
+        assert_eq!(settings.dedicated_client_instances(), 2);
+
+        settings.dedicated_clients = 0;
+        settings.client_placement = ClientPlacement::Remote {
+            regions: vec!["ap-south-1".into()],
+            clients: 3,
+        };
+        assert_eq!(settings.dedicated_client_instances(), 3);
+        assert_eq!(
+            settings.client_placement.to_string(),
+            "3 per region in ap-south-1"
+        );
+
+        settings.client_placement = ClientPlacement::PerRegion { clients: 1 };
+        assert_eq!(settings.dedicated_client_instances(), 3);
+        settings.client_placement = ClientPlacement::Collocated { nodes: vec![0, 2] };
+        assert_eq!(settings.dedicated_client_instances(), 0);
+    }
+
+    #[test]
+    fn validate() {
+        let mut settings = Settings::new_for_test();
+        settings.token_file = settings.ssh_public_key_file.clone().unwrap();
+        settings.ssh_private_key_file = settings.token_file.clone();
+        settings.regions = vec!["us-east-1".into(), "eu-west-1".into()];
+        settings.validate().unwrap();
+
+        settings.regions.push("nyc3".into());
+        assert!(settings.validate().is_err());
+        settings.regions.pop();
+
+        let mut vultr = settings.clone();
+        vultr.cloud_provider = CloudProvider::Vultr;
+        vultr.regions = vec!["ewr".into(), "fra".into()];
+        vultr.specs = "vhf-2c-4gb".into();
+        vultr.validate().unwrap();
+        vultr.regions.push("us-east-1".into());
+        assert!(vultr.validate().is_err());
+        vultr.regions.pop();
+        vultr
+            .region_specs
+            .insert("fra".into(), "s-2vcpu-4gb".into());
+        assert!(vultr.validate().is_err());
+
+        settings.cross_compile = true;
+        settings.release_binary = Some(Url::parse("https://example.com/mysticeti").unwrap());
+        assert!(settings.validate().is_err());
+        settings.release_binary = None;
+        settings.validate().unwrap();
+
+        settings.repository.url = Url::parse("ftp://example.com/author/repo").unwrap();
+        assert!(settings.validate().is_err());
+        settings.repository.url = Url::parse("https://example.com/author/repo").unwrap();
+
+        settings.client_placement = ClientPlacement::Remote {
+            regions: vec!["ap-south-1".into()],
+            clients: 1,
+        };
+        assert!(settings.validate().is_err());
+        settings.client_placement = ClientPlacement::Dedicated;
+
+        let private_networking = |settings: &Settings| {
+            matches!(
+                settings.validate(),
+                Err(SettingsError::InvalidField {
+                    field: "private_networking",
+                    ..
+                })
+            )
+        };
+        settings.private_networking = true;
+        assert!(private_networking(&settings));
+        settings.regions.pop();
+        settings.validate().unwrap();
+        settings.cloud_provider = CloudProvider::Linode;
+        assert!(private_networking(&settings));
+        settings.cloud_provider = CloudProvider::Aws;
+        settings.private_networking = false;
+
+        settings.token_file = "/path/to/token/file".into();
+        assert!(settings.validate().is_err());
+
+        let yaml = "url: https://example.com/author/repo\ncommit: main";
+        assert!(serde_yaml::from_str::<Repository>(yaml).is_ok());
+        let yaml = "url: https://example.com/author/repo\ncommit: main\nbranch: main";
+        assert!(serde_yaml::from_str::<Repository>(yaml).is_err());
+    }
+
+    #[test]
+    fn validate_faults() {
+        let mut settings = Settings::new_for_test();
+        settings.faults = FaultsType::Permanent { faults: 4 };
+        settings.validate_faults(10).unwrap();
+
+        // A committee of 10 nodes tolerates 3 faults.
+        settings.byzantine = Some(ByzantineNodes {
+            fraction: 0.1,
+            strategy: ByzantineStrategy::Equivocate,
+        });
+        assert!(settings.validate_faults(10).is_err());
+        settings.faults = FaultsType::Permanent { faults: 2 };
+        settings.validate_faults(10).unwrap();
+    }
+
+    #[test]
+    fn specs_for_region() {
+        let mut settings = Settings::new_for_test();
+        settings.specs = "n2-standard-16".into();
+        settings.region_specs = [("me-central1-a".into(), "n2d-standard-16".into())].into();
+        assert_eq!(settings.specs_for_region("us-east1-b"), "n2-standard-16");
+        assert_eq!(
+            settings.specs_for_region("me-central1-a"),
+            "n2d-standard-16"
+        );
+    }
+
+    #[test]
+    fn hardware_profiles() {
+        let mut settings = Settings::new_for_test();
+        settings.specs = "m5d.8xlarge".into();
+        settings.hardware_profiles = vec![HardwareProfile {
+            specs: "m5d.2xlarge".into(),
+            share: 0.25,
+        }];
+        assert!(settings.accepts_specs("us-east-1", "M5d8xlarge"));
+        assert!(settings.accepts_specs("us-east-1", "M5d2xlarge"));
+        assert!(!settings.accepts_specs("us-east-1", "M5d4xlarge"));
+        assert_eq!(settings.hardware_profiles[0].instances(10), 3);
+        assert_eq!(settings.hardware_profiles[0].instances(4), 1);
+    }
 }
@@ -2,19 +2,34 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-    io::Read,
+    io::{Read, Write},
     net::SocketAddr,
+    os::{fd::OwnedFd, unix::net::UnixStream},
     path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
-use futures::future::try_join_all;
+use futures::{
+    future::try_join_all,
+    stream::{self, StreamExt},
+    Future,
+};
+use sha2::{Digest, Sha256};
 use ssh2::{Channel, Session};
-use tokio::{net::TcpStream, runtime::Handle, task::JoinHandle, time::sleep};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    process::{Child, Command},
+    runtime::Handle,
+    task::JoinHandle,
+    time::sleep,
+};
 
 use crate::{
     client::Instance,
-    ensure,
+    display, ensure,
     error::{SshError, SshResult},
 };
 
@@ -93,29 +108,193 @@ impl CommandContext {
     }
 }
 
+/// How the ssh connections authenticate to the instances.
+#[derive(Clone)]
+pub enum SshAuthentication {
+    /// With a private key file, decrypted with the passphrase (if the key is encrypted).
+    PrivateKey {
+        file: PathBuf,
+        passphrase: Option<String>,
+    },
+    /// With the identities of the ssh agent of the user (found through `SSH_AUTH_SOCK`).
+    Agent,
+}
+
+impl SshAuthentication {
+    /// Authenticate with a plaintext private key file.
+    pub fn private_key(file: PathBuf) -> Self {
+        Self::PrivateKey {
+            file,
+            passphrase: None,
+        }
+    }
+
+    /// The `ssh` command authenticating the same way (e.g., for rsync). It cannot be given the
+    /// passphrase of an encrypted key, which should then also be loaded in the ssh agent.
+    fn ssh_command(&self) -> String {
+        let options = "-o StrictHostKeyChecking=no -o UserKnownHostsFile=/dev/null";
+        match self {
+            Self::PrivateKey { file, .. } => format!("ssh -i {} {options}", file.display()),
+            Self::Agent => format!("ssh {options}"),
+        }
+    }
+}
+
+/// A jump host (bastion) relaying the ssh connections to the instances, for testbeds whose
+/// instances are not reachable from the orchestrator (e.g., without public addresses). As with
+/// ssh's `ProxyJump`, each connection goes through an `ssh -W` process connected to the jump
+/// host, and reaches the instance through its private address.
+#[derive(Clone)]
+pub struct JumpHost {
+    /// The jump host, as `[user@]host[:port]`.
+    destination: String,
+    /// How to authenticate to the jump host.
+    authentication: SshAuthentication,
+}
+
+impl JumpHost {
+    /// Create a new jump host from its destination (`[user@]host[:port]`) and authentication.
+    pub fn new(destination: String, authentication: SshAuthentication) -> Self {
+        Self {
+            destination,
+            authentication,
+        }
+    }
+
+    /// The destination of the jump host, as `[user@]host[:port]`.
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// The ssh address of an instance as seen from the jump host: its private address (if any).
+    pub fn ssh_address(instance: &Instance) -> SocketAddr {
+        SocketAddr::new(instance.private_ip.unwrap_or(instance.main_ip).into(), 22)
+    }
+
+    /// The command relaying its stdin and stdout to the specified target (e.g., '10.0.0.1:22',
+    /// or '%h:%p' as ssh `ProxyCommand`) through the jump host.
+    pub fn proxy_command(&self, target: &str) -> String {
+        format!(
+            "{} -o BatchMode=yes -W {target} ssh://{}",
+            self.authentication.ssh_command(),
+            self.destination
+        )
+    }
+
+    /// Start relaying a connection to the specified address through the jump host. Returns the
+    /// local end of the connection, along with the process relaying it (killed when dropped).
+    fn relay(&self, address: SocketAddr) -> std::io::Result<(UnixStream, Child)> {
+        let (local, remote) = UnixStream::pair()?;
+        let process = Command::new("sh")
+            .arg("-c")
+            .arg(self.proxy_command(&address.to_string()))
+            .stdin(OwnedFd::from(remote.try_clone()?))
+            .stdout(OwnedFd::from(remote))
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        Ok((local, process))
+    }
+}
+
+/// How the commands reach the instances.
+#[derive(Clone)]
+enum Transport {
+    /// Through ssh.
+    Ssh,
+    /// As local processes, each instance running in its own home directory under this root.
+    Local(PathBuf),
+    /// Through `kubectl exec`, each instance being a pod of this namespace.
+    Kubernetes(String),
+    /// Nowhere: the commands are only recorded (along with the address of their instance).
+    DryRun(Arc<Mutex<Vec<(String, String)>>>),
+}
+
 #[derive(Clone)]
 pub struct SshConnectionManager {
     /// The ssh username.
     username: String,
-    /// The ssh primate key to connect to the instances.
-    private_key_file: PathBuf,
+    /// How to authenticate to the instances.
+    authentication: SshAuthentication,
     /// The timeout value of the connection.
     timeout: Option<Duration>,
     /// The number of retries before giving up to execute the command.
     retries: usize,
+    /// How the commands reach the instances.
+    transport: Transport,
+    /// The jump host relaying the ssh connections to the instances (if any).
+    jump_host: Option<JumpHost>,
 }
 
 impl SshConnectionManager {
     /// Delay before re-attempting an ssh execution.
     const RETRY_DELAY: Duration = Duration::from_secs(5);
+    /// The maximum number of concurrent file transfers.
+    const MAX_CONCURRENT_TRANSFERS: usize = 8;
 
-    /// Create a new ssh manager from the instances username and private keys.
-    pub fn new(username: String, private_key_file: PathBuf) -> Self {
+    /// Create a new ssh manager from the instances username and authentication method.
+    pub fn new(username: String, authentication: SshAuthentication) -> Self {
         Self {
             username,
-            private_key_file,
+            authentication,
             timeout: None,
             retries: 0,
+            transport: Transport::Ssh,
+            jump_host: None,
+        }
+    }
+
+    /// Create a manager executing the commands locally, for testbeds made of local processes.
+    /// The commands of each instance run with their home directory (and tmux server) set to a
+    /// dedicated sub-directory of the specified root.
+    pub fn local(root: PathBuf) -> Self {
+        Self {
+            username: String::new(),
+            authentication: SshAuthentication::private_key(PathBuf::new()),
+            timeout: None,
+            retries: 0,
+            transport: Transport::Local(root),
+            jump_host: None,
+        }
+    }
+
+    /// Create a manager executing the commands in the pods of a kubernetes namespace (through
+    /// `kubectl exec`), for testbeds deployed on kubernetes.
+    pub fn kubernetes(namespace: String) -> Self {
+        Self {
+            username: String::new(),
+            authentication: SshAuthentication::private_key(PathBuf::new()),
+            timeout: None,
+            retries: 0,
+            transport: Transport::Kubernetes(namespace),
+            jump_host: None,
+        }
+    }
+
+    /// Create a manager that does not touch the instances but only records the commands it
+    /// would execute, to print the plan of a benchmark.
+    pub fn dry_run() -> Self {
+        Self {
+            username: String::new(),
+            authentication: SshAuthentication::private_key(PathBuf::new()),
+            timeout: None,
+            retries: 0,
+            transport: Transport::DryRun(Arc::default()),
+            jump_host: None,
+        }
+    }
+
+    /// Whether the manager only records the commands rather than executing them.
+    pub fn is_dry_run(&self) -> bool {
+        matches!(self.transport, Transport::DryRun(_))
+    }
+
+    /// Return (and forget) the commands recorded since the last call, along with the address of
+    /// the instance they target. Always empty unless the manager is a dry run.
+    pub fn take_recorded_commands(&self) -> Vec<(String, String)> {
+        match &self.transport {
+            Transport::DryRun(commands) => std::mem::take(&mut *commands.lock().unwrap()),
+            _ => Vec::new(),
         }
     }
 
@@ -131,11 +310,27 @@ impl SshConnectionManager {
         self
     }
 
+    /// Route the ssh connections to the instances through a jump host.
+    pub fn with_jump_host(mut self, jump_host: JumpHost) -> Self {
+        self.jump_host = Some(jump_host);
+        self
+    }
+
+    /// The ssh address of an instance (its private address if connecting through a jump host).
+    pub fn ssh_address(&self, instance: &Instance) -> SocketAddr {
+        match &self.jump_host {
+            Some(_) => JumpHost::ssh_address(instance),
+            None => instance.ssh_address(),
+        }
+    }
+
     /// Create a new ssh connection with the provided host.
     pub async fn connect(&self, address: SocketAddr) -> SshResult<SshConnection> {
         let mut error = None;
         for _ in 0..self.retries + 1 {
-            match SshConnection::new(address, &self.username, self.private_key_file.clone()).await {
+            let (username, authentication) = (&self.username, &self.authentication);
+            let jump_host = self.jump_host.as_ref();
+            match SshConnection::new(address, username, authentication, jump_host).await {
                 Ok(x) => return Ok(x.with_timeout(&self.timeout).with_retries(self.retries)),
                 Err(e) => error = Some(e),
             }
@@ -196,7 +391,14 @@ impl SshConnectionManager {
                 let context = context.clone();
 
                 tokio::spawn(async move {
-                    let connection = ssh_manager.connect(instance.ssh_address()).await?;
+                    let transport = &ssh_manager.transport;
+                    if !matches!(transport, Transport::Ssh) {
+                        let command = context.apply(command);
+                        return Self::execute_process(&instance, transport, command, None).await;
+                    }
+
+                    let address = ssh_manager.ssh_address(&instance);
+                    let connection = ssh_manager.connect(address).await?;
                     // SshConnection::execute is a blocking call, needs to go to blocking pool
                     Handle::current()
                         .spawn_blocking(move || connection.execute(context.apply(command)))
@@ -207,6 +409,407 @@ impl SshConnectionManager {
             .collect::<Vec<_>>()
     }
 
+    /// Execute the ssh command associated with each (named) instance and print the lines of its
+    /// output as they come, prefixed by the name of the instance, rather than only returning the
+    /// output at completion (e.g., to follow a long compilation). Returns the output of each
+    /// command, with stderr merged into stdout.
+    pub async fn execute_streamed<I, S>(
+        &self,
+        targets: I,
+        context: CommandContext,
+    ) -> SshResult<Vec<String>>
+    where
+        I: IntoIterator<Item = (String, Instance, S)>,
+        S: Into<String> + Send + 'static,
+    {
+        let handles = targets.into_iter().map(|(name, instance, command)| {
+            let ssh_manager = self.clone();
+            // Merge stderr into stdout to print the lines in order.
+            let command = context.apply(format!("({}) 2>&1", command.into()));
+
+            tokio::spawn(async move {
+                let transport = &ssh_manager.transport;
+                match transport {
+                    Transport::Ssh => {
+                        let address = ssh_manager.ssh_address(&instance);
+                        let connection = ssh_manager.connect(address).await?;
+                        // SshConnection::execute_streamed is a blocking call, needs to go to
+                        // blocking pool
+                        Handle::current()
+                            .spawn_blocking(move || {
+                                connection
+                                    .execute_streamed(command, |x| display::remote_output(&name, x))
+                            })
+                            .await
+                            .unwrap()
+                    }
+                    Transport::DryRun(_) => {
+                        Self::execute_process(&instance, transport, command, None).await?;
+                        Ok(String::new())
+                    }
+                    transport => {
+                        Self::execute_process_streamed(&instance, transport, command, &name).await
+                    }
+                }
+            })
+        });
+
+        try_join_all(handles)
+            .await
+            .unwrap()
+            .into_iter()
+            .collect::<SshResult<_>>()
+    }
+
+    /// Execute a command from the home directory of the specified instance through a local
+    /// process and print the lines of its stdout as they come, prefixed by the specified name.
+    async fn execute_process_streamed(
+        instance: &Instance,
+        transport: &Transport,
+        command: String,
+        name: &str,
+    ) -> SshResult<String> {
+        let address = instance.ssh_address();
+        let mut child = Self::process(instance, transport, command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| SshError::ConnectionError { address, error })?;
+
+        let mut output = String::new();
+        if let Some(stdout) = child.stdout.take() {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Some(line) = lines
+                .next_line()
+                .await
+                .map_err(|error| SshError::ConnectionError { address, error })?
+            {
+                display::remote_output(name, &line);
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+        let status = child
+            .wait()
+            .await
+            .map_err(|error| SshError::ConnectionError { address, error })?;
+        ensure!(
+            status.success(),
+            SshError::NonZeroExitCode {
+                address,
+                code: status.code().unwrap_or(-1),
+                message: output.clone()
+            }
+        );
+        Ok(output)
+    }
+
+    /// Execute a command from the home directory of the specified instance through a local
+    /// process: either the command itself or the `kubectl exec` running it in a pod. The
+    /// optional input is written to the stdin of the command.
+    async fn execute_process(
+        instance: &Instance,
+        transport: &Transport,
+        command: String,
+        input: Option<&[u8]>,
+    ) -> SshResult<(String, String)> {
+        let address = instance.ssh_address();
+        if let Transport::DryRun(commands) = transport {
+            let address = instance.main_ip.to_string();
+            commands.lock().unwrap().push((address, command));
+            return Ok((String::new(), String::new()));
+        }
+        let mut child = Self::process(instance, transport, command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| SshError::ConnectionError { address, error })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(input.unwrap_or_default())
+                .await
+                .map_err(|error| SshError::ConnectionError { address, error })?;
+        }
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|error| SshError::ConnectionError { address, error })?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        ensure!(
+            output.status.success(),
+            SshError::NonZeroExitCode {
+                address,
+                code: output.status.code().unwrap_or(-1),
+                message: stderr.clone()
+            }
+        );
+        Ok((stdout, stderr))
+    }
+
+    /// The local process running a command from the home directory of the specified instance:
+    /// either the command itself or the `kubectl exec` running it in a pod.
+    fn process(instance: &Instance, transport: &Transport, command: String) -> Command {
+        match transport {
+            Transport::Local(root) => {
+                let home = root.join(&instance.id);
+                let mut process = Command::new("bash");
+                process
+                    .arg("-c")
+                    .arg(command)
+                    .env("HOME", &home)
+                    .env("TMUX_TMPDIR", &home)
+                    .current_dir(&home);
+                process
+            }
+            Transport::Kubernetes(namespace) => {
+                let mut process = Command::new("kubectl");
+                process
+                    .args([
+                        "exec",
+                        "--stdin",
+                        "--namespace",
+                        namespace,
+                        &instance.id,
+                        "--",
+                    ])
+                    .args(["bash", "-c"])
+                    .arg(format!("cd ~ && {command}"));
+                process
+            }
+            Transport::Ssh | Transport::DryRun(_) => {
+                unreachable!("Ssh commands and dry runs do not run as local processes")
+            }
+        }
+    }
+
+    /// Execute a command on an instance and print its output as it comes (e.g., to follow a log
+    /// file), until the command terminates.
+    pub async fn stream(&self, instance: &Instance, command: String) -> SshResult<()> {
+        let address = self.ssh_address(instance);
+        match &self.transport {
+            Transport::Ssh => {
+                let connection = self.connect(address).await?;
+                // SshConnection::stream is a blocking call, needs to go to blocking pool
+                Handle::current()
+                    .spawn_blocking(move || connection.stream(command))
+                    .await
+                    .unwrap()
+            }
+            Transport::DryRun(_) => {
+                Self::execute_process(instance, &self.transport, command, None).await?;
+                Ok(())
+            }
+            transport => {
+                let status = Self::process(instance, transport, command)
+                    .stdin(Stdio::null())
+                    .status()
+                    .await
+                    .map_err(|error| SshError::ConnectionError { address, error })?;
+                ensure!(
+                    status.success(),
+                    SshError::NonZeroExitCode {
+                        address,
+                        code: status.code().unwrap_or(-1),
+                        message: String::new()
+                    }
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Download a file (specified relatively to the home directory) from an instance.
+    pub async fn download(&self, instance: &Instance, path: &str) -> SshResult<String> {
+        if !matches!(self.transport, Transport::Ssh) {
+            let command = format!("cat {path}");
+            let (content, _) =
+                Self::execute_process(instance, &self.transport, command, None).await?;
+            return Ok(content);
+        }
+
+        let connection = self.connect(self.ssh_address(instance)).await?;
+        connection.download(path)
+    }
+
+    /// Upload an executable file (specified relatively to the home directory) to an instance.
+    pub async fn upload(&self, instance: &Instance, path: &str, content: &[u8]) -> SshResult<()> {
+        if !matches!(self.transport, Transport::Ssh) {
+            let command = format!("cat > {path} && chmod +x {path}");
+            Self::execute_process(instance, &self.transport, command, Some(content)).await?;
+            return Ok(());
+        }
+
+        let connection = self.connect(self.ssh_address(instance)).await?;
+        let (path, content) = (path.to_string(), content.to_vec());
+        // SshConnection::upload is a blocking call, needs to go to blocking pool
+        Handle::current()
+            .spawn_blocking(move || connection.upload(path, &content))
+            .await
+            .unwrap()
+    }
+
+    /// The checksum (sha256) of the content of a file.
+    fn checksum(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    /// The checksum (sha256) of a file (specified relatively to the home directory) of an
+    /// instance.
+    async fn remote_checksum(&self, instance: &Instance, path: &str) -> SshResult<String> {
+        let command = format!("sha256sum {path}");
+        let context = CommandContext::default();
+        let mut output = self.execute([instance.clone()], command, context).await?;
+        let (stdout, _) = output.pop().unwrap_or_default();
+        Ok(stdout.split_whitespace().next().unwrap_or_default().into())
+    }
+
+    /// Upload an executable file to an instance and verify its checksum, uploading it again (up
+    /// to the number of retries) if it does not match.
+    async fn checked_upload(
+        &self,
+        instance: &Instance,
+        path: &str,
+        content: &[u8],
+    ) -> SshResult<()> {
+        let checksum = Self::checksum(content);
+        for _ in 0..self.retries + 1 {
+            self.upload(instance, path, content).await?;
+            if self.is_dry_run() || self.remote_checksum(instance, path).await? == checksum {
+                return Ok(());
+            }
+        }
+        Err(SshError::ChecksumMismatch {
+            address: self.ssh_address(instance),
+            path: path.into(),
+        })
+    }
+
+    /// Download a file from an instance and verify its checksum, downloading it again (up to the
+    /// number of retries) if it does not match.
+    async fn checked_download(&self, instance: &Instance, path: &str) -> SshResult<String> {
+        for _ in 0..self.retries + 1 {
+            let content = self.download(instance, path).await?;
+            if self.is_dry_run() {
+                return Ok(content);
+            }
+            let checksum = self.remote_checksum(instance, path).await?;
+            if checksum == Self::checksum(content.as_bytes()) {
+                return Ok(content);
+            }
+        }
+        Err(SshError::ChecksumMismatch {
+            address: self.ssh_address(instance),
+            path: path.into(),
+        })
+    }
+
+    /// Run file transfers concurrently (at most `MAX_CONCURRENT_TRANSFERS` at a time), showing
+    /// their progress as the status of the current action. Returns their results in order.
+    async fn transfer<F, T>(transfers: impl Iterator<Item = F>) -> SshResult<Vec<T>>
+    where
+        F: Future<Output = SshResult<T>>,
+    {
+        let transfers: Vec<_> = transfers
+            .enumerate()
+            .map(|(i, transfer)| async move { (i, transfer.await) })
+            .collect();
+        let total = transfers.len();
+        let mut transfers =
+            stream::iter(transfers).buffer_unordered(Self::MAX_CONCURRENT_TRANSFERS);
+
+        let mut results = Vec::with_capacity(total);
+        display::status(format!("0/{total}"));
+        while let Some((i, result)) = transfers.next().await {
+            results.push((i, result?));
+            display::status(format!("{}/{total}", results.len()));
+        }
+        results.sort_by_key(|(i, _)| *i);
+        Ok(results.into_iter().map(|(_, x)| x).collect())
+    }
+
+    /// Upload executable files (specified relatively to the home directory) to many instances
+    /// concurrently, verifying their checksums.
+    pub async fn upload_files<'a, I>(&self, uploads: I) -> SshResult<()>
+    where
+        I: IntoIterator<Item = (&'a Instance, &'a str, &'a [u8])>,
+    {
+        let uploads = uploads
+            .into_iter()
+            .map(|(instance, path, content)| self.checked_upload(instance, path, content));
+        Self::transfer(uploads).await?;
+        Ok(())
+    }
+
+    /// Download files (specified relatively to the home directory) from many instances
+    /// concurrently, verifying their checksums. Returns the content of the files in order.
+    pub async fn download_files<'a, I>(&self, downloads: I) -> SshResult<Vec<String>>
+    where
+        I: IntoIterator<Item = (&'a Instance, &'a str)>,
+    {
+        let downloads = downloads
+            .into_iter()
+            .map(|(instance, path)| self.checked_download(instance, path));
+        Self::transfer(downloads).await
+    }
+
+    /// Synchronize (with rsync) the content of a local directory to a directory of an instance
+    /// (specified relatively to the home directory). Files ignored by git are not synchronized.
+    pub async fn sync(&self, instance: &Instance, local: &Path, remote: &str) -> SshResult<()> {
+        if self.is_dry_run() {
+            let command = format!("rsync {}/ {remote}/", local.display());
+            Self::execute_process(instance, &self.transport, command, None).await?;
+            return Ok(());
+        }
+
+        let address = self.ssh_address(instance);
+        let mut command = Command::new("rsync");
+        command
+            .args(["-az", "--delete", "--exclude", ".git"])
+            .args(["--filter", ":- .gitignore"])
+            .arg(format!("{}/", local.display()));
+        match &self.transport {
+            Transport::Ssh => {
+                let mut ssh = self.authentication.ssh_command();
+                if let Some(jump_host) = &self.jump_host {
+                    let proxy = jump_host.proxy_command("%h:%p");
+                    ssh.push_str(&format!(" -o ProxyCommand='{proxy}'"));
+                }
+                let destination = format!("{}@{}:{remote}/", self.username, address.ip());
+                command.args(["-e", &ssh]).arg(destination);
+            }
+            Transport::Local(root) => {
+                command.arg(root.join(&instance.id).join(remote));
+            }
+            Transport::DryRun(_) => unreachable!("Dry runs do not synchronize files"),
+            Transport::Kubernetes(namespace) => {
+                // Rsync passes the pod name (as host) before the remote command.
+                let kubectl =
+                    format!("sh -c 'kubectl exec -i --namespace {namespace} \"$0\" -- \"$@\"'");
+                command
+                    .args(["--blocking-io", "-e", &kubectl])
+                    .arg(format!("{}:{remote}/", instance.id));
+            }
+        }
+
+        let output = command
+            .output()
+            .await
+            .map_err(|error| SshError::ConnectionError { address, error })?;
+        ensure!(
+            output.status.success(),
+            SshError::NonZeroExitCode {
+                address,
+                code: output.status.code().unwrap_or(-1),
+                message: String::from_utf8_lossy(&output.stderr).into_owned()
+            }
+        );
+        Ok(())
+    }
+
     /// Wait until a command running in the background returns or started.
     pub async fn wait_for_command<I>(
         &self,
@@ -217,6 +820,11 @@ impl SshConnectionManager {
     where
         I: IntoIterator<Item = Instance> + Clone,
     {
+        // Nothing runs in the background of a dry run.
+        if self.is_dry_run() {
+            return Ok(());
+        }
+
         loop {
             sleep(Self::RETRY_DELAY).await;
 
@@ -242,6 +850,10 @@ impl SshConnectionManager {
         I: IntoIterator<Item = (Instance, S)> + Clone,
         S: Into<String> + Send + 'static + Clone,
     {
+        if self.is_dry_run() {
+            return;
+        }
+
         loop {
             sleep(Self::RETRY_DELAY).await;
 
@@ -277,37 +889,57 @@ pub struct SshConnection {
     address: SocketAddr,
     /// The number of retries before giving up to execute the command.
     retries: usize,
+    /// The process relaying the connection through the jump host (if any), killed along with
+    /// the connection.
+    _relay: Option<Child>,
 }
 
 impl SshConnection {
     /// Default duration before timing out the ssh connection.
     const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
 
-    /// Create a new ssh connection with a specific host.
-    pub async fn new<P: AsRef<Path>>(
+    /// Create a new ssh connection with a specific host (optionally through a jump host).
+    pub async fn new(
         address: SocketAddr,
         username: &str,
-        private_key_file: P,
+        authentication: &SshAuthentication,
+        jump_host: Option<&JumpHost>,
     ) -> SshResult<Self> {
-        let tcp = TcpStream::connect(address)
-            .await
-            .map_err(|error| SshError::ConnectionError { address, error })?;
-
         let mut session =
             Session::new().map_err(|error| SshError::SessionError { address, error })?;
         session.set_timeout(Self::DEFAULT_TIMEOUT.as_millis() as u32);
-        session.set_tcp_stream(tcp);
+        let relay = match jump_host {
+            Some(jump_host) => {
+                let (stream, relay) = jump_host
+                    .relay(address)
+                    .map_err(|error| SshError::ConnectionError { address, error })?;
+                session.set_tcp_stream(stream);
+                Some(relay)
+            }
+            None => {
+                let tcp = TcpStream::connect(address)
+                    .await
+                    .map_err(|error| SshError::ConnectionError { address, error })?;
+                session.set_tcp_stream(tcp);
+                None
+            }
+        };
         session
             .handshake()
             .map_err(|error| SshError::SessionError { address, error })?;
-        session
-            .userauth_pubkey_file(username, None, private_key_file.as_ref(), None)
-            .map_err(|error| SshError::SessionError { address, error })?;
+        match authentication {
+            SshAuthentication::PrivateKey { file, passphrase } => {
+                session.userauth_pubkey_file(username, None, file, passphrase.as_deref())
+            }
+            SshAuthentication::Agent => session.userauth_agent(username),
+        }
+        .map_err(|error| SshError::SessionError { address, error })?;
 
         Ok(Self {
             session,
             address,
             retries: 0,
+            _relay: relay,
         })
     }
 
@@ -401,6 +1033,94 @@ impl SshConnection {
         Ok((stdout, stderr))
     }
 
+    /// Execute a ssh command on the remote machine and print its output (stdout and stderr) as
+    /// it comes, until the command terminates.
+    pub fn stream(&self, command: String) -> SshResult<()> {
+        let mut channel = self
+            .session
+            .channel_session()
+            .map_err(|e| self.make_session_error(e))?;
+        // Reading the output may block for long (e.g., when following a quiet log file).
+        self.session.set_timeout(0);
+        channel
+            .handle_extended_data(ssh2::ExtendedData::Merge)
+            .and_then(|()| channel.exec(&command))
+            .map_err(|e| self.make_session_error(e))?;
+
+        let mut stdout = std::io::stdout();
+        let mut buffer = [0; 8192];
+        loop {
+            let n = channel
+                .read(&mut buffer)
+                .map_err(|e| self.make_connection_error(e))?;
+            if n == 0 {
+                break;
+            }
+            stdout
+                .write_all(&buffer[..n])
+                .and_then(|()| stdout.flush())
+                .map_err(|e| self.make_connection_error(e))?;
+        }
+
+        channel
+            .wait_close()
+            .map_err(|e| self.make_session_error(e))?;
+        let exit_status = channel
+            .exit_status()
+            .map_err(|e| self.make_session_error(e))?;
+        ensure!(
+            exit_status == 0,
+            SshError::NonZeroExitCode {
+                address: self.address,
+                code: exit_status,
+                message: String::new()
+            }
+        );
+        Ok(())
+    }
+
+    /// Execute a ssh command on the remote machine and call the specified function on each line of
+    /// its output (stdout) as it comes, until the command terminates. Returns the whole output.
+    pub fn execute_streamed<F>(&self, command: String, mut on_line: F) -> SshResult<String>
+    where
+        F: FnMut(&str),
+    {
+        let mut channel = self
+            .session
+            .channel_session()
+            .map_err(|e| self.make_session_error(e))?;
+        // Reading the output may block for long (e.g., during a quiet compilation step).
+        self.session.set_timeout(0);
+        channel
+            .exec(&command)
+            .map_err(|e| self.make_session_error(e))?;
+
+        let mut output = String::new();
+        for line in std::io::BufReader::new(&mut channel).split(b'\n') {
+            let line = line.map_err(|e| self.make_connection_error(e))?;
+            let line = String::from_utf8_lossy(&line);
+            on_line(&line);
+            output.push_str(&line);
+            output.push('\n');
+        }
+
+        channel
+            .wait_close()
+            .map_err(|e| self.make_session_error(e))?;
+        let exit_status = channel
+            .exit_status()
+            .map_err(|e| self.make_session_error(e))?;
+        ensure!(
+            exit_status == 0,
+            SshError::NonZeroExitCode {
+                address: self.address,
+                code: exit_status,
+                message: output.clone()
+            }
+        );
+        Ok(output)
+    }
+
     /// Download a file from the remote machines through scp.
     pub fn download<P: AsRef<Path>>(&self, path: P) -> SshResult<String> {
         let mut error = None;
@@ -424,4 +1144,36 @@ impl SshConnection {
         }
         Err(error.unwrap())
     }
+
+    /// Upload an executable file to the remote machines through scp.
+    pub fn upload<P: AsRef<Path>>(&self, path: P, content: &[u8]) -> SshResult<()> {
+        let mut error = None;
+        for _ in 0..self.retries + 1 {
+            let size = content.len() as u64;
+            let mut channel = match self.session.scp_send(path.as_ref(), 0o755, size, None) {
+                Ok(x) => x,
+                Err(e) => {
+                    error = Some(self.make_session_error(e));
+                    continue;
+                }
+            };
+
+            let result = channel
+                .write_all(content)
+                .map_err(|e| self.make_connection_error(e))
+                .and_then(|()| {
+                    channel
+                        .send_eof()
+                        .and_then(|()| channel.wait_eof())
+                        .and_then(|()| channel.close())
+                        .and_then(|()| channel.wait_close())
+                        .map_err(|e| self.make_session_error(e))
+                });
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) => error = Some(e),
+            }
+        }
+        Err(error.unwrap())
+    }
 }
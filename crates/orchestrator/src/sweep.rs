@@ -0,0 +1,143 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    benchmark::BenchmarkParameters,
+    error::{TestbedError, TestbedResult},
+    measurements::MeasurementsCollection,
+};
+
+/// The progress of a sweep of benchmarks, persisted in the results directory after every
+/// completed benchmark. Rerunning the same sweep (after a crash or a loss of connectivity)
+/// skips the benchmarks it already completed.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct SweepProgress {
+    /// The benchmarks of the sweep (identified by the debug representation of their parameters).
+    benchmarks: Vec<String>,
+    /// The benchmarks of the sweep completed so far.
+    completed: Vec<String>,
+    /// The directory holding the progress file and the measurements.
+    #[serde(skip)]
+    directory: PathBuf,
+}
+
+impl SweepProgress {
+    /// The name of the file persisting the progress of the sweep.
+    const FILE_NAME: &'static str = "sweep.json";
+
+    /// Load the progress of the specified sweep from the results directory. Starts a new sweep
+    /// if the directory holds the progress of a different sweep (or none).
+    pub fn load<P: AsRef<Path>>(
+        directory: P,
+        set_of_parameters: &[BenchmarkParameters],
+    ) -> TestbedResult<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        let benchmarks: Vec<_> = set_of_parameters.iter().map(Self::key).collect();
+
+        let path = directory.join(Self::FILE_NAME);
+        if path.exists() {
+            let data = fs::read(&path).map_err(|e| TestbedError::SweepError(e.to_string()))?;
+            let progress: Self = serde_json::from_slice(&data)
+                .map_err(|e| TestbedError::SweepError(e.to_string()))?;
+            if progress.benchmarks == benchmarks {
+                return Ok(Self {
+                    directory,
+                    ..progress
+                });
+            }
+        }
+
+        Ok(Self {
+            benchmarks,
+            completed: Vec::new(),
+            directory,
+        })
+    }
+
+    /// Forget the benchmarks completed so far.
+    pub fn reset(&mut self) -> TestbedResult<()> {
+        self.completed.clear();
+        self.save()
+    }
+
+    /// The identifier of a benchmark within a sweep.
+    fn key(parameters: &BenchmarkParameters) -> String {
+        format!("{parameters:?}")
+    }
+
+    /// Whether the specified benchmark was completed (and its measurements are still there).
+    pub fn is_completed(&self, parameters: &BenchmarkParameters) -> bool {
+        self.completed.contains(&Self::key(parameters))
+            && MeasurementsCollection::path(&self.directory, parameters).exists()
+    }
+
+    /// The number of benchmarks of the sweep completed so far.
+    pub fn completed(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Record the completion of the specified benchmark.
+    pub fn complete(&mut self, parameters: &BenchmarkParameters) -> TestbedResult<()> {
+        let key = Self::key(parameters);
+        if !self.completed.contains(&key) {
+            self.completed.push(key);
+        }
+        self.save()
+    }
+
+    /// Persist the progress of the sweep.
+    fn save(&self) -> TestbedResult<()> {
+        let data = serde_json::to_string_pretty(self).unwrap();
+        fs::create_dir_all(&self.directory)
+            .and_then(|_| fs::write(self.directory.join(Self::FILE_NAME), data))
+            .map_err(|e| TestbedError::SweepError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SweepProgress;
+    use crate::{
+        benchmark::BenchmarkParametersGenerator, measurements::MeasurementsCollection,
+        settings::Settings, ClientParameters, NodeParameters,
+    };
+
+    #[test]
+    fn resume() {
+        let directory = tempfile::tempdir().unwrap();
+        let sweep = BenchmarkParametersGenerator::new(
+            Settings::new_for_test(),
+            NodeParameters::default(),
+            ClientParameters::default(),
+        )
+        .with_cartesian_product(&[4], &[100, 200, 300], &[], &[])
+        .generate();
+
+        // Complete the first benchmark, then interrupt the sweep.
+        let mut progress = SweepProgress::load(directory.path(), &sweep).unwrap();
+        MeasurementsCollection::new(sweep[0].clone()).save(directory.path());
+        progress.complete(&sweep[0]).unwrap();
+
+        // Rerunning the same sweep resumes it.
+        let progress = SweepProgress::load(directory.path(), &sweep).unwrap();
+        assert!(progress.is_completed(&sweep[0]));
+        assert!(!progress.is_completed(&sweep[1]));
+        assert_eq!(progress.completed(), 1);
+
+        // A different sweep starts over.
+        let progress = SweepProgress::load(directory.path(), &sweep[..2]).unwrap();
+        assert!(!progress.is_completed(&sweep[0]));
+
+        // Benchmarks whose measurements were deleted run again.
+        let progress = SweepProgress::load(directory.path(), &sweep).unwrap();
+        std::fs::remove_file(MeasurementsCollection::path(directory.path(), &sweep[0])).unwrap();
+        assert!(!progress.is_completed(&sweep[0]));
+    }
+}
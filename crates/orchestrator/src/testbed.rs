@@ -1,21 +1,43 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::time::Duration;
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use futures::future::try_join_all;
+use futures::future::{join_all, try_join_all};
 use prettytable::{row, Table};
+use serde::{Deserialize, Serialize};
 use tokio::time::{self, Instant};
 
 use super::client::Instance;
 use crate::{
     client::ServerProviderClient,
-    display,
-    error::{TestbedError, TestbedResult},
+    display, ensure,
+    error::{SshResult, TestbedError, TestbedResult},
+    health::HealthCheck,
     settings::Settings,
-    ssh::SshConnection,
+    ssh::{CommandContext, JumpHost, SshConnection, SshConnectionManager},
 };
 
+/// The record of the ttl of a testbed, written when deploying instances.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TtlRecord {
+    /// The time (in seconds since the unix epoch) of the deployment.
+    pub deployed_at: u64,
+    /// The time to live (in seconds) of the testbed.
+    pub ttl: u64,
+}
+
+impl TtlRecord {
+    /// The time (in seconds since the unix epoch) after which the testbed expires.
+    pub fn expires_at(&self) -> u64 {
+        self.deployed_at.saturating_add(self.ttl)
+    }
+}
+
 /// Represents a testbed running on a cloud provider.
 pub struct Testbed<C> {
     /// The testbed's settings.
@@ -29,8 +51,10 @@ pub struct Testbed<C> {
 impl<C: ServerProviderClient> Testbed<C> {
     /// Create a new testbed instance with the specified settings and client.
     pub async fn new(settings: Settings, client: C) -> TestbedResult<Self> {
-        let public_key = settings.load_ssh_public_key()?;
-        client.register_ssh_public_key(public_key).await?;
+        if settings.uses_ssh() {
+            let public_key = settings.load_ssh_public_key()?;
+            client.register_ssh_public_key(public_key).await?;
+        }
         let instances = client.list_instances().await?;
 
         Ok(Self {
@@ -41,8 +65,8 @@ impl<C: ServerProviderClient> Testbed<C> {
     }
 
     /// Return the username to connect to the instances through ssh.
-    pub fn username(&self) -> &'static str {
-        C::USERNAME
+    pub fn username(&self) -> &str {
+        self.settings.ssh_username(C::USERNAME)
     }
 
     /// Return the list of instances of the testbed.
@@ -54,6 +78,58 @@ impl<C: ServerProviderClient> Testbed<C> {
             .collect()
     }
 
+    /// The path of the file recording the ttl of the testbed.
+    fn ttl_record_path(&self) -> PathBuf {
+        self.settings
+            .testbeds_dir
+            .join(format!("{}.json", self.settings.testbed_id))
+    }
+
+    /// Load the ttl record of the testbed, if any.
+    pub fn ttl_record(&self) -> TestbedResult<Option<TtlRecord>> {
+        let path = self.ttl_record_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(&path).map_err(|e| TestbedError::TtlRecordError(e.to_string()))?;
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| TestbedError::TtlRecordError(e.to_string()))
+    }
+
+    /// Record that the testbed was just deployed with the specified ttl.
+    fn record_ttl(&self, ttl: Duration) -> TestbedResult<()> {
+        let record = TtlRecord {
+            deployed_at: Self::now(),
+            ttl: ttl.as_secs(),
+        };
+        let data = serde_json::to_string_pretty(&record).unwrap();
+        fs::create_dir_all(&self.settings.testbeds_dir)
+            .and_then(|_| fs::write(self.ttl_record_path(), data))
+            .map_err(|e| TestbedError::TtlRecordError(e.to_string()))
+    }
+
+    /// The current time in seconds since the unix epoch.
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    /// Return for how long the testbed is past its ttl, if it has expired and still has
+    /// instances.
+    pub fn expired(&self) -> TestbedResult<Option<Duration>> {
+        if self.instances.iter().all(|x| x.is_terminated()) {
+            return Ok(None);
+        }
+        let now = Self::now();
+        Ok(self
+            .ttl_record()?
+            .filter(|record| record.expires_at() <= now)
+            .map(|record| Duration::from_secs(now - record.expires_at())))
+    }
+
     /// Return the list of provider-specific instance setup commands.
     pub async fn setup_commands(&self) -> TestbedResult<Vec<String>> {
         self.client
@@ -96,9 +172,14 @@ impl<C: ServerProviderClient> Testbed<C> {
                     table.add_row(row![]);
                 }
                 let private_key_file = self.settings.ssh_private_key_file.display();
-                let username = C::USERNAME;
-                let ip = instance.main_ip;
-                let connect = format!("ssh -i {private_key_file} {username}@{ip}");
+                let username = self.username();
+                let connect = match self.settings.jump_host() {
+                    Some(jump_host) => {
+                        let (jump, ip) = (jump_host.destination(), JumpHost::ssh_address(instance));
+                        format!("ssh -i {private_key_file} -J {jump} {username}@{}", ip.ip())
+                    }
+                    None => format!("ssh -i {private_key_file} {username}@{}", instance.main_ip),
+                };
                 if !instance.is_terminated() {
                     if instance.is_active() {
                         table.add_row(row![bFg->format!("{j}"), connect]);
@@ -114,30 +195,64 @@ impl<C: ServerProviderClient> Testbed<C> {
         }
 
         display::newline();
+        display::config("Testbed", &self.settings.testbed_id);
         display::config("Client", &self.client);
         let repo = &self.settings.repository;
         display::config("Repo", format!("{} ({})", repo.url, repo.commit));
         display::newline();
         table.printstd();
         display::newline();
+
+        let terminated = self
+            .instances()
+            .iter()
+            .filter(|x| x.is_terminated())
+            .count();
+        if terminated != 0 {
+            display::warn(format!(
+                "{terminated} instances are terminated: run 'testbed replace' to replace them"
+            ));
+        }
     }
 
     /// Populate the testbed by creating the specified amount of instances per region. The total
-    /// number of instances created is thus the specified amount x the number of regions.
-    pub async fn deploy(&mut self, quantity: usize, region: Option<String>) -> TestbedResult<()> {
+    /// number of instances created is thus the specified amount x the number of regions. The
+    /// specified ttl (if any) overrides the one of the settings.
+    pub async fn deploy(
+        &mut self,
+        quantity: usize,
+        region: Option<String>,
+        ttl: Option<Duration>,
+    ) -> TestbedResult<()> {
         display::action(format!("Deploying instances ({quantity} per region)"));
 
-        let instances = match region {
-            Some(x) => {
-                try_join_all((0..quantity).map(|_| self.client.create_instance(x.clone()))).await?
-            }
-            None => {
-                try_join_all(self.settings.regions.iter().flat_map(|region| {
-                    (0..quantity).map(|_| self.client.create_instance(region.clone()))
-                }))
-                .await?
-            }
+        // Record the ttl of the testbed before creating (and paying for) any instance.
+        if let Some(ttl) = ttl.or(self.settings.testbed_ttl) {
+            self.record_ttl(ttl)?;
+        }
+
+        let regions = match region {
+            Some(x) => vec![x],
+            None => self.settings.regions.clone(),
         };
+        // Create the instances of every hardware profile (and of the default specs) per region.
+        let requests: Vec<_> = regions
+            .iter()
+            .flat_map(|region| {
+                self.settings
+                    .instances_by_specs(region, quantity)
+                    .into_iter()
+                    .map(move |(specs, quantity)| (region, specs, quantity))
+            })
+            .collect();
+        let instances: Vec<_> = try_join_all(requests.iter().map(|(region, specs, quantity)| {
+            self.client
+                .create_instances(region.as_str(), specs, *quantity)
+        }))
+        .await?
+        .into_iter()
+        .flatten()
+        .collect();
 
         // Wait until the instances are booted.
         if cfg!(not(test)) {
@@ -159,11 +274,104 @@ impl<C: ServerProviderClient> Testbed<C> {
                 .map(|instance| self.client.delete_instance(instance)),
         )
         .await?;
+        self.client.delete_testbed_resources().await?;
+
+        // The testbed no longer needs to be reaped.
+        let path = self.ttl_record_path();
+        if path.exists() {
+            fs::remove_file(path).map_err(|e| TestbedError::TtlRecordError(e.to_string()))?;
+        }
 
         display::done();
         Ok(())
     }
 
+    /// Open an ssh connection to an instance (through the jump host of the settings, if any).
+    async fn connect(&self, instance: &Instance) -> SshResult<SshConnection> {
+        let (username, authentication) = (self.username(), self.settings.ssh_authentication());
+        let jump_host = self.settings.jump_host();
+        let address = match &jump_host {
+            Some(_) => JumpHost::ssh_address(instance),
+            None => instance.ssh_address(),
+        };
+        SshConnection::new(address, username, &authentication, jump_host.as_ref()).await
+    }
+
+    /// Return the instances of the testbed that are terminated or (if the orchestrator accesses
+    /// them through ssh) active but unreachable.
+    pub async fn dead_instances(&self) -> Vec<Instance> {
+        let instances = self.instances();
+        let checks = instances.iter().map(|instance| async move {
+            if instance.is_terminated() {
+                return true;
+            }
+            if !instance.is_active() || !self.settings.uses_ssh() {
+                return false;
+            }
+            self.connect(instance).await.is_err()
+        });
+        let dead = join_all(checks).await;
+        instances
+            .into_iter()
+            .zip(dead)
+            .filter_map(|(instance, dead)| dead.then_some(instance))
+            .collect()
+    }
+
+    /// Replace the specified (dead) instances with new instances of the same specs in the same
+    /// regions. Returns the new instances.
+    pub async fn replace(&mut self, instances: &[Instance]) -> TestbedResult<Vec<Instance>> {
+        display::action(format!("Replacing {} dead instances", instances.len()));
+
+        try_join_all(
+            instances
+                .iter()
+                .map(|instance| self.client.delete_instance(instance.clone())),
+        )
+        .await?;
+        let replacements = try_join_all(instances.iter().map(|instance| {
+            let specs = self.settings.configured_specs(instance);
+            self.client.create_instance(instance.region.as_str(), specs)
+        }))
+        .await?;
+
+        // Wait until the instances are booted.
+        if cfg!(not(test)) {
+            self.wait_until_reachable(replacements.iter()).await?;
+        }
+        self.instances = self.client.list_instances().await?;
+
+        // Return the up-to-date description of the instances (e.g., with their ip addresses).
+        let ids: Vec<_> = replacements.iter().map(|x| &x.id).collect();
+        let replacements = self
+            .instances
+            .iter()
+            .filter(|x| ids.contains(&&x.id))
+            .cloned()
+            .collect();
+
+        display::done();
+        Ok(replacements)
+    }
+
+    /// Destroy the testbed if it is past its ttl.
+    pub async fn reap(&mut self) -> TestbedResult<()> {
+        match self.expired()? {
+            Some(overdue) => {
+                display::warn(format!(
+                    "Testbed '{}' expired {}s ago",
+                    self.settings.testbed_id,
+                    overdue.as_secs()
+                ));
+                self.destroy().await
+            }
+            None => {
+                display::config("Testbed", "not expired (nothing to reap)");
+                Ok(())
+            }
+        }
+    }
+
     /// Start the specified number of instances in each region. Returns an error if there are not
     /// enough available instances.
     pub async fn start(&mut self, quantity: usize) -> TestbedResult<()> {
@@ -219,11 +427,64 @@ impl<C: ServerProviderClient> Testbed<C> {
         Ok(())
     }
 
+    /// Check in parallel that all active instances are reachable and ready to run benchmarks,
+    /// and print the failures of each instance. Returns an error if any instance fails.
+    pub async fn check(&self, ssh_manager: &SshConnectionManager) -> TestbedResult<()> {
+        display::action("Checking instances health");
+
+        let instances: Vec<_> = self
+            .instances
+            .iter()
+            .filter(|x| x.is_active() && self.settings.filter_instances(x))
+            .cloned()
+            .collect();
+        let setup_commands = self.setup_commands().await?;
+        let expect_mount = setup_commands.iter().any(|x| x.contains("sudo mount"));
+        let check = HealthCheck::new(&self.settings, expect_mount, instances.len());
+
+        let targets = instances.iter().map(|x| (x.clone(), check.command()));
+        let handles = ssh_manager.run_per_instance(targets, CommandContext::default());
+        let mut unhealthy = Vec::new();
+        for (instance, handle) in instances.iter().zip(handles) {
+            let failures = match handle.await.unwrap() {
+                Ok((stdout, _)) => check.failures(&stdout),
+                Err(e) => vec![format!("unreachable ({e})")],
+            };
+            if !failures.is_empty() {
+                unhealthy.push((instance, failures));
+            }
+        }
+        display::done();
+
+        let mut table = Table::new();
+        table.set_format(display::default_table_format());
+        let healthy = instances.len() - unhealthy.len();
+        table.set_titles(row![bH2->format!("Healthy instances ({healthy}/{})", instances.len())]);
+        for (instance, failures) in &unhealthy {
+            let name = format!("{} ({})", instance.main_ip, instance.region);
+            table.add_row(row![bFr->name, failures.join(", ")]);
+        }
+        display::newline();
+        table.printstd();
+        display::newline();
+
+        ensure!(
+            unhealthy.is_empty(),
+            TestbedError::UnhealthyInstances(unhealthy.len())
+        );
+        Ok(())
+    }
+
     /// Wait until all specified instances are ready to accept ssh connections.
     async fn wait_until_reachable<'a, I>(&self, instances: I) -> TestbedResult<()>
     where
         I: Iterator<Item = &'a Instance> + Clone,
     {
+        // Local instances and pods are not accessed through ssh.
+        if !self.settings.uses_ssh() {
+            return Ok(());
+        }
+
         let instances_ids: Vec<_> = instances.map(|x| x.id.clone()).collect();
 
         let mut interval = time::interval(Duration::from_secs(5));
@@ -239,10 +500,7 @@ impl<C: ServerProviderClient> Testbed<C> {
             let futures = instances
                 .iter()
                 .filter(|x| instances_ids.contains(&x.id))
-                .map(|instance| {
-                    let private_key_file = self.settings.ssh_private_key_file.clone();
-                    SshConnection::new(instance.ssh_address(), C::USERNAME, private_key_file)
-                });
+                .map(|instance| self.connect(instance));
             if try_join_all(futures).await.is_ok() {
                 break;
             }
@@ -253,7 +511,13 @@ impl<C: ServerProviderClient> Testbed<C> {
 
 #[cfg(test)]
 mod test {
-    use crate::{client::test_client::TestClient, settings::Settings, testbed::Testbed};
+    use std::time::Duration;
+
+    use crate::{
+        client::test_client::TestClient,
+        settings::{HardwareProfile, Settings},
+        testbed::Testbed,
+    };
 
     #[tokio::test]
     async fn deploy() {
@@ -261,7 +525,7 @@ mod test {
         let client = TestClient::new(settings.clone());
         let mut testbed = Testbed::new(settings, client).await.unwrap();
 
-        testbed.deploy(5, None).await.unwrap();
+        testbed.deploy(5, None, None).await.unwrap();
 
         assert_eq!(
             testbed.instances.len(),
@@ -272,6 +536,27 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn deploy_hardware_profiles() {
+        let mut settings = Settings::new_for_test();
+        settings.hardware_profiles = vec![HardwareProfile {
+            specs: "small".into(),
+            share: 0.25,
+        }];
+        let client = TestClient::new(settings.clone());
+        let mut testbed = Testbed::new(settings, client).await.unwrap();
+
+        testbed.deploy(4, None, None).await.unwrap();
+
+        let profile = &testbed.settings.hardware_profiles[0];
+        let small = testbed.instances.iter().filter(|x| profile.matches(x));
+        assert_eq!(small.count(), testbed.settings.number_of_regions());
+        assert_eq!(
+            testbed.instances.len(),
+            4 * testbed.settings.number_of_regions()
+        );
+    }
+
     #[tokio::test]
     async fn destroy() {
         let settings = Settings::new_for_test();
@@ -283,12 +568,58 @@ mod test {
         assert_eq!(testbed.instances.len(), 0);
     }
 
+    #[tokio::test]
+    async fn replace() {
+        let mut settings = Settings::new_for_test();
+        settings.specs = "m5d.8xlarge".into();
+        let client = TestClient::new(settings.clone());
+        let mut testbed = Testbed::new(settings, client).await.unwrap();
+        testbed.deploy(2, None, None).await.unwrap();
+
+        // The cloud provider may report the specs differently than configured.
+        let mut dead = vec![testbed.instances[0].clone()];
+        dead[0].specs = "M5d8xlarge".into();
+        let replacements = testbed.replace(&dead).await.unwrap();
+
+        assert_eq!(replacements.len(), 1);
+        assert_eq!(replacements[0].region, dead[0].region);
+        assert_eq!(replacements[0].specs, "m5d.8xlarge");
+        assert!(testbed.instances.iter().all(|x| x.id != dead[0].id));
+        assert_eq!(
+            testbed.instances.len(),
+            2 * testbed.settings.number_of_regions()
+        );
+    }
+
+    #[tokio::test]
+    async fn reap() {
+        let mut settings = Settings::new_for_test();
+        settings.testbeds_dir = tempfile::tempdir().unwrap().into_path();
+        let client = TestClient::new(settings.clone());
+        let mut testbed = Testbed::new(settings, client).await.unwrap();
+
+        // The testbed does not expire before its ttl.
+        let ttl = Duration::from_secs(3600);
+        testbed.deploy(2, None, Some(ttl)).await.unwrap();
+        assert_eq!(testbed.ttl_record().unwrap().unwrap().ttl, 3600);
+        assert_eq!(testbed.expired().unwrap(), None);
+        testbed.reap().await.unwrap();
+        assert!(!testbed.instances.is_empty());
+
+        // Reaping destroys expired testbeds.
+        testbed.deploy(2, None, Some(Duration::ZERO)).await.unwrap();
+        assert!(testbed.expired().unwrap().is_some());
+        testbed.reap().await.unwrap();
+        assert!(testbed.instances.is_empty());
+        assert!(testbed.ttl_record().unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn start() {
         let settings = Settings::new_for_test();
         let client = TestClient::new(settings.clone());
         let mut testbed = Testbed::new(settings, client).await.unwrap();
-        testbed.deploy(5, None).await.unwrap();
+        testbed.deploy(5, None, None).await.unwrap();
         testbed.stop().await.unwrap();
 
         let result = testbed.start(2).await;
@@ -316,7 +647,7 @@ mod test {
         let settings = Settings::new_for_test();
         let client = TestClient::new(settings.clone());
         let mut testbed = Testbed::new(settings, client).await.unwrap();
-        testbed.deploy(5, None).await.unwrap();
+        testbed.deploy(5, None, None).await.unwrap();
         testbed.start(2).await.unwrap();
 
         testbed.stop().await.unwrap();